@@ -0,0 +1,284 @@
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{detect_diagram_type, files};
+
+// Diagrams whose grammar has no accTitle or accDescr statement; the lines
+// would be read as nodes or data.
+const UNSUPPORTED: [&str; 3] = ["mindmap", "sankey", "zenuml"];
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Report {
+    #[serde(flatten)]
+    pub metadata: Metadata,
+    pub supported: bool,
+    pub issues: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MissingMetadata {
+    pub path: String,
+    pub missing_title: bool,
+    pub missing_description: bool,
+}
+
+// Lines holding the accessibility statements, as (first, last) indexes, and
+// the index of the header line.
+struct Layout {
+    header: Option<usize>,
+    title: Option<(usize, usize)>,
+    description: Option<(usize, usize)>,
+    // The line of an `accDescr {` block that is never closed.
+    unclosed: Option<usize>,
+}
+
+fn layout(lines: &[&str]) -> (Layout, Metadata) {
+    let title = Regex::new(r"^accTitle\s*:\s*(.*)$").unwrap();
+    let single = Regex::new(r"^accDescr\s*:\s*(.*)$").unwrap();
+    let block = Regex::new(r"^accDescr\s*\{\s*(.*?)\s*(\})?\s*$").unwrap();
+
+    let mut layout = Layout {
+        header: None,
+        title: None,
+        description: None,
+        unclosed: None,
+    };
+    let mut metadata = Metadata::default();
+    let mut index = 0;
+    if lines.first().map(|line| line.trim()) == Some("---") {
+        index = lines
+            .iter()
+            .skip(1)
+            .position(|line| line.trim() == "---")
+            .map_or(lines.len(), |end| end + 2);
+    }
+
+    while index < lines.len() {
+        let line = lines[index].trim();
+        if line.is_empty() || line.starts_with("%%") {
+            index += 1;
+            continue;
+        }
+        if layout.header.is_none() {
+            layout.header = Some(index);
+        } else if let Some(caps) = title.captures(line) {
+            layout.title = Some((index, index));
+            metadata.title = Some(caps[1].trim().to_string());
+        } else if let Some(caps) = single.captures(line) {
+            layout.description = Some((index, index));
+            metadata.description = Some(caps[1].trim().to_string());
+        } else if let Some(caps) = block.captures(line) {
+            let mut text = vec![caps[1].to_string()];
+            let mut end = index;
+            if caps.get(2).is_none() {
+                // A block runs until the line holding the closing brace.
+                let mut closed = false;
+                while end + 1 < lines.len() {
+                    end += 1;
+                    let line = lines[end].trim();
+                    if let Some(last) = line.strip_suffix('}') {
+                        text.push(last.trim().to_string());
+                        closed = true;
+                        break;
+                    }
+                    text.push(line.to_string());
+                }
+                if !closed {
+                    layout.unclosed = Some(index);
+                }
+            }
+            layout.description = Some((index, end));
+            metadata.description = Some(
+                text.into_iter()
+                    .filter(|line| !line.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+            index = end;
+        }
+        index += 1;
+    }
+    (layout, metadata)
+}
+
+fn supported(content: &str) -> bool {
+    !detect_diagram_type(content).is_some_and(|diagram_type| UNSUPPORTED.contains(&diagram_type))
+}
+
+pub fn inspect(content: &str) -> Metadata {
+    let lines: Vec<&str> = content.lines().collect();
+    layout(&lines).1
+}
+
+fn unclosed_message(line: usize) -> String {
+    format!("The accDescr block on line {} is not closed", line + 1)
+}
+
+// Reports missing or unhelpful accessibility metadata. Screen readers read
+// the title first, so it should be short, and the description should say
+// more than the title does.
+pub fn check(content: &str) -> Report {
+    let lines: Vec<&str> = content.lines().collect();
+    let (layout, metadata) = layout(&lines);
+    let supported = supported(content);
+    let mut issues = Vec::new();
+    if supported {
+        if let Some(line) = layout.unclosed {
+            issues.push(unclosed_message(line));
+        }
+        match metadata.title.as_deref() {
+            None => issues.push("Missing accTitle".to_string()),
+            Some("") => issues.push("accTitle is empty".to_string()),
+            Some(title) if title.chars().count() > 100 => issues.push(
+                "accTitle is longer than 100 characters; move the details to accDescr".to_string(),
+            ),
+            Some(_) => {}
+        }
+        match metadata.description.as_deref() {
+            None => issues.push("Missing accDescr".to_string()),
+            Some("") => issues.push("accDescr is empty".to_string()),
+            Some(description) if metadata.title.as_deref() == Some(description) => {
+                issues.push("accDescr repeats accTitle".to_string())
+            }
+            Some(_) => {}
+        }
+    }
+    Report {
+        metadata,
+        supported,
+        issues,
+    }
+}
+
+// Adds or replaces accTitle and accDescr right after the header, leaving
+// whichever is None as it is. Multi-line descriptions use the block form.
+pub fn apply(
+    content: &str,
+    title: Option<&str>,
+    description: Option<&str>,
+) -> Result<String, String> {
+    if !supported(content) {
+        return Err("This diagram type does not support accessibility metadata".to_string());
+    }
+    let lines: Vec<&str> = content.lines().collect();
+    let (layout, _) = layout(&lines);
+    let Some(header) = layout.header else {
+        return Err("The diagram has no header".to_string());
+    };
+    // The block would take the rest of the diagram with it.
+    if let Some(line) = layout.unclosed {
+        return Err(unclosed_message(line));
+    }
+    let indent = lines
+        .iter()
+        .skip(header + 1)
+        .find(|line| !line.trim().is_empty())
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .filter(|indent| !indent.is_empty())
+        .unwrap_or("    ");
+
+    let mut statements = Vec::new();
+    if let Some(title) = title {
+        let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+        if title.is_empty() {
+            return Err("The title cannot be empty".to_string());
+        }
+        statements.push(format!("{}accTitle: {}", indent, title));
+    }
+    if let Some(description) = description {
+        let description: Vec<&str> = description
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        if description.is_empty() {
+            return Err("The description cannot be empty".to_string());
+        }
+        if description.iter().any(|line| line.contains('}')) {
+            return Err("The description cannot contain `}`".to_string());
+        }
+        if let [line] = description.as_slice() {
+            statements.push(format!("{}accDescr: {}", indent, line));
+        } else {
+            statements.push(format!("{}accDescr {{", indent));
+            for line in description {
+                statements.push(format!("{}{}{}", indent, indent, line));
+            }
+            statements.push(format!("{}}}", indent));
+        }
+    }
+
+    // Drop the statements being replaced, then insert after the header, or
+    // after a title that is being kept.
+    let anchor = match (title, layout.title) {
+        (None, Some((_, last))) => last,
+        _ => header,
+    };
+    let mut replaced: Vec<(usize, usize)> = Vec::new();
+    if title.is_some() {
+        replaced.extend(layout.title);
+    }
+    if description.is_some() {
+        replaced.extend(layout.description);
+    }
+    let mut output: Vec<String> = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        if replaced
+            .iter()
+            .any(|(first, last)| (*first..=*last).contains(&index))
+        {
+            continue;
+        }
+        output.push(line.to_string());
+        if index == anchor {
+            output.append(&mut statements);
+        }
+    }
+    let mut result = output.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+// Diagrams under `dir` missing a title or description, for accessibility
+// audits. Types that cannot carry the metadata are left out.
+pub fn find_missing(dir: &Path) -> Result<Vec<MissingMetadata>, String> {
+    if !dir.is_dir() {
+        return Err(format!("Not a folder: {}", dir.display()));
+    }
+    let mut paths: Vec<PathBuf> = files::collect_diagrams(dir, None)
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect();
+    paths.sort();
+
+    let mut missing = Vec::new();
+    for path in paths {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if !supported(&content) {
+            continue;
+        }
+        let metadata = inspect(&content);
+        let missing_title = metadata.title.unwrap_or_default().is_empty();
+        let missing_description = metadata.description.unwrap_or_default().is_empty();
+        if missing_title || missing_description {
+            missing.push(MissingMetadata {
+                path: path.to_string_lossy().to_string(),
+                missing_title,
+                missing_description,
+            });
+        }
+    }
+    Ok(missing)
+}
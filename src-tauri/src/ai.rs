@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::{detect_diagram_type, markdown, render, secrets, validate_content};
+
+pub const API_KEY_SECRET: &str = "ai-api-key";
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+// Any OpenAI-compatible chat completions endpoint. The API key is kept in
+// secure storage, see `secrets`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AiConfig {
+    pub endpoint: String,
+    pub model: String,
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AiSettings {
+    pub config: Option<AiConfig>,
+    pub has_api_key: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct GeneratedDiagram {
+    pub content: String,
+    pub attempts: u32,
+    pub warnings: Vec<String>,
+}
+
+fn completions_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with("/chat/completions") {
+        endpoint.to_string()
+    } else {
+        format!("{}/chat/completions", endpoint)
+    }
+}
+
+fn system_prompt(diagram_type: Option<&str>) -> String {
+    let mut prompt = "You write Mermaid diagrams. Reply with the Mermaid source only, \
+                      without explanations or Markdown fences."
+        .to_string();
+    if let Some(diagram_type) = diagram_type {
+        prompt.push_str(&format!(
+            " The diagram must be a Mermaid `{}` diagram.",
+            diagram_type
+        ));
+    }
+    prompt
+}
+
+// Models often wrap the answer in a fence or add a sentence around it.
+fn extract_mermaid(reply: &str) -> String {
+    match markdown::extract_code_blocks(reply).into_iter().next() {
+        Some(block) => block,
+        None => reply
+            .trim()
+            .trim_start_matches("```")
+            .trim_end_matches("```")
+            .trim()
+            .to_string(),
+    }
+}
+
+// Returns the problems the model should fix, or an empty list when the
+// diagram is acceptable.
+fn check(content: &str, diagram_type: Option<&str>, use_renderer: bool) -> Vec<String> {
+    let validation = validate_content(content);
+    let mut problems = validation.errors;
+
+    match (detect_diagram_type(content), diagram_type) {
+        (None, _) => problems.push("The reply does not start with a Mermaid diagram type.".into()),
+        (Some(found), Some(expected)) if !found.eq_ignore_ascii_case(expected) => {
+            problems.push(format!(
+                "Expected a `{}` diagram but got `{}`.",
+                expected, found
+            ));
+        }
+        _ => {}
+    }
+
+    if problems.is_empty() && use_renderer {
+        if let Err(e) = render::render_content_to_bytes(content, "svg") {
+            problems.push(e);
+        }
+    }
+
+    problems
+}
+
+async fn complete(
+    client: &reqwest::Client,
+    config: &AiConfig,
+    api_key: Option<&str>,
+    messages: &[Value],
+) -> Result<String, String> {
+    let mut request = client
+        .post(completions_url(&config.endpoint))
+        .timeout(REQUEST_TIMEOUT)
+        .json(&json!({
+            "model": config.model,
+            "messages": messages,
+            "temperature": 0.2,
+        }));
+    if let Some(api_key) = api_key {
+        request = request.bearer_auth(api_key);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach AI endpoint: {}", e))?;
+    let status = response.status();
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to read AI response: {}", e))?;
+
+    if !status.is_success() {
+        let message = body["error"]["message"]
+            .as_str()
+            .unwrap_or_else(|| status.canonical_reason().unwrap_or("unknown error"));
+        return Err(format!(
+            "AI endpoint returned {}: {}",
+            status.as_u16(),
+            message
+        ));
+    }
+
+    body["choices"][0]["message"]["content"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "AI response did not contain a message".to_string())
+}
+
+// Asks the model for a diagram and feeds validation errors back until the
+// result passes or the attempt budget runs out.
+pub async fn generate(
+    config: &AiConfig,
+    prompt: &str,
+    diagram_type: Option<&str>,
+) -> Result<GeneratedDiagram, String> {
+    let api_key = secrets::get_secret(API_KEY_SECRET)?;
+    let client = reqwest::Client::new();
+    let max_attempts = config.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS).max(1);
+    let use_renderer = render::is_available();
+
+    let mut warnings = Vec::new();
+    if !use_renderer {
+        warnings.push("Mermaid CLI not found, only basic validation was applied".to_string());
+    }
+
+    let mut messages = vec![
+        json!({ "role": "system", "content": system_prompt(diagram_type) }),
+        json!({ "role": "user", "content": prompt }),
+    ];
+
+    let mut problems = Vec::new();
+    for attempt in 1..=max_attempts {
+        let reply = complete(&client, config, api_key.as_deref(), &messages).await?;
+        let content = extract_mermaid(&reply);
+
+        problems = check(&content, diagram_type, use_renderer);
+        if problems.is_empty() {
+            warnings.extend(validate_content(&content).warnings);
+            return Ok(GeneratedDiagram {
+                content,
+                attempts: attempt,
+                warnings,
+            });
+        }
+
+        tracing::info!(attempt, problems = ?problems, "generated diagram failed validation");
+        messages.push(json!({ "role": "assistant", "content": reply }));
+        messages.push(json!({
+            "role": "user",
+            "content": format!(
+                "That diagram is not valid:\n{}\nReply with the corrected Mermaid source only.",
+                problems.join("\n")
+            ),
+        }));
+    }
+
+    Err(format!(
+        "Could not generate a valid diagram after {} attempts: {}",
+        max_attempts,
+        problems.join("; ")
+    ))
+}
@@ -0,0 +1,402 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::comment_report;
+use crate::model::{self, DiagramModel};
+use crate::spelling::{self, TextKind};
+
+// Edge labels that describe the flow rather than the system.
+const KEEP: [&str; 5] = ["yes", "no", "true", "false", "ok"];
+
+// Node kinds picked from words in the label or id, checked in order.
+const CATEGORIES: [(&str, &[&str]); 3] = [
+    (
+        "DB",
+        &[
+            "db", "database", "sql", "postgres", "mysql", "mongo", "redis", "cache", "store",
+            "storage", "bucket", "s3", "table",
+        ],
+    ),
+    (
+        "Queue",
+        &[
+            "queue", "topic", "kafka", "rabbitmq", "bus", "stream", "sqs", "pubsub",
+        ],
+    ),
+    (
+        "User",
+        &[
+            "user", "users", "customer", "client", "admin", "browser", "person",
+        ],
+    ),
+];
+// Anything else.
+const DEFAULT_CATEGORY: &str = "Service";
+
+fn yes() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CategoryRule {
+    pub name: String,
+    pub keywords: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AnonymizeRules {
+    // Node labels, participant names, subgraph titles and identifiers.
+    #[serde(default = "yes")]
+    pub labels: bool,
+    #[serde(default = "yes")]
+    pub edge_labels: bool,
+    #[serde(default = "yes")]
+    pub notes: bool,
+    // Texts left as they are, such as vendor names that are public anyway.
+    #[serde(default)]
+    pub keep: Vec<String>,
+    // Checked before the built-in categories.
+    #[serde(default)]
+    pub categories: Vec<CategoryRule>,
+}
+
+impl Default for AnonymizeRules {
+    fn default() -> Self {
+        Self {
+            labels: true,
+            edge_labels: true,
+            notes: true,
+            keep: Vec::new(),
+            categories: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Replacement {
+    pub original: String,
+    pub placeholder: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Anonymized {
+    pub content: String,
+    // For the user to map feedback on the shared copy back to the original;
+    // it stays on this machine.
+    pub replacements: Vec<Replacement>,
+}
+
+// Hands out placeholders, the same one for the same text.
+#[derive(Default)]
+struct Placeholders {
+    counters: HashMap<String, usize>,
+    assigned: HashMap<(String, String), String>,
+    replacements: Vec<Replacement>,
+}
+
+impl Placeholders {
+    // The default category is lettered (Service A, Service B), the others
+    // numbered (DB 1, DB 2).
+    fn get(&mut self, category: &str, original: &str) -> String {
+        let key = (category.to_string(), original.to_string());
+        if let Some(placeholder) = self.assigned.get(&key) {
+            return placeholder.clone();
+        }
+        let counter = self.counters.entry(category.to_string()).or_insert(0);
+        *counter += 1;
+        let placeholder = if category == DEFAULT_CATEGORY {
+            format!("{} {}", category, letters(*counter))
+        } else {
+            format!("{} {}", category, counter)
+        };
+        self.assigned.insert(key, placeholder.clone());
+        self.replacements.push(Replacement {
+            original: original.to_string(),
+            placeholder: placeholder.clone(),
+        });
+        placeholder
+    }
+}
+
+// 1 is A, 26 is Z, 27 is AA.
+fn letters(mut number: usize) -> String {
+    let mut letters = Vec::new();
+    while number > 0 {
+        number -= 1;
+        letters.push((b'A' + (number % 26) as u8) as char);
+        number /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+// Words of the text in lower case, with camel case split too, so
+// `PaymentDB` counts as `payment` and `db`.
+fn words(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let camel = Regex::new(r"[A-Z]+[a-z]*|[a-z]+").unwrap();
+    words.extend(
+        camel
+            .find_iter(text)
+            .map(|word| word.as_str().to_lowercase()),
+    );
+    words
+}
+
+fn category(rules: &AnonymizeRules, text: &str, cylinder: bool) -> String {
+    let words = words(text);
+    let matches = |keyword: &str| words.iter().any(|word| word.eq_ignore_ascii_case(keyword));
+    if let Some(rule) = rules
+        .categories
+        .iter()
+        .find(|rule| rule.keywords.iter().any(|keyword| matches(keyword)))
+    {
+        return rule.name.clone();
+    }
+    if cylinder {
+        return "DB".to_string();
+    }
+    CATEGORIES
+        .iter()
+        .find(|(_, keywords)| keywords.iter().any(|keyword| matches(keyword)))
+        .map_or(DEFAULT_CATEGORY, |(name, _)| name)
+        .to_string()
+}
+
+// Drops what can name things without being part of the shape: comments,
+// directives, frontmatter, accessibility text and click targets.
+fn without_metadata(content: &str) -> String {
+    let stripped = comment_report::strip(content);
+    let mut lines = Vec::new();
+    let mut in_description = false;
+    for line in stripped.lines() {
+        let trimmed = line.trim();
+        if in_description {
+            in_description = !trimmed.ends_with('}');
+            continue;
+        }
+        if trimmed.starts_with("accDescr") && trimmed.contains('{') && !trimmed.ends_with('}') {
+            in_description = true;
+            continue;
+        }
+        if trimmed.starts_with("accTitle")
+            || trimmed.starts_with("accDescr")
+            || trimmed.starts_with("click ")
+        {
+            continue;
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+fn kept(rules: &AnonymizeRules, text: &str) -> bool {
+    KEEP.contains(&text.to_lowercase().as_str())
+        || rules
+            .keep
+            .iter()
+            .any(|keep| keep.eq_ignore_ascii_case(text))
+}
+
+// Replaces node labels, participant names, edge labels and notes with
+// placeholders such as `Service A` or `DB 1`, and identifiers to match.
+// Arrows, grouping and styling stay as they are, so the copy shows the same
+// shape.
+pub fn anonymize(content: &str, rules: &AnonymizeRules) -> Anonymized {
+    let text = without_metadata(content);
+    let parsed = model::parse_cached(&text);
+    let mut placeholders = Placeholders::default();
+
+    // Nodes first, so their labels and ids share a placeholder.
+    let mut node_placeholders: Vec<(String, String)> = Vec::new();
+    let mut label_placeholders: HashMap<String, String> = HashMap::new();
+    if rules.labels {
+        // Subgraphs can be linked to like nodes, so their ids go too. They
+        // come first, as the model takes linked ones for nodes.
+        let subgraph =
+            Regex::new(r#"^\s*subgraph\s+([\w-]+)(?:\s*\[\s*"?([^"\]]*)"?\s*\])?"#).unwrap();
+        for line in text.lines() {
+            let Some(caps) = subgraph.captures(line) else {
+                continue;
+            };
+            let title = caps.get(2).map_or(&caps[1], |title| title.as_str()).trim();
+            if !kept(rules, title) {
+                node_placeholders.push((caps[1].to_string(), placeholders.get("Group", title)));
+            }
+        }
+        for node in &parsed.nodes {
+            let name = node.label.clone().unwrap_or_else(|| node.id.clone());
+            let name = name.trim().trim_matches('"').trim().to_string();
+            if kept(rules, &name) || node_placeholders.iter().any(|(id, _)| *id == node.id) {
+                continue;
+            }
+            let cylinder = Regex::new(&format!(
+                r"(?:^|[\s;&>-]){}\s*\[\(",
+                regex::escape(&node.id)
+            ))
+            .unwrap()
+            .is_match(&text);
+            let actor = Regex::new(&format!(r"(?m)^\s*actor\s+{}\b", regex::escape(&node.id)))
+                .unwrap()
+                .is_match(&text);
+            let category = if actor {
+                "User".to_string()
+            } else {
+                category(rules, &format!("{} {}", name, node.id), cylinder)
+            };
+            let placeholder = placeholders.get(&category, &name);
+            label_placeholders.insert(name, placeholder.clone());
+            node_placeholders.push((node.id.clone(), placeholder));
+        }
+    }
+
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let mut spans = spelling::texts(&text);
+    // Right to left, so earlier offsets stay valid.
+    spans.sort_by_key(|(number, offset, ..)| Reverse((*number, *offset)));
+    let mut last: Option<(usize, usize)> = None;
+    for (number, offset, kind, span) in spans {
+        let core = span.trim();
+        let start = offset + (span.len() - span.trim_start().len());
+        let end = start + core.len();
+        if core.is_empty() || kept(rules, core.trim_matches('"')) {
+            continue;
+        }
+        // Overlapping matches of the same text are replaced once.
+        if let Some((line, next_start)) = last {
+            if line == number && end > next_start {
+                continue;
+            }
+        }
+        let line = &lines[number - 1];
+        let placeholder = match kind {
+            TextKind::Label if !rules.labels => continue,
+            // A subgraph without a title shows its id, renamed below.
+            TextKind::Label if line.trim_start().starts_with("subgraph") => {
+                if !line.contains('[') {
+                    continue;
+                }
+                placeholders.get("Group", core.trim_matches('"'))
+            }
+            TextKind::Label => match label_placeholders.get(core.trim_matches('"')) {
+                Some(placeholder) => placeholder.clone(),
+                None => placeholders.get("Label", core),
+            },
+            TextKind::Edge if !rules.edge_labels => continue,
+            TextKind::Edge => placeholders.get("Label", core),
+            TextKind::Note if !rules.notes => continue,
+            TextKind::Note => placeholders.get("Note", core),
+        };
+        lines[number - 1] = format!("{}{}{}", &line[..start], placeholder, &line[end..]);
+        last = Some((number, start));
+    }
+
+    // Identifiers go through temporary names first, so renaming `A` to
+    // `ServiceB` cannot collide with a node already called that.
+    let mut model = DiagramModel::parse(&lines.join("\n"));
+    for (index, (id, _)) in node_placeholders.iter().enumerate() {
+        model.rename_node(id, &format!("anonymized_{}_", index));
+    }
+    for (index, (_, placeholder)) in node_placeholders.iter().enumerate() {
+        model.rename_node(
+            &format!("anonymized_{}_", index),
+            &placeholder.replace(' ', ""),
+        );
+    }
+
+    let mut anonymized = model.lines.join("\n");
+    if content.ends_with('\n') {
+        anonymized.push('\n');
+    }
+    Anonymized {
+        content: anonymized,
+        replacements: placeholders.replacements,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholder<'a>(result: &'a Anonymized, original: &str) -> Option<&'a str> {
+        result
+            .replacements
+            .iter()
+            .find(|replacement| replacement.original == original)
+            .map(|replacement| replacement.placeholder.as_str())
+    }
+
+    #[test]
+    fn replaces_labels_and_ids_by_category() {
+        let source = "flowchart LR\n  api[Payment API] -->|charges| db[(Orders DB)]\n  web[Checkout] --> api\n";
+        let result = anonymize(source, &AnonymizeRules::default());
+        assert_eq!(
+            result.content,
+            "flowchart LR\n  ServiceA[Service A] -->|Label 1| DB1[(DB 1)]\n  ServiceB[Service B] --> ServiceA\n"
+        );
+        assert_eq!(placeholder(&result, "Payment API"), Some("Service A"));
+        assert_eq!(placeholder(&result, "Orders DB"), Some("DB 1"));
+        assert_eq!(placeholder(&result, "charges"), Some("Label 1"));
+    }
+
+    #[test]
+    fn drops_comments_and_click_targets() {
+        let source = "flowchart LR\n  %% owned by team payments\n  a[Billing] --> b[Ledger]\n  click a \"https://billing.internal\"\n";
+        let result = anonymize(source, &AnonymizeRules::default());
+        assert!(!result.content.contains("payments"));
+        assert!(!result.content.contains("billing.internal"));
+        assert!(!result.content.contains("Billing"));
+    }
+
+    #[test]
+    fn keeps_listed_texts_and_flow_words() {
+        let rules = AnonymizeRules {
+            keep: vec!["stripe".to_string()],
+            ..AnonymizeRules::default()
+        };
+        let source = "flowchart LR\n  a[Shop] -->|yes| b[Stripe]\n";
+        let result = anonymize(source, &rules);
+        assert_eq!(
+            result.content,
+            "flowchart LR\n  ServiceA[Service A] -->|yes| b[Stripe]\n"
+        );
+    }
+
+    #[test]
+    fn actors_and_notes_in_sequence_diagrams() {
+        let source = "sequenceDiagram\n  actor Alice\n  Alice->>Billing: pay invoice\n  Note over Billing: retries twice";
+        let result = anonymize(source, &AnonymizeRules::default());
+        assert_eq!(
+            result.content,
+            "sequenceDiagram\n  actor User1\n  User1->>ServiceA: Label 1\n  Note over ServiceA: Note 1"
+        );
+    }
+
+    #[test]
+    fn leaves_disabled_kinds_alone() {
+        let rules = AnonymizeRules {
+            edge_labels: false,
+            notes: false,
+            ..AnonymizeRules::default()
+        };
+        let source =
+            "sequenceDiagram\n  Alice->>Billing: pay invoice\n  Note over Billing: retries twice";
+        let result = anonymize(source, &rules);
+        assert!(result.content.contains(": pay invoice"));
+        assert!(result.content.contains(": retries twice"));
+        assert!(!result.content.contains("Alice"));
+    }
+
+    #[test]
+    fn letters_count_like_spreadsheet_columns() {
+        assert_eq!(letters(1), "A");
+        assert_eq!(letters(26), "Z");
+        assert_eq!(letters(27), "AA");
+        assert_eq!(letters(52), "AZ");
+    }
+}
@@ -0,0 +1,121 @@
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::{diagrams, icons};
+
+// Checks an icon reference: a built-in icon, or `pack:name` from a
+// registered icon pack. Packs are only loaded once an icon needs them.
+fn check_icon(
+    icon: &str,
+    number: usize,
+    packs: &mut Option<icons::Registered>,
+    errors: &mut Vec<String>,
+) {
+    let Some((pack, name)) = icon.split_once(':') else {
+        if !icons::BUILTIN_ICONS.contains(&icon) {
+            errors.push(format!(
+                "Line {}: unknown icon `{}`; use one of {} or `pack:name`",
+                number,
+                icon,
+                icons::BUILTIN_ICONS.join(", ")
+            ));
+        }
+        return;
+    };
+    icons::check_reference(pack, name, number, packs, errors);
+}
+
+// Checks the groups, services, junctions and edges of an architecture
+// diagram: unique ids, known parents and edge ends, valid sides and icons
+// that exist in a built-in or registered pack.
+pub fn validate(content: &str, _: &str, errors: &mut Vec<String>, _: &mut Vec<String>) {
+    let node = Regex::new(
+        r"^(group|service|junction)\s+([\w-]+)\s*(?:\(([^)]*)\))?\s*(?:\[([^\]]*)\])?\s*(?:\bin\s+([\w-]+))?$",
+    )
+    .unwrap();
+    let edge = Regex::new(
+        r"^([\w-]+)(\{group\})?\s*:\s*(\w+)\s*(<?-{1,2}>?)\s*(\w+)\s*:\s*([\w-]+)(\{group\})?$",
+    )
+    .unwrap();
+
+    // Kind and parent group of every id.
+    let mut ids: HashMap<String, (String, Option<String>)> = HashMap::new();
+    let mut parents: Vec<(usize, String)> = Vec::new();
+    let mut edges: Vec<(usize, String, bool)> = Vec::new();
+    let mut packs = None;
+
+    for (number, line) in diagrams::statements(content) {
+        if line.starts_with("title") || line.starts_with("acc") {
+            continue;
+        }
+        if let Some(caps) = node.captures(line) {
+            let kind = &caps[1];
+            let id = &caps[2];
+            if kind == "junction" && (caps.get(3).is_some() || caps.get(4).is_some()) {
+                errors.push(format!("Line {}: junctions take no icon or title", number));
+            }
+            if let Some(icon) = caps.get(3) {
+                check_icon(icon.as_str().trim(), number, &mut packs, errors);
+            }
+            let parent = caps.get(5).map(|parent| parent.as_str().to_string());
+            if let Some(parent) = &parent {
+                if parent == id {
+                    errors.push(format!("Line {}: {} cannot be inside itself", number, id));
+                }
+                parents.push((number, parent.clone()));
+            }
+            if ids
+                .insert(id.to_string(), (kind.to_string(), parent))
+                .is_some()
+            {
+                errors.push(format!("Line {}: id `{}` is already defined", number, id));
+            }
+            continue;
+        }
+        if let Some(caps) = edge.captures(line) {
+            for side in [&caps[3], &caps[5]] {
+                if !matches!(side, "L" | "R" | "T" | "B") {
+                    errors.push(format!(
+                        "Line {}: `{}` is not a side; use L, R, T or B",
+                        number, side
+                    ));
+                }
+            }
+            edges.push((number, caps[1].to_string(), caps.get(2).is_some()));
+            edges.push((number, caps[6].to_string(), caps.get(7).is_some()));
+            continue;
+        }
+        errors.push(format!(
+            "Line {}: expected a group, service, junction or an edge such as `a:R --> L:b`",
+            number
+        ));
+    }
+
+    for (number, parent) in parents {
+        match ids.get(&parent) {
+            Some((kind, _)) if kind == "group" => {}
+            Some(_) => errors.push(format!("Line {}: `{}` is not a group", number, parent)),
+            None => errors.push(format!(
+                "Line {}: group `{}` is not defined",
+                number, parent
+            )),
+        }
+    }
+    for (number, id, from_group) in edges {
+        match ids.get(&id) {
+            None => errors.push(format!(
+                "Line {}: edge refers to undefined `{}`",
+                number, id
+            )),
+            Some((kind, _)) if kind == "group" => errors.push(format!(
+                "Line {}: `{}` is a group; connect one of its services with `{{group}}` instead",
+                number, id
+            )),
+            Some((_, None)) if from_group => errors.push(format!(
+                "Line {}: `{}{{group}}` needs {} to be inside a group",
+                number, id, id
+            )),
+            Some(_) => {}
+        }
+    }
+}
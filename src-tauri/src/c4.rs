@@ -0,0 +1,230 @@
+use regex::Regex;
+use std::collections::HashSet;
+
+use crate::diagrams;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Context,
+    Container,
+    Component,
+    Deployment,
+}
+
+// Element macros with the level they belong to and their required and
+// total positional arguments; the first argument is always the alias.
+const ELEMENTS: [(&str, Level, usize, usize); 28] = [
+    ("Person", Level::Context, 2, 6),
+    ("Person_Ext", Level::Context, 2, 6),
+    ("System", Level::Context, 2, 6),
+    ("System_Ext", Level::Context, 2, 6),
+    ("SystemDb", Level::Context, 2, 6),
+    ("SystemDb_Ext", Level::Context, 2, 6),
+    ("SystemQueue", Level::Context, 2, 6),
+    ("SystemQueue_Ext", Level::Context, 2, 6),
+    ("Boundary", Level::Context, 2, 4),
+    ("Enterprise_Boundary", Level::Context, 2, 3),
+    ("System_Boundary", Level::Context, 2, 3),
+    ("Container", Level::Container, 2, 7),
+    ("Container_Ext", Level::Container, 2, 7),
+    ("ContainerDb", Level::Container, 2, 7),
+    ("ContainerDb_Ext", Level::Container, 2, 7),
+    ("ContainerQueue", Level::Container, 2, 7),
+    ("ContainerQueue_Ext", Level::Container, 2, 7),
+    ("Container_Boundary", Level::Container, 2, 3),
+    ("Component", Level::Component, 2, 7),
+    ("Component_Ext", Level::Component, 2, 7),
+    ("ComponentDb", Level::Component, 2, 7),
+    ("ComponentDb_Ext", Level::Component, 2, 7),
+    ("ComponentQueue", Level::Component, 2, 7),
+    ("ComponentQueue_Ext", Level::Component, 2, 7),
+    ("Deployment_Node", Level::Deployment, 2, 7),
+    ("Node", Level::Deployment, 2, 7),
+    ("Node_L", Level::Deployment, 2, 7),
+    ("Node_R", Level::Deployment, 2, 7),
+];
+
+// Relationship macros, which take a source, a target and a label. Dynamic
+// diagrams may number their steps with a leading index in `RelIndex`.
+const RELATIONS: [&str; 12] = [
+    "Rel",
+    "BiRel",
+    "Rel_U",
+    "Rel_Up",
+    "Rel_D",
+    "Rel_Down",
+    "Rel_L",
+    "Rel_Left",
+    "Rel_R",
+    "Rel_Right",
+    "Rel_Back",
+    "Rel_Neighbor",
+];
+
+const STYLES: [&str; 7] = [
+    "UpdateElementStyle",
+    "UpdateRelStyle",
+    "UpdateBoundaryStyle",
+    "UpdateLayoutConfig",
+    "AddElementTag",
+    "AddRelTag",
+    "AddBoundaryTag",
+];
+
+// The levels each diagram is meant to show; other elements still render
+// but usually belong in a more detailed diagram.
+fn allowed(diagram_type: &str, level: Level) -> bool {
+    match diagram_type {
+        "c4context" => level == Level::Context,
+        "c4container" => matches!(level, Level::Context | Level::Container),
+        "c4component" | "c4dynamic" => level != Level::Deployment,
+        _ => true,
+    }
+}
+
+// Splits macro arguments on commas outside quotes. Named arguments such as
+// `$tags="v1"` are left out since they may appear anywhere.
+fn arguments(text: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            ',' if !quoted => parts.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    parts.push(current);
+    parts
+        .into_iter()
+        .map(|part| part.trim().to_string())
+        .filter(|part| !part.starts_with('$'))
+        .collect()
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+// Checks the element and relationship macros of a C4 diagram: known
+// names, argument counts, unique aliases, balanced boundary braces and
+// relationships between defined elements.
+pub fn validate(
+    content: &str,
+    diagram_type: &str,
+    errors: &mut Vec<String>,
+    warnings: &mut Vec<String>,
+) {
+    let statement = Regex::new(r"^(\w+)\s*\((.*)\)\s*(\{)?\s*$").unwrap();
+    let mut aliases = HashSet::new();
+    let mut relations: Vec<(usize, String, String)> = Vec::new();
+    let mut depth = 0usize;
+
+    for (number, line) in diagrams::statements(content) {
+        if line == "}" {
+            if depth == 0 {
+                errors.push(format!("Line {}: `}}` without an open boundary", number));
+            } else {
+                depth -= 1;
+            }
+            continue;
+        }
+        if line.starts_with("title ") || line == "title" {
+            continue;
+        }
+
+        let Some(caps) = statement.captures(line) else {
+            errors.push(format!(
+                "Line {}: expected an element such as `Person(alias, \"Label\")`",
+                number
+            ));
+            continue;
+        };
+        let name = &caps[1];
+        let args = arguments(&caps[2]);
+        let opens = caps.get(3).is_some();
+
+        if RELATIONS.contains(&name) || name == "RelIndex" {
+            let offset = usize::from(name == "RelIndex");
+            if args.len() < 3 + offset {
+                errors.push(format!(
+                    "Line {}: {} needs a source, a target and a label",
+                    number, name
+                ));
+                continue;
+            }
+            relations.push((
+                number,
+                unquote(&args[offset]).to_string(),
+                unquote(&args[offset + 1]).to_string(),
+            ));
+            continue;
+        }
+        if STYLES.contains(&name) {
+            continue;
+        }
+
+        let Some((_, level, required, total)) =
+            ELEMENTS.iter().find(|(element, ..)| *element == name)
+        else {
+            errors.push(format!("Line {}: unknown C4 element `{}`", number, name));
+            continue;
+        };
+        if args.len() < *required || args[0].is_empty() {
+            errors.push(format!(
+                "Line {}: {} needs an alias and a label",
+                number, name
+            ));
+            continue;
+        }
+        if args.len() > *total {
+            warnings.push(format!(
+                "Line {}: {} takes at most {} arguments, extra ones are ignored",
+                number, name, total
+            ));
+        }
+        let alias = unquote(&args[0]).to_string();
+        if !aliases.insert(alias.clone()) {
+            errors.push(format!(
+                "Line {}: alias `{}` is already defined",
+                number, alias
+            ));
+        }
+        if !allowed(diagram_type, *level) {
+            warnings.push(format!(
+                "Line {}: {} is not usually shown in a {} diagram",
+                number, name, diagram_type
+            ));
+        }
+        if opens {
+            if !name.ends_with("Boundary") && *level != Level::Deployment {
+                errors.push(format!(
+                    "Line {}: {} cannot contain other elements",
+                    number, name
+                ));
+            }
+            depth += 1;
+        }
+    }
+
+    if depth > 0 {
+        errors.push(format!("{} boundary block(s) are not closed", depth));
+    }
+    for (number, from, to) in relations {
+        for alias in [&from, &to] {
+            if !aliases.contains(alias.as_str()) {
+                warnings.push(format!(
+                    "Line {}: relationship refers to undefined element `{}`",
+                    number, alias
+                ));
+            }
+        }
+    }
+}
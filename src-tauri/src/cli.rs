@@ -0,0 +1,531 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::export::{self, ExportOptions, Orientation, PageSize};
+use crate::export_css;
+use crate::signing::{self, SignatureMode};
+use crate::watch::{self, WatchConfig, WatchEvent};
+use crate::{
+    file_settings, format, git_hook, kroki, load_app_state, markdown, mermaid_versions, render,
+    themes, validate_content, workspace_export,
+};
+
+const USAGE: &str = "FlowCraft Studio
+
+Usage: flowcraft-studio <command> [options]
+
+Commands:
+  validate <input>...             Check diagrams for syntax problems, including
+                                  the mermaid blocks of Markdown files
+  validate --staged               Check the diagrams staged in the current git
+                                  repository (used by the pre-commit hook)
+  render <input> -o <output>      Render a diagram to SVG, PNG, PDF, TIFF, EMF or EPS
+  export <input> -o <output>      Same as render
+  format <input>... [--check]     Normalize indentation and whitespace in place,
+                                  or into -o <output> for a single input
+  convert <input> -o <output>     Convert between .mmd and Markdown files
+  watch <dir> -o <dir>            Re-export changed diagrams until interrupted
+  export-all <dir> -o <dir>       Export every diagram under <dir> whose output is
+                                  out of date, and write export-manifest.json;
+                                  fails when any diagram fails to render
+  verify <file>...                Check the signatures of exported SVG or PDF files
+                                  against the editor's own and trusted keys
+  help                            Show this message
+
+Options:
+  -o, --output <path>             Output file
+  -f, --format <format>           svg, png, pdf, tiff, or emf and eps, which need
+                                  Inkscape
+                                  (defaults to the output extension, or a
+                                  comma-separated list of svg, png and pdf for watch)
+      --check                     Report unformatted files instead of rewriting them
+      --embed-fonts               Embed the fonts an SVG uses so it renders the same
+                                  on machines without them
+      --inline-images             Embed local images the diagram refers to, so the
+                                  export works without them
+      --emoji                     Expand :rocket:-style shortcodes in labels
+      --css <file>                Apply the CSS in <file> instead of the CSS stored
+                                  for the diagram and workspace
+      --max-width <px>            Scale the diagram down to at most this width
+      --max-height <px>           Scale the diagram down to at most this height
+      --fit <a4|letter>           Scale the diagram to fit one page (PDF pages use
+                                  this size)
+      --tile <a4|letter>          Split a PDF across as many pages of this size as
+                                  it needs, with overlap marks for assembly
+      --landscape                 Use landscape pages with --fit or --tile
+      --sign <detached|embedded>  Sign SVG or PDF output with the editor's signing
+                                  key, in a .sig file or inside the export
+      --optimize-png <0-6>        Recompress PNG output losslessly; higher levels
+                                  take longer and save more
+      --dpi <n>                   Resolution of TIFF output (default 300)
+      --key <public key>          Also trust this base64 Ed25519 public key when
+                                  verifying; can be repeated
+      --pattern <pattern>         Output names for export-all, from {relpath},
+                                  {name} and {ext} (default {relpath}/{name}.{ext})
+      --theme <id>                Render with this theme instead of the editor's
+                                  or the one a diagram's frontmatter picks
+      --kroki <url>               Render on this Kroki server instead of with the
+                                  Mermaid CLI
+
+Running without a command starts the editor.";
+
+#[derive(Debug, Default)]
+struct CliArgs {
+    inputs: Vec<PathBuf>,
+    output: Option<PathBuf>,
+    format: Option<String>,
+    theme: Option<String>,
+    kroki: Option<String>,
+    pattern: Option<String>,
+    keys: Vec<String>,
+    check: bool,
+    staged: bool,
+    export: ExportOptions,
+}
+
+// Returns None when the arguments are not a CLI invocation, in which case
+// the caller should start the GUI instead.
+pub fn run_from_args<I>(args: I) -> Option<i32>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter();
+    let command = args.next()?;
+    let rest: Vec<String> = args.collect();
+
+    let result = match command.as_str() {
+        "validate" => parse_args(&rest).and_then(|args| validate(&args)),
+        "render" | "export" => parse_args(&rest).and_then(|args| render_diagram(&args)),
+        "format" => parse_args(&rest).and_then(|args| format_files(&args)),
+        "convert" => parse_args(&rest).and_then(|args| convert(&args)),
+        "watch" => parse_args(&rest).and_then(|args| watch_folder(&args)),
+        "export-all" => parse_args(&rest).and_then(|args| export_all(&args)),
+        "verify" => parse_args(&rest).and_then(|args| verify(&args)),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => return None,
+    };
+
+    match result {
+        Ok(()) => Some(0),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            Some(1)
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<CliArgs, String> {
+    let mut parsed = CliArgs::default();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                let value = iter.next().ok_or("Missing value for --output")?;
+                parsed.output = Some(PathBuf::from(value));
+            }
+            "-f" | "--format" => {
+                let value = iter.next().ok_or("Missing value for --format")?;
+                parsed.format = Some(value.to_lowercase());
+            }
+            "--pattern" => {
+                let value = iter.next().ok_or("Missing value for --pattern")?;
+                parsed.pattern = Some(value.clone());
+            }
+            "--theme" => {
+                let value = iter.next().ok_or("Missing value for --theme")?;
+                parsed.theme = Some(value.clone());
+            }
+            "--kroki" => {
+                let value = iter.next().ok_or("Missing value for --kroki")?;
+                kroki::validate_url(value)?;
+                parsed.kroki = Some(value.clone());
+            }
+            "--key" => {
+                let value = iter.next().ok_or("Missing value for --key")?;
+                signing::validate_public_keys(std::slice::from_ref(value))?;
+                parsed.keys.push(value.clone());
+            }
+            "--check" => parsed.check = true,
+            "--staged" => parsed.staged = true,
+            "--embed-fonts" => parsed.export.embed_fonts = true,
+            "--inline-images" => parsed.export.inline_images = true,
+            "--emoji" => parsed.export.expand_emoji = true,
+            "--css" => {
+                let value = iter.next().ok_or("Missing value for --css")?;
+                parsed.export.css = Some(read_input(Path::new(value))?);
+            }
+            "--max-width" => {
+                let value = iter.next().ok_or("Missing value for --max-width")?;
+                parsed.export.max_width = Some(parse_pixels(value)?);
+            }
+            "--max-height" => {
+                let value = iter.next().ok_or("Missing value for --max-height")?;
+                parsed.export.max_height = Some(parse_pixels(value)?);
+            }
+            "--fit" => {
+                let value = iter.next().ok_or("Missing value for --fit")?;
+                parsed.export.fit_to_page = Some(PageSize::parse(value)?);
+            }
+            "--tile" => {
+                let value = iter.next().ok_or("Missing value for --tile")?;
+                parsed.export.tile = Some(PageSize::parse(value)?);
+            }
+            "--landscape" => parsed.export.orientation = Orientation::Landscape,
+            "--sign" => {
+                let value = iter.next().ok_or("Missing value for --sign")?;
+                parsed.export.sign = Some(SignatureMode::parse(value)?);
+            }
+            "--dpi" => {
+                let value = iter.next().ok_or("Missing value for --dpi")?;
+                let dpi = value
+                    .parse()
+                    .map_err(|_| format!("Invalid resolution: {}", value))?;
+                export::validate_dpi(dpi)?;
+                parsed.export.dpi = Some(dpi);
+            }
+            "--optimize-png" => {
+                let value = iter.next().ok_or("Missing value for --optimize-png")?;
+                let level = value
+                    .parse()
+                    .map_err(|_| format!("Invalid optimization level: {}", value))?;
+                export::validate_png_optimization(level)?;
+                parsed.export.optimize_png = Some(level);
+            }
+            flag if flag.starts_with('-') => return Err(format!("Unknown option: {}", flag)),
+            path => parsed.inputs.push(PathBuf::from(path)),
+        }
+    }
+
+    if parsed.inputs.is_empty() && !parsed.staged {
+        return Err("No input file given".to_string());
+    }
+
+    Ok(parsed)
+}
+
+fn parse_pixels(value: &str) -> Result<u32, String> {
+    value
+        .trim_end_matches("px")
+        .parse()
+        .ok()
+        .filter(|pixels| *pixels > 0)
+        .ok_or_else(|| format!("Invalid size: {}", value))
+}
+
+fn read_input(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+fn single_input(args: &CliArgs) -> Result<&Path, String> {
+    match args.inputs.as_slice() {
+        [input] => Ok(input),
+        _ => Err("Expected exactly one input file".to_string()),
+    }
+}
+
+// Prints the problems in one diagram, or in each mermaid block of a
+// Markdown file, and returns whether any of them is invalid.
+fn report(name: &str, content: &str, markdown: bool) -> bool {
+    let diagrams = if markdown {
+        markdown::extract_code_blocks(content)
+            .into_iter()
+            .enumerate()
+            .map(|(index, block)| (format!("{} (block {})", name, index + 1), block))
+            .collect()
+    } else {
+        vec![(name.to_string(), content.to_string())]
+    };
+
+    let mut invalid = false;
+    for (label, diagram) in diagrams {
+        let result = validate_content(&diagram);
+
+        for warning in &result.warnings {
+            println!("{}: warning: {}", label, warning);
+        }
+        for error in &result.errors {
+            println!("{}: error: {}", label, error);
+        }
+        invalid |= !result.is_valid;
+    }
+    invalid
+}
+
+fn validate(args: &CliArgs) -> Result<(), String> {
+    let mut failed = 0;
+
+    for input in &args.inputs {
+        let name = input.display().to_string();
+        if report(&name, &read_input(input)?, is_markdown(input)) {
+            failed += 1;
+        }
+    }
+
+    if args.staged {
+        for (name, content) in git_hook::staged_files()? {
+            if report(&name, &content, is_markdown(Path::new(&name))) {
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} file(s) failed validation", failed));
+    }
+
+    Ok(())
+}
+
+// Renders with the editor's theme, Mermaid settings and pinned Mermaid
+// version or Kroki server; --theme and --kroki override them.
+fn apply_render_config(args: &CliArgs) -> Result<(), String> {
+    if let Some(theme) = &args.theme {
+        themes::get(theme)?;
+    }
+    let app_state = load_app_state().unwrap_or_default();
+    let theme = args.theme.as_deref().or(app_state.theme.as_deref());
+    crate::apply_render_config(theme, &app_state.mermaid_config)?;
+    render::set_binary(mermaid_versions::mmdc_path(&app_state.mermaid_renderer));
+    render::set_kroki_url(
+        args.kroki
+            .clone()
+            .or_else(|| app_state.mermaid_renderer.kroki_url.clone()),
+    );
+    Ok(())
+}
+
+fn render_diagram(args: &CliArgs) -> Result<(), String> {
+    let input = single_input(args)?;
+    let output = args.output.as_deref().ok_or("Missing --output")?;
+    let format = match &args.format {
+        Some(format) => format.clone(),
+        None => export::format_from_path(output)
+            .ok_or("Could not determine output format, pass --format")?,
+    };
+
+    let content = read_input(input)?;
+    let result = validate_content(&content);
+    if !result.is_valid {
+        return Err(result.errors.join("; "));
+    }
+
+    apply_render_config(args)?;
+    let mut options = args.export.clone();
+    if options.image_dir.is_none() {
+        options.image_dir = input.parent().map(|dir| dir.to_string_lossy().to_string());
+    }
+    let state = load_app_state().unwrap_or_default();
+    options.workspace = state.workspace.clone();
+    options.strip_unsafe |= state.strip_unsafe_content;
+    options.redaction = state.redaction.active();
+    let workspace = state.workspace.as_deref().map(Path::new);
+    if options.css.is_none() {
+        options.css = export_css::resolve(Some(input), workspace)?;
+    }
+    let run = || export::export(&content, output, &format, &options);
+    let result = match args.theme {
+        Some(_) => run()?,
+        None => {
+            // Fails on settings that do not parse, unlike the app.
+            file_settings::get(&content)?;
+            let theme = file_settings::export_theme(&content, Some(input), workspace);
+            file_settings::with_theme(&theme, &state.mermaid_config, run)?
+        }
+    };
+    for warning in &result.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    if result.pages > 1 {
+        println!("{} ({} pages)", output.display(), result.pages);
+    } else {
+        println!("{}", output.display());
+    }
+    if let Some(signature) = &result.signature {
+        println!("{}", signature);
+    }
+    Ok(())
+}
+
+fn verify(args: &CliArgs) -> Result<(), String> {
+    let mut trusted = load_app_state().unwrap_or_default().trusted_signing_keys;
+    trusted.extend(args.keys.iter().cloned());
+    let mut failed = 0;
+
+    for input in &args.inputs {
+        match signing::verify(input, &trusted) {
+            Ok(result) if result.valid => {
+                println!("{}: signed by {}", input.display(), result.fingerprint)
+            }
+            Ok(result) if result.signature_matches => {
+                println!(
+                    "{}: signed by untrusted key {}; pass it with --key to trust it",
+                    input.display(),
+                    result.fingerprint
+                );
+                failed += 1;
+            }
+            Ok(result) => {
+                println!(
+                    "{}: signature does not match (key {})",
+                    input.display(),
+                    result.fingerprint
+                );
+                failed += 1;
+            }
+            Err(e) => {
+                println!("{}: {}", input.display(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} file(s) failed verification", failed));
+    }
+
+    Ok(())
+}
+
+fn format_files(args: &CliArgs) -> Result<(), String> {
+    // Every input would be written to the same file.
+    if args.output.is_some() && args.inputs.len() > 1 {
+        return Err("--output takes a single input file".to_string());
+    }
+    let mut unformatted = 0;
+
+    for input in &args.inputs {
+        let content = read_input(input)?;
+        let formatted = format::format_diagram(&content);
+
+        if args.check {
+            if formatted != content {
+                println!("{}", input.display());
+                unformatted += 1;
+            }
+            continue;
+        }
+
+        // A separate output is written even when nothing changed.
+        let target = match args.output.as_deref() {
+            Some(output) => output,
+            None if formatted != content => input.as_path(),
+            None => continue,
+        };
+        fs::write(target, formatted)
+            .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+    }
+
+    if unformatted > 0 {
+        return Err(format!("{} file(s) need formatting", unformatted));
+    }
+
+    Ok(())
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown"))
+        .unwrap_or(false)
+}
+
+fn convert(args: &CliArgs) -> Result<(), String> {
+    let input = single_input(args)?;
+    let output = args.output.as_deref().ok_or("Missing --output")?;
+    let content = read_input(input)?;
+
+    let converted = match (is_markdown(input), is_markdown(output)) {
+        (true, false) => markdown::extract_code_blocks(&content)
+            .into_iter()
+            .next()
+            .ok_or("No mermaid code block found")?,
+        (false, true) => markdown::to_code_block(&content),
+        _ => format::format_diagram(&content),
+    };
+
+    fs::write(output, converted).map_err(|e| format!("Failed to write {}: {}", output.display(), e))
+}
+
+fn export_all(args: &CliArgs) -> Result<(), String> {
+    let input = single_input(args)?;
+    let output = args.output.as_deref().ok_or("Missing --output")?;
+    let format = args.format.as_deref().unwrap_or("svg");
+    let pattern = args
+        .pattern
+        .as_deref()
+        .unwrap_or(workspace_export::DEFAULT_PATTERN);
+    apply_render_config(args)?;
+    let state = load_app_state().unwrap_or_default();
+    let mut options = args.export.clone();
+    options.strip_unsafe |= state.strip_unsafe_content;
+    options.redaction = state.redaction.active();
+    // --theme applies to every diagram, including ones that pick their own.
+    let mermaid_config = match args.theme {
+        Some(_) => None,
+        None => Some(state.mermaid_config),
+    };
+
+    let result = workspace_export::export_all(
+        input,
+        output,
+        pattern,
+        format,
+        &options,
+        mermaid_config.as_ref(),
+    )?;
+    for exported in &result.exported {
+        println!("{}", output.join(&exported.output).display());
+        for warning in &exported.warnings {
+            eprintln!("warning: {}: {}", exported.source, warning);
+        }
+    }
+    for failed in &result.failed {
+        eprintln!("{}: {}", failed.source, failed.error);
+    }
+    println!(
+        "{} exported, {} up to date, {} failed",
+        result.exported.len(),
+        result.skipped.len(),
+        result.failed.len()
+    );
+    if result.failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} diagrams failed to export", result.failed.len()))
+    }
+}
+
+fn watch_folder(args: &CliArgs) -> Result<(), String> {
+    let input = single_input(args)?;
+    let output = args.output.as_deref().ok_or("Missing --output")?;
+    apply_render_config(args)?;
+    let formats = args
+        .format
+        .as_deref()
+        .unwrap_or("svg")
+        .split(',')
+        .map(|f| f.trim().to_string())
+        .filter(|f| !f.is_empty())
+        .collect();
+
+    let config = WatchConfig {
+        source_dir: input.to_string_lossy().to_string(),
+        output_dir: output.to_string_lossy().to_string(),
+        formats,
+    };
+
+    let redaction = load_app_state().unwrap_or_default().redaction.active();
+    let _handle = watch::spawn(config, redaction, |event| match event {
+        WatchEvent::Started { source_dir } => println!("watching {}", source_dir),
+        WatchEvent::Exported { output, .. } => println!("{}", output),
+        WatchEvent::Failed { source, error } => eprintln!("{}: {}", source, error),
+        WatchEvent::Stopped => {}
+    })?;
+
+    loop {
+        std::thread::park();
+    }
+}
@@ -0,0 +1,209 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::importers;
+
+pub mod rust;
+pub mod typescript;
+
+// Folders that hold build output or dependencies rather than the project's
+// own sources.
+const SKIPPED_DIRS: [&str; 4] = ["target", "node_modules", "dist", "build"];
+const MAX_FILES: usize = 1000;
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Generated {
+    pub content: String,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    Inheritance,
+    Realization,
+    Composition,
+    Association,
+}
+
+#[derive(Debug, Default)]
+pub struct Class {
+    pub name: String,
+    pub annotation: Option<String>,
+    pub members: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Link {
+    pub from: String,
+    pub to: String,
+    pub relation: Relation,
+    pub label: Option<String>,
+}
+
+// Classes and the links between them, in the order they were found. Links
+// to types that are not part of the model are dropped when writing it out.
+#[derive(Debug, Default)]
+pub struct Model {
+    pub classes: Vec<Class>,
+    pub links: Vec<Link>,
+    duplicates: Vec<String>,
+}
+
+impl Model {
+    // Types with the same name in different modules would merge into one
+    // class, so only the first is kept and `false` returned for the rest.
+    pub fn add_class(&mut self, name: &str, annotation: Option<&str>) -> bool {
+        if self.class_mut(name).is_some() {
+            if !self.duplicates.iter().any(|duplicate| duplicate == name) {
+                self.duplicates.push(name.to_string());
+            }
+            return false;
+        }
+        self.classes.push(Class {
+            name: name.to_string(),
+            annotation: annotation.map(str::to_string),
+            members: Vec::new(),
+        });
+        true
+    }
+
+    pub fn class_mut(&mut self, name: &str) -> Option<&mut Class> {
+        self.classes.iter_mut().find(|class| class.name == name)
+    }
+
+    pub fn link(&mut self, from: &str, to: &str, relation: Relation, label: Option<&str>) {
+        let link = Link {
+            from: from.to_string(),
+            to: to.to_string(),
+            relation,
+            label: label.map(str::to_string),
+        };
+        if !self.links.contains(&link) {
+            self.links.push(link);
+        }
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        let mut lines = vec!["classDiagram".to_string()];
+        for class in &self.classes {
+            if class.annotation.is_none() && class.members.is_empty() {
+                lines.push(format!("    class {}", class.name));
+                continue;
+            }
+            lines.push(format!("    class {} {{", class.name));
+            if let Some(annotation) = &class.annotation {
+                lines.push(format!("        <<{}>>", annotation));
+            }
+            for member in &class.members {
+                lines.push(format!("        {}", member));
+            }
+            lines.push("    }".to_string());
+        }
+
+        let known = |name: &str| self.classes.iter().any(|class| class.name == name);
+        for link in &self.links {
+            if !known(&link.from) || !known(&link.to) {
+                continue;
+            }
+            // Inheritance and realization point from the child to the
+            // parent, so the parent is written first with the arrow head.
+            let line = match link.relation {
+                Relation::Inheritance => format!("{} <|-- {}", link.to, link.from),
+                Relation::Realization => format!("{} <|.. {}", link.to, link.from),
+                Relation::Composition => format!("{} *-- {}", link.from, link.to),
+                Relation::Association => format!("{} --> {}", link.from, link.to),
+            };
+            match &link.label {
+                Some(label) => lines.push(format!("    {} : {}", line, escape(label))),
+                None => lines.push(format!("    {}", line)),
+            }
+        }
+        lines.join("\n")
+    }
+
+    pub fn into_generated(self, mut warnings: Vec<String>) -> Generated {
+        for name in &self.duplicates {
+            warnings.push(format!(
+                "Several types are named {}; only the first one is shown",
+                name
+            ));
+        }
+        Generated {
+            content: self.to_mermaid(),
+            warnings,
+        }
+    }
+}
+
+// Type text inside class members. Mermaid reads `(` and `)` as the start of
+// a method signature, `;` as the end of a statement and braces as the class
+// body, so they are written as entity codes; generics should already use `~`.
+pub fn escape(text: &str) -> String {
+    importers::escape_text(text, &['{', '}', '(', ')', '<', '>'])
+}
+
+// Source files with one of `extensions` under `dir`, or `dir` itself when it
+// is a file. Hidden folders and build output are skipped.
+pub fn source_files(
+    path: &Path,
+    extensions: &[&str],
+    warnings: &mut Vec<String>,
+) -> Result<Vec<PathBuf>, String> {
+    let metadata =
+        fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if metadata.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    collect(path, extensions, &mut files);
+    if files.is_empty() {
+        return Err(format!("No source files found in {}", path.display()));
+    }
+    // The walk stops one file past the limit, so the total is unknown.
+    if files.len() > MAX_FILES {
+        warnings.push(format!(
+            "Read the first {} source files; the folder has more",
+            MAX_FILES
+        ));
+        files.truncate(MAX_FILES);
+    }
+    Ok(files)
+}
+
+// Walks in name order and stops once it has found more than MAX_FILES, so a
+// huge tree is not read to the end just to be cut down.
+fn collect(dir: &Path, extensions: &[&str], found: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        if found.len() > MAX_FILES {
+            return;
+        }
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()) {
+                collect(&path, extensions, found);
+            }
+        } else if metadata.len() <= MAX_FILE_BYTES
+            && path.extension().is_some_and(|ext| {
+                extensions
+                    .iter()
+                    .any(|known| ext.eq_ignore_ascii_case(known))
+            })
+        {
+            found.push(path);
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use std::fs;
+use std::path::Path;
+use syn::punctuated::Punctuated;
+use syn::{
+    Attribute, Expr, Fields, FnArg, GenericArgument, ImplItem, Item, ItemImpl, Lit, Pat,
+    PathArguments, ReturnType, Signature, TraitItem, Type, TypeParamBound, Visibility,
+};
+
+use super::{escape, source_files, Generated, Model, Relation};
+
+// Pointers that share the value they point to instead of owning it, so
+// fields through them are associations rather than compositions.
+const SHARED: [&str; 3] = ["Rc", "Arc", "Weak"];
+
+#[derive(Default)]
+struct Analyzer {
+    model: Model,
+    impls: Vec<ItemImpl>,
+}
+
+fn ident(ident: &syn::Ident) -> String {
+    let name = ident.to_string();
+    name.strip_prefix("r#").unwrap_or(&name).to_string()
+}
+
+fn visibility(vis: &Visibility) -> &'static str {
+    match vis {
+        Visibility::Public(_) => "+",
+        Visibility::Restricted(_) => "~",
+        Visibility::Inherited => "-",
+    }
+}
+
+fn is_test(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("cfg")
+            && matches!(&attr.meta, syn::Meta::List(list) if list.tokens.to_string() == "test")
+    })
+}
+
+fn join(names: impl Iterator<Item = String>) -> String {
+    names.collect::<Vec<_>>().join(", ")
+}
+
+fn returns(output: &ReturnType) -> String {
+    match output {
+        ReturnType::Default => String::new(),
+        ReturnType::Type(_, ty) => format!(" -> {}", type_name(ty)),
+    }
+}
+
+fn bounds<P>(bounds: &Punctuated<TypeParamBound, P>) -> String {
+    bounds
+        .iter()
+        .filter_map(|bound| match bound {
+            TypeParamBound::Trait(bound) => Some(path_name(&bound.path)),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+// The last segment of a path with its generic arguments, in Mermaid's
+// `Vec~String~` notation.
+fn path_name(path: &syn::Path) -> String {
+    let Some(segment) = path.segments.last() else {
+        return String::new();
+    };
+    let name = ident(&segment.ident);
+    match &segment.arguments {
+        PathArguments::None => name,
+        PathArguments::AngleBracketed(arguments) => {
+            let arguments: Vec<String> = arguments
+                .args
+                .iter()
+                .filter_map(|argument| match argument {
+                    GenericArgument::Type(ty) => Some(type_name(ty)),
+                    GenericArgument::AssocType(assoc) => {
+                        Some(format!("{}={}", ident(&assoc.ident), type_name(&assoc.ty)))
+                    }
+                    _ => None,
+                })
+                .collect();
+            if arguments.is_empty() {
+                name
+            } else {
+                format!("{}~{}~", name, arguments.join(", "))
+            }
+        }
+        PathArguments::Parenthesized(arguments) => format!(
+            "{}({}){}",
+            name,
+            join(arguments.inputs.iter().map(type_name)),
+            returns(&arguments.output)
+        ),
+    }
+}
+
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Path(ty) => path_name(&ty.path),
+        Type::Reference(ty) => format!(
+            "&{}{}",
+            if ty.mutability.is_some() { "mut " } else { "" },
+            type_name(&ty.elem)
+        ),
+        Type::Ptr(ty) => format!(
+            "*{} {}",
+            if ty.mutability.is_some() {
+                "mut"
+            } else {
+                "const"
+            },
+            type_name(&ty.elem)
+        ),
+        Type::Slice(ty) => format!("[{}]", type_name(&ty.elem)),
+        Type::Array(ty) => {
+            let len = match &ty.len {
+                Expr::Lit(len) => match &len.lit {
+                    Lit::Int(int) => int.base10_digits().to_string(),
+                    _ => "_".to_string(),
+                },
+                Expr::Path(len) => path_name(&len.path),
+                _ => "_".to_string(),
+            };
+            format!("[{}; {}]", type_name(&ty.elem), len)
+        }
+        Type::Tuple(ty) => format!("({})", join(ty.elems.iter().map(type_name))),
+        Type::Paren(ty) => type_name(&ty.elem),
+        Type::Group(ty) => type_name(&ty.elem),
+        Type::TraitObject(ty) => format!("dyn {}", bounds(&ty.bounds)),
+        Type::ImplTrait(ty) => format!("impl {}", bounds(&ty.bounds)),
+        Type::BareFn(ty) => format!(
+            "fn({}){}",
+            join(ty.inputs.iter().map(|input| type_name(&input.ty))),
+            returns(&ty.output)
+        ),
+        Type::Never(_) => "!".to_string(),
+        _ => "_".to_string(),
+    }
+}
+
+// Named types a field refers to, and whether it reaches them through a
+// reference or shared pointer.
+fn referenced(ty: &Type, shared: bool, found: &mut Vec<(String, bool)>) {
+    match ty {
+        Type::Path(ty) => {
+            let Some(segment) = ty.path.segments.last() else {
+                return;
+            };
+            let name = ident(&segment.ident);
+            let inner_shared = shared || SHARED.contains(&name.as_str());
+            found.push((name, shared));
+            if let PathArguments::AngleBracketed(arguments) = &segment.arguments {
+                for argument in &arguments.args {
+                    if let GenericArgument::Type(ty) = argument {
+                        referenced(ty, inner_shared, found);
+                    }
+                }
+            }
+        }
+        Type::Reference(ty) => referenced(&ty.elem, true, found),
+        Type::Ptr(ty) => referenced(&ty.elem, true, found),
+        Type::Slice(ty) => referenced(&ty.elem, shared, found),
+        Type::Array(ty) => referenced(&ty.elem, shared, found),
+        Type::Paren(ty) => referenced(&ty.elem, shared, found),
+        Type::Group(ty) => referenced(&ty.elem, shared, found),
+        Type::Tuple(ty) => {
+            for elem in &ty.elems {
+                referenced(elem, shared, found);
+            }
+        }
+        Type::TraitObject(ty) => {
+            for bound in &ty.bounds {
+                if let TypeParamBound::Trait(bound) = bound {
+                    if let Some(segment) = bound.path.segments.last() {
+                        found.push((ident(&segment.ident), shared));
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// A method as `+name(arg: Type) Return`, marked static (`$`) without a
+// receiver and abstract (`*`) for trait methods without a default body.
+fn method(sig: &Signature, visibility: &str, is_abstract: bool) -> String {
+    let params = join(sig.inputs.iter().filter_map(|input| match input {
+        FnArg::Typed(arg) => {
+            let name = match &*arg.pat {
+                Pat::Ident(pat) => ident(&pat.ident),
+                _ => "_".to_string(),
+            };
+            Some(format!("{}: {}", name, type_name(&arg.ty)))
+        }
+        FnArg::Receiver(_) => None,
+    }));
+    let output = match &sig.output {
+        ReturnType::Default => String::new(),
+        ReturnType::Type(_, ty) => format!(" {}", escape(&type_name(ty))),
+    };
+    let classifier = if is_abstract {
+        "*"
+    } else if sig.receiver().is_none() {
+        "$"
+    } else {
+        ""
+    };
+    format!(
+        "{}{}({}){}{}",
+        visibility,
+        ident(&sig.ident),
+        escape(&params),
+        output,
+        classifier
+    )
+}
+
+impl Analyzer {
+    fn add_class(&mut self, name: &syn::Ident, annotation: Option<&str>) -> Option<String> {
+        let name = ident(name);
+        self.model.add_class(&name, annotation).then_some(name)
+    }
+
+    fn link_type(&mut self, owner: &str, ty: &Type, label: &str) {
+        let mut found = Vec::new();
+        referenced(ty, false, &mut found);
+        for (target, shared) in found {
+            let relation = if shared {
+                Relation::Association
+            } else {
+                Relation::Composition
+            };
+            self.model.link(owner, &target, relation, Some(label));
+        }
+    }
+
+    fn fields(&mut self, owner: &str, fields: &Fields) {
+        for (index, field) in fields.iter().enumerate() {
+            let name = field
+                .ident
+                .as_ref()
+                .map(ident)
+                .unwrap_or_else(|| index.to_string());
+            let member = format!(
+                "{}{}: {}",
+                visibility(&field.vis),
+                name,
+                escape(&type_name(&field.ty))
+            );
+            if let Some(class) = self.model.class_mut(owner) {
+                class.members.push(member);
+            }
+            self.link_type(owner, &field.ty, &name);
+        }
+    }
+
+    fn items(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Struct(item) if !is_test(&item.attrs) => {
+                    if let Some(name) = self.add_class(&item.ident, None) {
+                        self.fields(&name, &item.fields);
+                    }
+                }
+                Item::Union(item) if !is_test(&item.attrs) => {
+                    if let Some(name) = self.add_class(&item.ident, Some("union")) {
+                        self.fields(&name, &Fields::Named(item.fields.clone()));
+                    }
+                }
+                Item::Enum(item) if !is_test(&item.attrs) => {
+                    let Some(name) = self.add_class(&item.ident, Some("enum")) else {
+                        continue;
+                    };
+                    for variant in &item.variants {
+                        let variant_name = ident(&variant.ident);
+                        if let Some(class) = self.model.class_mut(&name) {
+                            class.members.push(variant_name.clone());
+                        }
+                        for field in &variant.fields {
+                            self.link_type(&name, &field.ty, &variant_name);
+                        }
+                    }
+                }
+                Item::Trait(item) if !is_test(&item.attrs) => {
+                    let Some(name) = self.add_class(&item.ident, Some("trait")) else {
+                        continue;
+                    };
+                    for bound in &item.supertraits {
+                        if let TypeParamBound::Trait(bound) = bound {
+                            if let Some(segment) = bound.path.segments.last() {
+                                let parent = ident(&segment.ident);
+                                self.model.link(&name, &parent, Relation::Inheritance, None);
+                            }
+                        }
+                    }
+                    let methods: Vec<String> = item
+                        .items
+                        .iter()
+                        .filter_map(|item| match item {
+                            TraitItem::Fn(item) => {
+                                Some(method(&item.sig, "+", item.default.is_none()))
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    if let Some(class) = self.model.class_mut(&name) {
+                        class.members.extend(methods);
+                    }
+                }
+                Item::Impl(item) if !is_test(&item.attrs) => self.impls.push(item.clone()),
+                Item::Mod(item) if !is_test(&item.attrs) => {
+                    if let Some((_, items)) = &item.content {
+                        self.items(items);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Impl blocks may come before the type or live in another file, so
+    // they are applied once every type is known. Inherent methods become
+    // members and trait impls become realizations.
+    fn apply_impls(&mut self) {
+        for item in std::mem::take(&mut self.impls) {
+            let Type::Path(self_ty) = &*item.self_ty else {
+                continue;
+            };
+            let Some(name) = self_ty.path.segments.last().map(|s| ident(&s.ident)) else {
+                continue;
+            };
+            match &item.trait_ {
+                Some((_, path, _)) => {
+                    if let Some(segment) = path.segments.last() {
+                        let parent = ident(&segment.ident);
+                        self.model.link(&name, &parent, Relation::Realization, None);
+                    }
+                }
+                None => {
+                    let Some(class) = self.model.class_mut(&name) else {
+                        continue;
+                    };
+                    class
+                        .members
+                        .extend(item.items.iter().filter_map(|item| match item {
+                            ImplItem::Fn(item) => {
+                                Some(method(&item.sig, visibility(&item.vis), false))
+                            }
+                            _ => None,
+                        }));
+                }
+            }
+        }
+    }
+}
+
+// Builds a class diagram of the structs, enums and traits in a Rust file,
+// or in every Rust file below a folder such as a crate root. Fields that
+// hold other types of the code become compositions, or associations
+// through references and shared pointers; trait impls and supertraits
+// become realizations and inheritance.
+pub fn generate(path: &Path) -> Result<Generated, String> {
+    let mut warnings = Vec::new();
+    let files = source_files(path, &["rs"], &mut warnings)?;
+
+    let mut analyzer = Analyzer::default();
+    for file in &files {
+        let parsed = fs::read_to_string(file)
+            .map_err(|e| e.to_string())
+            .and_then(|text| syn::parse_file(&text).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(parsed) => analyzer.items(&parsed.items),
+            Err(e) => warnings.push(format!("Skipped {}: {}", file.display(), e)),
+        }
+    }
+    analyzer.apply_impls();
+
+    if analyzer.model.classes.is_empty() {
+        return Err(format!(
+            "No structs, enums or traits found in {}",
+            path.display()
+        ));
+    }
+    Ok(analyzer.model.into_generated(warnings))
+}
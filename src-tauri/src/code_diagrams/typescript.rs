@@ -0,0 +1,398 @@
+use std::fs;
+use std::path::Path;
+use tree_sitter::{Language, Node, Parser};
+
+use super::{escape, source_files, Generated, Model, Relation};
+
+const EXTENSIONS: [&str; 8] = ["ts", "tsx", "mts", "cts", "js", "jsx", "mjs", "cjs"];
+
+// Longer type annotations, such as inline object types, are cut short so
+// they do not stretch the class box across the diagram.
+const MAX_TYPE_CHARS: usize = 60;
+
+struct Source<'a> {
+    text: &'a [u8],
+}
+
+impl Source<'_> {
+    fn text(&self, node: Node) -> String {
+        node.utf8_text(self.text).unwrap_or_default().to_string()
+    }
+
+    fn field(&self, node: Node, name: &str) -> Option<String> {
+        node.child_by_field_name(name).map(|child| self.text(child))
+    }
+
+    // A type annotation without its leading `:`, on one line, with generics
+    // in Mermaid's `Array~Item~` notation.
+    fn type_text(&self, annotation: Option<Node>) -> Option<String> {
+        let text = self.text(annotation?);
+        let text = text
+            .trim_start_matches(':')
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut text = text
+            .replace("=>", "\u{0}")
+            .replace(['<', '>'], "~")
+            .replace('\u{0}', "=>");
+        if text.chars().count() > MAX_TYPE_CHARS {
+            text = text.chars().take(MAX_TYPE_CHARS - 1).collect::<String>() + "…";
+        }
+        Some(escape(&text))
+    }
+}
+
+fn language(path: &Path) -> Language {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        // JavaScript parses as TSX, which also accepts JSX in `.js` files.
+        "ts" | "mts" | "cts" => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+        _ => tree_sitter_typescript::LANGUAGE_TSX.into(),
+    }
+}
+
+fn children(node: Node) -> Vec<Node> {
+    let mut cursor = node.walk();
+    node.children(&mut cursor).collect()
+}
+
+fn has_token(node: Node, token: &str) -> bool {
+    children(node).iter().any(|child| child.kind() == token)
+}
+
+// TypeScript members are public unless marked otherwise; `#name` fields
+// are private in JavaScript too.
+fn visibility_of(source: &Source, member: Node) -> &'static str {
+    let modifier = children(member)
+        .into_iter()
+        .find(|child| child.kind() == "accessibility_modifier")
+        .map(|modifier| source.text(modifier));
+    let is_private_name = member
+        .child_by_field_name("name")
+        .is_some_and(|name| name.kind() == "private_property_identifier");
+    match modifier.as_deref() {
+        Some("private") => "-",
+        Some("protected") => "#",
+        _ if is_private_name => "-",
+        _ => "+",
+    }
+}
+
+// The last name of `Base`, `ns.Base` or `Base<T>`.
+fn base_name(source: &Source, node: Node) -> Option<String> {
+    let text = source.text(node);
+    let text = text.split('<').next().unwrap_or_default();
+    text.rsplit('.')
+        .next()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+// Visits `root` and its descendants in order, with a cursor rather than
+// recursion so deeply nested code cannot overflow the stack. Children of a
+// node are skipped when `visit` returns false.
+fn walk<'tree>(root: Node<'tree>, mut visit: impl FnMut(Node<'tree>) -> bool) {
+    let mut cursor = root.walk();
+    loop {
+        if visit(cursor.node()) && cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return;
+            }
+        }
+    }
+}
+
+fn type_identifiers(source: &Source, node: Node, found: &mut Vec<String>) {
+    walk(node, |node| {
+        if node.kind() == "type_identifier" {
+            found.push(source.text(node));
+        }
+        true
+    });
+}
+
+struct Analyzer<'a> {
+    source: Source<'a>,
+    model: &'a mut Model,
+}
+
+impl Analyzer<'_> {
+    fn visit(&mut self, node: Node) {
+        walk(node, |node| {
+            match node.kind() {
+                "class_declaration" | "abstract_class_declaration" => self.class(node),
+                "interface_declaration" => self.interface(node),
+                "enum_declaration" => self.enumeration(node),
+                _ => return true,
+            }
+            false
+        });
+    }
+
+    fn link_types(&mut self, owner: &str, annotation: Option<Node>, label: &str) {
+        let Some(annotation) = annotation else {
+            return;
+        };
+        let mut found = Vec::new();
+        type_identifiers(&self.source, annotation, &mut found);
+        for target in found {
+            self.model
+                .link(owner, &target, Relation::Association, Some(label));
+        }
+    }
+
+    fn add_member(&mut self, owner: &str, member: String) {
+        if let Some(class) = self.model.class_mut(owner) {
+            class.members.push(member);
+        }
+    }
+
+    fn field(&mut self, owner: &str, member: Node, visibility: &str) {
+        let Some(name) = self.source.field(member, "name").map(|name| escape(&name)) else {
+            return;
+        };
+        let optional = if has_token(member, "?") { "?" } else { "" };
+        let annotation = member.child_by_field_name("type");
+        let classifier = if has_token(member, "static") { "$" } else { "" };
+        let line = match self.source.type_text(annotation) {
+            Some(ty) => format!("{}{}{}: {}{}", visibility, name, optional, ty, classifier),
+            None => format!("{}{}{}{}", visibility, name, optional, classifier),
+        };
+        self.add_member(owner, line);
+        self.link_types(owner, annotation, &name);
+    }
+
+    fn method(&mut self, owner: &str, member: Node, visibility: &str, is_abstract: bool) {
+        let Some(name) = self.source.field(member, "name").map(|name| escape(&name)) else {
+            return;
+        };
+        let mut params = Vec::new();
+        if let Some(parameters) = member.child_by_field_name("parameters") {
+            for parameter in children(parameters) {
+                if !matches!(
+                    parameter.kind(),
+                    "required_parameter" | "optional_parameter"
+                ) {
+                    continue;
+                }
+                let Some(pattern) = parameter.child_by_field_name("pattern") else {
+                    continue;
+                };
+                if pattern.kind() == "this" {
+                    continue;
+                }
+                let pattern = escape(&self.source.text(pattern));
+                let optional = if parameter.kind() == "optional_parameter" {
+                    "?"
+                } else {
+                    ""
+                };
+                let annotation = parameter.child_by_field_name("type");
+
+                // Constructor parameters with a modifier are also fields.
+                if name == "constructor"
+                    && (has_token(parameter, "accessibility_modifier")
+                        || has_token(parameter, "readonly"))
+                {
+                    let visibility = visibility_of(&self.source, parameter);
+                    let line = match self.source.type_text(annotation) {
+                        Some(ty) => format!("{}{}{}: {}", visibility, pattern, optional, ty),
+                        None => format!("{}{}{}", visibility, pattern, optional),
+                    };
+                    self.add_member(owner, line);
+                    self.link_types(owner, annotation, &pattern);
+                }
+
+                params.push(match self.source.type_text(annotation) {
+                    Some(ty) => format!("{}{}: {}", pattern, optional, ty),
+                    None => format!("{}{}", pattern, optional),
+                });
+            }
+        }
+        let output = self
+            .source
+            .type_text(member.child_by_field_name("return_type"))
+            .map(|ty| format!(" {}", ty))
+            .unwrap_or_default();
+        let classifier = if is_abstract {
+            "*"
+        } else if has_token(member, "static") {
+            "$"
+        } else {
+            ""
+        };
+        let line = format!(
+            "{}{}({}){}{}",
+            visibility,
+            name,
+            params.join(", "),
+            output,
+            classifier
+        );
+        self.add_member(owner, line);
+    }
+
+    fn class(&mut self, node: Node) {
+        let Some(name) = self.source.field(node, "name") else {
+            return;
+        };
+        let annotation = (node.kind() == "abstract_class_declaration").then_some("abstract");
+        if !self.model.add_class(&name, annotation) {
+            return;
+        }
+
+        for heritage in children(node)
+            .into_iter()
+            .filter(|child| child.kind() == "class_heritage")
+        {
+            for clause in children(heritage) {
+                let (relation, bases) = match clause.kind() {
+                    "extends_clause" => (
+                        Relation::Inheritance,
+                        clause.child_by_field_name("value").into_iter().collect(),
+                    ),
+                    "implements_clause" => {
+                        let mut cursor = clause.walk();
+                        let bases: Vec<Node> = clause.named_children(&mut cursor).collect();
+                        (Relation::Realization, bases)
+                    }
+                    _ => continue,
+                };
+                for base in bases {
+                    if let Some(base) = base_name(&self.source, base) {
+                        self.model.link(&name, &base, relation, None);
+                    }
+                }
+            }
+        }
+
+        let Some(body) = node.child_by_field_name("body") else {
+            return;
+        };
+        for member in children(body) {
+            let visibility = visibility_of(&self.source, member);
+            match member.kind() {
+                "public_field_definition" | "field_definition" => {
+                    self.field(&name, member, visibility)
+                }
+                "method_definition" | "method_signature" => {
+                    self.method(&name, member, visibility, false)
+                }
+                "abstract_method_signature" => self.method(&name, member, visibility, true),
+                _ => {}
+            }
+        }
+    }
+
+    fn interface(&mut self, node: Node) {
+        let Some(name) = self.source.field(node, "name") else {
+            return;
+        };
+        if !self.model.add_class(&name, Some("interface")) {
+            return;
+        }
+
+        for clause in children(node)
+            .into_iter()
+            .filter(|child| child.kind() == "extends_type_clause")
+        {
+            let mut cursor = clause.walk();
+            for base in clause.children_by_field_name("type", &mut cursor) {
+                if let Some(base) = base_name(&self.source, base) {
+                    self.model.link(&name, &base, Relation::Inheritance, None);
+                }
+            }
+        }
+
+        let Some(body) = node.child_by_field_name("body") else {
+            return;
+        };
+        for member in children(body) {
+            match member.kind() {
+                "property_signature" => self.field(&name, member, "+"),
+                "method_signature" => self.method(&name, member, "+", false),
+                _ => {}
+            }
+        }
+    }
+
+    fn enumeration(&mut self, node: Node) {
+        let Some(name) = self.source.field(node, "name") else {
+            return;
+        };
+        if !self.model.add_class(&name, Some("enumeration")) {
+            return;
+        }
+        let Some(body) = node.child_by_field_name("body") else {
+            return;
+        };
+        let mut cursor = body.walk();
+        let members: Vec<String> = body
+            .named_children(&mut cursor)
+            .filter_map(|member| match member.kind() {
+                "enum_assignment" => self.source.field(member, "name"),
+                "comment" => None,
+                _ => Some(self.source.text(member)),
+            })
+            .map(|member| escape(member.trim_matches(['"', '\''])))
+            .collect();
+        if let Some(class) = self.model.class_mut(&name) {
+            class.members.extend(members);
+        }
+    }
+}
+
+// Builds a class diagram of the classes, interfaces and enums in a
+// TypeScript or JavaScript file, or every such file below a folder. Fields
+// typed with other classes of the code become associations, and `extends`
+// and `implements` become inheritance and realization.
+pub fn generate(path: &Path) -> Result<Generated, String> {
+    let mut warnings = Vec::new();
+    let files = source_files(path, &EXTENSIONS, &mut warnings)?;
+
+    let mut model = Model::default();
+    let mut parser = Parser::new();
+    for file in &files {
+        let text = match fs::read_to_string(file) {
+            Ok(text) => text,
+            Err(e) => {
+                warnings.push(format!("Skipped {}: {}", file.display(), e));
+                continue;
+            }
+        };
+        parser
+            .set_language(&language(file))
+            .map_err(|e| format!("Failed to load the TypeScript parser: {}", e))?;
+        let Some(tree) = parser.parse(&text, None) else {
+            warnings.push(format!("Skipped {}: could not be parsed", file.display()));
+            continue;
+        };
+        // Parsing recovers from syntax errors, so the rest of the file is
+        // still read.
+        if tree.root_node().has_error() {
+            warnings.push(format!("{} has syntax errors", file.display()));
+        }
+        let mut analyzer = Analyzer {
+            source: Source {
+                text: text.as_bytes(),
+            },
+            model: &mut model,
+        };
+        analyzer.visit(tree.root_node());
+    }
+
+    if model.classes.is_empty() {
+        return Err(format!(
+            "No classes, interfaces or enums found in {}",
+            path.display()
+        ));
+    }
+    Ok(model.into_generated(warnings))
+}
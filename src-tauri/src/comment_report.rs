@@ -0,0 +1,188 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::files;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiagramComment {
+    pub line: usize,
+    pub text: String,
+    // TODO, FIXME, NOTE, HACK or XXX when the comment starts with one; it is
+    // removed from `text`.
+    pub marker: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileComments {
+    pub path: String,
+    pub comments: Vec<DiagramComment>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CommentReport {
+    // Only diagrams with comments, by path.
+    pub files: Vec<FileComments>,
+    pub total: usize,
+    // How many comments carry each marker.
+    pub markers: BTreeMap<String, usize>,
+}
+
+// The `%%` comments of a diagram. Mermaid only has whole-line comments;
+// `%%{...}%%` directives and the frontmatter are skipped, as are comments
+// with no text.
+pub fn extract(content: &str) -> Vec<DiagramComment> {
+    // Markers in lower case need a colon, so "note that..." stays prose.
+    let marker = Regex::new(
+        r"^(?:(TODO|FIXME|NOTE|HACK|XXX)\b|(?i:(todo|fixme|note|hack|xxx))\s*:)\s*:?\s*",
+    )
+    .unwrap();
+    let mut comments = Vec::new();
+    let mut in_frontmatter = false;
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed == "---" && (index == 0 || in_frontmatter) {
+            in_frontmatter = !in_frontmatter;
+            continue;
+        }
+        if in_frontmatter || trimmed.starts_with("%%{") {
+            continue;
+        }
+        let Some(text) = trimmed.strip_prefix("%%") else {
+            continue;
+        };
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let comment = match marker.captures(text) {
+            Some(caps) => DiagramComment {
+                line: index + 1,
+                text: text[caps.get(0).unwrap().end()..].to_string(),
+                marker: caps
+                    .get(1)
+                    .or_else(|| caps.get(2))
+                    .map(|marker| marker.as_str().to_uppercase()),
+            },
+            None => DiagramComment {
+                line: index + 1,
+                text: text.to_string(),
+                marker: None,
+            },
+        };
+        comments.push(comment);
+    }
+    comments
+}
+
+// The comments of a diagram, or of every diagram under a folder.
+pub fn report(path: &Path) -> Result<CommentReport, String> {
+    let mut paths = Vec::new();
+    if path.is_dir() {
+        paths.extend(
+            files::collect_diagrams(path, None)
+                .into_iter()
+                .map(|(path, _)| path),
+        );
+        paths.sort();
+    } else {
+        paths.push(path.to_path_buf());
+    }
+
+    let mut files = Vec::new();
+    let mut markers = BTreeMap::new();
+    for path in paths {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "skipping unreadable diagram");
+                continue;
+            }
+        };
+        let comments = extract(&content);
+        if comments.is_empty() {
+            continue;
+        }
+        for marker in comments.iter().filter_map(|comment| comment.marker.clone()) {
+            *markers.entry(marker).or_insert(0) += 1;
+        }
+        files.push(FileComments {
+            path: path.to_string_lossy().to_string(),
+            comments,
+        });
+    }
+    Ok(CommentReport {
+        total: files.iter().map(|file| file.comments.len()).sum(),
+        files,
+        markers,
+    })
+}
+
+// The diagram without its comments, `%%{...}%%` directives and frontmatter,
+// for source that leaves the app. These can hold internal notes, and the
+// frontmatter the editor's own settings.
+pub fn strip(content: &str) -> String {
+    let mut output = Vec::new();
+    let mut in_frontmatter = false;
+    let mut in_directive = false;
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed == "---" && (index == 0 || in_frontmatter) {
+            in_frontmatter = !in_frontmatter;
+            continue;
+        }
+        if in_frontmatter {
+            continue;
+        }
+        // Directives can span lines until their closing `}%%`.
+        if in_directive || trimmed.starts_with("%%{") {
+            in_directive = !trimmed.ends_with("}%%");
+            continue;
+        }
+        if trimmed.starts_with("%%") {
+            continue;
+        }
+        output.push(line);
+    }
+    // Leading blank lines are what the frontmatter left behind.
+    let start = output
+        .iter()
+        .position(|line| !line.trim().is_empty())
+        .unwrap_or(output.len());
+    let mut stripped = output[start..].join("\n");
+    if content.ends_with('\n') && !stripped.is_empty() {
+        stripped.push('\n');
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_comments_directives_and_frontmatter() {
+        let source = "---\ntitle: Internal plan\n---\n%%{init: {\"theme\": \"dark\"}}%%\nflowchart TD\n  %% TODO ask legal\n  A --> B\n";
+        assert_eq!(strip(source), "flowchart TD\n  A --> B\n");
+    }
+
+    #[test]
+    fn strips_directives_spanning_lines() {
+        let source = "%%{\n  init: {\"theme\": \"dark\"}\n}%%\ngraph LR\n  A --> B";
+        assert_eq!(strip(source), "graph LR\n  A --> B");
+    }
+
+    #[test]
+    fn keeps_separators_after_the_first_line() {
+        let source = "graph LR\n---\n  A --> B\n";
+        assert_eq!(strip(source), source);
+    }
+
+    #[test]
+    fn nothing_left_is_empty() {
+        assert_eq!(strip("%% only a comment\n"), "");
+        assert_eq!(strip(""), "");
+    }
+}
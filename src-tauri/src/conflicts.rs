@@ -0,0 +1,241 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{validate_content, ValidationResult};
+
+// Git's conflict markers. The base section only appears with
+// `merge.conflictStyle = diff3` (or zdiff3).
+const OURS_MARKER: &str = "<<<<<<<";
+const BASE_MARKER: &str = "|||||||";
+const SEPARATOR: &str = "=======";
+const THEIRS_MARKER: &str = ">>>>>>>";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ConflictHunk {
+    // 1-based line of the `<<<<<<<` marker.
+    pub line: usize,
+    pub ours_label: String,
+    pub theirs_label: String,
+    pub ours: String,
+    pub base: Option<String>,
+    pub theirs: String,
+}
+
+// One side of the merge as a whole file, with its validation result.
+#[derive(Debug, Serialize)]
+pub struct Version {
+    pub content: String,
+    pub validation: ValidationResult,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConflictInfo {
+    pub hunks: Vec<ConflictHunk>,
+    pub ours: Version,
+    // Only available when every hunk has a base section.
+    pub base: Option<Version>,
+    pub theirs: Version,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Resolution {
+    pub content: String,
+    pub validation: ValidationResult,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Choice {
+    Ours,
+    Theirs,
+    Base,
+    // Both sides, one after the other.
+    OursThenTheirs,
+    TheirsThenOurs,
+}
+
+enum Segment {
+    Common(String),
+    Conflict(ConflictHunk),
+}
+
+#[derive(PartialEq)]
+enum Section {
+    Common,
+    Ours,
+    Base,
+    Theirs,
+}
+
+// The label after a marker, when `line` is that marker.
+fn marker<'a>(line: &'a str, marker: &str) -> Option<&'a str> {
+    let rest = line.trim_end_matches(['\r', '\n']).strip_prefix(marker)?;
+    if rest.is_empty() || rest.starts_with(' ') {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+fn parse(content: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut common = String::new();
+    let mut hunk: Option<ConflictHunk> = None;
+    let mut section = Section::Common;
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        match (&section, hunk.as_mut()) {
+            (Section::Common, _) => {
+                if let Some(label) = marker(line, OURS_MARKER) {
+                    segments.push(Segment::Common(std::mem::take(&mut common)));
+                    hunk = Some(ConflictHunk {
+                        line: index + 1,
+                        ours_label: label.to_string(),
+                        theirs_label: String::new(),
+                        ours: String::new(),
+                        base: None,
+                        theirs: String::new(),
+                    });
+                    section = Section::Ours;
+                } else {
+                    common.push_str(line);
+                }
+            }
+            (Section::Ours, Some(current)) => {
+                if marker(line, BASE_MARKER).is_some() {
+                    current.base = Some(String::new());
+                    section = Section::Base;
+                } else if marker(line, SEPARATOR) == Some("") {
+                    section = Section::Theirs;
+                } else {
+                    current.ours.push_str(line);
+                }
+            }
+            (Section::Base, Some(current)) => {
+                if marker(line, SEPARATOR) == Some("") {
+                    section = Section::Theirs;
+                } else if let Some(base) = current.base.as_mut() {
+                    base.push_str(line);
+                }
+            }
+            (Section::Theirs, Some(current)) => {
+                if let Some(label) = marker(line, THEIRS_MARKER) {
+                    current.theirs_label = label.to_string();
+                    if let Some(done) = hunk.take() {
+                        segments.push(Segment::Conflict(done));
+                    }
+                    section = Section::Common;
+                } else {
+                    current.theirs.push_str(line);
+                }
+            }
+            (_, None) => section = Section::Common,
+        }
+    }
+
+    if let Some(open) = hunk {
+        return Err(format!(
+            "Conflict starting at line {} is not closed",
+            open.line
+        ));
+    }
+    segments.push(Segment::Common(common));
+    Ok(segments)
+}
+
+pub fn has_conflicts(content: &str) -> bool {
+    content
+        .lines()
+        .any(|line| marker(line, OURS_MARKER).is_some())
+        && parse(content).is_ok()
+}
+
+// The whole file as one side of the merge has it.
+fn side(segments: &[Segment], pick: impl Fn(&ConflictHunk) -> &str) -> Version {
+    let mut content = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Common(text) => content.push_str(text),
+            Segment::Conflict(hunk) => content.push_str(pick(hunk)),
+        }
+    }
+    Version {
+        validation: validate_content(&content),
+        content,
+    }
+}
+
+// Splits a file with conflict markers into its hunks and the complete
+// versions from each side, each one validated.
+pub fn conflicts(content: &str) -> Result<ConflictInfo, String> {
+    let segments = parse(content)?;
+    let hunks: Vec<ConflictHunk> = segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Conflict(hunk) => Some(hunk.clone()),
+            Segment::Common(_) => None,
+        })
+        .collect();
+    if hunks.is_empty() {
+        return Err("File has no merge conflicts".to_string());
+    }
+
+    let has_base = hunks.iter().all(|hunk| hunk.base.is_some());
+    Ok(ConflictInfo {
+        ours: side(&segments, |hunk| &hunk.ours),
+        base: has_base.then(|| side(&segments, |hunk| hunk.base.as_deref().unwrap_or_default())),
+        theirs: side(&segments, |hunk| &hunk.theirs),
+        hunks,
+    })
+}
+
+// Rebuilds the file with `choices[i]` applied to the i-th hunk.
+pub fn resolve(content: &str, choices: &[Choice]) -> Result<Resolution, String> {
+    let segments = parse(content)?;
+    let count = segments
+        .iter()
+        .filter(|segment| matches!(segment, Segment::Conflict(_)))
+        .count();
+    if choices.len() != count {
+        return Err(format!(
+            "Expected a choice for each of the {} conflicts, got {}",
+            count,
+            choices.len()
+        ));
+    }
+
+    let mut merged = String::new();
+    let mut choices = choices.iter();
+    for segment in &segments {
+        let hunk = match segment {
+            Segment::Common(text) => {
+                merged.push_str(text);
+                continue;
+            }
+            Segment::Conflict(hunk) => hunk,
+        };
+        match choices.next() {
+            Some(Choice::Ours) => merged.push_str(&hunk.ours),
+            Some(Choice::Theirs) => merged.push_str(&hunk.theirs),
+            Some(Choice::Base) => merged.push_str(hunk.base.as_deref().ok_or_else(|| {
+                format!(
+                    "Conflict at line {} has no base version; it was merged without diff3",
+                    hunk.line
+                )
+            })?),
+            Some(Choice::OursThenTheirs) => {
+                merged.push_str(&hunk.ours);
+                merged.push_str(&hunk.theirs);
+            }
+            Some(Choice::TheirsThenOurs) => {
+                merged.push_str(&hunk.theirs);
+                merged.push_str(&hunk.ours);
+            }
+            None => {}
+        }
+    }
+
+    Ok(Resolution {
+        validation: validate_content(&merged),
+        content: merged,
+    })
+}
@@ -0,0 +1,83 @@
+// A small RFC 4180 reader for the spreadsheet and tracker exports the
+// importers accept. Quoted fields may hold separators, doubled quotes and
+// line breaks. The separator is whichever of comma, semicolon or tab occurs
+// most in the header line, since European exports often use semicolons.
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.headers
+            .iter()
+            .position(|header| header.eq_ignore_ascii_case(name))
+    }
+}
+
+fn separator(text: &str) -> char {
+    let header = text.lines().next().unwrap_or_default();
+    [',', ';', '\t']
+        .into_iter()
+        .max_by_key(|c| header.matches(*c).count())
+        .unwrap_or(',')
+}
+
+pub fn parse(text: &str) -> Result<Table, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let separator = separator(text);
+
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            c if quoted => field.push(c),
+            c if c == separator => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("CSV has an unterminated quoted field".to_string());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records.retain(|record| record.iter().any(|field| !field.trim().is_empty()));
+    if records.is_empty() {
+        return Err("CSV file is empty".to_string());
+    }
+
+    let headers: Vec<String> = records
+        .remove(0)
+        .into_iter()
+        .map(|header| header.trim().to_string())
+        .collect();
+    let rows = records
+        .into_iter()
+        .map(|mut row| {
+            row.resize(headers.len(), String::new());
+            row
+        })
+        .collect();
+    Ok(Table { headers, rows })
+}
@@ -0,0 +1,337 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{csv, get_app_data_dir, importers, Template};
+
+// Data files larger than this are refused rather than read into memory.
+const MAX_DATA_BYTES: u64 = 16 * 1024 * 1024;
+
+// Templates are diagrams with placeholders filled from a CSV or JSON file:
+//
+//   {{name}}                        a value, looked up in the current row
+//                                   first and then in the enclosing data
+//   {{#each rows}}...{{/each}}      repeats for every item of a list
+//   {{#each rows ", "}}...{{/each}} the same, with a separator in between
+//   {{@index}}                      the position in the list, from 0
+//
+// CSV files become `{ "columns": [...], "rows": [{ column: value }] }`, and
+// a JSON list becomes `{ "rows": [...] }`.
+const BUILTIN_TEMPLATES: [(&str, &str, &str, &str); 2] = [
+    (
+        "data-pie",
+        "Pie Chart from Data",
+        "One slice per row, from `label` and `value` columns",
+        "pie showData\n{{#each rows}}\n    \"{{label}}\" : {{value}}\n{{/each}}\n",
+    ),
+    (
+        "data-bar",
+        "Bar Chart from Data",
+        "One bar per row, from `label` and `value` columns",
+        "xychart-beta\n    x-axis [{{#each rows \", \"}}\"{{label}}\"{{/each}}]\n    bar [{{#each rows \", \"}}{{value}}{{/each}}]\n",
+    ),
+];
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Rendered {
+    pub content: String,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Value(String),
+    Each {
+        path: String,
+        separator: String,
+        body: Vec<Node>,
+    },
+}
+
+pub fn templates_dir() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("data-templates"))
+}
+
+fn builtin_templates() -> Vec<Template> {
+    BUILTIN_TEMPLATES
+        .iter()
+        .map(|(id, name, description, content)| Template {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            content: content.to_string(),
+            category: "Data".to_string(),
+        })
+        .collect()
+}
+
+// User templates are `.mmd` files in the data templates folder, named by
+// their id. A leading `%%` comment line becomes the description.
+fn user_templates() -> Vec<Template> {
+    let Ok(entries) = templates_dir().and_then(|dir| {
+        fs::read_dir(dir).map_err(|e| format!("Failed to read data templates: {}", e))
+    }) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "mmd"))
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let content = fs::read_to_string(&path)
+                .map_err(|e| {
+                    tracing::warn!(path = %path.display(), error = %e, "skipping data template");
+                })
+                .ok()?;
+            let id = path.file_stem()?.to_string_lossy().to_string();
+            let description = content
+                .lines()
+                .next()
+                .and_then(|line| line.trim().strip_prefix("%%"))
+                .map(|comment| comment.trim().to_string())
+                .unwrap_or_default();
+            Some(Template {
+                name: id.clone(),
+                id,
+                description,
+                content,
+                category: "Data".to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn list() -> Vec<Template> {
+    let user = user_templates();
+    let mut templates: Vec<Template> = builtin_templates()
+        .into_iter()
+        .filter(|builtin| !user.iter().any(|template| template.id == builtin.id))
+        .collect();
+    templates.extend(user);
+    templates
+}
+
+pub fn get(id: &str) -> Result<Template, String> {
+    list()
+        .into_iter()
+        .find(|template| template.id == id)
+        .ok_or_else(|| format!("Unknown data template: {}", id))
+}
+
+pub fn load_data(path: &Path) -> Result<Value, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("Failed to read data file: {}", e))?;
+    if metadata.len() > MAX_DATA_BYTES {
+        return Err(format!("{} is too large", path.display()));
+    }
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read data file: {}", e))?;
+
+    let is_json = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+    if !is_json {
+        let table = csv::parse(&text)?;
+        let rows: Vec<Value> = table
+            .rows
+            .iter()
+            .map(|row| {
+                let fields: Map<String, Value> = table
+                    .headers
+                    .iter()
+                    .cloned()
+                    .zip(
+                        row.iter()
+                            .map(|field| Value::String(field.trim().to_string())),
+                    )
+                    .collect();
+                Value::Object(fields)
+            })
+            .collect();
+        return Ok(serde_json::json!({ "columns": table.headers, "rows": rows }));
+    }
+
+    match serde_json::from_str(&text).map_err(|e| format!("Invalid JSON data: {}", e))? {
+        Value::Array(rows) => Ok(serde_json::json!({ "rows": rows })),
+        value @ Value::Object(_) => Ok(value),
+        _ => Err("JSON data must be an object or a list".to_string()),
+    }
+}
+
+// Renders the template `id` with the data file at `data_path`.
+pub fn render_file(id: &str, data_path: &Path) -> Result<Rendered, String> {
+    let template = get(id)?;
+    let data = load_data(data_path)?;
+    render(&template.content, &data)
+}
+
+pub fn render(template: &str, data: &Value) -> Result<Rendered, String> {
+    let mut position = 0;
+    let nodes = parse(template, &mut position, None)?;
+
+    let mut rendered = Rendered {
+        content: String::new(),
+        warnings: Vec::new(),
+    };
+    emit(&nodes, &mut vec![(data, None)], &mut rendered);
+    Ok(rendered)
+}
+
+// A block tag alone on its line takes the whole line with it, so templates
+// can put `{{#each}}` and `{{/each}}` on lines of their own.
+fn standalone(template: &str, start: usize, end: usize) -> Option<(usize, usize)> {
+    let line_start = template[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    if !template[line_start..start].trim().is_empty() {
+        return None;
+    }
+    let rest = &template[end..];
+    let line_end = rest
+        .find('\n')
+        .map(|i| end + i + 1)
+        .unwrap_or(template.len());
+    if !template[end..line_end].trim().is_empty() {
+        return None;
+    }
+    Some((line_start, line_end))
+}
+
+// Parses from `position` until the end of the template, or the `{{/each}}`
+// closing `block`.
+fn parse(template: &str, position: &mut usize, block: Option<&str>) -> Result<Vec<Node>, String> {
+    let mut nodes = Vec::new();
+
+    while let Some(offset) = template[*position..].find("{{") {
+        let start = *position + offset;
+        let close = template[start..]
+            .find("}}")
+            .ok_or("Template has an unclosed {{")?;
+        let end = start + close + 2;
+        let tag = template[start + 2..end - 2].trim();
+
+        let is_block = tag.starts_with("#each") || tag == "/each";
+        let (text_end, next) = match is_block.then(|| standalone(template, start, end)).flatten() {
+            Some(span) => span,
+            None => (start, end),
+        };
+        if text_end > *position {
+            nodes.push(Node::Text(template[*position..text_end].to_string()));
+        }
+        *position = next;
+
+        if tag == "/each" {
+            return match block {
+                Some(_) => Ok(nodes),
+                None => Err("Template has {{/each}} without {{#each}}".to_string()),
+            };
+        }
+        if let Some(arguments) = tag.strip_prefix("#each") {
+            let arguments = arguments.trim();
+            let (path, separator) = match arguments.split_once(char::is_whitespace) {
+                Some((path, separator)) => (path, separator.trim()),
+                None => (arguments, ""),
+            };
+            if path.is_empty() {
+                return Err("{{#each}} needs a list to repeat".to_string());
+            }
+            let separator = separator
+                .strip_prefix('"')
+                .and_then(|separator| separator.strip_suffix('"'))
+                .unwrap_or(separator)
+                .to_string();
+            let body = parse(template, position, Some(path))?;
+            nodes.push(Node::Each {
+                path: path.to_string(),
+                separator,
+                body,
+            });
+            continue;
+        }
+        nodes.push(Node::Value(tag.to_string()));
+    }
+
+    if let Some(block) = block {
+        return Err(format!("{{{{#each {}}}}} is never closed", block));
+    }
+    if *position < template.len() {
+        nodes.push(Node::Text(template[*position..].to_string()));
+        *position = template.len();
+    }
+    Ok(nodes)
+}
+
+// Each scope is a value with its position when it is an item of a list.
+type Scope<'a> = (&'a Value, Option<usize>);
+
+fn lookup<'a>(scopes: &[Scope<'a>], path: &str) -> Option<&'a Value> {
+    if path == "." || path == "this" {
+        return scopes.last().map(|(value, _)| *value);
+    }
+    scopes.iter().rev().find_map(|(value, _)| {
+        path.split('.').try_fold(*value, |value, key| match value {
+            Value::Object(fields) => fields.get(key),
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+    })
+}
+
+// Values go into diagram text, so line breaks, quotes, brackets and what
+// Mermaid reads as comments or statement ends are kept from breaking the
+// statement they are in.
+fn text_of(value: &Value) -> String {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    };
+    importers::escape_text(&text, &['"', '[', ']', '(', ')', '{', '}'])
+}
+
+fn warn(rendered: &mut Rendered, warning: String) {
+    if !rendered.warnings.contains(&warning) {
+        rendered.warnings.push(warning);
+    }
+}
+
+fn emit<'a>(nodes: &[Node], scopes: &mut Vec<Scope<'a>>, rendered: &mut Rendered) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => rendered.content.push_str(text),
+            Node::Value(path) if path == "@index" => {
+                match scopes.iter().rev().find_map(|(_, index)| *index) {
+                    Some(index) => rendered.content.push_str(&index.to_string()),
+                    None => warn(rendered, "{{@index}} used outside {{#each}}".to_string()),
+                }
+            }
+            Node::Value(path) => match lookup(scopes, path) {
+                Some(value) => rendered.content.push_str(&text_of(value)),
+                None => warn(rendered, format!("No value for {{{{{}}}}}", path)),
+            },
+            Node::Each {
+                path,
+                separator,
+                body,
+            } => {
+                let Some(Value::Array(items)) = lookup(scopes, path) else {
+                    warn(rendered, format!("{} is not a list", path));
+                    continue;
+                };
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        rendered.content.push_str(separator);
+                    }
+                    scopes.push((item, Some(index)));
+                    emit(body, scopes, rendered);
+                    scopes.pop();
+                }
+            }
+        }
+    }
+}
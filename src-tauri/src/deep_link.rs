@@ -0,0 +1,45 @@
+use tauri::Url;
+
+pub const SCHEME: &str = "flowcraft";
+
+#[derive(Debug, Clone)]
+pub struct OpenRequest {
+    pub path: String,
+    pub line: Option<u32>,
+}
+
+// Accepts flowcraft://open?path=<file>&line=<n>; line is 1-based and optional.
+pub fn parse_open_url(url: &Url) -> Result<OpenRequest, String> {
+    if url.scheme() != SCHEME {
+        return Err(format!("Unsupported URL scheme: {}", url.scheme()));
+    }
+
+    let action = url
+        .host_str()
+        .unwrap_or_else(|| url.path().trim_matches('/'));
+    if action != "open" {
+        return Err(format!("Unsupported deep link action: {}", action));
+    }
+
+    let mut path = None;
+    let mut line = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "path" => path = Some(value.into_owned()),
+            "line" => {
+                line = Some(
+                    value
+                        .parse::<u32>()
+                        .map_err(|_| format!("Invalid line number: {}", value))?,
+                )
+            }
+            _ => {}
+        }
+    }
+
+    let path = path
+        .filter(|p| !p.is_empty())
+        .ok_or("Deep link is missing the path parameter")?;
+
+    Ok(OpenRequest { path, line })
+}
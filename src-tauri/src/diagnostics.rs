@@ -0,0 +1,166 @@
+use chrono::Utc;
+use serde_json::json;
+use std::fs::{self, File};
+use std::io::Write;
+use std::panic;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::{get_app_data_dir, inkscape, logging, render};
+
+const MAX_CRASH_REPORTS: usize = 5;
+
+pub fn crashes_dir() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("crashes"))
+}
+
+// Writes a crash report next to the logs before the default hook prints the
+// panic, so the next diagnostic bundle picks it up.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+            .unwrap_or_default();
+        let thread = std::thread::current()
+            .name()
+            .unwrap_or("unnamed")
+            .to_string();
+
+        tracing::error!(%message, %location, %thread, "panic");
+
+        let report = format!(
+            "Flowcraft Studio {} crashed at {}\nOS: {} {}\nThread: {}\nLocation: {}\nMessage: {}\n\nBacktrace:\n{}\n",
+            env!("CARGO_PKG_VERSION"),
+            Utc::now().to_rfc3339(),
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            thread,
+            location,
+            message,
+            std::backtrace::Backtrace::force_capture()
+        );
+        if let Ok(dir) = crashes_dir() {
+            let _ = fs::create_dir_all(&dir);
+            let name = format!("crash-{}.txt", Utc::now().format("%Y%m%d-%H%M%S"));
+            let _ = fs::write(dir.join(name), report);
+        }
+
+        default_hook(info);
+    }));
+}
+
+// Replaces the home directory so paths do not reveal the user name.
+fn sanitize(text: &str) -> String {
+    match dirs::home_dir() {
+        Some(home) => {
+            let home = home.to_string_lossy();
+            if home.len() > 1 {
+                // JSON escapes the backslashes of Windows paths.
+                text.replace(&home.replace('\\', "\\\\"), "~")
+                    .replace(home.as_ref(), "~")
+            } else {
+                text.to_string()
+            }
+        }
+        None => text.to_string(),
+    }
+}
+
+fn sorted_files(dir: &Path, prefix: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.is_file()
+                        && path
+                            .file_name()
+                            .is_some_and(|name| name.to_string_lossy().starts_with(prefix))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+fn add_text(
+    zip: &mut ZipWriter<File>,
+    name: &str,
+    text: &str,
+    options: SimpleFileOptions,
+) -> Result<(), String> {
+    zip.start_file(name, options)
+        .map_err(|e| format!("Failed to add {} to bundle: {}", name, e))?;
+    zip.write_all(text.as_bytes())
+        .map_err(|e| format!("Failed to add {} to bundle: {}", name, e))
+}
+
+// Zips system info, sanitized settings, logs and recent crash reports.
+// `settings` is the serialized app state; API keys live in the keychain and
+// never appear there.
+pub fn create_bundle(output: &Path, settings: &serde_json::Value) -> Result<(), String> {
+    let file = File::create(output).map_err(|e| format!("Failed to create bundle: {}", e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let system = json!({
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "family": std::env::consts::FAMILY,
+        "mermaid_cli_available": render::is_available(),
+        "inkscape_available": inkscape::is_available(),
+        "created_at": Utc::now(),
+    });
+    add_text(
+        &mut zip,
+        "system.json",
+        &serde_json::to_string_pretty(&system).unwrap_or_default(),
+        options,
+    )?;
+
+    let settings = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    add_text(&mut zip, "settings.json", &sanitize(&settings), options)?;
+
+    if let Ok(dir) = logging::logs_dir() {
+        for path in sorted_files(&dir, "") {
+            if let Ok(content) = fs::read_to_string(&path) {
+                let name = format!(
+                    "logs/{}",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                );
+                add_text(&mut zip, &name, &sanitize(&content), options)?;
+            }
+        }
+    }
+
+    if let Ok(dir) = crashes_dir() {
+        let reports = sorted_files(&dir, "crash-");
+        for path in reports.iter().rev().take(MAX_CRASH_REPORTS) {
+            if let Ok(content) = fs::read_to_string(path) {
+                let name = format!(
+                    "crashes/{}",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                );
+                add_text(&mut zip, &name, &sanitize(&content), options)?;
+            }
+        }
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to write bundle: {}", e))?;
+    Ok(())
+}
@@ -0,0 +1,459 @@
+use regex::Regex;
+use std::collections::HashSet;
+
+use crate::{architecture, c4, icons, sankey};
+
+// Checks the statements of a diagram of one kind, adding to the errors and
+// warnings. Gets the detected id, for validators shared by several kinds.
+type Validator = fn(&str, &str, &mut Vec<String>, &mut Vec<String>);
+
+pub struct DiagramKind {
+    // The lowercased start of the header line, as `detect_diagram_type`
+    // reports it; `xychart` covers both `xychart-beta` and `xychart`.
+    pub id: &'static str,
+    pub validate: Option<Validator>,
+}
+
+const fn kind(id: &'static str) -> DiagramKind {
+    DiagramKind { id, validate: None }
+}
+
+const fn checked(id: &'static str, validate: Validator) -> DiagramKind {
+    DiagramKind {
+        id,
+        validate: Some(validate),
+    }
+}
+
+// Every diagram type the editor knows. Ids are matched as prefixes in
+// order, so none may be the start of a later one.
+pub const REGISTRY: [DiagramKind; 25] = [
+    checked("graph", icons::validate_shapes),
+    checked("flowchart", icons::validate_shapes),
+    kind("sequencediagram"),
+    kind("classdiagram"),
+    kind("statediagram"),
+    kind("erdiagram"),
+    kind("journey"),
+    kind("gantt"),
+    kind("pie"),
+    kind("gitgraph"),
+    kind("mindmap"),
+    kind("timeline"),
+    kind("zenuml"),
+    checked("sankey", |content, _, errors, warnings| {
+        sankey::validate(content, errors, warnings)
+    }),
+    checked("c4context", c4::validate),
+    checked("c4container", c4::validate),
+    checked("c4component", c4::validate),
+    checked("c4dynamic", c4::validate),
+    checked("c4deployment", c4::validate),
+    checked("xychart", validate_xychart),
+    checked("quadrantchart", validate_quadrant),
+    checked("block", validate_block),
+    checked("packet", validate_packet),
+    checked("requirementdiagram", validate_requirement),
+    checked("architecture", architecture::validate),
+];
+
+// The registered kind a header line or type name starts with, as a whole
+// word: `graph TD` and `xychart-beta` match, `graphviz` does not.
+pub fn find(header: &str) -> Option<&'static DiagramKind> {
+    let header = header.trim().to_lowercase();
+    REGISTRY.iter().find(|kind| {
+        header.strip_prefix(kind.id).is_some_and(|rest| {
+            !rest
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_')
+        })
+    })
+}
+
+// The statement lines after the header, numbered from the top of the file,
+// without frontmatter, comments, directives and blank lines.
+pub fn statements(content: &str) -> Vec<(usize, &str)> {
+    let mut lines = content.lines().enumerate().peekable();
+    if lines.peek().map(|(_, line)| line.trim()) == Some("---") {
+        lines.next();
+        for (_, line) in lines.by_ref() {
+            if line.trim() == "---" {
+                break;
+            }
+        }
+    }
+    lines
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with("%%"))
+        .skip(1)
+        .collect()
+}
+
+// The numbers in a `[1, 2.5, -3]` list, or the first item that is not one.
+fn numbers(list: &str) -> Result<Vec<f64>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| item.parse::<f64>().map_err(|_| item.to_string()))
+        .collect()
+}
+
+fn validate_xychart(content: &str, _: &str, errors: &mut Vec<String>, warnings: &mut Vec<String>) {
+    let axis = Regex::new(r"^([xy])-axis\b(.*)$").unwrap();
+    let series = Regex::new(r#"^(line|bar)\b\s*(?:"[^"]*"\s*)?\[(.*)\]$"#).unwrap();
+    let range = Regex::new(r"-?[\d.]+\s*-->\s*-?[\d.]+$").unwrap();
+
+    let mut categories = None;
+    let mut lengths = Vec::new();
+    for (number, line) in statements(content) {
+        if line.starts_with("title") {
+            continue;
+        }
+        // An axis has an optional title followed by `[categories]` or a
+        // `min --> max` range.
+        if let Some(caps) = axis.captures(line) {
+            let rest = caps[2].trim();
+            let list = rest
+                .strip_suffix(']')
+                .and_then(|rest| rest.rfind('[').map(|open| &rest[open + 1..]));
+            match list {
+                Some(list) if &caps[1] == "x" => {
+                    categories = Some(list.split(',').filter(|c| !c.trim().is_empty()).count());
+                }
+                Some(_) => errors.push(format!(
+                    "Line {}: the y-axis takes a range such as `0 --> 100`, not categories",
+                    number
+                )),
+                None if rest.contains("-->") && !range.is_match(rest) => errors.push(format!(
+                    "Line {}: expected a range such as `0 --> 100`",
+                    number
+                )),
+                None => {}
+            }
+            continue;
+        }
+        if let Some(caps) = series.captures(line) {
+            match numbers(&caps[2]) {
+                Ok(values) => lengths.push((number, values.len())),
+                Err(item) => errors.push(format!("Line {}: `{}` is not a number", number, item)),
+            }
+            continue;
+        }
+        errors.push(format!(
+            "Line {}: expected `title`, `x-axis`, `y-axis`, `line [...]` or `bar [...]`",
+            number
+        ));
+    }
+
+    if lengths.is_empty() {
+        warnings.push("The chart has no `line` or `bar` data".to_string());
+    }
+    if let Some(categories) = categories {
+        for (number, length) in lengths {
+            if length != categories {
+                warnings.push(format!(
+                    "Line {}: {} values for {} x-axis categories",
+                    number, length, categories
+                ));
+            }
+        }
+    }
+}
+
+fn validate_quadrant(content: &str, _: &str, errors: &mut Vec<String>, warnings: &mut Vec<String>) {
+    let point = Regex::new(r"^(.+?)(?::::\w+)?\s*:\s*\[([^\]]*)\]\s*(.*)$").unwrap();
+    let keyword = Regex::new(r"^(title|x-axis|y-axis|quadrant-[1-4]|classDef)\b").unwrap();
+
+    let mut names = HashSet::new();
+    for (number, line) in statements(content) {
+        if keyword.is_match(line) {
+            continue;
+        }
+        let Some(caps) = point.captures(line) else {
+            errors.push(format!(
+                "Line {}: expected a point such as `Name: [0.3, 0.6]`",
+                number
+            ));
+            continue;
+        };
+        match numbers(&caps[2]).as_deref() {
+            Ok([x, y]) => {
+                if !(0.0..=1.0).contains(x) || !(0.0..=1.0).contains(y) {
+                    errors.push(format!(
+                        "Line {}: point coordinates must be between 0 and 1",
+                        number
+                    ));
+                }
+            }
+            Ok(_) => errors.push(format!(
+                "Line {}: a point needs exactly two coordinates",
+                number
+            )),
+            Err(item) => errors.push(format!("Line {}: `{}` is not a number", number, item)),
+        }
+        if !names.insert(caps[1].trim().to_string()) {
+            warnings.push(format!(
+                "Line {}: {} is plotted more than once",
+                number,
+                caps[1].trim()
+            ));
+        }
+    }
+}
+
+fn validate_block(content: &str, _: &str, errors: &mut Vec<String>, _: &mut Vec<String>) {
+    let columns = Regex::new(r"^columns\s+(.*)$").unwrap();
+    let mut open = Vec::new();
+    for (number, line) in statements(content) {
+        if let Some(caps) = columns.captures(line) {
+            let value = caps[1].trim();
+            if value != "auto" && !value.parse::<u32>().is_ok_and(|n| n > 0) {
+                errors.push(format!(
+                    "Line {}: columns must be a positive number or `auto`",
+                    number
+                ));
+            }
+        } else if line == "block" || line.starts_with("block:") {
+            open.push(number);
+        } else if line == "end" && open.pop().is_none() {
+            errors.push(format!("Line {}: `end` without a `block`", number));
+        }
+
+        let mut depth = 0i32;
+        let mut quoted = false;
+        let mut previous = None;
+        for c in line.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                '[' | '(' | '{' if !quoted => depth += 1,
+                // `id>"label"]` is the asymmetric shape; in arrows like
+                // `-->` the `>` follows a dash instead.
+                '>' if !quoted
+                    && previous.is_some_and(|p: char| p.is_alphanumeric() || p == '_') =>
+                {
+                    depth += 1
+                }
+                ']' | ')' | '}' if !quoted => depth -= 1,
+                _ => {}
+            }
+            previous = Some(c);
+        }
+        if quoted || depth != 0 {
+            errors.push(format!("Line {}: unbalanced brackets or quotes", number));
+        }
+    }
+    for number in open {
+        errors.push(format!(
+            "Line {}: `block` is never closed with `end`",
+            number
+        ));
+    }
+}
+
+fn validate_packet(content: &str, _: &str, errors: &mut Vec<String>, warnings: &mut Vec<String>) {
+    let field = Regex::new(r#"^(?:(\d+)(?:\s*-\s*(\d+))?|\+(\d+))\s*:\s*"[^"]*"$"#).unwrap();
+
+    // The next free bit, for checking that fields follow each other.
+    let mut next = 0u64;
+    for (number, line) in statements(content) {
+        if line.starts_with("title") {
+            continue;
+        }
+        let Some(caps) = field.captures(line) else {
+            errors.push(format!(
+                "Line {}: expected a field such as `0-15: \"Source Port\"`",
+                number
+            ));
+            continue;
+        };
+        // Bit numbers come from the user, so anything out of range is an
+        // error rather than an overflow.
+        let too_large = || format!("Line {}: the bit number is too large", number);
+        // `+8` is a field of eight bits starting at the next free bit.
+        let (start, end) = match (caps.get(1), caps.get(2), caps.get(3)) {
+            (_, _, Some(bits)) => {
+                let Ok(bits) = bits.as_str().parse::<u64>() else {
+                    errors.push(too_large());
+                    continue;
+                };
+                if bits == 0 {
+                    errors.push(format!("Line {}: a field needs at least one bit", number));
+                    continue;
+                }
+                let Some(end) = next.checked_add(bits - 1) else {
+                    errors.push(too_large());
+                    continue;
+                };
+                (next, end)
+            }
+            (Some(start), end, _) => {
+                let start = start.as_str().parse::<u64>();
+                let end = end.map(|end| end.as_str().parse::<u64>());
+                match (start, end) {
+                    (Ok(start), None) => (start, start),
+                    (Ok(start), Some(Ok(end))) => (start, end),
+                    _ => {
+                        errors.push(too_large());
+                        continue;
+                    }
+                }
+            }
+            _ => continue,
+        };
+        if end < start {
+            errors.push(format!("Line {}: field ends before it starts", number));
+            continue;
+        }
+        let Some(after) = end.checked_add(1) else {
+            errors.push(too_large());
+            continue;
+        };
+        if start < next {
+            errors.push(format!(
+                "Line {}: bit {} is already part of an earlier field",
+                number, start
+            ));
+        } else if start > next {
+            warnings.push(format!(
+                "Line {}: bits {} to {} are not part of any field",
+                number,
+                next,
+                start - 1
+            ));
+        }
+        next = next.max(after);
+    }
+}
+
+const REQUIREMENT_TYPES: [&str; 6] = [
+    "requirement",
+    "functionalRequirement",
+    "interfaceRequirement",
+    "performanceRequirement",
+    "physicalRequirement",
+    "designConstraint",
+];
+const RELATIONSHIPS: [&str; 7] = [
+    "contains",
+    "copies",
+    "derives",
+    "satisfies",
+    "verifies",
+    "refines",
+    "traces",
+];
+
+fn validate_requirement(
+    content: &str,
+    _: &str,
+    errors: &mut Vec<String>,
+    warnings: &mut Vec<String>,
+) {
+    let block = Regex::new(r#"^(\w+)\s+("[^"]*"|[^\s{]+)\s*\{$"#).unwrap();
+    let property = Regex::new(r"^(\w+)\s*:\s*(.*)$").unwrap();
+    let relation =
+        Regex::new(r#"^("[^"]*"|\S+)\s*(?:-\s*(\w+)\s*->|<-\s*(\w+)\s*-)\s*("[^"]*"|\S+)$"#)
+            .unwrap();
+
+    let mut names = HashSet::new();
+    let mut relations = Vec::new();
+    // The type of the block being read, if any.
+    let mut current: Option<String> = None;
+    for (number, line) in statements(content) {
+        if let Some(block_type) = current.clone() {
+            if line == "}" {
+                current = None;
+                continue;
+            }
+            let Some(caps) = property.captures(line) else {
+                errors.push(format!("Line {}: expected `name: value`", number));
+                continue;
+            };
+            let (key, value) = (&caps[1], caps[2].trim());
+            let allowed: &[&str] = if block_type == "element" {
+                &["type", "docref"]
+            } else {
+                &["id", "text", "risk", "verifymethod"]
+            };
+            if !allowed.iter().any(|name| name.eq_ignore_ascii_case(key)) {
+                errors.push(format!(
+                    "Line {}: {} is not a property of {}",
+                    number, key, block_type
+                ));
+            } else if key.eq_ignore_ascii_case("risk")
+                && !["low", "medium", "high"].contains(&value.to_lowercase().as_str())
+            {
+                errors.push(format!("Line {}: risk must be low, medium or high", number));
+            } else if key.eq_ignore_ascii_case("verifymethod")
+                && !["analysis", "inspection", "test", "demonstration"]
+                    .contains(&value.to_lowercase().as_str())
+            {
+                errors.push(format!(
+                    "Line {}: verifymethod must be analysis, inspection, test or demonstration",
+                    number
+                ));
+            }
+            continue;
+        }
+
+        if let Some(caps) = block.captures(line) {
+            let block_type = &caps[1];
+            if block_type != "element" && !REQUIREMENT_TYPES.contains(&block_type) {
+                errors.push(format!(
+                    "Line {}: unknown requirement type `{}`",
+                    number, block_type
+                ));
+            }
+            if !names.insert(caps[2].trim_matches('"').to_string()) {
+                errors.push(format!(
+                    "Line {}: {} is already defined",
+                    number,
+                    caps[2].trim_matches('"')
+                ));
+            }
+            current = Some(block_type.to_string());
+            continue;
+        }
+        if let Some(caps) = relation.captures(line) {
+            let kind = caps
+                .get(2)
+                .or_else(|| caps.get(3))
+                .map_or("", |m| m.as_str());
+            if !RELATIONSHIPS.contains(&kind) {
+                errors.push(format!("Line {}: unknown relationship `{}`", number, kind));
+            }
+            relations.push((
+                number,
+                caps[1].trim_matches('"').to_string(),
+                caps[4].trim_matches('"').to_string(),
+            ));
+            continue;
+        }
+        if line.starts_with("direction ")
+            || line.starts_with("style ")
+            || line.starts_with("classDef ")
+            || line.starts_with("class ")
+        {
+            continue;
+        }
+        errors.push(format!(
+            "Line {}: expected a requirement, an element or a relationship",
+            number
+        ));
+    }
+
+    if current.is_some() {
+        errors.push("The last block is missing its closing `}`".to_string());
+    }
+    for (number, from, to) in relations {
+        for name in [&from, &to] {
+            if !names.contains(name) {
+                warnings.push(format!(
+                    "Line {}: relationship refers to undefined {}",
+                    number, name
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,437 @@
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use crate::encryption;
+use crate::model::{self, Edge, Node};
+
+// Unchanged lines shown around each hunk of the unified diff.
+const CONTEXT_LINES: usize = 3;
+// Above this many cells the line table would take too much memory, and the
+// differing middle is reported as replaced wholesale instead.
+const MAX_TABLE_CELLS: usize = 16_000_000;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Equal,
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct NumberedLine {
+    // 1-based.
+    pub number: usize,
+    pub text: String,
+}
+
+// One row of a side-by-side view: `left` is the old file, `right` the new.
+#[derive(Debug, Serialize, Clone)]
+pub struct SideBySideRow {
+    pub kind: ChangeKind,
+    pub left: Option<NumberedLine>,
+    pub right: Option<NumberedLine>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RelabeledNode {
+    pub id: String,
+    pub old_label: Option<String>,
+    pub new_label: Option<String>,
+}
+
+// What changed in the diagram itself, ignoring layout and formatting.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SemanticDiff {
+    pub old_type: Option<String>,
+    pub new_type: Option<String>,
+    pub nodes_added: Vec<Node>,
+    pub nodes_removed: Vec<Node>,
+    pub nodes_relabeled: Vec<RelabeledNode>,
+    // Edges are matched on their ends and label; `line` is where they are in
+    // the file they come from.
+    pub edges_added: Vec<Edge>,
+    pub edges_removed: Vec<Edge>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileDiff {
+    pub old_path: String,
+    pub new_path: String,
+    pub identical: bool,
+    pub unified: String,
+    pub side_by_side: Vec<SideBySideRow>,
+    pub semantic: SemanticDiff,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Equal(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+// Longest-common-subsequence line matching, after trimming the common
+// prefix and suffix, which is usually most of a diagram.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut ops: Vec<Op> = (0..prefix).map(|index| Op::Equal(index, index)).collect();
+
+    let (n, m) = (old_middle.len(), new_middle.len());
+    if n * m > MAX_TABLE_CELLS {
+        ops.extend((0..n).map(|index| Op::Removed(prefix + index)));
+        ops.extend((0..m).map(|index| Op::Added(prefix + index)));
+    } else {
+        // lengths[i][j]: common lines between old_middle[i..] and new_middle[j..].
+        let mut lengths = vec![0u32; (n + 1) * (m + 1)];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lengths[i * (m + 1) + j] = if old_middle[i] == new_middle[j] {
+                    lengths[(i + 1) * (m + 1) + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * (m + 1) + j].max(lengths[i * (m + 1) + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && old_middle[i] == new_middle[j] {
+                ops.push(Op::Equal(prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if i < n
+                && (j == m || lengths[(i + 1) * (m + 1) + j] >= lengths[i * (m + 1) + j + 1])
+            {
+                // Removals go first, as in GNU diff.
+                ops.push(Op::Removed(prefix + i));
+                i += 1;
+            } else {
+                ops.push(Op::Added(prefix + j));
+                j += 1;
+            }
+        }
+    }
+
+    let old_start = old.len() - suffix;
+    let new_start = new.len() - suffix;
+    ops.extend((0..suffix).map(|index| Op::Equal(old_start + index, new_start + index)));
+    ops
+}
+
+fn range(start: usize, count: usize) -> String {
+    // Empty ranges point at the line before them, as in GNU diff.
+    let start = if count == 0 { start } else { start + 1 };
+    if count == 1 {
+        start.to_string()
+    } else {
+        format!("{},{}", start, count)
+    }
+}
+
+fn unified(ops: &[Op], old: &[&str], new: &[&str], old_name: &str, new_name: &str) -> String {
+    let changed: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, Op::Equal(..)))
+        .map(|(index, _)| index)
+        .collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    // Group changes whose context would touch into one hunk.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for index in changed {
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + CONTEXT_LINES + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for (start, end) in hunks {
+        let hunk = &ops[start..end];
+        // Where the hunk starts in each file: the first line it covers there,
+        // or the position of the lines it inserts or removes.
+        let old_start = ops[..start]
+            .iter()
+            .filter(|op| !matches!(op, Op::Added(_)))
+            .count();
+        let new_start = ops[..start]
+            .iter()
+            .filter(|op| !matches!(op, Op::Removed(_)))
+            .count();
+        let old_count = hunk.iter().filter(|op| !matches!(op, Op::Added(_))).count();
+        let new_count = hunk
+            .iter()
+            .filter(|op| !matches!(op, Op::Removed(_)))
+            .count();
+
+        output.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_count),
+            range(new_start, new_count)
+        ));
+        for op in hunk {
+            let line = match *op {
+                Op::Equal(index, _) => format!(" {}", old[index]),
+                Op::Removed(index) => format!("-{}", old[index]),
+                Op::Added(index) => format!("+{}", new[index]),
+            };
+            output.push_str(&line);
+            output.push('\n');
+        }
+    }
+    output
+}
+
+fn numbered(lines: &[&str], index: usize) -> NumberedLine {
+    NumberedLine {
+        number: index + 1,
+        text: lines[index].to_string(),
+    }
+}
+
+// Pairs each run of removed lines with the added lines that follow it, so
+// edited lines sit next to each other.
+fn side_by_side(ops: &[Op], old: &[&str], new: &[&str]) -> Vec<SideBySideRow> {
+    let mut rows = Vec::new();
+    let mut index = 0;
+    while index < ops.len() {
+        if let Op::Equal(old_index, new_index) = ops[index] {
+            rows.push(SideBySideRow {
+                kind: ChangeKind::Equal,
+                left: Some(numbered(old, old_index)),
+                right: Some(numbered(new, new_index)),
+            });
+            index += 1;
+            continue;
+        }
+
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        while let Some(op) = ops.get(index) {
+            match *op {
+                Op::Removed(old_index) => removed.push(old_index),
+                Op::Added(new_index) => added.push(new_index),
+                Op::Equal(..) => break,
+            }
+            index += 1;
+        }
+        for row in 0..removed.len().max(added.len()) {
+            let left = removed.get(row).map(|&i| numbered(old, i));
+            let right = added.get(row).map(|&i| numbered(new, i));
+            let kind = match (&left, &right) {
+                (Some(_), Some(_)) => ChangeKind::Changed,
+                (Some(_), None) => ChangeKind::Removed,
+                _ => ChangeKind::Added,
+            };
+            rows.push(SideBySideRow { kind, left, right });
+        }
+    }
+    rows
+}
+
+fn edge_key(edge: &Edge) -> (&str, &str, Option<&str>) {
+    (&edge.from, &edge.to, edge.label.as_deref())
+}
+
+// Edges in `edges` with no match left in `others`; repeated edges count
+// separately.
+fn unmatched_edges(edges: &[Edge], others: &[Edge]) -> Vec<Edge> {
+    let mut remaining: Vec<&Edge> = others.iter().collect();
+    edges
+        .iter()
+        .filter(|edge| {
+            match remaining
+                .iter()
+                .position(|other| edge_key(other) == edge_key(edge))
+            {
+                Some(position) => {
+                    remaining.swap_remove(position);
+                    false
+                }
+                None => true,
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+pub fn semantic_diff(old: &str, new: &str) -> SemanticDiff {
+    let old = model::parse_cached(old);
+    let new = model::parse_cached(new);
+
+    let mut diff = SemanticDiff {
+        old_type: old.model.diagram_type.clone(),
+        new_type: new.model.diagram_type.clone(),
+        ..SemanticDiff::default()
+    };
+    for node in &new.nodes {
+        match old.nodes.iter().find(|old_node| old_node.id == node.id) {
+            None => diff.nodes_added.push(node.clone()),
+            Some(old_node) if old_node.label != node.label => {
+                diff.nodes_relabeled.push(RelabeledNode {
+                    id: node.id.clone(),
+                    old_label: old_node.label.clone(),
+                    new_label: node.label.clone(),
+                });
+            }
+            Some(_) => {}
+        }
+    }
+    diff.nodes_removed = old
+        .nodes
+        .iter()
+        .filter(|node| !new.nodes.iter().any(|new_node| new_node.id == node.id))
+        .cloned()
+        .collect();
+    diff.edges_added = unmatched_edges(&new.edges, &old.edges);
+    diff.edges_removed = unmatched_edges(&old.edges, &new.edges);
+    diff
+}
+
+pub fn diff(old: &str, new: &str, old_name: &str, new_name: &str) -> FileDiff {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+
+    FileDiff {
+        old_path: old_name.to_string(),
+        new_path: new_name.to_string(),
+        identical: ops.iter().all(|op| matches!(op, Op::Equal(..))),
+        unified: unified(&ops, &old_lines, &new_lines, old_name, new_name),
+        side_by_side: side_by_side(&ops, &old_lines, &new_lines),
+        semantic: semantic_diff(old, new),
+    }
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if encryption::is_encrypted(&content) {
+        return Err(format!(
+            "{} is encrypted and cannot be compared",
+            path.display()
+        ));
+    }
+    Ok(content)
+}
+
+pub fn diff_files(old: &Path, new: &Path) -> Result<FileDiff, String> {
+    let old_content = read(old)?;
+    let new_content = read(new)?;
+    Ok(diff(
+        &old_content,
+        &new_content,
+        &old.to_string_lossy(),
+        &new.to_string_lossy(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Rebuilds both files from the ops, checking each line is used once and
+    // in order.
+    fn replay(ops: &[Op], old: &[&str], new: &[&str]) -> (Vec<String>, Vec<String>) {
+        let (mut left, mut right) = (Vec::new(), Vec::new());
+        for op in ops {
+            match *op {
+                Op::Equal(i, j) => {
+                    assert_eq!(old[i], new[j]);
+                    assert_eq!((i, j), (left.len(), right.len()));
+                    left.push(old[i].to_string());
+                    right.push(new[j].to_string());
+                }
+                Op::Removed(i) => {
+                    assert_eq!(i, left.len());
+                    left.push(old[i].to_string());
+                }
+                Op::Added(j) => {
+                    assert_eq!(j, right.len());
+                    right.push(new[j].to_string());
+                }
+            }
+        }
+        (left, right)
+    }
+
+    #[test]
+    fn identical_files_are_all_equal() {
+        let lines = ["graph TD", "A --> B", "B --> C"];
+        let ops = diff_lines(&lines, &lines);
+        assert_eq!(ops, vec![Op::Equal(0, 0), Op::Equal(1, 1), Op::Equal(2, 2)]);
+    }
+
+    #[test]
+    fn replaced_line_is_removed_then_added() {
+        let ops = diff_lines(&["a", "b", "c"], &["a", "x", "c"]);
+        assert_eq!(
+            ops,
+            vec![
+                Op::Equal(0, 0),
+                Op::Removed(1),
+                Op::Added(1),
+                Op::Equal(2, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_the_longest_common_subsequence() {
+        let old = ["a", "b", "c", "a", "b", "b", "a"];
+        let new = ["c", "b", "a", "b", "a", "c"];
+        let ops = diff_lines(&old, &new);
+        let equal = ops.iter().filter(|op| matches!(op, Op::Equal(..))).count();
+        assert_eq!(equal, 4);
+        let (left, right) = replay(&ops, &old, &new);
+        assert_eq!(left, old);
+        assert_eq!(right, new);
+    }
+
+    #[test]
+    fn handles_empty_sides() {
+        assert_eq!(diff_lines(&[], &["a"]), vec![Op::Added(0)]);
+        assert_eq!(diff_lines(&["a"], &[]), vec![Op::Removed(0)]);
+        assert!(diff_lines(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn unified_output_matches_diff() {
+        let result = diff("a\nb\nc\n", "a\nx\nc\n", "old.mmd", "new.mmd");
+        assert!(!result.identical);
+        assert_eq!(
+            result.unified,
+            "--- old.mmd\n+++ new.mmd\n@@ -1,3 +1,3 @@\n a\n-b\n+x\n c\n"
+        );
+    }
+
+    #[test]
+    fn insertion_into_empty_file_points_before_it() {
+        let result = diff("", "a\n", "old.mmd", "new.mmd");
+        assert_eq!(
+            result.unified,
+            "--- old.mmd\n+++ new.mmd\n@@ -0,0 +1 @@\n+a\n"
+        );
+    }
+}
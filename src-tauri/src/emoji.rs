@@ -0,0 +1,124 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::spelling;
+
+// GitHub-style shortcodes, one `name emoji` pair per line.
+const SHORTCODES: &str = include_str!("../emoji/shortcodes.txt");
+const MAX_SUGGESTIONS: usize = 3;
+
+static TABLE: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+
+fn table() -> &'static HashMap<&'static str, &'static str> {
+    TABLE.get_or_init(|| {
+        SHORTCODES
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .collect()
+    })
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UnknownShortcode {
+    pub line: usize,
+    pub column: usize,
+    pub shortcode: String,
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Expanded {
+    pub content: String,
+    // How many shortcodes were replaced.
+    pub expanded: usize,
+    pub unknown: Vec<UnknownShortcode>,
+}
+
+pub fn lookup(name: &str) -> Option<&'static str> {
+    table().get(name).copied()
+}
+
+// Close shortcodes first, then longer ones starting with the name, so
+// `:thumbs:` suggests `:thumbs_up:`. Skin tone variants only come up when
+// asked for.
+fn suggestions(name: &str) -> Vec<String> {
+    let limit = if name.len() <= 5 { 1 } else { 2 };
+    let mut close: Vec<(usize, &str)> = table()
+        .keys()
+        .filter(|candidate| name.contains("skin") || !candidate.contains("skin_tone"))
+        .filter_map(|candidate| {
+            spelling::distance(name, candidate, limit)
+                .or_else(|| candidate.starts_with(&format!("{}_", name)).then_some(3))
+                .map(|distance| (distance, *candidate))
+        })
+        .collect();
+    close.sort_unstable();
+    close
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| format!(":{}:", candidate))
+        .collect()
+}
+
+// Replaces `:rocket:`-style shortcodes in labels, edge labels and notes
+// with their emoji. Identifiers and styling are left alone, since `:::` and
+// times like `10:30:00` look similar. Unknown shortcodes stay as they are
+// and are reported; ones without letters are taken to be something else.
+pub fn expand(content: &str) -> Expanded {
+    let shortcode = Regex::new(r":([a-z0-9_+-]+):").unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    // Byte ranges of the content to replace, with their emoji.
+    let mut replacements: Vec<(usize, usize, &str)> = Vec::new();
+    let mut unknown = Vec::new();
+
+    let mut starts = Vec::with_capacity(lines.len());
+    let mut start = 0;
+    for line in content.split_inclusive('\n') {
+        starts.push(start);
+        start += line.len();
+    }
+
+    for (number, offset, _, text) in spelling::texts(content) {
+        let base = starts[number - 1] + offset;
+        let mut position = 0;
+        // Manual iteration, so `:a:b:` can still match `:b:` when `:a:` is
+        // not a shortcode.
+        while let Some(caps) = shortcode.captures_at(text, position) {
+            let whole = caps.get(0).unwrap();
+            let name = &caps[1];
+            if let Some(emoji) = lookup(name) {
+                replacements.push((base + whole.start(), base + whole.end(), emoji));
+                position = whole.end();
+                continue;
+            }
+            if name.chars().any(|c| c.is_ascii_alphabetic()) {
+                unknown.push(UnknownShortcode {
+                    line: number,
+                    column: lines[number - 1][..offset + whole.start()].chars().count() + 1,
+                    shortcode: whole.as_str().to_string(),
+                    suggestions: suggestions(name),
+                });
+            }
+            position = whole.end() - 1;
+        }
+    }
+
+    replacements.sort_unstable_by_key(|(start, ..)| *start);
+    replacements.dedup_by_key(|(start, ..)| *start);
+    let mut expanded = String::with_capacity(content.len());
+    let mut copied = 0;
+    for (start, end, emoji) in &replacements {
+        expanded.push_str(&content[copied..*start]);
+        expanded.push_str(emoji);
+        copied = *end;
+    }
+    expanded.push_str(&content[copied..]);
+
+    Expanded {
+        content: expanded,
+        expanded: replacements.len(),
+        unknown,
+    }
+}
@@ -0,0 +1,266 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const EXTENSION: &str = "mmdx";
+const FORMAT: &str = "flowcraft-encrypted";
+const VERSION: u32 = 1;
+const CIPHER: &str = "aes-256-gcm";
+const MIN_PASSPHRASE_CHARS: usize = 8;
+
+// Argon2id settings for new files, from the OWASP recommendations. Files
+// store their own settings so these can be raised later.
+const MEMORY_KIB: u32 = 64 * 1024;
+const ITERATIONS: u32 = 3;
+const PARALLELISM: u32 = 1;
+// Refuse files asking for more than these, so a crafted file cannot make
+// loading exhaust memory or run for hours.
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_ITERATIONS: u32 = 16;
+const MAX_PARALLELISM: u32 = 16;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KdfParams {
+    algorithm: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: String,
+}
+
+// An encrypted diagram file. Everything except the ciphertext is in the
+// clear and authenticated as associated data.
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedFile {
+    format: String,
+    version: u32,
+    kdf: KdfParams,
+    cipher: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl EncryptedFile {
+    fn associated_data(&self) -> Vec<u8> {
+        format!(
+            "{}:{}:{}:{}:{}:{}:{}:{}",
+            self.format,
+            self.version,
+            self.kdf.algorithm,
+            self.kdf.memory_kib,
+            self.kdf.iterations,
+            self.kdf.parallelism,
+            self.kdf.salt,
+            self.cipher
+        )
+        .into_bytes()
+    }
+}
+
+pub fn is_encrypted_path(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case(EXTENSION))
+        .unwrap_or(false)
+}
+
+pub fn is_encrypted(data: &str) -> bool {
+    serde_json::from_str::<EncryptedFile>(data)
+        .map(|file| file.format == FORMAT)
+        .unwrap_or(false)
+}
+
+fn derive_key(passphrase: &str, kdf: &KdfParams) -> Result<[u8; 32], String> {
+    if kdf.algorithm != "argon2id" {
+        return Err(format!("Unsupported key derivation: {}", kdf.algorithm));
+    }
+    if kdf.memory_kib > MAX_MEMORY_KIB {
+        return Err("Encrypted file asks for too much memory to unlock".to_string());
+    }
+    if kdf.iterations > MAX_ITERATIONS || kdf.parallelism > MAX_PARALLELISM {
+        return Err("Encrypted file asks for too much work to unlock".to_string());
+    }
+    let salt = general_purpose::STANDARD
+        .decode(&kdf.salt)
+        .map_err(|e| format!("Invalid encrypted file: {}", e))?;
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| format!("Invalid encrypted file: {}", e))?;
+
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .map_err(|e| format!("Failed to derive key: {}", e))?;
+    Ok(key)
+}
+
+pub fn encrypt(content: &str, passphrase: &str) -> Result<String, String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(format!(
+            "Passphrase must be at least {} characters",
+            MIN_PASSPHRASE_CHARS
+        ));
+    }
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let mut file = EncryptedFile {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        kdf: KdfParams {
+            algorithm: "argon2id".to_string(),
+            memory_kib: MEMORY_KIB,
+            iterations: ITERATIONS,
+            parallelism: PARALLELISM,
+            salt: general_purpose::STANDARD.encode(salt),
+        },
+        cipher: CIPHER.to_string(),
+        nonce: general_purpose::STANDARD.encode(nonce),
+        ciphertext: String::new(),
+    };
+
+    let key = derive_key(passphrase, &file.kdf)?;
+    let cipher =
+        Aes256Gcm::new_from_slice(&key).map_err(|e| format!("Failed to encrypt: {}", e))?;
+    let aad = file.associated_data();
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: content.as_bytes(),
+                aad: &aad,
+            },
+        )
+        .map_err(|_| "Failed to encrypt diagram".to_string())?;
+    file.ciphertext = general_purpose::STANDARD.encode(ciphertext);
+
+    serde_json::to_string_pretty(&file).map_err(|e| format!("Failed to encrypt: {}", e))
+}
+
+pub fn decrypt(data: &str, passphrase: &str) -> Result<String, String> {
+    let file: EncryptedFile =
+        serde_json::from_str(data).map_err(|e| format!("Invalid encrypted file: {}", e))?;
+    if file.format != FORMAT || file.version != VERSION || file.cipher != CIPHER {
+        return Err("Unsupported encrypted file format".to_string());
+    }
+
+    let nonce = general_purpose::STANDARD
+        .decode(&file.nonce)
+        .map_err(|e| format!("Invalid encrypted file: {}", e))?;
+    if nonce.len() != 12 {
+        return Err("Invalid encrypted file: bad nonce".to_string());
+    }
+    let ciphertext = general_purpose::STANDARD
+        .decode(&file.ciphertext)
+        .map_err(|e| format!("Invalid encrypted file: {}", e))?;
+
+    let key = derive_key(passphrase, &file.kdf)?;
+    let cipher =
+        Aes256Gcm::new_from_slice(&key).map_err(|e| format!("Failed to decrypt: {}", e))?;
+    let aad = file.associated_data();
+    // A wrong passphrase and a tampered file look the same here.
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| "Wrong passphrase or damaged file".to_string())?;
+
+    String::from_utf8(plaintext).map_err(|e| format!("Failed to decrypt: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+
+    const PASSPHRASE: &str = "correct horse battery";
+    const CONTENT: &str = "flowchart TD\n  A[Größe] --> B[日本]\n";
+
+    // Key derivation is slow on purpose, so the tests share one file.
+    fn sample() -> &'static str {
+        static SAMPLE: OnceLock<String> = OnceLock::new();
+        SAMPLE.get_or_init(|| encrypt(CONTENT, PASSPHRASE).unwrap())
+    }
+
+    // Rewrites one field of an encrypted file, as an attacker could.
+    fn edit(data: &str, change: impl FnOnce(&mut EncryptedFile)) -> String {
+        let mut file: EncryptedFile = serde_json::from_str(data).unwrap();
+        change(&mut file);
+        serde_json::to_string(&file).unwrap()
+    }
+
+    #[test]
+    fn round_trips_content() {
+        assert!(is_encrypted(sample()));
+        assert!(!sample().contains("Größe"));
+        assert_eq!(decrypt(sample(), PASSPHRASE).unwrap(), CONTENT);
+    }
+
+    #[test]
+    fn salts_and_nonces_differ_between_files() {
+        let first: EncryptedFile = serde_json::from_str(sample()).unwrap();
+        let second: EncryptedFile =
+            serde_json::from_str(&encrypt(CONTENT, PASSPHRASE).unwrap()).unwrap();
+        assert_ne!(first.kdf.salt, second.kdf.salt);
+        assert_ne!(first.nonce, second.nonce);
+        assert_ne!(first.ciphertext, second.ciphertext);
+    }
+
+    #[test]
+    fn rejects_a_wrong_passphrase() {
+        assert_eq!(
+            decrypt(sample(), "correct horse battery!").unwrap_err(),
+            "Wrong passphrase or damaged file"
+        );
+    }
+
+    #[test]
+    fn counts_passphrase_length_in_characters() {
+        assert!(encrypt("graph TD", "short").is_err());
+        assert!(encrypt("graph TD", "ÿÿÿÿ").is_err());
+        assert!(encrypt("graph TD", "ÿÿÿÿÿÿÿÿ").is_ok());
+    }
+
+    #[test]
+    fn detects_tampering_with_the_clear_fields() {
+        let weaker = edit(sample(), |file| file.kdf.iterations = 1);
+        assert!(decrypt(&weaker, PASSPHRASE).is_err());
+
+        let ciphertext = edit(sample(), |file| {
+            let mut bytes = general_purpose::STANDARD.decode(&file.ciphertext).unwrap();
+            bytes[0] ^= 1;
+            file.ciphertext = general_purpose::STANDARD.encode(bytes);
+        });
+        assert!(decrypt(&ciphertext, PASSPHRASE).is_err());
+    }
+
+    #[test]
+    fn refuses_excessive_key_derivation_settings() {
+        let memory = edit(sample(), |file| file.kdf.memory_kib = MAX_MEMORY_KIB + 1);
+        assert!(decrypt(&memory, PASSPHRASE)
+            .unwrap_err()
+            .contains("too much memory"));
+        let iterations = edit(sample(), |file| file.kdf.iterations = MAX_ITERATIONS + 1);
+        assert!(decrypt(&iterations, PASSPHRASE)
+            .unwrap_err()
+            .contains("too much work"));
+        let algorithm = edit(sample(), |file| file.kdf.algorithm = "pbkdf2".to_string());
+        assert!(decrypt(&algorithm, PASSPHRASE).is_err());
+    }
+
+    #[test]
+    fn recognizes_encrypted_files() {
+        assert!(!is_encrypted("flowchart TD\n  A --> B"));
+        assert!(!is_encrypted(r#"{"format": "other"}"#));
+        assert!(is_encrypted_path(Path::new("/diagrams/flow.MMDX")));
+        assert!(!is_encrypted_path(Path::new("/diagrams/flow.mmd")));
+    }
+}
@@ -0,0 +1,1114 @@
+use base64::{engine::general_purpose, Engine as _};
+use image::imageops::FilterType;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use tiff::encoder::{colortype, compression::Lzw, Rational, TiffEncoder};
+use tiff::tags::ResolutionUnit;
+
+use crate::accessibility;
+use crate::comment_report;
+use crate::emoji;
+use crate::export_css;
+use crate::images;
+use crate::inkscape;
+use crate::pdf::{self, PdfImage, PdfPage, PT_PER_PX};
+use crate::redaction::{self, RedactionConfig, RedactionReport};
+use crate::render::{self, RenderConfig, Viewport};
+use crate::security;
+use crate::signing::{self, SignatureMode};
+use crate::svg_fonts;
+
+// Margin around the diagram on fitted pages.
+const PAGE_MARGIN_PT: f64 = 36.0;
+// Pixel density of the raster embedded in fitted PDFs, about 288 dpi.
+const PDF_RASTER_SCALE: u32 = 3;
+// Upper bound for either side of any raster, to keep memory in check.
+const MAX_RASTER_SIDE: f64 = 16_384.0;
+// How much of the diagram neighbouring tiles repeat, so pages can be
+// trimmed and glued along the marks.
+const TILE_OVERLAP_PT: f64 = 18.0;
+const TILE_MARK_PT: f64 = 12.0;
+const MAX_TILED_PAGES: usize = 100;
+
+// Formats exports can be written in: the renderer's own, and those
+// converted from its SVG.
+pub const FORMATS: [&str; 6] = ["svg", "png", "pdf", "emf", "eps", "tiff"];
+// Converted by Inkscape, which drops labels Mermaid draws as HTML.
+const CONVERTED_FORMATS: [&str; 2] = ["emf", "eps"];
+// What print shops and journals usually ask for.
+const DEFAULT_TIFF_DPI: u32 = 300;
+const MAX_TIFF_DPI: u32 = 2400;
+// CSS pixels per inch, which Mermaid lays diagrams out in.
+const CSS_DPI: f64 = 96.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PageSize {
+    A4,
+    Letter,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    #[default]
+    Portrait,
+    Landscape,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ExportOptions {
+    #[serde(default)]
+    pub max_width: Option<u32>,
+    #[serde(default)]
+    pub max_height: Option<u32>,
+    #[serde(default)]
+    pub fit_to_page: Option<PageSize>,
+    // Splits a PDF across as many pages of this size as the diagram needs,
+    // instead of shrinking it onto one.
+    #[serde(default)]
+    pub tile: Option<PageSize>,
+    #[serde(default)]
+    pub orientation: Orientation,
+    #[serde(default)]
+    pub embed_fonts: bool,
+    // Replaces local image references with data URIs. Relative paths
+    // resolve against `image_dir`, normally the folder of the diagram.
+    #[serde(default)]
+    pub inline_images: bool,
+    #[serde(default)]
+    pub image_dir: Option<String>,
+    // The open workspace, which images may also come from. Set by the
+    // backend, never by the caller.
+    #[serde(skip)]
+    pub workspace: Option<String>,
+    // Expands `:rocket:`-style shortcodes in labels into emoji.
+    #[serde(default)]
+    pub expand_emoji: bool,
+    // Strips unsafe parts from the source, and rebuilds the rendered SVG
+    // from an allowlist so nothing in it can run script.
+    #[serde(default)]
+    pub strip_unsafe: bool,
+    // Drops comments, `%%{...}%%` directives and frontmatter from the source
+    // before rendering. The export theme is worked out beforehand, so it
+    // still applies.
+    #[serde(default)]
+    pub strip_metadata: bool,
+    // Redaction rules applied before anything is rendered. Set by the
+    // backend from the settings, never by the caller.
+    #[serde(skip)]
+    pub redaction: Option<RedactionConfig>,
+    // Extra CSS for the rendered diagram. When unset, callers fill it in from
+    // the CSS stored for `source` and the workspace.
+    #[serde(default)]
+    pub css: Option<String>,
+    // The file the content was opened from, if it was saved.
+    #[serde(default)]
+    pub source: Option<String>,
+    // Signs SVG and PDF exports with the user's signing key.
+    #[serde(default)]
+    pub sign: Option<SignatureMode>,
+    // Recompresses PNG exports losslessly at this effort level, from 0 to
+    // MAX_PNG_OPTIMIZATION. Other formats ignore it.
+    #[serde(default)]
+    pub optimize_png: Option<u8>,
+    // Resolution of TIFF exports, DEFAULT_TIFF_DPI when unset. The diagram
+    // keeps its printed size, so higher values mean more pixels.
+    #[serde(default)]
+    pub dpi: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ExportResult {
+    pub path: String,
+    // Output size in pixels, or in points for PDFs.
+    pub width: f64,
+    pub height: f64,
+    pub scale: f64,
+    pub pages: usize,
+    pub warnings: Vec<String>,
+    // Path of the detached signature, when one was written.
+    pub signature: Option<String>,
+    // What was redacted, when redaction was on.
+    pub redaction: Option<RedactionReport>,
+}
+
+// A light and a dark rendering of the same diagram, with the `<picture>`
+// markup that shows the one matching the reader's color scheme.
+#[derive(Debug, Serialize, Clone)]
+pub struct LightDarkExport {
+    pub light: ExportResult,
+    pub dark: ExportResult,
+    pub html: String,
+}
+
+// Options for `preview`: the export options plus the image format, which
+// defaults to SVG.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct PreviewOptions {
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(flatten)]
+    pub export: ExportOptions,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Preview {
+    pub data_uri: String,
+    pub width: f64,
+    pub height: f64,
+    pub warnings: Vec<String>,
+}
+
+// An export held in memory, for previews that should match the file.
+#[derive(Debug, Clone)]
+pub struct Rendered {
+    pub bytes: Vec<u8>,
+    pub width: f64,
+    pub height: f64,
+    pub scale: f64,
+    pub pages: usize,
+    pub warnings: Vec<String>,
+    pub redaction: Option<RedactionReport>,
+}
+
+impl PageSize {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "a4" => Ok(PageSize::A4),
+            "letter" => Ok(PageSize::Letter),
+            _ => Err(format!("Unknown page size: {}", name)),
+        }
+    }
+
+    // Width and height in points.
+    pub fn dimensions(self, orientation: Orientation) -> (f64, f64) {
+        let (width, height) = match self {
+            PageSize::A4 => (595.28, 841.89),
+            PageSize::Letter => (612.0, 792.0),
+        };
+        match orientation {
+            Orientation::Portrait => (width, height),
+            Orientation::Landscape => (height, width),
+        }
+    }
+}
+
+// Adds CSS to diagram source as a `themeCSS` init directive, after any
+// frontmatter, so Mermaid scopes it to the diagram. Rendered SVG gets a
+// style element instead.
+fn with_css(content: &str, css: &str) -> String {
+    if is_svg(content) {
+        let Some(start) = content.find("<svg") else {
+            return content.to_string();
+        };
+        let Some(end) = content[start..].find('>').map(|end| start + end + 1) else {
+            return content.to_string();
+        };
+        return format!(
+            "{}<style>{}</style>{}",
+            &content[..end],
+            css,
+            &content[end..]
+        );
+    }
+
+    let directive = format!("%%{{init: {}}}%%", serde_json::json!({ "themeCSS": css }));
+    let lines: Vec<&str> = content.lines().collect();
+    let header = if lines.first().map(|line| line.trim()) == Some("---") {
+        lines
+            .iter()
+            .skip(1)
+            .position(|line| line.trim() == "---")
+            .map_or(0, |end| end + 2)
+    } else {
+        0
+    };
+    let mut output: Vec<&str> = lines[..header].to_vec();
+    output.push(&directive);
+    output.extend(&lines[header..]);
+    output.join("\n")
+}
+
+pub fn is_svg(content: &str) -> bool {
+    let start = content.trim_start();
+    start.starts_with("<svg") || start.starts_with("<?xml")
+}
+
+fn svg_number(svg_tag: &str, attribute: &str) -> Option<f64> {
+    let pattern = Regex::new(&format!(r#"\s{}\s*=\s*["']([0-9.]+)(px)?["']"#, attribute)).ok()?;
+    pattern
+        .captures(svg_tag)
+        .and_then(|caps| caps[1].parse().ok())
+}
+
+// The natural size of an SVG, from its viewBox or width and height.
+pub fn svg_size(svg: &str) -> Option<(f64, f64)> {
+    let tag = Regex::new(r"<svg\b[^>]*>").unwrap().find(svg)?.as_str();
+    let view_box = Regex::new(r#"viewBox\s*=\s*["']([^"']+)["']"#).unwrap();
+
+    if let Some(caps) = view_box.captures(tag) {
+        let values: Vec<f64> = caps[1]
+            .split([' ', ','])
+            .filter(|value| !value.is_empty())
+            .filter_map(|value| value.parse().ok())
+            .collect();
+        if let [_, _, width, height] = values[..] {
+            if width > 0.0 && height > 0.0 {
+                return Some((width, height));
+            }
+        }
+    }
+
+    Some((svg_number(tag, "width")?, svg_number(tag, "height")?))
+}
+
+// The largest scale up to 1 that satisfies every constraint.
+pub fn fit_scale(width: f64, height: f64, options: &ExportOptions) -> f64 {
+    let mut scale: f64 = 1.0;
+    if let Some(max_width) = options.max_width {
+        scale = scale.min(max_width as f64 / width);
+    }
+    if let Some(max_height) = options.max_height {
+        scale = scale.min(max_height as f64 / height);
+    }
+    if let Some(page) = options.fit_to_page {
+        let (page_width, page_height) = page.dimensions(options.orientation);
+        scale = scale
+            .min((page_width - 2.0 * PAGE_MARGIN_PT) / (width * PT_PER_PX))
+            .min((page_height - 2.0 * PAGE_MARGIN_PT) / (height * PT_PER_PX));
+    }
+    scale.max(f64::EPSILON)
+}
+
+// Rewrites the root width and height so viewers display the SVG at the
+// scaled size; the viewBox keeps the drawing intact.
+fn resize_svg(svg: &str, width: f64, height: f64) -> String {
+    let tag = Regex::new(r"<svg\b[^>]*>").unwrap();
+    let size = Regex::new(r#"\s(width|height)\s*=\s*["'][^"']*["']"#).unwrap();
+    let max_width = Regex::new(r"max-width:\s*[0-9.]+px;?").unwrap();
+
+    tag.replace(svg, |caps: &regex::Captures| {
+        let without_size = size.replace_all(&caps[0], "");
+        let without_max = max_width.replace_all(&without_size, "");
+        format!(
+            "<svg width=\"{}\" height=\"{}\"{}",
+            pdf::format_number(width),
+            pdf::format_number(height),
+            &without_max[4..]
+        )
+    })
+    .to_string()
+}
+
+// oxipng's presets, from a quick pass at 0 to trying every filter and
+// compression setting at 6.
+pub const MAX_PNG_OPTIMIZATION: u8 = 6;
+
+pub fn validate_png_optimization(level: u8) -> Result<(), String> {
+    if level > MAX_PNG_OPTIMIZATION {
+        return Err(format!(
+            "PNG optimization levels go from 0 to {}",
+            MAX_PNG_OPTIMIZATION
+        ));
+    }
+    Ok(())
+}
+
+// Exported PNGs mostly end up committed, where every kilobyte stays in the
+// history. The pixels and metadata chunks are kept as they are.
+fn optimize_png(bytes: Vec<u8>, level: u8) -> Result<Vec<u8>, String> {
+    let options = oxipng::Options::from_preset(level);
+    oxipng::optimize_from_memory(&bytes, &options)
+        .map_err(|e| format!("Failed to optimize PNG: {}", e))
+}
+
+pub fn format_from_path(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    let format = match extension.as_str() {
+        "tif" => "tiff".to_string(),
+        _ => extension,
+    };
+    FORMATS.contains(&format.as_str()).then_some(format)
+}
+
+// The active render config with labels drawn as SVG text rather than HTML
+// in a `foreignObject`, for SVG that is converted to other formats.
+fn svg_text_config() -> RenderConfig {
+    let mut config = render::current_config().unwrap_or(RenderConfig {
+        mermaid: json!({}),
+        background: None,
+    });
+    config.mermaid["htmlLabels"] = json!(false);
+    config.mermaid["flowchart"]["htmlLabels"] = json!(false);
+    config
+}
+
+pub fn validate_dpi(dpi: u32) -> Result<(), String> {
+    if !(1..=MAX_TIFF_DPI).contains(&dpi) {
+        return Err(format!("The resolution must be 1 to {} dpi", MAX_TIFF_DPI));
+    }
+    Ok(())
+}
+
+// An LZW-compressed TIFF with the resolution recorded, flattened onto white
+// since print workflows expect opaque images.
+fn png_to_tiff(png: &[u8], dpi: u32) -> Result<Vec<u8>, String> {
+    let image = image::load_from_memory(png)
+        .map_err(|e| format!("Failed to decode rendered image: {}", e))?
+        .to_rgba8();
+    let mut rgb = Vec::with_capacity((image.width() * image.height() * 3) as usize);
+    for pixel in image.pixels() {
+        let [red, green, blue, alpha] = pixel.0;
+        for channel in [red, green, blue] {
+            let blended = (channel as u32 * alpha as u32 + 255 * (255 - alpha as u32)) / 255;
+            rgb.push(blended as u8);
+        }
+    }
+
+    let mut output = Cursor::new(Vec::new());
+    let mut encoder =
+        TiffEncoder::new(&mut output).map_err(|e| format!("Failed to encode TIFF: {}", e))?;
+    let mut tiff = encoder
+        .new_image_with_compression::<colortype::RGB8, _>(image.width(), image.height(), Lzw)
+        .map_err(|e| format!("Failed to encode TIFF: {}", e))?;
+    tiff.resolution(ResolutionUnit::Inch, Rational { n: dpi, d: 1 });
+    tiff.write_data(&rgb)
+        .map_err(|e| format!("Failed to encode TIFF: {}", e))?;
+    Ok(output.into_inner())
+}
+
+// Reads the size from the PNG header, so a render larger than the limit is
+// refused before it is decoded.
+fn check_raster_size(png: &[u8]) -> Result<(), String> {
+    let (width, height) = image::ImageReader::new(Cursor::new(png))
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to read rendered image: {}", e))?
+        .into_dimensions()
+        .map_err(|e| format!("Failed to read rendered image: {}", e))?;
+    if width as f64 > MAX_RASTER_SIDE || height as f64 > MAX_RASTER_SIDE {
+        return Err(format!(
+            "The rendered image is {}x{} pixels, more than the {} pixel limit; set a maximum width or height",
+            width, height, MAX_RASTER_SIDE
+        ));
+    }
+    Ok(())
+}
+
+fn resize_png(bytes: Vec<u8>, width: u32, height: u32) -> Result<(Vec<u8>, u32, u32), String> {
+    check_raster_size(&bytes)?;
+    let image = image::load_from_memory(&bytes)
+        .map_err(|e| format!("Failed to decode rendered image: {}", e))?;
+    if image.width() <= width && image.height() <= height {
+        return Ok((bytes, image.width(), image.height()));
+    }
+
+    let resized = image.resize(width, height, FilterType::Lanczos3);
+    let mut output = Vec::new();
+    resized
+        .write_to(&mut Cursor::new(&mut output), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok((output, resized.width(), resized.height()))
+}
+
+// Renders the diagram as a raster for embedding in a PDF.
+fn pdf_raster(content: &str, width: f64, height: f64) -> Result<PdfImage, String> {
+    let raster_scale = (PDF_RASTER_SCALE as f64)
+        .min(MAX_RASTER_SIDE / width)
+        .min(MAX_RASTER_SIDE / height)
+        .floor()
+        .max(1.0) as u32;
+    let png = render::render_content_to_bytes_at(
+        content,
+        "png",
+        Viewport {
+            width: Some(width.ceil() as u32),
+            scale: Some(raster_scale),
+        },
+    )?;
+    check_raster_size(&png)?;
+    PdfImage::from_png(&png)
+}
+
+// The diagram centred on a page-sized SVG, in points, for Inkscape to turn
+// into a PDF page.
+fn page_svg(svg: &str, page: (f64, f64), x: f64, y: f64, width: f64, height: f64) -> String {
+    let start = svg.find("<svg").unwrap_or(0);
+    let diagram = resize_svg(&svg[start..], width, height);
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}pt\" height=\"{}pt\" viewBox=\"0 0 {} {}\"><svg x=\"{}\" y=\"{}\"{}</svg>",
+        pdf::format_number(page.0),
+        pdf::format_number(page.1),
+        pdf::format_number(page.0),
+        pdf::format_number(page.1),
+        pdf::format_number(x),
+        pdf::format_number(y),
+        &diagram[4..]
+    )
+}
+
+// Centres the diagram at `scale` on a single page of the given size in
+// points. Inkscape keeps it vector; without Inkscape a raster is embedded
+// instead.
+fn fitted_pdf(
+    content: &str,
+    svg: &str,
+    (page_width, page_height): (f64, f64),
+    (width, height): (f64, f64),
+    scale: f64,
+    warnings: &mut Vec<String>,
+) -> Result<(Vec<u8>, f64, f64), String> {
+    let draw_width = width * PT_PER_PX * scale;
+    let draw_height = height * PT_PER_PX * scale;
+
+    if inkscape::is_available() {
+        let page = page_svg(
+            svg,
+            (page_width, page_height),
+            (page_width - draw_width) / 2.0,
+            (page_height - draw_height) / 2.0,
+            draw_width,
+            draw_height,
+        );
+        return Ok((inkscape::convert(&page, "pdf")?, page_width, page_height));
+    }
+    warnings.push("Inkscape is not installed, so the page holds a raster image".to_string());
+
+    let image = pdf_raster(content, width, height)?;
+    let content = pdf::draw_image(
+        (page_width - draw_width) / 2.0,
+        (page_height - draw_height) / 2.0,
+        draw_width,
+        draw_height,
+    );
+
+    let bytes = pdf::write(
+        &image,
+        &[PdfPage {
+            width: page_width,
+            height: page_height,
+            content,
+        }],
+    );
+    Ok((bytes, page_width, page_height))
+}
+
+// Number of tiles needed to cover `length` when each one shows `area`, with
+// neighbours overlapping.
+fn tile_count(length: f64, area: f64) -> usize {
+    if length <= area {
+        return 1;
+    }
+    ((length - TILE_OVERLAP_PT) / (area - TILE_OVERLAP_PT)).ceil() as usize
+}
+
+// Overlap marks in the margins: ticks where the neighbouring page's content
+// ends, on every side that has a neighbour.
+fn tile_marks(
+    area: (f64, f64, f64, f64),
+    column: usize,
+    row: usize,
+    columns: usize,
+    rows: usize,
+) -> String {
+    let (left, bottom, width, height) = area;
+    let (right, top) = (left + width, bottom + height);
+    let mut marks = String::new();
+
+    let mut vertical = |x: f64| {
+        marks.push_str(&pdf::line((x, top), (x, top + TILE_MARK_PT), false));
+        marks.push_str(&pdf::line((x, bottom), (x, bottom - TILE_MARK_PT), false));
+    };
+    if column > 0 {
+        vertical(left + TILE_OVERLAP_PT);
+    }
+    if column + 1 < columns {
+        vertical(right - TILE_OVERLAP_PT);
+    }
+
+    let mut horizontal = |y: f64| {
+        marks.push_str(&pdf::line((left, y), (left - TILE_MARK_PT, y), false));
+        marks.push_str(&pdf::line((right, y), (right + TILE_MARK_PT, y), false));
+    };
+    if row > 0 {
+        horizontal(top - TILE_OVERLAP_PT);
+    }
+    if row + 1 < rows {
+        horizontal(bottom + TILE_OVERLAP_PT);
+    }
+
+    // Dashed outline of the printed area, for trimming.
+    let corners = [
+        (left, bottom),
+        (right, bottom),
+        (right, top),
+        (left, top),
+        (left, bottom),
+    ];
+    for pair in corners.windows(2) {
+        marks.push_str(&pdf::line(pair[0], pair[1], true));
+    }
+    marks
+}
+
+// Splits the diagram at its scaled size across pages, left to right and
+// top to bottom, with each page repeating a strip of its neighbours.
+fn tiled_pdf(
+    content: &str,
+    page: PageSize,
+    orientation: Orientation,
+    width: f64,
+    height: f64,
+    scale: f64,
+) -> Result<(Vec<u8>, f64, f64, usize), String> {
+    let (page_width, page_height) = page.dimensions(orientation);
+    let area_width = page_width - 2.0 * PAGE_MARGIN_PT;
+    let area_height = page_height - 2.0 * PAGE_MARGIN_PT;
+    let draw_width = width * PT_PER_PX * scale;
+    let draw_height = height * PT_PER_PX * scale;
+
+    let columns = tile_count(draw_width, area_width);
+    let rows = tile_count(draw_height, area_height);
+    if columns * rows > MAX_TILED_PAGES {
+        return Err(format!(
+            "Tiling would need {} pages (at most {}); set a maximum width or height",
+            columns * rows,
+            MAX_TILED_PAGES
+        ));
+    }
+
+    let image = pdf_raster(content, width, height)?;
+    let area = (PAGE_MARGIN_PT, PAGE_MARGIN_PT, area_width, area_height);
+    let step_x = area_width - TILE_OVERLAP_PT;
+    let step_y = area_height - TILE_OVERLAP_PT;
+
+    let mut pages = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            // Shift the image so this tile's part of it lands in the area,
+            // measuring the offset from the diagram's top left corner.
+            let x = PAGE_MARGIN_PT - column as f64 * step_x;
+            let y = PAGE_MARGIN_PT + area_height + row as f64 * step_y - draw_height;
+
+            let mut content = pdf::draw_image_clipped(area, x, y, draw_width, draw_height);
+            content.push_str(&tile_marks(area, column, row, columns, rows));
+            content.push_str(&pdf::text(
+                PAGE_MARGIN_PT,
+                PAGE_MARGIN_PT / 2.0,
+                8.0,
+                &format!(
+                    "Page {} of {} - row {}, column {}",
+                    pages.len() + 1,
+                    columns * rows,
+                    row + 1,
+                    column + 1
+                ),
+            ));
+            pages.push(PdfPage {
+                width: page_width,
+                height: page_height,
+                content,
+            });
+        }
+    }
+
+    let count = pages.len();
+    Ok((pdf::write(&image, &pages), page_width, page_height, count))
+}
+
+// Renders diagram source (or post-processes a finished SVG), shrinking it
+// to the size constraints in `options`.
+pub fn render(content: &str, format: &str, options: &ExportOptions) -> Result<Rendered, String> {
+    if !FORMATS.contains(&format) {
+        return Err("Unsupported format".to_string());
+    }
+    if is_svg(content) && format != "svg" {
+        return Err("Only SVG exports can start from rendered SVG".to_string());
+    }
+    if options.tile.is_some() && format != "pdf" {
+        return Err("Tiling is only available for PDF exports".to_string());
+    }
+    if options.tile.is_some() && options.fit_to_page.is_some() {
+        return Err("Choose either fit to page or tiling, not both".to_string());
+    }
+    if let Some(level) = options.optimize_png {
+        validate_png_optimization(level)?;
+    }
+    if let Some(dpi) = options.dpi {
+        validate_dpi(dpi)?;
+    }
+
+    let mut warnings = Vec::new();
+    let mut content = content.to_string();
+    let mut report = None;
+    if let Some(config) = &options.redaction {
+        let redacted = redaction::apply(&content, config);
+        content = redacted.content;
+        report = Some(redacted.report);
+    }
+    if options.strip_metadata && !is_svg(&content) {
+        content = comment_report::strip(&content);
+    }
+    if options.strip_unsafe && !is_svg(&content) {
+        let sanitized = security::sanitize(&content);
+        warnings.extend(sanitized.findings.iter().map(|finding| {
+            format!(
+                "Line {}: removed unsafe content: {}",
+                finding.line, finding.message
+            )
+        }));
+        content = sanitized.content;
+    }
+    if options.expand_emoji && !is_svg(&content) {
+        let result = emoji::expand(&content);
+        warnings.extend(result.unknown.iter().map(|unknown| {
+            format!(
+                "Line {}: unknown emoji shortcode {}",
+                unknown.line, unknown.shortcode
+            )
+        }));
+        content = result.content;
+    }
+    if options.inline_images {
+        let scope = images::ImageScope::new(
+            options.image_dir.as_deref().map(Path::new),
+            options.workspace.as_deref().map(Path::new),
+        );
+        let result = images::inline(&content, &scope);
+        warnings.extend(result.warnings);
+        content = result.content;
+    }
+    if let Some(css) = options.css.as_deref().filter(|css| !css.trim().is_empty()) {
+        export_css::validate(css)?;
+        content = with_css(&content, css);
+    }
+    let content = content.as_str();
+
+    let svg = if is_svg(content) {
+        content.to_string()
+    } else {
+        let render_svg = || render::render_content_to_bytes(content, "svg");
+        let bytes = if CONVERTED_FORMATS.contains(&format) {
+            render::with_config(svg_text_config(), render_svg)?
+        } else {
+            render_svg()?
+        };
+        String::from_utf8(bytes).map_err(|e| format!("Failed to read rendered SVG: {}", e))?
+    };
+    let svg = if options.strip_unsafe {
+        let sanitized = security::sanitize_svg(&svg)?;
+        warnings.extend(
+            sanitized
+                .findings
+                .iter()
+                .map(|finding| format!("Removed unsafe content: {}", finding.message)),
+        );
+        sanitized.content
+    } else {
+        svg
+    };
+    let (width, height) = svg_size(&svg).ok_or("Could not determine the diagram size")?;
+    let mut scale = fit_scale(width, height, options);
+    // TIFF renders denser than the CSS size and bounds itself below.
+    if format == "png" {
+        let bounded = scale
+            .min(MAX_RASTER_SIDE / width)
+            .min(MAX_RASTER_SIDE / height);
+        if bounded < scale {
+            warnings.push(format!(
+                "The diagram is larger than {} pixels; the PNG is scaled down",
+                MAX_RASTER_SIDE
+            ));
+            scale = bounded;
+        }
+    }
+    let mut pages = 1;
+
+    let (bytes, out_width, out_height) = match format {
+        "svg" => {
+            let mut svg = if scale < 1.0 {
+                resize_svg(&svg, width * scale, height * scale)
+            } else {
+                svg
+            };
+            if options.embed_fonts {
+                let result = svg_fonts::embed_fonts(&svg)?;
+                warnings.extend(
+                    result
+                        .missing
+                        .iter()
+                        .map(|family| format!("Font not installed, not embedded: {}", family)),
+                );
+                warnings.extend(result.skipped);
+                svg = result.svg;
+            }
+            (svg.into_bytes(), width * scale, height * scale)
+        }
+        "png" => {
+            let target_width = (width * scale).round().max(1.0) as u32;
+            let target_height = (height * scale).round().max(1.0) as u32;
+            // Diagrams with `useMaxWidth` shrink to the page width in the
+            // renderer already; the resize catches the ones that do not.
+            let png = render::render_content_to_bytes_at(
+                content,
+                "png",
+                Viewport {
+                    width: Some(target_width),
+                    scale: None,
+                },
+            )?;
+            let (mut png, out_width, out_height) = resize_png(png, target_width, target_height)?;
+            if let Some(level) = options.optimize_png {
+                png = optimize_png(png, level)?;
+            }
+            (png, out_width as f64, out_height as f64)
+        }
+        "tiff" => {
+            let mut dpi = options.dpi.unwrap_or(DEFAULT_TIFF_DPI);
+            let (css_width, css_height) = (width * scale, height * scale);
+            let largest = css_width.max(css_height);
+            // The renderer only scales by whole numbers, so it renders at
+            // least as dense and the resize brings it down to the resolution.
+            // The limit applies to what it renders.
+            let render_scale = |dpi: u32| (dpi as f64 / CSS_DPI).ceil().max(1.0);
+            if largest * render_scale(dpi) > MAX_RASTER_SIDE {
+                let fitting = (MAX_RASTER_SIDE / largest).floor();
+                if fitting < 1.0 {
+                    return Err(
+                        "The diagram is too large for a TIFF; set a maximum width or height"
+                            .to_string(),
+                    );
+                }
+                dpi = ((fitting * CSS_DPI) as u32).max(1);
+                warnings.push(format!(
+                    "The diagram is too large for the chosen resolution; exported at {} dpi",
+                    dpi
+                ));
+            }
+            let target_width = (css_width * dpi as f64 / CSS_DPI).round().max(1.0) as u32;
+            let target_height = (css_height * dpi as f64 / CSS_DPI).round().max(1.0) as u32;
+            let png = render::render_content_to_bytes_at(
+                content,
+                "png",
+                Viewport {
+                    width: Some(css_width.ceil() as u32),
+                    scale: Some(render_scale(dpi) as u32),
+                },
+            )?;
+            let (png, out_width, out_height) = resize_png(png, target_width, target_height)?;
+            (png_to_tiff(&png, dpi)?, out_width as f64, out_height as f64)
+        }
+        // Vector formats for Office, which scales embedded PNGs badly, and
+        // for LaTeX through graphicx.
+        "emf" | "eps" => {
+            let svg = if scale < 1.0 {
+                resize_svg(&svg, width * scale, height * scale)
+            } else {
+                svg
+            };
+            (
+                inkscape::convert(&svg, format)?,
+                width * scale,
+                height * scale,
+            )
+        }
+        _ => match (options.fit_to_page, options.tile) {
+            (Some(page), _) => fitted_pdf(
+                content,
+                &svg,
+                page.dimensions(options.orientation),
+                (width, height),
+                scale,
+                &mut warnings,
+            )?,
+            (None, Some(page)) => {
+                let (bytes, page_width, page_height, count) =
+                    tiled_pdf(content, page, options.orientation, width, height, scale)?;
+                pages = count;
+                (bytes, page_width, page_height)
+            }
+            // A maximum width or height shrinks the page to the diagram.
+            (None, None) if scale < 1.0 => fitted_pdf(
+                content,
+                &svg,
+                (width * PT_PER_PX * scale, height * PT_PER_PX * scale),
+                (width, height),
+                scale,
+                &mut warnings,
+            )?,
+            (None, None) => (
+                render::render_content_to_bytes(content, "pdf")?,
+                width * PT_PER_PX,
+                height * PT_PER_PX,
+            ),
+        },
+    };
+
+    Ok(Rendered {
+        bytes,
+        width: out_width,
+        height: out_height,
+        scale,
+        pages,
+        warnings,
+        redaction: report,
+    })
+}
+
+// Like `render`, but writes the result to `output`.
+pub fn export(
+    content: &str,
+    output: &Path,
+    format: &str,
+    options: &ExportOptions,
+) -> Result<ExportResult, String> {
+    let rendered = render(content, format, options)?;
+
+    // Sign before writing anything, so a missing key leaves no unsigned file.
+    let mut bytes = rendered.bytes;
+    let mut detached = None;
+    match options.sign {
+        Some(SignatureMode::Embedded) => bytes = signing::embed(&bytes, format)?,
+        Some(SignatureMode::Detached) => {
+            detached = Some((
+                signing::detached_path(output),
+                signing::detached(&bytes, format)?,
+            ));
+        }
+        None => {}
+    }
+
+    fs::write(output, bytes).map_err(|e| format!("Failed to export: {}", e))?;
+    if let Some((path, signature)) = &detached {
+        fs::write(path, signature).map_err(|e| format!("Failed to write signature: {}", e))?;
+    }
+
+    Ok(ExportResult {
+        path: output.to_string_lossy().to_string(),
+        width: rendered.width,
+        height: rendered.height,
+        scale: rendered.scale,
+        pages: rendered.pages,
+        warnings: rendered.warnings,
+        signature: detached.map(|(path, _)| path.to_string_lossy().to_string()),
+        redaction: rendered.redaction,
+    })
+}
+
+// `flow.svg` becomes `flow-light.svg` or `flow-dark.svg`.
+pub fn variant_path(output: &Path, suffix: &str) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match output.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, suffix, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, suffix),
+    };
+    output.with_file_name(name)
+}
+
+// Exports the diagram twice, with the light and the dark render config,
+// next to `output` with `-light` and `-dark` suffixes.
+pub fn export_light_dark(
+    content: &str,
+    output: &Path,
+    format: &str,
+    options: &ExportOptions,
+    light: RenderConfig,
+    dark: RenderConfig,
+) -> Result<LightDarkExport, String> {
+    let light_path = variant_path(output, "light");
+    let dark_path = variant_path(output, "dark");
+    let light = render::with_config(light, || export(content, &light_path, format, options))?;
+    let dark = render::with_config(dark, || export(content, &dark_path, format, options))?;
+
+    let file_name = |path: &Path| {
+        path.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    };
+    let alt = accessibility::inspect(content)
+        .title
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| {
+            output
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        })
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;");
+    let html = format!(
+        "<picture>\n  <source media=\"(prefers-color-scheme: dark)\" srcset=\"{}\">\n  <img alt=\"{}\" src=\"{}\">\n</picture>",
+        file_name(&dark_path),
+        alt,
+        file_name(&light_path)
+    );
+    Ok(LightDarkExport { light, dark, html })
+}
+
+// Renders an export-accurate image as a data URI, for previews and
+// thumbnails that should not touch the disk.
+pub fn preview(content: &str, options: &PreviewOptions) -> Result<Preview, String> {
+    let format = options.format.as_deref().unwrap_or("svg");
+    if !matches!(format, "svg" | "png") {
+        return Err(format!("Previews can be SVG or PNG, not {}", format));
+    }
+
+    let rendered = render(content, format, &options.export)?;
+    Ok(Preview {
+        data_uri: format!(
+            "data:{};base64,{}",
+            render::content_type(format),
+            general_purpose::STANDARD.encode(&rendered.bytes)
+        ),
+        width: rendered.width,
+        height: rendered.height,
+        warnings: rendered.warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" id="m" width="100%" style="max-width: 800px;" viewBox="0 0 800 400"><rect/></svg>"#;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn fit_scale_takes_the_tightest_limit() {
+        let mut options = ExportOptions::default();
+        assert_eq!(fit_scale(1000.0, 200.0, &options), 1.0);
+        options.max_width = Some(500);
+        assert_eq!(fit_scale(1000.0, 200.0, &options), 0.5);
+        options.max_height = Some(50);
+        assert_eq!(fit_scale(1000.0, 200.0, &options), 0.25);
+    }
+
+    #[test]
+    fn fit_scale_never_enlarges() {
+        let options = ExportOptions {
+            max_width: Some(2000),
+            max_height: Some(2000),
+            fit_to_page: Some(PageSize::A4),
+            ..ExportOptions::default()
+        };
+        assert_eq!(fit_scale(100.0, 100.0, &options), 1.0);
+    }
+
+    #[test]
+    fn fit_to_page_leaves_the_margins_free() {
+        let options = ExportOptions {
+            fit_to_page: Some(PageSize::A4),
+            ..ExportOptions::default()
+        };
+        let scale = fit_scale(1000.0, 1000.0, &options);
+        let (page_width, _) = PageSize::A4.dimensions(Orientation::Portrait);
+        assert!(close(
+            1000.0 * PT_PER_PX * scale,
+            page_width - 2.0 * PAGE_MARGIN_PT
+        ));
+
+        let landscape = ExportOptions {
+            orientation: Orientation::Landscape,
+            ..options
+        };
+        let (_, page_height) = PageSize::A4.dimensions(Orientation::Landscape);
+        let scale = fit_scale(1000.0, 1000.0, &landscape);
+        assert!(close(
+            1000.0 * PT_PER_PX * scale,
+            page_height - 2.0 * PAGE_MARGIN_PT
+        ));
+    }
+
+    #[test]
+    fn reads_the_size_from_the_view_box_first() {
+        assert_eq!(svg_size(SVG), Some((800.0, 400.0)));
+        assert_eq!(
+            svg_size(r#"<svg viewBox="0,0,120,80" width="10" height="10">"#),
+            Some((120.0, 80.0))
+        );
+        assert_eq!(
+            svg_size(r#"<svg viewBox="0 0 0 0" width="120" height="80">"#),
+            Some((120.0, 80.0))
+        );
+        assert_eq!(svg_size("<svg>"), None);
+    }
+
+    #[test]
+    fn resizing_keeps_the_view_box() {
+        let resized = resize_svg(SVG, 400.0, 200.0);
+        assert!(resized.starts_with(r#"<svg width="400" height="200""#));
+        assert!(resized.contains(r#"viewBox="0 0 800 400""#));
+        assert!(!resized.contains("max-width"));
+        assert!(!resized.contains("100%"));
+    }
+
+    #[test]
+    fn reported_size_matches_the_scale() {
+        let options = ExportOptions {
+            max_width: Some(400),
+            ..ExportOptions::default()
+        };
+        let rendered = render(SVG, "svg", &options).unwrap();
+        assert_eq!(rendered.scale, 0.5);
+        assert_eq!((rendered.width, rendered.height), (400.0, 200.0));
+        let svg = String::from_utf8(rendered.bytes).unwrap();
+        assert_eq!(svg_size(&svg), Some((800.0, 400.0)));
+        assert!(svg.starts_with(r#"<svg width="400" height="200""#));
+    }
+
+    #[test]
+    fn page_sizes() {
+        assert_eq!(PageSize::parse("Letter").unwrap(), PageSize::Letter);
+        assert!(PageSize::parse("a3").is_err());
+        assert_eq!(
+            PageSize::Letter.dimensions(Orientation::Landscape),
+            (792.0, 612.0)
+        );
+    }
+
+    #[test]
+    fn tile_count_accounts_for_the_overlap() {
+        assert_eq!(tile_count(500.0, 500.0), 1);
+        assert_eq!(tile_count(100.0, 500.0), 1);
+        assert_eq!(tile_count(501.0, 500.0), 2);
+        assert_eq!(tile_count(2.0 * 500.0 - TILE_OVERLAP_PT, 500.0), 2);
+        assert_eq!(tile_count(2.0 * 500.0 - TILE_OVERLAP_PT + 1.0, 500.0), 3);
+    }
+
+    #[test]
+    fn tile_marks_only_face_neighbours() {
+        let area = (36.0, 36.0, 500.0, 700.0);
+        let count = |marks: String| marks.matches(" l S Q").count();
+        // Four outline edges, plus two ticks per neighbouring side.
+        assert_eq!(count(tile_marks(area, 0, 0, 1, 1)), 4);
+        assert_eq!(count(tile_marks(area, 0, 0, 2, 1)), 6);
+        assert_eq!(count(tile_marks(area, 1, 1, 3, 3)), 12);
+    }
+
+    #[test]
+    fn tiling_needs_a_pdf_without_fit_to_page() {
+        let tiled = ExportOptions {
+            tile: Some(PageSize::A4),
+            ..ExportOptions::default()
+        };
+        let error = render("graph TD", "png", &tiled).err().unwrap();
+        assert_eq!(error, "Tiling is only available for PDF exports");
+
+        let both = ExportOptions {
+            fit_to_page: Some(PageSize::A4),
+            ..tiled
+        };
+        let error = render("graph TD", "pdf", &both).err().unwrap();
+        assert_eq!(error, "Choose either fit to page or tiling, not both");
+    }
+}
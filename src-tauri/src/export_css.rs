@@ -0,0 +1,207 @@
+use cssparser::{ParseError, ParseErrorKind, Parser, ParserInput, Token};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Large enough for any house style; anything bigger is probably a whole
+// stylesheet pasted by mistake.
+const MAX_CSS_BYTES: usize = 64 * 1024;
+
+// Functions whose string arguments are URLs.
+const URL_FUNCTIONS: [&str; 5] = ["url", "src", "image-set", "-webkit-image-set", "image"];
+
+// Properties that attach script to elements in old browsers.
+const SCRIPT_PROPERTIES: [&str; 2] = ["-moz-binding", "behavior"];
+
+// Per-workspace CSS lives in the workspace's hidden settings folder, and
+// per-file CSS in a sidecar such as `flow.mmd.export.css`.
+pub fn workspace_path(workspace: &Path) -> PathBuf {
+    workspace.join(".flowcraft").join("export.css")
+}
+
+pub fn file_path(diagram: &Path) -> PathBuf {
+    let mut name = diagram.as_os_str().to_owned();
+    name.push(".export.css");
+    PathBuf::from(name)
+}
+
+// URLs may only point into the document or carry their data inline;
+// anything else would make exports depend on the network.
+fn check_url(url: &str) -> Result<(), String> {
+    let url = url.trim().to_lowercase();
+    if url.starts_with("data:") || url.starts_with('#') {
+        Ok(())
+    } else {
+        Err("url() can only refer to data: URIs or fragments".to_string())
+    }
+}
+
+// Walks the tokens as a browser reads them, with escapes already resolved,
+// so `\75 rl(` or `@\69mport` are seen as the url and @import they are.
+fn check_tokens<'i>(
+    parser: &mut Parser<'i, '_>,
+    in_url_function: bool,
+) -> Result<(), ParseError<'i, String>> {
+    loop {
+        let token = match parser.next_including_whitespace_and_comments() {
+            Ok(token) => token.clone(),
+            Err(_) => return Ok(()),
+        };
+        let forbidden = |message: &str| Err(parser.new_custom_error(message.to_string()));
+        match &token {
+            Token::AtKeyword(name) if name.eq_ignore_ascii_case("import") => {
+                return forbidden("@import is not allowed; inline the rules instead");
+            }
+            Token::UnquotedUrl(url) => {
+                check_url(url).map_err(|e| parser.new_custom_error(e))?;
+            }
+            Token::QuotedString(value) if in_url_function => {
+                check_url(value).map_err(|e| parser.new_custom_error(e))?;
+            }
+            Token::BadUrl(_) => return forbidden("The CSS has an invalid url()"),
+            Token::BadString(_) => return forbidden("The CSS has an unclosed string"),
+            Token::Ident(name)
+                if SCRIPT_PROPERTIES
+                    .iter()
+                    .any(|property| name.eq_ignore_ascii_case(property)) =>
+            {
+                return Err(parser.new_custom_error(format!("{} is not allowed", name)));
+            }
+            Token::Function(name) if name.eq_ignore_ascii_case("expression") => {
+                return forbidden("CSS expressions are not allowed");
+            }
+            Token::Function(name) => {
+                let name = name.to_lowercase();
+                let url_function = URL_FUNCTIONS.contains(&name.as_str());
+                parser.parse_nested_block(|parser| check_tokens(parser, url_function))?;
+            }
+            Token::ParenthesisBlock => {
+                parser.parse_nested_block(|parser| check_tokens(parser, in_url_function))?;
+            }
+            Token::CurlyBracketBlock | Token::SquareBracketBlock => {
+                parser.parse_nested_block(|parser| check_tokens(parser, false))?;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn skip_char(text: &str) -> &str {
+    let mut chars = text.chars();
+    chars.next();
+    chars.as_str()
+}
+
+// Checks that a snippet only styles the diagram: balanced blocks, no markup
+// that could leave the style element, no scripts and no external resources,
+// which would make exports depend on the network.
+pub fn validate(css: &str) -> Result<(), String> {
+    if css.len() > MAX_CSS_BYTES {
+        return Err(format!(
+            "The CSS is larger than {} KB",
+            MAX_CSS_BYTES / 1024
+        ));
+    }
+    if css.contains('<') {
+        return Err("The CSS cannot contain `<`".to_string());
+    }
+    // It would end the init directive the CSS travels in.
+    if css.contains("%%") {
+        return Err("`%%` is not allowed".to_string());
+    }
+
+    let mut input = ParserInput::new(css);
+    let mut parser = Parser::new(&mut input);
+    check_tokens(&mut parser, false).map_err(|error| match error.kind {
+        ParseErrorKind::Custom(message) => message,
+        ParseErrorKind::Basic(_) => "The CSS could not be read".to_string(),
+    })?;
+
+    let mut depth = 0usize;
+    let mut rest = css;
+    while let Some(index) = rest.find(['{', '}', '/', '"', '\'', '\\']) {
+        let (token, after) = (&rest[index..index + 1], &rest[index + 1..]);
+        rest = after;
+        match token {
+            "{" => depth += 1,
+            "}" => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or("The CSS has a `}` without a matching `{`")?;
+            }
+            // An escaped character is never a brace or quote.
+            "\\" => rest = skip_char(rest),
+            "\"" | "'" => loop {
+                let end = rest
+                    .find([token.chars().next().unwrap_or('"'), '\\'])
+                    .ok_or("The CSS has an unclosed string")?;
+                let (found, after) = (&rest[end..end + 1], &rest[end + 1..]);
+                if found == "\\" {
+                    rest = skip_char(after);
+                } else {
+                    rest = after;
+                    break;
+                }
+            },
+            _ if rest.starts_with('*') => {
+                let end = rest.find("*/").ok_or("The CSS has an unclosed comment")?;
+                rest = &rest[end + 2..];
+            }
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return Err("The CSS has a `{` without a matching `}`".to_string());
+    }
+    Ok(())
+}
+
+pub fn get(path: &Path) -> Result<Option<String>, String> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    fs::read_to_string(path)
+        .map(Some)
+        .map_err(|e| format!("Failed to read export CSS: {}", e))
+}
+
+// Stores the snippet at `path`, or removes it when the snippet is None or
+// blank.
+pub fn set(path: &Path, css: Option<&str>) -> Result<(), String> {
+    match css.filter(|css| !css.trim().is_empty()) {
+        Some(css) => {
+            validate(css)?;
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|e| format!("Failed to create folder: {}", e))?;
+            }
+            fs::write(path, css).map_err(|e| format!("Failed to write export CSS: {}", e))
+        }
+        None if path.exists() => {
+            fs::remove_file(path).map_err(|e| format!("Failed to remove export CSS: {}", e))
+        }
+        None => Ok(()),
+    }
+}
+
+// The CSS an export of `diagram` gets: the workspace's first, then the
+// file's own, so file rules win. Workspace CSS applies to unsaved diagrams
+// and to files inside the workspace.
+pub fn resolve(diagram: Option<&Path>, workspace: Option<&Path>) -> Result<Option<String>, String> {
+    let mut parts = Vec::new();
+    if let Some(workspace) = workspace {
+        let inside = match diagram {
+            Some(diagram) => diagram.starts_with(workspace),
+            None => true,
+        };
+        if inside {
+            parts.extend(get(&workspace_path(workspace))?);
+        }
+    }
+    if let Some(diagram) = diagram {
+        parts.extend(get(&file_path(diagram))?);
+    }
+    // Stored files may have been edited by hand since they were saved.
+    for css in &parts {
+        validate(css)?;
+    }
+    Ok((!parts.is_empty()).then(|| parts.join("\n")))
+}
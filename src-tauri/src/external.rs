@@ -0,0 +1,236 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+// Open documents watched for changes made outside the editor, with the
+// modification time last seen for each.
+static DOCUMENTS: Mutex<Option<HashMap<PathBuf, Option<SystemTime>>>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DocumentEvent {
+    Changed { path: String },
+    Removed { path: String },
+}
+
+pub struct DocumentWatchHandle {
+    stop: Arc<AtomicBool>,
+}
+
+impl DocumentWatchHandle {
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for DocumentWatchHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+pub fn watch_document(path: &Path) {
+    if let Ok(mut documents) = DOCUMENTS.lock() {
+        documents
+            .get_or_insert_with(HashMap::new)
+            .insert(path.to_path_buf(), modified(path));
+    }
+}
+
+pub fn unwatch_document(path: &Path) {
+    if let Ok(mut documents) = DOCUMENTS.lock() {
+        if let Some(documents) = documents.as_mut() {
+            documents.remove(path);
+        }
+    }
+}
+
+// Keeps watching a document under its new name after it was renamed.
+pub fn rename_document(from: &Path, to: &Path) {
+    if let Ok(mut documents) = DOCUMENTS.lock() {
+        if let Some(documents) = documents.as_mut() {
+            if documents.remove(from).is_some() {
+                documents.insert(to.to_path_buf(), modified(to));
+            }
+        }
+    }
+}
+
+// Called after the editor itself writes `path`, so its own saves are not
+// reported as external edits.
+pub fn note_saved(path: &Path) {
+    if let Ok(mut documents) = DOCUMENTS.lock() {
+        if let Some(seen) = documents
+            .as_mut()
+            .and_then(|documents| documents.get_mut(path))
+        {
+            *seen = modified(path);
+        }
+    }
+}
+
+pub fn spawn<F>(on_event: F) -> Result<DocumentWatchHandle, String>
+where
+    F: Fn(DocumentEvent) + Send + 'static,
+{
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+
+    thread::Builder::new()
+        .name("document-watch".to_string())
+        .spawn(move || run(&thread_stop, on_event))
+        .map_err(|e| format!("Failed to start document watcher: {}", e))?;
+
+    Ok(DocumentWatchHandle { stop })
+}
+
+fn run<F>(stop: &AtomicBool, on_event: F)
+where
+    F: Fn(DocumentEvent),
+{
+    while !stop.load(Ordering::SeqCst) {
+        let mut events = Vec::new();
+        if let Ok(mut documents) = DOCUMENTS.lock() {
+            for (path, seen) in documents.iter_mut().flatten() {
+                let current = modified(path);
+                if current == *seen {
+                    continue;
+                }
+                let path_str = path.to_string_lossy().to_string();
+                events.push(match current {
+                    Some(_) => DocumentEvent::Changed { path: path_str },
+                    None => DocumentEvent::Removed { path: path_str },
+                });
+                *seen = current;
+            }
+        }
+        // Emit outside the lock so handlers can watch or unwatch documents.
+        for event in events {
+            on_event(event);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn launch(command: &mut Command) -> Result<(), String> {
+    command
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to launch {:?}: {}", command.get_program(), e))
+}
+
+// Shows `path` selected in Finder, Explorer or the desktop's file manager.
+#[cfg(target_os = "macos")]
+pub fn reveal(path: &Path) -> Result<(), String> {
+    launch(Command::new("open").arg("-R").arg(path))
+}
+
+#[cfg(windows)]
+pub fn reveal(path: &Path) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+    // Explorer parses its own command line, so the path is quoted by hand.
+    launch(Command::new("explorer.exe").raw_arg(format!("/select,\"{}\"", path.display())))
+}
+
+// File managers implementing the freedesktop interface can select the file;
+// others just get its folder opened.
+#[cfg(not(any(target_os = "macos", windows)))]
+pub fn reveal(path: &Path) -> Result<(), String> {
+    let shown = Command::new("dbus-send")
+        .args([
+            "--session",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", file_uri(path)))
+        .arg("string:")
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+    if shown {
+        return Ok(());
+    }
+    launch(Command::new("xdg-open").arg(path.parent().unwrap_or(path)))
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+// The desktop entry xdg-open would hand `path` to, like
+// `flowcraft-studio.desktop`.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn default_application(path: &Path) -> Option<String> {
+    let query = |args: &[&std::ffi::OsStr]| {
+        Command::new("xdg-mime")
+            .arg("query")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let mime = query(&["filetype".as_ref(), path.as_os_str()])?;
+    query(&["default".as_ref(), mime.as_ref()])
+}
+
+// Opens `path` in `editor`, or in the system's default text editor when none
+// is configured.
+pub fn open_in_editor(path: &Path, editor: Option<&Path>) -> Result<(), String> {
+    // Once the app is installed it is the default for diagrams, and xdg-open
+    // would only open the file here again.
+    #[cfg(not(any(target_os = "macos", windows)))]
+    if editor.is_none()
+        && default_application(path)
+            .map(|app| app.to_lowercase().contains("flowcraft"))
+            .unwrap_or(false)
+    {
+        return Err("No external editor is configured; choose one in the settings".to_string());
+    }
+    let mut command = match editor {
+        #[cfg(target_os = "macos")]
+        Some(editor) if editor.extension().map(|ext| ext == "app").unwrap_or(false) => {
+            let mut command = Command::new("open");
+            command.arg("-a").arg(editor);
+            command
+        }
+        Some(editor) => Command::new(editor),
+        #[cfg(target_os = "macos")]
+        None => {
+            let mut command = Command::new("open");
+            command.arg("-t");
+            command
+        }
+        #[cfg(windows)]
+        None => Command::new("notepad.exe"),
+        #[cfg(not(any(target_os = "macos", windows)))]
+        None => Command::new("xdg-open"),
+    };
+    launch(command.arg(path))
+}
@@ -0,0 +1,298 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serde_yaml::{Mapping, Value};
+use std::path::Path;
+
+use crate::export::{self, ExportOptions};
+use crate::mermaid_config::MermaidConfig;
+use crate::render::RenderConfig;
+use crate::{render, themes};
+
+// The frontmatter key whose block the editor manages. Mermaid ignores keys
+// it does not know, so the settings travel with the diagram.
+pub const KEY: &str = "flowcraft";
+
+// Checks a file can turn off for itself.
+pub const LINT_RULES: [&str; 4] = ["fontawesome", "spelling", "links", "images"];
+
+// Defaults for exporting this diagram. The export dialog starts from them,
+// and workspace exports apply them since there is no dialog.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ExportDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_height: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embed_fonts: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inline_images: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expand_emoji: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimize_png: Option<u8>,
+}
+
+impl ExportDefaults {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // Sets what the file sets; the format is up to the caller.
+    pub fn apply(&self, options: &mut ExportOptions) {
+        if self.max_width.is_some() {
+            options.max_width = self.max_width;
+        }
+        if self.max_height.is_some() {
+            options.max_height = self.max_height;
+        }
+        if let Some(embed_fonts) = self.embed_fonts {
+            options.embed_fonts = embed_fonts;
+        }
+        if let Some(inline_images) = self.inline_images {
+            options.inline_images = inline_images;
+        }
+        if let Some(expand_emoji) = self.expand_emoji {
+            options.expand_emoji = expand_emoji;
+        }
+        if self.optimize_png.is_some() {
+            options.optimize_png = self.optimize_png;
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LintOverrides {
+    // Rules from LINT_RULES that are off for this file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disable: Vec<String>,
+}
+
+impl LintOverrides {
+    fn is_empty(&self) -> bool {
+        self.disable.is_empty()
+    }
+}
+
+// Unknown fields are rejected, as in theme files, so a typo in hand-edited
+// frontmatter is reported instead of ignored.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FileSettings {
+    // Theme id, in place of the editor's theme.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    #[serde(default, skip_serializing_if = "ExportDefaults::is_empty")]
+    pub export: ExportDefaults,
+    #[serde(default, skip_serializing_if = "LintOverrides::is_empty")]
+    pub lint: LintOverrides,
+}
+
+impl FileSettings {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn allows(&self, rule: &str) -> bool {
+        !self.lint.disable.iter().any(|disabled| disabled == rule)
+    }
+}
+
+// Index of the closing `---` of the frontmatter block, if the content
+// starts with one.
+fn closing(lines: &[&str]) -> Option<usize> {
+    if lines.first().map(|line| line.trim()) != Some("---") {
+        return None;
+    }
+    lines
+        .iter()
+        .skip(1)
+        .position(|line| line.trim() == "---")
+        .map(|index| index + 1)
+}
+
+fn parse(frontmatter: &[&str]) -> Result<Value, String> {
+    serde_yaml::from_str(&frontmatter.join("\n"))
+        .map_err(|e| format!("Failed to parse frontmatter: {}", e))
+}
+
+// Reads the settings from the frontmatter; a diagram without any gets the
+// defaults.
+pub fn get(content: &str) -> Result<FileSettings, String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let Some(closing) = closing(&lines) else {
+        return Ok(FileSettings::default());
+    };
+    match parse(&lines[1..closing])?.get(KEY) {
+        None | Some(Value::Null) => Ok(FileSettings::default()),
+        Some(settings) => serde_yaml::from_value(settings.clone())
+            .map_err(|e| format!("Invalid {} settings in frontmatter: {}", KEY, e)),
+    }
+}
+
+pub fn validate(settings: &FileSettings) -> Result<(), String> {
+    if let Some(theme) = &settings.theme {
+        themes::get(theme)?;
+    }
+    if let Some(format) = &settings.export.format {
+        if !export::FORMATS.contains(&format.as_str()) {
+            return Err(format!("Unsupported format: {}", format));
+        }
+    }
+    if let Some(level) = settings.export.optimize_png {
+        export::validate_png_optimization(level)?;
+    }
+    for rule in &settings.lint.disable {
+        if !LINT_RULES.contains(&rule.as_str()) {
+            return Err(format!(
+                "Unknown lint rule `{}`; use one of {}",
+                rule,
+                LINT_RULES.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Writes the settings into the frontmatter, replacing the managed block and
+// leaving the rest of the frontmatter as it is. Empty settings remove the
+// block, and the frontmatter with it when nothing else is left.
+pub fn set(content: &str, settings: &FileSettings) -> Result<String, String> {
+    validate(settings)?;
+
+    let block = if settings.is_empty() {
+        String::new()
+    } else {
+        let mut mapping = Mapping::new();
+        mapping.insert(
+            Value::from(KEY),
+            serde_yaml::to_value(settings)
+                .map_err(|e| format!("Failed to serialize settings: {}", e))?,
+        );
+        serde_yaml::to_string(&mapping)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?
+    };
+    let block: Vec<&str> = block.lines().collect();
+
+    let lines: Vec<&str> = content.lines().collect();
+    let mut output: Vec<&str> = match closing(&lines) {
+        Some(closing) => {
+            // Frontmatter that is not valid YAML cannot be edited safely.
+            parse(&lines[1..closing])?;
+            let mut frontmatter = lines[1..closing].to_vec();
+            // The managed block runs until the next top-level key.
+            let start = frontmatter
+                .iter()
+                .position(|line| line.starts_with(&format!("{}:", KEY)));
+            match start {
+                Some(start) => {
+                    let end = frontmatter[start + 1..]
+                        .iter()
+                        .position(|line| !line.is_empty() && !line.starts_with([' ', '\t']))
+                        .map_or(frontmatter.len(), |end| start + 1 + end);
+                    frontmatter.splice(start..end, block);
+                }
+                None => frontmatter.extend(block),
+            }
+            let mut output = Vec::new();
+            if frontmatter.iter().any(|line| !line.trim().is_empty()) {
+                output.push("---");
+                output.extend(frontmatter);
+                output.push("---");
+            }
+            output.extend(&lines[closing + 1..]);
+            output
+        }
+        None if block.is_empty() => lines,
+        None => {
+            let mut output = vec!["---"];
+            output.extend(block);
+            output.push("---");
+            output.extend(lines);
+            output
+        }
+    };
+
+    if content.ends_with('\n') {
+        output.push("");
+    }
+    Ok(output.join("\n"))
+}
+
+// The theme an export uses, and where it was picked. The most specific
+// wins, so the export dialog need not pick it again.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "source", content = "theme", rename_all = "snake_case")]
+pub enum ExportTheme {
+    // A theme id in the file's own settings.
+    File(String),
+    // Mermaid's `config.theme` in the frontmatter, which Mermaid Live and
+    // GitHub honour too.
+    Mermaid(String),
+    // The theme id set for the workspace the file is in.
+    Workspace(String),
+    // The editor's theme.
+    App,
+}
+
+// Mermaid's own theme from the frontmatter, if it names one Mermaid ships.
+fn mermaid_theme(content: &str) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let frontmatter = parse(&lines[1..closing(&lines)?]).ok()?;
+    let theme = frontmatter.get("config")?.get("theme")?.as_str()?;
+    themes::MERMAID_THEMES
+        .contains(&theme)
+        .then(|| theme.to_string())
+}
+
+// Works out the export theme of `content`, saved at `source` if it was.
+// Like workspace CSS, the workspace's theme applies to unsaved diagrams and
+// to files inside the workspace.
+pub fn export_theme(content: &str, source: Option<&Path>, workspace: Option<&Path>) -> ExportTheme {
+    if let Some(id) = get(content).ok().and_then(|settings| settings.theme) {
+        return ExportTheme::File(id);
+    }
+    if let Some(theme) = mermaid_theme(content) {
+        return ExportTheme::Mermaid(theme);
+    }
+    let workspace = workspace.filter(|workspace| match source {
+        Some(source) => source.starts_with(workspace),
+        None => true,
+    });
+    match workspace.and_then(themes::workspace_theme) {
+        Some(id) => ExportTheme::Workspace(id),
+        None => ExportTheme::App,
+    }
+}
+
+// Runs `work` with the export theme, with the user's Mermaid settings on
+// top. A theme that does not exist here is ignored, so diagrams from other
+// machines still render.
+pub fn with_theme<T>(
+    theme: &ExportTheme,
+    mermaid_config: &MermaidConfig,
+    work: impl FnOnce() -> T,
+) -> T {
+    let mut config = match theme {
+        ExportTheme::File(id) | ExportTheme::Workspace(id) => match themes::get(id) {
+            Ok(theme) => theme.render_config(),
+            Err(e) => {
+                tracing::warn!(theme = %id, error = %e, "ignoring the diagram's theme");
+                return work();
+            }
+        },
+        // Only the Mermaid theme, without the editor theme's variables and
+        // background, so the export looks as it does elsewhere.
+        ExportTheme::Mermaid(theme) => RenderConfig {
+            mermaid: json!({ "theme": theme }),
+            background: None,
+        },
+        ExportTheme::App => return work(),
+    };
+    mermaid_config.apply(&mut config.mermaid);
+    render::with_config(config, work)
+}
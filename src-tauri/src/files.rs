@@ -0,0 +1,277 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+use crate::{encryption, is_diagram_file, is_openable_file, model, paths};
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileInfo {
+    pub path: String,
+    pub size: u64,
+    // RFC 3339 timestamps; `created` is missing on filesystems that do not
+    // record it.
+    pub created: Option<String>,
+    pub modified: Option<String>,
+    pub encrypted: bool,
+    // Encrypted files cannot be inspected, so these stay empty for them.
+    pub diagram_type: Option<String>,
+    pub nodes: Option<usize>,
+    pub edges: Option<usize>,
+    // None when git is not installed.
+    pub git_tracked: Option<bool>,
+}
+
+// Sidecars are files next to a diagram whose names extend the diagram's,
+// like `flow.mmd.comments.json` for `flow.mmd`. They follow the diagram
+// when it is copied.
+pub fn sidecars(path: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.", name.to_string_lossy());
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut found: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .filter(|entry| {
+            entry
+                .file_type()
+                .map(|kind| kind.is_file())
+                .unwrap_or(false)
+        })
+        .map(|entry| entry.path())
+        .collect();
+    found.sort();
+    found
+}
+
+// Diagrams under `dir` with their metadata. Hidden folders like `.git` are
+// skipped, as is `skip`, a folder inside `dir` that holds generated files.
+pub fn collect_diagrams(dir: &Path, skip: Option<&Path>) -> Vec<(PathBuf, fs::Metadata)> {
+    let mut found = Vec::new();
+    walk_diagrams(dir, skip, &mut found);
+    found
+}
+
+fn walk_diagrams(dir: &Path, skip: Option<&Path>, found: &mut Vec<(PathBuf, fs::Metadata)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            if !entry.file_name().to_string_lossy().starts_with('.') && skip != Some(path.as_path())
+            {
+                walk_diagrams(&path, skip, found);
+            }
+        } else if is_diagram_file(&path) {
+            found.push((path, metadata));
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+// `flow.mmd` becomes `flow copy.mmd`, then `flow copy 2.mmd` and so on.
+fn copy_name(path: &Path) -> Result<PathBuf, String> {
+    let stem = path
+        .file_stem()
+        .ok_or_else(|| format!("Invalid path: {}", path.display()))?
+        .to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..1000)
+        .map(|index| {
+            let suffix = if index == 1 {
+                " copy".to_string()
+            } else {
+                format!(" copy {}", index)
+            };
+            path.with_file_name(format!("{}{}{}", stem, suffix, extension))
+        })
+        .find(|candidate| !candidate.exists())
+        .ok_or_else(|| format!("Too many copies of {}", path.display()))
+}
+
+// Resolves a file name from the frontend to a sibling of `path`. Only bare
+// names are accepted, and the copy keeps the original's extension when the
+// name has none.
+fn named_copy(path: &Path, new_name: &str) -> Result<PathBuf, String> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() || new_name == "." || new_name == ".." || new_name.contains(['/', '\\'])
+    {
+        return Err(format!("Invalid file name: {}", new_name));
+    }
+
+    let mut target = path.with_file_name(new_name);
+    if target.extension().is_none() {
+        if let Some(extension) = path.extension() {
+            target.set_extension(extension);
+        }
+    }
+    if target.exists() {
+        return Err(format!("{} already exists", target.display()));
+    }
+    Ok(target)
+}
+
+// Copies the diagram at `path` and its sidecars next to it, and returns the
+// new diagram's path.
+pub fn duplicate(path: &Path, new_name: Option<&str>) -> Result<PathBuf, String> {
+    if !path.is_file() {
+        return Err(format!("Not a file: {}", path.display()));
+    }
+    let target = match new_name {
+        Some(new_name) => named_copy(path, new_name)?,
+        None => copy_name(path)?,
+    };
+    // The frontend picks the name, so it must not be able to plant anything
+    // but a diagram.
+    if !is_openable_file(&target) {
+        return Err(format!("Not a diagram file: {}", target.display()));
+    }
+    if paths::is_protected(&target) {
+        return Err(format!("Access to {} is not allowed", target.display()));
+    }
+
+    fs::copy(path, &target).map_err(|e| format!("Failed to copy file: {}", e))?;
+
+    let name = file_name(path);
+    let target_name = file_name(&target);
+    for sidecar in sidecars(path) {
+        let copy = target.with_file_name(file_name(&sidecar).replacen(&name, &target_name, 1));
+        if copy.exists() {
+            continue;
+        }
+        if let Err(e) = fs::copy(&sidecar, &copy) {
+            tracing::warn!(path = %sidecar.display(), error = %e, "failed to copy sidecar");
+        }
+    }
+
+    Ok(target)
+}
+
+// Renames within a volume, falling back to copy and delete across volumes.
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map_err(|e| format!("Failed to move {}: {}", from.display(), e))?;
+    if let Err(e) = fs::remove_file(from) {
+        let _ = fs::remove_file(to);
+        return Err(format!("Failed to move {}: {}", from.display(), e));
+    }
+    Ok(())
+}
+
+// Moves the diagram at `from` to `to`, sidecars included. Either everything
+// moves or, after undoing the moves already made, nothing does.
+pub fn rename(from: &Path, to: &Path) -> Result<(), String> {
+    if !from.is_file() {
+        return Err(format!("Not a file: {}", from.display()));
+    }
+    if from == to {
+        return Ok(());
+    }
+    if !is_openable_file(to) {
+        return Err(format!("Not a diagram file: {}", to.display()));
+    }
+    if paths::is_protected(to) {
+        return Err(format!("Access to {} is not allowed", to.display()));
+    }
+
+    let name = file_name(from);
+    let target_name = file_name(to);
+    let mut moves = vec![(from.to_path_buf(), to.to_path_buf())];
+    for sidecar in sidecars(from) {
+        let renamed = file_name(&sidecar).replacen(&name, &target_name, 1);
+        moves.push((sidecar, to.with_file_name(renamed)));
+    }
+    if let Some((_, existing)) = moves.iter().find(|(_, target)| target.exists()) {
+        return Err(format!("{} already exists", existing.display()));
+    }
+
+    for (index, (source, target)) in moves.iter().enumerate() {
+        if let Err(e) = move_file(source, target) {
+            for (source, target) in moves[..index].iter().rev() {
+                if let Err(e) = move_file(target, source) {
+                    tracing::error!(path = %target.display(), error = %e, "failed to undo rename");
+                }
+            }
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+fn timestamp(time: std::io::Result<SystemTime>) -> Option<String> {
+    time.ok()
+        .map(|time| DateTime::<Utc>::from(time).to_rfc3339())
+}
+
+// Asks git whether the file is tracked in whatever repository contains it.
+fn git_tracked(path: &Path) -> Option<bool> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Some(false);
+    };
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["ls-files", "--error-unmatch", "--"])
+        .arg(name)
+        .output()
+        .ok()?;
+    Some(output.status.success())
+}
+
+pub fn info(path: &Path) -> Result<FileInfo, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    if !metadata.is_file() {
+        return Err(format!("Not a file: {}", path.display()));
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let encrypted = encryption::is_encrypted(&content);
+    let (diagram_type, nodes, edges) = if encrypted {
+        (None, None, None)
+    } else {
+        let parsed = model::parse_cached(&content);
+        (
+            parsed.model.diagram_type.clone(),
+            Some(parsed.nodes.len()),
+            Some(parsed.edges.len()),
+        )
+    };
+
+    Ok(FileInfo {
+        path: path.to_string_lossy().to_string(),
+        size: metadata.len(),
+        created: timestamp(metadata.created()),
+        modified: timestamp(metadata.modified()),
+        encrypted,
+        diagram_type,
+        nodes,
+        edges,
+        git_tracked: git_tracked(path),
+    })
+}
@@ -0,0 +1,215 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::spelling;
+
+// The free Font Awesome 6 icons, one per line: the name, its styles (`s`
+// solid, `r` regular, `b` brands) and the older names it still answers to.
+const ICONS: &str = include_str!("../fontawesome/icons.txt");
+const MAX_SUGGESTIONS: usize = 3;
+
+struct Icon {
+    styles: &'static str,
+    // Set for older names, to the current one.
+    renamed_to: Option<&'static str>,
+}
+
+static TABLE: OnceLock<HashMap<&'static str, Icon>> = OnceLock::new();
+
+fn table() -> &'static HashMap<&'static str, Icon> {
+    TABLE.get_or_init(|| {
+        let mut table = HashMap::new();
+        for line in ICONS.lines() {
+            let mut fields = line.split_whitespace();
+            let (Some(name), Some(styles)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            for alias in fields {
+                table.insert(
+                    alias,
+                    Icon {
+                        styles,
+                        renamed_to: Some(name),
+                    },
+                );
+            }
+            table.insert(
+                name,
+                Icon {
+                    styles,
+                    renamed_to: None,
+                },
+            );
+        }
+        table
+    })
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct IconIssue {
+    pub line: usize,
+    pub column: usize,
+    // The class as written, such as `fa-usr`.
+    pub class: String,
+    pub message: String,
+    // Full replacements for the reference, such as `fab:fa-github`.
+    pub suggestions: Vec<String>,
+}
+
+// The style a prefix asks for, and the prefix to write for a style.
+fn style(prefix: &str) -> Option<char> {
+    match prefix {
+        "fas" | "fa-solid" => Some('s'),
+        "far" | "fa-regular" => Some('r'),
+        "fab" | "fa-brands" => Some('b'),
+        _ => None,
+    }
+}
+
+fn prefix(style: char) -> &'static str {
+    match style {
+        'r' => "far",
+        'b' => "fab",
+        _ => "fas",
+    }
+}
+
+// Close names first, then longer ones starting with the class, so `fa-arrow`
+// suggests `fa-arrow-up`. Shorter names come first among equals, and only
+// current names are suggested.
+fn suggestions(name: &str) -> Vec<&'static str> {
+    let limit = if name.len() <= 5 { 1 } else { 2 };
+    let mut close: Vec<(usize, usize, &str)> = table()
+        .iter()
+        .filter(|(_, icon)| icon.renamed_to.is_none())
+        .filter_map(|(candidate, _)| {
+            spelling::distance(name, candidate, limit)
+                .or_else(|| candidate.starts_with(&format!("{}-", name)).then_some(3))
+                .map(|distance| (distance, candidate.len(), *candidate))
+        })
+        .collect();
+    close.sort_unstable();
+    close
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(.., candidate)| candidate)
+        .collect()
+}
+
+// The prefix as written when the icon has that style, otherwise the one of
+// its first style. A plain `fa` prefix renders solid icons.
+fn fit<'a>(prefix: &'a str, styles: &str) -> &'a str {
+    if styles.contains(style(prefix).unwrap_or('s')) {
+        prefix
+    } else {
+        self::prefix(styles.chars().next().unwrap_or('s'))
+    }
+}
+
+fn reference(prefix: &str, name: &str, mindmap: bool) -> String {
+    if mindmap {
+        format!("{} fa-{}", prefix, name)
+    } else {
+        format!("{}:fa-{}", prefix, name)
+    }
+}
+
+// The problem with one reference and its replacements. Light, thin and
+// duotone icons need a Pro kit, and `fak` icons come from the user's own
+// kit, so neither is looked up.
+fn inspect(prefix: &str, name: &str, mindmap: bool) -> Option<(String, Vec<String>)> {
+    if matches!(prefix, "fal" | "fad" | "fat") {
+        return Some((
+            format!(
+                "`{}` icons need a Font Awesome Pro kit and render blank without one",
+                prefix
+            ),
+            vec![reference("fas", name, mindmap)],
+        ));
+    }
+    if prefix == "fak" {
+        return None;
+    }
+
+    let Some(icon) = table().get(name) else {
+        let suggestions = suggestions(name)
+            .into_iter()
+            .map(|candidate| reference(fit(prefix, table()[candidate].styles), candidate, mindmap))
+            .collect();
+        return Some((
+            format!("`fa-{}` is not a Font Awesome icon", name),
+            suggestions,
+        ));
+    };
+
+    let fitted = fit(prefix, icon.styles);
+    if fitted != prefix {
+        let message = match fitted {
+            "fab" => format!("`fa-{}` is a brand icon", name),
+            _ if style(prefix) == Some('r') => format!("`fa-{}` has no free regular style", name),
+            _ => format!("`fa-{}` is not a brand icon", name),
+        };
+        return Some((message, vec![reference(fitted, name, mindmap)]));
+    }
+    icon.renamed_to.map(|current| {
+        (
+            format!("`fa-{}` is an old name of `fa-{}`", name, current),
+            vec![reference(prefix, current, mindmap)],
+        )
+    })
+}
+
+// Checks Font Awesome references in labels, `fa:fa-user` in flowcharts and
+// `::icon(fa fa-user)` in mindmaps, against the free icon set. Unknown
+// icons render blank, so each comes with the closest names.
+pub fn check(content: &str) -> Vec<IconIssue> {
+    let label = Regex::new(r"\b(fa[bdklrst]?):fa-([a-z0-9-]+)").unwrap();
+    let mindmap = Regex::new(
+        r"::icon\(\s*(fa[bdklrst]?|fa-solid|fa-regular|fa-brands)\s+fa-([a-z0-9-]+)\s*\)",
+    )
+    .unwrap();
+    let mut issues = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim_start().starts_with("%%") {
+            continue;
+        }
+        let found = label
+            .captures_iter(line)
+            .map(|caps| (caps, false))
+            .chain(mindmap.captures_iter(line).map(|caps| (caps, true)));
+        for (caps, mindmap) in found {
+            let name = &caps[2];
+            if let Some((message, suggestions)) = inspect(&caps[1], name, mindmap) {
+                issues.push(IconIssue {
+                    line: index + 1,
+                    column: line[..caps.get(1).unwrap().start()].chars().count() + 1,
+                    class: format!("fa-{}", name),
+                    message,
+                    suggestions,
+                });
+            }
+        }
+    }
+    issues
+}
+
+// The lint rule, as validation warnings.
+pub fn validate(content: &str, warnings: &mut Vec<String>) {
+    for issue in check(content) {
+        let hint = match issue.suggestions.as_slice() {
+            [] => String::new(),
+            [only] => format!("; did you mean `{}`?", only),
+            several => format!(
+                "; did you mean {}?",
+                several
+                    .iter()
+                    .map(|suggestion| format!("`{}`", suggestion))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        warnings.push(format!("Line {}: {}{}", issue.line, issue.message, hint));
+    }
+}
@@ -0,0 +1,94 @@
+const INDENT: &str = "    ";
+
+const BLOCK_OPENERS: [&str; 9] = [
+    "subgraph", "loop", "alt", "opt", "par", "critical", "break", "rect", "box",
+];
+
+const BLOCK_CONTINUATIONS: [&str; 3] = ["else", "and", "option"];
+
+pub fn format_diagram(content: &str) -> String {
+    let mut output: Vec<String> = Vec::new();
+    let mut lines = content.lines().map(str::trim_end).peekable();
+
+    while lines.peek().is_some_and(|line| line.trim().is_empty()) {
+        lines.next();
+    }
+
+    // Frontmatter is YAML and is kept exactly as written.
+    if lines.peek().is_some_and(|line| line.trim() == "---") {
+        output.push("---".to_string());
+        lines.next();
+        for line in lines.by_ref() {
+            if line.trim() == "---" {
+                output.push("---".to_string());
+                break;
+            }
+            output.push(line.to_string());
+        }
+    }
+
+    let mut header: Option<String> = None;
+    let mut depth = 0usize;
+    let mut previous_blank = false;
+
+    for line in lines {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            if header.is_some() && !previous_blank {
+                output.push(String::new());
+            }
+            previous_blank = true;
+            continue;
+        }
+        previous_blank = false;
+
+        if header.is_none() {
+            if trimmed.starts_with("%%") {
+                output.push(trimmed.to_string());
+                continue;
+            }
+            header = Some(trimmed.to_lowercase());
+            output.push(trimmed.to_string());
+            continue;
+        }
+
+        // Mindmaps encode their hierarchy through indentation, so only
+        // trailing whitespace is normalized for them.
+        if header.as_deref().is_some_and(|h| h.starts_with("mindmap")) {
+            output.push(line.to_string());
+            continue;
+        }
+
+        let keyword = trimmed.split_whitespace().next().unwrap_or_default();
+
+        if keyword == "end" || trimmed == "}" {
+            depth = depth.saturating_sub(1);
+            output.push(format!("{}{}", INDENT.repeat(depth + 1), trimmed));
+            continue;
+        }
+
+        if BLOCK_CONTINUATIONS.contains(&keyword) && depth > 0 {
+            output.push(format!("{}{}", INDENT.repeat(depth), trimmed));
+            continue;
+        }
+
+        output.push(format!("{}{}", INDENT.repeat(depth + 1), trimmed));
+
+        if BLOCK_OPENERS.contains(&keyword) || trimmed.ends_with('{') {
+            depth += 1;
+        }
+    }
+
+    while output.last().is_some_and(|line| line.is_empty()) {
+        output.pop();
+    }
+
+    if output.is_empty() {
+        return String::new();
+    }
+
+    let mut formatted = output.join("\n");
+    formatted.push('\n');
+    formatted
+}
@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::{importers, is_diagram_file, secrets};
+
+// A GitHub personal access token with the `gist` scope, kept in secure
+// storage, see `secrets`.
+pub const TOKEN_SECRET: &str = "github-token";
+
+const API_URL: &str = "https://api.github.com/gists";
+const RAW_HOST: &str = "gist.githubusercontent.com";
+const DEFAULT_FILE_NAME: &str = "diagram.mmd";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Public,
+    // Unlisted: anyone with the link can see it.
+    Secret,
+}
+
+#[derive(Debug, Deserialize)]
+struct GistResponse {
+    html_url: String,
+    #[serde(default)]
+    files: BTreeMap<String, GistFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GistFile {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    truncated: bool,
+    #[serde(default)]
+    raw_url: Option<String>,
+}
+
+fn request(
+    client: &reqwest::Client,
+    method: reqwest::Method,
+    url: &str,
+) -> reqwest::RequestBuilder {
+    client
+        .request(method, url)
+        .header(
+            "User-Agent",
+            format!("flowcraft-studio/{}", env!("CARGO_PKG_VERSION")),
+        )
+        .header("Accept", "application/vnd.github+json")
+        .timeout(REQUEST_TIMEOUT)
+}
+
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, String> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    Err(match status.as_u16() {
+        401 => "GitHub rejected the token; check that it is valid".to_string(),
+        403 | 404 if response.url().path() == "/gists" => {
+            "The GitHub token needs the `gist` scope".to_string()
+        }
+        404 => "Gist not found".to_string(),
+        code => format!("GitHub returned {}", code),
+    })
+}
+
+// Uploads `content` as a new gist and returns its URL.
+pub async fn share(
+    content: &str,
+    visibility: Visibility,
+    file_name: Option<&str>,
+) -> Result<String, String> {
+    let token = secrets::get_secret(TOKEN_SECRET)?
+        .ok_or("No GitHub token configured; add one in the settings first")?;
+    let file_name = file_name
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .unwrap_or(DEFAULT_FILE_NAME);
+
+    let client = reqwest::Client::new();
+    let response = request(&client, reqwest::Method::POST, API_URL)
+        .bearer_auth(token)
+        .json(&json!({
+            "description": "Mermaid diagram shared from FlowCraft Studio",
+            "public": visibility == Visibility::Public,
+            "files": { file_name: { "content": content } },
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach GitHub: {}", e))?;
+    let gist: GistResponse = check_status(response)
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to read GitHub response: {}", e))?;
+    Ok(gist.html_url)
+}
+
+fn is_gist_id(value: &str) -> bool {
+    value.len() >= 20 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// Accepts a bare gist id or a gist.github.com / gist.githubusercontent.com
+// link, with or without the owner, revision or file anchor.
+pub fn gist_id(url: &str) -> Result<String, String> {
+    let url = url.trim();
+    if is_gist_id(url) {
+        return Ok(url.to_lowercase());
+    }
+    let parsed = reqwest::Url::parse(url).map_err(|_| format!("Not a gist link: {}", url))?;
+    if !matches!(parsed.host_str(), Some("gist.github.com") | Some(RAW_HOST)) {
+        return Err(format!("Not a gist link: {}", url));
+    }
+    parsed
+        .path_segments()
+        .into_iter()
+        .flatten()
+        .take(2)
+        .filter(|segment| is_gist_id(segment))
+        .last()
+        .map(str::to_lowercase)
+        .ok_or_else(|| format!("Not a gist link: {}", url))
+}
+
+// Gist files over a megabyte come back truncated and have to be fetched raw.
+async fn file_content(client: &reqwest::Client, file: &GistFile) -> Result<String, String> {
+    if let (Some(content), false) = (&file.content, file.truncated) {
+        return Ok(content.clone());
+    }
+    let raw_url = file
+        .raw_url
+        .as_deref()
+        .filter(|raw_url| {
+            reqwest::Url::parse(raw_url)
+                .map(|url| url.scheme() == "https" && url.host_str() == Some(RAW_HOST))
+                .unwrap_or(false)
+        })
+        .ok_or("Gist file has no downloadable content")?;
+    check_status(
+        request(client, reqwest::Method::GET, raw_url)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach GitHub: {}", e))?,
+    )
+    .await?
+    .text()
+    .await
+    .map_err(|e| format!("Failed to download gist: {}", e))
+}
+
+// Downloads the gist at `url` and imports its diagram file, or its first
+// file when none has a diagram extension.
+pub async fn import(url: &str) -> Result<importers::ImportResult, String> {
+    let id = gist_id(url)?;
+    let client = reqwest::Client::new();
+    let mut request = request(
+        &client,
+        reqwest::Method::GET,
+        &format!("{}/{}", API_URL, id),
+    );
+    // Not required, but lifts the anonymous rate limit.
+    if let Some(token) = secrets::get_secret(TOKEN_SECRET)? {
+        request = request.bearer_auth(token);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach GitHub: {}", e))?;
+    let gist: GistResponse = check_status(response)
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to read GitHub response: {}", e))?;
+
+    let file = gist
+        .files
+        .iter()
+        .find(|(name, _)| is_diagram_file(std::path::Path::new(name)))
+        .or_else(|| gist.files.iter().next())
+        .map(|(_, file)| file)
+        .ok_or("Gist has no files")?;
+    let content = file_content(&client, file).await?;
+    importers::import_text(&content)
+        .map_err(|_| "Gist does not contain a recognized diagram".to_string())
+}
@@ -0,0 +1,166 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::DIAGRAM_EXTENSIONS;
+
+// Identifies hooks written by `install`, so foreign hooks are never
+// overwritten or removed.
+const MARKER: &str = "# flowcraft-studio pre-commit hook";
+const MARKDOWN_EXTENSIONS: [&str; 2] = ["md", "markdown"];
+
+pub fn git(dir: Option<&Path>, args: &[&str]) -> Result<Vec<u8>, String> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.arg("-C").arg(dir);
+    }
+    let output = command
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+// Where git looks for the pre-commit hook of the repository containing
+// `dir`, honouring core.hooksPath.
+fn hook_path(dir: &Path) -> Result<PathBuf, String> {
+    let output = git(Some(dir), &["rev-parse", "--git-path", "hooks/pre-commit"])
+        .map_err(|_| format!("{} is not inside a git repository", dir.display()))?;
+    let path = PathBuf::from(String::from_utf8_lossy(&output).trim());
+    Ok(if path.is_absolute() {
+        path
+    } else {
+        dir.join(path)
+    })
+}
+
+fn is_ours(path: &Path) -> bool {
+    fs::read_to_string(path)
+        .map(|script| script.lines().any(|line| line == MARKER))
+        .unwrap_or(false)
+}
+
+// Double-quoted shell string; Git for Windows runs hooks with its own sh,
+// which wants forward slashes.
+fn shell_quote(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut quoted = String::from("\"");
+    for c in path.chars() {
+        if matches!(c, '"' | '$' | '`' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+// The hook calls this executable, falling back to one on PATH after the
+// app moved, and lets commits through when neither exists.
+fn script(exe: &Path) -> String {
+    format!(
+        "#!/bin/sh
+{}
+# Checks staged diagrams before each commit. Skip it with --no-verify.
+FLOWCRAFT={}
+if [ ! -x \"$FLOWCRAFT\" ]; then
+    FLOWCRAFT=$(command -v flowcraft-studio)
+fi
+if [ -z \"$FLOWCRAFT\" ]; then
+    echo \"FlowCraft Studio not found; skipping diagram validation\" >&2
+    exit 0
+fi
+exec \"$FLOWCRAFT\" validate --staged
+",
+        MARKER,
+        shell_quote(exe)
+    )
+}
+
+// Installs the pre-commit hook into the repository containing `dir` and
+// returns its path. A previous copy is replaced; any other hook is left
+// alone.
+pub fn install(dir: &Path, exe: &Path) -> Result<PathBuf, String> {
+    let path = hook_path(dir)?;
+    if path.exists() && !is_ours(&path) {
+        return Err(format!(
+            "{} already exists; add `flowcraft-studio validate --staged` to it instead",
+            path.display()
+        ));
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create hooks directory: {}", e))?;
+    }
+    fs::write(&path, script(exe)).map_err(|e| format!("Failed to write hook: {}", e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make hook executable: {}", e))?;
+    }
+
+    Ok(path)
+}
+
+// Removes the hook if `install` wrote it, and reports whether it did.
+pub fn uninstall(dir: &Path) -> Result<bool, String> {
+    let path = hook_path(dir)?;
+    if !is_ours(&path) {
+        return Ok(false);
+    }
+    fs::remove_file(&path).map_err(|e| format!("Failed to remove hook: {}", e))?;
+    Ok(true)
+}
+
+fn is_checked(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .map(|ext| {
+            DIAGRAM_EXTENSIONS
+                .iter()
+                .chain(MARKDOWN_EXTENSIONS.iter())
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+        .unwrap_or(false)
+}
+
+// The staged diagrams and Markdown files of the repository in the current
+// directory, as (path, staged content). The index is read rather than the
+// working tree, so unstaged edits do not hide problems being committed.
+pub fn staged_files() -> Result<Vec<(String, String)>, String> {
+    let names = git(
+        None,
+        &[
+            "diff",
+            "--cached",
+            "--name-only",
+            "--diff-filter=ACMR",
+            "-z",
+        ],
+    )?;
+
+    let mut files = Vec::new();
+    for name in names
+        .split(|byte| *byte == 0)
+        .filter(|name| !name.is_empty())
+    {
+        let name = String::from_utf8_lossy(name).to_string();
+        if !is_checked(&name) {
+            continue;
+        }
+        let blob = git(None, &["show", &format!(":{}", name)])?;
+        let content =
+            String::from_utf8(blob).map_err(|_| format!("{} is not valid UTF-8", name))?;
+        files.push((name, content));
+    }
+    Ok(files)
+}
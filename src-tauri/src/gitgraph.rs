@@ -0,0 +1,371 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::code_diagrams::Generated;
+use crate::git_hook::git;
+
+const DEFAULT_COMMITS: usize = 100;
+const MAX_COMMITS: usize = 1000;
+const MAX_MESSAGE_CHARS: usize = 40;
+
+#[derive(Debug, Deserialize, Default)]
+pub struct GitGraphOptions {
+    // Most recent commits to draw; 100 when unset.
+    #[serde(default)]
+    pub max_commits: Option<usize>,
+    // Local branches to draw, or all of them when empty.
+    #[serde(default)]
+    pub branches: Vec<String>,
+    // Only commits after this date, in any format `git log --since` takes.
+    #[serde(default)]
+    pub since: Option<String>,
+    #[serde(default)]
+    pub hide_tags: bool,
+    #[serde(default)]
+    pub hide_messages: bool,
+}
+
+struct Commit {
+    hash: String,
+    short: String,
+    parents: Vec<String>,
+    subject: String,
+    branch: Option<usize>,
+}
+
+fn lines(output: Vec<u8>) -> Vec<String> {
+    String::from_utf8_lossy(&output)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// Local branches with their tips, least recently committed first.
+fn branch_tips(dir: &Path) -> Result<Vec<(String, String)>, String> {
+    let output = git(
+        Some(dir),
+        &[
+            "for-each-ref",
+            "--sort=committerdate",
+            "--format=%(refname:short)%1f%(objectname)",
+            "refs/heads",
+        ],
+    )?;
+    Ok(lines(output)
+        .iter()
+        .filter_map(|line| line.split_once('\x1f'))
+        .map(|(name, hash)| (name.to_string(), hash.to_string()))
+        .collect())
+}
+
+// Tag names by the commit they point to, through annotated tags.
+fn tags(dir: &Path) -> Result<HashMap<String, Vec<String>>, String> {
+    let output = git(
+        Some(dir),
+        &[
+            "for-each-ref",
+            "--format=%(refname:short)%1f%(objectname)%1f%(*objectname)",
+            "refs/tags",
+        ],
+    )?;
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    for line in lines(output) {
+        let fields: Vec<&str> = line.split('\x1f').collect();
+        if let [name, object, peeled] = fields.as_slice() {
+            let commit = if peeled.is_empty() { object } else { peeled };
+            tags.entry(commit.to_string())
+                .or_default()
+                .push(name.to_string());
+        }
+    }
+    Ok(tags)
+}
+
+// The branch a merge commit brought in, from git's and the forges' default
+// merge messages.
+fn merged_branch(subject: &str) -> Option<String> {
+    let patterns = [
+        r"^Merge (?:remote-tracking )?branch '([^']+)'",
+        r"^Merge pull request #\d+ from [^/\s]+/(\S+)",
+        r"^Merge branch (\S+)",
+    ];
+    patterns.iter().find_map(|pattern| {
+        Regex::new(pattern)
+            .unwrap()
+            .captures(subject)
+            .map(|caps| caps[1].to_string())
+    })
+}
+
+// Branch names as Mermaid reads them: word characters, `-`, `.` and `/`.
+fn branch_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+fn add_name(names: &mut Vec<String>, name: &str) {
+    let base = branch_name(name);
+    let mut unique = base.clone();
+    let mut n = 2;
+    while names.contains(&unique) {
+        unique = format!("{}-{}", base, n);
+        n += 1;
+    }
+    names.push(unique);
+}
+
+// Puts the first-parent line from `start` on `branch`, up to the first
+// commit another branch already has. Returns whether it took any.
+fn claim(
+    commits: &mut [Commit],
+    index: &HashMap<String, usize>,
+    start: &str,
+    branch: usize,
+) -> bool {
+    let mut claimed = false;
+    let mut hash = start.to_string();
+    while let Some(&i) = index.get(&hash) {
+        if commits[i].branch.is_some() {
+            break;
+        }
+        commits[i].branch = Some(branch);
+        claimed = true;
+        match commits[i].parents.first() {
+            Some(parent) => hash = parent.clone(),
+            None => break,
+        }
+    }
+    claimed
+}
+
+fn quoted(text: &str) -> String {
+    text.replace('"', "'")
+}
+
+// Draws the history of a repository's branches as a gitGraph. Every commit
+// belongs to the first branch, main first, whose first-parent line reaches
+// it; commits only reachable through a merge are put on a branch named
+// after the merge message, as deleted branches usually were.
+pub fn generate(dir: &Path, options: &GitGraphOptions) -> Result<Generated, String> {
+    let mut warnings = Vec::new();
+    let mut tips = branch_tips(dir)
+        .map_err(|_| format!("{} is not inside a git repository", dir.display()))?;
+    if !options.branches.is_empty() {
+        for name in &options.branches {
+            if !tips.iter().any(|(tip, _)| tip == name) {
+                return Err(format!("Unknown branch: {}", name));
+            }
+        }
+        tips.retain(|(name, _)| options.branches.contains(name));
+    }
+    if tips.is_empty() {
+        return Err("The repository has no branches".to_string());
+    }
+
+    // The main branch is drawn first and keeps the commits it shares with
+    // others.
+    let head = git(Some(dir), &["symbolic-ref", "--quiet", "--short", "HEAD"])
+        .map(|output| String::from_utf8_lossy(&output).trim().to_string())
+        .unwrap_or_default();
+    let main = ["main", "master", head.as_str()]
+        .iter()
+        .find_map(|name| tips.iter().position(|(tip, _)| tip == name))
+        .unwrap_or(tips.len() - 1);
+    let main_tip = tips.remove(main);
+    tips.insert(0, main_tip);
+
+    let limit = options
+        .max_commits
+        .unwrap_or(DEFAULT_COMMITS)
+        .clamp(1, MAX_COMMITS);
+    let limit_arg = limit.to_string();
+    let mut args = vec![
+        "log",
+        "--topo-order",
+        "--reverse",
+        "-n",
+        limit_arg.as_str(),
+        "--format=%H%x1f%h%x1f%P%x1f%s",
+    ];
+    let since = options
+        .since
+        .as_ref()
+        .map(|since| format!("--since={}", since));
+    if let Some(since) = &since {
+        args.push(since);
+    }
+    let refs: Vec<String> = tips
+        .iter()
+        .map(|(name, _)| format!("refs/heads/{}", name))
+        .collect();
+    args.extend(refs.iter().map(String::as_str));
+    args.push("--");
+
+    let mut commits: Vec<Commit> = lines(git(Some(dir), &args)?)
+        .iter()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.splitn(4, '\x1f').collect();
+            let [hash, short, parents, subject] = fields.as_slice() else {
+                return None;
+            };
+            Some(Commit {
+                hash: hash.to_string(),
+                short: short.to_string(),
+                parents: parents.split_whitespace().map(str::to_string).collect(),
+                subject: subject.to_string(),
+                branch: None,
+            })
+        })
+        .collect();
+    if commits.is_empty() {
+        return Err("No commits match the options".to_string());
+    }
+    let index: HashMap<String, usize> = commits
+        .iter()
+        .enumerate()
+        .map(|(i, commit)| (commit.hash.clone(), i))
+        .collect();
+
+    let mut names: Vec<String> = Vec::new();
+    for (branch, (name, tip)) in tips.iter().enumerate() {
+        add_name(&mut names, name);
+        claim(&mut commits, &index, tip, branch);
+    }
+    // Newest merges first, so a branch is named after its last merge.
+    for i in (0..commits.len()).rev() {
+        let merged: Vec<String> = commits[i].parents.iter().skip(1).cloned().collect();
+        for parent in merged {
+            if claim(&mut commits, &index, &parent, names.len()) {
+                let name = merged_branch(&commits[i].subject)
+                    .unwrap_or_else(|| format!("merged-{}", commits[i].short));
+                add_name(&mut names, &name);
+            }
+        }
+    }
+
+    let tags = if options.hide_tags {
+        HashMap::new()
+    } else {
+        tags(dir)?
+    };
+    let label = |commit: &Commit| {
+        let mut attributes = if options.hide_messages {
+            format!("id: \"{}\"", commit.short)
+        } else {
+            let subject: String = commit.subject.chars().take(MAX_MESSAGE_CHARS).collect();
+            let ellipsis = if commit.subject.chars().count() > MAX_MESSAGE_CHARS {
+                "…"
+            } else {
+                ""
+            };
+            format!("id: \"{} {}{}\"", commit.short, quoted(&subject), ellipsis)
+        };
+        if let Some(names) = tags.get(&commit.hash) {
+            attributes.push_str(&format!(" tag: \"{}\"", quoted(&names.join(", "))));
+        }
+        attributes
+    };
+
+    // Branches that fork inside the drawn history are created right after
+    // the commit they fork from; the others when their first commit comes.
+    let mut forks: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut seen = HashSet::new();
+    for commit in &commits {
+        let Some(branch) = commit.branch else {
+            continue;
+        };
+        if branch != 0 && seen.insert(branch) {
+            if let Some(parent) = commit.parents.first().filter(|p| index.contains_key(*p)) {
+                forks.entry(parent.as_str()).or_default().push(branch);
+            }
+        }
+    }
+
+    let main_name = &names[0];
+    let mut output = Vec::new();
+    if main_name != "main" {
+        output.push(format!(
+            "%%{{init: {{\"gitGraph\": {{\"mainBranchName\": \"{}\"}}}}}}%%",
+            main_name
+        ));
+    }
+    output.push("gitGraph".to_string());
+
+    // The last commit drawn on each branch. Mermaid rejects merges into or
+    // from a branch without commits, and merges of two branches at the same
+    // commit, so those are drawn as plain commits.
+    let mut heads: HashMap<usize, &str> = HashMap::new();
+    let mut created: HashSet<usize> = HashSet::from([0]);
+    let mut current = 0;
+    for commit in &commits {
+        let branch = commit.branch.unwrap_or(0);
+        if created.insert(branch) {
+            // Without a fork point in the window, the branch starts from main.
+            if current != 0 {
+                output.push(format!("    checkout {}", names[0]));
+            }
+            output.push(format!("    branch {}", names[branch]));
+            if let Some(&head) = heads.get(&0) {
+                heads.insert(branch, head);
+            }
+            current = branch;
+        }
+        if current != branch {
+            output.push(format!("    checkout {}", names[branch]));
+            current = branch;
+        }
+
+        let merged = commit
+            .parents
+            .iter()
+            .skip(1)
+            .filter_map(|parent| index.get(parent))
+            .filter_map(|&i| commits[i].branch)
+            .filter(|merged| {
+                *merged != branch
+                    && heads.contains_key(merged)
+                    && heads.contains_key(&branch)
+                    && heads.get(merged) != heads.get(&branch)
+            })
+            .collect::<Vec<_>>();
+        match merged.as_slice() {
+            [] => output.push(format!("    commit {}", label(commit))),
+            [merged, rest @ ..] => {
+                if !rest.is_empty() {
+                    warnings.push(format!(
+                        "Octopus merge {} is drawn with its first merged branch only",
+                        commit.short
+                    ));
+                }
+                output.push(format!("    merge {} {}", names[*merged], label(commit)));
+            }
+        }
+        heads.insert(branch, commit.hash.as_str());
+
+        for &fork in forks.get(commit.hash.as_str()).into_iter().flatten() {
+            if created.insert(fork) {
+                output.push(format!("    branch {}", names[fork]));
+                heads.insert(fork, commit.hash.as_str());
+                current = fork;
+            }
+        }
+    }
+
+    if commits.len() == limit {
+        warnings.push(format!("Showing the most recent {} commits", commits.len()));
+    }
+    Ok(Generated {
+        content: output.join("\n"),
+        warnings,
+    })
+}
@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::get_app_data_dir;
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+// Bundles shipped with the app. JSON files in `<app data>/locales` add new
+// locales or override individual strings of a shipped one.
+const BUILTIN_BUNDLES: [(&str, &str); 3] = [
+    ("en", include_str!("../locales/en.json")),
+    ("de", include_str!("../locales/de.json")),
+    ("fr", include_str!("../locales/fr.json")),
+];
+
+static CURRENT: Mutex<Option<(String, BTreeMap<String, String>)>> = Mutex::new(None);
+
+fn user_locales_dir() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("locales"))
+}
+
+fn parse_bundle(json: &str) -> BTreeMap<String, String> {
+    serde_json::from_str(json).unwrap_or_default()
+}
+
+fn load_bundle(locale: &str) -> Option<BTreeMap<String, String>> {
+    let builtin = BUILTIN_BUNDLES
+        .iter()
+        .find(|(id, _)| id.eq_ignore_ascii_case(locale))
+        .map(|(_, json)| parse_bundle(json));
+
+    let user = user_locales_dir()
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join(format!("{}.json", locale))).ok())
+        .map(|json| parse_bundle(&json));
+
+    match (builtin, user) {
+        (Some(mut builtin), Some(user)) => {
+            builtin.extend(user);
+            Some(builtin)
+        }
+        (builtin, user) => builtin.or(user),
+    }
+}
+
+// `pt_BR.UTF-8` and `pt-br` both become `pt-BR`.
+pub fn normalize(locale: &str) -> String {
+    let locale = locale
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-");
+    match locale.split_once('-') {
+        Some((language, region)) => {
+            format!("{}-{}", language.to_lowercase(), region.to_uppercase())
+        }
+        None => locale.to_lowercase(),
+    }
+}
+
+pub fn available_locales() -> Vec<String> {
+    let mut locales: Vec<String> = BUILTIN_BUNDLES
+        .iter()
+        .map(|(id, _)| id.to_string())
+        .collect();
+
+    if let Ok(entries) = user_locales_dir().and_then(|dir| {
+        fs::read_dir(dir).map_err(|e| format!("Failed to read locales directory: {}", e))
+    }) {
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(stem) = path.file_stem() {
+                    locales.push(normalize(&stem.to_string_lossy()));
+                }
+            }
+        }
+    }
+
+    locales.sort();
+    locales.dedup();
+    locales
+}
+
+pub fn is_available(locale: &str) -> bool {
+    let locale = normalize(locale);
+    let language = locale.split('-').next().unwrap_or_default();
+    available_locales()
+        .iter()
+        .any(|known| *known == locale || known == language)
+}
+
+// English first, then the language, then the regional variant, so missing
+// keys fall back gracefully.
+pub fn strings(locale: &str) -> BTreeMap<String, String> {
+    let locale = normalize(locale);
+    let mut strings = load_bundle(DEFAULT_LOCALE).unwrap_or_default();
+
+    if let Some((language, _)) = locale.split_once('-') {
+        strings.extend(load_bundle(language).unwrap_or_default());
+    }
+    strings.extend(load_bundle(&locale).unwrap_or_default());
+
+    strings
+}
+
+pub fn system_locale() -> String {
+    sys_locale::get_locale()
+        .map(|locale| normalize(&locale))
+        .filter(|locale| is_available(locale))
+        .unwrap_or_else(|| DEFAULT_LOCALE.to_string())
+}
+
+pub fn set_current(locale: &str) {
+    let locale = normalize(locale);
+    let strings = strings(&locale);
+    if let Ok(mut current) = CURRENT.lock() {
+        *current = Some((locale, strings));
+    }
+}
+
+pub fn current() -> String {
+    CURRENT
+        .lock()
+        .ok()
+        .and_then(|current| current.as_ref().map(|(locale, _)| locale.clone()))
+        .unwrap_or_else(|| DEFAULT_LOCALE.to_string())
+}
+
+// Looks up `key` in the active locale; unknown keys are returned as is.
+pub fn t(key: &str) -> String {
+    let mut current = match CURRENT.lock() {
+        Ok(current) => current,
+        Err(_) => return key.to_string(),
+    };
+    let (_, strings) =
+        current.get_or_insert_with(|| (DEFAULT_LOCALE.to_string(), strings(DEFAULT_LOCALE)));
+
+    strings.get(key).cloned().unwrap_or_else(|| key.to_string())
+}
+
+// `t` with `{name}` placeholders filled in.
+pub fn t_with(key: &str, args: &[(&str, &str)]) -> String {
+    args.iter().fold(t(key), |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), value)
+    })
+}
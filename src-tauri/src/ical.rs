@@ -0,0 +1,472 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+const TAGS: [&str; 4] = ["done", "active", "crit", "milestone"];
+// Longest duration a task may have, about a hundred years; enough for any
+// plan, and it keeps the day-by-day walk below short.
+const MAX_DURATION_DAYS: f64 = 36_500.0;
+
+#[derive(Debug, Clone)]
+pub struct Task {
+    pub id: String,
+    pub name: String,
+    pub section: Option<String>,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub milestone: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Gantt {
+    pub title: Option<String>,
+    pub tasks: Vec<Task>,
+}
+
+// Days left out of day-based durations by `excludes`.
+#[derive(Default)]
+struct Excludes {
+    weekends: bool,
+    weekdays: Vec<Weekday>,
+    dates: Vec<NaiveDate>,
+}
+
+impl Excludes {
+    fn contains(&self, date: NaiveDate) -> bool {
+        let weekday = date.weekday();
+        (self.weekends && matches!(weekday, Weekday::Sat | Weekday::Sun))
+            || self.weekdays.contains(&weekday)
+            || self.dates.contains(&date)
+    }
+
+    // True when no day is left to count.
+    fn excludes_every_weekday(&self) -> bool {
+        let weekdays = [
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Sat,
+            Weekday::Sun,
+        ];
+        weekdays.iter().all(|weekday| {
+            self.weekdays.contains(weekday)
+                || (self.weekends && matches!(weekday, Weekday::Sat | Weekday::Sun))
+        })
+    }
+}
+
+// Turns a Day.js date format, as used by `dateFormat`, into a chrono one.
+fn chrono_format(format: &str) -> String {
+    const TOKENS: [(&str, &str); 14] = [
+        ("YYYY", "%Y"),
+        ("MMMM", "%B"),
+        ("MMM", "%b"),
+        ("YY", "%y"),
+        ("MM", "%m"),
+        ("DD", "%d"),
+        ("HH", "%H"),
+        ("hh", "%I"),
+        ("mm", "%M"),
+        ("ss", "%S"),
+        ("M", "%m"),
+        ("D", "%d"),
+        ("H", "%H"),
+        ("A", "%p"),
+    ];
+    let mut converted = String::new();
+    let mut rest = format;
+    'outer: while !rest.is_empty() {
+        for (token, replacement) in TOKENS {
+            if let Some(after) = rest.strip_prefix(token) {
+                converted.push_str(replacement);
+                rest = after;
+                continue 'outer;
+            }
+        }
+        let c = rest.chars().next().unwrap_or_default();
+        if c == '%' {
+            converted.push('%');
+        }
+        converted.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    converted
+}
+
+fn parse_datetime(value: &str, format: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, format)
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, format)
+                .ok()
+                .map(|date| date.and_time(NaiveTime::MIN))
+        })
+}
+
+fn add_duration(
+    start: NaiveDateTime,
+    value: &str,
+    excludes: &Excludes,
+) -> Result<NaiveDateTime, String> {
+    let invalid = || format!("Cannot read duration \"{}\"", value);
+    let caps = Regex::new(r"^(\d+(?:\.\d+)?)(ms|s|m|h|d|w|M|y)$")
+        .unwrap()
+        .captures(value)
+        .ok_or_else(invalid)?;
+    let amount: f64 = caps[1].parse().map_err(|_| invalid())?;
+    let days = match &caps[2] {
+        "ms" => amount / 86_400_000.0,
+        "s" => amount / 86_400.0,
+        "m" => amount / 1440.0,
+        "h" => amount / 24.0,
+        "d" => amount,
+        "w" => amount * 7.0,
+        "M" => amount * 30.0,
+        _ => amount * 365.0,
+    };
+    if days > MAX_DURATION_DAYS {
+        return Err(format!("Duration \"{}\" is longer than 100 years", value));
+    }
+    let too_late = || format!("Duration \"{}\" ends too far in the future", value);
+    if matches!(&caps[2], "ms" | "s" | "m" | "h") {
+        return start
+            .checked_add_signed(Duration::milliseconds((days * 86_400_000.0) as i64))
+            .ok_or_else(too_late);
+    }
+
+    // Whole days skip excluded dates, the way Mermaid stretches such tasks.
+    let mut end = start;
+    let mut whole = days.trunc() as i64;
+    if whole > 0 && excludes.excludes_every_weekday() {
+        return Err("The excludes leave no working days".to_string());
+    }
+    while whole > 0 {
+        end = end
+            .checked_add_signed(Duration::days(1))
+            .ok_or_else(too_late)?;
+        if !excludes.contains(end.date() - Duration::days(1)) {
+            whole -= 1;
+        }
+    }
+    end.checked_add_signed(Duration::milliseconds((days.fract() * 86_400_000.0) as i64))
+        .ok_or_else(too_late)
+}
+
+// Reads the tasks of a gantt diagram with their dates worked out: `after`
+// and `until` references, durations, `excludes` and tasks that follow the
+// one before them.
+pub fn parse(content: &str) -> Result<Gantt, String> {
+    let mut lines = content.lines().map(str::trim).peekable();
+    let mut title = None;
+    if lines.peek() == Some(&"---") {
+        lines.next();
+        for line in lines.by_ref() {
+            if line == "---" {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("title:") {
+                title = Some(value.trim().trim_matches(['"', '\'']).to_string());
+            }
+        }
+    }
+
+    let mut format = chrono_format("YYYY-MM-DD");
+    let mut excludes = Excludes::default();
+    let mut inclusive_end_dates = false;
+    let mut section = None;
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut ids: HashMap<String, usize> = HashMap::new();
+    let mut is_gantt = false;
+
+    for line in lines {
+        if line.is_empty() || line.starts_with("%%") {
+            continue;
+        }
+        if !is_gantt {
+            if line != "gantt" {
+                return Err("Not a gantt diagram".to_string());
+            }
+            is_gantt = true;
+            continue;
+        }
+
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match keyword {
+            "title" => {
+                title = Some(rest.to_string());
+                continue;
+            }
+            "dateFormat" => {
+                format = chrono_format(rest);
+                continue;
+            }
+            "excludes" => {
+                for item in rest.split([',', ' ']).filter(|item| !item.is_empty()) {
+                    match item.to_lowercase().as_str() {
+                        "weekends" => excludes.weekends = true,
+                        day => match day.parse::<Weekday>() {
+                            Ok(weekday) => excludes.weekdays.push(weekday),
+                            Err(_) => excludes
+                                .dates
+                                .extend(parse_datetime(item, &format).map(|date| date.date())),
+                        },
+                    }
+                }
+                continue;
+            }
+            "inclusiveEndDates" => {
+                inclusive_end_dates = true;
+                continue;
+            }
+            "section" => {
+                section = Some(rest.to_string());
+                continue;
+            }
+            "axisFormat" | "tickInterval" | "todayMarker" | "weekday" | "includes" | "topAxis"
+            | "accTitle" | "accTitle:" | "accDescr" | "accDescr:" | "click" => continue,
+            _ => {}
+        }
+
+        let Some((name, metadata)) = line.split_once(':') else {
+            continue;
+        };
+        let mut fields: Vec<&str> = metadata.split(',').map(str::trim).collect();
+        let mut milestone = false;
+        while let Some(tag) = fields.first().filter(|field| TAGS.contains(field)) {
+            milestone |= *tag == "milestone";
+            fields.remove(0);
+        }
+
+        let index = tasks.len();
+        let (id, start, end) = match fields.as_slice() {
+            [end] => (format!("task{}", index + 1), None, *end),
+            [start, end] => (format!("task{}", index + 1), Some(*start), *end),
+            [id, start, end] => (id.to_string(), Some(*start), *end),
+            _ => return Err(format!("Cannot read task: {}", line)),
+        };
+
+        let previous_end = tasks.last().map(|task: &Task| task.end);
+        let start = match start {
+            Some(start) if start.starts_with("after ") => start
+                .split_whitespace()
+                .skip(1)
+                .filter_map(|id| ids.get(id).map(|&task| tasks[task].end))
+                .max()
+                .ok_or_else(|| format!("Unknown task in \"{}\"", start))?,
+            Some(start) => parse_datetime(start, &format)
+                .ok_or_else(|| format!("Cannot read date \"{}\" of {}", start, name.trim()))?,
+            None => previous_end.ok_or_else(|| format!("{} needs a start date", name.trim()))?,
+        };
+        let end = if let Some(references) = end.strip_prefix("until ") {
+            references
+                .split_whitespace()
+                .filter_map(|id| ids.get(id).map(|&task| tasks[task].start))
+                .min()
+                .ok_or_else(|| format!("Unknown task in \"{}\"", end))?
+        } else if let Some(date) = parse_datetime(end, &format) {
+            if inclusive_end_dates {
+                date + Duration::days(1)
+            } else {
+                date
+            }
+        } else {
+            add_duration(start, end, &excludes).map_err(|e| format!("{} ({})", e, name.trim()))?
+        };
+
+        ids.insert(id.clone(), index);
+        tasks.push(Task {
+            id,
+            name: name.trim().to_string(),
+            section: section.clone(),
+            start,
+            end: end.max(start),
+            milestone,
+        });
+    }
+
+    if !is_gantt {
+        return Err("Not a gantt diagram".to_string());
+    }
+    if tasks.is_empty() {
+        return Err("The gantt diagram has no tasks".to_string());
+    }
+    Ok(Gantt { title, tasks })
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// Lines longer than 75 bytes are folded onto continuation lines, which
+// start with a space (RFC 5545, section 3.1).
+fn fold(line: &str, output: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            output.push_str("\r\n ");
+            width = 1;
+        }
+        output.push(c);
+        width += c.len_utf8();
+    }
+    output.push_str("\r\n");
+}
+
+fn is_midnight(time: NaiveDateTime) -> bool {
+    time.time() == NaiveTime::MIN
+}
+
+// Tasks that start and end on day boundaries become all-day events; others
+// keep their times, in the calendar's local time zone.
+fn dates(task: &Task) -> (String, String) {
+    if task.milestone {
+        let day = task.start.date();
+        return (
+            format!("DTSTART;VALUE=DATE:{}", day.format("%Y%m%d")),
+            format!(
+                "DTEND;VALUE=DATE:{}",
+                (day + Duration::days(1)).format("%Y%m%d")
+            ),
+        );
+    }
+    if is_midnight(task.start) && is_midnight(task.end) {
+        let end = task.end.date().max(task.start.date() + Duration::days(1));
+        return (
+            format!("DTSTART;VALUE=DATE:{}", task.start.format("%Y%m%d")),
+            format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")),
+        );
+    }
+    (
+        format!("DTSTART:{}", task.start.format("%Y%m%dT%H%M%S")),
+        format!("DTEND:{}", task.end.format("%Y%m%dT%H%M%S")),
+    )
+}
+
+// Writes the tasks as iCalendar events. UIDs come from the title and task
+// id, so importing an updated export replaces the earlier events.
+pub fn to_ics(gantt: &Gantt) -> String {
+    let title = gantt.title.as_deref().unwrap_or("Gantt");
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//FlowCraft Studio//Gantt Export//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", escape(title)),
+    ];
+
+    for task in &gantt.tasks {
+        let digest = Sha256::digest(format!("{}\n{}", title, task.id).as_bytes());
+        let uid: String = digest[..12]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let (start, end) = dates(task);
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}@flowcraft-studio", uid));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(start);
+        lines.push(end);
+        lines.push(format!("SUMMARY:{}", escape(&task.name)));
+        if let Some(section) = &task.section {
+            lines.push(format!("CATEGORIES:{}", escape(section)));
+        }
+        if task.milestone {
+            lines.push("DESCRIPTION:Milestone".to_string());
+        }
+        lines.push("TRANSP:TRANSPARENT".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut output = String::new();
+    for line in &lines {
+        fold(line, &mut output);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn adds_each_unit() {
+        let start = at(2024, 1, 1, 0);
+        let none = Excludes::default();
+        assert_eq!(add_duration(start, "2d", &none), Ok(at(2024, 1, 3, 0)));
+        assert_eq!(add_duration(start, "36h", &none), Ok(at(2024, 1, 2, 12)));
+        assert_eq!(
+            add_duration(start, "90m", &none),
+            Ok(start + Duration::minutes(90))
+        );
+        assert_eq!(add_duration(start, "1w", &none), Ok(at(2024, 1, 8, 0)));
+        assert_eq!(add_duration(start, "1.5d", &none), Ok(at(2024, 1, 2, 12)));
+    }
+
+    #[test]
+    fn skips_excluded_days() {
+        let weekends = Excludes {
+            weekends: true,
+            ..Excludes::default()
+        };
+        // Friday plus two working days ends on Tuesday.
+        assert_eq!(
+            add_duration(at(2024, 1, 5, 0), "2d", &weekends),
+            Ok(at(2024, 1, 9, 0))
+        );
+        let holiday = Excludes {
+            dates: vec![NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()],
+            ..Excludes::default()
+        };
+        assert_eq!(
+            add_duration(at(2024, 1, 1, 0), "2d", &holiday),
+            Ok(at(2024, 1, 4, 0))
+        );
+        // Hours run through excluded days.
+        assert_eq!(
+            add_duration(at(2024, 1, 5, 12), "24h", &weekends),
+            Ok(at(2024, 1, 6, 12))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        let start = at(2024, 1, 1, 0);
+        let none = Excludes::default();
+        assert!(add_duration(start, "2x", &none).is_err());
+        assert!(add_duration(start, "d", &none).is_err());
+        assert!(add_duration(start, "200y", &none).is_err());
+        let everything = Excludes {
+            weekends: true,
+            weekdays: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            ..Excludes::default()
+        };
+        assert!(add_duration(start, "1d", &everything).is_err());
+        assert_eq!(
+            add_duration(start, "1h", &everything),
+            Ok(at(2024, 1, 1, 1))
+        );
+    }
+}
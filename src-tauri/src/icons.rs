@@ -0,0 +1,343 @@
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::{diagrams, get_app_data_dir};
+
+// Iconify publishes every icon set as a package; bare names such as `logos`
+// or `fa6-brands` download from here.
+const ICONIFY_URL: &str = "https://unpkg.com/@iconify-json/{name}/icons.json";
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+// The largest Iconify sets are around 10 MB.
+const MAX_PACK_BYTES: usize = 32 * 1024 * 1024;
+
+// Icon names by pack prefix.
+pub type Registered = Arc<HashMap<String, HashSet<String>>>;
+
+// Size and modification time of each pack file.
+type PackFiles = Vec<(PathBuf, u64, Option<SystemTime>)>;
+
+// The packs as last read, with the files they came from, so validating a
+// diagram does not parse every pack again.
+static REGISTERED: Mutex<Option<(PackFiles, Registered)>> = Mutex::new(None);
+
+// Icons architecture diagrams can use without registering a pack.
+pub const BUILTIN_ICONS: [&str; 5] = ["cloud", "database", "disk", "internet", "server"];
+
+#[derive(Debug, Serialize, Clone)]
+pub struct IconPack {
+    pub name: String,
+    pub icons: usize,
+    // Where the pack was downloaded from; None for packs installed from a
+    // file.
+    pub source: Option<String>,
+}
+
+// Icon packs are Iconify JSON files in `<app data>/icons`, named by their
+// `prefix`, with the download URL in `<prefix>.url` next to downloaded
+// ones. The preview and the renderer register the same files with Mermaid.
+pub fn icons_dir() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("icons"))
+}
+
+// The prefix and icon names of an Iconify JSON pack, aliases included.
+fn parse(json: &str) -> Result<(String, HashSet<String>), String> {
+    let pack: Value =
+        serde_json::from_str(json).map_err(|e| format!("Invalid icon pack: {}", e))?;
+    let prefix = pack
+        .get("prefix")
+        .and_then(Value::as_str)
+        .filter(|prefix| {
+            !prefix.is_empty()
+                && prefix
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        })
+        .ok_or("Icon pack needs a prefix of lowercase letters, digits or '-'")?;
+    let Some(icons) = pack.get("icons").and_then(Value::as_object) else {
+        return Err("Icon pack has no icons".to_string());
+    };
+    let mut names: HashSet<String> = icons.keys().cloned().collect();
+    if let Some(aliases) = pack.get("aliases").and_then(Value::as_object) {
+        names.extend(aliases.keys().cloned());
+    }
+    Ok((prefix.to_string(), names))
+}
+
+fn pack_files() -> Vec<PathBuf> {
+    let Ok(entries) = icons_dir().and_then(|dir| {
+        fs::read_dir(dir).map_err(|e| format!("Failed to read icons directory: {}", e))
+    }) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+// Packs are saved as `<prefix>.json`, so the file name is the prefix.
+fn prefix_of(path: &Path) -> Option<String> {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+}
+
+// Readable packs with their file and icon names. Unreadable packs are
+// skipped, as the preview would fail to register them too.
+fn packs() -> Vec<(String, PathBuf, HashSet<String>)> {
+    let mut packs = Vec::new();
+    for path in pack_files() {
+        match fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read icon pack: {}", e))
+            .and_then(|json| parse(&json))
+        {
+            Ok((prefix, names)) => packs.push((prefix, path, names)),
+            Err(error) => {
+                tracing::warn!(path = %path.display(), error = %error, "skipping invalid icon pack")
+            }
+        }
+    }
+    packs
+}
+
+fn pack_file(name: &str) -> Result<PathBuf, String> {
+    let unknown = || format!("Unknown icon pack: {}", name);
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        return Err(unknown());
+    }
+    let path = icons_dir()?.join(format!("{}.json", name));
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err(unknown())
+    }
+}
+
+// Icon names by pack prefix, read again only when a pack file changed.
+pub fn registered() -> Registered {
+    let files: PackFiles = pack_files()
+        .into_iter()
+        .map(|path| {
+            let metadata = fs::metadata(&path).ok();
+            let size = metadata.as_ref().map_or(0, |metadata| metadata.len());
+            let modified = metadata.and_then(|metadata| metadata.modified().ok());
+            (path, size, modified)
+        })
+        .collect();
+    if let Ok(cache) = REGISTERED.lock() {
+        if let Some((_, registered)) = cache.as_ref().filter(|(cached, _)| *cached == files) {
+            return registered.clone();
+        }
+    }
+    let registered: Registered = Arc::new(
+        packs()
+            .into_iter()
+            .map(|(prefix, _, names)| (prefix, names))
+            .collect(),
+    );
+    if let Ok(mut cache) = REGISTERED.lock() {
+        *cache = Some((files, registered.clone()));
+    }
+    registered
+}
+
+fn source_file(path: &Path) -> PathBuf {
+    path.with_extension("url")
+}
+
+fn source(path: &Path) -> Option<String> {
+    fs::read_to_string(source_file(path))
+        .ok()
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+}
+
+pub fn list() -> Vec<IconPack> {
+    let registered = registered();
+    let mut packs: Vec<IconPack> = pack_files()
+        .into_iter()
+        .filter_map(|path| {
+            let name = prefix_of(&path)?;
+            let icons = registered.get(&name)?.len();
+            Some(IconPack {
+                source: source(&path),
+                name,
+                icons,
+            })
+        })
+        .collect();
+    packs.sort_by(|a, b| a.name.cmp(&b.name));
+    packs
+}
+
+// The raw Iconify JSON of a pack, for `mermaid.registerIconPacks`.
+pub fn get(name: &str) -> Result<String, String> {
+    fs::read_to_string(pack_file(name)?).map_err(|e| format!("Failed to read icon pack: {}", e))
+}
+
+// Saves a pack into the icons directory, replacing any pack with the same
+// prefix.
+fn save(json: &str, source: Option<&str>) -> Result<IconPack, String> {
+    let (prefix, names) = parse(json)?;
+    if let Ok(existing) = pack_file(&prefix) {
+        fs::remove_file(&existing).map_err(|e| format!("Failed to replace icon pack: {}", e))?;
+        let _ = fs::remove_file(source_file(&existing));
+    }
+    let dir = icons_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create icons directory: {}", e))?;
+    let path = dir.join(format!("{}.json", prefix));
+    fs::write(&path, json).map_err(|e| format!("Failed to save icon pack: {}", e))?;
+    if let Some(source) = source {
+        fs::write(source_file(&path), source)
+            .map_err(|e| format!("Failed to save icon pack: {}", e))?;
+    }
+    Ok(IconPack {
+        name: prefix,
+        icons: names.len(),
+        source: source.map(str::to_string),
+    })
+}
+
+// Copies an Iconify JSON file into the icons directory.
+pub fn install(path: &Path) -> Result<IconPack, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("Failed to read icon pack: {}", e))?;
+    save(&json, None)
+}
+
+// The URL of an Iconify set name, or `source` itself when it is a URL.
+pub fn download_url(source: &str) -> Result<String, String> {
+    let source = source.trim();
+    if source.contains("://") {
+        let url = reqwest::Url::parse(source).map_err(|e| format!("Invalid URL: {}", e))?;
+        if url.scheme() != "https" {
+            return Err("Icon packs can only be downloaded over https".to_string());
+        }
+        return Ok(url.to_string());
+    }
+    if source.is_empty()
+        || !source
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        return Err(format!("Not an Iconify icon set name: {}", source));
+    }
+    Ok(ICONIFY_URL.replace("{name}", source))
+}
+
+// Downloads an Iconify JSON pack, by set name (`logos`, `mdi`) or URL, and
+// installs it.
+pub async fn download(source: &str) -> Result<IconPack, String> {
+    let url = download_url(source)?;
+    let client = reqwest::Client::builder()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download icon pack: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to download icon pack: HTTP {}",
+            response.status().as_u16()
+        ));
+    }
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_PACK_BYTES as u64)
+    {
+        return Err("The icon pack is too large".to_string());
+    }
+    // Servers may leave the length out, so it is counted while reading too.
+    let mut response = response;
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to download icon pack: {}", e))?
+    {
+        if bytes.len() + chunk.len() > MAX_PACK_BYTES {
+            return Err("The icon pack is too large".to_string());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    let json =
+        String::from_utf8(bytes).map_err(|_| "The download is not an icon pack".to_string())?;
+    save(&json, Some(&url))
+}
+
+pub fn remove(name: &str) -> Result<(), String> {
+    let path = pack_file(name)?;
+    fs::remove_file(&path).map_err(|e| format!("Failed to remove icon pack: {}", e))?;
+    let _ = fs::remove_file(source_file(&path));
+    Ok(())
+}
+
+// `prefix#url` pairs for the Mermaid CLI's `--iconPacksNamesAndUrls`, for the
+// packs `content` uses. Downloaded packs load from where they came from, as
+// the headless browser may not read local files.
+pub fn render_args(content: &str) -> Vec<String> {
+    pack_files()
+        .into_iter()
+        .filter_map(|path| prefix_of(&path).map(|prefix| (prefix, path)))
+        .filter(|(prefix, _)| content.contains(&format!("{}:", prefix)))
+        .filter_map(|(prefix, path)| {
+            let url = source(&path)
+                .or_else(|| reqwest::Url::from_file_path(&path).ok().map(String::from))?;
+            Some(format!("{}#{}", prefix, url))
+        })
+        .collect()
+}
+
+// Checks a `pack:name` icon reference against the registered packs, which
+// are only loaded once an icon needs them.
+pub fn check_reference(
+    pack: &str,
+    name: &str,
+    number: usize,
+    packs: &mut Option<Registered>,
+    errors: &mut Vec<String>,
+) {
+    let packs = packs.get_or_insert_with(registered);
+    match packs.get(pack) {
+        None => errors.push(format!(
+            "Line {}: icon pack `{}` is not registered",
+            number, pack
+        )),
+        Some(names) if !names.contains(name) => errors.push(format!(
+            "Line {}: icon pack `{}` has no icon `{}`",
+            number, pack, name
+        )),
+        Some(_) => {}
+    }
+}
+
+// Checks the `icon` of flowchart icon shapes, `A@{ icon: "logos:aws" }`.
+pub fn validate_shapes(content: &str, _: &str, errors: &mut Vec<String>, _: &mut Vec<String>) {
+    let icon = Regex::new(r#"@\{[^}]*\bicon\s*:\s*["']([^"']*)["']"#).unwrap();
+    let mut packs = None;
+    for (number, line) in diagrams::statements(content) {
+        for caps in icon.captures_iter(line) {
+            match caps[1].trim().split_once(':') {
+                Some((pack, name)) => check_reference(pack, name, number, &mut packs, errors),
+                None => errors.push(format!(
+                    "Line {}: icon `{}` needs a pack, as in `logos:{}`",
+                    number, &caps[1], &caps[1]
+                )),
+            }
+        }
+    }
+}
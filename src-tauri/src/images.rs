@@ -0,0 +1,266 @@
+use base64::{engine::general_purpose, Engine as _};
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::paths;
+
+// Images larger than this are left as references; inlined they would make
+// the export unwieldy.
+const MAX_INLINE_BYTES: u64 = 5 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageStatus {
+    // A local file that exists.
+    Found,
+    Missing,
+    // A web image; exports keep referring to it.
+    Remote,
+    // Already a data URI.
+    Embedded,
+    Unsupported,
+    // Outside the folders images may come from, so not looked at.
+    Blocked,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImageReport {
+    pub line: usize,
+    pub src: String,
+    pub status: ImageStatus,
+    // The file the reference resolves to, for local images.
+    pub path: Option<String>,
+    pub message: Option<String>,
+}
+
+// Where local images may come from: the diagram's folder, which relative
+// references resolve against, and the open workspace. Diagrams are shared,
+// so a reference elsewhere on disk is not followed.
+#[derive(Debug, Clone, Default)]
+pub struct ImageScope {
+    base: Option<PathBuf>,
+    roots: Vec<PathBuf>,
+}
+
+impl ImageScope {
+    pub fn new(base: Option<&Path>, workspace: Option<&Path>) -> Self {
+        let canonical = |path: &Path| path.canonicalize().ok();
+        let base = base.and_then(canonical);
+        let roots = base
+            .iter()
+            .cloned()
+            .chain(workspace.and_then(canonical))
+            .collect();
+        ImageScope { base, roots }
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.roots.iter().any(|root| path.starts_with(root)) && !paths::is_protected(path)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Inlined {
+    pub content: String,
+    pub warnings: Vec<String>,
+}
+
+// Image references: `img` in node shape data, `src` of `<img>` tags in
+// labels and `href` of `<image>` elements in rendered SVG. The reference
+// itself is the second group.
+fn pattern() -> Regex {
+    Regex::new(
+        r#"(?i)(\bimg\s*:\s*|<img\b[^>]*?\bsrc\s*=\s*|<image\b[^>]*?\b(?:xlink:)?href\s*=\s*)(?:"([^"]*)"|'([^']*)')"#,
+    )
+    .unwrap()
+}
+
+fn reference<'a>(caps: &'a Captures<'_>) -> &'a str {
+    caps.get(2).or(caps.get(3)).map_or("", |src| src.as_str())
+}
+
+fn mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "svg" => Some("image/svg+xml"),
+        "bmp" => Some("image/bmp"),
+        "ico" => Some("image/x-icon"),
+        _ => None,
+    }
+}
+
+fn is_remote(src: &str) -> bool {
+    let lower = src.to_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("//")
+}
+
+// Resolves a local reference, relative ones against the diagram's folder,
+// with `..` applied without touching the disk.
+fn resolve(src: &str, scope: &ImageScope) -> Result<PathBuf, String> {
+    let src = src.strip_prefix("file://").unwrap_or(src);
+    let path = PathBuf::from(src);
+    let path = if path.is_absolute() {
+        path
+    } else {
+        match &scope.base {
+            Some(base) => base.join(path),
+            None => {
+                return Err(
+                    "Save the diagram first so relative image paths can be resolved".to_string(),
+                )
+            }
+        }
+    };
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            component => normalized.push(component),
+        }
+    }
+    Ok(normalized)
+}
+
+fn inspect(line: usize, src: &str, scope: &ImageScope) -> ImageReport {
+    let report = |status, path: Option<&Path>, message: Option<String>| ImageReport {
+        line,
+        src: src.to_string(),
+        status,
+        path: path.map(|path| path.to_string_lossy().to_string()),
+        message,
+    };
+    if src.trim().is_empty() {
+        return report(
+            ImageStatus::Missing,
+            None,
+            Some("The image reference is empty".to_string()),
+        );
+    }
+    if src.starts_with("data:") {
+        return report(ImageStatus::Embedded, None, None);
+    }
+    if is_remote(src) {
+        return report(
+            ImageStatus::Remote,
+            None,
+            Some("Web images are not checked or inlined".to_string()),
+        );
+    }
+    let path = match resolve(src, scope) {
+        Ok(path) => path,
+        Err(e) => return report(ImageStatus::Missing, None, Some(e)),
+    };
+    let blocked = || {
+        report(
+            ImageStatus::Blocked,
+            None,
+            Some("Only images in the diagram's folder or the workspace are used".to_string()),
+        )
+    };
+    // Checked before the file is looked at, and again once symlinks are
+    // followed.
+    if !scope.contains(&path) {
+        return blocked();
+    }
+    let path = match path.canonicalize() {
+        Ok(path) if !scope.contains(&path) => return blocked(),
+        Ok(path) => path,
+        Err(_) => path,
+    };
+    if !path.is_file() {
+        return report(
+            ImageStatus::Missing,
+            Some(&path),
+            Some(format!("File not found: {}", path.display())),
+        );
+    }
+    if mime_type(&path).is_none() {
+        return report(
+            ImageStatus::Unsupported,
+            Some(&path),
+            Some("Not a PNG, JPEG, GIF, WebP, SVG, BMP or ICO image".to_string()),
+        );
+    }
+    report(ImageStatus::Found, Some(&path), None)
+}
+
+// Checks every image the diagram refers to.
+pub fn check(content: &str, scope: &ImageScope) -> Vec<ImageReport> {
+    let pattern = pattern();
+    let mut reports = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        for caps in pattern.captures_iter(line) {
+            reports.push(inspect(index + 1, reference(&caps).trim(), scope));
+        }
+    }
+    reports
+}
+
+fn data_uri(path: &Path) -> Result<String, String> {
+    let mime = mime_type(path).ok_or("Unsupported image type")?;
+    let size = fs::metadata(path)
+        .map_err(|e| format!("Failed to read image: {}", e))?
+        .len();
+    if size > MAX_INLINE_BYTES {
+        return Err(format!(
+            "Larger than {} MB, not inlined",
+            MAX_INLINE_BYTES / 1024 / 1024
+        ));
+    }
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read image: {}", e))?;
+    Ok(format!(
+        "data:{};base64,{}",
+        mime,
+        general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+// Replaces references to local images with data URIs, so the export does
+// not depend on files next to the diagram. Images that cannot be inlined
+// are left as they are, and those from elsewhere on disk are removed, each
+// with a warning.
+pub fn inline(content: &str, scope: &ImageScope) -> Inlined {
+    let mut warnings = Vec::new();
+    let content = pattern()
+        .replace_all(content, |caps: &Captures| {
+            let src = reference(caps).trim();
+            let report = inspect(0, src, scope);
+            let quote = if caps.get(2).is_some() { '"' } else { '\'' };
+            let path = match (report.status, report.path) {
+                (ImageStatus::Found, Some(path)) => PathBuf::from(path),
+                // Dropped, so the renderer does not load it either.
+                (ImageStatus::Blocked, _) => {
+                    warnings.push(format!(
+                        "Image {} removed: only images in the diagram's folder or the workspace are used",
+                        src
+                    ));
+                    return format!("{}{}{}", &caps[1], quote, quote);
+                }
+                (ImageStatus::Missing | ImageStatus::Unsupported, _) => {
+                    if let Some(message) = report.message {
+                        warnings.push(format!("Image {} not inlined: {}", src, message));
+                    }
+                    return caps[0].to_string();
+                }
+                _ => return caps[0].to_string(),
+            };
+            match data_uri(&path) {
+                Ok(uri) => format!("{}{}{}{}", &caps[1], quote, uri, quote),
+                Err(e) => {
+                    warnings.push(format!("Image {} not inlined: {}", src, e));
+                    caps[0].to_string()
+                }
+            }
+        })
+        .to_string();
+    Inlined { content, warnings }
+}
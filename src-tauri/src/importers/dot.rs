@@ -0,0 +1,257 @@
+use regex::Regex;
+use std::collections::HashMap;
+
+// Converts Graphviz DOT graphs into a Mermaid flowchart. Nodes, edges,
+// labels, common shapes, rankdir and subgraphs are carried over; styling
+// attributes are dropped.
+pub fn convert(source: &str, warnings: &mut Vec<String>) -> Result<String, String> {
+    let source = strip_comments(source);
+    let open = source.find('{').ok_or("DOT graph has no body")?;
+    let close = source.rfind('}').ok_or("DOT graph is not closed")?;
+    if close <= open {
+        return Err("DOT graph is not closed".to_string());
+    }
+
+    let header = source[..open].to_lowercase();
+    let edge_op = if header.contains("digraph") {
+        "->"
+    } else {
+        "--"
+    };
+    let edge_arrow = if edge_op == "->" { "-->" } else { "---" };
+
+    let rankdir = Regex::new(r#"(?i)rankdir\s*=\s*"?(LR|RL|TB|BT)"?"#).unwrap();
+    let direction = rankdir
+        .captures(&source)
+        .map(|caps| match caps[1].to_uppercase().as_str() {
+            "TB" => "TD".to_string(),
+            other => other.to_string(),
+        })
+        .unwrap_or_else(|| "TD".to_string());
+
+    let mut output = vec![format!("flowchart {}", direction)];
+    let mut depth = 1;
+
+    for statement in split_statements(&source[open + 1..close]) {
+        let statement = statement.trim();
+        if statement.is_empty() {
+            continue;
+        }
+
+        if statement == "}" {
+            if depth > 1 {
+                depth -= 1;
+                output.push(format!("{}end", "    ".repeat(depth)));
+            }
+            continue;
+        }
+
+        if let Some(rest) = statement.strip_prefix("subgraph") {
+            let name = rest.trim_end_matches('{').trim();
+            let id = if name.is_empty() {
+                format!("subgraph_{}", output.len())
+            } else {
+                node_id(name)
+            };
+            output.push(format!("{}subgraph {}", "    ".repeat(depth), id));
+            depth += 1;
+            continue;
+        }
+
+        if statement == "{" {
+            continue;
+        }
+
+        let (body, attributes) = split_attributes(statement);
+        let lowered = body.to_lowercase();
+        if lowered == "node" || lowered == "edge" || lowered == "graph" || body.contains('=') {
+            continue;
+        }
+
+        let indent = "    ".repeat(depth);
+        let label = attributes.get("label").cloned();
+
+        if body.contains(edge_op) {
+            let nodes: Vec<String> = body.split(edge_op).map(node_id).collect();
+            for pair in nodes.windows(2) {
+                match &label {
+                    Some(label) => output.push(format!(
+                        "{}{} {}|{}| {}",
+                        indent,
+                        pair[0],
+                        edge_arrow,
+                        escape_label(label),
+                        pair[1]
+                    )),
+                    None => {
+                        output.push(format!("{}{} {} {}", indent, pair[0], edge_arrow, pair[1]))
+                    }
+                }
+            }
+            continue;
+        }
+
+        let id = node_id(body);
+        let text = label.unwrap_or_else(|| unquote(body).to_string());
+        let shape = attributes.get("shape").map(String::as_str).unwrap_or("box");
+        output.push(format!(
+            "{}{}",
+            indent,
+            format_node(&id, &text, shape, warnings)
+        ));
+    }
+
+    while depth > 1 {
+        depth -= 1;
+        output.push(format!("{}end", "    ".repeat(depth)));
+        warnings.push("Unbalanced subgraph braces were closed automatically".to_string());
+    }
+
+    Ok(output.join("\n"))
+}
+
+fn strip_comments(source: &str) -> String {
+    let block = Regex::new(r"(?s)/\*.*?\*/").unwrap();
+    block
+        .replace_all(source, "")
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with('#') {
+                ""
+            } else {
+                match find_unquoted(line, "//") {
+                    Some(index) => &line[..index],
+                    None => line,
+                }
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn find_unquoted(line: &str, pattern: &str) -> Option<usize> {
+    let mut in_quotes = false;
+    for (index, c) in line.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        } else if !in_quotes && line[index..].starts_with(pattern) {
+            return Some(index);
+        }
+    }
+    None
+}
+
+// Splits the graph body on `;` and newlines, keeping braces as their own
+// statements so subgraphs can be tracked.
+fn split_statements(body: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut bracket_depth = 0;
+
+    for c in body.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            '[' if !in_quotes => {
+                bracket_depth += 1;
+                current.push(c);
+            }
+            ']' if !in_quotes => {
+                bracket_depth -= 1;
+                current.push(c);
+            }
+            ';' | '\n' if !in_quotes && bracket_depth == 0 => {
+                statements.push(std::mem::take(&mut current));
+            }
+            '{' if !in_quotes && bracket_depth == 0 => {
+                current.push('{');
+                statements.push(std::mem::take(&mut current));
+            }
+            '}' if !in_quotes && bracket_depth == 0 => {
+                statements.push(std::mem::take(&mut current));
+                statements.push("}".to_string());
+            }
+            _ => current.push(c),
+        }
+    }
+    statements.push(current);
+
+    statements
+}
+
+fn split_attributes(statement: &str) -> (&str, HashMap<String, String>) {
+    let Some(open) = find_unquoted(statement, "[") else {
+        return (statement.trim(), HashMap::new());
+    };
+
+    let attribute = Regex::new(r#"(\w+)\s*=\s*("(?:[^"\\]|\\.)*"|[^,;\s\]]+)"#).unwrap();
+    let attributes = attribute
+        .captures_iter(&statement[open..])
+        .map(|caps| {
+            (
+                caps[1].to_lowercase(),
+                unquote(&caps[2]).replace("\\n", " "),
+            )
+        })
+        .collect();
+
+    (statement[..open].trim(), attributes)
+}
+
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn node_id(name: &str) -> String {
+    let id: String = unquote(name.trim())
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    // `end` is reserved in flowcharts.
+    if id.eq_ignore_ascii_case("end") {
+        format!("{}_", id)
+    } else {
+        id
+    }
+}
+
+fn escape_label(label: &str) -> String {
+    label.replace('"', "#quot;")
+}
+
+fn format_node(id: &str, label: &str, shape: &str, warnings: &mut Vec<String>) -> String {
+    let label = escape_label(label);
+    match shape.to_lowercase().as_str() {
+        "box" | "rect" | "rectangle" | "square" | "plaintext" | "plain" | "none" => {
+            format!("{}[\"{}\"]", id, label)
+        }
+        "ellipse" | "oval" => format!("{}([\"{}\"])", id, label),
+        "circle" | "point" => format!("{}((\"{}\"))", id, label),
+        "doublecircle" => format!("{}(((\"{}\")))", id, label),
+        "diamond" => format!("{}{{\"{}\"}}", id, label),
+        "hexagon" => format!("{}{{{{\"{}\"}}}}", id, label),
+        "cylinder" => format!("{}[(\"{}\")]", id, label),
+        "parallelogram" => format!("{}[/\"{}\"/]", id, label),
+        "trapezium" => format!("{}[/\"{}\"\\]", id, label),
+        "invtrapezium" => format!("{}[\\\"{}\"/]", id, label),
+        other => {
+            warnings.push(format!("Shape '{}' is not supported, using a box", other));
+            format!("{}[\"{}\"]", id, label)
+        }
+    }
+}
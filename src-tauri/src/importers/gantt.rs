@@ -0,0 +1,218 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::HashMap;
+
+use crate::csv::{self, Table};
+
+// Header names accepted for each field, compared case-insensitively. They
+// cover generic task lists as well as Jira, Linear and spreadsheet exports.
+const NAME: [&str; 5] = ["name", "task", "summary", "title", "task name"];
+const ID: [&str; 4] = ["id", "key", "issue key", "issue id"];
+const START: [&str; 4] = ["start", "start date", "begin", "starts"];
+const END: [&str; 5] = ["end", "end date", "due", "due date", "finish"];
+const DURATION: [&str; 3] = ["duration", "days", "length"];
+const DEPENDENCIES: [&str; 6] = [
+    "dependencies",
+    "depends on",
+    "dependency",
+    "predecessors",
+    "blocked by",
+    "after",
+];
+const ASSIGNEE: [&str; 4] = ["assignee", "owner", "resource", "assigned to"];
+
+const DATE_FORMATS: [&str; 6] = [
+    "%Y-%m-%d", "%Y/%m/%d", "%d/%b/%y", "%d/%b/%Y", "%m/%d/%Y", "%d.%m.%Y",
+];
+const DATETIME_FORMATS: [&str; 3] = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%d/%b/%y %I:%M %p"];
+
+struct Columns {
+    name: usize,
+    id: Option<usize>,
+    start: Option<usize>,
+    end: Option<usize>,
+    duration: Option<usize>,
+    dependencies: Option<usize>,
+    assignee: Option<usize>,
+}
+
+fn find(table: &Table, names: &[&str]) -> Option<usize> {
+    names.iter().find_map(|name| table.column(name))
+}
+
+fn columns(table: &Table) -> Option<Columns> {
+    let columns = Columns {
+        name: find(table, &NAME)?,
+        id: find(table, &ID),
+        start: find(table, &START),
+        end: find(table, &END),
+        duration: find(table, &DURATION),
+        dependencies: find(table, &DEPENDENCIES),
+        assignee: find(table, &ASSIGNEE),
+    };
+    let scheduled =
+        columns.start.is_some() || columns.duration.is_some() || columns.dependencies.is_some();
+    scheduled.then_some(columns)
+}
+
+// A CSV or tab-separated task list with a name column and at least one
+// scheduling column.
+pub fn is_task_list(text: &str) -> bool {
+    let header = text.lines().next().unwrap_or_default();
+    if !header.contains([',', ';', '\t']) {
+        return false;
+    }
+    csv::parse(header)
+        .map(|table| columns(&table).is_some())
+        .unwrap_or(false)
+}
+
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+        .or_else(|| {
+            DATETIME_FORMATS
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+                .map(|datetime| datetime.date())
+        })
+        .or_else(|| {
+            // ISO timestamps with fractions or offsets: the date is enough.
+            value
+                .get(..10)
+                .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        })
+}
+
+// Mermaid durations like `3d`, `2w` or `4h`; bare numbers are days.
+fn parse_duration(value: &str) -> Option<String> {
+    let value = value.trim().to_lowercase().replace(' ', "");
+    let (number, unit) = match value.find(|c: char| c.is_alphabetic()) {
+        Some(index) => value.split_at(index),
+        None => (value.as_str(), "d"),
+    };
+    let unit = match unit {
+        "d" | "day" | "days" => "d",
+        "w" | "week" | "weeks" => "w",
+        "h" | "hour" | "hours" => "h",
+        _ => return None,
+    };
+    let number: f64 = number.parse().ok().filter(|n: &f64| *n > 0.0)?;
+    Some(format!("{}{}", number, unit))
+}
+
+// Task names end at the first `:` in Gantt syntax, and `#` or `;` would be
+// read as entity codes or statement ends.
+fn task_name(name: &str) -> String {
+    name.replace([':', ';', '#'], " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Converts a task export into a gantt chart with one section per assignee.
+// Dependencies name other tasks by id or name; a task with dependencies
+// starts after them, otherwise on its start date, otherwise after the task
+// before it.
+pub fn convert(text: &str, warnings: &mut Vec<String>) -> Result<String, String> {
+    let table = csv::parse(text)?;
+    let columns = columns(&table)
+        .ok_or("Task list needs a name column and a start, duration or dependencies column")?;
+    let field = |row: &[String], column: Option<usize>| {
+        column
+            .and_then(|column| row.get(column))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let rows: Vec<&Vec<String>> = table
+        .rows
+        .iter()
+        .filter(|row| field(row, Some(columns.name)).is_some())
+        .collect();
+    if rows.is_empty() {
+        return Err("The task list has no tasks".to_string());
+    }
+
+    let mut ids = HashMap::new();
+    for (index, row) in rows.iter().enumerate() {
+        let id = format!("t{}", index + 1);
+        if let Some(key) = field(row, columns.id) {
+            ids.entry(key.to_lowercase()).or_insert_with(|| id.clone());
+        }
+        if let Some(name) = field(row, Some(columns.name)) {
+            ids.entry(name.to_lowercase()).or_insert(id);
+        }
+    }
+
+    let mut sections: Vec<(String, Vec<String>)> = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        let name = field(row, Some(columns.name)).unwrap_or_default();
+        let mut parts = vec![format!("t{}", index + 1)];
+
+        let after: Vec<String> = field(row, columns.dependencies)
+            .map(|dependencies| {
+                dependencies
+                    .split([',', ';'])
+                    .map(str::trim)
+                    .filter(|dependency| !dependency.is_empty())
+                    .filter_map(|dependency| {
+                        let id = ids.get(&dependency.to_lowercase()).cloned();
+                        if id.is_none() {
+                            warnings
+                                .push(format!("{} depends on unknown task {}", name, dependency));
+                        }
+                        id
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let start = field(row, columns.start).and_then(|value| {
+            let date = parse_date(&value);
+            if date.is_none() {
+                warnings.push(format!("{} has an unreadable start date: {}", name, value));
+            }
+            date
+        });
+        if !after.is_empty() {
+            parts.push(format!("after {}", after.join(" ")));
+        } else if let Some(start) = start {
+            parts.push(start.format("%Y-%m-%d").to_string());
+        }
+
+        let duration = field(row, columns.duration).and_then(|value| {
+            let duration = parse_duration(&value);
+            if duration.is_none() {
+                warnings.push(format!("{} has an unreadable duration: {}", name, value));
+            }
+            duration
+        });
+        let end = field(row, columns.end).and_then(|value| parse_date(&value));
+        match (duration, end) {
+            (Some(duration), _) => parts.push(duration),
+            (None, Some(end)) => parts.push(end.format("%Y-%m-%d").to_string()),
+            (None, None) => {
+                warnings.push(format!("{} has no duration, assumed 1 day", name));
+                parts.push("1d".to_string());
+            }
+        }
+
+        let assignee = field(row, columns.assignee).unwrap_or_else(|| "Unassigned".to_string());
+        let line = format!("    {} :{}", task_name(&name), parts.join(", "));
+        match sections
+            .iter_mut()
+            .find(|(section, _)| *section == assignee)
+        {
+            Some((_, lines)) => lines.push(line),
+            None => sections.push((assignee, vec![line])),
+        }
+    }
+
+    let mut lines = vec!["gantt".to_string(), "    dateFormat YYYY-MM-DD".to_string()];
+    for (assignee, tasks) in sections {
+        lines.push(format!("    section {}", task_name(&assignee)));
+        lines.extend(tasks);
+    }
+    Ok(lines.join("\n"))
+}
@@ -0,0 +1,298 @@
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::escape_text;
+
+// More calls than this make a sequence diagram unreadable and slow to
+// render, so the rest are dropped with a warning.
+const MAX_CALLS: usize = 300;
+const MAX_PATH_CHARS: usize = 60;
+
+// Field names tried, in order, for each part of a structured log record.
+// Dotted names reach into nested objects, which also covers HAR entries.
+const TIME: [&str; 5] = ["startedDateTime", "timestamp", "@timestamp", "time", "ts"];
+const METHOD: [&str; 4] = ["request.method", "method", "http_method", "request_method"];
+const URL: [&str; 5] = ["request.url", "url", "uri", "request_uri", "path"];
+const HOST: [&str; 6] = ["host", "hostname", "server", "upstream", "target", "to"];
+const SOURCE: [&str; 5] = ["source", "from", "client", "service", "caller"];
+const STATUS: [&str; 4] = [
+    "response.status",
+    "status",
+    "status_code",
+    "response_status",
+];
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct HttpLogOptions {
+    // Only calls to these hosts, or to any host when empty. A leading `*.`
+    // matches subdomains.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    // RFC 3339 bounds; calls without a time are kept.
+    #[serde(default)]
+    pub since: Option<String>,
+    #[serde(default)]
+    pub until: Option<String>,
+}
+
+struct Call {
+    time: Option<DateTime<FixedOffset>>,
+    source: String,
+    target: String,
+    request: String,
+    status: Option<String>,
+}
+
+// A HAR file, or a JSON list or JSON Lines log of request records.
+pub fn is_http_log(text: &str) -> bool {
+    if is_har(text) {
+        return true;
+    }
+    records(text)
+        .map(|records| {
+            records.first().is_some_and(|record| {
+                field(record, &METHOD).is_some() && field(record, &URL).is_some()
+            })
+        })
+        .unwrap_or(false)
+}
+
+fn is_har(text: &str) -> bool {
+    serde_json::from_str::<Value>(text.trim())
+        .map(|value| value.pointer("/log/entries").is_some_and(Value::is_array))
+        .unwrap_or(false)
+}
+
+fn records(text: &str) -> Result<Vec<Value>, String> {
+    let trimmed = text.trim();
+    if let Ok(value) = serde_json::from_str::<Value>(trimmed) {
+        return match value {
+            Value::Array(records) => Ok(records),
+            Value::Object(_) => match value.pointer("/log/entries") {
+                Some(Value::Array(entries)) => Ok(entries.clone()),
+                _ => Ok(vec![value]),
+            },
+            _ => Err("Request log must hold JSON records".to_string()),
+        };
+    }
+    trimmed
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("Invalid JSON on log line {}: {}", index + 1, e))
+        })
+        .collect()
+}
+
+fn field<'a>(record: &'a Value, names: &[&str]) -> Option<&'a Value> {
+    names.iter().find_map(|name| {
+        name.split('.')
+            .try_fold(record, |value, key| value.get(key))
+            .filter(|value| !value.is_null())
+    })
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+// RFC 3339 strings, or Unix times in seconds or milliseconds.
+fn parse_time(value: &Value) -> Option<DateTime<FixedOffset>> {
+    match value {
+        Value::String(time) => DateTime::parse_from_rfc3339(time).ok(),
+        Value::Number(number) => {
+            let number = number.as_f64()?;
+            let millis = if number > 1e11 {
+                number
+            } else {
+                number * 1000.0
+            };
+            Utc.timestamp_millis_opt(millis as i64)
+                .single()
+                .map(|time| time.fixed_offset())
+        }
+        _ => None,
+    }
+}
+
+fn parse_bound(
+    value: &Option<String>,
+    name: &str,
+) -> Result<Option<DateTime<FixedOffset>>, String> {
+    value
+        .as_deref()
+        .map(|time| {
+            DateTime::parse_from_rfc3339(time).map_err(|e| format!("Invalid {} time: {}", name, e))
+        })
+        .transpose()
+}
+
+fn host_matches(host: &str, patterns: &[String]) -> bool {
+    patterns.is_empty()
+        || patterns.iter().any(|pattern| {
+            let pattern = pattern.trim().to_lowercase();
+            match pattern.strip_prefix("*.") {
+                Some(domain) => host == domain || host.ends_with(&format!(".{}", domain)),
+                None => host == pattern,
+            }
+        })
+}
+
+// Host and path of a request URL. Relative URLs from access logs keep
+// their path and take the host from the record, if it has one.
+fn split_url(url: &str) -> (Option<String>, String) {
+    match Url::parse(url) {
+        Ok(parsed) => {
+            let host = parsed.host_str().map(|host| match parsed.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.to_string(),
+            });
+            (host, parsed.path().to_string())
+        }
+        Err(_) => (
+            None,
+            url.split(['?', '#']).next().unwrap_or(url).to_string(),
+        ),
+    }
+}
+
+fn call(record: &Value) -> Option<Call> {
+    let method = field(record, &METHOD).map(text)?.to_uppercase();
+    let (url_host, path) = split_url(&field(record, &URL).map(text)?);
+    let target = url_host
+        .or_else(|| field(record, &HOST).map(text))
+        .unwrap_or_else(|| "Server".to_string())
+        .to_lowercase();
+    let mut path: String = path.chars().take(MAX_PATH_CHARS).collect();
+    if path.is_empty() {
+        path.push('/');
+    }
+
+    let status = field(record, &STATUS)
+        .map(text)
+        .filter(|status| status != "0")
+        .map(|status| {
+            match record
+                .pointer("/response/statusText")
+                .and_then(Value::as_str)
+                .filter(|text| !text.is_empty())
+            {
+                Some(status_text) => format!("{} {}", status, status_text),
+                None => status,
+            }
+        });
+
+    Some(Call {
+        time: field(record, &TIME).and_then(parse_time),
+        source: field(record, &SOURCE)
+            .map(text)
+            .unwrap_or_else(|| "Client".to_string()),
+        target,
+        request: format!("{} {}", method, path),
+        status,
+    })
+}
+
+// Draws the calls in a HAR file or request log between their client or
+// calling service and the hosts they went to, in time order.
+pub fn convert(
+    text: &str,
+    options: &HttpLogOptions,
+    warnings: &mut Vec<String>,
+) -> Result<String, String> {
+    let since = parse_bound(&options.since, "start")?;
+    let until = parse_bound(&options.until, "end")?;
+
+    let records = records(text)?;
+    let mut skipped = 0;
+    let mut calls: Vec<Call> = records
+        .iter()
+        .filter_map(|record| {
+            let call = call(record);
+            if call.is_none() {
+                skipped += 1;
+            }
+            call
+        })
+        .filter(|call| host_matches(&call.target, &options.hosts))
+        .filter(|call| match call.time {
+            Some(time) => {
+                since.iter().all(|since| time >= *since) && until.iter().all(|until| time <= *until)
+            }
+            None => true,
+        })
+        .collect();
+    if skipped > 0 {
+        warnings.push(format!(
+            "Skipped {} records without a method or URL",
+            skipped
+        ));
+    }
+    if calls.is_empty() {
+        return Err("No requests match the filters".to_string());
+    }
+    calls.sort_by_key(|call| call.time);
+    if calls.len() > MAX_CALLS {
+        warnings.push(format!(
+            "Showing the first {} of {} requests",
+            MAX_CALLS,
+            calls.len()
+        ));
+        calls.truncate(MAX_CALLS);
+    }
+
+    // Participants get ids in order of appearance, with their names as
+    // aliases since hosts contain `:` and other characters ids cannot.
+    let mut participants: Vec<String> = Vec::new();
+    for call in &calls {
+        for name in [&call.source, &call.target] {
+            if !participants.contains(name) {
+                participants.push(name.clone());
+            }
+        }
+    }
+    let id = |name: &str| {
+        format!(
+            "p{}",
+            participants.iter().position(|p| p == name).unwrap_or(0) + 1
+        )
+    };
+
+    let mut lines = vec!["sequenceDiagram".to_string()];
+    for (index, name) in participants.iter().enumerate() {
+        let kind = if calls.iter().any(|call| &call.target == name) {
+            "participant"
+        } else {
+            "actor"
+        };
+        lines.push(format!(
+            "    {} p{} as {}",
+            kind,
+            index + 1,
+            escape_text(name, &[])
+        ));
+    }
+    for call in &calls {
+        let (source, target) = (id(&call.source), id(&call.target));
+        lines.push(format!(
+            "    {}->>+{}: {}",
+            source,
+            target,
+            escape_text(&call.request, &[])
+        ));
+        lines.push(format!(
+            "    {}-->>-{}: {}",
+            target,
+            source,
+            escape_text(call.status.as_deref().unwrap_or("(no response)"), &[])
+        ));
+    }
+    Ok(lines.join("\n"))
+}
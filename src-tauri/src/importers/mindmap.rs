@@ -0,0 +1,168 @@
+use regex::Regex;
+
+// Headings beyond this would make the mindmap unreadable, so the rest are
+// dropped with a warning.
+const MAX_NODES: usize = 300;
+
+pub struct Node {
+    pub text: String,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    pub fn new(text: String) -> Self {
+        Node {
+            text,
+            children: Vec::new(),
+        }
+    }
+}
+
+// Plain text reads as a default node, but brackets would be read as a
+// shape, so such text is quoted inside a square node.
+fn node_line(text: &str, id: &mut usize) -> String {
+    let text = if text.is_empty() { "(empty)" } else { text };
+    if text.contains(['(', ')', '[', ']', '{', '}', '"']) || text.starts_with("::") {
+        *id += 1;
+        format!("n{}[\"{}\"]", id, text.replace('"', "'"))
+    } else {
+        text.to_string()
+    }
+}
+
+fn write_node(node: &Node, depth: usize, id: &mut usize, lines: &mut Vec<String>) {
+    lines.push(format!(
+        "{}{}",
+        "  ".repeat(depth),
+        node_line(&node.text, id)
+    ));
+    for child in &node.children {
+        write_node(child, depth + 1, id, lines);
+    }
+}
+
+pub fn render(root: &Node) -> String {
+    let mut lines = vec!["mindmap".to_string()];
+    let mut id = 0;
+    write_node(root, 1, &mut id, &mut lines);
+    lines.join("\n")
+}
+
+// Headings as (level, text), skipping fenced code blocks and frontmatter.
+// Both `# Title` and underlined setext headings count.
+fn headings(text: &str) -> (Option<String>, Vec<(usize, String)>) {
+    let atx = Regex::new(r"^ {0,3}(#{1,6})(?:\s+(.*?))?(?:\s+#+)?\s*$").unwrap();
+    let setext = Regex::new(r"^ {0,3}(=+|-+)\s*$").unwrap();
+    let front_title = Regex::new(r#"^title:\s*["']?(.*?)["']?\s*$"#).unwrap();
+
+    let mut lines = text.lines().peekable();
+    let mut title = None;
+    if lines.peek().map(|line| line.trim()) == Some("---") {
+        lines.next();
+        for line in lines.by_ref() {
+            if line.trim() == "---" {
+                break;
+            }
+            if let Some(caps) = front_title.captures(line) {
+                title = Some(caps[1].to_string());
+            }
+        }
+    }
+
+    let mut found = Vec::new();
+    let mut fence: Option<String> = None;
+    let mut previous: Option<&str> = None;
+    for line in lines {
+        let trimmed = line.trim_start();
+        if let Some(open) = &fence {
+            if trimmed.trim_end().starts_with(open.as_str()) {
+                fence = None;
+            }
+            continue;
+        }
+        let fence_len = trimmed
+            .chars()
+            .take_while(|&c| c == '`' || c == '~')
+            .count();
+        if fence_len >= 3 {
+            fence = Some(trimmed[..fence_len].to_string());
+            previous = None;
+            continue;
+        }
+
+        if let Some(caps) = atx.captures(line) {
+            let text = caps.get(2).map(|text| text.as_str()).unwrap_or_default();
+            found.push((caps[1].len(), inline_text(text)));
+            previous = None;
+            continue;
+        }
+        if let (Some(caps), Some(above)) = (setext.captures(line), previous) {
+            let level = if caps[1].starts_with('=') { 1 } else { 2 };
+            found.push((level, inline_text(above)));
+            previous = None;
+            continue;
+        }
+        previous = (!line.trim().is_empty() && !line.starts_with("    ")).then_some(line);
+    }
+    (title, found)
+}
+
+// Heading text without links, emphasis, code spans or inline HTML.
+fn inline_text(text: &str) -> String {
+    let link = Regex::new(r"!?\[([^\]]*)\]\([^)]*\)").unwrap();
+    let html = Regex::new(r"<[^>]+>").unwrap();
+    let text = link.replace_all(text.trim(), "$1");
+    let text = html.replace_all(&text, "");
+    text.replace(['*', '`'], "")
+        .replace("__", "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn has_headings(text: &str) -> bool {
+    !headings(text).1.is_empty()
+}
+
+// Turns the heading outline of a Markdown document into a mindmap. A lone
+// top-level heading is the root; otherwise the root is the frontmatter
+// title, and skipped levels nest under the nearest heading above.
+pub fn convert_headings(text: &str, warnings: &mut Vec<String>) -> Result<String, String> {
+    let (title, mut headings) = headings(text);
+    if headings.is_empty() {
+        return Err("The document has no headings".to_string());
+    }
+    if headings.len() > MAX_NODES {
+        warnings.push(format!(
+            "Showing the first {} of {} headings",
+            MAX_NODES,
+            headings.len()
+        ));
+        headings.truncate(MAX_NODES);
+    }
+
+    let top = headings.iter().map(|(level, _)| *level).min().unwrap_or(1);
+    let single_top =
+        headings.iter().filter(|(level, _)| *level == top).count() == 1 && headings[0].0 == top;
+    let root = if single_top {
+        Node::new(headings.remove(0).1)
+    } else {
+        Node::new(title.unwrap_or_else(|| "Document".to_string()))
+    };
+
+    // Open headings by level; the root sits below every level.
+    let mut stack: Vec<(usize, Node)> = vec![(0, root)];
+    for (level, text) in headings {
+        while stack.len() > 1 && stack.last().is_some_and(|(open, _)| *open >= level) {
+            let (_, node) = stack.pop().unwrap();
+            stack.last_mut().unwrap().1.children.push(node);
+        }
+        stack.push((level, Node::new(text)));
+    }
+    while stack.len() > 1 {
+        let (_, node) = stack.pop().unwrap();
+        stack.last_mut().unwrap().1.children.push(node);
+    }
+    let (_, root) = stack.pop().unwrap();
+    Ok(render(&root))
+}
@@ -0,0 +1,266 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{detect_diagram_type, markdown, mermaid_live, share};
+
+pub mod dot;
+pub mod gantt;
+pub mod http_log;
+pub mod mindmap;
+pub mod openapi;
+pub mod opml;
+pub mod outline;
+pub mod plantuml;
+pub mod prisma;
+pub mod sankey;
+pub mod sql;
+pub mod timeline;
+pub mod xstate;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    Mermaid,
+    Markdown,
+    MermaidLive,
+    ShareLink,
+    PlantUml,
+    Dot,
+    Outline,
+    Yaml,
+    Sql,
+    OpenApi,
+    TaskList,
+    HttpLog,
+    XState,
+    Timeline,
+    Prisma,
+    Opml,
+    MarkdownHeadings,
+    Sankey,
+}
+
+// Diagram text on one line, with `#` and `;`, which Mermaid reads as entity
+// codes and statement ends, written as entity codes along with any of
+// `extra` the statement it goes into gives a meaning.
+pub fn escape_text(text: &str, extra: &[char]) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .map(|c| match c {
+            '#' | ';' => format!("#{};", c as u32),
+            c if extra.contains(&c) => format!("#{};", c as u32),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportResult {
+    pub content: String,
+    pub detected: SourceKind,
+    pub warnings: Vec<String>,
+}
+
+pub fn detect(text: &str) -> Option<SourceKind> {
+    let trimmed = text.trim();
+
+    if mermaid_live::is_live_url(trimmed) {
+        return Some(SourceKind::MermaidLive);
+    }
+    if share::is_share_link(trimmed) {
+        return Some(SourceKind::ShareLink);
+    }
+    if trimmed.contains("@startuml") {
+        return Some(SourceKind::PlantUml);
+    }
+    if opml::is_opml(trimmed) {
+        return Some(SourceKind::Opml);
+    }
+
+    // Checked before Mermaid since `graph G {` would also match Mermaid's `graph`.
+    let dot_header = Regex::new(r"(?i)^\s*(strict\s+)?(di)?graph\b[^\n{]*\{").unwrap();
+    if dot_header.is_match(trimmed) {
+        return Some(SourceKind::Dot);
+    }
+
+    if detect_diagram_type(trimmed).is_some() {
+        return Some(SourceKind::Mermaid);
+    }
+    if !markdown::extract_code_blocks(trimmed).is_empty() {
+        return Some(SourceKind::Markdown);
+    }
+    if sql::is_sql(trimmed) {
+        return Some(SourceKind::Sql);
+    }
+    if prisma::is_prisma(trimmed) {
+        return Some(SourceKind::Prisma);
+    }
+    if openapi::is_openapi(trimmed) {
+        return Some(SourceKind::OpenApi);
+    }
+    if xstate::is_xstate(trimmed) {
+        return Some(SourceKind::XState);
+    }
+    if gantt::is_task_list(trimmed) {
+        return Some(SourceKind::TaskList);
+    }
+    if http_log::is_http_log(trimmed) {
+        return Some(SourceKind::HttpLog);
+    }
+    if timeline::is_event_list(trimmed) {
+        return Some(SourceKind::Timeline);
+    }
+    if sankey::is_flow_list(trimmed) {
+        return Some(SourceKind::Sankey);
+    }
+
+    None
+}
+
+// Converts `text` as a source of the given kind.
+pub fn convert(kind: SourceKind, text: &str) -> Result<ImportResult, String> {
+    let mut warnings = Vec::new();
+
+    let content = match kind {
+        SourceKind::Mermaid => text.trim().to_string(),
+        SourceKind::Markdown => {
+            let mut blocks = markdown::extract_code_blocks(text);
+            if blocks.len() > 1 {
+                warnings.push(format!(
+                    "Found {} mermaid blocks, imported the first one",
+                    blocks.len()
+                ));
+            }
+            blocks.remove(0)
+        }
+        SourceKind::MermaidLive => mermaid_live::decode_url(text)?,
+        SourceKind::ShareLink => share::decode_link(text)?,
+        SourceKind::PlantUml => plantuml::convert(text, &mut warnings)?,
+        SourceKind::Dot => dot::convert(text, &mut warnings)?,
+        SourceKind::Outline => outline::convert_outline(text, &mut warnings)?,
+        SourceKind::Yaml => outline::convert_yaml(text, &mut warnings)?,
+        SourceKind::Sql => sql::convert(text, &mut warnings)?,
+        SourceKind::Prisma => prisma::convert(text, &mut warnings)?,
+        SourceKind::Opml => opml::convert(text, &mut warnings)?,
+        SourceKind::MarkdownHeadings => mindmap::convert_headings(text, &mut warnings)?,
+        SourceKind::Sankey => sankey::convert(text, &mut warnings)?,
+        SourceKind::OpenApi => openapi::convert(text, &mut warnings)?,
+        SourceKind::TaskList => gantt::convert(text, &mut warnings)?,
+        SourceKind::XState => xstate::convert(text, &mut warnings)?,
+        SourceKind::HttpLog => {
+            http_log::convert(text, &http_log::HttpLogOptions::default(), &mut warnings)?
+        }
+        SourceKind::Timeline => {
+            timeline::convert(text, &timeline::TimelineOptions::default(), &mut warnings)?
+        }
+    };
+
+    Ok(ImportResult {
+        content,
+        detected: kind,
+        warnings,
+    })
+}
+
+pub fn import_text(text: &str) -> Result<ImportResult, String> {
+    let detected = detect(text).ok_or("Clipboard does not contain a recognized diagram")?;
+    convert(detected, text)
+}
+
+// Picks the generator for a source file from its extension, falling back to
+// the content for extensions several formats share.
+pub fn source_kind(path: &Path, text: &str) -> Option<SourceKind> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "sql" | "ddl" => Some(SourceKind::Sql),
+        "prisma" => Some(SourceKind::Prisma),
+        "opml" => Some(SourceKind::Opml),
+        // Documents without diagrams of their own are drawn as a mindmap
+        // of their headings.
+        "md" | "markdown"
+            if markdown::extract_code_blocks(text).is_empty() && mindmap::has_headings(text) =>
+        {
+            Some(SourceKind::MarkdownHeadings)
+        }
+        "puml" | "plantuml" | "pu" => Some(SourceKind::PlantUml),
+        "dot" | "gv" => Some(SourceKind::Dot),
+        "csv" | "tsv" if gantt::is_task_list(text) => Some(SourceKind::TaskList),
+        "csv" | "tsv" if timeline::is_event_list(text) => Some(SourceKind::Timeline),
+        "csv" | "tsv" if sankey::is_flow_list(text) => Some(SourceKind::Sankey),
+        "har" => Some(SourceKind::HttpLog),
+        "json" if xstate::is_xstate(text) => Some(SourceKind::XState),
+        "json" | "jsonl" | "ndjson" | "log" if http_log::is_http_log(text) => {
+            Some(SourceKind::HttpLog)
+        }
+        "json" if timeline::is_event_list(text) => Some(SourceKind::Timeline),
+        "yaml" | "yml" | "json" if openapi::is_openapi(text) => Some(SourceKind::OpenApi),
+        "yaml" | "yml" if outline::is_yaml_description(text) => Some(SourceKind::Yaml),
+        _ => detect(text),
+    }
+}
+
+pub fn import_http_log(
+    text: &str,
+    options: &http_log::HttpLogOptions,
+) -> Result<ImportResult, String> {
+    let mut warnings = Vec::new();
+    let content = http_log::convert(text, options, &mut warnings)?;
+    Ok(ImportResult {
+        content,
+        detected: SourceKind::HttpLog,
+        warnings,
+    })
+}
+
+pub fn import_timeline(
+    text: &str,
+    options: &timeline::TimelineOptions,
+) -> Result<ImportResult, String> {
+    let mut warnings = Vec::new();
+    let content = timeline::convert(text, options, &mut warnings)?;
+    Ok(ImportResult {
+        content,
+        detected: SourceKind::Timeline,
+        warnings,
+    })
+}
+
+pub fn import_markdown_headings(text: &str) -> Result<ImportResult, String> {
+    let mut warnings = Vec::new();
+    let content = mindmap::convert_headings(text, &mut warnings)?;
+    Ok(ImportResult {
+        content,
+        detected: SourceKind::MarkdownHeadings,
+        warnings,
+    })
+}
+
+// Outlines and YAML descriptions are never auto-detected from the clipboard
+// since almost any text is a valid outline.
+pub fn import_description(text: &str, format: Option<&str>) -> Result<ImportResult, String> {
+    let detected = match format {
+        Some("outline") => SourceKind::Outline,
+        Some("yaml") => SourceKind::Yaml,
+        Some(other) => return Err(format!("Unknown description format: {}", other)),
+        None if outline::is_yaml_description(text) => SourceKind::Yaml,
+        None => SourceKind::Outline,
+    };
+    let mut warnings = Vec::new();
+
+    let content = match detected {
+        SourceKind::Yaml => outline::convert_yaml(text, &mut warnings)?,
+        _ => outline::convert_outline(text, &mut warnings)?,
+    };
+
+    Ok(ImportResult {
+        content,
+        detected,
+        warnings,
+    })
+}
@@ -0,0 +1,121 @@
+use serde_yaml::Value;
+
+use super::escape_text;
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+// OpenAPI 3 and Swagger 2 documents, in YAML or JSON.
+pub fn is_openapi(text: &str) -> bool {
+    serde_yaml::from_str::<Value>(text)
+        .map(|value| value.get("openapi").is_some() || value.get("swagger").is_some())
+        .unwrap_or(false)
+}
+
+// Draws a sequence diagram of a client calling each operation of an
+// OpenAPI document in order, answered with its first success response.
+// Operations are grouped under a note whenever their first tag changes.
+pub fn convert(text: &str, warnings: &mut Vec<String>) -> Result<String, String> {
+    let value: Value = serde_yaml::from_str(text)
+        .map_err(|e| format!("Failed to parse OpenAPI document: {}", e))?;
+    if value.get("openapi").is_none() && value.get("swagger").is_none() {
+        return Err("Not an OpenAPI document".to_string());
+    }
+    let paths = value
+        .get("paths")
+        .and_then(Value::as_mapping)
+        .ok_or("OpenAPI document has no paths")?;
+
+    let title = value
+        .get("info")
+        .and_then(|info| info.get("title"))
+        .and_then(Value::as_str);
+    let mut lines = vec![
+        "sequenceDiagram".to_string(),
+        "    actor Client".to_string(),
+        format!(
+            "    participant API as {}",
+            escape_text(title.unwrap_or("API"), &[])
+        ),
+    ];
+    let mut current_tag: Option<String> = None;
+    let mut operations = 0;
+
+    for (path, item) in paths {
+        let Some(path) = path.as_str() else {
+            continue;
+        };
+        let Some(item) = item.as_mapping() else {
+            continue;
+        };
+        for (method, operation) in item {
+            let Some(method) = method.as_str().filter(|method| METHODS.contains(method)) else {
+                continue;
+            };
+            operations += 1;
+
+            let tag = operation
+                .get("tags")
+                .and_then(Value::as_sequence)
+                .and_then(|tags| tags.first())
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if tag.is_some() && tag != current_tag {
+                lines.push(format!(
+                    "    Note over Client,API: {}",
+                    escape_text(tag.as_deref().unwrap_or_default(), &[])
+                ));
+                current_tag = tag;
+            }
+
+            let mut request = format!("{} {}", method.to_uppercase(), path);
+            if let Some(summary) = operation.get("summary").and_then(Value::as_str) {
+                request.push_str(&format!(" ({})", summary.trim()));
+            }
+            lines.push(format!("    Client->>+API: {}", escape_text(&request, &[])));
+            lines.push(format!(
+                "    API-->>-Client: {}",
+                escape_text(&response(operation), &[])
+            ));
+        }
+    }
+
+    if operations == 0 {
+        warnings.push("OpenAPI document has no operations".to_string());
+    }
+
+    let content = lines.join("\n");
+    Ok(match title {
+        Some(title) => crate::markdown::with_title(&content, title),
+        None => content,
+    })
+}
+
+// The first 2xx response, falling back to `default` and then to whatever
+// comes first.
+fn response(operation: &Value) -> String {
+    let Some(responses) = operation.get("responses").and_then(Value::as_mapping) else {
+        return "response".to_string();
+    };
+    let code = |key: &Value| match key {
+        Value::String(code) => code.clone(),
+        Value::Number(code) => code.to_string(),
+        _ => String::new(),
+    };
+
+    let chosen = responses
+        .iter()
+        .find(|(key, _)| code(key).starts_with('2'))
+        .or_else(|| responses.iter().find(|(key, _)| code(key) == "default"))
+        .or_else(|| responses.iter().next());
+    match chosen {
+        Some((key, response)) => match response.get("description").and_then(Value::as_str) {
+            Some(description) if !description.trim().is_empty() => {
+                format!("{} {}", code(key), description.trim())
+            }
+            _ => code(key),
+        },
+        None => "response".to_string(),
+    }
+}
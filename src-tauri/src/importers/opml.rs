@@ -0,0 +1,235 @@
+use regex::Regex;
+
+use super::mindmap::{render, Node};
+
+// Mermaid's node shapes as opening and closing delimiters, longest first
+// so `((` is not read as `(`.
+const SHAPES: [(&str, &str); 6] = [
+    ("((", "))"),
+    ("))", "(("),
+    ("{{", "}}"),
+    ("(", ")"),
+    (")", "("),
+    ("[", "]"),
+];
+
+pub fn is_opml(text: &str) -> bool {
+    Regex::new(r"(?i)<opml[\s>]").unwrap().is_match(text)
+}
+
+fn unescape(value: &str) -> String {
+    let numeric = Regex::new(r"&#(x[0-9a-fA-F]+|[0-9]+);").unwrap();
+    let value = numeric.replace_all(value, |caps: &regex::Captures| {
+        let code = &caps[1];
+        let code = match code.strip_prefix('x') {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => code.parse().ok(),
+        };
+        code.and_then(char::from_u32)
+            .map(String::from)
+            .unwrap_or_default()
+    });
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    Regex::new(&format!(r#"(?s)\s{}\s*=\s*(?:"([^"]*)"|'([^']*)')"#, name))
+        .unwrap()
+        .captures(tag)
+        .and_then(|caps| caps.get(1).or_else(|| caps.get(2)))
+        .map(|value| unescape(value.as_str()))
+}
+
+// Reads the `<outline>` elements of the body as a tree. Outliners put the
+// item in `text`; some exports use `title` instead.
+fn parse_outlines(body: &str, warnings: &mut Vec<String>) -> Vec<Node> {
+    let tag = Regex::new(r"(?s)<(/?)outline\b([^>]*?)(/?)>").unwrap();
+    let mut stack: Vec<Node> = vec![Node::new(String::new())];
+    for caps in tag.captures_iter(body) {
+        if !caps[1].is_empty() {
+            if stack.len() > 1 {
+                let node = stack.pop().unwrap();
+                stack.last_mut().unwrap().children.push(node);
+            }
+            continue;
+        }
+        let text = attribute(&caps[2], "text")
+            .or_else(|| attribute(&caps[2], "title"))
+            .unwrap_or_default();
+        let node = Node::new(text.split_whitespace().collect::<Vec<_>>().join(" "));
+        if caps[3].is_empty() {
+            stack.push(node);
+        } else {
+            stack.last_mut().unwrap().children.push(node);
+        }
+    }
+    if stack.len() > 1 {
+        warnings.push("The OPML file has unclosed outline elements".to_string());
+        while stack.len() > 1 {
+            let node = stack.pop().unwrap();
+            stack.last_mut().unwrap().children.push(node);
+        }
+    }
+    stack.pop().map(|root| root.children).unwrap_or_default()
+}
+
+// Converts an OPML outline, as exported by Workflowy, XMind and other
+// outliners, into a mindmap. A single top-level outline is the root;
+// several hang from a root named after the document title.
+pub fn convert(text: &str, warnings: &mut Vec<String>) -> Result<String, String> {
+    let body = Regex::new(r"(?is)<body[^>]*>(.*)</body>")
+        .unwrap()
+        .captures(text)
+        .map(|caps| caps[1].to_string())
+        .ok_or("The OPML file has no body")?;
+    let mut outlines = parse_outlines(&body, warnings);
+    if outlines.is_empty() {
+        return Err("The OPML file has no outline items".to_string());
+    }
+
+    let root = if outlines.len() == 1 {
+        outlines.remove(0)
+    } else {
+        let title = Regex::new(r"(?is)<title>(.*?)</title>")
+            .unwrap()
+            .captures(text)
+            .map(|caps| unescape(caps[1].trim()))
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| "Outline".to_string());
+        Node {
+            text: title,
+            children: outlines,
+        }
+    };
+
+    Ok(render(&root))
+}
+
+// The text of a mindmap node line, without its id, shape or quotes.
+fn node_text(line: &str) -> String {
+    let mut text = line.trim();
+    if let Some(start) = text.find(['(', ')', '[', '{']) {
+        let (id, shaped) = text.split_at(start);
+        if !id.contains(char::is_whitespace) {
+            if let Some(inner) = SHAPES
+                .iter()
+                .find_map(|(open, close)| shaped.strip_prefix(open)?.strip_suffix(close))
+            {
+                text = inner.trim();
+            }
+        }
+    }
+    let text = text
+        .strip_prefix("\"`")
+        .and_then(|text| text.strip_suffix("`\""))
+        .or_else(|| {
+            text.strip_prefix('"')
+                .and_then(|text| text.strip_suffix('"'))
+        })
+        .unwrap_or(text);
+    Regex::new(r"(?i)<br\s*/?>")
+        .unwrap()
+        .replace_all(text, " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Writes a mindmap as an OPML 2.0 outline. Icons and classes are Mermaid
+// styling and are left out; the root's text becomes the document title.
+pub fn to_opml(content: &str) -> Result<String, String> {
+    let mut lines = content
+        .lines()
+        .map(|line| (line, line.trim()))
+        .filter(|(_, trimmed)| !trimmed.is_empty() && !trimmed.starts_with("%%"));
+    // Skip a frontmatter block before the header.
+    let mut header = lines.next();
+    if header.is_some_and(|(_, trimmed)| trimmed == "---") {
+        for (_, trimmed) in lines.by_ref() {
+            if trimmed == "---" {
+                break;
+            }
+        }
+        header = lines.next();
+    }
+    if !header.is_some_and(|(_, trimmed)| trimmed.starts_with("mindmap")) {
+        return Err("Only mindmaps can be exported as OPML".to_string());
+    }
+
+    // Nodes with their indentation, nested by comparing it with the
+    // indentation of the nodes still open.
+    let mut stack: Vec<(usize, Node)> = Vec::new();
+    let mut roots: Vec<Node> = Vec::new();
+    let close = |stack: &mut Vec<(usize, Node)>, roots: &mut Vec<Node>| {
+        let (_, node) = stack.pop().unwrap();
+        match stack.last_mut() {
+            Some((_, parent)) => parent.children.push(node),
+            None => roots.push(node),
+        }
+    };
+    for (line, trimmed) in lines {
+        if trimmed.starts_with("::icon(") || trimmed.starts_with(":::") {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        while stack.last().is_some_and(|(open, _)| *open >= indent) {
+            close(&mut stack, &mut roots);
+        }
+        stack.push((indent, Node::new(node_text(trimmed))));
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots);
+    }
+    if roots.is_empty() {
+        return Err("The mindmap has no nodes".to_string());
+    }
+
+    let mut output = vec![
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_string(),
+        "<opml version=\"2.0\">".to_string(),
+        "  <head>".to_string(),
+        format!("    <title>{}</title>", escape(&roots[0].text)),
+        "  </head>".to_string(),
+        "  <body>".to_string(),
+    ];
+    for root in &roots {
+        write_outline(root, 2, &mut output);
+    }
+    output.push("  </body>".to_string());
+    output.push("</opml>".to_string());
+    Ok(output.join("\n") + "\n")
+}
+
+fn write_outline(node: &Node, depth: usize, output: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    if node.children.is_empty() {
+        output.push(format!(
+            "{}<outline text=\"{}\"/>",
+            indent,
+            escape(&node.text)
+        ));
+        return;
+    }
+    output.push(format!(
+        "{}<outline text=\"{}\">",
+        indent,
+        escape(&node.text)
+    ));
+    for child in &node.children {
+        write_outline(child, depth + 1, output);
+    }
+    output.push(format!("{}</outline>", indent));
+}
@@ -0,0 +1,378 @@
+use serde_yaml::Value;
+
+// Drafts a flowchart from a plain description of a process, either as an
+// indented outline:
+//
+//   Start
+//   - Receive order
+//   - Is it paid?
+//     - yes: Ship order
+//     - no: Send reminder
+//   - End
+//
+// or as YAML with `steps`, where a step is a string, a
+// `{ decision, branches }` map or a `{ group, steps }` map. Steps run in
+// order; an item ending in `?` is a decision whose children are its
+// branches, and any other item with children becomes a subgraph.
+#[derive(Debug, Clone)]
+enum Step {
+    Action(String),
+    Decision {
+        question: String,
+        branches: Vec<(Option<String>, Vec<Step>)>,
+    },
+    Group {
+        name: String,
+        steps: Vec<Step>,
+    },
+}
+
+struct OutlineItem<'a> {
+    text: &'a str,
+    children: Vec<OutlineItem<'a>>,
+}
+
+pub fn is_yaml_description(text: &str) -> bool {
+    serde_yaml::from_str::<Value>(text)
+        .map(|value| value.get("steps").is_some())
+        .unwrap_or(false)
+}
+
+pub fn convert_outline(text: &str, warnings: &mut Vec<String>) -> Result<String, String> {
+    let items: Vec<(usize, &str)> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|line| (indent_width(line), strip_bullet(line.trim())))
+        .collect();
+    if items.is_empty() {
+        return Err("The outline is empty".to_string());
+    }
+
+    let mut position = 0;
+    let tree = build_tree(&items, &mut position, warnings);
+    render(&outline_steps(&tree), "TD", None)
+}
+
+pub fn convert_yaml(text: &str, warnings: &mut Vec<String>) -> Result<String, String> {
+    let value: Value =
+        serde_yaml::from_str(text).map_err(|e| format!("Failed to parse YAML: {}", e))?;
+    let steps = value
+        .get("steps")
+        .and_then(Value::as_sequence)
+        .ok_or("YAML description needs a `steps` list")?;
+
+    let direction = value
+        .get("direction")
+        .and_then(Value::as_str)
+        .map(str::to_uppercase)
+        .unwrap_or_else(|| "TD".to_string());
+    if !["TD", "TB", "BT", "LR", "RL"].contains(&direction.as_str()) {
+        return Err(format!("Unknown direction: {}", direction));
+    }
+
+    render(
+        &yaml_steps(steps, warnings),
+        &direction,
+        value.get("title").and_then(Value::as_str),
+    )
+}
+
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+fn strip_bullet(item: &str) -> &str {
+    if let Some(rest) = item
+        .strip_prefix("- ")
+        .or_else(|| item.strip_prefix("* "))
+        .or_else(|| item.strip_prefix("+ "))
+    {
+        return rest.trim();
+    }
+
+    // Numbered lists: `1.` or `1)`.
+    let digits = item.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        let rest = &item[digits..];
+        if let Some(rest) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return rest.trim();
+        }
+    }
+
+    item
+}
+
+fn build_tree<'a>(
+    items: &[(usize, &'a str)],
+    position: &mut usize,
+    warnings: &mut Vec<String>,
+) -> Vec<OutlineItem<'a>> {
+    let indent = items[*position].0;
+    let mut tree = Vec::new();
+
+    while let Some(&(item_indent, text)) = items.get(*position) {
+        if item_indent < indent {
+            break;
+        }
+        if item_indent > indent {
+            warnings.push(format!("Unexpected indentation before '{}'", text));
+        }
+        *position += 1;
+
+        let children = if items
+            .get(*position)
+            .is_some_and(|&(next, _)| next > item_indent)
+        {
+            build_tree(items, position, warnings)
+        } else {
+            Vec::new()
+        };
+        tree.push(OutlineItem { text, children });
+    }
+
+    tree
+}
+
+fn outline_steps(items: &[OutlineItem]) -> Vec<Step> {
+    items
+        .iter()
+        .map(|item| {
+            if item.text.ends_with('?') {
+                Step::Decision {
+                    question: item.text.to_string(),
+                    branches: item.children.iter().map(outline_branch).collect(),
+                }
+            } else if item.children.is_empty() {
+                Step::Action(item.text.to_string())
+            } else {
+                Step::Group {
+                    name: item.text.to_string(),
+                    steps: outline_steps(&item.children),
+                }
+            }
+        })
+        .collect()
+}
+
+// `yes: Ship order` labels the edge with `yes` and starts the branch with
+// `Ship order`; nested items continue the branch, or become the branches of
+// a follow-up question.
+fn outline_branch(item: &OutlineItem) -> (Option<String>, Vec<Step>) {
+    let (label, first) = match item.text.split_once(':') {
+        Some((label, rest)) => (Some(label.trim().to_string()), rest.trim()),
+        None if item.children.is_empty() => (None, item.text),
+        None => (Some(item.text.to_string()), ""),
+    };
+
+    let steps = if first.is_empty() {
+        outline_steps(&item.children)
+    } else if first.ends_with('?') {
+        outline_steps(&[OutlineItem {
+            text: first,
+            children: item
+                .children
+                .iter()
+                .map(|child| OutlineItem {
+                    text: child.text,
+                    children: Vec::new(),
+                })
+                .collect(),
+        }])
+    } else {
+        let mut steps = vec![Step::Action(first.to_string())];
+        steps.extend(outline_steps(&item.children));
+        steps
+    };
+
+    (label, steps)
+}
+
+fn yaml_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Bool(flag) => Some(if *flag { "yes" } else { "no" }.to_string()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+fn yaml_steps(values: &[Value], warnings: &mut Vec<String>) -> Vec<Step> {
+    values
+        .iter()
+        .filter_map(|value| yaml_step(value, warnings))
+        .collect()
+}
+
+fn yaml_branch_steps(value: &Value, warnings: &mut Vec<String>) -> Vec<Step> {
+    match value {
+        Value::Sequence(steps) => yaml_steps(steps, warnings),
+        Value::Null => Vec::new(),
+        other => yaml_step(other, warnings).into_iter().collect(),
+    }
+}
+
+fn yaml_step(value: &Value, warnings: &mut Vec<String>) -> Option<Step> {
+    if let Some(text) = yaml_text(value) {
+        return Some(Step::Action(text));
+    }
+
+    if let Some(question) = value.get("decision").and_then(yaml_text) {
+        // Unquoted `true`/`false` branch keys are booleans, which yaml_text maps to yes/no.
+        let branches = match value.get("branches") {
+            Some(Value::Mapping(map)) => map
+                .iter()
+                .map(|(label, steps)| (yaml_text(label), yaml_branch_steps(steps, warnings)))
+                .collect(),
+            Some(Value::Sequence(list)) => list
+                .iter()
+                .map(|branch| {
+                    (
+                        branch.get("label").and_then(yaml_text),
+                        branch
+                            .get("steps")
+                            .map(|steps| yaml_branch_steps(steps, warnings))
+                            .unwrap_or_default(),
+                    )
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        return Some(Step::Decision { question, branches });
+    }
+
+    if let Some(name) = value.get("group").and_then(yaml_text) {
+        let steps = value
+            .get("steps")
+            .map(|steps| yaml_branch_steps(steps, warnings))
+            .unwrap_or_default();
+        return Some(Step::Group { name, steps });
+    }
+
+    warnings.push(format!(
+        "Skipped a step that is not a string, decision or group: {}",
+        serde_yaml::to_string(value).unwrap_or_default().trim()
+    ));
+    None
+}
+
+type Exit = (String, Option<String>);
+
+struct Renderer {
+    lines: Vec<String>,
+    next_id: usize,
+    depth: usize,
+}
+
+impl Renderer {
+    fn push(&mut self, line: String) {
+        self.lines
+            .push(format!("{}{}", "    ".repeat(self.depth), line));
+    }
+
+    fn id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}{}", prefix, self.next_id)
+    }
+
+    fn link(&mut self, entries: &[Exit], target: &str) {
+        for (source, label) in entries {
+            match label {
+                Some(label) => self.push(format!(
+                    "{} -->|{}| {}",
+                    source,
+                    escape_label(label),
+                    target
+                )),
+                None => self.push(format!("{} --> {}", source, target)),
+            }
+        }
+    }
+
+    // Emits `steps` in order, starting from `entries`, and returns the open
+    // ends that the next step should connect from.
+    fn emit(&mut self, steps: &[Step], mut entries: Vec<Exit>) -> Vec<Exit> {
+        for step in steps {
+            entries = match step {
+                Step::Action(text) => {
+                    let id = self.id("n");
+                    let terminal = is_terminal(text);
+                    if terminal {
+                        self.push(format!("{}([\"{}\"])", id, escape_label(text)));
+                    } else {
+                        self.push(format!("{}[\"{}\"]", id, escape_label(text)));
+                    }
+                    self.link(&entries, &id);
+
+                    let ends = ["end", "stop", "done", "finish"];
+                    if terminal && ends.contains(&text.trim().to_lowercase().as_str()) {
+                        Vec::new()
+                    } else {
+                        vec![(id, None)]
+                    }
+                }
+                Step::Decision { question, branches } => {
+                    let id = self.id("n");
+                    self.push(format!("{}{{\"{}\"}}", id, escape_label(question)));
+                    self.link(&entries, &id);
+
+                    if branches.is_empty() {
+                        vec![(id, None)]
+                    } else {
+                        let mut exits = Vec::new();
+                        for (label, steps) in branches {
+                            let start = vec![(id.clone(), label.clone())];
+                            if steps.is_empty() {
+                                exits.extend(start);
+                            } else {
+                                exits.extend(self.emit(steps, start));
+                            }
+                        }
+                        exits
+                    }
+                }
+                Step::Group { name, steps } => {
+                    let id = self.id("g");
+                    self.push(format!("subgraph {}[\"{}\"]", id, escape_label(name)));
+                    self.depth += 1;
+                    let exits = self.emit(steps, entries);
+                    self.depth -= 1;
+                    self.push("end".to_string());
+                    exits
+                }
+            };
+        }
+
+        entries
+    }
+}
+
+fn is_terminal(text: &str) -> bool {
+    let lowered = text.trim().to_lowercase();
+    ["start", "begin", "end", "stop", "done", "finish"].contains(&lowered.as_str())
+}
+
+fn escape_label(label: &str) -> String {
+    label.replace('"', "#quot;")
+}
+
+fn render(steps: &[Step], direction: &str, title: Option<&str>) -> Result<String, String> {
+    if steps.is_empty() {
+        return Err("The description has no steps".to_string());
+    }
+
+    let mut renderer = Renderer {
+        lines: vec![format!("flowchart {}", direction)],
+        next_id: 0,
+        depth: 1,
+    };
+    renderer.emit(steps, Vec::new());
+    let content = renderer.lines.join("\n");
+
+    Ok(match title {
+        Some(title) => crate::markdown::with_title(&content, title),
+        None => content,
+    })
+}
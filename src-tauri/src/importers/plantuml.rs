@@ -0,0 +1,209 @@
+use regex::Regex;
+
+const PARTICIPANT_KINDS: [&str; 8] = [
+    "participant",
+    "actor",
+    "boundary",
+    "control",
+    "entity",
+    "database",
+    "collections",
+    "queue",
+];
+
+// Converts the sequence diagram subset of PlantUML. Lines that have no
+// Mermaid equivalent are kept as %% comments and reported as warnings.
+pub fn convert(source: &str, warnings: &mut Vec<String>) -> Result<String, String> {
+    let body: Vec<&str> = source
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| !line.starts_with("@startuml"))
+        .skip(1)
+        .take_while(|line| !line.starts_with("@enduml"))
+        .collect();
+
+    let message = Regex::new(
+        r#"^("[^"]+"|[\w.]+)\s*(<<?-{1,2}|-{1,2}>>?|-{1,2}[xX]|-{1,2}\\\\?|-{1,2}/)\s*("[^"]+"|[\w.]+)\s*(?::\s*(.*))?$"#,
+    )
+    .unwrap();
+    let participant =
+        Regex::new(r#"^(\w+)\s+("[^"]+"|[\w.]+)(?:\s+as\s+("[^"]+"|[\w.]+))?"#).unwrap();
+
+    let mut output = vec!["sequenceDiagram".to_string()];
+    let mut title = None;
+    let mut open_note: Option<(String, Vec<&str>)> = None;
+    let mut has_messages = false;
+
+    for line in body {
+        if let Some((head, mut text)) = open_note.take() {
+            if line.eq_ignore_ascii_case("end note") {
+                output.push(format!("    {}", convert_note(&head, &text.join("<br/>"))));
+            } else {
+                text.push(line);
+                open_note = Some((head, text));
+            }
+            continue;
+        }
+
+        if line.is_empty() || line.starts_with('\'') {
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("title ") {
+            title = Some(rest.trim().to_string());
+            continue;
+        }
+
+        if let Some(caps) = participant.captures(line) {
+            let kind = caps[1].to_lowercase();
+            if PARTICIPANT_KINDS.contains(&kind.as_str()) {
+                output.push(convert_participant(
+                    &kind,
+                    &caps[2],
+                    caps.get(3).map(|m| m.as_str()),
+                ));
+                continue;
+            }
+        }
+
+        if let Some(caps) = message.captures(line) {
+            let (from, to, arrow) = convert_arrow(&caps[1], &caps[3], &caps[2]);
+            let text = caps.get(4).map(|m| m.as_str().trim()).unwrap_or_default();
+            output.push(
+                format!("    {}{}{}: {}", from, arrow, to, text)
+                    .trim_end()
+                    .to_string(),
+            );
+            has_messages = true;
+            continue;
+        }
+
+        let keyword = line
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match keyword.as_str() {
+            "alt" | "else" | "loop" | "opt" | "par" | "critical" | "break" | "end" => {
+                output.push(format!("    {}", line));
+            }
+            "group" => {
+                output.push("    rect rgba(0, 0, 0, 0.05)".to_string());
+                output.push(format!("    %% {}", line));
+            }
+            "activate" | "deactivate" => {
+                let target = line.split_whitespace().nth(1).unwrap_or_default();
+                output.push(format!("    {} {}", keyword, participant_id(target)));
+            }
+            "autonumber" => output.push("    autonumber".to_string()),
+            "note" => match line.split_once(':') {
+                Some((head, text)) => output.push(format!("    {}", convert_note(head, text))),
+                None => open_note = Some((line.to_string(), Vec::new())),
+            },
+            _ => {
+                warnings.push(format!("Unsupported PlantUML line: {}", line));
+                output.push(format!("    %% {}", line));
+            }
+        }
+    }
+
+    if !has_messages {
+        return Err("Only PlantUML sequence diagrams can be converted".to_string());
+    }
+
+    if let Some(title) = title {
+        return Ok(crate::markdown::with_title(&output.join("\n"), &title));
+    }
+
+    Ok(output.join("\n"))
+}
+
+fn unquote(value: &str) -> &str {
+    value.trim_matches('"')
+}
+
+fn participant_id(name: &str) -> String {
+    unquote(name)
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn convert_participant(kind: &str, name: &str, alias: Option<&str>) -> String {
+    let keyword = if kind == "actor" {
+        "actor"
+    } else {
+        "participant"
+    };
+
+    // PlantUML writes `participant "Label" as Id`, and also `participant Id as "Label"`.
+    let (id, label) = match alias {
+        Some(alias) if name.starts_with('"') => (participant_id(alias), unquote(name).to_string()),
+        Some(alias) => (participant_id(name), unquote(alias).to_string()),
+        None => (participant_id(name), unquote(name).to_string()),
+    };
+
+    if id == label {
+        format!("    {} {}", keyword, id)
+    } else {
+        format!("    {} {} as {}", keyword, id, label)
+    }
+}
+
+fn convert_arrow(from: &str, to: &str, arrow: &str) -> (String, String, &'static str) {
+    let reversed = arrow.starts_with('<');
+    let dashed = arrow.contains("--");
+
+    let mermaid_arrow = if arrow.ends_with(['x', 'X']) {
+        if dashed {
+            "--x"
+        } else {
+            "-x"
+        }
+    } else if arrow.ends_with(">>")
+        || arrow.starts_with("<<")
+        || arrow.ends_with('\\')
+        || arrow.ends_with('/')
+    {
+        if dashed {
+            "--)"
+        } else {
+            "-)"
+        }
+    } else if dashed {
+        "-->>"
+    } else {
+        "->>"
+    };
+
+    if reversed {
+        (participant_id(to), participant_id(from), mermaid_arrow)
+    } else {
+        (participant_id(from), participant_id(to), mermaid_arrow)
+    }
+}
+
+fn convert_note(head: &str, text: &str) -> String {
+    let words: Vec<&str> = head.split_whitespace().collect();
+
+    let placement = match words.get(1).copied() {
+        Some("left") => "left of",
+        Some("right") => "right of",
+        _ => "over",
+    };
+    let targets: Vec<String> = words
+        .iter()
+        .skip(if placement == "over" { 2 } else { 3 })
+        .flat_map(|w| w.split(','))
+        .filter(|w| !w.is_empty())
+        .map(participant_id)
+        .collect();
+
+    format!("Note {} {}: {}", placement, targets.join(","), text.trim())
+}
@@ -0,0 +1,286 @@
+use regex::Regex;
+
+struct Field {
+    name: String,
+    ty: String,
+    optional: bool,
+    list: bool,
+    keys: Vec<&'static str>,
+    // `@relation` name and the local fields it holds, if any.
+    relation: Option<String>,
+    holds: Vec<String>,
+}
+
+struct Model {
+    name: String,
+    fields: Vec<Field>,
+}
+
+pub fn is_prisma(text: &str) -> bool {
+    Regex::new(r"(?m)^\s*model\s+\w+\s*\{")
+        .unwrap()
+        .is_match(text)
+}
+
+// Drops `//` and `///` comments outside string literals.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut previous = ' ';
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' if previous != '\\' => quoted = !quoted,
+            '/' if !quoted && previous == '/' => return &line[..index - 1],
+            _ => {}
+        }
+        previous = c;
+    }
+    line
+}
+
+// The names in the `[a, b]` list following `key:` in attribute arguments.
+fn list_argument(arguments: &str, key: &str) -> Vec<String> {
+    Regex::new(&format!(r"\b{}\s*:\s*\[([^\]]*)\]", key))
+        .unwrap()
+        .captures(arguments)
+        .map(|caps| names(&caps[1]))
+        .unwrap_or_default()
+}
+
+// Field names in a block attribute list such as `[a, b(sort: Desc)]`.
+fn names(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|name| {
+            name.split('(')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+// The relation name: the first string argument or `name:`.
+fn relation_name(arguments: &str) -> Option<String> {
+    Regex::new(r#"^\s*(?:name\s*:\s*)?"([^"]*)""#)
+        .unwrap()
+        .captures(arguments)
+        .or_else(|| {
+            Regex::new(r#"\bname\s*:\s*"([^"]*)""#)
+                .unwrap()
+                .captures(arguments)
+        })
+        .map(|caps| caps[1].to_string())
+}
+
+fn parse_field(line: &str) -> Option<Field> {
+    let caps = Regex::new(r"^(\w+)\s+([\w.]+)(\[\])?(\?)?\s*(.*)$")
+        .unwrap()
+        .captures(line)?;
+    let attributes = &caps[5];
+    let mut field = Field {
+        name: caps[1].to_string(),
+        ty: caps[2].to_string(),
+        list: caps.get(3).is_some(),
+        optional: caps.get(4).is_some(),
+        keys: Vec::new(),
+        relation: None,
+        holds: Vec::new(),
+    };
+    if Regex::new(r"@id\b").unwrap().is_match(attributes) {
+        field.keys.push("PK");
+    }
+    if Regex::new(r"@unique\b").unwrap().is_match(attributes) {
+        field.keys.push("UK");
+    }
+    if let Some(caps) = Regex::new(r"@relation\(([^)]*)\)")
+        .unwrap()
+        .captures(attributes)
+    {
+        field.relation = relation_name(&caps[1]);
+        field.holds = list_argument(&caps[1], "fields");
+    }
+    Some(field)
+}
+
+fn mark(model: &mut Model, names: &[String], key: &'static str) {
+    for field in model
+        .fields
+        .iter_mut()
+        .filter(|field| names.contains(&field.name))
+    {
+        if !field.keys.contains(&key) {
+            field.keys.push(key);
+        }
+    }
+}
+
+fn parse(source: &str, warnings: &mut Vec<String>) -> Vec<Model> {
+    let header = Regex::new(r"^(model|view|enum|type|datasource|generator)\s+(\w+)\s*\{").unwrap();
+    let block_attribute = Regex::new(r"^@@(id|unique)\(\s*(?:fields\s*:\s*)?\[([^\]]*)\]").unwrap();
+
+    let mut models = Vec::new();
+    let mut current: Option<Model> = None;
+    let mut skipping = false;
+    for line in source.lines() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('}') {
+            models.extend(current.take());
+            skipping = false;
+            continue;
+        }
+        if skipping {
+            continue;
+        }
+        if let Some(model) = current.as_mut() {
+            if let Some(caps) = block_attribute.captures(line) {
+                let key = if &caps[1] == "id" { "PK" } else { "UK" };
+                mark(model, &names(&caps[2]), key);
+            } else if !line.starts_with("@@") {
+                match parse_field(line) {
+                    Some(field) => model.fields.push(field),
+                    None => warnings.push(format!("Could not read a field of {}", model.name)),
+                }
+            }
+        } else if let Some(caps) = header.captures(line) {
+            // Enums and composite types are shown as attribute types only.
+            if matches!(&caps[1], "model" | "view") {
+                current = Some(Model {
+                    name: caps[2].to_string(),
+                    fields: Vec::new(),
+                });
+            } else {
+                skipping = true;
+            }
+        }
+    }
+    if let Some(model) = current {
+        warnings.push(format!("{} is missing its closing brace", model.name));
+        models.push(model);
+    }
+    models
+}
+
+// The field on `model` that points back at `other` through the relation,
+// matched by relation name when the relation has one.
+fn back_field<'a>(
+    model: &'a Model,
+    other: &str,
+    relation: &Option<String>,
+    except: &str,
+) -> Option<&'a Field> {
+    model.fields.iter().find(|field| {
+        field.ty == other
+            && field.relation == *relation
+            && !(model.name == other && field.name == except)
+    })
+}
+
+// Draws an ER diagram from a Prisma schema. Models become entities with
+// their scalar fields, `@id`, `@unique` and `@relation(fields: ...)` become
+// keys, and relation fields become relationships whose cardinality follows
+// from the list and optional markers on both sides.
+pub fn convert(source: &str, warnings: &mut Vec<String>) -> Result<String, String> {
+    let mut models = parse(source, warnings);
+    if models.is_empty() {
+        return Err("No models found in the Prisma schema".to_string());
+    }
+    let known: Vec<String> = models.iter().map(|model| model.name.clone()).collect();
+
+    // Fields holding a relation's foreign key are keys too.
+    for model in &mut models {
+        let held: Vec<String> = model
+            .fields
+            .iter()
+            .flat_map(|field| field.holds.clone())
+            .collect();
+        mark(model, &held, "FK");
+    }
+
+    let mut lines = vec!["erDiagram".to_string()];
+    for model in &models {
+        lines.push(format!("    {} {{", model.name));
+        for field in &model.fields {
+            if known.contains(&field.ty) {
+                continue;
+            }
+            let ty = if field.list {
+                format!("{}[]", field.ty.replace('.', "_"))
+            } else {
+                field.ty.replace('.', "_")
+            };
+            let keys = if field.keys.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", field.keys.join(", "))
+            };
+            lines.push(format!("        {} {}{}", ty, field.name, keys));
+        }
+        lines.push("    }".to_string());
+    }
+
+    // Each relation is drawn once: from the side holding the foreign key,
+    // or from the first model of an implicit many-to-many relation.
+    let mut drawn: Vec<(String, String, Option<String>)> = Vec::new();
+    for model in &models {
+        for field in model
+            .fields
+            .iter()
+            .filter(|field| known.contains(&field.ty))
+        {
+            let Some(other) = models.iter().find(|other| other.name == field.ty) else {
+                continue;
+            };
+            let back = back_field(other, &model.name, &field.relation, &field.name);
+            let key = {
+                let mut pair = [model.name.clone(), other.name.clone()];
+                pair.sort();
+                let [first, second] = pair;
+                (first, second, field.relation.clone())
+            };
+
+            let line = if !field.holds.is_empty() {
+                // `model` holds the key: it belongs to one `other`, which has
+                // many or at most one of it.
+                let parent = if field.optional { "|o" } else { "||" };
+                let child = match back {
+                    Some(back) if !back.list => "o|",
+                    _ => "o{",
+                };
+                format!(
+                    "    {} {}--{} {} : \"{}\"",
+                    other.name,
+                    parent,
+                    child,
+                    model.name,
+                    field.relation.as_deref().unwrap_or(&field.name)
+                )
+            } else if back.is_some_and(|back| !back.holds.is_empty()) {
+                continue;
+            } else if field.list && back.iter().all(|back| back.list) {
+                if drawn.contains(&key) {
+                    continue;
+                }
+                format!(
+                    "    {} }}o--o{{ {} : \"{}\"",
+                    model.name,
+                    other.name,
+                    field.relation.as_deref().unwrap_or(&field.name)
+                )
+            } else {
+                warnings.push(format!(
+                    "{}.{} has no @relation(fields: ...) on either side",
+                    model.name, field.name
+                ));
+                continue;
+            };
+            drawn.push(key);
+            lines.push(line);
+        }
+    }
+
+    Ok(lines.join("\n"))
+}
@@ -0,0 +1,100 @@
+use crate::csv::{self, Table};
+
+// Header names accepted for each field, compared case-insensitively.
+const SOURCE: [&str; 3] = ["source", "from", "origin"];
+const TARGET: [&str; 3] = ["target", "to", "destination"];
+const VALUE: [&str; 6] = ["value", "amount", "weight", "count", "flow", "quantity"];
+
+fn find(table: &Table, names: &[&str]) -> Option<usize> {
+    names.iter().find_map(|name| table.column(name))
+}
+
+// A CSV file with source, target and value columns.
+pub fn is_flow_list(text: &str) -> bool {
+    let header = text.lines().next().unwrap_or_default();
+    if !header.contains([',', ';', '\t']) {
+        return false;
+    }
+    csv::parse(header)
+        .map(|table| {
+            find(&table, &SOURCE).is_some()
+                && find(&table, &TARGET).is_some()
+                && find(&table, &VALUE).is_some()
+        })
+        .unwrap_or(false)
+}
+
+// Node names with commas or quotes are quoted, as in CSV.
+fn node(name: &str) -> String {
+    if name.contains([',', '"']) {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    } else {
+        name.to_string()
+    }
+}
+
+// Converts a list of flows into a sankey diagram. Rows repeating a source
+// and target, as in raw transaction exports, are added up; rows with a
+// missing, negative or unreadable value are skipped.
+pub fn convert(text: &str, warnings: &mut Vec<String>) -> Result<String, String> {
+    let table = csv::parse(text)?;
+    let (Some(source), Some(target), Some(value)) = (
+        find(&table, &SOURCE),
+        find(&table, &TARGET),
+        find(&table, &VALUE),
+    ) else {
+        return Err("Flow list needs source, target and value columns".to_string());
+    };
+
+    let mut flows: Vec<(String, String, f64)> = Vec::new();
+    for (index, row) in table.rows.iter().enumerate() {
+        // Data rows start on line 2, after the header.
+        let number = index + 2;
+        let from = row[source].trim();
+        let to = row[target].trim();
+        if from.is_empty() || to.is_empty() {
+            warnings.push(format!("Skipped row {}: missing source or target", number));
+            continue;
+        }
+        if from == to {
+            warnings.push(format!(
+                "Skipped row {}: {} flows into itself",
+                number, from
+            ));
+            continue;
+        }
+        // Spreadsheets may group digits with spaces.
+        let amount = row[value].trim().replace([' ', '\u{a0}'], "");
+        let amount = match amount.parse::<f64>() {
+            Ok(amount) if amount >= 0.0 => amount,
+            Ok(_) => {
+                warnings.push(format!("Skipped row {}: negative value", number));
+                continue;
+            }
+            Err(_) => {
+                warnings.push(format!(
+                    "Skipped row {}: `{}` is not a number",
+                    number,
+                    row[value].trim()
+                ));
+                continue;
+            }
+        };
+        match flows
+            .iter_mut()
+            .find(|(existing_from, existing_to, _)| existing_from == from && existing_to == to)
+        {
+            Some((_, _, total)) => *total += amount,
+            None => flows.push((from.to_string(), to.to_string(), amount)),
+        }
+    }
+    if flows.is_empty() {
+        return Err("The flow list has no flows".to_string());
+    }
+
+    let mut lines = vec!["sankey-beta".to_string(), String::new()];
+    for (from, to, amount) in flows {
+        lines.push(format!("{},{},{}", node(&from), node(&to), amount));
+    }
+    Ok(lines.join("\n"))
+}
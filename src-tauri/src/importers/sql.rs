@@ -0,0 +1,329 @@
+use regex::Regex;
+
+struct Column {
+    name: String,
+    data_type: String,
+    not_null: bool,
+    keys: Vec<&'static str>,
+}
+
+struct Table {
+    name: String,
+    columns: Vec<Column>,
+}
+
+struct ForeignKey {
+    table: String,
+    columns: Vec<String>,
+    target: String,
+}
+
+pub fn is_sql(text: &str) -> bool {
+    Regex::new(r"(?i)\bcreate\s+table\b")
+        .unwrap()
+        .is_match(text)
+}
+
+// Draws an ER diagram from SQL DDL. Tables come from CREATE TABLE, keys from
+// inline and table-level PRIMARY KEY, UNIQUE and REFERENCES clauses, and
+// relationships from foreign keys, including those added later with
+// ALTER TABLE. Other statements are ignored.
+pub fn convert(source: &str, warnings: &mut Vec<String>) -> Result<String, String> {
+    let create = Regex::new(
+        r"(?is)^create\s+(?:(?:global\s+|local\s+)?(?:temporary|temp)\s+)?table\s+(?:if\s+not\s+exists\s+)?(\S+?)\s*\((.*)\)",
+    )
+    .unwrap();
+    let alter =
+        Regex::new(r"(?is)^alter\s+table\s+(?:if\s+exists\s+)?(?:only\s+)?(\S+)\s+add\s+(.*)$")
+            .unwrap();
+
+    let mut tables: Vec<Table> = Vec::new();
+    let mut foreign_keys = Vec::new();
+
+    for statement in split_top_level(&strip_comments(source), ';') {
+        let statement = statement.trim();
+        if let Some(caps) = create.captures(statement) {
+            let mut table = Table {
+                name: identifier(&caps[1]),
+                columns: Vec::new(),
+            };
+            for item in split_top_level(&caps[2], ',') {
+                parse_item(item.trim(), &mut table, &mut foreign_keys, warnings);
+            }
+            tables.retain(|existing| existing.name != table.name);
+            tables.push(table);
+        } else if let Some(caps) = alter.captures(statement) {
+            let name = identifier(&caps[1]);
+            let Some(index) = tables.iter().position(|table| table.name == name) else {
+                warnings.push(format!("ALTER TABLE for unknown table {}", name));
+                continue;
+            };
+            let mut table = tables.remove(index);
+            parse_item(caps[2].trim(), &mut table, &mut foreign_keys, warnings);
+            tables.insert(index, table);
+        }
+    }
+
+    if tables.is_empty() {
+        return Err("No CREATE TABLE statements found".to_string());
+    }
+
+    let mut lines = vec!["erDiagram".to_string()];
+    for table in &tables {
+        lines.push(format!("    {} {{", entity(&table.name)));
+        for column in &table.columns {
+            let keys = if column.keys.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", column.keys.join(", "))
+            };
+            lines.push(format!(
+                "        {} {}{}",
+                column.data_type,
+                entity(&column.name),
+                keys
+            ));
+        }
+        lines.push("    }".to_string());
+    }
+
+    for key in &foreign_keys {
+        if !tables.iter().any(|table| table.name == key.target) {
+            warnings.push(format!(
+                "{} references unknown table {}",
+                key.table, key.target
+            ));
+            continue;
+        }
+        // The parent side is optional when the referencing columns may be
+        // null.
+        let required = tables
+            .iter()
+            .find(|table| table.name == key.table)
+            .map(|table| {
+                key.columns.iter().all(|name| {
+                    table
+                        .columns
+                        .iter()
+                        .any(|column| &column.name == name && column.not_null)
+                })
+            })
+            .unwrap_or(false);
+        lines.push(format!(
+            "    {} {}--o{{ {} : \"{}\"",
+            entity(&key.target),
+            if required { "||" } else { "|o" },
+            entity(&key.table),
+            key.columns.join(", ").replace('"', "'")
+        ));
+    }
+
+    Ok(lines.join("\n"))
+}
+
+fn parse_item(
+    item: &str,
+    table: &mut Table,
+    foreign_keys: &mut Vec<ForeignKey>,
+    warnings: &mut Vec<String>,
+) {
+    let constraint = Regex::new(r"(?is)^constraint\s+\S+\s+(.*)$").unwrap();
+    let item = match constraint.captures(item) {
+        Some(caps) => caps.get(1).map(|m| m.as_str()).unwrap_or_default(),
+        None => item,
+    };
+    let first = item
+        .split(|c: char| c.is_whitespace() || c == '(')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    if first == "primary" {
+        mark(table, &column_list(item), "PK");
+        return;
+    }
+    if first == "unique" {
+        mark(table, &column_list(item), "UK");
+        return;
+    }
+    if first == "foreign" {
+        let columns = column_list(item);
+        match references(item) {
+            Some(target) => {
+                mark(table, &columns, "FK");
+                foreign_keys.push(ForeignKey {
+                    table: table.name.clone(),
+                    columns,
+                    target,
+                });
+            }
+            None => warnings.push(format!("Could not read a foreign key of {}", table.name)),
+        }
+        return;
+    }
+    if ["check", "index", "key", "exclude", "fulltext", "spatial"].contains(&first.as_str()) {
+        return;
+    }
+    // ALTER TABLE ... ADD [COLUMN] name type.
+    let item = Regex::new(r"(?i)^column\s+")
+        .unwrap()
+        .replace(item, "")
+        .to_string();
+
+    let Some((name, rest)) = split_name(&item) else {
+        return;
+    };
+    let rest = rest.split_whitespace().collect::<Vec<_>>().join(" ");
+    let lowered = rest.to_lowercase();
+
+    let mut keys = Vec::new();
+    if lowered.contains("primary key") {
+        keys.push("PK");
+    }
+    if let Some(target) = references(&rest) {
+        keys.push("FK");
+        foreign_keys.push(ForeignKey {
+            table: table.name.clone(),
+            columns: vec![name.clone()],
+            target,
+        });
+    }
+    if lowered.contains("unique") {
+        keys.push("UK");
+    }
+
+    table.columns.retain(|column| column.name != name);
+    table.columns.push(Column {
+        data_type: data_type(&rest),
+        not_null: lowered.contains("not null") || lowered.contains("primary key"),
+        name,
+        keys,
+    });
+}
+
+fn mark(table: &mut Table, names: &[String], key: &'static str) {
+    for column in table
+        .columns
+        .iter_mut()
+        .filter(|column| names.contains(&column.name))
+    {
+        if !column.keys.contains(&key) {
+            column.keys.push(key);
+        }
+        if key == "PK" {
+            column.not_null = true;
+        }
+    }
+}
+
+fn references(text: &str) -> Option<String> {
+    Regex::new(r"(?i)\breferences\s+([^\s(]+)")
+        .unwrap()
+        .captures(text)
+        .map(|caps| identifier(&caps[1]))
+}
+
+// The names in the first parenthesised list of `text`.
+fn column_list(text: &str) -> Vec<String> {
+    let (Some(open), Some(close)) = (text.find('('), text.find(')')) else {
+        return Vec::new();
+    };
+    if close < open {
+        return Vec::new();
+    }
+    text[open + 1..close]
+        .split(',')
+        .map(|name| identifier(name.trim()))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+// Mermaid attribute types cannot hold spaces or commas, so `varchar(255)`
+// and `double precision` are shortened to their first word.
+fn data_type(rest: &str) -> String {
+    let word: String = rest
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+    if word.is_empty() {
+        "unknown".to_string()
+    } else {
+        word.to_lowercase()
+    }
+}
+
+// Splits a column definition into its name, which may be quoted and hold
+// spaces, and the rest.
+fn split_name(item: &str) -> Option<(String, &str)> {
+    let close = match item.chars().next()? {
+        '"' => '"',
+        '`' => '`',
+        '[' => ']',
+        _ => {
+            let end = item.find(char::is_whitespace).unwrap_or(item.len());
+            return Some((identifier(&item[..end]), &item[end..]));
+        }
+    };
+    let end = item[1..].find(close)? + 1;
+    Some((item[1..end].to_string(), &item[end + 1..]))
+}
+
+// The last part of a possibly schema-qualified, quoted name.
+fn identifier(raw: &str) -> String {
+    raw.rsplit('.')
+        .next()
+        .unwrap_or(raw)
+        .trim_matches(|c| matches!(c, '"' | '`' | '[' | ']' | '\''))
+        .to_string()
+}
+
+fn entity(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+fn strip_comments(source: &str) -> String {
+    let block = Regex::new(r"(?s)/\*.*?\*/").unwrap();
+    let line = Regex::new(r"--[^\n]*").unwrap();
+    line.replace_all(&block.replace_all(source, " "), "")
+        .to_string()
+}
+
+// Splits on `separator` outside parentheses and quotes.
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut quote = None;
+    let mut start = 0;
+
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, c) if c == separator && depth == 0 => {
+                parts.push(&text[start..index]);
+                start = index + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts.retain(|part| !part.trim().is_empty());
+    parts
+}
@@ -0,0 +1,308 @@
+use chrono::{Datelike, NaiveDate};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::escape_text;
+use super::gantt::parse_date;
+use crate::csv::{self, Table};
+
+// `:` separates the events of a time period.
+const SEPARATORS: [char; 1] = [':'];
+
+// A timeline with more events than this is too wide to read, so the rest
+// are dropped with a warning.
+const MAX_EVENTS: usize = 200;
+
+// Header names or JSON keys accepted for each field, compared
+// case-insensitively.
+const DATE: [&str; 6] = ["date", "when", "time", "timestamp", "year", "start date"];
+const TITLE: [&str; 5] = ["title", "event", "name", "summary", "headline"];
+const DESCRIPTION: [&str; 5] = ["description", "details", "notes", "body", "text"];
+const CATEGORY: [&str; 6] = ["category", "group", "type", "section", "phase", "tag"];
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Grouping {
+    #[default]
+    None,
+    Year,
+    Category,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct TimelineOptions {
+    #[serde(default)]
+    pub group_by: Grouping,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+// How much of a date the source gave, so `2024` is not shown as
+// `2024-01-01`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Precision {
+    Year,
+    Month,
+    Day,
+}
+
+// A time period with the events listed under it.
+type Period = (String, Vec<String>);
+
+struct Event {
+    when: String,
+    date: Option<(NaiveDate, Precision)>,
+    title: String,
+    description: Option<String>,
+    category: Option<String>,
+}
+
+fn find(table: &Table, names: &[&str]) -> Option<usize> {
+    names.iter().find_map(|name| table.column(name))
+}
+
+fn key<'a>(record: &'a Value, names: &[&str]) -> Option<&'a Value> {
+    let object = record.as_object()?;
+    names.iter().find_map(|name| {
+        object
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    })
+}
+
+fn value_text(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::String(text) => text.trim().to_string(),
+        Value::Number(number) => number.to_string(),
+        _ => return None,
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+// A JSON array of events, or an object holding one under `events`, `items`
+// or `timeline`.
+fn records(text: &str) -> Option<Vec<Value>> {
+    let value: Value = serde_json::from_str(text.trim()).ok()?;
+    match value {
+        Value::Array(records) => Some(records),
+        Value::Object(_) => ["events", "items", "timeline"]
+            .iter()
+            .find_map(|name| value.get(name).and_then(Value::as_array).cloned()),
+        _ => None,
+    }
+}
+
+// A CSV file or JSON list whose events have a date and a title.
+pub fn is_event_list(text: &str) -> bool {
+    if let Some(records) = records(text) {
+        return records
+            .first()
+            .is_some_and(|record| key(record, &DATE).is_some() && key(record, &TITLE).is_some());
+    }
+    let header = text.lines().next().unwrap_or_default();
+    if !header.contains([',', ';', '\t']) {
+        return false;
+    }
+    csv::parse(header)
+        .map(|table| find(&table, &DATE).is_some() && find(&table, &TITLE).is_some())
+        .unwrap_or(false)
+}
+
+fn parse_when(value: &str) -> Option<(NaiveDate, Precision)> {
+    let value = value.trim();
+    if let Some(date) = parse_date(value) {
+        return Some((date, Precision::Day));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d") {
+        return Some((date, Precision::Month));
+    }
+    value
+        .parse::<i32>()
+        .ok()
+        .filter(|_| value.len() == 4)
+        .and_then(|year| NaiveDate::from_ymd_opt(year, 1, 1))
+        .map(|date| (date, Precision::Year))
+}
+
+fn events(text: &str) -> Result<Vec<Event>, String> {
+    let event = |when: Option<String>,
+                 title: Option<String>,
+                 description: Option<String>,
+                 category: Option<String>| {
+        let (when, title) = (when?, title?);
+        Some(Event {
+            date: parse_when(&when),
+            when,
+            title,
+            description,
+            category,
+        })
+    };
+
+    if let Some(records) = records(text) {
+        return Ok(records
+            .iter()
+            .filter_map(|record| {
+                let field = |names: &[&str]| key(record, names).and_then(value_text);
+                event(
+                    field(&DATE),
+                    field(&TITLE),
+                    field(&DESCRIPTION),
+                    field(&CATEGORY),
+                )
+            })
+            .collect());
+    }
+
+    let table = csv::parse(text)?;
+    let date = find(&table, &DATE).ok_or("Event list needs a date column")?;
+    let title = find(&table, &TITLE).ok_or("Event list needs a title column")?;
+    let description = find(&table, &DESCRIPTION);
+    let category = find(&table, &CATEGORY);
+    Ok(table
+        .rows
+        .iter()
+        .filter_map(|row| {
+            let field = |column: Option<usize>| {
+                column
+                    .and_then(|column| row.get(column))
+                    .map(|value| value.trim().to_string())
+                    .filter(|value| !value.is_empty())
+            };
+            event(
+                field(Some(date)),
+                field(Some(title)),
+                field(description),
+                field(category),
+            )
+        })
+        .collect())
+}
+
+// The time period an event is listed under. Inside a year section the
+// year is left out.
+fn period(event: &Event, grouping: Grouping) -> String {
+    let Some((date, precision)) = event.date else {
+        return event.when.clone();
+    };
+    let format = match (precision, grouping) {
+        (Precision::Year, _) => "%Y",
+        (Precision::Month, Grouping::Year) => "%B",
+        (Precision::Month, _) => "%B %Y",
+        (Precision::Day, Grouping::Year) => "%b %-d",
+        (Precision::Day, _) => "%Y-%m-%d",
+    };
+    date.format(format).to_string()
+}
+
+fn section(event: &Event, grouping: Grouping) -> Option<String> {
+    match grouping {
+        Grouping::None => None,
+        Grouping::Year => Some(
+            event
+                .date
+                .map(|(date, _)| date.year().to_string())
+                .unwrap_or_else(|| "Undated".to_string()),
+        ),
+        Grouping::Category => Some(
+            event
+                .category
+                .clone()
+                .unwrap_or_else(|| "Other".to_string()),
+        ),
+    }
+}
+
+// Converts a list of dated events into a timeline. Events are sorted by
+// date when every date could be read, and events on the same date share a
+// time period. Sections follow the years or the categories in the order
+// they first appear.
+pub fn convert(
+    text: &str,
+    options: &TimelineOptions,
+    warnings: &mut Vec<String>,
+) -> Result<String, String> {
+    let mut events = events(text)?;
+    if events.is_empty() {
+        return Err("The event list has no events with a date and a title".to_string());
+    }
+
+    let unreadable: Vec<&str> = events
+        .iter()
+        .filter(|event| event.date.is_none())
+        .map(|event| event.when.as_str())
+        .collect();
+    if unreadable.is_empty() {
+        events.sort_by_key(|event| event.date.map(|(date, _)| date));
+    } else {
+        warnings.push(format!(
+            "Kept the file order since some dates could not be read: {}",
+            unreadable.join(", ")
+        ));
+    }
+
+    // Cut after sorting, so the earliest events are the ones kept.
+    if events.len() > MAX_EVENTS {
+        warnings.push(format!(
+            "Showing the first {} of {} events",
+            MAX_EVENTS,
+            events.len()
+        ));
+        events.truncate(MAX_EVENTS);
+    }
+
+    let mut sections: Vec<(Option<String>, Vec<Period>)> = Vec::new();
+    for event in &events {
+        let section_name = section(event, options.group_by);
+        let period_name = period(event, options.group_by);
+        let entry = match event.description.as_deref() {
+            Some(description) => format!(
+                "{}<br>{}",
+                escape_text(&event.title, &SEPARATORS),
+                escape_text(description, &SEPARATORS)
+            ),
+            None => escape_text(&event.title, &SEPARATORS),
+        };
+
+        let index = match sections.iter().position(|(name, _)| *name == section_name) {
+            Some(index) => index,
+            None => {
+                sections.push((section_name, Vec::new()));
+                sections.len() - 1
+            }
+        };
+        let periods = &mut sections[index].1;
+        match periods.last_mut() {
+            Some((name, entries)) if *name == period_name => entries.push(entry),
+            _ => periods.push((period_name, vec![entry])),
+        }
+    }
+
+    let mut lines = vec!["timeline".to_string()];
+    if let Some(title) = options
+        .title
+        .as_deref()
+        .filter(|title| !title.trim().is_empty())
+    {
+        lines.push(format!("    title {}", escape_text(title, &SEPARATORS)));
+    }
+    for (name, periods) in sections {
+        let indent = match name {
+            Some(name) => {
+                lines.push(format!("    section {}", escape_text(&name, &SEPARATORS)));
+                "        "
+            }
+            None => "    ",
+        };
+        for (period, entries) in periods {
+            lines.push(format!(
+                "{}{} : {}",
+                indent,
+                escape_text(&period, &SEPARATORS),
+                entries.join(" : ")
+            ));
+        }
+    }
+    Ok(lines.join("\n"))
+}
@@ -0,0 +1,436 @@
+use serde_yaml::Value;
+use std::collections::HashMap;
+
+use super::escape_text;
+
+// Read as YAML, of which JSON is a subset, since its mappings keep the
+// order states were written in.
+fn parse(text: &str) -> Option<Value> {
+    serde_yaml::from_str::<Value>(text)
+        .ok()
+        .filter(Value::is_mapping)
+}
+
+// An XState machine definition: a JSON object with nested `states` and an
+// `initial` state, or parallel regions.
+pub fn is_xstate(text: &str) -> bool {
+    if !text.trim_start().starts_with('{') {
+        return false;
+    }
+    parse(text)
+        .map(|machine| {
+            machine.get("states").is_some_and(Value::is_mapping)
+                && (machine.get("initial").is_some()
+                    || machine.get("type").and_then(Value::as_str) == Some("parallel"))
+        })
+        .unwrap_or(false)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Atomic,
+    Compound,
+    Parallel,
+    Final,
+    History,
+}
+
+struct State<'a> {
+    key: String,
+    path: Vec<String>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    kind: Kind,
+    config: &'a Value,
+    id: String,
+}
+
+struct Transition {
+    from: usize,
+    to: usize,
+    label: String,
+}
+
+struct Machine<'a> {
+    states: Vec<State<'a>>,
+    // Explicit `id`s, for `#id` targets.
+    ids: HashMap<String, usize>,
+    transitions: Vec<Transition>,
+    notes: Vec<(usize, String)>,
+}
+
+// State ids may only hold word characters.
+fn sanitize(name: &str) -> String {
+    let id: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if id.is_empty() || id.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("s_{}", id)
+    } else {
+        id
+    }
+}
+
+// A guard by name, as `guard` (XState 5) or `cond` (XState 4), either a
+// string or an object with a `type`.
+fn guard_of(transition: &Value) -> Option<String> {
+    let guard = transition.get("guard").or_else(|| transition.get("cond"))?;
+    match guard {
+        Value::String(name) => Some(name.clone()),
+        other => other
+            .get("type")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    }
+}
+
+fn as_list(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Sequence(items) => items.iter().collect(),
+        value => vec![value],
+    }
+}
+
+impl<'a> Machine<'a> {
+    fn add(&mut self, key: &str, config: &'a Value, parent: Option<usize>) -> usize {
+        let mut path = parent
+            .map(|parent| self.states[parent].path.clone())
+            .unwrap_or_default();
+        if parent.is_some() {
+            path.push(key.to_string());
+        }
+        let children = config.get("states").and_then(Value::as_mapping);
+        let kind = match config.get("type").and_then(Value::as_str) {
+            Some("parallel") => Kind::Parallel,
+            Some("final") => Kind::Final,
+            Some("history") => Kind::History,
+            _ if children.is_some_and(|children| !children.is_empty()) => Kind::Compound,
+            _ => Kind::Atomic,
+        };
+
+        let index = self.states.len();
+        self.states.push(State {
+            key: key.to_string(),
+            path,
+            parent,
+            children: Vec::new(),
+            kind,
+            config,
+            id: String::new(),
+        });
+        if let Some(id) = config.get("id").and_then(Value::as_str) {
+            self.ids.insert(id.to_string(), index);
+        }
+        if let Some(parent) = parent {
+            self.states[parent].children.push(index);
+        }
+        for (child_key, child) in children.into_iter().flatten() {
+            if let Some(child_key) = child_key.as_str() {
+                self.add(child_key, child, Some(index));
+            }
+        }
+        index
+    }
+
+    // Mermaid ids are global, so a state keeps its own name unless another
+    // state shares it, in which case its whole path is used.
+    fn assign_ids(&mut self) {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for state in self.states.iter().skip(1) {
+            *counts.entry(sanitize(&state.key)).or_default() += 1;
+        }
+        for state in self.states.iter_mut().skip(1) {
+            let id = sanitize(&state.key);
+            state.id = if counts[&id] > 1 {
+                sanitize(&state.path.join("_"))
+            } else {
+                id
+            };
+        }
+    }
+
+    fn child(&self, state: usize, key: &str) -> Option<usize> {
+        self.states[state]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| self.states[child].key == key)
+    }
+
+    fn descend(&self, mut state: usize, path: &str) -> Option<usize> {
+        for key in path.split('.').filter(|key| !key.is_empty()) {
+            state = self.child(state, key)?;
+        }
+        Some(state)
+    }
+
+    // `#id.child` targets start at a state with that id, `.child` at the
+    // source state and anything else at its parent, making it a sibling.
+    fn resolve(&self, source: usize, target: &str) -> Option<usize> {
+        if let Some(reference) = target.strip_prefix('#') {
+            let (id, path) = reference.split_once('.').unwrap_or((reference, ""));
+            return self.descend(*self.ids.get(id)?, path);
+        }
+        if let Some(path) = target.strip_prefix('.') {
+            return self.descend(source, path);
+        }
+        let start = self.states[source].parent.unwrap_or(source);
+        self.descend(start, target)
+    }
+
+    fn transition(&mut self, source: usize, event: &str, spec: &Value, warnings: &mut Vec<String>) {
+        for spec in as_list(spec) {
+            let targets: Vec<&str> = match spec {
+                Value::String(target) => vec![target.as_str()],
+                Value::Mapping(_) => spec
+                    .get("target")
+                    .map(|target| {
+                        as_list(target)
+                            .into_iter()
+                            .filter_map(Value::as_str)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                _ => Vec::new(),
+            };
+            let guard = guard_of(spec);
+
+            for target in targets {
+                let Some(to) = self.resolve(source, target) else {
+                    warnings.push(format!(
+                        "Unknown target {} of {} in {}",
+                        target,
+                        event,
+                        self.states[source].path.join(".")
+                    ));
+                    continue;
+                };
+                if source == 0 || to == 0 {
+                    warnings.push(format!(
+                        "Transition {} to {} on the machine itself is not shown",
+                        event, target
+                    ));
+                    continue;
+                }
+                if let Some(guard) = &guard {
+                    let note = format!("{} → {} if {}", event, self.states[to].key, guard);
+                    self.notes.push((source, note));
+                }
+                self.transitions.push(Transition {
+                    from: source,
+                    to,
+                    label: event.to_string(),
+                });
+            }
+        }
+    }
+
+    fn transitions_of(&mut self, state: usize, warnings: &mut Vec<String>) {
+        let config = self.states[state].config;
+
+        match config.get("on") {
+            Some(Value::Mapping(events)) => {
+                for (event, spec) in events {
+                    if let Some(event) = event.as_str() {
+                        self.transition(state, event, spec, warnings);
+                    }
+                }
+            }
+            // XState 4 also accepts a list of transitions with an `event`.
+            Some(Value::Sequence(specs)) => {
+                for spec in specs {
+                    let event = spec.get("event").and_then(Value::as_str).unwrap_or("");
+                    self.transition(state, event, spec, warnings);
+                }
+            }
+            _ => {}
+        }
+        if let Some(spec) = config.get("always") {
+            self.transition(state, "always", spec, warnings);
+        }
+        if let Some(Value::Mapping(delays)) = config.get("after") {
+            for (delay, spec) in delays {
+                // JSON keys are strings, so delays in milliseconds are too.
+                let delay = match delay {
+                    Value::Number(ms) => format!("after {}ms", ms),
+                    Value::String(ms) if ms.parse::<u64>().is_ok() => format!("after {}ms", ms),
+                    Value::String(name) => format!("after {}", name),
+                    _ => continue,
+                };
+                self.transition(state, &delay, spec, warnings);
+            }
+        }
+        if let Some(spec) = config.get("onDone") {
+            self.transition(state, "done", spec, warnings);
+        }
+        for invoke in config.get("invoke").map(as_list).unwrap_or_default() {
+            if let Some(spec) = invoke.get("onDone") {
+                self.transition(state, "invoke done", spec, warnings);
+            }
+            if let Some(spec) = invoke.get("onError") {
+                self.transition(state, "invoke error", spec, warnings);
+            }
+        }
+    }
+
+    fn depth(&self, mut state: usize) -> usize {
+        let mut depth = 0;
+        while let Some(parent) = self.states[state].parent {
+            state = parent;
+            depth += 1;
+        }
+        depth
+    }
+
+    // The innermost composite containing both ends of a transition, where
+    // Mermaid needs it written so that neither end is redeclared elsewhere.
+    fn scope(&self, transition: &Transition) -> usize {
+        let mut a = self.states[transition.from].parent.unwrap_or(0);
+        let mut b = self.states[transition.to].parent.unwrap_or(0);
+        while a != b {
+            if self.depth(a) >= self.depth(b) {
+                a = self.states[a].parent.unwrap_or(0);
+            } else {
+                b = self.states[b].parent.unwrap_or(0);
+            }
+        }
+        a
+    }
+
+    fn initial(&self, state: usize) -> Option<usize> {
+        let initial = self.states[state].config.get("initial")?;
+        let key = match initial {
+            Value::String(key) => key.as_str(),
+            other => other.get("target").and_then(Value::as_str)?,
+        };
+        self.descend(state, key)
+    }
+
+    fn write(&self, state: usize, indent: usize, lines: &mut Vec<String>) {
+        let pad = "    ".repeat(indent);
+        if let Some(initial) = self.initial(state) {
+            lines.push(format!("{}[*] --> {}", pad, self.states[initial].id));
+        }
+
+        let node = &self.states[state];
+        for (position, &child) in node.children.iter().enumerate() {
+            let child_state = &self.states[child];
+            // Regions of a parallel state are separated by `--`.
+            if node.kind == Kind::Parallel && position > 0 {
+                lines.push(format!("{}--", pad));
+            }
+
+            let label = match child_state.kind {
+                Kind::History => {
+                    let deep =
+                        child_state.config.get("history").and_then(Value::as_str) == Some("deep");
+                    Some(if deep { "H*" } else { "H" }.to_string())
+                }
+                _ if child_state.id != child_state.key => Some(child_state.key.clone()),
+                _ => None,
+            };
+            if let Some(label) = &label {
+                lines.push(format!(
+                    "{}state \"{}\" as {}",
+                    pad,
+                    escape_text(label, &[]).replace('"', "#quot;"),
+                    child_state.id
+                ));
+            }
+            if matches!(child_state.kind, Kind::Compound | Kind::Parallel) {
+                lines.push(format!("{}state {} {{", pad, child_state.id));
+                self.write(child, indent + 1, lines);
+                lines.push(format!("{}}}", pad));
+            } else if label.is_none() {
+                lines.push(format!("{}{}", pad, child_state.id));
+            }
+            if let Some(description) = child_state
+                .config
+                .get("description")
+                .and_then(Value::as_str)
+            {
+                lines.push(format!(
+                    "{}{} : {}",
+                    pad,
+                    child_state.id,
+                    escape_text(description, &[])
+                ));
+            }
+            if child_state.kind == Kind::Final {
+                lines.push(format!("{}{} --> [*]", pad, child_state.id));
+            }
+        }
+
+        for transition in self.transitions.iter().filter(|t| self.scope(t) == state) {
+            lines.push(format!(
+                "{}{} --> {} : {}",
+                pad,
+                self.states[transition.from].id,
+                self.states[transition.to].id,
+                escape_text(&transition.label, &[])
+            ));
+        }
+
+        for &child in &node.children {
+            let notes: Vec<&String> = self
+                .notes
+                .iter()
+                .filter(|(source, _)| *source == child)
+                .map(|(_, note)| note)
+                .collect();
+            match notes.as_slice() {
+                [] => {}
+                [note] => lines.push(format!(
+                    "{}note right of {} : {}",
+                    pad,
+                    self.states[child].id,
+                    escape_text(note, &[])
+                )),
+                notes => {
+                    lines.push(format!("{}note right of {}", pad, self.states[child].id));
+                    for note in notes {
+                        lines.push(format!("{}    {}", pad, escape_text(note, &[])));
+                    }
+                    lines.push(format!("{}end note", pad));
+                }
+            }
+        }
+    }
+}
+
+// Converts an XState machine into a state diagram. Nested states become
+// composite states, parallel states get one region per child, and guarded
+// transitions are listed in a note on their source state.
+pub fn convert(text: &str, warnings: &mut Vec<String>) -> Result<String, String> {
+    let config = parse(text).ok_or("Failed to parse XState machine")?;
+    if !config.get("states").is_some_and(Value::is_mapping) {
+        return Err("XState machine has no states".to_string());
+    }
+
+    let mut machine = Machine {
+        states: Vec::new(),
+        ids: HashMap::new(),
+        transitions: Vec::new(),
+        notes: Vec::new(),
+    };
+    machine.add("", &config, None);
+    machine.assign_ids();
+    for state in 0..machine.states.len() {
+        machine.transitions_of(state, warnings);
+    }
+
+    let mut lines = vec!["stateDiagram-v2".to_string()];
+    machine.write(0, 1, &mut lines);
+    let content = lines.join("\n");
+    Ok(match config.get("id").and_then(Value::as_str) {
+        Some(title) => crate::markdown::with_title(&content, title),
+        None => content,
+    })
+}
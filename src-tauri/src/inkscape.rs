@@ -0,0 +1,64 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::render;
+
+// Inkscape turns rendered SVG into the vector formats the Mermaid CLI does
+// not produce. FLOWCRAFT_INKSCAPE overrides the binary location.
+fn command() -> Command {
+    let binary = env::var("FLOWCRAFT_INKSCAPE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("inkscape"));
+    Command::new(binary)
+}
+
+pub fn is_available() -> bool {
+    command()
+        .arg("--version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+// Converts `svg` to `format`, which Inkscape picks its exporter by. Text
+// stays text, so the result can be edited where it is pasted, except in EPS:
+// LaTeX runs through Ghostscript, which substitutes fonts it does not have,
+// so text becomes outlines there.
+pub fn convert(svg: &str, format: &str) -> Result<Vec<u8>, String> {
+    let input = render::temp_path("svg");
+    let output = render::temp_path(format);
+    fs::write(&input, svg).map_err(|e| format!("Failed to write temporary file: {}", e))?;
+
+    let mut command = command();
+    command
+        .arg(&input)
+        .arg(format!("--export-type={}", format))
+        .arg("--export-filename")
+        .arg(&output);
+    if format == "eps" {
+        command.arg("--export-text-to-path");
+    }
+    let result = command.output();
+    let _ = fs::remove_file(&input);
+    let result = result.map_err(|e| {
+        format!(
+            "Failed to launch Inkscape, which {} export needs: {}",
+            format.to_uppercase(),
+            e
+        )
+    })?;
+    if !result.status.success() {
+        let _ = fs::remove_file(&output);
+        return Err(format!(
+            "Failed to convert to {}: {}",
+            format.to_uppercase(),
+            String::from_utf8_lossy(&result.stderr).trim()
+        ));
+    }
+
+    let bytes = fs::read(&output).map_err(|e| format!("Failed to read converted output: {}", e));
+    let _ = fs::remove_file(&output);
+    bytes
+}
@@ -0,0 +1,293 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+
+use crate::export::{self, ExportOptions};
+use crate::file_settings::{self, ExportTheme};
+use crate::mermaid_config::MermaidConfig;
+use crate::model;
+use crate::{render, validate_content};
+
+// Finished jobs kept around for `get_job_status`; the oldest go first.
+const MAX_FINISHED_JOBS: usize = 100;
+
+type Task = Box<dyn FnOnce() + Send>;
+
+// Heavy work runs on a few dedicated threads instead of the async runtime,
+// so a long export cannot hold up unrelated commands.
+static POOL: OnceLock<Mutex<mpsc::Sender<Task>>> = OnceLock::new();
+static JOBS: Mutex<Option<HashMap<u64, JobStatus>>> = Mutex::new(None);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobRequest {
+    Parse {
+        content: String,
+    },
+    Validate {
+        content: String,
+    },
+    Render {
+        content: String,
+        format: String,
+    },
+    Export {
+        content: String,
+        // Resolved through the fs scope when the job is submitted.
+        path: PathBuf,
+        format: String,
+        #[serde(default)]
+        options: ExportOptions,
+        // The diagram's theme and the user's Mermaid settings, as for
+        // `export_diagram`. Set by the backend when the job is submitted.
+        #[serde(skip)]
+        theme: Option<Box<(ExportTheme, MermaidConfig)>>,
+    },
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct JobStatus {
+    pub id: u64,
+    pub kind: String,
+    pub state: JobState,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+    pub submitted_at: String,
+    pub finished_at: Option<String>,
+}
+
+impl JobRequest {
+    fn kind(&self) -> &'static str {
+        match self {
+            JobRequest::Parse { .. } => "parse",
+            JobRequest::Validate { .. } => "validate",
+            JobRequest::Render { .. } => "render",
+            JobRequest::Export { .. } => "export",
+        }
+    }
+
+    fn execute(self) -> Result<Value, String> {
+        match self {
+            JobRequest::Parse { content } => {
+                let parsed = model::parse_cached(&content);
+                Ok(json!({
+                    "diagram_type": parsed.model.diagram_type,
+                    "nodes": parsed.nodes,
+                    "edges": parsed.edges,
+                }))
+            }
+            JobRequest::Validate { content } => to_value(validate_content(&content)),
+            JobRequest::Render { content, format } => {
+                let bytes = render::render_content_to_bytes(&content, &format)?;
+                Ok(json!({
+                    "content_type": render::content_type(&format),
+                    "data": general_purpose::STANDARD.encode(bytes),
+                }))
+            }
+            JobRequest::Export {
+                content,
+                path,
+                format,
+                options,
+                theme,
+            } => {
+                let run = || export::export(&content, &path, &format, &options);
+                let result = match theme.as_deref() {
+                    Some((theme, mermaid_config)) => {
+                        file_settings::with_theme(theme, mermaid_config, run)
+                    }
+                    None => run(),
+                };
+                to_value(result?)
+            }
+        }
+    }
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| format!("Failed to serialize job result: {}", e))
+}
+
+fn worker_count() -> usize {
+    thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(2)
+        .clamp(2, 4)
+}
+
+fn pool() -> &'static Mutex<mpsc::Sender<Task>> {
+    POOL.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Task>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for index in 0..worker_count() {
+            let receiver = Arc::clone(&receiver);
+            let spawned = thread::Builder::new()
+                .name(format!("flowcraft-worker-{}", index))
+                .spawn(move || loop {
+                    let task = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+                    let Ok(task) = task else {
+                        return;
+                    };
+                    // A panicking job must not take the worker down with it.
+                    if panic::catch_unwind(AssertUnwindSafe(task)).is_err() {
+                        tracing::error!("worker job panicked");
+                    }
+                });
+            if let Err(e) = spawned {
+                tracing::error!(error = %e, "failed to start worker thread");
+            }
+        }
+
+        Mutex::new(sender)
+    })
+}
+
+fn enqueue(task: Task) -> Result<(), String> {
+    pool()
+        .lock()
+        .map_err(|_| "Failed to lock worker pool".to_string())?
+        .send(task)
+        .map_err(|_| "Worker pool is not running".to_string())
+}
+
+// Runs `work` on the pool and waits for it without blocking the caller's
+// runtime thread.
+pub async fn run<T, F>(work: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    enqueue(Box::new(move || {
+        let _ = sender.send(work());
+    }))?;
+    receiver
+        .await
+        .map_err(|_| "Worker stopped before finishing the job".to_string())
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+fn update_job(id: u64, update: impl FnOnce(&mut JobStatus)) -> Option<JobState> {
+    let mut jobs = JOBS.lock().ok()?;
+    let job = jobs.as_mut()?.get_mut(&id)?;
+    update(job);
+    Some(job.state)
+}
+
+fn prune(jobs: &mut HashMap<u64, JobStatus>) {
+    let mut finished: Vec<u64> = jobs
+        .values()
+        .filter(|job| job.finished_at.is_some())
+        .map(|job| job.id)
+        .collect();
+    if finished.len() <= MAX_FINISHED_JOBS {
+        return;
+    }
+    finished.sort_unstable();
+    for id in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+        jobs.remove(id);
+    }
+}
+
+// Queues a job and returns its id right away; poll `status` for the result.
+pub fn submit(request: JobRequest) -> Result<u64, String> {
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    {
+        let mut jobs = JOBS
+            .lock()
+            .map_err(|_| "Failed to lock job list".to_string())?;
+        let jobs = jobs.get_or_insert_with(HashMap::new);
+        prune(jobs);
+        jobs.insert(
+            id,
+            JobStatus {
+                id,
+                kind: request.kind().to_string(),
+                state: JobState::Queued,
+                result: None,
+                error: None,
+                submitted_at: now(),
+                finished_at: None,
+            },
+        );
+    }
+
+    enqueue(Box::new(move || {
+        // Jobs cancelled while queued are skipped.
+        let started = update_job(id, |job| {
+            if job.state == JobState::Queued {
+                job.state = JobState::Running;
+            }
+        });
+        if started != Some(JobState::Running) {
+            return;
+        }
+
+        let outcome = request.execute();
+        update_job(id, |job| {
+            // A job cancelled while running keeps that state; its result is
+            // dropped.
+            if job.state == JobState::Running {
+                match outcome {
+                    Ok(result) => {
+                        job.state = JobState::Completed;
+                        job.result = Some(result);
+                    }
+                    Err(error) => {
+                        job.state = JobState::Failed;
+                        job.error = Some(error);
+                    }
+                }
+            }
+            job.finished_at.get_or_insert_with(now);
+        });
+    }))?;
+
+    Ok(id)
+}
+
+pub fn status(id: u64) -> Result<JobStatus, String> {
+    JOBS.lock()
+        .map_err(|_| "Failed to lock job list".to_string())?
+        .as_ref()
+        .and_then(|jobs| jobs.get(&id).cloned())
+        .ok_or_else(|| format!("Unknown job: {}", id))
+}
+
+// Cancels a queued or running job. A running render cannot be interrupted,
+// so it finishes in the background and its output is discarded. Returns
+// false when the job had already finished.
+pub fn cancel(id: u64) -> Result<bool, String> {
+    let state = update_job(id, |job| {
+        if matches!(job.state, JobState::Queued | JobState::Running) {
+            job.state = JobState::Cancelled;
+            job.finished_at = Some(now());
+        }
+    })
+    .ok_or_else(|| format!("Unknown job: {}", id))?;
+    Ok(state == JobState::Cancelled)
+}
@@ -0,0 +1,269 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// Every bindable action with its default accelerator. Global actions are
+// registered with the OS and fire even when the app is in the background.
+const ACTIONS: [(&str, &str, bool); 12] = [
+    ("new_diagram", "CmdOrCtrl+N", false),
+    ("open_file", "CmdOrCtrl+O", false),
+    ("save_file", "CmdOrCtrl+S", false),
+    ("save_file_as", "CmdOrCtrl+Shift+S", false),
+    ("export", "CmdOrCtrl+E", false),
+    ("print", "CmdOrCtrl+P", false),
+    ("copy_as_markdown", "CmdOrCtrl+Shift+C", false),
+    ("format_document", "CmdOrCtrl+Shift+F", false),
+    ("find", "CmdOrCtrl+F", false),
+    ("toggle_preview", "CmdOrCtrl+Shift+V", false),
+    ("command_palette", "CmdOrCtrl+Shift+K", false),
+    ("quick_capture", "CmdOrCtrl+Alt+Space", true),
+];
+
+const MODIFIERS: [&str; 5] = ["CmdOrCtrl", "Ctrl", "Alt", "Shift", "Super"];
+
+const NAMED_KEYS: [&str; 26] = [
+    "Space",
+    "Enter",
+    "Tab",
+    "Escape",
+    "Backspace",
+    "Delete",
+    "Insert",
+    "Home",
+    "End",
+    "PageUp",
+    "PageDown",
+    "Up",
+    "Down",
+    "Left",
+    "Right",
+    "Minus",
+    "Equal",
+    "Comma",
+    "Period",
+    "Slash",
+    "Backslash",
+    "Semicolon",
+    "Quote",
+    "Backquote",
+    "BracketLeft",
+    "BracketRight",
+];
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Keybinding {
+    pub action: String,
+    pub accelerator: Option<String>,
+    pub default_accelerator: String,
+    pub global: bool,
+}
+
+pub fn is_known(action: &str) -> bool {
+    ACTIONS.iter().any(|(id, _, _)| *id == action)
+}
+
+pub fn is_global(action: &str) -> bool {
+    ACTIONS
+        .iter()
+        .any(|(id, _, global)| *id == action && *global)
+}
+
+// Applies the user's overrides to the defaults. An override of `None`
+// means the action was deliberately unbound.
+pub fn resolve(overrides: &BTreeMap<String, Option<String>>) -> Vec<Keybinding> {
+    ACTIONS
+        .iter()
+        .map(|(action, default, global)| Keybinding {
+            action: action.to_string(),
+            accelerator: match overrides.get(*action) {
+                Some(accelerator) => accelerator.clone(),
+                None => Some(default.to_string()),
+            },
+            default_accelerator: default.to_string(),
+            global: *global,
+        })
+        .collect()
+}
+
+fn canonical_modifier(token: &str) -> Option<&'static str> {
+    match token.to_lowercase().as_str() {
+        "cmdorctrl" | "commandorcontrol" | "cmdorcontrol" | "commandorctrl" | "mod" => {
+            Some("CmdOrCtrl")
+        }
+        "ctrl" | "control" => Some("Ctrl"),
+        "alt" | "option" => Some("Alt"),
+        "shift" => Some("Shift"),
+        "super" | "cmd" | "command" | "meta" | "win" => Some("Super"),
+        _ => None,
+    }
+}
+
+fn canonical_key(token: &str) -> Option<String> {
+    let upper = token.to_uppercase();
+
+    if token.chars().count() == 1 && token.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Some(upper);
+    }
+    if let Some(number) = upper.strip_prefix('F') {
+        if number.parse::<u8>().is_ok_and(|n| (1..=24).contains(&n)) {
+            return Some(upper);
+        }
+    }
+
+    let lowered = token.to_lowercase();
+    let alias = match lowered.as_str() {
+        "esc" => "escape",
+        "return" => "enter",
+        "del" => "delete",
+        "arrowup" => "up",
+        "arrowdown" => "down",
+        "arrowleft" => "left",
+        "arrowright" => "right",
+        other => other,
+    };
+    NAMED_KEYS
+        .iter()
+        .find(|key| key.to_lowercase() == alias)
+        .map(|key| key.to_string())
+}
+
+// Parses `ctrl+shift+s` style input into the canonical `Ctrl+Shift+S`.
+pub fn normalize(accelerator: &str) -> Result<String, String> {
+    let tokens: Vec<&str> = accelerator
+        .split('+')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .collect();
+    let Some((key, modifier_tokens)) = tokens.split_last() else {
+        return Err("Shortcut is empty".to_string());
+    };
+
+    let mut modifiers = Vec::new();
+    for token in modifier_tokens {
+        let modifier =
+            canonical_modifier(token).ok_or_else(|| format!("Unknown modifier: {}", token))?;
+        if modifiers.contains(&modifier) {
+            return Err(format!("Modifier {} is used twice", modifier));
+        }
+        modifiers.push(modifier);
+    }
+    let key = canonical_key(key).ok_or_else(|| format!("Unknown key: {}", key))?;
+
+    let mut parts: Vec<String> = MODIFIERS
+        .iter()
+        .filter(|modifier| modifiers.contains(modifier))
+        .map(|modifier| modifier.to_string())
+        .collect();
+    parts.push(key);
+    Ok(parts.join("+"))
+}
+
+// The physical keys an accelerator maps to on this platform, used to find
+// conflicts between `CmdOrCtrl+S` and `Ctrl+S`.
+fn platform_keys(accelerator: &str) -> String {
+    let cmd_or_ctrl = if cfg!(target_os = "macos") {
+        "Super"
+    } else {
+        "Ctrl"
+    };
+
+    let mut parts: Vec<&str> = accelerator
+        .split('+')
+        .map(|part| {
+            if part == "CmdOrCtrl" {
+                cmd_or_ctrl
+            } else {
+                part
+            }
+        })
+        .collect();
+    let key = parts.pop().unwrap_or_default();
+    parts.sort_unstable();
+    parts.dedup();
+    parts.push(key);
+    parts.join("+")
+}
+
+fn reserved_shortcuts() -> &'static [&'static str] {
+    if cfg!(target_os = "macos") {
+        &[
+            "Super+Q",
+            "Super+H",
+            "Super+M",
+            "Super+Tab",
+            "Super+Space",
+            "Alt+Super+Escape",
+        ]
+    } else if cfg!(windows) {
+        &[
+            "Alt+F4",
+            "Alt+Tab",
+            "Alt+Ctrl+Delete",
+            "Super+L",
+            "Super+D",
+            "Super+Tab",
+        ]
+    } else {
+        &[
+            "Alt+F4",
+            "Alt+Tab",
+            "Alt+Ctrl+Delete",
+            "Alt+Ctrl+T",
+            "Super+L",
+        ]
+    }
+}
+
+// Validates `accelerator` for `action` against platform rules and the other
+// bindings, returning the canonical form.
+pub fn validate(
+    action: &str,
+    accelerator: &str,
+    bindings: &[Keybinding],
+) -> Result<String, String> {
+    if !is_known(action) {
+        return Err(format!("Unknown action: {}", action));
+    }
+
+    let accelerator = normalize(accelerator)?;
+    let keys = platform_keys(&accelerator);
+    let is_function_key = keys
+        .rsplit('+')
+        .next()
+        .is_some_and(|key| key.len() > 1 && key.starts_with('F'));
+    // Shift alone would swallow typed capitals in the editor or, for global
+    // shortcuts, in every other app.
+    let has_command_modifier = keys
+        .split('+')
+        .rev()
+        .skip(1)
+        .any(|modifier| modifier != "Shift");
+
+    if is_global(action) && !has_command_modifier {
+        return Err("Global shortcuts need Ctrl, Alt or Cmd".to_string());
+    }
+    if !has_command_modifier && !is_function_key {
+        return Err("Shortcuts need Ctrl, Alt or Cmd unless they use a function key".to_string());
+    }
+    if reserved_shortcuts().contains(&keys.as_str()) {
+        return Err(format!(
+            "{} is reserved by the operating system",
+            accelerator
+        ));
+    }
+
+    let conflict = bindings.iter().find(|binding| {
+        binding.action != action
+            && binding
+                .accelerator
+                .as_deref()
+                .is_some_and(|other| platform_keys(other) == keys)
+    });
+    if let Some(conflict) = conflict {
+        return Err(format!(
+            "{} is already used by {}",
+            accelerator, conflict.action
+        ));
+    }
+
+    Ok(accelerator)
+}
@@ -0,0 +1,92 @@
+use std::io::Read;
+use std::thread;
+use std::time::Duration;
+
+use crate::pdf::{self, PdfImage, PdfPage, PT_PER_PX};
+use crate::security;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+// Far above any rendered diagram; a server sending more is not Kroki.
+const MAX_RESPONSE_BYTES: u64 = 64 * 1024 * 1024;
+
+pub fn validate_url(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid Kroki URL: {}", e))?;
+    if !matches!(parsed.scheme(), "https" | "http") {
+        return Err(format!("Invalid Kroki URL: {}", url));
+    }
+    // Diagrams go to the server in full, so only a local one may use http.
+    if !security::is_secure_endpoint(&parsed) {
+        return Err(format!(
+            "Kroki URL must use https unless the server runs on this computer: {}",
+            url
+        ));
+    }
+    Ok(())
+}
+
+// Renders Mermaid `source` on the Kroki server at `server`. Kroki only
+// produces SVG and PNG for Mermaid, so PDFs wrap its PNG in a single page.
+pub fn render(server: &str, source: &str, format: &str) -> Result<Vec<u8>, String> {
+    // FLOWCRAFT_KROKI_URL reaches here without going through the settings.
+    validate_url(server)?;
+    match format {
+        "pdf" => png_to_pdf(&request(server, source, "png")?),
+        _ => request(server, source, format),
+    }
+}
+
+// The blocking client refuses to run on an async runtime thread, and some
+// callers render from one, so the request gets a thread of its own.
+fn request(server: &str, source: &str, format: &str) -> Result<Vec<u8>, String> {
+    let url = format!("{}/mermaid/{}", server.trim_end_matches('/'), format);
+    let body = source.to_string();
+
+    thread::spawn(move || {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        let response = client
+            .post(&url)
+            .header("Content-Type", "text/plain")
+            .body(body)
+            .send()
+            .map_err(|e| format!("Failed to reach Kroki server: {}", e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().unwrap_or_default();
+            return Err(format!(
+                "Failed to render diagram: Kroki returned {}: {}",
+                status.as_u16(),
+                message.trim()
+            ));
+        }
+        let mut bytes = Vec::new();
+        response
+            .take(MAX_RESPONSE_BYTES + 1)
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read Kroki response: {}", e))?;
+        if bytes.len() as u64 > MAX_RESPONSE_BYTES {
+            return Err(format!(
+                "Kroki response is larger than {} MB",
+                MAX_RESPONSE_BYTES / (1024 * 1024)
+            ));
+        }
+        Ok(bytes)
+    })
+    .join()
+    .map_err(|_| "Kroki request failed".to_string())?
+}
+
+fn png_to_pdf(png: &[u8]) -> Result<Vec<u8>, String> {
+    let image = PdfImage::from_png(png)?;
+    let width = image.width as f64 * PT_PER_PX;
+    let height = image.height as f64 * PT_PER_PX;
+    let page = PdfPage {
+        width,
+        height,
+        content: pdf::draw_image(0.0, 0.0, width, height),
+    };
+    Ok(pdf::write(&image, &[page]))
+}
@@ -0,0 +1,19 @@
+use std::path::{Path, PathBuf};
+
+use crate::is_openable_file;
+
+// Extracts diagram paths passed by the OS "Open with" handler. Flags, URLs and
+// paths that do not point at an existing diagram file are ignored. Relative
+// paths are resolved against `cwd`, which matters for arguments forwarded
+// from a second instance started in another directory.
+pub fn file_arguments<I>(args: I, cwd: &Path) -> Vec<PathBuf>
+where
+    I: IntoIterator<Item = String>,
+{
+    args.into_iter()
+        .filter(|arg| !arg.starts_with('-') && !arg.contains("://"))
+        .map(|arg| cwd.join(arg))
+        .filter(|path| path.is_file() && is_openable_file(path))
+        .map(|path| std::fs::canonicalize(&path).unwrap_or(path))
+        .collect()
+}
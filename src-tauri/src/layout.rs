@@ -0,0 +1,116 @@
+use chrono::Utc;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+}
+
+// Positions the user dragged nodes to, by node id, in the renderer's
+// coordinates. Nodes without one are laid out by Mermaid.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Layout {
+    #[serde(default)]
+    pub nodes: BTreeMap<String, Position>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+}
+
+// Kept in a sidecar such as `flow.mmd.layout.json`, so hand-tuned layouts
+// follow the diagram when it is moved and can be committed with it.
+pub fn sidecar_path(diagram: &Path) -> PathBuf {
+    let mut name = diagram.as_os_str().to_owned();
+    name.push(".layout.json");
+    PathBuf::from(name)
+}
+
+pub fn get(diagram: &Path) -> Result<Layout, String> {
+    let sidecar = sidecar_path(diagram);
+    if !sidecar.is_file() {
+        return Ok(Layout::default());
+    }
+    let data = fs::read_to_string(&sidecar).map_err(|e| format!("Failed to read layout: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Invalid layout file: {}", e))
+}
+
+// Writes the layout, removing the sidecar once no node has a position.
+fn save(diagram: &Path, layout: &mut Layout) -> Result<(), String> {
+    let sidecar = sidecar_path(diagram);
+    if layout.nodes.is_empty() {
+        layout.updated_at = None;
+        if sidecar.exists() {
+            fs::remove_file(&sidecar).map_err(|e| format!("Failed to remove layout: {}", e))?;
+        }
+        return Ok(());
+    }
+    if layout
+        .nodes
+        .values()
+        .any(|position| !position.x.is_finite() || !position.y.is_finite())
+    {
+        return Err("Node positions must be finite numbers".to_string());
+    }
+    layout.updated_at = Some(Utc::now().to_rfc3339());
+    let data = serde_json::to_string_pretty(layout)
+        .map_err(|e| format!("Failed to save layout: {}", e))?;
+    fs::write(&sidecar, data).map_err(|e| format!("Failed to save layout: {}", e))
+}
+
+// Replaces every position; an empty layout resets the diagram to Mermaid's.
+pub fn set(diagram: &Path, mut layout: Layout) -> Result<Layout, String> {
+    save(diagram, &mut layout)?;
+    Ok(layout)
+}
+
+// Applies the positions of the nodes just dragged, leaving the others. A
+// node given no position goes back to Mermaid's.
+pub fn merge(
+    diagram: &Path,
+    positions: BTreeMap<String, Option<Position>>,
+) -> Result<Layout, String> {
+    let mut layout = get(diagram)?;
+    for (id, position) in positions {
+        match position {
+            Some(position) => layout.nodes.insert(id, position),
+            None => layout.nodes.remove(&id),
+        };
+    }
+    save(diagram, &mut layout)?;
+    Ok(layout)
+}
+
+// Moves a position along with a node that was renamed.
+pub fn rename_node(diagram: &Path, from: &str, to: &str) -> Result<Layout, String> {
+    let mut layout = get(diagram)?;
+    let Some(position) = layout.nodes.remove(from) else {
+        return Ok(layout);
+    };
+    layout.nodes.insert(to.to_string(), position);
+    save(diagram, &mut layout)?;
+    Ok(layout)
+}
+
+// Drops the positions of nodes whose id no longer appears in the saved
+// content. Ids are looked for as words rather than in the parsed model, so
+// subgraphs and participants the model does not list keep theirs.
+pub fn prune(diagram: &Path, content: &str) -> Result<(), String> {
+    if !sidecar_path(diagram).is_file() {
+        return Ok(());
+    }
+    let mut layout = get(diagram)?;
+    let before = layout.nodes.len();
+    layout.nodes.retain(|id, _| {
+        Regex::new(&format!(r"(?:^|[^\w-]){}(?:[^\w-]|$)", regex::escape(id)))
+            .map(|word| word.is_match(content))
+            .unwrap_or(true)
+    });
+    if layout.nodes.len() == before {
+        return Ok(());
+    }
+    save(diagram, &mut layout)
+}
@@ -2,39 +2,286 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use chrono::{DateTime, Utc};
-use tauri::{command, State};
+use tauri::{command, Emitter, Manager, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_deep_link::DeepLinkExt;
 use tauri_plugin_dialog::DialogExt;
 use std::sync::Mutex;
 
+pub mod accessibility;
+pub mod ai;
+pub mod anonymize;
+pub mod architecture;
+pub mod c4;
+pub mod cli;
+pub mod code_diagrams;
+pub mod comment_report;
+pub mod conflicts;
+pub mod csv;
+pub mod data_templates;
+pub mod deep_link;
+pub mod diagnostics;
+pub mod diagrams;
+pub mod diff;
+pub mod emoji;
+pub mod encryption;
+pub mod export;
+pub mod export_css;
+pub mod external;
+pub mod file_settings;
+pub mod files;
+pub mod fontawesome;
+pub mod format;
+pub mod gist;
+pub mod git_hook;
+pub mod gitgraph;
+pub mod i18n;
+pub mod ical;
+pub mod icons;
+pub mod images;
+pub mod importers;
+pub mod inkscape;
+pub mod jobs;
+pub mod keybindings;
+pub mod kroki;
+pub mod launch;
+pub mod layout;
+pub mod linked;
+pub mod links;
+pub mod logging;
+pub mod markdown;
+pub mod mermaid_config;
+pub mod mermaid_versions;
+pub mod mermaid_live;
+pub mod metrics_history;
+pub mod model;
+pub mod paths;
+pub mod pdf;
+pub mod plugins;
+mod os_recent;
+pub mod preview_server;
+pub mod print;
+pub mod redaction;
+pub mod render;
+pub mod review_comments;
+pub mod sankey;
+pub mod scratch;
+pub mod scripting;
+pub mod secrets;
+pub mod security;
+pub mod share;
+pub mod signing;
+pub mod snippets;
+pub mod spelling;
+pub mod split;
+pub mod storage;
+pub mod svg_fonts;
+pub mod tags;
+pub mod sync;
+pub mod telemetry;
+pub mod themes;
+pub mod time_tracking;
+pub mod thumbnails;
+pub mod undo_history;
+pub mod updates;
+pub mod validation;
+#[cfg(desktop)]
+mod shortcuts;
+#[cfg(desktop)]
+mod tray;
+pub mod watch;
+pub mod workspace_export;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RecentFile {
     pub path: String,
     pub name: String,
     pub last_opened: DateTime<Utc>,
+    // Came from a settings bundle made elsewhere. Listed, but not granted
+    // file access until the user opens the file on this machine.
+    #[serde(default)]
+    pub imported: bool,
+}
+
+// Favorites are kept apart from recent files and never roll off.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StarredFile {
+    pub path: String,
+    pub name: String,
+    pub starred_at: DateTime<Utc>,
+    // As for recent files.
+    #[serde(default)]
+    pub imported: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppState {
     pub recent_files: Vec<RecentFile>,
+    #[serde(default)]
+    pub starred_files: Vec<StarredFile>,
+    #[serde(default)]
+    pub file_tags: tags::FileTags,
+    #[serde(default)]
+    pub watch_config: Option<watch::WatchConfig>,
+    #[serde(default)]
+    pub preview_server: Option<preview_server::PreviewServerConfig>,
+    #[serde(default)]
+    pub close_to_tray: bool,
+    #[serde(default)]
+    pub enabled_plugins: Vec<String>,
+    #[serde(default)]
+    pub ai_config: Option<ai::AiConfig>,
+    #[serde(default)]
+    pub telemetry_enabled: bool,
+    #[serde(default)]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub keybindings: BTreeMap<String, Option<String>>,
+    #[serde(default)]
+    pub theme: Option<String>,
+    #[serde(default)]
+    pub mermaid_config: mermaid_config::MermaidConfig,
+    #[serde(default)]
+    pub mermaid_renderer: mermaid_versions::RendererConfig,
+    #[serde(default)]
+    pub workspace: Option<String>,
+    #[serde(default)]
+    pub external_editor: Option<String>,
+    #[serde(default)]
+    pub storage_backends: Vec<storage::BackendConfig>,
+    #[serde(default)]
+    pub spell_check: spelling::SpellCheckConfig,
+    // Strips scripts and other injection vectors from exported and shared
+    // diagrams.
+    #[serde(default)]
+    pub strip_unsafe_content: bool,
+    // Removes comments, directives and frontmatter from exports and from
+    // source that is shared as text, such as links, gists and Markdown.
+    #[serde(default)]
+    pub strip_source_metadata: bool,
+    // Patterns such as hostnames and customer names replaced in exported
+    // and shared diagrams.
+    #[serde(default)]
+    pub redaction: redaction::RedactionConfig,
+    // Expands `:rocket:`-style shortcodes in labels when rendering exports.
+    #[serde(default)]
+    pub expand_emoji: bool,
+    // Effort level for recompressing PNG exports, unless an export picks its
+    // own; None leaves them as rendered.
+    #[serde(default)]
+    pub optimize_png: Option<u8>,
+    // Base64 Ed25519 public keys whose signatures verify, besides the
+    // user's own.
+    #[serde(default)]
+    pub trusted_signing_keys: Vec<String>,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
             recent_files: Vec::new(),
+            starred_files: Vec::new(),
+            file_tags: tags::FileTags::new(),
+            watch_config: None,
+            preview_server: None,
+            close_to_tray: false,
+            enabled_plugins: Vec::new(),
+            ai_config: None,
+            telemetry_enabled: false,
+            locale: None,
+            keybindings: BTreeMap::new(),
+            theme: None,
+            mermaid_config: mermaid_config::MermaidConfig::default(),
+            mermaid_renderer: mermaid_versions::RendererConfig::default(),
+            workspace: None,
+            external_editor: None,
+            storage_backends: Vec::new(),
+            spell_check: spelling::SpellCheckConfig::default(),
+            strip_unsafe_content: false,
+            strip_source_metadata: false,
+            redaction: redaction::RedactionConfig::default(),
+            expand_emoji: false,
+            optimize_png: None,
+            trusted_signing_keys: Vec::new(),
         }
     }
 }
 
 pub type AppStateType = Mutex<AppState>;
 
-#[derive(Debug, Serialize, Deserialize)]
+pub const DIAGRAM_EXTENSIONS: [&str; 2] = ["mmd", "mermaid"];
+
+pub fn is_diagram_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| {
+            DIAGRAM_EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+        .unwrap_or(false)
+}
+
+// Files the user can open directly: plain diagrams plus encrypted ones,
+// which background features like watching and thumbnails skip.
+pub fn is_openable_file(path: &Path) -> bool {
+    is_diagram_file(path) || encryption::is_encrypted_path(path)
+}
+
+pub type WatchStateType = Mutex<Option<watch::WatchHandle>>;
+pub type ThumbnailStateType = Mutex<Option<thumbnails::ThumbnailHandle>>;
+pub type DocumentWatchStateType = Mutex<Option<external::DocumentWatchHandle>>;
+pub type LinkedWatchStateType = Mutex<Option<linked::LinkedWatchHandle>>;
+
+pub type PreviewServerStateType = Mutex<Option<preview_server::PreviewServerHandle>>;
+
+// Files opened before the frontend is listening are queued until it asks for them.
+#[derive(Debug, Default)]
+pub struct PendingOpens {
+    pub frontend_ready: bool,
+    pub queue: Vec<OpenFileEvent>,
+}
+
+pub type PendingOpensStateType = Mutex<PendingOpens>;
+
+// Canonical paths of documents opened read-only. Saving over them needs an
+// explicit Save As.
+pub type ReadOnlyStateType = Mutex<HashSet<PathBuf>>;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FileContent {
     pub content: String,
     pub path: Option<String>,
+    // Set for passphrase-protected files; `content` stays empty until the
+    // frontend asks for the passphrase and calls `open_encrypted_file`.
+    #[serde(default)]
+    pub encrypted: bool,
+    #[serde(default)]
+    pub read_only: bool,
+    // The file has unresolved git conflict markers; see `get_conflicts`.
+    #[serde(default)]
+    pub conflicted: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct OpenFileEvent {
+    pub file: FileContent,
+    pub line: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RejectedDrop {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileDropEvent {
+    pub files: Vec<FileContent>,
+    pub rejected: Vec<RejectedDrop>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,214 +305,3623 @@ pub async fn save_file_content_to_disk(
     content: String,
     path: Option<String>,
     app_handle: tauri::AppHandle,
-    state: State<'_, AppStateType>,
 ) -> Result<String, String> {
+    if let Some(p) = path.as_deref().filter(|p| storage::is_remote(p)) {
+        return save_remote_file(&app_handle, p, content).await;
+    }
     let file_path = if let Some(p) = path {
-        PathBuf::from(p)
+        let file_path = paths::check(&app_handle, &p)?;
+        ensure_writable_document(&app_handle, &file_path)?;
+        file_path
     } else {
         let dialog_result = app_handle
             .dialog()
             .file()
-            .add_filter("Mermaid Files", &["mmd", "mermaid"])
-            .add_filter("All Files", &["*"])
+            .add_filter(&i18n::t("dialog.filter.mermaid"), &["mmd", "mermaid"])
+            .add_filter(&i18n::t("dialog.filter.all"), &["*"])
             .blocking_save_file();
 
         match dialog_result {
             Some(file_path) => file_path.into_path().map_err(|e| format!("Failed to convert path: {}", e))?,
-            None => return Err("File save cancelled".to_string()),
+            None => return Err(i18n::t("error.save_cancelled")),
         }
     };
+    // Never write plain text under the encrypted extension.
+    if encryption::is_encrypted_path(&file_path) {
+        return Err("Encrypted files must be saved with a passphrase".to_string());
+    }
+    // Choosing a file in Save As is explicit enough to lift read-only mode.
+    set_read_only(&app_handle, &file_path, false);
+    paths::grant_file(&app_handle, &file_path);
 
-    match fs::write(&file_path, content) {
+    match fs::write(&file_path, &content) {
         Ok(_) => {
-            if let Ok(mut app_state) = state.lock() {
-                let path_str = file_path.to_string_lossy().to_string();
-                let name = file_path
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string();
-
-                app_state.recent_files.retain(|f| f.path != path_str);
-                app_state.recent_files.insert(
-                    0,
-                    RecentFile {
-                        path: path_str.clone(),
-                        name,
-                        last_opened: Utc::now(),
-                    },
-                );
-                app_state.recent_files.truncate(10);
-                let _ = save_app_state(&app_state);
+            external::note_saved(&file_path);
+            if let Err(e) = metrics_history::record(&file_path, &content) {
+                tracing::warn!(path = %file_path.display(), error = %e, "failed to record metrics");
             }
-
+            if let Err(e) = layout::prune(&file_path, &content) {
+                tracing::warn!(path = %file_path.display(), error = %e, "failed to prune layout");
+            }
+            remember_recent_file(&app_handle, &file_path);
             Ok(file_path.to_string_lossy().to_string())
         }
         Err(e) => Err(format!("Failed to save file: {}", e)),
     }
 }
 
+// `read_only` opens the document read-only even if the file is writable;
+// files the user cannot write to always open read-only.
 #[command]
 pub async fn load_file(
     path: Option<String>,
+    read_only: Option<bool>,
     app_handle: tauri::AppHandle,
-    state: State<'_, AppStateType>,
 ) -> Result<FileContent, String> {
+    if let Some(p) = path.as_deref().filter(|p| storage::is_remote(p)) {
+        return load_remote_file(&app_handle, p).await;
+    }
     let file_path = if let Some(p) = path {
-        PathBuf::from(p)
+        paths::check(&app_handle, &p)?
     } else {
         let dialog_result = app_handle
             .dialog()
             .file()
-            .add_filter("Mermaid Files", &["mmd", "mermaid", "txt"])
-            .add_filter("All Files", &["*"])
+            .add_filter(&i18n::t("dialog.filter.mermaid"), &["mmd", "mermaid", "txt"])
+            .add_filter(&i18n::t("dialog.filter.encrypted"), &[encryption::EXTENSION])
+            .add_filter(&i18n::t("dialog.filter.all"), &["*"])
             .blocking_pick_file();
 
         match dialog_result {
             Some(file_path) => file_path.into_path().map_err(|e| format!("Failed to convert path: {}", e))?,
-            None => return Err("File selection cancelled".to_string()),
+            None => return Err(i18n::t("error.open_cancelled")),
         }
     };
 
-    match fs::read_to_string(&file_path) {
-        Ok(content) => {
-            if let Ok(mut app_state) = state.lock() {
-                let path_str = file_path.to_string_lossy().to_string();
-                let name = file_path
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string();
-
-                app_state.recent_files.retain(|f| f.path != path_str);
-                app_state.recent_files.insert(
-                    0,
-                    RecentFile {
-                        path: path_str.clone(),
-                        name,
-                        last_opened: Utc::now(),
-                    },
-                );
-                app_state.recent_files.truncate(10);
-                let _ = save_app_state(&app_state);
-            }
+    read_diagram_file(&app_handle, &file_path, read_only.unwrap_or(false))
+}
 
+fn read_diagram_file(
+    app_handle: &tauri::AppHandle,
+    file_path: &Path,
+    read_only: bool,
+) -> Result<FileContent, String> {
+    match fs::read_to_string(file_path) {
+        Ok(content) => {
+            // Files the user opened, from a dialog or the OS, stay writable.
+            paths::grant_file(app_handle, file_path);
+            remember_recent_file(app_handle, file_path);
+            let read_only = read_only || !is_writable(file_path);
+            set_read_only(app_handle, file_path, read_only);
+            let encrypted = encryption::is_encrypted(&content);
+            let conflicted = !encrypted && conflicts::has_conflicts(&content);
             Ok(FileContent {
-                content,
+                content: if encrypted { String::new() } else { content },
                 path: Some(file_path.to_string_lossy().to_string()),
+                encrypted,
+                read_only,
+                conflicted,
             })
         }
         Err(e) => Err(format!("Failed to read file: {}", e)),
     }
 }
 
+fn storage_backend(
+    app_handle: &tauri::AppHandle,
+    backend_id: &str,
+) -> Result<storage::BackendConfig, String> {
+    let backend = match app_handle.state::<AppStateType>().lock() {
+        Ok(app_state) => app_state
+            .storage_backends
+            .iter()
+            .find(|backend| backend.id() == backend_id)
+            .cloned(),
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+    backend.ok_or_else(|| format!("Unknown storage backend: {}", backend_id))
+}
+
+// Remote documents bypass the path scope and recent files, which only
+// cover local files.
+async fn load_remote_file(app_handle: &tauri::AppHandle, path: &str) -> Result<FileContent, String> {
+    let (backend_id, key) = storage::parse(path)?;
+    let backend = storage_backend(app_handle, backend_id)?;
+    let bytes = storage::read(&backend, key).await?;
+    let content = String::from_utf8(bytes).map_err(|e| format!("Failed to read file: {}", e))?;
+    if encryption::is_encrypted(&content) {
+        return Err("Encrypted files cannot be opened from remote storage".to_string());
+    }
+    Ok(FileContent {
+        content,
+        path: Some(path.to_string()),
+        encrypted: false,
+        read_only: false,
+        conflicted: false,
+    })
+}
+
+async fn save_remote_file(
+    app_handle: &tauri::AppHandle,
+    path: &str,
+    content: String,
+) -> Result<String, String> {
+    let (backend_id, key) = storage::parse(path)?;
+    let backend = storage_backend(app_handle, backend_id)?;
+    storage::write(&backend, key, content.into_bytes()).await?;
+    Ok(path.to_string())
+}
+
+// Opening for writing without truncating catches both missing permissions
+// and read-only mounts, which the permission bits alone do not.
+fn is_writable(file_path: &Path) -> bool {
+    fs::OpenOptions::new().write(true).open(file_path).is_ok()
+}
+
+fn set_read_only(app_handle: &tauri::AppHandle, file_path: &Path, read_only: bool) {
+    let canonical = file_path.canonicalize().unwrap_or_else(|_| file_path.to_path_buf());
+    if let Ok(mut paths) = app_handle.state::<ReadOnlyStateType>().lock() {
+        if read_only {
+            paths.insert(canonical);
+        } else {
+            paths.remove(&canonical);
+        }
+    }
+}
+
+fn ensure_writable_document(app_handle: &tauri::AppHandle, file_path: &Path) -> Result<(), String> {
+    let canonical = file_path.canonicalize().unwrap_or_else(|_| file_path.to_path_buf());
+    let read_only = app_handle
+        .state::<ReadOnlyStateType>()
+        .lock()
+        .map_err(|_| i18n::t("error.app_state"))?
+        .contains(&canonical);
+    if read_only {
+        return Err(i18n::t("error.read_only"));
+    }
+    Ok(())
+}
+
+#[command]
+pub async fn save_encrypted_file(
+    content: String,
+    passphrase: String,
+    path: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let file_path = if let Some(p) = path {
+        let file_path = paths::check(&app_handle, &p)?;
+        ensure_writable_document(&app_handle, &file_path)?;
+        file_path
+    } else {
+        let dialog_result = app_handle
+            .dialog()
+            .file()
+            .add_filter(&i18n::t("dialog.filter.encrypted"), &[encryption::EXTENSION])
+            .blocking_save_file();
+
+        match dialog_result {
+            Some(file_path) => file_path.into_path().map_err(|e| format!("Failed to convert path: {}", e))?,
+            None => return Err(i18n::t("error.save_cancelled")),
+        }
+    };
+    set_read_only(&app_handle, &file_path, false);
+    paths::grant_file(&app_handle, &file_path);
+
+    // Key derivation is deliberately slow, so keep it off the async runtime.
+    let encrypted = jobs::run(move || encryption::encrypt(&content, &passphrase)).await??;
+    fs::write(&file_path, encrypted).map_err(|e| format!("Failed to save file: {}", e))?;
+    external::note_saved(&file_path);
+    remember_recent_file(&app_handle, &file_path);
+    Ok(file_path.to_string_lossy().to_string())
+}
+
+#[command]
+pub async fn open_encrypted_file(
+    path: String,
+    passphrase: String,
+    read_only: Option<bool>,
+    app_handle: tauri::AppHandle,
+) -> Result<FileContent, String> {
+    let file_path = paths::check(&app_handle, &path)?;
+    let data = fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let content = jobs::run(move || encryption::decrypt(&data, &passphrase)).await??;
+
+    remember_recent_file(&app_handle, &file_path);
+    let read_only = read_only.unwrap_or(false) || !is_writable(&file_path);
+    set_read_only(&app_handle, &file_path, read_only);
+    Ok(FileContent {
+        content,
+        path: Some(file_path.to_string_lossy().to_string()),
+        encrypted: true,
+        read_only,
+        conflicted: false,
+    })
+}
+
+// Copies a diagram and its sidecars next to the original. Without
+// `new_name` the copy is called "<name> copy".
+#[command]
+pub async fn duplicate_file(
+    path: String,
+    new_name: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let source = paths::check(&app_handle, &path)?;
+    let target = files::duplicate(&source, new_name.as_deref())?;
+
+    paths::grant_file(&app_handle, &target);
+    remember_recent_file(&app_handle, &target);
+    Ok(target.to_string_lossy().to_string())
+}
+
+// Size, timestamps, diagram statistics and git status for a properties
+// panel.
+#[command]
+pub async fn get_file_info(path: String, app_handle: tauri::AppHandle) -> Result<files::FileInfo, String> {
+    let path = paths::check(&app_handle, &path)?;
+    jobs::run(move || files::info(&path)).await?
+}
+
+// The diagram review comments are for. Encrypted diagrams get none, since
+// the sidecar would quote their lines in plain text.
+fn review_target(app_handle: &tauri::AppHandle, path: &str) -> Result<PathBuf, String> {
+    let path = paths::check(app_handle, path)?;
+    if encryption::is_encrypted_path(&path) {
+        return Err("Encrypted diagrams cannot have review comments".to_string());
+    }
+    Ok(path)
+}
+
+// `content` is the editor's text, which threads are anchored to; without it
+// the file on disk is used.
+fn review_content(path: &Path, content: Option<String>) -> Result<String, String> {
+    match content {
+        Some(content) => Ok(content),
+        None => fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e)),
+    }
+}
+
+#[command]
+pub async fn get_review_comments(
+    path: String,
+    content: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<review_comments::Thread>, String> {
+    let path = review_target(&app_handle, &path)?;
+    review_comments::list(&path, &review_content(&path, content)?)
+}
+
+// Starts a thread on a line of the diagram.
+#[command]
+pub async fn add_review_comment(
+    path: String,
+    line: usize,
+    body: String,
+    author: Option<String>,
+    content: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<review_comments::Thread, String> {
+    let path = review_target(&app_handle, &path)?;
+    let content = review_content(&path, content)?;
+    telemetry::record_feature("review_comments");
+    review_comments::start_thread(&path, &content, line, &body, author.as_deref())
+}
+
+#[command]
+pub async fn reply_to_review_comment(
+    path: String,
+    thread_id: u64,
+    body: String,
+    author: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<review_comments::Thread, String> {
+    review_comments::reply(&review_target(&app_handle, &path)?, thread_id, &body, author.as_deref())
+}
+
+#[command]
+pub async fn edit_review_comment(
+    path: String,
+    thread_id: u64,
+    comment_id: u64,
+    body: String,
+    app_handle: tauri::AppHandle,
+) -> Result<review_comments::Thread, String> {
+    review_comments::edit(&review_target(&app_handle, &path)?, thread_id, comment_id, &body)
+}
+
+#[command]
+pub async fn resolve_review_thread(
+    path: String,
+    thread_id: u64,
+    resolved: bool,
+    app_handle: tauri::AppHandle,
+) -> Result<review_comments::Thread, String> {
+    review_comments::set_resolved(&review_target(&app_handle, &path)?, thread_id, resolved)
+}
+
+#[command]
+pub async fn delete_review_comment(
+    path: String,
+    thread_id: u64,
+    comment_id: u64,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    review_comments::delete(&review_target(&app_handle, &path)?, thread_id, comment_id)
+}
+
+#[command]
+pub async fn delete_review_thread(
+    path: String,
+    thread_id: u64,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    review_comments::delete_thread(&review_target(&app_handle, &path)?, thread_id)
+}
+
+// Editing time: the editor starts a session when a file gets focus and
+// ends it on blur, idle or close, and the backend adds them up per day.
+#[command]
+pub async fn start_editing_session(path: String, app_handle: tauri::AppHandle) -> Result<(), String> {
+    time_tracking::start(&paths::check(&app_handle, &path)?);
+    Ok(())
+}
+
+#[command]
+pub async fn end_editing_session(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<time_tracking::TimeTotal, String> {
+    time_tracking::end(&paths::check(&app_handle, &path)?)
+}
+
+// Totals for one file, or for every file when `path` is None.
+#[command]
+pub async fn get_editing_time(
+    path: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<time_tracking::TimeTotal>, String> {
+    let path = path.map(|path| paths::check(&app_handle, &path)).transpose()?;
+    time_tracking::totals(path.as_deref())
+}
+
+#[command]
+pub async fn reset_editing_time(path: String, app_handle: tauri::AppHandle) -> Result<(), String> {
+    time_tracking::reset(&paths::check(&app_handle, &path)?)
+}
+
+// Node and edge counts and size as of each day the file was saved, oldest
+// first, for charting how the diagram grew.
+#[command]
+pub async fn get_file_metrics_history(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<metrics_history::MetricsEntry>, String> {
+    metrics_history::get(&paths::check(&app_handle, &path)?)
+}
+
+#[command]
+pub async fn get_conflicts(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<conflicts::ConflictInfo, String> {
+    let path = paths::check(&app_handle, &path)?;
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    conflicts::conflicts(&content)
+}
+
+// Replaces each conflict in the file with the chosen side, in order, and
+// saves the result.
+#[command]
+pub async fn resolve_conflict(
+    path: String,
+    choices: Vec<conflicts::Choice>,
+    app_handle: tauri::AppHandle,
+) -> Result<conflicts::Resolution, String> {
+    let path = paths::check(&app_handle, &path)?;
+    ensure_writable_document(&app_handle, &path)?;
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let resolution = conflicts::resolve(&content, &choices)?;
+
+    fs::write(&path, &resolution.content).map_err(|e| format!("Failed to save file: {}", e))?;
+    external::note_saved(&path);
+    telemetry::record_feature("resolve_conflict");
+    Ok(resolution)
+}
+
+// Compares two saved diagrams line by line and node by node; `path_a` is
+// treated as the older version.
+#[command]
+pub async fn diff_files(
+    path_a: String,
+    path_b: String,
+    app_handle: tauri::AppHandle,
+) -> Result<diff::FileDiff, String> {
+    let path_a = paths::check(&app_handle, &path_a)?;
+    let path_b = paths::check(&app_handle, &path_b)?;
+    jobs::run(move || diff::diff_files(&path_a, &path_b)).await?
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileRenamedEvent {
+    pub old_path: String,
+    pub new_path: String,
+}
+
+// Moves a diagram and its sidecars, then points recent files and read-only
+// marks at the new path. Open editors learn about it from "file-renamed".
+#[command]
+pub async fn rename_file(
+    old_path: String,
+    new_path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let source = paths::check(&app_handle, &old_path)?;
+    // Renaming in place only needs access to the file itself; moving it
+    // elsewhere needs access to the destination too.
+    let target = paths::resolve(&new_path)?;
+    if target.parent() != source.parent() {
+        paths::check(&app_handle, &new_path)?;
+    }
+
+    files::rename(&source, &target)?;
+    paths::grant_file(&app_handle, &target);
+    external::rename_document(&source, &target);
+    if let Err(e) = time_tracking::rename(&source, &target) {
+        tracing::warn!(path = %target.display(), error = %e, "failed to move editing time");
+    }
+    if let Err(e) = metrics_history::rename(&source, &target) {
+        tracing::warn!(path = %target.display(), error = %e, "failed to move metrics history");
+    }
+
+    if let Ok(mut app_state) = app_handle.state::<AppStateType>().lock() {
+        let old = source.to_string_lossy().to_string();
+        let name = target
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        for recent in app_state.recent_files.iter_mut().filter(|f| f.path == old) {
+            recent.path = target.to_string_lossy().to_string();
+            recent.name = name.clone();
+        }
+        for starred in app_state.starred_files.iter_mut().filter(|f| f.path == old) {
+            starred.path = target.to_string_lossy().to_string();
+            starred.name = name.clone();
+        }
+        tags::rename(&mut app_state.file_tags, &old, &target.to_string_lossy());
+        persist_app_state(&app_state);
+    }
+    if let Ok(mut read_only) = app_handle.state::<ReadOnlyStateType>().lock() {
+        if read_only.remove(&source) {
+            read_only.insert(target.clone());
+        }
+    }
+    refresh_tray(&app_handle);
+
+    let new_path = target.to_string_lossy().to_string();
+    let _ = app_handle.emit(
+        "file-renamed",
+        FileRenamedEvent {
+            old_path: source.to_string_lossy().to_string(),
+            new_path: new_path.clone(),
+        },
+    );
+    Ok(new_path)
+}
+
+fn remember_recent_file(app_handle: &tauri::AppHandle, file_path: &Path) {
+    if let Ok(mut app_state) = app_handle.state::<AppStateType>().lock() {
+        let path_str = file_path.to_string_lossy().to_string();
+        let name = file_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        app_state.recent_files.retain(|f| f.path != path_str);
+        app_state.recent_files.insert(
+            0,
+            RecentFile {
+                path: path_str.clone(),
+                name,
+                last_opened: Utc::now(),
+                imported: false,
+            },
+        );
+        app_state.recent_files.truncate(10);
+        persist_app_state(&app_state);
+    }
+
+    refresh_tray(app_handle);
+    os_recent::note_recent_document(app_handle, file_path);
+}
+
+// `document` identifies the editor buffer (usually its path) so rapid calls
+// for it can be coalesced; responses carry a generation for ordering.
 #[command]
-pub async fn validate_mermaid_syntax(content: String) -> Result<ValidationResult, String> {
-    let errors = Vec::new();
+pub async fn validate_mermaid_syntax(
+    content: String,
+    document: Option<String>,
+) -> Result<validation::ValidationResponse, String> {
+    validation::validate(content, document).await
+}
+
+// Returns the diagram keyword of the first statement, skipping frontmatter,
+// %% comments and init directives.
+pub fn detect_diagram_type(content: &str) -> Option<&'static str> {
+    let mut lines = content.lines().map(str::trim).peekable();
+
+    if lines.peek() == Some(&"---") {
+        lines.next();
+        for line in lines.by_ref() {
+            if line == "---" {
+                break;
+            }
+        }
+    }
+
+    let first_line = lines
+        .find(|line| !line.is_empty() && !line.starts_with("%%"))?
+        .to_lowercase();
+
+    diagrams::find(&first_line).map(|kind| kind.id)
+}
+
+pub fn validate_content(content: &str) -> ValidationResult {
+    let mut errors = Vec::new();
     let mut warnings = Vec::new();
 
     let lines: Vec<&str> = content.lines().collect();
 
     if lines.is_empty() {
         warnings.push("Empty diagram".to_string());
-        return Ok(ValidationResult {
+        return ValidationResult {
             is_valid: true,
             errors,
             warnings,
-        });
+        };
     }
 
-    let first_line = lines[0].trim().to_lowercase();
-    let valid_diagrams = [
-        "graph", "flowchart", "sequencediagram", "classdiagram",
-        "statediagram", "erdiagram", "journey", "gantt", "pie",
-        "gitgraph", "mindmap", "timeline", "zenuml", "sankey"
-    ];
-
-    let has_valid_start = valid_diagrams.iter().any(|&diagram| {
-        first_line.starts_with(diagram)
+    let settings = file_settings::get(content).unwrap_or_else(|e| {
+        warnings.push(e);
+        file_settings::FileSettings::default()
     });
 
-    if !has_valid_start {
-        warnings.push("Diagram type not recognized. Make sure to start with a valid diagram type.".to_string());
+    // Validation runs on every keystroke, so it only detects the type rather
+    // than filling the parse cache with each intermediate edit.
+    match detect_diagram_type(content).and_then(diagrams::find) {
+        None => warnings.push("Diagram type not recognized. Make sure to start with a valid diagram type.".to_string()),
+        Some(kind) => {
+            if let Some(validate) = kind.validate {
+                validate(content, kind.id, &mut errors, &mut warnings);
+            }
+            if settings.allows("fontawesome") {
+                fontawesome::validate(content, &mut warnings);
+            }
+        }
     }
 
-    Ok(ValidationResult {
+    ValidationResult {
         is_valid: errors.is_empty(),
         errors,
         warnings,
-    })
+    }
 }
 
 #[command]
 pub async fn get_recent_files(state: State<'_, AppStateType>) -> Result<Vec<RecentFile>, String> {
     match state.lock() {
         Ok(app_state) => Ok(app_state.recent_files.clone()),
-        Err(_) => Err("Failed to access app state".to_string()),
+        Err(_) => Err(i18n::t("error.app_state")),
     }
 }
 
 #[command]
-pub async fn clear_recent_files(state: State<'_, AppStateType>) -> Result<(), String> {
-    match state.lock() {
+pub async fn clear_recent_files(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    let result = match state.lock() {
         Ok(mut app_state) => {
             app_state.recent_files.clear();
             save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))
         }
-        Err(_) => Err("Failed to access app state".to_string()),
-    }
+        Err(_) => Err(i18n::t("error.app_state")),
+    };
+
+    refresh_tray(&app_handle);
+    result
 }
 
+// Starred files, most recently starred first.
 #[command]
-pub async fn get_templates() -> Result<Vec<Template>, String> {
-    Ok(vec![
-        Template {
-            id: "flowchart-basic".to_string(),
-            name: "Basic Flowchart".to_string(),
-            description: "A simple flowchart template".to_string(),
-            content: "flowchart TD\n    A[Start] --> B{Decision}\n    B -->|Yes| C[Action 1]\n    B -->|No| D[Action 2]\n    C --> E[End]\n    D --> E".to_string(),
-            category: "Flowchart".to_string(),
-        },
-        Template {
-            id: "sequence-basic".to_string(),
-            name: "Basic Sequence".to_string(),
-            description: "A simple sequence diagram template".to_string(),
-            content: "sequenceDiagram\n    participant A as Alice\n    participant B as Bob\n    A->>B: Hello Bob, how are you?\n    B-->>A: Great!".to_string(),
-            category: "Sequence".to_string(),
-        },
-    ])
+pub async fn get_starred_files(state: State<'_, AppStateType>) -> Result<Vec<StarredFile>, String> {
+    match state.lock() {
+        Ok(app_state) => Ok(app_state.starred_files.clone()),
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
 }
 
 #[command]
-pub async fn export_diagram(
-    content: String,
-    format: String,
+pub async fn star_file(
+    path: String,
     app_handle: tauri::AppHandle,
-) -> Result<String, String> {
-    let extension = match format.as_str() {
-        "png" => "png",
-        "svg" => "svg",
-        "pdf" => "pdf",
-        _ => return Err("Unsupported format".to_string()),
-    };
-
-    let dialog_result = app_handle
-        .dialog()
-        .file()
-        .add_filter(&format!("{} Files", format.to_uppercase()), &[extension])
-        .blocking_save_file();
-
-    match dialog_result {
-        Some(file_path) => {
-            let path_buf = file_path.into_path().map_err(|e| format!("Failed to convert path: {}", e))?;
-            let path_str = path_buf.to_string_lossy().to_string();
-
-            match fs::write(&path_buf, content) {
-                Ok(_) => Ok(path_str),
-                Err(e) => Err(format!("Failed to export: {}", e)),
+    state: State<'_, AppStateType>,
+) -> Result<Vec<StarredFile>, String> {
+    let path = paths::check(&app_handle, &path)?;
+    let path_str = path.to_string_lossy().to_string();
+    match state.lock() {
+        Ok(mut app_state) => {
+            // Starring an imported entry here makes it this machine's own.
+            if let Some(starred) = app_state
+                .starred_files
+                .iter_mut()
+                .find(|f| f.path == path_str && f.imported)
+            {
+                starred.imported = false;
+                save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))?;
+            } else if !app_state.starred_files.iter().any(|f| f.path == path_str) {
+                app_state.starred_files.insert(
+                    0,
+                    StarredFile {
+                        path: path_str,
+                        name: path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string(),
+                        starred_at: Utc::now(),
+                        imported: false,
+                    },
+                );
+                save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))?;
             }
+            Ok(app_state.starred_files.clone())
         }
-        None => Err("Export cancelled".to_string()),
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// Takes the path as listed, so files that no longer exist can be unstarred.
+#[command]
+pub async fn unstar_file(path: String, state: State<'_, AppStateType>) -> Result<Vec<StarredFile>, String> {
+    match state.lock() {
+        Ok(mut app_state) => {
+            let before = app_state.starred_files.len();
+            app_state.starred_files.retain(|f| f.path != path);
+            if app_state.starred_files.len() != before {
+                save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))?;
+            }
+            Ok(app_state.starred_files.clone())
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn get_file_tags(path: String, state: State<'_, AppStateType>) -> Result<Vec<String>, String> {
+    match state.lock() {
+        Ok(app_state) => Ok(tags::get(&app_state.file_tags, &path)),
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// Returns the file's tags after the change.
+#[command]
+pub async fn tag_file(
+    path: String,
+    tag: String,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<Vec<String>, String> {
+    let path = paths::check(&app_handle, &path)?.to_string_lossy().to_string();
+    match state.lock() {
+        Ok(mut app_state) => {
+            let file_tags = tags::add(&mut app_state.file_tags, &path, &tag)?;
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))?;
+            Ok(file_tags)
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// Takes the path as listed, so files that no longer exist can be untagged.
+#[command]
+pub async fn untag_file(
+    path: String,
+    tag: String,
+    state: State<'_, AppStateType>,
+) -> Result<Vec<String>, String> {
+    match state.lock() {
+        Ok(mut app_state) => {
+            let file_tags = tags::remove(&mut app_state.file_tags, &path, &tag);
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))?;
+            Ok(file_tags)
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn find_files_by_tag(
+    tag: String,
+    state: State<'_, AppStateType>,
+) -> Result<Vec<tags::TaggedFile>, String> {
+    match state.lock() {
+        Ok(app_state) => Ok(tags::find(&app_state.file_tags, &tag)),
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn get_all_tags(state: State<'_, AppStateType>) -> Result<Vec<tags::TagCount>, String> {
+    match state.lock() {
+        Ok(app_state) => Ok(tags::all(&app_state.file_tags)),
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn get_templates() -> Result<Vec<Template>, String> {
+    Ok(builtin_templates())
+}
+
+#[command]
+pub async fn list_snippets() -> Result<Vec<snippets::Snippet>, String> {
+    snippets::list()
+}
+
+#[command]
+pub async fn get_snippet_categories() -> Result<Vec<String>, String> {
+    snippets::categories()
+}
+
+#[command]
+pub async fn create_snippet(draft: snippets::SnippetDraft) -> Result<snippets::Snippet, String> {
+    telemetry::record_feature("snippets");
+    snippets::create(draft)
+}
+
+#[command]
+pub async fn update_snippet(
+    id: String,
+    draft: snippets::SnippetDraft,
+) -> Result<snippets::Snippet, String> {
+    snippets::update(&id, draft)
+}
+
+#[command]
+pub async fn delete_snippet(id: String) -> Result<(), String> {
+    snippets::delete(&id)
+}
+
+// The snippet's body with its placeholders filled in, ready to insert at the
+// cursor.
+#[command]
+pub async fn expand_snippet(
+    id: String,
+    values: Option<HashMap<String, String>>,
+) -> Result<snippets::Expanded, String> {
+    let snippet = snippets::get(&id)?;
+    Ok(snippets::expand(&snippet.body, &values.unwrap_or_default()))
+}
+
+#[command]
+pub async fn get_data_templates() -> Result<Vec<Template>, String> {
+    Ok(data_templates::list())
+}
+
+// Fills a data template with a CSV or JSON file. Linking the diagram to the
+// data file with `link_diagram_source` keeps it up to date.
+#[command]
+pub async fn render_data_template(
+    template_id: String,
+    data_path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<data_templates::Rendered, String> {
+    let data_path = paths::check(&app_handle, &data_path)?;
+    telemetry::track(
+        "render_data_template",
+        telemetry::ErrorKind::Parse,
+        jobs::run(move || data_templates::render_file(&template_id, &data_path)).await?,
+    )
+}
+
+#[command]
+pub async fn create_new_diagram() -> Result<FileContent, String> {
+    Ok(new_diagram_content())
+}
+
+#[command]
+pub async fn set_close_to_tray(enabled: bool, state: State<'_, AppStateType>) -> Result<(), String> {
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.close_to_tray = enabled;
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+fn new_diagram_content() -> FileContent {
+    FileContent {
+        content: builtin_templates()
+            .into_iter()
+            .next()
+            .map(|template| template.content)
+            .unwrap_or_default(),
+        path: None,
+        encrypted: false,
+        read_only: false,
+        conflicted: false,
+    }
+}
+
+fn builtin_templates() -> Vec<Template> {
+    vec![
+        Template {
+            id: "flowchart-basic".to_string(),
+            name: "Basic Flowchart".to_string(),
+            description: "A simple flowchart template".to_string(),
+            content: "flowchart TD\n    A[Start] --> B{Decision}\n    B -->|Yes| C[Action 1]\n    B -->|No| D[Action 2]\n    C --> E[End]\n    D --> E".to_string(),
+            category: "Flowchart".to_string(),
+        },
+        Template {
+            id: "sequence-basic".to_string(),
+            name: "Basic Sequence".to_string(),
+            description: "A simple sequence diagram template".to_string(),
+            content: "sequenceDiagram\n    participant A as Alice\n    participant B as Bob\n    A->>B: Hello Bob, how are you?\n    B-->>A: Great!".to_string(),
+            category: "Sequence".to_string(),
+        },
+        Template {
+            id: "c4-context".to_string(),
+            name: "C4 System Context".to_string(),
+            description: "A system with its users and the external systems it talks to".to_string(),
+            content: "C4Context\n    title System Context diagram for Internet Banking\n    Person(customer, \"Customer\", \"A customer of the bank\")\n    System(banking, \"Internet Banking\", \"Lets customers view their accounts and make payments\")\n    System_Ext(mail, \"E-mail System\", \"Sends e-mails to customers\")\n    Rel(customer, banking, \"Uses\")\n    Rel(banking, mail, \"Sends e-mails using\")\n    Rel(mail, customer, \"Sends e-mails to\")".to_string(),
+            category: "C4".to_string(),
+        },
+        Template {
+            id: "c4-container".to_string(),
+            name: "C4 Container".to_string(),
+            description: "The applications and data stores inside a system".to_string(),
+            content: "C4Container\n    title Container diagram for Internet Banking\n    Person(customer, \"Customer\")\n    System_Boundary(banking, \"Internet Banking\") {\n        Container(web, \"Web Application\", \"TypeScript, React\", \"Delivers the single-page app\")\n        Container(api, \"API Application\", \"Rust\", \"Provides banking functionality over JSON/HTTPS\")\n        ContainerDb(db, \"Database\", \"PostgreSQL\", \"Stores accounts and transactions\")\n    }\n    Rel(customer, web, \"Uses\", \"HTTPS\")\n    Rel(web, api, \"Calls\", \"JSON/HTTPS\")\n    Rel(api, db, \"Reads from and writes to\", \"SQL\")".to_string(),
+            category: "C4".to_string(),
+        },
+        Template {
+            id: "c4-component".to_string(),
+            name: "C4 Component".to_string(),
+            description: "The components inside one container".to_string(),
+            content: "C4Component\n    title Component diagram for the API Application\n    Container(web, \"Web Application\", \"TypeScript, React\")\n    ContainerDb(db, \"Database\", \"PostgreSQL\")\n    Container_Boundary(api, \"API Application\") {\n        Component(auth, \"Sign-in Controller\", \"Axum handler\", \"Signs users in\")\n        Component(accounts, \"Accounts Controller\", \"Axum handler\", \"Lists a customer's accounts\")\n        Component(store, \"Account Store\", \"sqlx\", \"Reads accounts from the database\")\n    }\n    Rel(web, auth, \"Signs in using\", \"JSON/HTTPS\")\n    Rel(web, accounts, \"Fetches accounts from\", \"JSON/HTTPS\")\n    Rel(accounts, store, \"Uses\")\n    Rel(store, db, \"Reads from\", \"SQL\")".to_string(),
+            category: "C4".to_string(),
+        },
+        Template {
+            id: "sankey-basic".to_string(),
+            name: "Basic Sankey".to_string(),
+            description: "Energy flows from sources to uses, as source,target,value rows".to_string(),
+            content: "sankey-beta\n\nSolar,Electricity,40\nWind,Electricity,35\nGas,Electricity,25\nGas,Heating,30\nElectricity,Homes,45\nElectricity,Industry,40\nElectricity,Losses,15\nHeating,Homes,30".to_string(),
+            category: "Sankey".to_string(),
+        },
+        Template {
+            id: "xychart-basic".to_string(),
+            name: "Basic XY Chart".to_string(),
+            description: "Monthly figures as bars with a trend line".to_string(),
+            content: "xychart-beta\n    title \"Sales Revenue\"\n    x-axis [jan, feb, mar, apr, may, jun]\n    y-axis \"Revenue (in $)\" 4000 --> 11000\n    bar [5000, 6000, 7500, 8200, 9500, 10500]\n    line [5000, 6000, 7500, 8200, 9500, 10500]".to_string(),
+            category: "Chart".to_string(),
+        },
+        Template {
+            id: "quadrant-basic".to_string(),
+            name: "Basic Quadrant Chart".to_string(),
+            description: "Items placed by reach and engagement in four quadrants".to_string(),
+            content: "quadrantChart\n    title Reach and engagement of campaigns\n    x-axis Low Reach --> High Reach\n    y-axis Low Engagement --> High Engagement\n    quadrant-1 We should expand\n    quadrant-2 Need to promote\n    quadrant-3 Re-evaluate\n    quadrant-4 May be improved\n    Campaign A: [0.3, 0.6]\n    Campaign B: [0.45, 0.23]\n    Campaign C: [0.57, 0.69]\n    Campaign D: [0.78, 0.34]".to_string(),
+            category: "Chart".to_string(),
+        },
+        Template {
+            id: "block-basic".to_string(),
+            name: "Basic Block Diagram".to_string(),
+            description: "Blocks laid out in columns, with a nested group".to_string(),
+            content: "block-beta\n    columns 3\n    frontend[\"Frontend\"] space backend[\"Backend\"]\n    block:storage:3\n        cache[(\"Cache\")] db[(\"Database\")] files[(\"Files\")]\n    end\n    frontend --> backend\n    backend --> storage".to_string(),
+            category: "Block".to_string(),
+        },
+        Template {
+            id: "packet-basic".to_string(),
+            name: "Basic Packet Diagram".to_string(),
+            description: "The fields of a UDP header by bit position".to_string(),
+            content: "packet-beta\n    title UDP Packet\n    0-15: \"Source Port\"\n    16-31: \"Destination Port\"\n    32-47: \"Length\"\n    48-63: \"Checksum\"\n    64-95: \"Data (variable length)\"".to_string(),
+            category: "Packet".to_string(),
+        },
+        Template {
+            id: "requirement-basic".to_string(),
+            name: "Basic Requirement Diagram".to_string(),
+            description: "Requirements with the elements that satisfy and verify them".to_string(),
+            content: "requirementDiagram\n\n    requirement login_req {\n        id: 1\n        text: Users can sign in with a password\n        risk: high\n        verifymethod: test\n    }\n\n    functionalRequirement lockout_req {\n        id: 1.1\n        text: Accounts lock after five failed attempts\n        risk: medium\n        verifymethod: inspection\n    }\n\n    element login_page {\n        type: simulation\n    }\n\n    element login_tests {\n        type: test suite\n        docref: tests/login\n    }\n\n    login_page - satisfies -> login_req\n    login_tests - verifies -> login_req\n    lockout_req - derives -> login_req".to_string(),
+            category: "Requirement".to_string(),
+        },
+        Template {
+            id: "architecture-basic".to_string(),
+            name: "Basic Architecture Diagram".to_string(),
+            description: "Services inside a cloud group, connected by side".to_string(),
+            content: "architecture-beta\n    group api(cloud)[API]\n\n    service db(database)[Database] in api\n    service disk1(disk)[Storage] in api\n    service disk2(disk)[Storage] in api\n    service server(server)[Server] in api\n    service gateway(internet)[Gateway]\n\n    db:L -- R:server\n    disk1:T -- B:server\n    disk2:T -- B:db\n    server{group}:R --> L:gateway".to_string(),
+            category: "Architecture".to_string(),
+        },
+    ]
+}
+
+// Content about to leave the app other than through `export::export`,
+// with redaction rules applied and unsafe parts stripped when the user
+// turned those on, in the order exports use. Exports do both themselves,
+// from their options. The redaction report goes to the frontend as an
+// event, like the one sent after an export.
+fn outgoing_content(
+    content: String,
+    feature: &str,
+    app_handle: &tauri::AppHandle,
+    state: &AppStateType,
+) -> String {
+    let (strip, redaction) = state
+        .lock()
+        .map(|app_state| (app_state.strip_unsafe_content, app_state.redaction.active()))
+        .unwrap_or_default();
+    let content = match redaction {
+        Some(redaction) => {
+            let redacted = redaction::apply(&content, &redaction);
+            let _ = app_handle.emit(
+                "content-redacted",
+                serde_json::json!({
+                    "feature": feature,
+                    "report": redacted.report,
+                }),
+            );
+            redacted.content
+        }
+        None => content,
+    };
+    if strip {
+        let sanitized = security::sanitize(&content);
+        for finding in &sanitized.findings {
+            tracing::warn!(line = finding.line, "stripped unsafe content: {}", finding.message);
+        }
+        sanitized.content
+    } else {
+        content
+    }
+}
+
+// Source shared as text rather than rendered, where comments and
+// frontmatter would be visible to whoever receives it.
+fn shared_source(
+    content: String,
+    feature: &str,
+    app_handle: &tauri::AppHandle,
+    state: &AppStateType,
+) -> String {
+    let content = outgoing_content(content, feature, app_handle, state);
+    let strip = state
+        .lock()
+        .map(|app_state| app_state.strip_source_metadata)
+        .unwrap_or(false);
+    if strip {
+        comment_report::strip(&content)
+    } else {
+        content
+    }
+}
+
+// Checks the paths in export options, which have to be in the fs scope like
+// any other path the frontend passes, fills in the stored export CSS unless
+// the caller passed CSS of its own, and applies the export settings.
+fn prepare_export_options(
+    app_handle: &tauri::AppHandle,
+    state: &AppStateType,
+    options: &mut export::ExportOptions,
+) -> Result<(), String> {
+    if options.inline_images {
+        if let Some(dir) = &options.image_dir {
+            paths::check(app_handle, dir)?;
+        }
+    }
+    let source = match &options.source {
+        Some(source) => Some(paths::check(app_handle, source)?),
+        None => None,
+    };
+    apply_export_settings(state, options)?;
+    if options.css.is_none() {
+        options.css = export_css::resolve(
+            source.as_deref(),
+            options.workspace.as_deref().map(Path::new),
+        )?;
+    }
+    Ok(())
+}
+
+// The settings every export follows: emoji, PNG optimization, unsafe
+// content, metadata, redaction, and the open workspace images may come from.
+fn apply_export_settings(
+    state: &AppStateType,
+    options: &mut export::ExportOptions,
+) -> Result<(), String> {
+    let app_state = state.lock().map_err(|_| i18n::t("error.app_state"))?;
+    options.expand_emoji |= app_state.expand_emoji;
+    options.optimize_png = options.optimize_png.or(app_state.optimize_png);
+    options.strip_unsafe |= app_state.strip_unsafe_content;
+    options.strip_metadata |= app_state.strip_source_metadata;
+    options.redaction = app_state.redaction.active();
+    options.workspace = app_state.workspace.clone();
+    Ok(())
+}
+
+// The theme the diagram is exported in, from its frontmatter or the open
+// workspace, with the user's Mermaid settings for rendering it. Settings
+// that do not parse are ignored here; validation reports them.
+fn export_render_theme(
+    state: &AppStateType,
+    content: &str,
+    source: Option<&str>,
+) -> Result<(file_settings::ExportTheme, mermaid_config::MermaidConfig), String> {
+    let (workspace, mermaid_config) = match state.lock() {
+        Ok(app_state) => (app_state.workspace.clone(), app_state.mermaid_config.clone()),
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+    let theme = file_settings::export_theme(
+        content,
+        source.map(Path::new),
+        workspace.as_deref().map(Path::new),
+    );
+    Ok((theme, mermaid_config))
+}
+
+// Where export CSS is stored: the diagram's sidecar when `path` is given,
+// otherwise the open workspace.
+fn export_css_path(
+    app_handle: &tauri::AppHandle,
+    state: &AppStateType,
+    path: Option<String>,
+) -> Result<PathBuf, String> {
+    if let Some(path) = path {
+        return Ok(export_css::file_path(&paths::check(app_handle, &path)?));
+    }
+    let workspace = state
+        .lock()
+        .map_err(|_| i18n::t("error.app_state"))?
+        .workspace
+        .clone()
+        .ok_or("No workspace is open")?;
+    Ok(export_css::workspace_path(Path::new(&workspace)))
+}
+
+#[command]
+pub async fn get_export_css(
+    path: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<Option<String>, String> {
+    export_css::get(&export_css_path(&app_handle, &state, path)?)
+}
+
+// Stores the CSS applied to exports of one diagram, or of the whole
+// workspace when `path` is None. None or blank CSS removes it.
+#[command]
+pub async fn set_export_css(
+    css: Option<String>,
+    path: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    let target = export_css_path(&app_handle, &state, path)?;
+    telemetry::record_feature("export_css");
+    export_css::set(&target, css.as_deref())
+}
+
+// The theme an export of the diagram will use and where it comes from, for
+// the export dialog to show.
+#[command]
+pub async fn get_export_theme(
+    content: String,
+    path: Option<String>,
+    state: State<'_, AppStateType>,
+) -> Result<file_settings::ExportTheme, String> {
+    Ok(export_render_theme(&state, &content, path.as_deref())?.0)
+}
+
+fn open_workspace(app_handle: &tauri::AppHandle, state: &AppStateType) -> Result<PathBuf, String> {
+    let workspace = state
+        .lock()
+        .map_err(|_| i18n::t("error.app_state"))?
+        .workspace
+        .clone()
+        .ok_or("No workspace is open")?;
+    paths::check(app_handle, &workspace)
+}
+
+#[command]
+pub async fn get_workspace_theme(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<Option<String>, String> {
+    Ok(themes::workspace_theme(&open_workspace(&app_handle, &state)?))
+}
+
+// The theme for diagrams in the open workspace that do not pick their own;
+// None leaves them to the editor's theme.
+#[command]
+pub async fn set_workspace_theme(
+    id: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    themes::set_workspace_theme(&open_workspace(&app_handle, &state)?, id.as_deref())
+}
+
+// The settings a diagram keeps in its frontmatter: its theme, export
+// defaults and the lint rules it turns off.
+#[command]
+pub async fn get_file_settings(content: String) -> Result<file_settings::FileSettings, String> {
+    file_settings::get(&content)
+}
+
+// Returns the content with the settings written into its frontmatter.
+#[command]
+pub async fn set_file_settings(
+    content: String,
+    settings: file_settings::FileSettings,
+) -> Result<String, String> {
+    telemetry::record_feature("file_settings");
+    file_settings::set(&content, &settings)
+}
+
+// The undo and redo steps saved with the file, or None when there are none
+// or the file was changed outside the editor since.
+#[command]
+pub async fn get_undo_history(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<Option<undo_history::UndoHistory>, String> {
+    undo_history::get(&paths::check(&app_handle, &path)?)
+}
+
+// Called after saving, so the history matches the file on disk.
+#[command]
+pub async fn set_undo_history(
+    path: String,
+    history: undo_history::UndoHistory,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let path = paths::check(&app_handle, &path)?;
+    jobs::run(move || undo_history::set(&path, history)).await?
+}
+
+// The diagram whose layout is read or changed. Encrypted diagrams keep none,
+// since the sidecar would list their node ids in plain text.
+fn layout_target(app_handle: &tauri::AppHandle, path: &str) -> Result<PathBuf, String> {
+    let path = paths::check(app_handle, path)?;
+    if encryption::is_encrypted_path(&path) {
+        return Err("Encrypted diagrams cannot keep a layout".to_string());
+    }
+    Ok(path)
+}
+
+// Node positions the user dragged into place, to apply after rendering.
+#[command]
+pub async fn get_layout(path: String, app_handle: tauri::AppHandle) -> Result<layout::Layout, String> {
+    layout::get(&layout_target(&app_handle, &path)?)
+}
+
+#[command]
+pub async fn set_layout(
+    path: String,
+    layout: layout::Layout,
+    app_handle: tauri::AppHandle,
+) -> Result<layout::Layout, String> {
+    layout::set(&layout_target(&app_handle, &path)?, layout)
+}
+
+// Called after a drag with the nodes that moved; None resets a node.
+#[command]
+pub async fn update_layout(
+    path: String,
+    positions: BTreeMap<String, Option<layout::Position>>,
+    app_handle: tauri::AppHandle,
+) -> Result<layout::Layout, String> {
+    telemetry::record_feature("layout");
+    layout::merge(&layout_target(&app_handle, &path)?, positions)
+}
+
+#[command]
+pub async fn rename_layout_node(
+    path: String,
+    from: String,
+    to: String,
+    app_handle: tauri::AppHandle,
+) -> Result<layout::Layout, String> {
+    layout::rename_node(&layout_target(&app_handle, &path)?, &from, &to)
+}
+
+#[command]
+pub async fn export_diagram(
+    content: String,
+    format: String,
+    options: Option<export::ExportOptions>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<String, String> {
+    let mut options = options.unwrap_or_default();
+    prepare_export_options(&app_handle, &state, &mut options)?;
+    let (theme, mermaid_config) = export_render_theme(&state, &content, options.source.as_deref())?;
+    let extension = match format.as_str() {
+        "png" => "png",
+        "svg" => "svg",
+        "pdf" => "pdf",
+        "emf" => "emf",
+        "eps" => "eps",
+        "tiff" => "tiff",
+        _ => return Err(i18n::t("error.unsupported_format")),
+    };
+
+    let dialog_result = app_handle
+        .dialog()
+        .file()
+        .add_filter(&i18n::t_with("dialog.filter.format", &[("format", &format.to_uppercase())]), &[extension])
+        .blocking_save_file();
+
+    let result = match dialog_result {
+        Some(file_path) => {
+            let path_buf = file_path.into_path().map_err(|e| format!("Failed to convert path: {}", e))?;
+            paths::grant_file(&app_handle, &path_buf);
+            let format = extension.to_string();
+
+            jobs::run(move || {
+                file_settings::with_theme(&theme, &mermaid_config, || {
+                    export::export(&content, &path_buf, &format, &options)
+                })
+            })
+                .await
+                .and_then(|result| result)
+                .map(|result| {
+                    for warning in &result.warnings {
+                        tracing::warn!(path = %result.path, "{}", warning);
+                    }
+                    tracing::info!(path = %result.path, scale = result.scale, pages = result.pages, "exported diagram");
+                    // The command returns the path; the report goes out
+                    // with it for the frontend to show.
+                    if let Some(report) = &result.redaction {
+                        let _ = app_handle.emit("export-redacted", serde_json::json!({
+                            "path": result.path,
+                            "report": report,
+                        }));
+                    }
+                    result.path
+                })
+        }
+        None => Err(i18n::t("error.export_cancelled")),
+    };
+
+    telemetry::track("export_diagram", telemetry::ErrorKind::Render, result)
+}
+
+// Renders every diagram in the workspace into `output_dir`, named by
+// `pattern` (`{relpath}/{name}.{ext}` by default), skipping diagrams whose
+// output is newer than they are.
+#[command]
+pub async fn export_workspace(
+    format: String,
+    output_dir: String,
+    pattern: Option<String>,
+    options: Option<export::ExportOptions>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<workspace_export::WorkspaceExport, String> {
+    let (workspace, mermaid_config) = match state.lock() {
+        Ok(app_state) => (app_state.workspace.clone(), app_state.mermaid_config.clone()),
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+    let workspace = paths::check(&app_handle, &workspace.ok_or("No workspace is open")?)?;
+    let output_dir = paths::check(&app_handle, &output_dir)?;
+    let pattern = pattern.unwrap_or_else(|| workspace_export::DEFAULT_PATTERN.to_string());
+    workspace_export::validate_pattern(&pattern)?;
+    let mut options = options.unwrap_or_default();
+    apply_export_settings(&state, &mut options)?;
+
+    let result = jobs::run(move || {
+        workspace_export::export_all(&workspace, &output_dir, &pattern, &format, &options, Some(&mermaid_config))
+    })
+    .await
+    .and_then(|result| result)
+    .map(|result| {
+        tracing::info!(
+            exported = result.exported.len(),
+            skipped = result.skipped.len(),
+            failed = result.failed.len(),
+            "exported workspace"
+        );
+        result
+    });
+    telemetry::track("export_workspace", telemetry::ErrorKind::Render, result)
+}
+
+// Exports a light and a dark variant in one go, for READMEs that pick one
+// through `<picture>`. The chosen file name gets `-light` and `-dark`
+// suffixes; the themes default to the shipped light and dark ones.
+#[command]
+pub async fn export_light_dark(
+    content: String,
+    format: String,
+    options: Option<export::ExportOptions>,
+    light_theme: Option<String>,
+    dark_theme: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<export::LightDarkExport, String> {
+    let mut options = options.unwrap_or_default();
+    prepare_export_options(&app_handle, &state, &mut options)?;
+    let extension = match format.as_str() {
+        "png" => "png",
+        "svg" => "svg",
+        "pdf" => "pdf",
+        _ => return Err(i18n::t("error.unsupported_format")),
+    };
+
+    let mermaid_config = match state.lock() {
+        Ok(app_state) => app_state.mermaid_config.clone(),
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+    let render_config = |id: &str| -> Result<render::RenderConfig, String> {
+        let mut config = themes::get(id)?.render_config();
+        mermaid_config.apply(&mut config.mermaid);
+        Ok(config)
+    };
+    let light = render_config(light_theme.as_deref().unwrap_or(themes::DEFAULT_THEME))?;
+    let dark = render_config(dark_theme.as_deref().unwrap_or(themes::DEFAULT_DARK_THEME))?;
+
+    let Some(file_path) = app_handle
+        .dialog()
+        .file()
+        .add_filter(&i18n::t_with("dialog.filter.format", &[("format", &format.to_uppercase())]), &[extension])
+        .blocking_save_file()
+    else {
+        return Err(i18n::t("error.export_cancelled"));
+    };
+    let path_buf = file_path.into_path().map_err(|e| format!("Failed to convert path: {}", e))?;
+    for suffix in ["light", "dark"] {
+        paths::grant_file(&app_handle, &export::variant_path(&path_buf, suffix));
+    }
+    let format = extension.to_string();
+
+    let result = jobs::run(move || export::export_light_dark(&content, &path_buf, &format, &options, light, dark))
+        .await
+        .and_then(|result| result)
+        .map(|result| {
+            for warning in result.light.warnings.iter().chain(&result.dark.warnings) {
+                tracing::warn!(path = %result.light.path, "{}", warning);
+            }
+            tracing::info!(light = %result.light.path, dark = %result.dark.path, "exported light and dark diagrams");
+            result
+        });
+    telemetry::track("export_light_dark", telemetry::ErrorKind::Render, result)
+}
+
+// Saves the tasks of a gantt diagram as an iCalendar file, and returns its
+// path.
+#[command]
+pub async fn export_gantt_calendar(
+    content: String,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<String, String> {
+    let content = outgoing_content(content, "export_gantt_calendar", &app_handle, &state);
+    let gantt = ical::parse(&content)?;
+    let file_name = format!(
+        "{}.ics",
+        gantt
+            .title
+            .as_deref()
+            .map(|title| title.replace(['/', '\\', ':'], "-"))
+            .unwrap_or_else(|| "gantt".to_string())
+    );
+    let dialog_result = app_handle
+        .dialog()
+        .file()
+        .set_file_name(&file_name)
+        .add_filter(&i18n::t("dialog.filter.calendar"), &["ics"])
+        .blocking_save_file();
+
+    let path_buf = match dialog_result {
+        Some(file_path) => file_path
+            .into_path()
+            .map_err(|e| format!("Failed to convert path: {}", e))?,
+        None => return Err(i18n::t("error.export_cancelled")),
+    };
+    paths::grant_file(&app_handle, &path_buf);
+    fs::write(&path_buf, ical::to_ics(&gantt))
+        .map_err(|e| format!("Failed to write calendar: {}", e))?;
+    telemetry::record_feature("export_gantt_calendar");
+    Ok(path_buf.to_string_lossy().to_string())
+}
+
+// Saves a mindmap as an OPML outline for mind-mapping tools, and returns
+// its path.
+#[command]
+pub async fn export_mindmap_opml(
+    content: String,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<String, String> {
+    let content = outgoing_content(content, "export_mindmap_opml", &app_handle, &state);
+    let opml = importers::opml::to_opml(&content)?;
+    let dialog_result = app_handle
+        .dialog()
+        .file()
+        .set_file_name("mindmap.opml")
+        .add_filter(&i18n::t("dialog.filter.opml"), &["opml"])
+        .blocking_save_file();
+
+    let path_buf = match dialog_result {
+        Some(file_path) => file_path
+            .into_path()
+            .map_err(|e| format!("Failed to convert path: {}", e))?,
+        None => return Err(i18n::t("error.export_cancelled")),
+    };
+    paths::grant_file(&app_handle, &path_buf);
+    fs::write(&path_buf, opml).map_err(|e| format!("Failed to write outline: {}", e))?;
+    telemetry::record_feature("export_mindmap_opml");
+    Ok(path_buf.to_string_lossy().to_string())
+}
+
+#[command]
+pub async fn get_signing_key() -> Result<Option<signing::SigningKeyInfo>, String> {
+    signing::key_info()
+}
+
+// Imports a base64 Ed25519 seed as the signing key, or generates a new key
+// when none is given.
+#[command]
+pub async fn set_signing_key(private_key: Option<String>) -> Result<signing::SigningKeyInfo, String> {
+    telemetry::track(
+        "set_signing_key",
+        telemetry::ErrorKind::Config,
+        signing::set_key(private_key.as_deref()),
+    )
+}
+
+#[command]
+pub async fn get_trusted_signing_keys(state: State<'_, AppStateType>) -> Result<Vec<String>, String> {
+    match state.lock() {
+        Ok(app_state) => Ok(app_state.trusted_signing_keys.clone()),
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// Replaces the public keys, besides the user's own, whose signatures
+// verify.
+#[command]
+pub async fn set_trusted_signing_keys(
+    keys: Vec<String>,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    signing::validate_public_keys(&keys)?;
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.trusted_signing_keys = keys;
+            save_app_state(&app_state)
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// Verifies a signed export. A signature from a key that is neither the
+// user's own nor trusted is reported as not valid.
+#[command]
+pub async fn verify_signature(
+    path: String,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<signing::Verification, String> {
+    let file_path = paths::check(&app_handle, &path)?;
+    let trusted = match state.lock() {
+        Ok(app_state) => app_state.trusted_signing_keys.clone(),
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+    jobs::run(move || signing::verify(&file_path, &trusted)).await?
+}
+
+// Renders SVG or PNG in memory and returns a data URI, so the editor can
+// show previews and thumbnails that match exports without a save dialog.
+#[command]
+pub async fn render_preview(
+    content: String,
+    options: Option<export::PreviewOptions>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<export::Preview, String> {
+    let mut options = options.unwrap_or_default();
+    prepare_export_options(&app_handle, &state, &mut options.export)?;
+    let (theme, mermaid_config) =
+        export_render_theme(&state, &content, options.export.source.as_deref())?;
+    jobs::run(move || {
+        file_settings::with_theme(&theme, &mermaid_config, || export::preview(&content, &options))
+    })
+    .await?
+}
+
+#[command]
+pub async fn start_watch_export(
+    config: watch::WatchConfig,
+    app_handle: tauri::AppHandle,
+    watch_state: State<'_, WatchStateType>,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    paths::check(&app_handle, &config.source_dir)?;
+    paths::check(&app_handle, &config.output_dir)?;
+
+    let redaction = match state.lock() {
+        Ok(app_state) => app_state.redaction.active(),
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+    let mut active = watch_state
+        .lock()
+        .map_err(|_| "Failed to access watch state".to_string())?;
+
+    if let Some(handle) = active.take() {
+        handle.stop();
+    }
+
+    let handle = watch::spawn(config.clone(), redaction, move |event| {
+        let _ = app_handle.emit("watch-export", event);
+    })?;
+    *active = Some(handle);
+
+    if let Ok(mut app_state) = state.lock() {
+        app_state.watch_config = Some(config);
+        persist_app_state(&app_state);
+    }
+
+    Ok(())
+}
+
+#[command]
+pub async fn stop_watch_export(
+    watch_state: State<'_, WatchStateType>,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    match watch_state.lock() {
+        Ok(mut active) => {
+            if let Some(handle) = active.take() {
+                handle.stop();
+            }
+        }
+        Err(_) => return Err("Failed to access watch state".to_string()),
+    }
+
+    if let Ok(mut app_state) = state.lock() {
+        app_state.watch_config = None;
+        persist_app_state(&app_state);
+    }
+
+    Ok(())
+}
+
+#[command]
+pub async fn get_watch_export_status(
+    watch_state: State<'_, WatchStateType>,
+) -> Result<Option<watch::WatchConfig>, String> {
+    match watch_state.lock() {
+        Ok(active) => Ok(active.as_ref().map(|handle| handle.config.clone())),
+        Err(_) => Err("Failed to access watch state".to_string()),
+    }
+}
+
+#[command]
+pub async fn start_preview_server(
+    config: preview_server::PreviewServerConfig,
+    app_handle: tauri::AppHandle,
+    server_state: State<'_, PreviewServerStateType>,
+    state: State<'_, AppStateType>,
+) -> Result<String, String> {
+    // The server hands out everything under its root.
+    paths::check(&app_handle, &config.root_dir)?;
+
+    let previous = server_state
+        .lock()
+        .map_err(|_| "Failed to access preview server state".to_string())?
+        .take();
+
+    // Release the port before binding it again, without holding the lock.
+    if let Some(handle) = previous {
+        handle.shutdown();
+    }
+
+    let handle = preview_server::spawn(config.clone())?;
+    let url = handle.url();
+    let replaced = server_state
+        .lock()
+        .map_err(|_| "Failed to access preview server state".to_string())?
+        .replace(handle);
+    if let Some(replaced) = replaced {
+        replaced.stop();
+    }
+
+    if let Ok(mut app_state) = state.lock() {
+        app_state.preview_server = Some(config);
+        persist_app_state(&app_state);
+    }
+
+    Ok(url)
+}
+
+#[command]
+pub async fn stop_preview_server(
+    server_state: State<'_, PreviewServerStateType>,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    match server_state.lock() {
+        Ok(mut active) => {
+            if let Some(handle) = active.take() {
+                handle.stop();
+            }
+        }
+        Err(_) => return Err("Failed to access preview server state".to_string()),
+    }
+
+    if let Ok(mut app_state) = state.lock() {
+        app_state.preview_server = None;
+        persist_app_state(&app_state);
+    }
+
+    Ok(())
+}
+
+#[command]
+pub async fn get_preview_server_url(
+    server_state: State<'_, PreviewServerStateType>,
+) -> Result<Option<String>, String> {
+    match server_state.lock() {
+        Ok(active) => Ok(active.as_ref().map(|handle| handle.url())),
+        Err(_) => Err("Failed to access preview server state".to_string()),
+    }
+}
+
+#[command]
+pub async fn take_pending_opens(
+    pending: State<'_, PendingOpensStateType>,
+) -> Result<Vec<OpenFileEvent>, String> {
+    match pending.lock() {
+        Ok(mut pending) => {
+            pending.frontend_ready = true;
+            Ok(std::mem::take(&mut pending.queue))
+        }
+        Err(_) => Err("Failed to access pending files".to_string()),
+    }
+}
+
+fn emit_open_file(app_handle: &tauri::AppHandle, event: OpenFileEvent) {
+    if let Ok(mut pending) = app_handle.state::<PendingOpensStateType>().lock() {
+        if !pending.frontend_ready {
+            pending.queue.push(event);
+            return;
+        }
+    }
+
+    let _ = app_handle.emit("open-file", event);
+    focus_main_window(app_handle);
+}
+
+fn open_in_running_app(app_handle: &tauri::AppHandle, path: &Path, line: Option<u32>) {
+    match read_diagram_file(app_handle, path, false) {
+        Ok(file) => emit_open_file(app_handle, OpenFileEvent { file, line }),
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "failed to open file");
+            let _ = app_handle.emit("open-file-error", e);
+        }
+    }
+}
+
+fn handle_file_drop(app_handle: &tauri::AppHandle, paths: &[PathBuf]) {
+    let mut event = FileDropEvent {
+        files: Vec::new(),
+        rejected: Vec::new(),
+    };
+
+    for path in paths {
+        let reason = if !path.is_file() {
+            Some(i18n::t("error.not_a_file"))
+        } else if !is_openable_file(path) {
+            Some(i18n::t("error.unsupported_file_type"))
+        } else {
+            match read_diagram_file(app_handle, path, false) {
+                Ok(file) => {
+                    event.files.push(file);
+                    None
+                }
+                Err(e) => Some(e),
+            }
+        };
+
+        if let Some(reason) = reason {
+            event.rejected.push(RejectedDrop {
+                path: path.to_string_lossy().to_string(),
+                reason,
+            });
+        }
+    }
+
+    let _ = app_handle.emit("file-drop", event);
+}
+
+fn refresh_tray(app_handle: &tauri::AppHandle) {
+    #[cfg(desktop)]
+    tray::refresh(app_handle);
+    #[cfg(not(desktop))]
+    let _ = app_handle;
+}
+
+fn register_global_shortcuts(app_handle: &tauri::AppHandle) {
+    #[cfg(desktop)]
+    if let Err(e) = shortcuts::register(app_handle) {
+        tracing::warn!(error = %e, "failed to register global shortcuts");
+    }
+    #[cfg(not(desktop))]
+    let _ = app_handle;
+}
+
+fn focus_main_window(app_handle: &tauri::AppHandle) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+// A small always-on-top window for jotting down a diagram without leaving
+// the current app. The frontend shows the capture view for `#capture`.
+fn open_capture_window(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    if let Some(window) = app_handle.get_webview_window("capture") {
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+
+    tauri::WebviewWindowBuilder::new(app_handle, "capture", tauri::WebviewUrl::App("index.html#capture".into()))
+        .title(i18n::t("window.capture_title"))
+        .inner_size(520.0, 360.0)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .center()
+        .focused(true)
+        .build()
+        .map_err(|e| format!("Failed to open capture window: {}", e))?;
+    Ok(())
+}
+
+// Shared diagrams open as new, unsaved documents.
+fn open_share_link(app_handle: &tauri::AppHandle, url: &tauri::Url) {
+    match share::decode_link(url.as_str()) {
+        Ok(content) => {
+            let file = FileContent {
+                content,
+                path: None,
+                encrypted: false,
+                read_only: false,
+                conflicted: false,
+            };
+            emit_open_file(app_handle, OpenFileEvent { file, line: None });
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "rejected share link");
+            let _ = app_handle.emit("open-file-error", e);
+        }
+    }
+}
+
+fn handle_deep_link(app_handle: &tauri::AppHandle, url: &tauri::Url) {
+    if share::is_app_link(url.as_str()) {
+        open_share_link(app_handle, url);
+        return;
+    }
+    // Any web page can trigger a deep link, so only diagrams outside the
+    // protected locations are opened.
+    let request = deep_link::parse_open_url(url).and_then(|request| {
+        let path = paths::resolve(&request.path)?;
+        if !is_diagram_file(&path) || paths::is_protected(&path) {
+            return Err(i18n::t("error.unsupported_file_type"));
+        }
+        Ok((path, request.line))
+    });
+    match request {
+        Ok((path, line)) => open_in_running_app(app_handle, &path, line),
+        Err(e) => {
+            tracing::warn!(url = %url, error = %e, "rejected deep link");
+            let _ = app_handle.emit("open-file-error", e);
+        }
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn handle_opened_url(app_handle: &tauri::AppHandle, url: &tauri::Url) {
+    if url.scheme() == deep_link::SCHEME {
+        handle_deep_link(app_handle, url);
+    } else if let Ok(path) = url.to_file_path() {
+        open_in_running_app(app_handle, &path, None);
+    }
+}
+
+fn resume_preview_server(app_handle: &tauri::AppHandle) {
+    let config = app_handle
+        .state::<AppStateType>()
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.preview_server.clone());
+
+    if let Some(config) = config {
+        match preview_server::spawn(config) {
+            Ok(handle) => {
+                if let Ok(mut active) = app_handle.state::<PreviewServerStateType>().lock() {
+                    *active = Some(handle);
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "failed to resume preview server"),
+        }
+    }
+}
+
+// Re-grants the files and folders the user chose in earlier sessions, since
+// the fs scope only lives as long as the app.
+fn restore_path_scope(app_handle: &tauri::AppHandle) {
+    paths::forbid_protected(app_handle);
+
+    let (files, directories): (Vec<String>, Vec<String>) = match app_handle.state::<AppStateType>().lock() {
+        Ok(app_state) => (
+            // Starred files are granted too, since they stay listed after
+            // rolling off the recent files. Entries from a settings bundle
+            // are not; the bundle could list any file.
+            app_state
+                .recent_files
+                .iter()
+                .filter(|file| !file.imported)
+                .map(|file| file.path.clone())
+                .chain(
+                    app_state
+                        .starred_files
+                        .iter()
+                        .filter(|file| !file.imported)
+                        .map(|file| file.path.clone()),
+                )
+                .collect(),
+            app_state
+                .workspace
+                .iter()
+                .chain(app_state.watch_config.iter().flat_map(|config| [&config.source_dir, &config.output_dir]))
+                .chain(app_state.preview_server.iter().map(|config| &config.root_dir))
+                .cloned()
+                .collect(),
+        ),
+        Err(_) => return,
+    };
+
+    for file in &files {
+        paths::grant_file(app_handle, Path::new(file));
+    }
+    for directory in &directories {
+        paths::grant_directory(app_handle, Path::new(directory));
+    }
+}
+
+fn resume_watch_export(app_handle: &tauri::AppHandle) {
+    let config = app_handle
+        .state::<AppStateType>()
+        .lock()
+        .ok()
+        .and_then(|app_state| {
+            let redaction = app_state.redaction.active();
+            app_state.watch_config.clone().map(|config| (config, redaction))
+        });
+
+    if let Some((config, redaction)) = config {
+        let emitter = app_handle.clone();
+        let spawned = watch::spawn(config, redaction, move |event| {
+            let _ = emitter.emit("watch-export", event);
+        });
+
+        match spawned {
+            Ok(handle) => {
+                if let Ok(mut active) = app_handle.state::<WatchStateType>().lock() {
+                    *active = Some(handle);
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "failed to resume watch export"),
+        }
+    }
+}
+
+// Opens `path` as the workspace, whose diagrams get thumbnails generated in
+// the background; None closes it.
+#[command]
+pub async fn set_workspace(
+    path: Option<String>,
+    app_handle: tauri::AppHandle,
+    thumbnail_state: State<'_, ThumbnailStateType>,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    if let Some(path) = &path {
+        paths::check(&app_handle, path)?;
+    }
+
+    let mut active = thumbnail_state
+        .lock()
+        .map_err(|_| "Failed to access thumbnail state".to_string())?;
+
+    if let Some(handle) = active.take() {
+        handle.stop();
+    }
+
+    if let Some(path) = &path {
+        let handle = thumbnails::spawn(path.clone(), move |event| {
+            let _ = app_handle.emit("thumbnail", event);
+        })?;
+        *active = Some(handle);
+    }
+
+    if let Ok(mut app_state) = state.lock() {
+        app_state.workspace = path;
+        persist_app_state(&app_state);
+    }
+
+    Ok(())
+}
+
+#[command]
+pub async fn get_workspace(state: State<'_, AppStateType>) -> Result<Option<String>, String> {
+    match state.lock() {
+        Ok(app_state) => Ok(app_state.workspace.clone()),
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// The git repository a hook command acts on is the one containing `path`,
+// which may be a folder or a file in it.
+fn repository_dir(app_handle: &tauri::AppHandle, path: &str) -> Result<PathBuf, String> {
+    let path = paths::check(app_handle, path)?;
+    if path.is_dir() {
+        return Ok(path);
+    }
+    path.parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| format!("Invalid path: {}", path.display()))
+}
+
+// Draws the branch and merge history of the repository containing
+// `repo_path`.
+#[command]
+pub async fn generate_gitgraph(
+    repo_path: String,
+    options: Option<gitgraph::GitGraphOptions>,
+    app_handle: tauri::AppHandle,
+) -> Result<code_diagrams::Generated, String> {
+    let dir = repository_dir(&app_handle, &repo_path)?;
+    let options = options.unwrap_or_default();
+    telemetry::track(
+        "generate_gitgraph",
+        telemetry::ErrorKind::Io,
+        jobs::run(move || gitgraph::generate(&dir, &options)).await?,
+    )
+}
+
+// Installs a pre-commit hook that runs `validate --staged` with this
+// executable, and returns the hook's path.
+#[command]
+pub async fn install_git_hook(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let dir = repository_dir(&app_handle, &path)?;
+    // Inside an AppImage the executable lives in a temporary mount.
+    let exe = match std::env::var_os("APPIMAGE") {
+        Some(appimage) => PathBuf::from(appimage),
+        None => std::env::current_exe()
+            .map_err(|e| format!("Failed to locate the executable: {}", e))?,
+    };
+    let hook = git_hook::install(&dir, &exe)?;
+    telemetry::record_feature("git_hook");
+    Ok(hook.to_string_lossy().to_string())
+}
+
+// Returns whether there was a hook of ours to remove.
+#[command]
+pub async fn remove_git_hook(path: String, app_handle: tauri::AppHandle) -> Result<bool, String> {
+    let dir = repository_dir(&app_handle, &path)?;
+    git_hook::uninstall(&dir)
+}
+
+// PNG data URI for a diagram file, rendered on demand when the background
+// generator has not got to it yet.
+#[command]
+pub async fn get_thumbnail(path: String, app_handle: tauri::AppHandle) -> Result<String, String> {
+    let path = paths::check(&app_handle, &path)?;
+    jobs::run(move || thumbnails::get(&path)).await?
+}
+
+#[command]
+pub async fn reveal_in_file_manager(path: String, app_handle: tauri::AppHandle) -> Result<(), String> {
+    let path = paths::check(&app_handle, &path)?;
+    external::reveal(&path)
+}
+
+// Opens the file in the configured external editor and watches it, so the
+// frontend gets a "document-changed" event when the edits are saved.
+#[command]
+pub async fn open_in_external_editor(
+    path: String,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    let path = paths::check(&app_handle, &path)?;
+    let editor = match state.lock() {
+        Ok(app_state) => app_state.external_editor.clone(),
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+
+    external::watch_document(&path);
+    telemetry::track(
+        "open_in_external_editor",
+        telemetry::ErrorKind::Io,
+        external::open_in_editor(&path, editor.as_deref().map(Path::new)),
+    )
+}
+
+// Stops "document-changed" events for a file, e.g. when its tab closes.
+#[command]
+pub async fn unwatch_document(path: String) -> Result<(), String> {
+    external::unwatch_document(&paths::resolve(&path)?);
+    Ok(())
+}
+
+#[command]
+pub async fn get_external_editor(state: State<'_, AppStateType>) -> Result<Option<String>, String> {
+    match state.lock() {
+        Ok(app_state) => Ok(app_state.external_editor.clone()),
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// The editor is always picked in a file dialog, never passed in, so the
+// frontend cannot make the app launch arbitrary programs.
+#[command]
+pub async fn choose_external_editor(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<String, String> {
+    let dialog_result = app_handle.dialog().file().blocking_pick_file();
+    let editor = match dialog_result {
+        Some(file_path) => file_path.into_path().map_err(|e| format!("Failed to convert path: {}", e))?,
+        None => return Err(i18n::t("error.open_cancelled")),
+    };
+    let editor = editor.to_string_lossy().to_string();
+
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.external_editor = Some(editor.clone());
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))?;
+            Ok(editor)
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// Goes back to the system's default text editor.
+#[command]
+pub async fn clear_external_editor(state: State<'_, AppStateType>) -> Result<(), String> {
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.external_editor = None;
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn list_storage_backends(
+    state: State<'_, AppStateType>,
+) -> Result<Vec<storage::BackendConfig>, String> {
+    match state.lock() {
+        Ok(app_state) => Ok(app_state.storage_backends.clone()),
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// Adds a backend or replaces the one with the same id. The password or
+// secret key goes to the keychain: `None` keeps the stored one and an empty
+// string removes it.
+#[command]
+pub async fn set_storage_backend(
+    config: storage::BackendConfig,
+    secret: Option<String>,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    storage::validate(&config)?;
+    storage::set_secret(config.id(), secret.as_deref())?;
+    match state.lock() {
+        Ok(mut app_state) => {
+            let backends = &mut app_state.storage_backends;
+            match backends.iter_mut().find(|backend| backend.id() == config.id()) {
+                Some(existing) => *existing = config,
+                None => backends.push(config),
+            }
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn remove_storage_backend(id: String, state: State<'_, AppStateType>) -> Result<(), String> {
+    storage::delete_secret(&id)?;
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.storage_backends.retain(|backend| backend.id() != id);
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// Lists the files in a remote folder; their `path`s can be passed to
+// `load_file` and `save_file_content_to_disk` like local paths.
+#[command]
+pub async fn list_remote_files(
+    backend_id: String,
+    prefix: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<storage::RemoteFile>, String> {
+    let backend = storage_backend(&app_handle, &backend_id)?;
+    telemetry::track(
+        "list_remote_files",
+        telemetry::ErrorKind::Network,
+        storage::list(&backend, prefix.as_deref().unwrap_or_default()).await,
+    )
+}
+
+fn start_document_watch(app_handle: &tauri::AppHandle) {
+    let emitter = app_handle.clone();
+    let spawned = external::spawn(move |event| {
+        let _ = emitter.emit("document-changed", event);
+    });
+
+    match spawned {
+        Ok(handle) => {
+            if let Ok(mut active) = app_handle.state::<DocumentWatchStateType>().lock() {
+                *active = Some(handle);
+            }
+        }
+        Err(e) => tracing::warn!(error = %e, "failed to start document watcher"),
+    }
+}
+
+fn start_linked_source_watch(app_handle: &tauri::AppHandle) {
+    let emitter = app_handle.clone();
+    let spawned = linked::spawn(move |event| {
+        let _ = emitter.emit("linked-source", event);
+    });
+
+    match spawned {
+        Ok(handle) => {
+            if let Ok(mut active) = app_handle.state::<LinkedWatchStateType>().lock() {
+                *active = Some(handle);
+            }
+        }
+        Err(e) => tracing::warn!(error = %e, "failed to start linked source watcher"),
+    }
+}
+
+fn resume_workspace(app_handle: &tauri::AppHandle) {
+    let workspace = app_handle
+        .state::<AppStateType>()
+        .lock()
+        .ok()
+        .and_then(|app_state| app_state.workspace.clone());
+
+    if let Some(workspace) = workspace {
+        let emitter = app_handle.clone();
+        let spawned = thumbnails::spawn(workspace, move |event| {
+            let _ = emitter.emit("thumbnail", event);
+        });
+
+        match spawned {
+            Ok(handle) => {
+                if let Ok(mut active) = app_handle.state::<ThumbnailStateType>().lock() {
+                    *active = Some(handle);
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "failed to resume thumbnail generator"),
+        }
+    }
+}
+
+#[command]
+pub async fn copy_as_markdown(
+    content: String,
+    title: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<String, String> {
+    let content = shared_source(content, "copy_as_markdown", &app_handle, &state);
+    let source = match title.as_deref().map(str::trim) {
+        Some(title) if !title.is_empty() => markdown::with_title(&content, title),
+        _ => content,
+    };
+    let block = markdown::to_code_block(&source);
+    telemetry::record_feature("copy_as_markdown");
+
+    app_handle
+        .clipboard()
+        .write_text(block.clone())
+        .map_err(|e| format!("Failed to copy to clipboard: {}", e))?;
+
+    Ok(block)
+}
+
+#[command]
+pub async fn import_from_clipboard(
+    app_handle: tauri::AppHandle,
+) -> Result<importers::ImportResult, String> {
+    let text = app_handle
+        .clipboard()
+        .read_text()
+        .map_err(|e| format!("Failed to read clipboard: {}", e))?;
+
+    telemetry::track(
+        "import_from_clipboard",
+        telemetry::ErrorKind::Parse,
+        importers::import_text(&text),
+    )
+}
+
+// Converts a SQL schema, OpenAPI document, task list export or other
+// supported file into a new diagram.
+#[command]
+pub async fn import_from_file(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<importers::ImportResult, String> {
+    let path = paths::check(&app_handle, &path)?;
+    let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let kind = importers::source_kind(&path, &text)
+        .ok_or_else(|| format!("Cannot generate a diagram from {}", path.display()))?;
+    telemetry::track(
+        "import_from_file",
+        telemetry::ErrorKind::Parse,
+        importers::convert(kind, &text),
+    )
+}
+
+// HAR files and JSON request logs, limited to some hosts or a time range.
+#[command]
+pub async fn import_http_log(
+    path: String,
+    options: Option<importers::http_log::HttpLogOptions>,
+    app_handle: tauri::AppHandle,
+) -> Result<importers::ImportResult, String> {
+    let path = paths::check(&app_handle, &path)?;
+    let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let options = options.unwrap_or_default();
+    telemetry::track(
+        "import_http_log",
+        telemetry::ErrorKind::Parse,
+        jobs::run(move || importers::import_http_log(&text, &options)).await?,
+    )
+}
+
+// CSV or JSON event lists, optionally split into sections by year or
+// category.
+#[command]
+pub async fn import_timeline(
+    path: String,
+    options: Option<importers::timeline::TimelineOptions>,
+    app_handle: tauri::AppHandle,
+) -> Result<importers::ImportResult, String> {
+    let path = paths::check(&app_handle, &path)?;
+    let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let options = options.unwrap_or_default();
+    telemetry::track(
+        "import_timeline",
+        telemetry::ErrorKind::Parse,
+        jobs::run(move || importers::import_timeline(&text, &options)).await?,
+    )
+}
+
+// Draws the heading outline of a Markdown document, such as a long design
+// doc, as a mindmap.
+#[command]
+pub async fn generate_mindmap_from_markdown(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<importers::ImportResult, String> {
+    let path = paths::check(&app_handle, &path)?;
+    let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+    telemetry::track(
+        "generate_mindmap_from_markdown",
+        telemetry::ErrorKind::Parse,
+        importers::import_markdown_headings(&text),
+    )
+}
+
+// `path` is a single Rust file or a folder such as a crate root.
+#[command]
+pub async fn generate_from_rust(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<code_diagrams::Generated, String> {
+    let path = paths::check(&app_handle, &path)?;
+    telemetry::track(
+        "generate_from_rust",
+        telemetry::ErrorKind::Parse,
+        jobs::run(move || code_diagrams::rust::generate(&path)).await?,
+    )
+}
+
+// `path` is a TypeScript or JavaScript file, or a folder of them.
+#[command]
+pub async fn generate_from_typescript(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<code_diagrams::Generated, String> {
+    let path = paths::check(&app_handle, &path)?;
+    telemetry::track(
+        "generate_from_typescript",
+        telemetry::ErrorKind::Parse,
+        jobs::run(move || code_diagrams::typescript::generate(&path)).await?,
+    )
+}
+
+#[command]
+pub async fn has_github_token() -> Result<bool, String> {
+    Ok(secrets::get_secret(gist::TOKEN_SECRET)?.is_some())
+}
+
+// An empty token removes the stored one.
+#[command]
+pub async fn set_github_token(token: String) -> Result<(), String> {
+    match token.trim() {
+        "" => secrets::delete_secret(gist::TOKEN_SECRET),
+        token => secrets::set_secret(gist::TOKEN_SECRET, token),
+    }
+}
+
+#[command]
+pub async fn share_as_gist(
+    content: String,
+    visibility: gist::Visibility,
+    file_name: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<String, String> {
+    let content = shared_source(content, "share_as_gist", &app_handle, &state);
+    telemetry::track(
+        "share_as_gist",
+        telemetry::ErrorKind::Network,
+        gist::share(&content, visibility, file_name.as_deref()).await,
+    )
+}
+
+#[command]
+pub async fn import_from_gist(url: String) -> Result<importers::ImportResult, String> {
+    telemetry::track("import_from_gist", telemetry::ErrorKind::Network, gist::import(&url).await)
+}
+
+// Uses the export config, so the editor shows the diagram with the same
+// theme.
+#[command]
+pub async fn create_mermaid_live_link(
+    content: String,
+    view: Option<bool>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<String, String> {
+    let content = shared_source(content, "create_mermaid_live_link", &app_handle, &state);
+    let config = render::current_config().map(|config| config.mermaid);
+    telemetry::track(
+        "create_mermaid_live_link",
+        telemetry::ErrorKind::Parse,
+        mermaid_live::encode_url(&content, config.as_ref(), view.unwrap_or(false)),
+    )
+}
+
+#[command]
+pub async fn decode_mermaid_live_link(url: String) -> Result<String, String> {
+    telemetry::track(
+        "decode_mermaid_live_link",
+        telemetry::ErrorKind::Parse,
+        mermaid_live::decode_url(&url),
+    )
+}
+
+// Without `viewer_url` the link opens the diagram in the app through the
+// flowcraft:// scheme.
+#[command]
+pub async fn create_share_link(
+    content: String,
+    viewer_url: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<String, String> {
+    let content = shared_source(content, "create_share_link", &app_handle, &state);
+    telemetry::track(
+        "create_share_link",
+        telemetry::ErrorKind::Parse,
+        share::create_link(&content, viewer_url.as_deref()),
+    )
+}
+
+#[command]
+pub async fn import_outline(
+    text: String,
+    format: Option<String>,
+) -> Result<importers::ImportResult, String> {
+    telemetry::track(
+        "import_outline",
+        telemetry::ErrorKind::Parse,
+        importers::import_description(&text, format.as_deref()),
+    )
+}
+
+// Generates the diagram at `path` from a SQL, OpenAPI or other source file,
+// or from a data file through a data template, and remembers the source so
+// the diagram can be regenerated later.
+#[command]
+pub async fn link_diagram_source(
+    path: String,
+    source: String,
+    generator: Option<linked::Generator>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<linked::Regeneration, String> {
+    let path = paths::check(&app_handle, &path)?;
+    let source = paths::check(&app_handle, &source)?;
+    if !is_diagram_file(&path) {
+        return Err(format!("Not a diagram file: {}", path.display()));
+    }
+    ensure_writable_document(&app_handle, &path)?;
+    let workspace = open_workspace(&app_handle, &state).ok();
+    telemetry::track(
+        "link_diagram_source",
+        telemetry::ErrorKind::Io,
+        jobs::run(move || linked::link(&path, &source, generator, workspace.as_deref())).await?,
+    )
+}
+
+#[command]
+pub async fn get_linked_source(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<Option<linked::LinkedSource>, String> {
+    let path = paths::check(&app_handle, &path)?;
+    linked::source_of(&path)
+}
+
+// Regenerates a linked diagram now. Without `force`, a diagram with manual
+// edits is reported as such and left untouched.
+#[command]
+pub async fn regenerate_linked_diagram(
+    path: String,
+    force: bool,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<linked::Regeneration, String> {
+    let path = paths::check(&app_handle, &path)?;
+    ensure_writable_document(&app_handle, &path)?;
+    let workspace = open_workspace(&app_handle, &state).ok();
+    jobs::run(move || linked::regenerate(&path, workspace.as_deref(), force)).await?
+}
+
+#[command]
+pub async fn unlink_diagram_source(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let path = paths::check(&app_handle, &path)?;
+    linked::unlink(&path)
+}
+
+// Regenerates the diagram whenever its source changes, reporting each
+// attempt in a "linked-source" event, until unwatched.
+#[command]
+pub async fn watch_linked_source(
+    path: String,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    let path = paths::check(&app_handle, &path)?;
+    ensure_writable_document(&app_handle, &path)?;
+    let workspace = open_workspace(&app_handle, &state).ok();
+    linked::watch(&path, workspace.as_deref())
+}
+
+#[command]
+pub async fn unwatch_linked_source(path: String) -> Result<(), String> {
+    linked::unwatch(&paths::resolve(&path)?);
+    Ok(())
+}
+
+// Saves what was sketched in the capture window as a scratch diagram and
+// tells the main window, which can open it.
+#[command]
+pub async fn create_scratch_diagram(
+    content: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<scratch::ScratchDiagram, String> {
+    let diagram = telemetry::track(
+        "create_scratch_diagram",
+        telemetry::ErrorKind::Io,
+        scratch::create(content.as_deref()),
+    )?;
+    let _ = app_handle.emit_to("main", "scratch-diagram-created", &diagram);
+    Ok(diagram)
+}
+
+// Untitled documents are scratch diagrams, so they survive closing the app.
+#[command]
+pub async fn list_scratch_diagrams() -> Result<Vec<scratch::ScratchSummary>, String> {
+    scratch::list()
+}
+
+#[command]
+pub async fn get_scratch_diagram(id: String) -> Result<scratch::ScratchDiagram, String> {
+    scratch::get(&id)
+}
+
+#[command]
+pub async fn update_scratch_diagram(
+    id: String,
+    content: String,
+) -> Result<scratch::ScratchDiagram, String> {
+    scratch::update(&id, &content)
+}
+
+#[command]
+pub async fn delete_scratch_diagram(id: String) -> Result<(), String> {
+    scratch::delete(&id)
+}
+
+// Save As for a scratch diagram: once the file is written, the scratch copy
+// is removed and the editor continues with the returned path.
+#[command]
+pub async fn save_scratch_diagram_as(
+    id: String,
+    content: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let content = match content {
+        Some(content) => content,
+        None => scratch::get(&id)?.content,
+    };
+    let path = save_file_content_to_disk(content, None, app_handle).await?;
+    if let Err(e) = scratch::delete(&id) {
+        tracing::warn!(id = %id, error = %e, "saved scratch diagram could not be removed");
+    }
+    telemetry::record_feature("save_scratch_diagram_as");
+    Ok(path)
+}
+
+#[command]
+pub async fn open_quick_capture(app_handle: tauri::AppHandle) -> Result<(), String> {
+    open_capture_window(&app_handle)
+}
+
+#[command]
+pub async fn print_diagram(
+    content: String,
+    is_svg: bool,
+    options: Option<print::PrintOptions>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    use base64::Engine;
+
+    let content = outgoing_content(content, "print_diagram", &app_handle, &state);
+
+    // The editor passes either the rendered SVG, which is rebuilt from the
+    // allowlist before the print webview loads it, or Mermaid source, which
+    // is rendered here.
+    let svg = if is_svg {
+        let sanitized = security::sanitize_svg(&content)?;
+        for finding in &sanitized.findings {
+            tracing::warn!(line = finding.line, "stripped unsafe content: {}", finding.message);
+        }
+        sanitized.content
+    } else {
+        let bytes = jobs::run(move || render::render_content_to_bytes(&content, "svg")).await??;
+        String::from_utf8(bytes).map_err(|e| format!("Failed to read rendered SVG: {}", e))?
+    };
+
+    telemetry::record_feature("print_diagram");
+    let document = print::print_document(&svg, &options.unwrap_or_default());
+    let url = format!(
+        "data:text/html;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(document)
+    );
+    let url = url
+        .parse()
+        .map_err(|e| format!("Failed to prepare print document: {}", e))?;
+
+    if let Some(existing) = app_handle.get_webview_window("print") {
+        let _ = existing.close();
+    }
+
+    tauri::WebviewWindowBuilder::new(&app_handle, "print", tauri::WebviewUrl::External(url))
+        .title(i18n::t("window.print_title"))
+        .inner_size(800.0, 600.0)
+        .on_page_load(|window, payload| {
+            if matches!(payload.event(), tauri::webview::PageLoadEvent::Finished) {
+                let _ = window.print();
+            }
+        })
+        .build()
+        .map_err(|e| format!("Failed to open print window: {}", e))?;
+
+    Ok(())
+}
+
+#[command]
+pub async fn submit_job(
+    mut request: jobs::JobRequest,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<u64, String> {
+    if let jobs::JobRequest::Export {
+        content,
+        path,
+        options,
+        theme,
+        ..
+    } = &mut request
+    {
+        *path = paths::check(&app_handle, &path.to_string_lossy())?;
+        prepare_export_options(&app_handle, &state, options)?;
+        let render_theme = export_render_theme(&state, content, options.source.as_deref())?;
+        *theme = Some(Box::new(render_theme));
+    }
+    jobs::submit(request)
+}
+
+#[command]
+pub async fn get_job_status(id: u64) -> Result<jobs::JobStatus, String> {
+    jobs::status(id)
+}
+
+#[command]
+pub async fn cancel_job(id: u64) -> Result<bool, String> {
+    jobs::cancel(id)
+}
+
+// Proposes clusters to move into sub-diagrams once a flowchart grows past
+// the thresholds.
+#[command]
+pub async fn suggest_diagram_splits(
+    content: String,
+    thresholds: Option<split::SplitThresholds>,
+) -> Result<split::SplitAnalysis, String> {
+    telemetry::record_feature("suggest_diagram_splits");
+    split::analyze(&content, &thresholds.unwrap_or_default())
+}
+
+#[command]
+pub async fn list_plugins(state: State<'_, AppStateType>) -> Result<Vec<plugins::PluginInfo>, String> {
+    let enabled = match state.lock() {
+        Ok(app_state) => app_state.enabled_plugins.clone(),
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+
+    plugins::discover(&enabled)
+}
+
+#[command]
+pub async fn enable_plugin(
+    id: String,
+    enabled: bool,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    if enabled {
+        plugins::find(&id)?;
+    }
+
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.enabled_plugins.retain(|plugin| plugin != &id);
+            if enabled {
+                app_state.enabled_plugins.push(id);
+            }
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+fn enabled_plugin(id: &str, state: &AppStateType) -> Result<plugins::LoadedPlugin, String> {
+    let enabled = state
+        .lock()
+        .map(|app_state| app_state.enabled_plugins.iter().any(|plugin| plugin == id))
+        .map_err(|_| i18n::t("error.app_state"))?;
+
+    if !enabled {
+        return Err(format!("Plugin is not enabled: {}", id));
+    }
+
+    plugins::find(id)
+}
+
+#[command]
+pub async fn run_plugin_importer(
+    plugin_id: String,
+    importer_id: String,
+    input: String,
+    state: State<'_, AppStateType>,
+) -> Result<String, String> {
+    enabled_plugin(&plugin_id, &state)?.import(&importer_id, &input)
+}
+
+#[command]
+pub async fn run_plugin_exporter(
+    plugin_id: String,
+    exporter_id: String,
+    content: String,
+    state: State<'_, AppStateType>,
+) -> Result<String, String> {
+    enabled_plugin(&plugin_id, &state)?.export(&exporter_id, &content)
+}
+
+#[command]
+pub async fn lint_with_plugins(
+    content: String,
+    state: State<'_, AppStateType>,
+) -> Result<Vec<plugins::PluginLintIssue>, String> {
+    let enabled = match state.lock() {
+        Ok(app_state) => app_state.enabled_plugins.clone(),
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+
+    let mut issues = Vec::new();
+    for id in enabled {
+        // A plugin removed from the folder should not stop the others.
+        let mut plugin = match plugins::find(&id) {
+            Ok(plugin) => plugin,
+            Err(e) => {
+                tracing::warn!(plugin = %id, error = %e, "skipping enabled plugin");
+                continue;
+            }
+        };
+        if !plugin.manifest.lint_rules.is_empty() {
+            issues.extend(plugin.lint(&content)?);
+        }
+    }
+
+    Ok(issues)
+}
+
+#[command]
+pub async fn run_script(
+    path: String,
+    files: Vec<String>,
+    dry_run: Option<bool>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<scripting::ScriptFileResult>, String> {
+    // Scripts rewrite the files they run on.
+    let path = paths::check(&app_handle, &path)?;
+    let files = files
+        .iter()
+        .map(|file| paths::check(&app_handle, file))
+        .collect::<Result<Vec<_>, _>>()?;
+    let script = fs::read_to_string(&path).map_err(|e| format!("Failed to read script: {}", e))?;
+    let runner = telemetry::track(
+        "run_script",
+        telemetry::ErrorKind::Parse,
+        scripting::ScriptRunner::compile(&script),
+    )?;
+    let dry_run = dry_run.unwrap_or(false);
+
+    Ok(files
+        .iter()
+        .map(|file| runner.run_file(file, dry_run))
+        .collect())
+}
+
+#[command]
+pub async fn get_ai_config(state: State<'_, AppStateType>) -> Result<ai::AiSettings, String> {
+    let config = match state.lock() {
+        Ok(app_state) => app_state.ai_config.clone(),
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+
+    Ok(ai::AiSettings {
+        config,
+        has_api_key: secrets::get_secret(ai::API_KEY_SECRET)?.is_some(),
+    })
+}
+
+#[command]
+pub async fn set_ai_config(
+    config: Option<ai::AiConfig>,
+    api_key: Option<String>,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    // `None` keeps the stored key, an empty string removes it.
+    match api_key.as_deref().map(str::trim) {
+        Some("") => secrets::delete_secret(ai::API_KEY_SECRET)?,
+        Some(key) => secrets::set_secret(ai::API_KEY_SECRET, key)?,
+        None => {}
+    }
+
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.ai_config = config;
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn generate_diagram_ai(
+    prompt: String,
+    diagram_type: Option<String>,
+    state: State<'_, AppStateType>,
+) -> Result<ai::GeneratedDiagram, String> {
+    let config = match state.lock() {
+        Ok(app_state) => app_state.ai_config.clone(),
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+    let config = config.ok_or("AI generation is not configured")?;
+
+    let diagram_type = match diagram_type.map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()) {
+        Some(name) => Some(
+            diagrams::find(&name)
+                .map(|kind| kind.id)
+                .ok_or_else(|| format!("Unknown diagram type: {}", name))?,
+        ),
+        None => None,
+    };
+
+    telemetry::track(
+        "generate_diagram_ai",
+        telemetry::ErrorKind::Network,
+        ai::generate(&config, &prompt, diagram_type).await,
+    )
+}
+
+#[command]
+pub async fn set_telemetry_enabled(
+    enabled: bool,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.telemetry_enabled = enabled;
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))?;
+        }
+        Err(_) => return Err(i18n::t("error.app_state")),
+    }
+
+    // Turning telemetry off also drops anything that was queued.
+    telemetry::init(enabled, &app_handle.package_info().version.to_string());
+    Ok(())
+}
+
+#[command]
+pub async fn get_pending_telemetry() -> Result<telemetry::PendingTelemetry, String> {
+    Ok(telemetry::pending())
+}
+
+#[command]
+pub async fn get_recent_logs(
+    level: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<logging::LogEntry>, String> {
+    logging::recent_logs(level.as_deref(), limit)
+}
+
+#[command]
+pub async fn create_diagnostic_bundle(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<String, String> {
+    let settings = match state.lock() {
+        Ok(app_state) => serde_json::to_value(&*app_state)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?,
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+
+    let file_name = format!(
+        "flowcraft-diagnostics-{}.zip",
+        Utc::now().format("%Y%m%d-%H%M%S")
+    );
+    let dialog_result = app_handle
+        .dialog()
+        .file()
+        .set_file_name(&file_name)
+        .add_filter(&i18n::t("dialog.filter.zip"), &["zip"])
+        .blocking_save_file();
+
+    match dialog_result {
+        Some(file_path) => {
+            let path_buf = file_path.into_path().map_err(|e| format!("Failed to convert path: {}", e))?;
+            diagnostics::create_bundle(&path_buf, &settings)?;
+            Ok(path_buf.to_string_lossy().to_string())
+        }
+        None => Err(i18n::t("error.export_cancelled")),
+    }
+}
+
+// Packages settings, recent files, keybindings and user themes into one zip
+// for moving to another machine.
+#[command]
+pub async fn export_app_state_bundle(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<String, String> {
+    let file_name = format!(
+        "flowcraft-settings-{}.zip",
+        Utc::now().format("%Y%m%d-%H%M%S")
+    );
+    let dialog_result = app_handle
+        .dialog()
+        .file()
+        .set_file_name(&file_name)
+        .add_filter(&i18n::t("dialog.filter.zip"), &["zip"])
+        .blocking_save_file();
+
+    let path_buf = match dialog_result {
+        Some(file_path) => file_path.into_path().map_err(|e| format!("Failed to convert path: {}", e))?,
+        None => return Err(i18n::t("error.export_cancelled")),
+    };
+    match state.lock() {
+        Ok(app_state) => sync::export_bundle(&path_buf, &app_state)?,
+        Err(_) => return Err(i18n::t("error.app_state")),
+    }
+    Ok(path_buf.to_string_lossy().to_string())
+}
+
+// Replaces the settings with those from a bundle, keeping machine-specific
+// ones, and applies them right away.
+#[command]
+pub async fn import_app_state_bundle(
+    path: String,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<sync::ImportSummary, String> {
+    let path = paths::check(&app_handle, &path)?;
+
+    let (summary, locale, theme, mermaid_config) = match state.lock() {
+        Ok(mut app_state) => {
+            let (imported, summary) = sync::import_bundle(&path, &app_state)?;
+            *app_state = imported;
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))?;
+            (
+                summary,
+                app_state.locale.clone(),
+                app_state.theme.clone(),
+                app_state.mermaid_config.clone(),
+            )
+        }
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+
+    i18n::set_current(&locale.unwrap_or_else(i18n::system_locale));
+    if let Err(e) = apply_render_config(theme.as_deref(), &mermaid_config) {
+        tracing::warn!(error = %e, "failed to apply theme");
+    }
+    register_global_shortcuts(&app_handle);
+    refresh_tray(&app_handle);
+    telemetry::record_feature("import_app_state_bundle");
+    Ok(summary)
+}
+
+#[command]
+pub async fn check_for_updates(app_handle: tauri::AppHandle) -> Result<updates::UpdateInfo, String> {
+    let current_version = app_handle.package_info().version.to_string();
+    telemetry::track(
+        "check_for_updates",
+        telemetry::ErrorKind::Network,
+        updates::check(&current_version).await,
+    )
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocaleInfo {
+    pub current: String,
+    pub available: Vec<String>,
+}
+
+#[command]
+pub async fn get_locale_strings(
+    locale: Option<String>,
+) -> Result<BTreeMap<String, String>, String> {
+    Ok(i18n::strings(&locale.unwrap_or_else(i18n::current)))
+}
+
+#[command]
+pub async fn get_locales() -> Result<LocaleInfo, String> {
+    Ok(LocaleInfo {
+        current: i18n::current(),
+        available: i18n::available_locales(),
+    })
+}
+
+#[command]
+pub async fn set_locale(
+    locale: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<String, String> {
+    // `None` goes back to following the system language.
+    let locale = locale.map(|locale| i18n::normalize(&locale));
+    if let Some(locale) = &locale {
+        if !i18n::is_available(locale) {
+            return Err(i18n::t_with("error.unknown_locale", &[("locale", locale)]));
+        }
+    }
+
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.locale = locale.clone();
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))?;
+        }
+        Err(_) => return Err(i18n::t("error.app_state")),
+    }
+
+    i18n::set_current(&locale.unwrap_or_else(i18n::system_locale));
+    refresh_tray(&app_handle);
+    Ok(i18n::current())
+}
+
+#[command]
+pub async fn get_keybindings(
+    state: State<'_, AppStateType>,
+) -> Result<Vec<keybindings::Keybinding>, String> {
+    match state.lock() {
+        Ok(app_state) => Ok(keybindings::resolve(&app_state.keybindings)),
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn set_keybinding(
+    action: String,
+    accelerator: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<Vec<keybindings::Keybinding>, String> {
+    let bindings = match state.lock() {
+        Ok(mut app_state) => {
+            let bindings = keybindings::resolve(&app_state.keybindings);
+            // `None` restores the default and an empty string unbinds the action.
+            match accelerator.as_deref().map(str::trim) {
+                None => {
+                    let default = bindings
+                        .iter()
+                        .find(|binding| binding.action == action)
+                        .map(|binding| binding.default_accelerator.clone())
+                        .ok_or_else(|| format!("Unknown action: {}", action))?;
+                    keybindings::validate(&action, &default, &bindings)?;
+                    app_state.keybindings.remove(&action);
+                }
+                Some("") => {
+                    if !keybindings::is_known(&action) {
+                        return Err(format!("Unknown action: {}", action));
+                    }
+                    app_state.keybindings.insert(action.clone(), None);
+                }
+                Some(accelerator) => {
+                    let accelerator = keybindings::validate(&action, accelerator, &bindings)?;
+                    app_state.keybindings.insert(action.clone(), Some(accelerator));
+                }
+            }
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))?;
+            keybindings::resolve(&app_state.keybindings)
+        }
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+
+    if keybindings::is_global(&action) {
+        register_global_shortcuts(&app_handle);
+    }
+    Ok(bindings)
+}
+
+// Points the export renderer at the selected theme plus the user's Mermaid
+// overrides, falling back to the default theme when it no longer exists.
+fn apply_render_config(
+    id: Option<&str>,
+    mermaid_config: &mermaid_config::MermaidConfig,
+) -> Result<themes::Theme, String> {
+    let theme = match id {
+        Some(id) => themes::get(id).or_else(|e| {
+            tracing::warn!(theme = id, error = %e, "falling back to default theme");
+            themes::get(themes::DEFAULT_THEME)
+        })?,
+        None => themes::get(themes::DEFAULT_THEME)?,
+    };
+    let mut config = theme.render_config();
+    mermaid_config.apply(&mut config.mermaid);
+    render::set_config(Some(config));
+    Ok(theme)
+}
+
+#[command]
+pub async fn list_themes() -> Result<themes::ThemeList, String> {
+    Ok(themes::list())
+}
+
+#[command]
+pub async fn get_theme(id: String) -> Result<themes::Theme, String> {
+    themes::get(&id)
+}
+
+#[command]
+pub async fn set_theme(
+    id: Option<String>,
+    state: State<'_, AppStateType>,
+) -> Result<themes::Theme, String> {
+    if let Some(id) = &id {
+        themes::get(id)?;
+    }
+
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.theme = id.clone();
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))?;
+            apply_render_config(id.as_deref(), &app_state.mermaid_config)
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn list_icon_packs() -> Result<Vec<icons::IconPack>, String> {
+    Ok(icons::list())
+}
+
+// The Iconify JSON of a pack, which the preview registers with Mermaid.
+#[command]
+pub async fn get_icon_pack(name: String) -> Result<String, String> {
+    icons::get(&name)
+}
+
+#[command]
+pub async fn install_icon_pack(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<icons::IconPack, String> {
+    let path = paths::check(&app_handle, &path)?;
+    let pack = icons::install(&path)?;
+    telemetry::record_feature("icon_pack");
+    Ok(pack)
+}
+
+// Downloads an Iconify icon set by name, such as `logos` or `mdi`, or from
+// an https URL.
+#[command]
+pub async fn download_icon_pack(source: String) -> Result<icons::IconPack, String> {
+    telemetry::track(
+        "download_icon_pack",
+        telemetry::ErrorKind::Network,
+        icons::download(&source).await,
+    )
+}
+
+#[command]
+pub async fn remove_icon_pack(name: String) -> Result<(), String> {
+    icons::remove(&name)
+}
+
+#[command]
+pub async fn get_mermaid_config(
+    state: State<'_, AppStateType>,
+) -> Result<mermaid_config::MermaidConfig, String> {
+    match state.lock() {
+        Ok(app_state) => Ok(app_state.mermaid_config.clone()),
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn set_mermaid_config(
+    config: mermaid_config::MermaidConfig,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    config.validate()?;
+
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.mermaid_config = config;
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))?;
+            apply_render_config(app_state.theme.as_deref(), &app_state.mermaid_config)?;
+            Ok(())
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// The merged theme and user config, for the preview's `mermaid.initialize`
+// so it matches exports.
+#[command]
+pub async fn get_mermaid_init_config() -> Result<serde_json::Value, String> {
+    Ok(render::current_config()
+        .map(|config| config.mermaid)
+        .unwrap_or_else(|| serde_json::json!({})))
+}
+
+// Scripts, event handlers, script URLs and click callbacks in diagram
+// source or rendered SVG, to review before exporting or sharing.
+#[command]
+pub async fn scan_content(content: String) -> Result<Vec<security::Finding>, String> {
+    Ok(security::scan(&content))
+}
+
+#[command]
+pub async fn sanitize_content(content: String) -> Result<security::Sanitized, String> {
+    Ok(security::sanitize(&content))
+}
+
+#[command]
+pub async fn set_strip_unsafe_content(
+    enabled: bool,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.strip_unsafe_content = enabled;
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn set_strip_source_metadata(
+    enabled: bool,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.strip_source_metadata = enabled;
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// What shared source looks like with the setting on, for a preview.
+#[command]
+pub async fn strip_source_metadata(content: String) -> Result<String, String> {
+    Ok(comment_report::strip(&content))
+}
+
+#[command]
+pub async fn get_redaction_config(
+    state: State<'_, AppStateType>,
+) -> Result<redaction::RedactionConfig, String> {
+    match state.lock() {
+        Ok(app_state) => Ok(app_state.redaction.clone()),
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn set_redaction_config(
+    config: redaction::RedactionConfig,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    config.validate()?;
+
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.redaction = config;
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn get_redaction_presets() -> Result<Vec<redaction::RedactionRule>, String> {
+    Ok(redaction::presets())
+}
+
+// What the rules would redact, for a preview before sharing. Uses the saved
+// rules unless the settings page passes the ones being edited, and runs
+// them even while automatic redaction is off.
+#[command]
+pub async fn redact_content(
+    content: String,
+    config: Option<redaction::RedactionConfig>,
+    state: State<'_, AppStateType>,
+) -> Result<redaction::Redacted, String> {
+    let config = match config {
+        Some(config) => {
+            config.validate()?;
+            config
+        }
+        None => match state.lock() {
+            Ok(app_state) => app_state.redaction.clone(),
+            Err(_) => return Err(i18n::t("error.app_state")),
+        },
+    };
+    jobs::run(move || redaction::redact(&content, &config)).await
+}
+
+// A copy of the diagram with names swapped for placeholders such as
+// `Service A`, for sharing outside the company. The replacements stay here.
+#[command]
+pub async fn anonymize_diagram(
+    content: String,
+    rules: Option<anonymize::AnonymizeRules>,
+) -> Result<anonymize::Anonymized, String> {
+    telemetry::record_feature("anonymize_diagram");
+    let rules = rules.unwrap_or_default();
+    jobs::run(move || anonymize::anonymize(&content, &rules)).await
+}
+
+// Replaces `:rocket:`-style shortcodes in labels with emoji, and lists the
+// unknown ones with suggestions.
+#[command]
+pub async fn expand_emoji(content: String) -> Result<emoji::Expanded, String> {
+    jobs::run(move || emoji::expand(&content)).await
+}
+
+#[command]
+pub async fn set_expand_emoji(enabled: bool, state: State<'_, AppStateType>) -> Result<(), String> {
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.expand_emoji = enabled;
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// The effort level PNG exports are recompressed at, from 0 to
+// `export::MAX_PNG_OPTIMIZATION`, or None to leave them as rendered.
+#[command]
+pub async fn set_optimize_png(level: Option<u8>, state: State<'_, AppStateType>) -> Result<(), String> {
+    if let Some(level) = level {
+        export::validate_png_optimization(level)?;
+    }
+
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.optimize_png = level;
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// Link targets of `click`, `link` and `href` directives with their syntax
+// checked; `online` also requests each web link to find dead ones.
+#[command]
+pub async fn check_links(content: String, online: bool) -> Result<Vec<links::LinkReport>, String> {
+    if !file_settings::get(&content).unwrap_or_default().allows("links") {
+        return Ok(Vec::new());
+    }
+    let mut reports = links::check(&content);
+    if online {
+        telemetry::track(
+            "check_links_online",
+            telemetry::ErrorKind::Network,
+            links::check_online(&mut reports).await,
+        )?;
+    }
+    Ok(reports)
+}
+
+// Checks that the images a diagram refers to exist. `path` is the diagram's
+// own file, which relative image paths resolve against.
+#[command]
+pub async fn check_images(
+    content: String,
+    path: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<Vec<images::ImageReport>, String> {
+    if !file_settings::get(&content).unwrap_or_default().allows("images") {
+        return Ok(Vec::new());
+    }
+    let base = match path {
+        Some(path) => paths::check(&app_handle, &path)?
+            .parent()
+            .map(|dir| dir.to_path_buf()),
+        None => None,
+    };
+    let workspace = open_workspace(&app_handle, &state).ok();
+    let scope = images::ImageScope::new(base.as_deref(), workspace.as_deref());
+    jobs::run(move || images::check(&content, &scope)).await
+}
+
+// Font Awesome icons that do not exist or are written with the wrong
+// prefix, with replacements the editor can offer.
+#[command]
+pub async fn check_fontawesome(content: String) -> Result<Vec<fontawesome::IconIssue>, String> {
+    if !file_settings::get(&content).unwrap_or_default().allows("fontawesome") {
+        return Ok(Vec::new());
+    }
+    jobs::run(move || fontawesome::check(&content)).await
+}
+
+#[command]
+pub async fn check_accessibility(content: String) -> Result<accessibility::Report, String> {
+    Ok(accessibility::check(&content))
+}
+
+// Adds or replaces the accTitle and accDescr statements; a None field is
+// left as it is.
+#[command]
+pub async fn set_accessibility(
+    content: String,
+    title: Option<String>,
+    description: Option<String>,
+) -> Result<String, String> {
+    accessibility::apply(&content, title.as_deref(), description.as_deref())
+}
+
+// Diagrams under `path`, or the workspace when None, that lack a title or
+// description.
+#[command]
+pub async fn find_missing_accessibility(
+    path: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<Vec<accessibility::MissingMetadata>, String> {
+    let path = match path {
+        Some(path) => path,
+        None => state
+            .lock()
+            .map_err(|_| i18n::t("error.app_state"))?
+            .workspace
+            .clone()
+            .ok_or("No workspace is open")?,
+    };
+    let dir = paths::check(&app_handle, &path)?;
+    telemetry::track(
+        "find_missing_accessibility",
+        telemetry::ErrorKind::Io,
+        jobs::run(move || accessibility::find_missing(&dir)).await?,
+    )
+}
+
+// The `%%` comments of the open diagram, with TODO-style markers picked out.
+#[command]
+pub async fn extract_comments(content: String) -> Result<Vec<comment_report::DiagramComment>, String> {
+    Ok(comment_report::extract(&content))
+}
+
+// The comments of a diagram or of every diagram in a folder, or the
+// workspace when `path` is None.
+#[command]
+pub async fn get_comment_report(
+    path: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<comment_report::CommentReport, String> {
+    let path = match path {
+        Some(path) => path,
+        None => state
+            .lock()
+            .map_err(|_| i18n::t("error.app_state"))?
+            .workspace
+            .clone()
+            .ok_or("No workspace is open")?,
+    };
+    let path = paths::check(&app_handle, &path)?;
+    telemetry::track(
+        "get_comment_report",
+        telemetry::ErrorKind::Io,
+        jobs::run(move || comment_report::report(&path)).await?,
+    )
+}
+
+#[command]
+pub async fn get_spell_check_config(
+    state: State<'_, AppStateType>,
+) -> Result<spelling::SpellCheckConfig, String> {
+    match state.lock() {
+        Ok(app_state) => Ok(app_state.spell_check.clone()),
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn set_spell_check_config(
+    config: spelling::SpellCheckConfig,
+    state: State<'_, AppStateType>,
+) -> Result<(), String> {
+    config.validate()?;
+
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.spell_check = config;
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// Spelling issues in labels and notes. Returns nothing while spell checking
+// is turned off, so the editor can call it after every change.
+#[command]
+pub async fn check_spelling(
+    content: String,
+    state: State<'_, AppStateType>,
+) -> Result<Vec<spelling::SpellingIssue>, String> {
+    let config = match state.lock() {
+        Ok(app_state) => app_state.spell_check.clone(),
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+    if !config.enabled || !file_settings::get(&content).unwrap_or_default().allows("spelling") {
+        return Ok(Vec::new());
+    }
+    jobs::run(move || spelling::check(&content, &config)).await
+}
+
+#[command]
+pub async fn get_user_dictionary() -> Result<Vec<String>, String> {
+    Ok(spelling::user_words())
+}
+
+#[command]
+pub async fn add_to_dictionary(word: String) -> Result<Vec<String>, String> {
+    spelling::add_word(&word)
+}
+
+#[command]
+pub async fn remove_from_dictionary(word: String) -> Result<Vec<String>, String> {
+    spelling::remove_word(&word)
+}
+
+#[command]
+pub async fn list_available_mermaid_versions(
+    state: State<'_, AppStateType>,
+) -> Result<Vec<mermaid_versions::MermaidVersion>, String> {
+    match state.lock() {
+        Ok(app_state) => Ok(mermaid_versions::list(&app_state.mermaid_renderer)),
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+#[command]
+pub async fn set_mermaid_renderer(
+    config: mermaid_versions::RendererConfig,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppStateType>,
+) -> Result<Vec<mermaid_versions::MermaidVersion>, String> {
+    if let Some(script) = &config.custom_script {
+        paths::check(&app_handle, script)?;
+    }
+    mermaid_versions::validate(&config)?;
+
+    match state.lock() {
+        Ok(mut app_state) => {
+            app_state.mermaid_renderer = config;
+            save_app_state(&app_state).map_err(|e| format!("Failed to save state: {}", e))?;
+            render::set_binary(mermaid_versions::mmdc_path(&app_state.mermaid_renderer));
+            render::set_kroki_url(app_state.mermaid_renderer.kroki_url.clone());
+            Ok(mermaid_versions::list(&app_state.mermaid_renderer))
+        }
+        Err(_) => Err(i18n::t("error.app_state")),
+    }
+}
+
+// Source of the pinned mermaid.js for the preview; `None` means it should
+// keep using the bundled version.
+#[command]
+pub async fn get_mermaid_script(state: State<'_, AppStateType>) -> Result<Option<String>, String> {
+    let script = match state.lock() {
+        Ok(app_state) => mermaid_versions::script_path(&app_state.mermaid_renderer),
+        Err(_) => return Err(i18n::t("error.app_state")),
+    };
+
+    match script {
+        Some(path) => fs::read_to_string(&path)
+            .map(Some)
+            .map_err(|e| format!("Failed to read Mermaid script: {}", e)),
+        None => Ok(None),
     }
 }
 
@@ -275,6 +3931,13 @@ fn get_app_data_dir() -> Result<PathBuf, String> {
         .ok_or_else(|| "Could not determine app data directory".to_string())
 }
 
+// For callers that carry on when the state cannot be written.
+fn persist_app_state(state: &AppState) {
+    if let Err(e) = save_app_state(state) {
+        tracing::error!(error = %e, "failed to save app state");
+    }
+}
+
 fn load_app_state() -> Result<AppState, String> {
     let app_dir = get_app_data_dir()?;
     let state_file = app_dir.join("state.json");
@@ -306,20 +3969,322 @@ fn save_app_state(state: &AppState) -> Result<(), String> {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    if let Err(e) = logging::init() {
+        eprintln!("{}", e);
+    }
+    diagnostics::install_panic_hook();
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "starting Flowcraft Studio");
+
+    let mut builder = tauri::Builder::default();
+
+    // A second launch hands its arguments to the running instance and exits,
+    // so only one process ever owns state.json.
+    #[cfg(desktop)]
+    {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            for path in launch::file_arguments(args.into_iter().skip(1), Path::new(&cwd)) {
+                open_in_running_app(app, &path, None);
+            }
+            focus_main_window(app);
+        }));
+        builder = builder.plugin(tauri_plugin_global_shortcut::Builder::new().build());
+    }
+
+    builder
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
-        .manage(Mutex::new(load_app_state().unwrap_or_default()))
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .manage(Mutex::new(load_app_state().unwrap_or_else(|e| {
+            tracing::error!(error = %e, "failed to load app state, using defaults");
+            AppState::default()
+        })))
+        .manage(WatchStateType::default())
+        .manage(ThumbnailStateType::default())
+        .manage(DocumentWatchStateType::default())
+        .manage(LinkedWatchStateType::default())
+        .manage(PreviewServerStateType::default())
+        .manage(PendingOpensStateType::default())
+        .manage(ReadOnlyStateType::default())
+        .setup(|app| {
+            let (telemetry_enabled, locale, theme, mermaid_config, mermaid_renderer) = app
+                .state::<AppStateType>()
+                .lock()
+                .map(|app_state| {
+                    (
+                        app_state.telemetry_enabled,
+                        app_state.locale.clone(),
+                        app_state.theme.clone(),
+                        app_state.mermaid_config.clone(),
+                        app_state.mermaid_renderer.clone(),
+                    )
+                })
+                .unwrap_or_default();
+            i18n::set_current(&locale.unwrap_or_else(i18n::system_locale));
+            if let Err(e) = apply_render_config(theme.as_deref(), &mermaid_config) {
+                tracing::warn!(error = %e, "failed to apply theme");
+            }
+            render::set_binary(mermaid_versions::mmdc_path(&mermaid_renderer));
+            render::set_kroki_url(mermaid_renderer.kroki_url.clone());
+            telemetry::init(telemetry_enabled, &app.package_info().version.to_string());
+            telemetry::spawn_uploader();
+
+            restore_path_scope(app.handle());
+            resume_watch_export(app.handle());
+            resume_workspace(app.handle());
+            start_document_watch(app.handle());
+            start_linked_source_watch(app.handle());
+            resume_preview_server(app.handle());
+
+            #[cfg(desktop)]
+            tray::init(app.handle())?;
+            register_global_shortcuts(app.handle());
+
+            #[cfg(any(windows, target_os = "linux"))]
+            app.deep_link().register_all()?;
+
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    handle_deep_link(&handle, &url);
+                }
+            });
+
+            let cwd = std::env::current_dir().unwrap_or_default();
+            for path in launch::file_arguments(std::env::args().skip(1), &cwd) {
+                open_in_running_app(app.handle(), &path, None);
+            }
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                for url in urls {
+                    handle_deep_link(app.handle(), &url);
+                }
+            }
+            Ok(())
+        })
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::CloseRequested { api, .. } => {
+                let close_to_tray = window
+                    .state::<AppStateType>()
+                    .lock()
+                    .map(|app_state| app_state.close_to_tray)
+                    .unwrap_or(false);
+
+                if close_to_tray {
+                    let _ = window.hide();
+                    api.prevent_close();
+                }
+            }
+            tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) => {
+                handle_file_drop(window.app_handle(), paths);
+            }
+            _ => {}
+        })
         .invoke_handler(tauri::generate_handler![
             save_file_content_to_disk,
             load_file,
+            save_encrypted_file,
+            open_encrypted_file,
+            duplicate_file,
+            rename_file,
+            get_file_info,
+            get_file_metrics_history,
+            start_editing_session,
+            end_editing_session,
+            get_editing_time,
+            reset_editing_time,
+            get_review_comments,
+            add_review_comment,
+            reply_to_review_comment,
+            edit_review_comment,
+            resolve_review_thread,
+            delete_review_comment,
+            delete_review_thread,
+            diff_files,
+            get_conflicts,
+            resolve_conflict,
+            reveal_in_file_manager,
+            open_in_external_editor,
+            unwatch_document,
+            get_external_editor,
+            choose_external_editor,
+            clear_external_editor,
+            list_storage_backends,
+            set_storage_backend,
+            remove_storage_backend,
+            list_remote_files,
             validate_mermaid_syntax,
             get_recent_files,
             clear_recent_files,
+            get_starred_files,
+            star_file,
+            unstar_file,
+            get_file_tags,
+            tag_file,
+            untag_file,
+            find_files_by_tag,
+            get_all_tags,
             get_templates,
-            export_diagram
+            list_snippets,
+            get_snippet_categories,
+            create_snippet,
+            update_snippet,
+            delete_snippet,
+            expand_snippet,
+            get_data_templates,
+            render_data_template,
+            export_diagram,
+            export_light_dark,
+            export_workspace,
+            get_export_css,
+            set_export_css,
+            get_export_theme,
+            get_workspace_theme,
+            set_workspace_theme,
+            get_undo_history,
+            set_undo_history,
+            get_layout,
+            set_layout,
+            update_layout,
+            rename_layout_node,
+            get_file_settings,
+            set_file_settings,
+            render_preview,
+            export_gantt_calendar,
+            export_mindmap_opml,
+            get_signing_key,
+            set_signing_key,
+            get_trusted_signing_keys,
+            set_trusted_signing_keys,
+            verify_signature,
+            start_watch_export,
+            stop_watch_export,
+            get_watch_export_status,
+            set_workspace,
+            get_workspace,
+            install_git_hook,
+            remove_git_hook,
+            get_thumbnail,
+            start_preview_server,
+            stop_preview_server,
+            get_preview_server_url,
+            take_pending_opens,
+            create_new_diagram,
+            set_close_to_tray,
+            copy_as_markdown,
+            import_from_clipboard,
+            import_from_file,
+            import_http_log,
+            import_timeline,
+            generate_mindmap_from_markdown,
+            generate_from_rust,
+            generate_from_typescript,
+            generate_gitgraph,
+            import_outline,
+            link_diagram_source,
+            get_linked_source,
+            regenerate_linked_diagram,
+            unlink_diagram_source,
+            watch_linked_source,
+            unwatch_linked_source,
+            has_github_token,
+            set_github_token,
+            share_as_gist,
+            import_from_gist,
+            create_mermaid_live_link,
+            decode_mermaid_live_link,
+            create_share_link,
+            create_scratch_diagram,
+            list_scratch_diagrams,
+            get_scratch_diagram,
+            update_scratch_diagram,
+            delete_scratch_diagram,
+            save_scratch_diagram_as,
+            open_quick_capture,
+            print_diagram,
+            suggest_diagram_splits,
+            submit_job,
+            get_job_status,
+            cancel_job,
+            list_plugins,
+            enable_plugin,
+            run_plugin_importer,
+            run_plugin_exporter,
+            lint_with_plugins,
+            run_script,
+            get_ai_config,
+            set_ai_config,
+            generate_diagram_ai,
+            set_telemetry_enabled,
+            get_pending_telemetry,
+            get_recent_logs,
+            create_diagnostic_bundle,
+            export_app_state_bundle,
+            import_app_state_bundle,
+            check_for_updates,
+            get_locale_strings,
+            get_locales,
+            set_locale,
+            get_keybindings,
+            set_keybinding,
+            list_themes,
+            get_theme,
+            set_theme,
+            list_icon_packs,
+            get_icon_pack,
+            install_icon_pack,
+            download_icon_pack,
+            remove_icon_pack,
+            get_mermaid_config,
+            set_mermaid_config,
+            get_mermaid_init_config,
+            scan_content,
+            sanitize_content,
+            set_strip_unsafe_content,
+            set_strip_source_metadata,
+            strip_source_metadata,
+            anonymize_diagram,
+            get_redaction_config,
+            set_redaction_config,
+            get_redaction_presets,
+            redact_content,
+            expand_emoji,
+            set_expand_emoji,
+            set_optimize_png,
+            check_links,
+            check_images,
+            check_fontawesome,
+            check_accessibility,
+            set_accessibility,
+            find_missing_accessibility,
+            extract_comments,
+            get_comment_report,
+            get_spell_check_config,
+            set_spell_check_config,
+            check_spelling,
+            get_user_dictionary,
+            add_to_dictionary,
+            remove_from_dictionary,
+            list_available_mermaid_versions,
+            set_mermaid_renderer,
+            get_mermaid_script
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app_handle, event| match event {
+            #[cfg(any(target_os = "macos", target_os = "ios"))]
+            tauri::RunEvent::Opened { urls } => {
+                for url in urls {
+                    handle_opened_url(_app_handle, &url);
+                }
+            }
+            tauri::RunEvent::Exit => {
+                telemetry::persist();
+                if let Err(e) = time_tracking::end_all() {
+                    tracing::warn!(error = %e, "failed to record editing time");
+                }
+                logging::shutdown();
+            }
+            _ => {}
+        });
 }
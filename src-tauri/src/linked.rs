@@ -0,0 +1,432 @@
+use chrono::Utc;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::importers::{self, SourceKind};
+use crate::{data_templates, encryption, external, paths};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+// Linked diagrams being watched, with the workspace open when watching
+// started and the modification time last seen for each one's source.
+static WATCHED: Mutex<Option<HashMap<PathBuf, Watched>>> = Mutex::new(None);
+
+struct Watched {
+    workspace: Option<PathBuf>,
+    seen: Option<SystemTime>,
+}
+
+// How a diagram is made from its source: converted by an importer, or
+// rendered from a data template with the source as its data.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Generator {
+    Import(SourceKind),
+    DataTemplate(String),
+}
+
+impl Generator {
+    fn run(&self, source: &Path) -> Result<(String, Vec<String>), String> {
+        match self {
+            Generator::Import(kind) => {
+                let text = fs::read_to_string(source)
+                    .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+                let result = importers::convert(*kind, &text)?;
+                Ok((result.content, result.warnings))
+            }
+            Generator::DataTemplate(id) => {
+                let rendered = data_templates::render_file(id, source)?;
+                Ok((rendered.content, rendered.warnings))
+            }
+        }
+    }
+}
+
+// Sidecars written before data templates could be linked name the importer
+// alone, as `"kind": "sql"`.
+fn generator<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Generator, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Kind(SourceKind),
+        Generator(Generator),
+    }
+    Ok(match Stored::deserialize(deserializer)? {
+        Stored::Kind(kind) => Generator::Import(kind),
+        Stored::Generator(generator) => generator,
+    })
+}
+
+// What a source path is relative to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceBase {
+    // The folder the diagram is in.
+    #[default]
+    Diagram,
+    // The open workspace, for sources elsewhere in it.
+    Workspace,
+}
+
+// Where a generated diagram came from, kept in a `<diagram>.source.json`
+// sidecar. The hash is of the diagram as last generated, so edits made to
+// it since can be told apart.
+//
+// The sidecar travels with repositories, so nothing in it is trusted: the
+// source is a relative path that has to stay inside the diagram's folder or
+// the workspace, and the hash only protects against accidents.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LinkedSource {
+    pub source: String,
+    #[serde(default)]
+    pub base: SourceBase,
+    #[serde(alias = "kind", deserialize_with = "generator")]
+    pub generator: Generator,
+    pub generated_hash: String,
+    pub generated_at: String,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Regenerated,
+    Unchanged,
+    // The diagram was edited since it was generated, so it was left alone.
+    ManualEdits,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Regeneration {
+    pub path: String,
+    pub status: Status,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LinkedSourceEvent {
+    Regenerated { path: String, warnings: Vec<String> },
+    ManualEdits { path: String, source: String },
+    Failed { path: String, error: String },
+}
+
+pub struct LinkedWatchHandle {
+    stop: Arc<AtomicBool>,
+}
+
+impl LinkedWatchHandle {
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for LinkedWatchHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+pub fn sidecar_path(diagram: &Path) -> PathBuf {
+    let mut name = diagram.as_os_str().to_owned();
+    name.push(".source.json");
+    PathBuf::from(name)
+}
+
+fn hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn folder_of(diagram: &Path) -> Result<PathBuf, String> {
+    diagram
+        .parent()
+        .and_then(|folder| folder.canonicalize().ok())
+        .ok_or_else(|| format!("Failed to resolve the folder of {}", diagram.display()))
+}
+
+// Turns a source into the path kept in the sidecar: relative to the
+// diagram's folder if it is below it, otherwise to the workspace.
+fn relative_source(
+    diagram: &Path,
+    source: &Path,
+    workspace: Option<&Path>,
+) -> Result<(String, SourceBase), String> {
+    let source = source
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", source.display(), e))?;
+    let folder = folder_of(diagram)?;
+    let workspace = workspace.and_then(|workspace| workspace.canonicalize().ok());
+    let (relative, base) = if let Ok(relative) = source.strip_prefix(&folder) {
+        (relative, SourceBase::Diagram)
+    } else if let Some(relative) = workspace
+        .as_deref()
+        .and_then(|workspace| source.strip_prefix(workspace).ok())
+    {
+        (relative, SourceBase::Workspace)
+    } else {
+        return Err(format!(
+            "{} must be in the diagram's folder or the open workspace to be linked",
+            source.display()
+        ));
+    };
+    let relative = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Ok((relative, base))
+}
+
+// Resolves the source named in a sidecar. Absolute paths and `..` are
+// refused, and the resolved file, symlinks followed, must still be inside
+// its base and outside the protected locations.
+pub fn resolve_source(
+    diagram: &Path,
+    link: &LinkedSource,
+    workspace: Option<&Path>,
+) -> Result<PathBuf, String> {
+    let relative = Path::new(&link.source);
+    if link.source.is_empty()
+        || !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(format!(
+            "Linked source '{}' must be a path relative to the {} without '..'; link the diagram again",
+            link.source,
+            match link.base {
+                SourceBase::Diagram => "diagram's folder",
+                SourceBase::Workspace => "workspace",
+            }
+        ));
+    }
+    let base = match link.base {
+        SourceBase::Diagram => folder_of(diagram)?,
+        SourceBase::Workspace => workspace
+            .ok_or("The linked source is in a workspace, but no workspace is open")?
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve the workspace: {}", e))?,
+    };
+    let source = base
+        .join(relative)
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve linked source {}: {}", link.source, e))?;
+    if !source.starts_with(&base) || paths::is_protected(&source) {
+        return Err(format!(
+            "Access to linked source {} is not allowed",
+            source.display()
+        ));
+    }
+    Ok(source)
+}
+
+pub fn source_of(diagram: &Path) -> Result<Option<LinkedSource>, String> {
+    let sidecar = sidecar_path(diagram);
+    if !sidecar.is_file() {
+        return Ok(None);
+    }
+    let data =
+        fs::read_to_string(&sidecar).map_err(|e| format!("Failed to read linked source: {}", e))?;
+    serde_json::from_str(&data)
+        .map(Some)
+        .map_err(|e| format!("Invalid linked source: {}", e))
+}
+
+fn save(
+    diagram: &Path,
+    (source, base): (String, SourceBase),
+    generator: Generator,
+    content: &str,
+    warnings: Vec<String>,
+) -> Result<Regeneration, String> {
+    fs::write(diagram, content).map_err(|e| format!("Failed to write diagram: {}", e))?;
+    external::note_saved(diagram);
+
+    let link = LinkedSource {
+        source,
+        base,
+        generator,
+        generated_hash: hash(content),
+        generated_at: Utc::now().to_rfc3339(),
+    };
+    let data = serde_json::to_string_pretty(&link)
+        .map_err(|e| format!("Failed to save linked source: {}", e))?;
+    fs::write(sidecar_path(diagram), data)
+        .map_err(|e| format!("Failed to save linked source: {}", e))?;
+
+    Ok(Regeneration {
+        path: diagram.to_string_lossy().to_string(),
+        status: Status::Regenerated,
+        warnings,
+    })
+}
+
+// Generates `diagram` from `source` and remembers the link. Without a
+// generator, an importer is picked from the source's extension or content.
+pub fn link(
+    diagram: &Path,
+    source: &Path,
+    generator: Option<Generator>,
+    workspace: Option<&Path>,
+) -> Result<Regeneration, String> {
+    if encryption::is_encrypted_path(diagram) {
+        return Err("Encrypted diagrams cannot be linked to a source".to_string());
+    }
+    let relative = relative_source(diagram, source, workspace)?;
+    let generator = match generator {
+        Some(generator) => generator,
+        None => {
+            let text = fs::read_to_string(source)
+                .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+            importers::source_kind(source, &text)
+                .map(Generator::Import)
+                .ok_or_else(|| format!("Cannot generate a diagram from {}", source.display()))?
+        }
+    };
+    let (content, warnings) = generator.run(source)?;
+    save(diagram, relative, generator, &content, warnings)
+}
+
+// Generates the diagram again from its linked source. Unless `force` is
+// set, a diagram edited since it was last generated is not overwritten.
+pub fn regenerate(
+    diagram: &Path,
+    workspace: Option<&Path>,
+    force: bool,
+) -> Result<Regeneration, String> {
+    let link =
+        source_of(diagram)?.ok_or_else(|| format!("{} has no linked source", diagram.display()))?;
+    let source = resolve_source(diagram, &link, workspace)?;
+    let current = fs::read_to_string(diagram).unwrap_or_default();
+    let status = |status| Regeneration {
+        path: diagram.to_string_lossy().to_string(),
+        status,
+        warnings: Vec::new(),
+    };
+
+    if !force && hash(&current) != link.generated_hash {
+        return Ok(status(Status::ManualEdits));
+    }
+
+    let (content, warnings) = link.generator.run(&source)?;
+    if content == current {
+        return Ok(status(Status::Unchanged));
+    }
+    save(
+        diagram,
+        (link.source, link.base),
+        link.generator,
+        &content,
+        warnings,
+    )
+}
+
+// Forgets the link; the diagram itself stays as it is.
+pub fn unlink(diagram: &Path) -> Result<(), String> {
+    unwatch(diagram);
+    let sidecar = sidecar_path(diagram);
+    if sidecar.exists() {
+        fs::remove_file(&sidecar).map_err(|e| format!("Failed to remove linked source: {}", e))?;
+    }
+    Ok(())
+}
+
+pub fn watch(diagram: &Path, workspace: Option<&Path>) -> Result<(), String> {
+    let link =
+        source_of(diagram)?.ok_or_else(|| format!("{} has no linked source", diagram.display()))?;
+    let source = resolve_source(diagram, &link, workspace)?;
+    if let Ok(mut watched) = WATCHED.lock() {
+        watched.get_or_insert_with(HashMap::new).insert(
+            diagram.to_path_buf(),
+            Watched {
+                workspace: workspace.map(Path::to_path_buf),
+                seen: modified(&source),
+            },
+        );
+    }
+    Ok(())
+}
+
+pub fn unwatch(diagram: &Path) {
+    if let Ok(mut watched) = WATCHED.lock() {
+        if let Some(watched) = watched.as_mut() {
+            watched.remove(diagram);
+        }
+    }
+}
+
+pub fn spawn<F>(on_event: F) -> Result<LinkedWatchHandle, String>
+where
+    F: Fn(LinkedSourceEvent) + Send + 'static,
+{
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+
+    thread::Builder::new()
+        .name("linked-source-watch".to_string())
+        .spawn(move || run(&thread_stop, on_event))
+        .map_err(|e| format!("Failed to start linked source watcher: {}", e))?;
+
+    Ok(LinkedWatchHandle { stop })
+}
+
+fn run<F>(stop: &AtomicBool, on_event: F)
+where
+    F: Fn(LinkedSourceEvent),
+{
+    while !stop.load(Ordering::SeqCst) {
+        let mut changed = Vec::new();
+        if let Ok(mut watched) = WATCHED.lock() {
+            for (diagram, entry) in watched.iter_mut().flatten() {
+                let Ok(Some(link)) = source_of(diagram) else {
+                    continue;
+                };
+                let workspace = entry.workspace.as_deref();
+                let Ok(source) = resolve_source(diagram, &link, workspace) else {
+                    continue;
+                };
+                let current = modified(&source);
+                if current == entry.seen {
+                    continue;
+                }
+                entry.seen = current;
+                changed.push((diagram.clone(), entry.workspace.clone(), link.source));
+            }
+        }
+
+        // Regenerate outside the lock, since generating can take a while.
+        for (diagram, workspace, source) in changed {
+            let path = diagram.to_string_lossy().to_string();
+            let event = match regenerate(&diagram, workspace.as_deref(), false) {
+                Ok(Regeneration {
+                    status: Status::Regenerated,
+                    warnings,
+                    ..
+                }) => LinkedSourceEvent::Regenerated { path, warnings },
+                Ok(Regeneration {
+                    status: Status::ManualEdits,
+                    ..
+                }) => LinkedSourceEvent::ManualEdits { path, source },
+                Ok(_) => continue,
+                Err(error) => LinkedSourceEvent::Failed { path, error },
+            };
+            on_event(event);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
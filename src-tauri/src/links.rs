@@ -0,0 +1,283 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use reqwest::header::LOCATION;
+use reqwest::redirect::Policy;
+use reqwest::Url;
+
+use crate::diagrams;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// Redirects followed per link, each checked like the link itself.
+const MAX_REDIRECTS: usize = 5;
+
+// Links checked at the same time, so a large diagram does not open dozens
+// of connections at once.
+const CONCURRENT_REQUESTS: usize = 8;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkStatus {
+    // Well formed; reachable too when checked online.
+    Ok,
+    // Relative to wherever the SVG is embedded, so only the syntax is checked.
+    Relative,
+    Invalid,
+    // The server answered with an error status.
+    Dead,
+    // No answer: DNS failure, refused connection or timeout.
+    Unreachable,
+    // On this computer or a private network, so not requested.
+    Private,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LinkReport {
+    pub line: usize,
+    pub url: String,
+    pub status: LinkStatus,
+    pub message: Option<String>,
+}
+
+// Link targets as (line, url): `click` and `link` directives, the `links`
+// map of sequence actors and `$link` arguments of C4 elements. Callbacks
+// are not links and are skipped.
+pub fn extract(content: &str) -> Vec<(usize, String)> {
+    let click = Regex::new(r#"^(?:click|link)\s+\S+\s+(?:href\s+)?"([^"]*)""#).unwrap();
+    let actor_link = Regex::new(r"^link\s+[^:]+:[^@]*@\s*(\S+)\s*$").unwrap();
+    let actor_links = Regex::new(r"^links\s+[^:]+:\s*(\{.*\})\s*$").unwrap();
+    let c4_link = Regex::new(r#"\$link\s*=\s*"([^"]*)""#).unwrap();
+
+    let mut found = Vec::new();
+    for (number, line) in diagrams::statements(content) {
+        if let Some(caps) = click.captures(line) {
+            found.push((number, caps[1].trim().to_string()));
+        } else if let Some(caps) = actor_link.captures(line) {
+            found.push((number, caps[1].to_string()));
+        } else if let Some(caps) = actor_links.captures(line) {
+            match serde_json::from_str::<HashMap<String, String>>(&caps[1]) {
+                Ok(map) => {
+                    let mut urls: Vec<String> = map.into_values().collect();
+                    urls.sort();
+                    found.extend(urls.into_iter().map(|url| (number, url)));
+                }
+                // Kept so the malformed map shows up as an invalid link.
+                Err(_) => found.push((number, caps[1].to_string())),
+            }
+        } else {
+            for caps in c4_link.captures_iter(line) {
+                found.push((number, caps[1].trim().to_string()));
+            }
+        }
+    }
+    found
+}
+
+fn check_syntax(line: usize, url: String) -> LinkReport {
+    let (status, message) = match reqwest::Url::parse(&url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {
+            if parsed.host_str().is_some_and(|host| !host.is_empty()) {
+                (LinkStatus::Ok, None)
+            } else {
+                (
+                    LinkStatus::Invalid,
+                    Some("The link has no host".to_string()),
+                )
+            }
+        }
+        Ok(parsed) if matches!(parsed.scheme(), "mailto" | "tel") => (LinkStatus::Ok, None),
+        Ok(parsed) => (
+            LinkStatus::Invalid,
+            Some(format!("`{}:` links are not supported", parsed.scheme())),
+        ),
+        Err(_) if url.is_empty() => (LinkStatus::Invalid, Some("The link is empty".to_string())),
+        Err(_) if url.chars().any(|c| c.is_whitespace() || c.is_control()) => (
+            LinkStatus::Invalid,
+            Some("Spaces in links must be encoded as %20".to_string()),
+        ),
+        // Relative links resolve against any base.
+        Err(_)
+            if reqwest::Url::parse("http://localhost/")
+                .and_then(|base| base.join(&url))
+                .is_ok() =>
+        {
+            (LinkStatus::Relative, None)
+        }
+        Err(e) => (LinkStatus::Invalid, Some(format!("Invalid link: {}", e))),
+    };
+    LinkReport {
+        line,
+        url,
+        status,
+        message,
+    }
+}
+
+// Checks the syntax of every link target in the diagram.
+pub fn check(content: &str) -> Vec<LinkReport> {
+    extract(content)
+        .into_iter()
+        .map(|(line, url)| check_syntax(line, url))
+        .collect()
+}
+
+// Addresses a link from a shared diagram may make the app request: not
+// this computer, the local network or other reserved ranges.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || first == 0
+                // Shared address space used by carrier-grade NAT.
+                || (first == 100 && (second & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    // Unique local and link-local addresses.
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+// A client for `url` that only connects to the addresses checked here, so
+// the name cannot resolve somewhere else between the check and the request.
+// Redirects are followed by `probe`, which checks each hop the same way.
+async fn client_for(url: &Url) -> Result<reqwest::Client, (LinkStatus, String)> {
+    let unreachable = |e: String| (LinkStatus::Unreachable, e);
+    let port = url.port_or_known_default().unwrap_or(80);
+    let host = url
+        .host_str()
+        .ok_or_else(|| (LinkStatus::Invalid, "The link has no host".to_string()))?;
+    let builder = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .redirect(Policy::none());
+    // IPv6 hosts come in brackets.
+    let literal = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>();
+    let (builder, addresses) = match literal {
+        Ok(ip) => (builder, vec![SocketAddr::new(ip, port)]),
+        Err(_) => {
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+                .await
+                .map_err(|e| unreachable(format!("Failed to look up {}: {}", host, e)))?
+                .collect();
+            if addresses.is_empty() {
+                return Err(unreachable(format!("Failed to look up {}", host)));
+            }
+            (builder.resolve_to_addrs(host, &addresses), addresses)
+        }
+    };
+    if !addresses.iter().all(|address| is_public(address.ip())) {
+        return Err((
+            LinkStatus::Private,
+            "Points to this computer or a private network, not checked".to_string(),
+        ));
+    }
+    builder
+        .build()
+        .map_err(|e| unreachable(format!("Failed to create HTTP client: {}", e)))
+}
+
+// Some servers refuse HEAD, so a 405 or 501 is retried as a GET.
+async fn request(url: &Url) -> Result<reqwest::Response, (LinkStatus, String)> {
+    let client = client_for(url).await?;
+    let mut response = client.head(url.clone()).send().await;
+    if let Ok(head) = &response {
+        if matches!(head.status().as_u16(), 405 | 501) {
+            response = client.get(url.clone()).send().await;
+        }
+    }
+    response.map_err(|e| {
+        let message = if e.is_timeout() {
+            "The server did not answer in time".to_string()
+        } else {
+            format!("Failed to reach the server: {}", e)
+        };
+        (LinkStatus::Unreachable, message)
+    })
+}
+
+async fn probe(url: &str) -> Result<(), (LinkStatus, String)> {
+    let mut url =
+        Url::parse(url).map_err(|e| (LinkStatus::Invalid, format!("Invalid link: {}", e)))?;
+    for _ in 0..=MAX_REDIRECTS {
+        let response = request(&url).await?;
+        let status = response.status();
+        if status.is_redirection() && status.as_u16() != 304 {
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .ok_or_else(|| (LinkStatus::Dead, "Redirect without a target".to_string()))?;
+            url = url
+                .join(location)
+                .map_err(|e| (LinkStatus::Dead, format!("Invalid redirect: {}", e)))?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err((
+                    LinkStatus::Dead,
+                    format!("Redirects to an unsupported `{}:` link", url.scheme()),
+                ));
+            }
+            continue;
+        }
+        if status.is_client_error() || status.is_server_error() {
+            return Err((LinkStatus::Dead, format!("HTTP {}", status.as_u16())));
+        }
+        return Ok(());
+    }
+    Err((LinkStatus::Dead, "Too many redirects".to_string()))
+}
+
+// Requests every web link that passed the syntax check and marks the ones
+// that fail. Each distinct URL is only requested once.
+pub async fn check_online(reports: &mut [LinkReport]) -> Result<(), String> {
+    let mut urls: Vec<String> = reports
+        .iter()
+        .filter(|report| report.status == LinkStatus::Ok && report.url.starts_with("http"))
+        .map(|report| report.url.clone())
+        .collect();
+    urls.sort();
+    urls.dedup();
+
+    let mut results: HashMap<String, Result<(), (LinkStatus, String)>> = HashMap::new();
+    for batch in urls.chunks(CONCURRENT_REQUESTS) {
+        let mut tasks = tokio::task::JoinSet::new();
+        for url in batch {
+            let url = url.clone();
+            tasks.spawn(async move {
+                let result = probe(&url).await;
+                (url, result)
+            });
+        }
+        while let Some(joined) = tasks.join_next().await {
+            if let Ok((url, result)) = joined {
+                results.insert(url, result);
+            }
+        }
+    }
+
+    for report in reports.iter_mut() {
+        if let Some(Err((status, message))) = results.get(&report.url) {
+            report.status = *status;
+            report.message = Some(message.clone());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::Level;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+use crate::get_app_data_dir;
+
+const LOG_FILE_PREFIX: &str = "flowcraft";
+const LOG_FILE_SUFFIX: &str = "log";
+const MAX_LOG_FILES: usize = 7;
+const DEFAULT_LIMIT: usize = 200;
+
+// Flushes buffered lines when dropped, so it is held until the app exits.
+static GUARD: Mutex<Option<WorkerGuard>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    pub fields: serde_json::Map<String, Value>,
+}
+
+pub fn logs_dir() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("logs"))
+}
+
+// Writes JSON lines to a daily rotating file in the app data dir, keeping a
+// week of history. FLOWCRAFT_LOG overrides the filter, e.g. `debug`.
+pub fn init() -> Result<(), String> {
+    let dir = logs_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create logs directory: {}", e))?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| format!("Failed to open log file: {}", e))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let filter = EnvFilter::try_from_env("FLOWCRAFT_LOG")
+        .unwrap_or_else(|_| EnvFilter::new("info,flowcraft_studio_lib=debug"));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(
+            fmt::layer()
+                .json()
+                .with_current_span(false)
+                .with_span_list(false)
+                .with_writer(writer),
+        )
+        .with(fmt::layer().compact().with_writer(std::io::stderr))
+        .try_init()
+        .map_err(|e| format!("Failed to initialize logging: {}", e))?;
+
+    if let Ok(mut slot) = GUARD.lock() {
+        *slot = Some(guard);
+    }
+    Ok(())
+}
+
+pub fn shutdown() {
+    if let Ok(mut slot) = GUARD.lock() {
+        slot.take();
+    }
+}
+
+fn parse_level(level: &str) -> Option<Level> {
+    level.trim().parse().ok()
+}
+
+fn parse_entry(line: &str) -> Option<LogEntry> {
+    let mut value: serde_json::Map<String, Value> = serde_json::from_str(line).ok()?;
+    let mut fields = match value.remove("fields") {
+        Some(Value::Object(fields)) => fields,
+        _ => serde_json::Map::new(),
+    };
+    let message = match fields.remove("message") {
+        Some(Value::String(message)) => message,
+        Some(other) => other.to_string(),
+        None => String::new(),
+    };
+    let text = |value: Option<Value>| match value {
+        Some(Value::String(text)) => text,
+        _ => String::new(),
+    };
+
+    Some(LogEntry {
+        timestamp: text(value.remove("timestamp")),
+        level: text(value.remove("level")),
+        target: text(value.remove("target")),
+        message,
+        fields,
+    })
+}
+
+// Returns the newest entries at or above `level`, newest first.
+pub fn recent_logs(level: Option<&str>, limit: Option<usize>) -> Result<Vec<LogEntry>, String> {
+    let min_level = match level {
+        Some(level) => {
+            Some(parse_level(level).ok_or_else(|| format!("Unknown log level: {}", level))?)
+        }
+        None => None,
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+
+    let dir = logs_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    // Rotated files carry the date in their name, so name order is age order.
+    let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read logs directory: {}", e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(LOG_FILE_PREFIX))
+        })
+        .collect();
+    files.sort();
+
+    let mut entries = Vec::new();
+    for file in files.iter().rev() {
+        let content =
+            fs::read_to_string(file).map_err(|e| format!("Failed to read log file: {}", e))?;
+
+        for entry in content.lines().rev().filter_map(parse_entry) {
+            // Levels compare by verbosity: ERROR < WARN < INFO < DEBUG < TRACE.
+            let keep = match (min_level, parse_level(&entry.level)) {
+                (Some(min_level), Some(entry_level)) => entry_level <= min_level,
+                (Some(_), None) => false,
+                (None, _) => true,
+            };
+            if keep {
+                entries.push(entry);
+                if entries.len() >= limit {
+                    return Ok(entries);
+                }
+            }
+        }
+    }
+
+    Ok(entries)
+}
@@ -2,5 +2,9 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    if let Some(code) = flowcraft_studio_lib::cli::run_from_args(std::env::args().skip(1)) {
+        std::process::exit(code);
+    }
+
     flowcraft_studio_lib::run();
 }
\ No newline at end of file
@@ -0,0 +1,80 @@
+pub fn to_code_block(content: &str) -> String {
+    format!("```mermaid\n{}\n```\n", content.trim_end())
+}
+
+pub fn extract_code_blocks(markdown: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+
+        match current.take() {
+            Some((fence, body)) if trimmed.trim_end() == fence => {
+                blocks.push(body.join("\n"));
+            }
+            Some((fence, mut body)) => {
+                body.push(line);
+                current = Some((fence, body));
+            }
+            None => {
+                let fence_len = trimmed
+                    .chars()
+                    .take_while(|&c| c == '`' || c == '~')
+                    .count();
+                if fence_len < 3 {
+                    continue;
+                }
+                let (fence, info) = trimmed.split_at(fence_len);
+                if info.trim().eq_ignore_ascii_case("mermaid") {
+                    current = Some((fence.to_string(), Vec::new()));
+                }
+            }
+        }
+    }
+
+    blocks
+}
+
+// Sets the diagram title in the frontmatter block, creating the block when
+// the source does not have one yet.
+pub fn with_title(content: &str, title: &str) -> String {
+    let title_line = format!("title: {}", yaml_string(title));
+    let mut lines: Vec<&str> = content.lines().collect();
+
+    let has_frontmatter = lines.first().map(|l| l.trim()) == Some("---");
+    let closing = lines
+        .iter()
+        .skip(1)
+        .position(|l| l.trim() == "---")
+        .map(|i| i + 1);
+
+    match (has_frontmatter, closing) {
+        (true, Some(closing)) => {
+            match lines[1..closing]
+                .iter()
+                .position(|l| l.trim_start().starts_with("title:"))
+            {
+                Some(i) => lines[i + 1] = &title_line,
+                None => lines.insert(1, &title_line),
+            }
+            let mut result = lines.join("\n");
+            result.push('\n');
+            result
+        }
+        _ => format!("---\n{}\n---\n{}", title_line, content),
+    }
+}
+
+fn yaml_string(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value.contains([':', '#', '"', '\'', '[', ']', '{', '}', ','])
+        || value.starts_with(['-', '?', '!', '&', '*', '|', '>', '%', '@', '`', ' '])
+        || value.ends_with(' ');
+
+    if needs_quotes {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+const CURVES: [&str; 12] = [
+    "basis",
+    "bumpX",
+    "bumpY",
+    "cardinal",
+    "catmullRom",
+    "linear",
+    "monotoneX",
+    "monotoneY",
+    "natural",
+    "step",
+    "stepAfter",
+    "stepBefore",
+];
+
+const SECURITY_LEVELS: [&str; 4] = ["strict", "loose", "antiscript", "sandbox"];
+
+// User overrides for `mermaid.initialize`, layered on top of the active
+// theme. Unset fields keep Mermaid's or the theme's defaults.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MermaidConfig {
+    #[serde(default)]
+    pub theme_variables: BTreeMap<String, String>,
+    #[serde(default)]
+    pub flowchart_curve: Option<String>,
+    #[serde(default)]
+    pub security_level: Option<String>,
+    #[serde(default)]
+    pub font_family: Option<String>,
+}
+
+impl MermaidConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(curve) = &self.flowchart_curve {
+            if !CURVES.contains(&curve.as_str()) {
+                return Err(format!(
+                    "Unknown flowchart curve: {} (expected one of {})",
+                    curve,
+                    CURVES.join(", ")
+                ));
+            }
+        }
+        if let Some(level) = &self.security_level {
+            if !SECURITY_LEVELS.contains(&level.as_str()) {
+                return Err(format!(
+                    "Unknown security level: {} (expected one of {})",
+                    level,
+                    SECURITY_LEVELS.join(", ")
+                ));
+            }
+        }
+        // Values end up in the CSS Mermaid generates.
+        let values = self
+            .theme_variables
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .chain(
+                self.font_family
+                    .as_deref()
+                    .map(|font| ("font_family", font)),
+            );
+        for (key, value) in values {
+            if value.contains(['{', '}', ';', '<', '>']) {
+                return Err(format!("{} contains invalid characters", key));
+            }
+        }
+        Ok(())
+    }
+
+    // Merges the overrides into an init config such as a theme's.
+    pub fn apply(&self, config: &mut Value) {
+        if !config.is_object() {
+            *config = json!({});
+        }
+
+        if !self.theme_variables.is_empty() || self.font_family.is_some() {
+            let variables = &mut config["themeVariables"];
+            if !variables.is_object() {
+                *variables = json!({});
+            }
+            for (key, value) in &self.theme_variables {
+                variables[key] = json!(value);
+            }
+            if let Some(font) = &self.font_family {
+                variables["fontFamily"] = json!(font);
+            }
+        }
+        if let Some(font) = &self.font_family {
+            config["fontFamily"] = json!(font);
+        }
+        if let Some(level) = &self.security_level {
+            config["securityLevel"] = json!(level);
+        }
+        if let Some(curve) = &self.flowchart_curve {
+            let flowchart = &mut config["flowchart"];
+            if !flowchart.is_object() {
+                *flowchart = json!({});
+            }
+            flowchart["curve"] = json!(curve);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+
+const EDITOR_URL: &str = "https://mermaid.live";
+// Decompressing stops here, so a crafted link cannot exhaust memory.
+const MAX_STATE_BYTES: u64 = 1024 * 1024;
+
+// The editor state mermaid.live keeps in its URL. `mermaid` holds the
+// Mermaid config as a JSON string, or as an object in older links.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LiveState {
+    code: String,
+    #[serde(default)]
+    mermaid: Value,
+    #[serde(default)]
+    auto_sync: bool,
+    #[serde(default)]
+    update_diagram: bool,
+}
+
+pub fn is_live_url(text: &str) -> bool {
+    let text = text.trim();
+    (text.starts_with("https://") || text.starts_with("http://"))
+        && (text.contains("pako:") || text.contains("base64:"))
+        && (text.contains("mermaid.live") || text.contains("mermaid.ink"))
+}
+
+// Decodes the editor state embedded in mermaid.live and mermaid.ink links.
+// `pako:` payloads are zlib-compressed JSON, `base64:` payloads are plain JSON.
+pub fn decode_url(url: &str) -> Result<String, String> {
+    let url = url.trim();
+
+    let json = if let Some(index) = url.find("pako:") {
+        let compressed = decode_base64(payload(&url[index + "pako:".len()..]))?;
+        let mut json = Vec::new();
+        ZlibDecoder::new(compressed.as_slice())
+            .take(MAX_STATE_BYTES + 1)
+            .read_to_end(&mut json)
+            .map_err(|e| format!("Failed to decompress link: {}", e))?;
+        if json.len() as u64 > MAX_STATE_BYTES {
+            return Err("Linked diagram is too large".to_string());
+        }
+        String::from_utf8(json).map_err(|e| format!("Invalid link payload: {}", e))?
+    } else if let Some(index) = url.find("base64:") {
+        let bytes = decode_base64(payload(&url[index + "base64:".len()..]))?;
+        String::from_utf8(bytes).map_err(|e| format!("Invalid link payload: {}", e))?
+    } else {
+        return Err("Not a mermaid.live link".to_string());
+    };
+
+    let state: LiveState =
+        serde_json::from_str(&json).map_err(|e| format!("Invalid link payload: {}", e))?;
+    Ok(state.code)
+}
+
+// Builds a mermaid.live link in the `pako:` format the editor itself writes,
+// opening the editor or, with `view`, the read-only view.
+pub fn encode_url(code: &str, config: Option<&Value>, view: bool) -> Result<String, String> {
+    let config = config
+        .cloned()
+        .unwrap_or_else(|| json!({ "theme": "default" }));
+    let state = LiveState {
+        code: code.to_string(),
+        mermaid: Value::String(
+            serde_json::to_string_pretty(&config)
+                .map_err(|e| format!("Failed to encode link: {}", e))?,
+        ),
+        auto_sync: true,
+        update_diagram: true,
+    };
+    let json = serde_json::to_vec(&state).map_err(|e| format!("Failed to encode link: {}", e))?;
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder
+        .write_all(&json)
+        .map_err(|e| format!("Failed to compress link: {}", e))?;
+    let compressed = encoder
+        .finish()
+        .map_err(|e| format!("Failed to compress link: {}", e))?;
+
+    Ok(format!(
+        "{}/{}#pako:{}",
+        EDITOR_URL,
+        if view { "view" } else { "edit" },
+        URL_SAFE_NO_PAD.encode(compressed)
+    ))
+}
+
+fn payload(rest: &str) -> &str {
+    rest.split(['?', '&', '#']).next().unwrap_or_default()
+}
+
+// Links are produced with URL-safe base64 but older ones use the standard
+// alphabet, with or without padding; normalize before decoding.
+fn decode_base64(encoded: &str) -> Result<Vec<u8>, String> {
+    let normalized: String = encoded
+        .trim_end_matches('=')
+        .chars()
+        .map(|c| match c {
+            '+' => '-',
+            '/' => '_',
+            other => other,
+        })
+        .collect();
+
+    URL_SAFE_NO_PAD
+        .decode(normalized)
+        .or_else(|_| STANDARD.decode(encoded))
+        .map_err(|e| format!("Invalid link encoding: {}", e))
+}
@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{get_app_data_dir, kroki};
+
+const PACKAGE_JSON: &str = include_str!("../../package.json");
+const SCRIPT_NAME: &str = "mermaid.min.js";
+
+// The renderer the preview and exports should use. `custom_script` wins over
+// `version`; neither set means the version bundled with the app.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RendererConfig {
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub custom_script: Option<String>,
+    // Exports are rendered by this Kroki server instead of the Mermaid CLI,
+    // for machines that cannot run a headless browser.
+    #[serde(default)]
+    pub kroki_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionSource {
+    Bundled,
+    Installed,
+    Custom,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MermaidVersion {
+    pub version: String,
+    pub source: VersionSource,
+    pub script: Option<String>,
+    // Whether exports can use this version too, which needs a matching
+    // Mermaid CLI next to the script.
+    pub exports: bool,
+    pub selected: bool,
+}
+
+// Installed versions live in `<app data>/mermaid/<version>/`, holding a
+// `mermaid.min.js` and optionally a Mermaid CLI installed with
+// `npm install @mermaid-js/mermaid-cli` for exports.
+pub fn versions_dir() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("mermaid"))
+}
+
+pub fn bundled_version() -> String {
+    serde_json::from_str::<serde_json::Value>(PACKAGE_JSON)
+        .ok()
+        .and_then(|package| {
+            package["dependencies"]["mermaid"]
+                .as_str()
+                .map(|version| version.trim_start_matches(['^', '~', '=']).to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn mmdc_in(dir: &Path) -> Option<PathBuf> {
+    let names: &[&str] = if cfg!(windows) {
+        &["mmdc.cmd", "mmdc.exe"]
+    } else {
+        &["mmdc"]
+    };
+    let bin = dir.join("node_modules").join(".bin");
+
+    names
+        .iter()
+        .flat_map(|name| [dir.join(name), bin.join(name)])
+        .find(|path| path.is_file())
+}
+
+fn installed_versions() -> Vec<(String, PathBuf)> {
+    let Ok(entries) = versions_dir().and_then(|dir| {
+        fs::read_dir(dir).map_err(|e| format!("Failed to read Mermaid versions: {}", e))
+    }) else {
+        return Vec::new();
+    };
+
+    let mut versions: Vec<(String, PathBuf)> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|dir| dir.join(SCRIPT_NAME).is_file())
+        .filter_map(|dir| {
+            let version = dir.file_name()?.to_string_lossy().to_string();
+            Some((version, dir))
+        })
+        .collect();
+    // Newest first; names that are not semver sort after the rest.
+    versions.sort_by(|(a, _), (b, _)| {
+        match (semver::Version::parse(a), semver::Version::parse(b)) {
+            (Ok(a), Ok(b)) => b.cmp(&a),
+            (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+            (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+            (Err(_), Err(_)) => a.cmp(b),
+        }
+    });
+    versions
+}
+
+pub fn list(config: &RendererConfig) -> Vec<MermaidVersion> {
+    let bundled = bundled_version();
+    let mut versions = vec![MermaidVersion {
+        version: bundled.clone(),
+        source: VersionSource::Bundled,
+        script: None,
+        exports: true,
+        selected: config.custom_script.is_none()
+            && config.version.as_deref().unwrap_or(&bundled) == bundled,
+    }];
+
+    for (version, dir) in installed_versions() {
+        versions.push(MermaidVersion {
+            selected: config.custom_script.is_none() && config.version.as_ref() == Some(&version),
+            version,
+            source: VersionSource::Installed,
+            script: Some(dir.join(SCRIPT_NAME).to_string_lossy().to_string()),
+            exports: mmdc_in(&dir).is_some(),
+        });
+    }
+
+    if let Some(script) = &config.custom_script {
+        versions.push(MermaidVersion {
+            version: "custom".to_string(),
+            source: VersionSource::Custom,
+            script: Some(script.clone()),
+            exports: false,
+            selected: true,
+        });
+    }
+
+    versions
+}
+
+pub fn validate(config: &RendererConfig) -> Result<(), String> {
+    if let Some(script) = &config.custom_script {
+        let path = Path::new(script);
+        if !path.is_file() {
+            return Err(format!("Mermaid script not found: {}", script));
+        }
+        if path.extension().and_then(|ext| ext.to_str()) != Some("js") {
+            return Err("Custom Mermaid script must be a .js file".to_string());
+        }
+    }
+    if let Some(version) = &config.version {
+        let known =
+            *version == bundled_version() || installed_versions().iter().any(|(v, _)| v == version);
+        if !known {
+            return Err(format!("Mermaid {} is not installed", version));
+        }
+    }
+    if let Some(url) = &config.kroki_url {
+        kroki::validate_url(url)?;
+    }
+    Ok(())
+}
+
+// Script path for the preview to load instead of its bundled copy.
+pub fn script_path(config: &RendererConfig) -> Option<PathBuf> {
+    if let Some(script) = &config.custom_script {
+        return Some(PathBuf::from(script));
+    }
+    let version = config.version.as_ref()?;
+    installed_versions()
+        .into_iter()
+        .find(|(v, _)| v == version)
+        .map(|(_, dir)| dir.join(SCRIPT_NAME))
+}
+
+// Mermaid CLI to export with, when the selected version ships one.
+pub fn mmdc_path(config: &RendererConfig) -> Option<PathBuf> {
+    if config.custom_script.is_some() {
+        return None;
+    }
+    let version = config.version.as_ref()?;
+    installed_versions()
+        .into_iter()
+        .find(|(v, _)| v == version)
+        .and_then(|(_, dir)| mmdc_in(&dir))
+}
@@ -0,0 +1,102 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{get_app_data_dir, model};
+
+// One entry per day is kept, so this covers a few years of edits.
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MetricsEntry {
+    // The day of the entry; a later save that day replaces it.
+    pub date: String,
+    pub recorded_at: String,
+    pub diagram_type: Option<String>,
+    pub nodes: usize,
+    pub edges: usize,
+    pub lines: usize,
+    pub size: usize,
+}
+
+// Kept in the app data folder under a hash of the diagram's path, so saving
+// does not leave a file next to the diagram. Only the folder is resolved, so
+// the key is the same whether or not the file exists yet.
+fn store_path(diagram: &Path) -> Result<PathBuf, String> {
+    let resolved = match (diagram.parent(), diagram.file_name()) {
+        (Some(dir), Some(name)) => dir
+            .canonicalize()
+            .map(|dir| dir.join(name))
+            .unwrap_or_else(|_| diagram.to_path_buf()),
+        _ => diagram.to_path_buf(),
+    };
+    // A stable hash, unlike the standard library's, since the names persist.
+    let name: String = Sha256::digest(resolved.to_string_lossy().as_bytes())
+        .iter()
+        .take(16)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    Ok(get_app_data_dir()?
+        .join("metrics")
+        .join(format!("{}.json", name)))
+}
+
+// Oldest first.
+pub fn get(diagram: &Path) -> Result<Vec<MetricsEntry>, String> {
+    let path = store_path(diagram)?;
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let data =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read metrics history: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Invalid metrics history: {}", e))
+}
+
+// Keeps the history with the diagram when it is renamed.
+pub fn rename(from: &Path, to: &Path) -> Result<(), String> {
+    let source = store_path(from)?;
+    if !source.is_file() {
+        return Ok(());
+    }
+    fs::rename(source, store_path(to)?)
+        .map_err(|e| format!("Failed to move metrics history: {}", e))
+}
+
+pub fn measure(content: &str) -> MetricsEntry {
+    let parsed = model::parse_cached(content);
+    let now = Utc::now();
+    MetricsEntry {
+        date: now.format("%Y-%m-%d").to_string(),
+        recorded_at: now.to_rfc3339(),
+        diagram_type: parsed.model.diagram_type.clone(),
+        nodes: parsed.nodes.len(),
+        edges: parsed.edges.len(),
+        lines: content.lines().count(),
+        size: content.len(),
+    }
+}
+
+// Adds the saved content's stats to the diagram's history. A history that
+// cannot be read is started over rather than blocking the save.
+pub fn record(diagram: &Path, content: &str) -> Result<(), String> {
+    let mut history = get(diagram).unwrap_or_else(|e| {
+        tracing::warn!(path = %diagram.display(), error = %e, "starting a new metrics history");
+        Vec::new()
+    });
+    let entry = measure(content);
+    if history.last().map(|last| &last.date) == Some(&entry.date) {
+        history.pop();
+    }
+    history.push(entry);
+    history.drain(..history.len().saturating_sub(MAX_ENTRIES));
+
+    let path = store_path(diagram)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create data folder: {}", e))?;
+    }
+    let data = serde_json::to_string_pretty(&history)
+        .map_err(|e| format!("Failed to save metrics history: {}", e))?;
+    fs::write(path, data).map_err(|e| format!("Failed to save metrics history: {}", e))
+}
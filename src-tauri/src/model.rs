@@ -0,0 +1,406 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+use crate::detect_diagram_type;
+
+// A lightweight, line-preserving model of a diagram. Statements are kept as
+// written so edits round-trip without reformatting; nodes and edges are
+// derived on demand for flowcharts and sequence diagrams.
+#[derive(Debug, Clone, Default)]
+pub struct DiagramModel {
+    pub diagram_type: Option<String>,
+    pub lines: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub id: String,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub label: Option<String>,
+    pub line: usize,
+}
+
+// A diagram parsed once and shared by every caller asking about the same
+// content, so validation, analysis and export do not each re-parse it on
+// every keystroke.
+#[derive(Debug)]
+pub struct ParsedDiagram {
+    pub model: DiagramModel,
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+// Recently parsed diagrams, most recent first, keyed by content hash.
+const CACHE_SIZE: usize = 32;
+type CacheEntries = VecDeque<(u64, Arc<ParsedDiagram>)>;
+static CACHE: Mutex<Option<CacheEntries>> = Mutex::new(None);
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Returns the parsed form of `content`, from the cache when the same
+// content was parsed recently.
+pub fn parse_cached(content: &str) -> Arc<ParsedDiagram> {
+    let hash = content_hash(content);
+
+    if let Ok(mut cache) = CACHE.lock() {
+        let entries = cache.get_or_insert_with(VecDeque::new);
+        // Compare the lines too, so a hash collision cannot return another
+        // diagram.
+        let hit = entries.iter().position(|(key, parsed)| {
+            *key == hash
+                && content
+                    .lines()
+                    .eq(parsed.model.lines.iter().map(String::as_str))
+        });
+        if let Some(index) = hit {
+            if let Some(entry) = entries.remove(index) {
+                let parsed = Arc::clone(&entry.1);
+                entries.push_front(entry);
+                return parsed;
+            }
+        }
+    }
+
+    // Parsing happens outside the lock; two callers racing on new content
+    // both parse it, which is harmless.
+    let model = DiagramModel::parse(content);
+    let parsed = Arc::new(ParsedDiagram {
+        nodes: model.nodes(),
+        edges: model.edges(),
+        model,
+    });
+
+    if let Ok(mut cache) = CACHE.lock() {
+        let entries = cache.get_or_insert_with(VecDeque::new);
+        entries.retain(|(key, _)| *key != hash);
+        entries.push_front((hash, Arc::clone(&parsed)));
+        entries.truncate(CACHE_SIZE);
+    }
+    parsed
+}
+
+// `A -- text --> B` comes first, as the plain arrow would match its `--`.
+const FLOWCHART_ARROW: &str =
+    r"\s*--\s+([^->|]+?)\s+-->\s*|\s*(?:<?(?:-{2,}|={2,}|-\.+-?)[>ox]?|~~~)\s*(?:\|([^|]*)\|)?\s*";
+const SEQUENCE_MESSAGE: &str =
+    r"^\s*([^\s:+\-<>()]+)\s*(?:<<)?(?:-{1,2}(?:>>|>|x|\)))\s*[+-]?([^\s:]+)\s*(?::\s*(.*))?$";
+const SEQUENCE_PARTICIPANT: &str = r"^\s*(?:participant|actor)\s+(\S+)(?:\s+as\s+(.+))?$";
+
+impl DiagramModel {
+    pub fn parse(content: &str) -> Self {
+        Self {
+            diagram_type: detect_diagram_type(content).map(str::to_string),
+            lines: content.lines().map(str::to_string).collect(),
+        }
+    }
+
+    pub fn to_source(&self) -> String {
+        let mut source = self.lines.join("\n");
+        source.push('\n');
+        source
+    }
+
+    fn is_flowchart(&self) -> bool {
+        matches!(
+            self.diagram_type.as_deref(),
+            Some("graph") | Some("flowchart")
+        )
+    }
+
+    fn is_sequence(&self) -> bool {
+        self.diagram_type.as_deref() == Some("sequencediagram")
+    }
+
+    // Lines that carry statements: no header, comments, directives or frontmatter.
+    fn statements(&self) -> impl Iterator<Item = (usize, &str)> {
+        let mut in_frontmatter = false;
+        let mut seen_header = false;
+
+        self.lines
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, line)| {
+                let trimmed = line.trim();
+                if trimmed == "---" && !seen_header {
+                    in_frontmatter = !in_frontmatter;
+                    return None;
+                }
+                if in_frontmatter || trimmed.is_empty() || trimmed.starts_with("%%") {
+                    return None;
+                }
+                if !seen_header {
+                    seen_header = true;
+                    return None;
+                }
+                Some((index, trimmed))
+            })
+    }
+
+    pub fn nodes(&self) -> Vec<Node> {
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut add = |node: Node| match nodes.iter_mut().find(|n| n.id == node.id) {
+            Some(existing) => {
+                if existing.label.is_none() {
+                    existing.label = node.label;
+                }
+            }
+            None => nodes.push(node),
+        };
+
+        if self.is_flowchart() {
+            let arrow = Regex::new(FLOWCHART_ARROW).unwrap();
+            for (_, statement) in self.statements() {
+                if is_flowchart_keyword(statement) {
+                    continue;
+                }
+                for part in arrow.split(statement) {
+                    for expression in part.split(" & ") {
+                        if let Some(node) = parse_node_expression(expression) {
+                            add(node);
+                        }
+                    }
+                }
+            }
+        } else if self.is_sequence() {
+            let participant = Regex::new(SEQUENCE_PARTICIPANT).unwrap();
+            let message = Regex::new(SEQUENCE_MESSAGE).unwrap();
+            for (_, statement) in self.statements() {
+                if let Some(caps) = participant.captures(statement) {
+                    add(Node {
+                        id: caps[1].to_string(),
+                        label: caps.get(2).map(|m| m.as_str().trim().to_string()),
+                    });
+                } else if let Some(caps) = message.captures(statement) {
+                    for id in [&caps[1], &caps[2]] {
+                        add(Node {
+                            id: id.to_string(),
+                            label: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        nodes
+    }
+
+    pub fn edges(&self) -> Vec<Edge> {
+        let mut edges = Vec::new();
+
+        if self.is_flowchart() {
+            let arrow = Regex::new(FLOWCHART_ARROW).unwrap();
+            for (line, statement) in self.statements() {
+                if is_flowchart_keyword(statement) {
+                    continue;
+                }
+
+                let labels: Vec<Option<String>> = arrow
+                    .captures_iter(statement)
+                    .map(|caps| {
+                        caps.get(1)
+                            .or_else(|| caps.get(2))
+                            .map(|m| unquote(m.as_str().trim()).to_string())
+                    })
+                    .collect();
+                let groups: Vec<Vec<String>> = arrow
+                    .split(statement)
+                    .map(|part| {
+                        part.split(" & ")
+                            .filter_map(parse_node_expression)
+                            .map(|node| node.id)
+                            .collect()
+                    })
+                    .collect();
+
+                for (index, label) in labels.iter().enumerate() {
+                    let (Some(sources), Some(targets)) = (groups.get(index), groups.get(index + 1))
+                    else {
+                        continue;
+                    };
+                    for from in sources {
+                        for to in targets {
+                            edges.push(Edge {
+                                from: from.clone(),
+                                to: to.clone(),
+                                label: label.clone(),
+                                line,
+                            });
+                        }
+                    }
+                }
+            }
+        } else if self.is_sequence() {
+            let message = Regex::new(SEQUENCE_MESSAGE).unwrap();
+            for (line, statement) in self.statements() {
+                if let Some(caps) = message.captures(statement) {
+                    edges.push(Edge {
+                        from: caps[1].to_string(),
+                        to: caps[2].to_string(),
+                        label: caps.get(3).map(|m| m.as_str().trim().to_string()),
+                        line,
+                    });
+                }
+            }
+        }
+
+        edges
+    }
+
+    // Renames an identifier wherever it appears outside labels and quoted
+    // text. Returns the number of replacements.
+    pub fn rename_node(&mut self, old: &str, new: &str) -> usize {
+        if old.is_empty() || old == new {
+            return 0;
+        }
+
+        let statement_lines: Vec<usize> = self.statements().map(|(index, _)| index).collect();
+        let label_after_colon = self.is_sequence();
+        let mut count = 0;
+
+        for index in statement_lines {
+            let (renamed, replaced) =
+                rename_identifiers(&self.lines[index], old, new, label_after_colon);
+            if replaced > 0 {
+                self.lines[index] = renamed;
+                count += replaced;
+            }
+        }
+
+        count
+    }
+
+    // Plain text replacement across statements, labels included.
+    pub fn replace_text(&mut self, old: &str, new: &str) -> usize {
+        if old.is_empty() {
+            return 0;
+        }
+
+        let statement_lines: Vec<usize> = self.statements().map(|(index, _)| index).collect();
+        let mut count = 0;
+
+        for index in statement_lines {
+            let occurrences = self.lines[index].matches(old).count();
+            if occurrences > 0 {
+                self.lines[index] = self.lines[index].replace(old, new);
+                count += occurrences;
+            }
+        }
+
+        count
+    }
+}
+
+fn is_flowchart_keyword(statement: &str) -> bool {
+    let keyword = statement.split_whitespace().next().unwrap_or_default();
+    matches!(
+        keyword,
+        "subgraph" | "end" | "direction" | "classDef" | "class" | "style" | "linkStyle" | "click"
+    )
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn parse_node_expression(expression: &str) -> Option<Node> {
+    let expression = expression.trim().trim_end_matches(';');
+    let id_len = expression
+        .char_indices()
+        .find(|(_, c)| !(c.is_alphanumeric() || *c == '_' || *c == '-' || *c == '.'))
+        .map(|(index, _)| index)
+        .unwrap_or(expression.len());
+    if id_len == 0 {
+        return None;
+    }
+
+    let (id, rest) = expression.split_at(id_len);
+    let rest = rest.trim();
+    let rest = match rest.find(":::") {
+        Some(index) => rest[..index].trim(),
+        None => rest,
+    };
+
+    let label = if rest.is_empty() {
+        None
+    } else {
+        let inner = rest
+            .trim_start_matches(['[', '(', '{', '>', '/', '\\'])
+            .trim_end_matches([']', ')', '}', '/', '\\']);
+        Some(unquote(inner.trim()).to_string())
+    };
+
+    Some(Node {
+        id: id.to_string(),
+        label,
+    })
+}
+
+fn rename_identifiers(
+    line: &str,
+    old: &str,
+    new: &str,
+    label_after_colon: bool,
+) -> (String, usize) {
+    let mut result = String::with_capacity(line.len());
+    let mut count = 0;
+    let mut depth = 0usize;
+    let mut in_quotes = false;
+    let mut in_pipe_label = false;
+    let mut word = String::new();
+
+    let flush = |word: &mut String, result: &mut String, count: &mut usize, active: bool| {
+        if active && word == old {
+            result.push_str(new);
+            *count += 1;
+        } else {
+            result.push_str(word);
+        }
+        word.clear();
+    };
+
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let active = depth == 0 && !in_quotes && !in_pipe_label;
+
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        flush(&mut word, &mut result, &mut count, active);
+
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '[' | '(' | '{' if !in_quotes => depth += 1,
+            ']' | ')' | '}' if !in_quotes => depth = depth.saturating_sub(1),
+            '|' if !in_quotes && depth == 0 => in_pipe_label = !in_pipe_label,
+            ':' if label_after_colon && active => {
+                result.push(c);
+                result.extend(chars.by_ref());
+                return (result, count);
+            }
+            _ => {}
+        }
+        result.push(c);
+    }
+
+    let active = depth == 0 && !in_quotes && !in_pipe_label;
+    flush(&mut word, &mut result, &mut count, active);
+    (result, count)
+}
@@ -0,0 +1,53 @@
+use std::path::Path;
+
+// Mirrors the in-app recent files into the OS lists: the Windows jump list
+// (through the shell's recent documents) and the macOS Open Recent menu.
+// Picking an entry there launches or activates the app with the file, which
+// arrives through the launch arguments, the single-instance callback or
+// RunEvent::Opened like any other "Open with" request.
+#[cfg(windows)]
+pub fn note_recent_document(_app_handle: &tauri::AppHandle, path: &Path) {
+    use std::ffi::c_void;
+    use std::os::windows::ffi::OsStrExt;
+
+    const SHARD_PATHW: u32 = 0x00000003;
+
+    #[link(name = "shell32")]
+    extern "system" {
+        fn SHAddToRecentDocs(flags: u32, pv: *const c_void);
+    }
+
+    let wide: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+
+    unsafe {
+        SHAddToRecentDocs(SHARD_PATHW, wide.as_ptr().cast());
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn note_recent_document(app_handle: &tauri::AppHandle, path: &Path) {
+    use objc2::MainThreadMarker;
+    use objc2_app_kit::NSDocumentController;
+    use objc2_foundation::{NSString, NSURL};
+
+    let path = path.to_string_lossy().to_string();
+
+    // NSDocumentController may only be touched from the main thread.
+    let _ = app_handle.run_on_main_thread(move || {
+        let Some(mtm) = MainThreadMarker::new() else {
+            return;
+        };
+
+        unsafe {
+            let url = NSURL::fileURLWithPath(&NSString::from_str(&path));
+            NSDocumentController::sharedDocumentController(mtm).noteNewRecentDocumentURL(&url);
+        }
+    });
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn note_recent_document(_app_handle: &tauri::AppHandle, _path: &Path) {}
@@ -0,0 +1,226 @@
+use std::path::{Component, Path, PathBuf};
+use tauri_plugin_fs::FsExt;
+
+use crate::get_app_data_dir;
+
+#[cfg(unix)]
+const SYSTEM_DIRS: [&str; 16] = [
+    "/bin",
+    "/boot",
+    "/dev",
+    "/etc",
+    "/lib",
+    "/lib64",
+    "/proc",
+    "/sbin",
+    "/sys",
+    "/usr",
+    "/var",
+    "/Applications",
+    "/Library",
+    "/System",
+    "/private/etc",
+    "/private/var/db",
+];
+
+// Places under the home directory that hold credentials or run code at
+// login.
+const SENSITIVE_HOME_PATHS: [&str; 11] = [
+    ".ssh",
+    ".gnupg",
+    ".aws",
+    ".kube",
+    ".config/autostart",
+    ".bashrc",
+    ".bash_profile",
+    ".zshrc",
+    ".profile",
+    "Library/LaunchAgents",
+    "AppData/Roaming/Microsoft/Windows/Start Menu",
+];
+
+fn system_dirs() -> Vec<PathBuf> {
+    #[cfg(unix)]
+    let dirs: Vec<PathBuf> = SYSTEM_DIRS.iter().map(PathBuf::from).collect();
+    #[cfg(windows)]
+    let dirs: Vec<PathBuf> = [
+        "SystemRoot",
+        "ProgramFiles",
+        "ProgramFiles(x86)",
+        "ProgramData",
+    ]
+    .iter()
+    .filter_map(|name| std::env::var_os(name).map(PathBuf::from))
+    .collect();
+    #[cfg(not(any(unix, windows)))]
+    let dirs: Vec<PathBuf> = Vec::new();
+    dirs
+}
+
+// Locations the app never reads or writes on the frontend's behalf: system
+// directories, credentials and the app's own data, which holds settings a
+// compromised webview could otherwise rewrite.
+fn protected_paths() -> Vec<PathBuf> {
+    let mut paths = system_dirs();
+    if let Some(home) = dirs::home_dir() {
+        paths.extend(SENSITIVE_HOME_PATHS.iter().map(|path| home.join(path)));
+    }
+    if let Ok(data_dir) = get_app_data_dir() {
+        paths.push(data_dir);
+    }
+    // Compare canonical forms, since `resolve` returns those.
+    paths
+        .into_iter()
+        .map(|path| path.canonicalize().unwrap_or(path))
+        .collect()
+}
+
+pub fn is_protected(path: &Path) -> bool {
+    protected_paths()
+        .iter()
+        .any(|protected| path.starts_with(protected))
+}
+
+// Turns a path from the frontend into an absolute, symlink-free one.
+// Relative paths and `..` are refused outright. Files that do not exist
+// yet are resolved through their parent directory.
+pub fn resolve(path: &str) -> Result<PathBuf, String> {
+    let path = Path::new(path);
+    if !path.is_absolute() {
+        return Err(format!("Path must be absolute: {}", path.display()));
+    }
+    if path
+        .components()
+        .any(|component| component == Component::ParentDir)
+    {
+        return Err(format!("Path must not contain '..': {}", path.display()));
+    }
+
+    if let Ok(resolved) = path.canonicalize() {
+        return Ok(resolved);
+    }
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(format!("Invalid path: {}", path.display()));
+    };
+    let parent = parent
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", parent.display(), e))?;
+    Ok(parent.join(name))
+}
+
+// Resolves a path the frontend passed in and makes sure the user granted
+// access to it, by picking it in a dialog, opening it from the OS or
+// choosing a folder that contains it.
+pub fn check(app_handle: &tauri::AppHandle, path: &str) -> Result<PathBuf, String> {
+    let resolved = resolve(path)?;
+    if is_protected(&resolved) {
+        return Err(format!("Access to {} is not allowed", resolved.display()));
+    }
+    if !app_handle.fs_scope().is_allowed(&resolved) {
+        return Err(format!(
+            "Access to {} has not been granted; choose it in a file dialog first",
+            resolved.display()
+        ));
+    }
+    Ok(resolved)
+}
+
+// Adds a file the user chose to the fs scope, for this app session.
+pub fn grant_file(app_handle: &tauri::AppHandle, path: &Path) {
+    let resolved = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if is_protected(&resolved) {
+        return;
+    }
+    if let Err(e) = app_handle.fs_scope().allow_file(&resolved) {
+        tracing::warn!(path = %resolved.display(), error = %e, "failed to extend file scope");
+    }
+}
+
+// Adds a folder the user chose, with everything below it.
+pub fn grant_directory(app_handle: &tauri::AppHandle, path: &Path) {
+    let resolved = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if is_protected(&resolved) {
+        return;
+    }
+    if let Err(e) = app_handle.fs_scope().allow_directory(&resolved, true) {
+        tracing::warn!(path = %resolved.display(), error = %e, "failed to extend file scope");
+    }
+}
+
+// Forbids the protected locations in the fs scope too, so the frontend's
+// own fs calls cannot reach them either.
+pub fn forbid_protected(app_handle: &tauri::AppHandle) {
+    let scope = app_handle.fs_scope();
+    for path in protected_paths() {
+        let result = if path.is_dir() {
+            scope.forbid_directory(&path, true)
+        } else {
+            scope.forbid_file(&path)
+        };
+        if let Err(e) = result {
+            tracing::warn!(path = %path.display(), error = %e, "failed to restrict file scope");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // A fresh folder under the temp dir, in its canonical form.
+    fn scratch(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("flowcraft-paths-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn refuses_relative_paths_and_parent_components() {
+        assert!(resolve("diagrams/flow.mmd").is_err());
+        let dir = scratch("parent");
+        let escaping = format!("{}/../flow.mmd", dir.display());
+        assert!(resolve(&escaping).unwrap_err().contains(".."));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn resolves_new_files_through_their_folder() {
+        let dir = scratch("new");
+        let path = dir.join("flow.mmd");
+        assert_eq!(resolve(path.to_str().unwrap()).unwrap(), path);
+        let missing = dir.join("missing").join("flow.mmd");
+        assert!(resolve(missing.to_str().unwrap()).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follows_symlinks_before_checking() {
+        let dir = scratch("links");
+        let link = dir.join("settings");
+        std::os::unix::fs::symlink("/etc", &link).unwrap();
+        let resolved = resolve(link.join("hosts").to_str().unwrap()).unwrap();
+        assert!(resolved.starts_with(Path::new("/etc").canonicalize().unwrap()));
+        assert!(is_protected(&resolved));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn protects_system_folders_and_credentials() {
+        assert!(is_protected(&resolve("/etc/passwd").unwrap()));
+        assert!(is_protected(
+            &get_app_data_dir().unwrap().join("state.json")
+        ));
+        if let Some(home) = dirs::home_dir() {
+            let home = home.canonicalize().unwrap_or(home);
+            assert!(is_protected(&home.join(".ssh").join("id_ed25519")));
+            assert!(!is_protected(&home.join("Documents").join("flow.mmd")));
+            // Only whole components count.
+            assert!(!is_protected(&home.join(".sshfs").join("flow.mmd")));
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+// PDF units per CSS pixel (72 pt per inch, 96 px per inch).
+pub const PT_PER_PX: f64 = 0.75;
+
+// The name pages use to draw the image, e.g. `/Im0 Do`.
+pub const IMAGE_NAME: &str = "Im0";
+
+// Helvetica is one of the standard fonts every viewer has, so page labels
+// need no embedded font.
+const FONT_NAME: &str = "F1";
+
+pub struct PdfImage {
+    pub width: u32,
+    pub height: u32,
+    rgb: Vec<u8>,
+    alpha: Option<Vec<u8>>,
+}
+
+pub struct PdfPage {
+    pub width: f64,
+    pub height: f64,
+    pub content: String,
+}
+
+impl PdfImage {
+    pub fn from_png(bytes: &[u8]) -> Result<Self, String> {
+        let image = image::load_from_memory(bytes)
+            .map_err(|e| format!("Failed to decode rendered image: {}", e))?
+            .to_rgba8();
+        let (width, height) = image.dimensions();
+
+        let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+        let mut alpha = Vec::with_capacity((width * height) as usize);
+        for pixel in image.pixels() {
+            rgb.extend_from_slice(&pixel.0[..3]);
+            alpha.push(pixel.0[3]);
+        }
+        let opaque = alpha.iter().all(|a| *a == u8::MAX);
+
+        Ok(Self {
+            width,
+            height,
+            rgb,
+            alpha: (!opaque).then_some(alpha),
+        })
+    }
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // Writing to a Vec cannot fail.
+    let _ = encoder.write_all(data);
+    encoder.finish().unwrap_or_default()
+}
+
+struct Writer {
+    out: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl Writer {
+    fn object(&mut self, id: usize, dictionary: &str, stream: Option<&[u8]>) {
+        // Objects are written in id order, starting at 1.
+        self.offsets.push(self.out.len());
+        self.out
+            .extend_from_slice(format!("{} 0 obj\n{}\n", id, dictionary).as_bytes());
+        if let Some(stream) = stream {
+            self.out.extend_from_slice(b"stream\n");
+            self.out.extend_from_slice(stream);
+            self.out.extend_from_slice(b"\nendstream\n");
+        }
+        self.out.extend_from_slice(b"endobj\n");
+    }
+}
+
+pub fn format_number(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+// Writes a document whose pages all draw from one shared raster image, so
+// tiled pages do not repeat the image data.
+pub fn write(image: &PdfImage, pages: &[PdfPage]) -> Vec<u8> {
+    let mut writer = Writer {
+        out: b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec(),
+        offsets: Vec::new(),
+    };
+
+    let font_id = 3;
+    let image_id = 4;
+    let mask_id = image.alpha.as_ref().map(|_| 5);
+    let first_page_id = if mask_id.is_some() { 6 } else { 5 };
+    let page_ids: Vec<usize> = (0..pages.len())
+        .map(|index| first_page_id + index * 2)
+        .collect();
+
+    writer.object(1, "<< /Type /Catalog /Pages 2 0 R >>", None);
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+    writer.object(
+        2,
+        &format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        ),
+        None,
+    );
+
+    writer.object(
+        font_id,
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>",
+        None,
+    );
+
+    let rgb = deflate(&image.rgb);
+    let smask = mask_id
+        .map(|id| format!(" /SMask {} 0 R", id))
+        .unwrap_or_default();
+    writer.object(
+        image_id,
+        &format!(
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode /Length {}{} >>",
+            image.width,
+            image.height,
+            rgb.len(),
+            smask
+        ),
+        Some(&rgb),
+    );
+    if let (Some(id), Some(alpha)) = (mask_id, &image.alpha) {
+        let alpha = deflate(alpha);
+        writer.object(
+            id,
+            &format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>",
+                image.width,
+                image.height,
+                alpha.len()
+            ),
+            Some(&alpha),
+        );
+    }
+
+    for (page, id) in pages.iter().zip(&page_ids) {
+        let content = deflate(page.content.as_bytes());
+        writer.object(
+            *id,
+            &format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /XObject << /{} {} 0 R >> /Font << /{} {} 0 R >> >> /Contents {} 0 R >>",
+                format_number(page.width),
+                format_number(page.height),
+                IMAGE_NAME,
+                image_id,
+                FONT_NAME,
+                font_id,
+                id + 1
+            ),
+            None,
+        );
+        writer.object(
+            id + 1,
+            &format!("<< /Filter /FlateDecode /Length {} >>", content.len()),
+            Some(&content),
+        );
+    }
+
+    let xref_offset = writer.out.len();
+    let mut xref = format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        writer.offsets.len() + 1
+    );
+    for offset in &writer.offsets {
+        xref.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    xref.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        writer.offsets.len() + 1,
+        xref_offset
+    ));
+    writer.out.extend_from_slice(xref.as_bytes());
+    writer.out
+}
+
+// Content stream operators that draw the image with its lower left corner at
+// (x, y) and the given size, all in points.
+pub fn draw_image(x: f64, y: f64, width: f64, height: f64) -> String {
+    format!(
+        "q {} 0 0 {} {} {} cm /{} Do Q\n",
+        format_number(width),
+        format_number(height),
+        format_number(x),
+        format_number(y),
+        IMAGE_NAME
+    )
+}
+
+// Draws the image like `draw_image`, but only the part inside the clip
+// rectangle shows.
+pub fn draw_image_clipped(
+    clip: (f64, f64, f64, f64),
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+) -> String {
+    format!(
+        "q {} {} {} {} re W n {}Q\n",
+        format_number(clip.0),
+        format_number(clip.1),
+        format_number(clip.2),
+        format_number(clip.3),
+        draw_image(x, y, width, height)
+    )
+}
+
+// A thin grey line, dashed when `dashed` is set.
+pub fn line(from: (f64, f64), to: (f64, f64), dashed: bool) -> String {
+    format!(
+        "q 0.5 G 0.5 w {}{} {} m {} {} l S Q\n",
+        if dashed { "[3 2] 0 d " } else { "" },
+        format_number(from.0),
+        format_number(from.1),
+        format_number(to.0),
+        format_number(to.1)
+    )
+}
+
+// Small grey Helvetica text with its baseline starting at (x, y). Only
+// ASCII is kept, which is all page labels need.
+pub fn text(x: f64, y: f64, size: f64, text: &str) -> String {
+    let escaped: String = text
+        .chars()
+        .filter(|c| c.is_ascii() && !c.is_ascii_control())
+        .flat_map(|c| match c {
+            '(' | ')' | '\\' => vec!['\\', c],
+            _ => vec![c],
+        })
+        .collect();
+    format!(
+        "q 0.4 g BT /{} {} Tf {} {} Td ({}) Tj ET Q\n",
+        FONT_NAME,
+        format_number(size),
+        format_number(x),
+        format_number(y),
+        escaped
+    )
+}
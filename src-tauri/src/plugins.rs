@@ -0,0 +1,340 @@
+// WASM plugin host.
+//
+// A plugin is a `.wasm` file in the plugins directory exporting:
+//
+//   memory                                  linear memory
+//   flowcraft_alloc(len: i32) -> i32        allocate `len` bytes for the host
+//   flowcraft_manifest() -> i64             JSON `PluginManifest`
+//   flowcraft_call(ptr: i32, len: i32) -> i64
+//                                           JSON `PluginRequest` in,
+//                                           JSON `PluginResponse` out
+//
+// Strings returned to the host are packed as `(ptr << 32) | len`. Plugins
+// get no imports, so they cannot touch the file system or network, every
+// call runs with a fuel budget so a misbehaving plugin cannot hang the app,
+// and the store caps how far it can grow its memory and tables.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use wasmi::{
+    Config, Engine, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+use crate::get_app_data_dir;
+
+pub const ABI_VERSION: u32 = 1;
+
+const FUEL_PER_CALL: u64 = 500_000_000;
+const MAX_MEMORY_BYTES: usize = 128 * 1024 * 1024;
+const MAX_TABLE_ELEMENTS: u32 = 100_000;
+
+// Size and modification time of a plugin file, to tell when it changed.
+type Stamp = (u64, Option<SystemTime>);
+
+// Manifests of the plugin files read so far, so `find` only instantiates the
+// plugin it returns.
+static MANIFESTS: Mutex<Option<HashMap<PathBuf, (Stamp, PluginManifest)>>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginFormat {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginLintRule {
+    pub id: String,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginManifest {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub abi_version: u32,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub importers: Vec<PluginFormat>,
+    #[serde(default)]
+    pub exporters: Vec<PluginFormat>,
+    #[serde(default)]
+    pub lint_rules: Vec<PluginLintRule>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PluginInfo {
+    pub file: String,
+    pub enabled: bool,
+    pub manifest: Option<PluginManifest>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PluginRequest<'a> {
+    Import { id: &'a str, input: &'a str },
+    Export { id: &'a str, content: &'a str },
+    Lint { content: &'a str },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginLintIssue {
+    pub rule: String,
+    pub message: String,
+    #[serde(default)]
+    pub line: Option<u32>,
+    #[serde(default)]
+    pub plugin: String,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct PluginResponse {
+    #[serde(default)]
+    output: Option<String>,
+    #[serde(default)]
+    issues: Vec<PluginLintIssue>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+pub fn plugins_dir() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("plugins"))
+}
+
+pub fn discover(enabled: &[String]) -> Result<Vec<PluginInfo>, String> {
+    let dir = plugins_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries =
+        fs::read_dir(&dir).map_err(|e| format!("Failed to read plugins directory: {}", e))?;
+
+    let mut plugins: Vec<PluginInfo> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+        .map(|path| {
+            let file = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+
+            match LoadedPlugin::load(&path) {
+                Ok(plugin) => {
+                    remember(&path, &plugin.manifest);
+                    PluginInfo {
+                        file,
+                        enabled: enabled.contains(&plugin.manifest.id),
+                        manifest: Some(plugin.manifest),
+                        error: None,
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(file = %file, error = %e, "failed to load plugin");
+                    PluginInfo {
+                        file,
+                        enabled: false,
+                        manifest: None,
+                        error: Some(e),
+                    }
+                }
+            }
+        })
+        .collect();
+
+    plugins.sort_by(|a, b| a.file.cmp(&b.file));
+    Ok(plugins)
+}
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()))
+}
+
+fn remember(path: &Path, manifest: &PluginManifest) {
+    let Some(stamp) = stamp(path) else {
+        return;
+    };
+    if let Ok(mut manifests) = MANIFESTS.lock() {
+        manifests
+            .get_or_insert_with(HashMap::new)
+            .insert(path.to_path_buf(), (stamp, manifest.clone()));
+    }
+}
+
+// The manifest of the plugin at `path`, loading the plugin only when the
+// file is new or changed since it was last read.
+fn manifest(path: &Path) -> Option<PluginManifest> {
+    let current = stamp(path)?;
+    if let Ok(manifests) = MANIFESTS.lock() {
+        if let Some((stamp, manifest)) = manifests.as_ref().and_then(|m| m.get(path)) {
+            if *stamp == current {
+                return Some(manifest.clone());
+            }
+        }
+    }
+    let manifest = LoadedPlugin::load(path).ok()?.manifest;
+    remember(path, &manifest);
+    Some(manifest)
+}
+
+pub fn find(id: &str) -> Result<LoadedPlugin, String> {
+    let dir = plugins_dir()?;
+    let entries =
+        fs::read_dir(&dir).map_err(|e| format!("Failed to read plugins directory: {}", e))?;
+
+    let path = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+        .find(|path| manifest(path).is_some_and(|manifest| manifest.id == id))
+        .ok_or_else(|| format!("Plugin not found: {}", id))?;
+    LoadedPlugin::load(&path)
+}
+
+pub struct LoadedPlugin {
+    pub manifest: PluginManifest,
+    store: Store<StoreLimits>,
+    instance: Instance,
+    memory: Memory,
+}
+
+impl LoadedPlugin {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read plugin: {}", e))?;
+
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module =
+            Module::new(&engine, &bytes).map_err(|e| format!("Invalid WASM module: {}", e))?;
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY_BYTES)
+            .table_elements(MAX_TABLE_ELEMENTS)
+            .instances(1)
+            .memories(1)
+            .tables(1)
+            .build();
+        let mut store = Store::new(&engine, limits);
+        store.limiter(|limits| limits);
+        store
+            .set_fuel(FUEL_PER_CALL)
+            .map_err(|e| format!("Failed to set fuel: {}", e))?;
+
+        let linker = Linker::<StoreLimits>::new(&engine);
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| format!("Failed to instantiate plugin: {}", e))?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or("Plugin does not export memory")?;
+
+        let packed = instance
+            .get_typed_func::<(), i64>(&store, "flowcraft_manifest")
+            .map_err(|e| format!("Plugin does not export flowcraft_manifest: {}", e))?
+            .call(&mut store, ())
+            .map_err(|e| format!("flowcraft_manifest failed: {}", e))?;
+        let manifest: PluginManifest = serde_json::from_str(&read_packed(&memory, &store, packed)?)
+            .map_err(|e| format!("Invalid plugin manifest: {}", e))?;
+
+        if manifest.abi_version != ABI_VERSION {
+            return Err(format!(
+                "Plugin ABI version {} is not supported (expected {})",
+                manifest.abi_version, ABI_VERSION
+            ));
+        }
+
+        Ok(Self {
+            manifest,
+            store,
+            instance,
+            memory,
+        })
+    }
+
+    pub fn import(&mut self, importer: &str, input: &str) -> Result<String, String> {
+        self.call(&PluginRequest::Import {
+            id: importer,
+            input,
+        })?
+        .output
+        .ok_or_else(|| "Plugin returned no output".to_string())
+    }
+
+    pub fn export(&mut self, exporter: &str, content: &str) -> Result<String, String> {
+        self.call(&PluginRequest::Export {
+            id: exporter,
+            content,
+        })?
+        .output
+        .ok_or_else(|| "Plugin returned no output".to_string())
+    }
+
+    pub fn lint(&mut self, content: &str) -> Result<Vec<PluginLintIssue>, String> {
+        let plugin_id = self.manifest.id.clone();
+        let mut issues = self.call(&PluginRequest::Lint { content })?.issues;
+        for issue in &mut issues {
+            issue.plugin = plugin_id.clone();
+        }
+        Ok(issues)
+    }
+
+    fn call(&mut self, request: &PluginRequest) -> Result<PluginResponse, String> {
+        self.store
+            .set_fuel(FUEL_PER_CALL)
+            .map_err(|e| format!("Failed to set fuel: {}", e))?;
+
+        let request =
+            serde_json::to_vec(request).map_err(|e| format!("Failed to encode request: {}", e))?;
+        let len = i32::try_from(request.len()).map_err(|_| "Request too large".to_string())?;
+
+        let ptr = self
+            .instance
+            .get_typed_func::<i32, i32>(&self.store, "flowcraft_alloc")
+            .map_err(|e| format!("Plugin does not export flowcraft_alloc: {}", e))?
+            .call(&mut self.store, len)
+            .map_err(|e| format!("flowcraft_alloc failed: {}", e))?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, &request)
+            .map_err(|e| format!("Failed to write plugin memory: {}", e))?;
+
+        let packed = self
+            .instance
+            .get_typed_func::<(i32, i32), i64>(&self.store, "flowcraft_call")
+            .map_err(|e| format!("Plugin does not export flowcraft_call: {}", e))?
+            .call(&mut self.store, (ptr, len))
+            .map_err(|e| format!("Plugin call failed: {}", e))?;
+
+        let response: PluginResponse =
+            serde_json::from_str(&read_packed(&self.memory, &self.store, packed)?)
+                .map_err(|e| format!("Invalid plugin response: {}", e))?;
+
+        match response.error {
+            Some(error) => Err(format!("{}: {}", self.manifest.name, error)),
+            None => Ok(response),
+        }
+    }
+}
+
+fn read_packed(memory: &Memory, store: &Store<StoreLimits>, packed: i64) -> Result<String, String> {
+    let ptr = ((packed as u64) >> 32) as usize;
+    let len = (packed as u64 & 0xffff_ffff) as usize;
+
+    let bytes = memory
+        .data(store)
+        .get(ptr..ptr.saturating_add(len))
+        .ok_or("Plugin returned an out-of-bounds string")?;
+    String::from_utf8(bytes.to_vec()).map_err(|e| format!("Plugin returned invalid UTF-8: {}", e))
+}
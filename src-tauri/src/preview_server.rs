@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use crate::render;
+
+pub const DEFAULT_PORT: u16 = 7745;
+
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_REQUEST_SIZE: usize = 8192;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PreviewServerConfig {
+    pub root_dir: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+type RenderCache = Mutex<HashMap<(PathBuf, String), (SystemTime, u64, Vec<u8>)>>;
+
+pub struct PreviewServerHandle {
+    pub config: PreviewServerConfig,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PreviewServerHandle {
+    pub fn url(&self) -> String {
+        format!("http://localhost:{}/diagram/", self.config.port)
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    // Stops the server and waits until its port is free again.
+    pub fn shutdown(mut self) {
+        self.stop();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for PreviewServerHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+pub fn spawn(config: PreviewServerConfig) -> Result<PreviewServerHandle, String> {
+    let root = fs::canonicalize(&config.root_dir)
+        .map_err(|e| format!("Invalid preview root {}: {}", config.root_dir, e))?;
+
+    // Only loopback is bound; the server is meant for local tools and
+    // browsers. Web pages are kept out by the Host check below and by not
+    // sending CORS headers.
+    let listener = TcpListener::bind(("127.0.0.1", config.port))
+        .map_err(|e| format!("Failed to bind port {}: {}", config.port, e))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("Failed to configure listener: {}", e))?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let cache: Arc<RenderCache> = Arc::new(Mutex::new(HashMap::new()));
+    let port = config.port;
+
+    let thread = thread::Builder::new()
+        .name("preview-server".to_string())
+        .spawn(move || {
+            while !thread_stop.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let root = root.clone();
+                        let cache = cache.clone();
+                        thread::spawn(move || handle_connection(stream, &root, port, &cache));
+                    }
+                    // WouldBlock is the normal idle case for a non-blocking listener.
+                    Err(_) => thread::sleep(ACCEPT_INTERVAL),
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to start preview server: {}", e))?;
+
+    Ok(PreviewServerHandle {
+        config,
+        stop,
+        thread: Some(thread),
+    })
+}
+
+// Only requests addressed to the loopback address are answered. A page
+// that rebinds its own domain to 127.0.0.1 still sends its domain as the
+// host, so it cannot read diagrams through this server.
+fn is_local_host(request: &str, port: u16) -> bool {
+    let host = request.lines().skip(1).find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("host")
+            .then(|| value.trim().to_ascii_lowercase())
+    });
+    match host {
+        Some(host) => {
+            host == format!("127.0.0.1:{}", port) || host == format!("localhost:{}", port)
+        }
+        None => false,
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, root: &Path, port: u16, cache: &RenderCache) {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buffer.windows(4).any(|w| w == b"\r\n\r\n") && buffer.len() < MAX_REQUEST_SIZE {
+        match stream.read(&mut chunk) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+        }
+    }
+
+    let request = String::from_utf8_lossy(&buffer);
+    let mut parts = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+
+    if !is_local_host(&request, port) {
+        let _ = write_response(
+            &mut stream,
+            "403 Forbidden",
+            "text/plain",
+            b"Forbidden",
+            false,
+        );
+        return;
+    }
+
+    if method != "GET" && method != "HEAD" {
+        let _ = write_response(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"Method not allowed",
+            false,
+        );
+        return;
+    }
+
+    let path = target.split('?').next().unwrap_or_default();
+    let (status, content_type, body) = match path.strip_prefix("/diagram/") {
+        Some(name) => match serve_diagram(root, &decode_path(name), cache) {
+            Ok((format, bytes)) => ("200 OK", render::content_type(&format), bytes),
+            Err((status, message)) => {
+                tracing::warn!(path, status, error = %message, "preview request failed");
+                (status, "text/plain", message.into_bytes())
+            }
+        },
+        None => ("404 Not Found", "text/plain", b"Not found".to_vec()),
+    };
+
+    let _ = write_response(&mut stream, status, content_type, &body, method == "HEAD");
+}
+
+fn serve_diagram(
+    root: &Path,
+    name: &str,
+    cache: &RenderCache,
+) -> Result<(String, Vec<u8>), (&'static str, String)> {
+    let requested = Path::new(name);
+    let format = render::format_from_path(requested)
+        .ok_or(("404 Not Found", "Unsupported format".to_string()))?;
+
+    if requested
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(("400 Bad Request", "Invalid diagram path".to_string()));
+    }
+
+    let source = ["mmd", "mermaid"]
+        .iter()
+        .map(|ext| root.join(requested).with_extension(ext))
+        .find(|candidate| candidate.is_file())
+        .ok_or(("404 Not Found", "Diagram not found".to_string()))?;
+
+    let modified = fs::metadata(&source)
+        .and_then(|m| m.modified())
+        .map_err(|e| ("500 Internal Server Error", e.to_string()))?;
+
+    let generation = render::config_generation();
+    let key = (source.clone(), format.clone());
+    if let Ok(cache) = cache.lock() {
+        if let Some((cached_at, cached_generation, bytes)) = cache.get(&key) {
+            if *cached_at == modified && *cached_generation == generation {
+                return Ok((format, bytes.clone()));
+            }
+        }
+    }
+
+    let bytes =
+        render::render_to_bytes(&source, &format).map_err(|e| ("500 Internal Server Error", e))?;
+
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(key, (modified, generation, bytes.clone()));
+    }
+
+    Ok((format, bytes))
+}
+
+fn decode_path(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        // `get` covers an escape that ends the string, like `a%41`.
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok());
+        if let (b'%', Some(hex)) = (bytes[i], hex) {
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+    head_only: bool,
+) -> std::io::Result<()> {
+    // no-cache lets embedding pages pick up a new render after every save.
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+
+    stream.write_all(header.as_bytes())?;
+    if !head_only {
+        stream.write_all(body)?;
+    }
+    stream.flush()
+}
@@ -0,0 +1,96 @@
+use regex::Regex;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PageFit {
+    #[default]
+    FitPage,
+    FitWidth,
+    ActualSize,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Orientation {
+    #[default]
+    Auto,
+    Portrait,
+    Landscape,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct PrintOptions {
+    #[serde(default)]
+    pub fit: PageFit,
+    #[serde(default)]
+    pub orientation: Orientation,
+    #[serde(default)]
+    pub margin_mm: Option<f32>,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+const DEFAULT_MARGIN_MM: f32 = 10.0;
+
+pub fn svg_dimensions(svg: &str) -> Option<(f64, f64)> {
+    let view_box =
+        Regex::new(r#"viewBox\s*=\s*"\s*[-\d.]+[\s,]+[-\d.]+[\s,]+([\d.]+)[\s,]+([\d.]+)\s*""#)
+            .unwrap();
+    let caps = view_box.captures(svg)?;
+    Some((caps[1].parse().ok()?, caps[2].parse().ok()?))
+}
+
+// Builds a standalone page for the print webview. Mermaid sets an inline
+// max-width on its SVGs, hence the !important overrides. The SVG goes in as
+// it is, so it must come from the renderer or `security::sanitize_svg`.
+pub fn print_document(svg: &str, options: &PrintOptions) -> String {
+    let orientation = match options.orientation {
+        Orientation::Portrait => "portrait",
+        Orientation::Landscape => "landscape",
+        Orientation::Auto => match svg_dimensions(svg) {
+            Some((width, height)) if width > height => "landscape",
+            _ => "portrait",
+        },
+    };
+
+    let sizing = match options.fit {
+        PageFit::FitPage => {
+            "width: auto !important; height: auto !important; max-width: 100% !important; max-height: 100vh !important;"
+        }
+        PageFit::FitWidth => "width: 100% !important; height: auto !important; max-width: none !important;",
+        PageFit::ActualSize => "max-width: none !important; max-height: none !important;",
+    };
+
+    let title = options
+        .title
+        .as_deref()
+        .unwrap_or("FlowCraft Studio")
+        .replace('&', "&amp;")
+        .replace('<', "&lt;");
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+@page {{ size: {orientation}; margin: {margin}mm; }}
+html, body {{ margin: 0; padding: 0; height: 100%; background: #fff; }}
+body {{ display: flex; align-items: center; justify-content: center; }}
+body > svg {{ display: block; {sizing} }}
+</style>
+</head>
+<body>
+{svg}
+</body>
+</html>
+"#,
+        title = title,
+        orientation = orientation,
+        margin = options.margin_mm.unwrap_or(DEFAULT_MARGIN_MM).max(0.0),
+        sizing = sizing,
+        svg = svg,
+    )
+}
@@ -0,0 +1,239 @@
+use regex::{NoExpand, Regex};
+use serde::{Deserialize, Serialize};
+
+// Without brackets, which would end a node label early.
+const DEFAULT_REPLACEMENT: &str = "REDACTED";
+// Findings kept for the report; a rule matching every line of a large
+// diagram should not make the report larger than the diagram.
+const MAX_FINDINGS: usize = 500;
+
+fn default_replacement() -> String {
+    DEFAULT_REPLACEMENT.to_string()
+}
+
+fn yes() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RedactionRule {
+    pub name: String,
+    // A regular expression, matched within each line. `(?i)` makes it ignore
+    // case.
+    pub pattern: String,
+    #[serde(default = "default_replacement")]
+    pub replacement: String,
+    #[serde(default = "yes")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RedactionConfig {
+    // Applies the rules to everything exported or shared.
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub rules: Vec<RedactionRule>,
+}
+
+impl RedactionConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in &self.rules {
+            if rule.name.trim().is_empty() {
+                return Err("Redaction rules need a name".to_string());
+            }
+            let regex = Regex::new(&rule.pattern)
+                .map_err(|e| format!("Invalid pattern for rule '{}': {}", rule.name, e))?;
+            if regex.is_match("") {
+                return Err(format!(
+                    "The pattern for rule '{}' matches empty text",
+                    rule.name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.rules.iter().any(|rule| rule.enabled)
+    }
+
+    // The config when it applies to exports, None while it is off.
+    pub fn active(&self) -> Option<RedactionConfig> {
+        self.is_active().then(|| self.clone())
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Redaction {
+    pub line: usize,
+    pub rule: String,
+    // The text that was replaced. The report stays on this machine.
+    pub text: String,
+    pub replacement: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct RedactionReport {
+    pub redactions: Vec<Redaction>,
+    // Every match, including those past the report's limit.
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Redacted {
+    pub content: String,
+    pub report: RedactionReport,
+}
+
+// Starting points for the settings page: IP addresses, hostnames under
+// internal domains and email addresses. Customer names are the user's own.
+pub fn presets() -> Vec<RedactionRule> {
+    let preset = |name: &str, pattern: &str| RedactionRule {
+        name: name.to_string(),
+        pattern: pattern.to_string(),
+        replacement: default_replacement(),
+        enabled: true,
+    };
+    vec![
+        preset(
+            "IPv4 address",
+            r"\b(?:25[0-5]|2[0-4]\d|1?\d?\d)(?:\.(?:25[0-5]|2[0-4]\d|1?\d?\d)){3}\b",
+        ),
+        preset(
+            "Internal hostname",
+            r"(?i)\b[a-z0-9-]+(?:\.[a-z0-9-]+)*\.(?:internal|local|corp|lan|intranet)\b",
+        ),
+        preset(
+            "Email address",
+            r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b",
+        ),
+    ]
+}
+
+// Applies the enabled rules in order, line by line, so a later rule sees
+// what earlier ones left. Invalid patterns are skipped; settings are
+// validated when saved.
+pub fn redact(content: &str, config: &RedactionConfig) -> Redacted {
+    let rules: Vec<(&RedactionRule, Regex)> = config
+        .rules
+        .iter()
+        .filter(|rule| rule.enabled)
+        .filter_map(|rule| Regex::new(&rule.pattern).ok().map(|regex| (rule, regex)))
+        .collect();
+
+    let mut report = RedactionReport::default();
+    let mut lines = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let mut line = line.to_string();
+        for (rule, regex) in &rules {
+            for found in regex.find_iter(&line) {
+                report.total += 1;
+                if report.redactions.len() < MAX_FINDINGS {
+                    report.redactions.push(Redaction {
+                        line: index + 1,
+                        rule: rule.name.clone(),
+                        text: found.as_str().to_string(),
+                        replacement: rule.replacement.clone(),
+                    });
+                }
+            }
+            line = regex
+                .replace_all(&line, NoExpand(&rule.replacement))
+                .into_owned();
+        }
+        lines.push(line);
+    }
+
+    let mut redacted = lines.join("\n");
+    if content.ends_with('\n') {
+        redacted.push('\n');
+    }
+    Redacted {
+        content: redacted,
+        report,
+    }
+}
+
+// Redacts content on its way out. The report goes back with whatever
+// the content was used for.
+pub fn apply(content: &str, config: &RedactionConfig) -> Redacted {
+    let redacted = redact(content, config);
+    if redacted.report.total > 0 {
+        tracing::info!(
+            redactions = redacted.report.total,
+            "redacted outgoing content"
+        );
+    }
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, pattern: &str, replacement: &str) -> RedactionRule {
+        RedactionRule {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+            enabled: true,
+        }
+    }
+
+    fn config(rules: Vec<RedactionRule>) -> RedactionConfig {
+        RedactionConfig {
+            enabled: true,
+            rules,
+        }
+    }
+
+    #[test]
+    fn presets_cover_addresses_hosts_and_emails() {
+        let source = "graph LR\n  A[10.0.4.12] --> B[db01.corp]\n  B --> C[ops@example.com]\n";
+        let redacted = redact(source, &config(presets()));
+        assert_eq!(
+            redacted.content,
+            "graph LR\n  A[REDACTED] --> B[REDACTED]\n  B --> C[REDACTED]\n"
+        );
+        assert_eq!(redacted.report.total, 3);
+        assert_eq!(redacted.report.redactions[0].line, 2);
+        assert_eq!(redacted.report.redactions[0].text, "10.0.4.12");
+        assert_eq!(redacted.report.redactions[2].rule, "Email address");
+    }
+
+    #[test]
+    fn replacements_are_literal() {
+        let redacted = redact("A[acme]", &config(vec![rule("Customer", "(a)cme", "$1x")]));
+        assert_eq!(redacted.content, "A[$1x]");
+    }
+
+    #[test]
+    fn later_rules_see_earlier_replacements() {
+        let rules = vec![
+            rule("Customer", "acme", "CUSTOMER"),
+            rule("Shout", "CUSTOMER", "X"),
+        ];
+        let redacted = redact("A[acme]", &config(rules));
+        assert_eq!(redacted.content, "A[X]");
+        assert_eq!(redacted.report.total, 2);
+    }
+
+    #[test]
+    fn skips_disabled_and_invalid_rules() {
+        let mut disabled = rule("Customer", "acme", "X");
+        disabled.enabled = false;
+        let redacted = redact("A[acme]", &config(vec![disabled, rule("Broken", "(", "X")]));
+        assert_eq!(redacted.content, "A[acme]");
+        assert_eq!(redacted.report.total, 0);
+    }
+
+    #[test]
+    fn report_is_capped_but_counts_everything() {
+        let source = "a\n".repeat(MAX_FINDINGS + 10);
+        let redacted = redact(&source, &config(vec![rule("Letter", "a", "b")]));
+        assert_eq!(redacted.report.total, MAX_FINDINGS + 10);
+        assert_eq!(redacted.report.redactions.len(), MAX_FINDINGS);
+        assert_eq!(redacted.content, "b\n".repeat(MAX_FINDINGS + 10));
+    }
+}
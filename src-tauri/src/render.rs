@@ -0,0 +1,303 @@
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{icons, kroki};
+
+pub const SUPPORTED_FORMATS: [&str; 3] = ["svg", "png", "pdf"];
+
+// Mermaid configuration applied to every render, set from the active theme.
+#[derive(Debug, Clone)]
+pub struct RenderConfig {
+    pub mermaid: serde_json::Value,
+    pub background: Option<String>,
+}
+
+static CONFIG: Mutex<Option<RenderConfig>> = Mutex::new(None);
+
+// Bumped on every config change so cached renders can tell they are stale.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub fn set_config(config: Option<RenderConfig>) {
+    if let Ok(mut current) = CONFIG.lock() {
+        *current = config;
+        GENERATION.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+pub fn config_generation() -> u64 {
+    GENERATION.load(Ordering::SeqCst)
+}
+
+thread_local! {
+    // Set while `with_config` runs, for renders that need another theme than
+    // the active one.
+    static OVERRIDE: RefCell<Option<RenderConfig>> = const { RefCell::new(None) };
+}
+
+pub fn current_config() -> Option<RenderConfig> {
+    OVERRIDE
+        .with(|config| config.borrow().clone())
+        .or_else(|| CONFIG.lock().ok().and_then(|current| current.clone()))
+}
+
+// Runs `render` with `config` in place of the active config. Only renders on
+// the calling thread see it, so other renders keep the active theme.
+pub fn with_config<T>(config: RenderConfig, render: impl FnOnce() -> T) -> T {
+    // Puts the previous config back even when `render` panics, since job
+    // workers catch panics and go on to render other diagrams.
+    struct Restore(Option<RenderConfig>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            OVERRIDE.with(|current| *current.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = Restore(OVERRIDE.with(|current| current.replace(Some(config))));
+    render()
+}
+
+// A Mermaid CLI pinned to the selected Mermaid version, if any.
+static BINARY: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+pub fn set_binary(binary: Option<PathBuf>) {
+    if let Ok(mut current) = BINARY.lock() {
+        *current = binary;
+        GENERATION.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+// A Kroki server that renders instead of the Mermaid CLI.
+static KROKI_URL: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn set_kroki_url(url: Option<String>) {
+    if let Ok(mut current) = KROKI_URL.lock() {
+        *current = url;
+        GENERATION.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+// FLOWCRAFT_KROKI_URL overrides the configured server.
+fn kroki_url() -> Option<String> {
+    env::var("FLOWCRAFT_KROKI_URL")
+        .ok()
+        .filter(|url| !url.is_empty())
+        .or_else(|| KROKI_URL.lock().ok().and_then(|url| url.clone()))
+}
+
+// Rendering goes through the Mermaid CLI so headless runs produce the same
+// output as the preview. FLOWCRAFT_MMDC overrides the binary location.
+fn mmdc_command() -> Command {
+    let binary = env::var("FLOWCRAFT_MMDC")
+        .map(PathBuf::from)
+        .ok()
+        .or_else(|| BINARY.lock().ok().and_then(|binary| binary.clone()))
+        .unwrap_or_else(|| PathBuf::from("mmdc"));
+    Command::new(binary)
+}
+
+// True when a Kroki server is configured or the Mermaid CLI can be
+// launched at all.
+pub fn is_available() -> bool {
+    if kroki_url().is_some() {
+        return true;
+    }
+    mmdc_command()
+        .arg("--version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+pub fn format_from_path(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    SUPPORTED_FORMATS
+        .contains(&extension.as_str())
+        .then_some(extension)
+}
+
+// Size of the headless browser page. Diagrams using `useMaxWidth` shrink to
+// the page width, and `scale` multiplies the pixel density of PNGs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Viewport {
+    pub width: Option<u32>,
+    pub scale: Option<u32>,
+}
+
+pub fn render_file(input: &Path, output: &Path, format: &str) -> Result<(), String> {
+    render_file_at(input, output, format, Viewport::default())
+}
+
+pub fn render_file_at(
+    input: &Path,
+    output: &Path,
+    format: &str,
+    viewport: Viewport,
+) -> Result<(), String> {
+    if !SUPPORTED_FORMATS.contains(&format) {
+        return Err("Unsupported format".to_string());
+    }
+    if let Some(server) = kroki_url() {
+        return render_with_kroki(&server, input, output, format);
+    }
+
+    let mut command = mmdc_command();
+    command
+        .arg("--input")
+        .arg(input)
+        .arg("--output")
+        .arg(output)
+        .arg("--outputFormat")
+        .arg(format)
+        .arg("--quiet");
+    if let Some(width) = viewport.width {
+        command.arg("--width").arg(width.to_string());
+    }
+    if let Some(scale) = viewport.scale {
+        command.arg("--scale").arg(scale.to_string());
+    }
+    // Only passed when the diagram uses a pack, so older CLIs without the
+    // option still render everything else.
+    let icon_packs = icons::render_args(&fs::read_to_string(input).unwrap_or_default());
+    if !icon_packs.is_empty() {
+        command.arg("--iconPacksNamesAndUrls").args(&icon_packs);
+    }
+
+    let config = current_config();
+    let config_file = temp_path("json");
+    if let Some(config) = &config {
+        fs::write(&config_file, config.mermaid.to_string())
+            .map_err(|e| format!("Failed to write temporary file: {}", e))?;
+        command.arg("--configFile").arg(&config_file);
+        if let Some(background) = &config.background {
+            command.arg("--backgroundColor").arg(background);
+        }
+    }
+
+    let result = command.output();
+    if config.is_some() {
+        let _ = fs::remove_file(&config_file);
+    }
+    let result = result.map_err(|e| format!("Failed to launch Mermaid CLI: {}", e))?;
+
+    if result.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Failed to render diagram: {}",
+            String::from_utf8_lossy(&result.stderr).trim()
+        ))
+    }
+}
+
+// Kroki takes no config file, so the config travels as an init directive
+// ahead of the source. The viewport and background color do not apply.
+fn render_with_kroki(
+    server: &str,
+    input: &Path,
+    output: &Path,
+    format: &str,
+) -> Result<(), String> {
+    let mut source = fs::read_to_string(input)
+        .map_err(|e| format!("Failed to read {}: {}", input.display(), e))?;
+    if let Some(config) = current_config() {
+        source = with_init_directive(&source, &config.mermaid);
+    }
+    let bytes = kroki::render(server, &source, format)?;
+    fs::write(output, bytes).map_err(|e| format!("Failed to write {}: {}", output.display(), e))
+}
+
+// Mermaid only reads frontmatter at the very start of the source, so the
+// directive goes after its closing `---` when there is one.
+fn with_init_directive(source: &str, config: &serde_json::Value) -> String {
+    let directive = format!("%%{{init: {}}}%%", config);
+    let lines: Vec<&str> = source.lines().collect();
+    if lines.first().map(|line| line.trim()) == Some("---") {
+        if let Some(end) = lines.iter().skip(1).position(|line| line.trim() == "---") {
+            let (frontmatter, body) = lines.split_at(end + 2);
+            return format!(
+                "{}\n{}\n{}",
+                frontmatter.join("\n"),
+                directive,
+                body.join("\n")
+            );
+        }
+    }
+    format!("{}\n{}", directive, source)
+}
+
+// Renders run in parallel on the worker pool, so the timestamp alone is not
+// unique enough.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+pub fn temp_path(extension: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    env::temp_dir().join(format!(
+        "flowcraft-{}-{}-{}.{}",
+        std::process::id(),
+        nanos,
+        TEMP_COUNTER.fetch_add(1, Ordering::SeqCst),
+        extension
+    ))
+}
+
+pub fn render_content(content: &str, output: &Path, format: &str) -> Result<(), String> {
+    let input = temp_path("mmd");
+
+    fs::write(&input, content).map_err(|e| format!("Failed to write temporary file: {}", e))?;
+    let result = render_file(&input, output, format);
+    let _ = fs::remove_file(&input);
+    result
+}
+
+pub fn render_to_bytes(input: &Path, format: &str) -> Result<Vec<u8>, String> {
+    render_to_bytes_at(input, format, Viewport::default())
+}
+
+fn render_to_bytes_at(input: &Path, format: &str, viewport: Viewport) -> Result<Vec<u8>, String> {
+    let output = temp_path(format);
+
+    render_file_at(input, &output, format, viewport)?;
+    let bytes = fs::read(&output).map_err(|e| format!("Failed to read rendered output: {}", e));
+    let _ = fs::remove_file(&output);
+    bytes
+}
+
+pub fn render_content_to_bytes(content: &str, format: &str) -> Result<Vec<u8>, String> {
+    render_content_to_bytes_at(content, format, Viewport::default())
+}
+
+pub fn render_content_to_bytes_at(
+    content: &str,
+    format: &str,
+    viewport: Viewport,
+) -> Result<Vec<u8>, String> {
+    let input = temp_path("mmd");
+
+    fs::write(&input, content).map_err(|e| format!("Failed to write temporary file: {}", e))?;
+    let result = render_to_bytes_at(&input, format, viewport);
+    let _ = fs::remove_file(&input);
+    result
+}
+
+pub fn content_type(format: &str) -> &'static str {
+    match format {
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "pdf" => "application/pdf",
+        "emf" => "image/emf",
+        "eps" => "application/postscript",
+        "tiff" => "image/tiff",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,321 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Long enough for a paragraph of feedback, short enough to stay a comment.
+const MAX_BODY_CHARS: usize = 4000;
+// How far a thread looks for its line when lines were added or removed
+// above it.
+const REANCHOR_DISTANCE: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Comment {
+    pub id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    pub body: String,
+    pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Thread {
+    pub id: u64,
+    // 1-based, like validation messages.
+    pub line: usize,
+    // The line's text when the thread was started, to find it again after
+    // edits move it.
+    pub anchor: String,
+    #[serde(default)]
+    pub resolved: bool,
+    pub comments: Vec<Comment>,
+    // The anchored line is gone. Worked out when listing, so a stored value
+    // is ignored.
+    #[serde(default, skip_deserializing)]
+    pub outdated: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct Stored {
+    #[serde(default)]
+    threads: Vec<Thread>,
+}
+
+// Kept in a sidecar such as `flow.mmd.comments.json`, so reviewers never
+// touch the diagram source.
+pub fn sidecar_path(diagram: &Path) -> PathBuf {
+    let mut name = diagram.as_os_str().to_owned();
+    name.push(".comments.json");
+    PathBuf::from(name)
+}
+
+fn load(diagram: &Path) -> Result<Stored, String> {
+    let sidecar = sidecar_path(diagram);
+    if !sidecar.is_file() {
+        return Ok(Stored::default());
+    }
+    let data =
+        fs::read_to_string(&sidecar).map_err(|e| format!("Failed to read comments: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Invalid comments file: {}", e))
+}
+
+// Writes the comments, removing the sidecar once the last thread is gone.
+fn save(diagram: &Path, stored: &Stored) -> Result<(), String> {
+    let sidecar = sidecar_path(diagram);
+    if stored.threads.is_empty() {
+        if sidecar.exists() {
+            fs::remove_file(&sidecar).map_err(|e| format!("Failed to remove comments: {}", e))?;
+        }
+        return Ok(());
+    }
+    let data = serde_json::to_string_pretty(stored)
+        .map_err(|e| format!("Failed to save comments: {}", e))?;
+    fs::write(&sidecar, data).map_err(|e| format!("Failed to save comments: {}", e))
+}
+
+fn body(body: &str) -> Result<String, String> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Err("The comment is empty".to_string());
+    }
+    if body.chars().count() > MAX_BODY_CHARS {
+        return Err(format!(
+            "Comments can be at most {} characters",
+            MAX_BODY_CHARS
+        ));
+    }
+    Ok(body.to_string())
+}
+
+fn author(author: Option<&str>) -> Option<String> {
+    author
+        .map(str::trim)
+        .filter(|author| !author.is_empty())
+        .map(str::to_string)
+}
+
+fn next_comment_id(stored: &Stored) -> u64 {
+    stored
+        .threads
+        .iter()
+        .flat_map(|thread| &thread.comments)
+        .map(|comment| comment.id)
+        .max()
+        .unwrap_or(0)
+        + 1
+}
+
+fn thread_mut(stored: &mut Stored, thread_id: u64) -> Result<&mut Thread, String> {
+    stored
+        .threads
+        .iter_mut()
+        .find(|thread| thread.id == thread_id)
+        .ok_or_else(|| format!("Unknown comment thread: {}", thread_id))
+}
+
+// Moves each thread to the nearest line that still reads as it did, and
+// marks the ones whose line is gone.
+fn reanchor(threads: &mut [Thread], content: &str) {
+    let lines: Vec<&str> = content.lines().map(str::trim).collect();
+    for thread in threads {
+        let anchor = thread.anchor.trim();
+        let index = thread.line.saturating_sub(1);
+        if lines.get(index) == Some(&anchor) {
+            continue;
+        }
+        let found = (1..=REANCHOR_DISTANCE).find_map(|distance| {
+            [index.checked_sub(distance), Some(index + distance)]
+                .into_iter()
+                .flatten()
+                .find(|candidate| lines.get(*candidate) == Some(&anchor))
+        });
+        match found {
+            Some(candidate) => thread.line = candidate + 1,
+            None => thread.outdated = true,
+        }
+    }
+}
+
+// The diagram's threads by line, anchored to `content`. Threads that moved
+// are saved at their new lines.
+pub fn list(diagram: &Path, content: &str) -> Result<Vec<Thread>, String> {
+    let mut stored = load(diagram)?;
+    let lines: Vec<usize> = stored.threads.iter().map(|thread| thread.line).collect();
+    reanchor(&mut stored.threads, content);
+    if stored
+        .threads
+        .iter()
+        .zip(lines)
+        .any(|(thread, line)| thread.line != line)
+    {
+        save(diagram, &stored)?;
+    }
+    let mut threads = stored.threads;
+    threads.sort_by_key(|thread| (thread.line, thread.id));
+    Ok(threads)
+}
+
+pub fn start_thread(
+    diagram: &Path,
+    content: &str,
+    line: usize,
+    text: &str,
+    author_name: Option<&str>,
+) -> Result<Thread, String> {
+    let anchor = content
+        .lines()
+        .nth(line.saturating_sub(1))
+        .filter(|_| line > 0)
+        .ok_or_else(|| format!("Line {} is not in the diagram", line))?;
+    let mut stored = load(diagram)?;
+    let thread = Thread {
+        id: stored
+            .threads
+            .iter()
+            .map(|thread| thread.id)
+            .max()
+            .unwrap_or(0)
+            + 1,
+        line,
+        anchor: anchor.trim().to_string(),
+        resolved: false,
+        comments: vec![Comment {
+            id: next_comment_id(&stored),
+            author: author(author_name),
+            body: body(text)?,
+            created_at: Utc::now().to_rfc3339(),
+            edited_at: None,
+        }],
+        outdated: false,
+    };
+    stored.threads.push(thread.clone());
+    save(diagram, &stored)?;
+    Ok(thread)
+}
+
+pub fn reply(
+    diagram: &Path,
+    thread_id: u64,
+    text: &str,
+    author_name: Option<&str>,
+) -> Result<Thread, String> {
+    let mut stored = load(diagram)?;
+    let comment = Comment {
+        id: next_comment_id(&stored),
+        author: author(author_name),
+        body: body(text)?,
+        created_at: Utc::now().to_rfc3339(),
+        edited_at: None,
+    };
+    let thread = thread_mut(&mut stored, thread_id)?;
+    thread.comments.push(comment);
+    // A reply reopens the discussion.
+    thread.resolved = false;
+    let thread = thread.clone();
+    save(diagram, &stored)?;
+    Ok(thread)
+}
+
+pub fn edit(diagram: &Path, thread_id: u64, comment_id: u64, text: &str) -> Result<Thread, String> {
+    let text = body(text)?;
+    let mut stored = load(diagram)?;
+    let thread = thread_mut(&mut stored, thread_id)?;
+    let comment = thread
+        .comments
+        .iter_mut()
+        .find(|comment| comment.id == comment_id)
+        .ok_or_else(|| format!("Unknown comment: {}", comment_id))?;
+    comment.body = text;
+    comment.edited_at = Some(Utc::now().to_rfc3339());
+    let thread = thread.clone();
+    save(diagram, &stored)?;
+    Ok(thread)
+}
+
+pub fn set_resolved(diagram: &Path, thread_id: u64, resolved: bool) -> Result<Thread, String> {
+    let mut stored = load(diagram)?;
+    let thread = thread_mut(&mut stored, thread_id)?;
+    thread.resolved = resolved;
+    let thread = thread.clone();
+    save(diagram, &stored)?;
+    Ok(thread)
+}
+
+// Deletes one comment; deleting the last one in a thread deletes the
+// thread.
+pub fn delete(diagram: &Path, thread_id: u64, comment_id: u64) -> Result<(), String> {
+    let mut stored = load(diagram)?;
+    let thread = thread_mut(&mut stored, thread_id)?;
+    let before = thread.comments.len();
+    thread.comments.retain(|comment| comment.id != comment_id);
+    if thread.comments.len() == before {
+        return Err(format!("Unknown comment: {}", comment_id));
+    }
+    stored.threads.retain(|thread| !thread.comments.is_empty());
+    save(diagram, &stored)
+}
+
+pub fn delete_thread(diagram: &Path, thread_id: u64) -> Result<(), String> {
+    let mut stored = load(diagram)?;
+    let before = stored.threads.len();
+    stored.threads.retain(|thread| thread.id != thread_id);
+    if stored.threads.len() == before {
+        return Err(format!("Unknown comment thread: {}", thread_id));
+    }
+    save(diagram, &stored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thread(line: usize, anchor: &str) -> Thread {
+        Thread {
+            id: 1,
+            line,
+            anchor: anchor.to_string(),
+            resolved: false,
+            comments: Vec::new(),
+            outdated: false,
+        }
+    }
+
+    #[test]
+    fn stays_on_an_unchanged_line() {
+        let mut threads = [thread(2, "A --> B")];
+        reanchor(&mut threads, "graph TD\n  A --> B\n  B --> C\n");
+        assert_eq!(threads[0].line, 2);
+        assert!(!threads[0].outdated);
+    }
+
+    #[test]
+    fn follows_lines_moved_up_or_down() {
+        let mut threads = [thread(2, "A --> B"), thread(3, "B --> C")];
+        reanchor(&mut threads, "graph TD\n  X --> A\n  Y --> A\n  A --> B\n");
+        assert_eq!(threads[0].line, 4);
+        assert!(threads[1].outdated);
+
+        let mut threads = [thread(4, "B --> C")];
+        reanchor(&mut threads, "graph TD\n  B --> C\n");
+        assert_eq!(threads[0].line, 2);
+    }
+
+    #[test]
+    fn prefers_the_nearest_match() {
+        let mut threads = [thread(5, "A --> B")];
+        reanchor(&mut threads, "A --> B\nx\nx\nx\nx\nA --> B\n");
+        assert_eq!(threads[0].line, 6);
+    }
+
+    #[test]
+    fn gives_up_beyond_the_search_distance() {
+        let mut content = "x\n".repeat(REANCHOR_DISTANCE + 1);
+        content.push_str("A --> B\n");
+        let mut threads = [thread(1, "A --> B")];
+        reanchor(&mut threads, &content);
+        assert!(threads[0].outdated);
+        assert_eq!(threads[0].line, 1);
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::diagrams;
+
+// Splits a sankey line into its CSV fields. Quoted fields may hold commas
+// and doubled quotes, as in Mermaid's own parser.
+fn fields(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field".to_string());
+    }
+    fields.push(field);
+    Ok(fields
+        .into_iter()
+        .map(|field| field.trim().to_string())
+        .collect())
+}
+
+// Nodes left after repeatedly dropping every node with no remaining incoming
+// or outgoing link. Each one sits on a cycle or on a path between cycles.
+fn looped<'a>(links: &[(&'a str, &'a str)]) -> Vec<&'a str> {
+    let mut targets: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut sources: HashMap<&str, Vec<&str>> = HashMap::new();
+    for &(source, target) in links {
+        targets.entry(source).or_default().push(target);
+        sources.entry(target).or_default().push(source);
+        targets.entry(target).or_default();
+        sources.entry(source).or_default();
+    }
+    let mut inbound: HashMap<&str, usize> = sources
+        .iter()
+        .map(|(&node, from)| (node, from.len()))
+        .collect();
+    let mut outbound: HashMap<&str, usize> =
+        targets.iter().map(|(&node, to)| (node, to.len())).collect();
+
+    let mut removed: HashSet<&str> = HashSet::new();
+    let mut queue: Vec<&str> = targets
+        .keys()
+        .filter(|node| inbound[*node] == 0 || outbound[*node] == 0)
+        .copied()
+        .collect();
+    while let Some(node) = queue.pop() {
+        if !removed.insert(node) {
+            continue;
+        }
+        for &next in &targets[node] {
+            let count = inbound.get_mut(next).expect("every node is counted");
+            *count -= 1;
+            if *count == 0 {
+                queue.push(next);
+            }
+        }
+        for &previous in &sources[node] {
+            let count = outbound.get_mut(previous).expect("every node is counted");
+            *count -= 1;
+            if *count == 0 {
+                queue.push(previous);
+            }
+        }
+    }
+
+    targets
+        .into_keys()
+        .filter(|node| !removed.contains(node))
+        .collect()
+}
+
+// Checks the `source,target,value` rows of a sankey diagram. Values must be
+// positive numbers and flows cannot loop back, since the layout has no way
+// to draw either. Nodes that send on more than they receive and names that
+// differ only in case or spacing are reported as warnings.
+pub fn validate(content: &str, errors: &mut Vec<String>, warnings: &mut Vec<String>) {
+    let mut links: Vec<(usize, String, String, f64)> = Vec::new();
+
+    for (number, line) in diagrams::statements(content) {
+        let row = match fields(line) {
+            Ok(row) => row,
+            Err(e) => {
+                errors.push(format!("Line {}: {}", number, e));
+                continue;
+            }
+        };
+        let [source, target, value] = row.as_slice() else {
+            errors.push(format!(
+                "Line {}: expected `source,target,value` but found {} fields",
+                number,
+                row.len()
+            ));
+            continue;
+        };
+        if source.is_empty() || target.is_empty() {
+            errors.push(format!(
+                "Line {}: source and target cannot be empty",
+                number
+            ));
+            continue;
+        }
+        let Some(value) = value.parse::<f64>().ok().filter(|value| value.is_finite()) else {
+            errors.push(format!(
+                "Line {}: `{}` is not a finite number",
+                number, value
+            ));
+            continue;
+        };
+        if value < 0.0 {
+            errors.push(format!("Line {}: flows cannot be negative", number));
+            continue;
+        }
+        if value == 0.0 {
+            warnings.push(format!("Line {}: a flow of 0 is not drawn", number));
+        }
+        if source == target {
+            errors.push(format!("Line {}: {} flows into itself", number, source));
+            continue;
+        }
+        if links
+            .iter()
+            .any(|(_, from, to, _)| from == source && to == target)
+        {
+            warnings.push(format!(
+                "Line {}: {} to {} is listed more than once",
+                number, source, target
+            ));
+        }
+        links.push((number, source.clone(), target.clone(), value));
+    }
+
+    let mut inflow: HashMap<&str, f64> = HashMap::new();
+    let mut outflow: HashMap<&str, f64> = HashMap::new();
+    let mut nodes: Vec<&str> = Vec::new();
+    for (_, source, target, value) in &links {
+        *outflow.entry(source).or_default() += value;
+        *inflow.entry(target).or_default() += value;
+        for node in [source.as_str(), target.as_str()] {
+            if !nodes.contains(&node) {
+                nodes.push(node);
+            }
+        }
+    }
+
+    let edges = links
+        .iter()
+        .map(|(_, source, target, _)| (source.as_str(), target.as_str()))
+        .collect::<Vec<_>>();
+    let mut looped = looped(&edges);
+    if !looped.is_empty() {
+        looped.sort_unstable();
+        errors.push(format!(
+            "Flows loop back through {}; sankey diagrams cannot show cycles",
+            looped.join(", ")
+        ));
+    }
+
+    for &node in &nodes {
+        if let (Some(received), Some(sent)) = (inflow.get(node), outflow.get(node)) {
+            // Allow for rounding in decimal values.
+            if sent - received > 1e-9 * received.max(1.0) {
+                warnings.push(format!(
+                    "{} sends on {} but only receives {}",
+                    node, sent, received
+                ));
+            }
+        }
+    }
+
+    let mut normalized: HashMap<String, &str> = HashMap::new();
+    for &node in &nodes {
+        let key = node
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        match normalized.get(&key) {
+            Some(other) => warnings.push(format!(
+                "`{}` and `{}` look like the same node",
+                other, node
+            )),
+            None => {
+                normalized.insert(key, node);
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{detect_diagram_type, get_app_data_dir};
+
+// What a new scratch diagram starts with when nothing else is given.
+const STARTER: &str = "flowchart TD\n    A[Idea] --> B[Next step]\n";
+
+// Scratch diagrams live in the app's data folder, which the frontend cannot
+// reach by path, so they are addressed by id.
+#[derive(Debug, Serialize, Clone)]
+pub struct ScratchDiagram {
+    pub id: String,
+    pub content: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+// A scratch diagram as listed on the start screen.
+#[derive(Debug, Serialize, Clone)]
+pub struct ScratchSummary {
+    pub id: String,
+    pub diagram_type: Option<String>,
+    // The first line after the diagram keyword, to tell them apart.
+    pub preview: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+pub fn scratch_dir() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("scratch"))
+}
+
+// Saves a new scratch diagram, named by the time it was made, so a capture
+// is on disk before the user has decided where it belongs.
+pub fn create(content: Option<&str>) -> Result<ScratchDiagram, String> {
+    let dir = scratch_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create scratch folder: {}", e))?;
+
+    let now = Utc::now();
+    let base = format!("scratch-{}", now.format("%Y%m%d-%H%M%S"));
+    let mut id = base.clone();
+    let mut number = 2;
+    while dir.join(format!("{}.mmd", id)).exists() {
+        id = format!("{}-{}", base, number);
+        number += 1;
+    }
+
+    let content = content
+        .filter(|content| !content.trim().is_empty())
+        .unwrap_or(STARTER)
+        .to_string();
+    let path = dir.join(format!("{}.mmd", id));
+    fs::write(&path, &content).map_err(|e| format!("Failed to save scratch diagram: {}", e))?;
+    Ok(ScratchDiagram {
+        id,
+        content,
+        created_at: now.to_rfc3339(),
+        updated_at: now.to_rfc3339(),
+    })
+}
+
+// Ids come from the frontend, so only names `create` could have made are
+// turned into paths.
+fn path(id: &str) -> Result<PathBuf, String> {
+    let valid = id.starts_with("scratch-")
+        && id
+            .chars()
+            .all(|c| c.is_ascii_digit() || c.is_ascii_lowercase() || c == '-');
+    if !valid {
+        return Err(format!("Invalid scratch diagram id: {}", id));
+    }
+    let path = scratch_dir()?.join(format!("{}.mmd", id));
+    if !path.exists() {
+        return Err(format!("Unknown scratch diagram: {}", id));
+    }
+    Ok(path)
+}
+
+// The creation time is in the id; older files fall back to when they were
+// last written.
+fn created_at(id: &str, updated_at: &str) -> String {
+    id.get("scratch-".len().."scratch-YYYYMMDD-HHMMSS".len())
+        .and_then(|stamp| NaiveDateTime::parse_from_str(stamp, "%Y%m%d-%H%M%S").ok())
+        .map(|created| created.and_utc().to_rfc3339())
+        .unwrap_or_else(|| updated_at.to_string())
+}
+
+fn updated_at(path: &Path) -> String {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map(|modified| DateTime::<Utc>::from(modified).to_rfc3339())
+        .unwrap_or_default()
+}
+
+pub fn get(id: &str) -> Result<ScratchDiagram, String> {
+    let path = path(id)?;
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read scratch diagram: {}", e))?;
+    let updated_at = updated_at(&path);
+    Ok(ScratchDiagram {
+        id: id.to_string(),
+        content,
+        created_at: created_at(id, &updated_at),
+        updated_at,
+    })
+}
+
+// Scratch diagrams, most recently edited first.
+pub fn list() -> Result<Vec<ScratchSummary>, String> {
+    let dir = scratch_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries =
+        fs::read_dir(&dir).map_err(|e| format!("Failed to read scratch folder: {}", e))?;
+    let mut diagrams: Vec<ScratchSummary> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            get(name.strip_suffix(".mmd")?).ok()
+        })
+        .map(|diagram| {
+            let mut statements = diagram
+                .content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with("%%"))
+                .peekable();
+            if statements.peek() == Some(&"---") {
+                statements.next();
+                statements.by_ref().find(|line| *line == "---");
+            }
+            let diagram_type = detect_diagram_type(&diagram.content);
+            if diagram_type.is_some() {
+                statements.next();
+            }
+            ScratchSummary {
+                preview: statements
+                    .next()
+                    .unwrap_or_default()
+                    .chars()
+                    .take(80)
+                    .collect(),
+                diagram_type: diagram_type.map(str::to_string),
+                id: diagram.id,
+                created_at: diagram.created_at,
+                updated_at: diagram.updated_at,
+            }
+        })
+        .collect();
+    diagrams.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    Ok(diagrams)
+}
+
+// Called by the editor as the user types. The content goes to a temporary
+// file first, so a crash mid-write leaves the previous version.
+pub fn update(id: &str, content: &str) -> Result<ScratchDiagram, String> {
+    let path = path(id)?;
+    let temporary = path.with_extension("mmd.tmp");
+    fs::write(&temporary, content)
+        .and_then(|_| fs::rename(&temporary, &path))
+        .map_err(|e| format!("Failed to save scratch diagram: {}", e))?;
+    get(id)
+}
+
+pub fn delete(id: &str) -> Result<(), String> {
+    fs::remove_file(path(id)?).map_err(|e| format!("Failed to delete scratch diagram: {}", e))
+}
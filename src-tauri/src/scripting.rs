@@ -0,0 +1,187 @@
+// Rhai scripting for bulk edits. A script runs once per file with these
+// variables in scope:
+//
+//   diagram   the parsed `Diagram`; changes to it are written back
+//   path      the file being processed
+//
+// `Diagram` exposes `type`, `source` (get/set), `lines`, `nodes()`,
+// `edges()`, `rename_node(old, new)` and `replace_text(old, new)`.
+// `print` output is collected into the file's report.
+
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::model::DiagramModel;
+
+const MAX_OPERATIONS: u64 = 10_000_000;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ScriptFileResult {
+    pub path: String,
+    pub changed: bool,
+    pub output: Vec<String>,
+    pub error: Option<String>,
+}
+
+pub struct ScriptRunner {
+    engine: Engine,
+    ast: AST,
+    output: Arc<Mutex<Vec<String>>>,
+}
+
+impl ScriptRunner {
+    pub fn compile(script: &str) -> Result<Self, String> {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let print_output = output.clone();
+        engine.on_print(move |text| {
+            if let Ok(mut lines) = print_output.lock() {
+                lines.push(text.to_string());
+            }
+        });
+        let debug_output = output.clone();
+        engine.on_debug(move |text, _, _| {
+            if let Ok(mut lines) = debug_output.lock() {
+                lines.push(text.to_string());
+            }
+        });
+
+        register_diagram_api(&mut engine);
+
+        let ast = engine
+            .compile(script)
+            .map_err(|e| format!("Failed to compile script: {}", e))?;
+
+        Ok(Self {
+            engine,
+            ast,
+            output,
+        })
+    }
+
+    // Runs the script against `content`, returning the new content and any
+    // printed output.
+    pub fn run(&self, path: &Path, content: &str) -> Result<(String, Vec<String>), String> {
+        if let Ok(mut lines) = self.output.lock() {
+            lines.clear();
+        }
+
+        let mut scope = Scope::new();
+        scope.push("diagram", DiagramModel::parse(content));
+        scope.push_constant("path", path.to_string_lossy().to_string());
+
+        let result = self.engine.run_ast_with_scope(&mut scope, &self.ast);
+        let output = self
+            .output
+            .lock()
+            .map(|mut lines| std::mem::take(&mut *lines))
+            .unwrap_or_default();
+        result.map_err(|e| format!("Script failed: {}", e))?;
+
+        let diagram = scope
+            .get_value::<DiagramModel>("diagram")
+            .ok_or("Script replaced `diagram` with a different value")?;
+
+        Ok((diagram.to_source(), output))
+    }
+
+    pub fn run_file(&self, path: &Path, dry_run: bool) -> ScriptFileResult {
+        let mut result = ScriptFileResult {
+            path: path.to_string_lossy().to_string(),
+            changed: false,
+            output: Vec::new(),
+            error: None,
+        };
+
+        let outcome = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read file: {}", e))
+            .and_then(|original| {
+                let (updated, output) = self.run(path, &original)?;
+                result.output = output;
+                Ok((original, updated))
+            })
+            .and_then(|(original, updated)| {
+                // Content is compared ignoring the trailing newline `to_source` adds.
+                let changed = updated.trim_end() != original.trim_end();
+                if changed && !dry_run {
+                    fs::write(path, &updated)
+                        .map_err(|e| format!("Failed to write file: {}", e))?;
+                }
+                Ok(changed)
+            });
+
+        match outcome {
+            Ok(changed) => result.changed = changed,
+            Err(e) => result.error = Some(e),
+        }
+
+        result
+    }
+}
+
+fn register_diagram_api(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<DiagramModel>("Diagram")
+        .register_get("type", |diagram: &mut DiagramModel| {
+            diagram.diagram_type.clone().unwrap_or_default()
+        })
+        .register_get_set(
+            "source",
+            |diagram: &mut DiagramModel| diagram.to_source(),
+            |diagram: &mut DiagramModel, source: String| *diagram = DiagramModel::parse(&source),
+        )
+        .register_get_set(
+            "lines",
+            |diagram: &mut DiagramModel| -> Array {
+                diagram.lines.iter().cloned().map(Dynamic::from).collect()
+            },
+            |diagram: &mut DiagramModel, lines: Array| {
+                diagram.lines = lines.into_iter().map(|line| line.to_string()).collect();
+            },
+        )
+        .register_fn("nodes", |diagram: &mut DiagramModel| -> Array {
+            diagram
+                .nodes()
+                .into_iter()
+                .map(|node| {
+                    let mut map = Map::new();
+                    map.insert("id".into(), node.id.into());
+                    map.insert("label".into(), optional(node.label));
+                    Dynamic::from_map(map)
+                })
+                .collect()
+        })
+        .register_fn("edges", |diagram: &mut DiagramModel| -> Array {
+            diagram
+                .edges()
+                .into_iter()
+                .map(|edge| {
+                    let mut map = Map::new();
+                    map.insert("from".into(), edge.from.into());
+                    map.insert("to".into(), edge.to.into());
+                    map.insert("label".into(), optional(edge.label));
+                    map.insert("line".into(), (edge.line as i64 + 1).into());
+                    Dynamic::from_map(map)
+                })
+                .collect()
+        })
+        .register_fn(
+            "rename_node",
+            |diagram: &mut DiagramModel, old: &str, new: &str| diagram.rename_node(old, new) as i64,
+        )
+        .register_fn(
+            "replace_text",
+            |diagram: &mut DiagramModel, old: &str, new: &str| {
+                diagram.replace_text(old, new) as i64
+            },
+        );
+}
+
+fn optional(value: Option<String>) -> Dynamic {
+    value.map(Dynamic::from).unwrap_or(Dynamic::UNIT)
+}
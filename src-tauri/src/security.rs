@@ -0,0 +1,617 @@
+use quick_xml::events::attributes::Attribute;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, Writer};
+use regex::{Captures, Regex};
+use serde::Serialize;
+
+// URL schemes that run code or render a page of their own when followed.
+const SCRIPT_SCHEMES: [&str; 4] = [
+    "javascript:",
+    "vbscript:",
+    "data:text/html",
+    "data:image/svg+xml",
+];
+
+// Tags whose content is dropped along with them. Style elements are left
+// alone, since every rendered SVG carries one.
+const CONTENT_TAGS: [&str; 4] = ["script", "iframe", "object", "noscript"];
+// Tags that are dropped on their own. `animate` and `set` can write a
+// script URL into another element's `href`.
+const EMPTY_TAGS: [&str; 7] = ["embed", "base", "meta", "link", "form", "animate", "set"];
+
+// Elements rendered SVG may keep: shapes, text, paint servers and filters.
+// Everything else, animation included, is removed with its content.
+const SVG_ELEMENTS: [&str; 39] = [
+    "svg",
+    "g",
+    "defs",
+    "symbol",
+    "use",
+    "title",
+    "desc",
+    "style",
+    "path",
+    "rect",
+    "circle",
+    "ellipse",
+    "line",
+    "polyline",
+    "polygon",
+    "text",
+    "tspan",
+    "textpath",
+    "marker",
+    "lineargradient",
+    "radialgradient",
+    "stop",
+    "clippath",
+    "mask",
+    "pattern",
+    "filter",
+    "feblend",
+    "fecolormatrix",
+    "fecomposite",
+    "fedropshadow",
+    "feflood",
+    "fegaussianblur",
+    "femerge",
+    "femergenode",
+    "femorphology",
+    "feoffset",
+    "image",
+    "foreignobject",
+    "a",
+];
+// The HTML Mermaid puts in labels, inside `foreignObject`.
+const HTML_ELEMENTS: [&str; 26] = [
+    "div", "span", "p", "br", "b", "i", "u", "s", "em", "strong", "code", "pre", "small", "sub",
+    "sup", "img", "ul", "ol", "li", "table", "thead", "tbody", "tr", "th", "td", "hr",
+];
+// Attributes holding a URL, which must start with one of SAFE_URL_PREFIXES
+// or be a relative reference.
+const URL_ATTRIBUTES: [&str; 4] = ["href", "src", "action", "formaction"];
+const SAFE_URL_PREFIXES: [&str; 8] = [
+    "#",
+    "http://",
+    "https://",
+    "mailto:",
+    "data:image/png",
+    "data:image/jpeg",
+    "data:image/gif",
+    "data:image/webp",
+];
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    ScriptTag,
+    EmbeddedContent,
+    EventHandler,
+    ScriptUrl,
+    // `click` directives that call JavaScript functions of the page.
+    Callback,
+    // Init directives or frontmatter lowering Mermaid's security level.
+    SecurityLevel,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Finding {
+    pub line: usize,
+    pub kind: FindingKind,
+    pub message: String,
+    pub snippet: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Sanitized {
+    pub content: String,
+    // What was found in the original content, and stripped.
+    pub findings: Vec<Finding>,
+}
+
+// Decodes numeric entities, as HTML (`&#106;`) and Mermaid (`#106;`) write
+// them, with or without the `;` browsers do not insist on, and drops
+// whitespace and control characters, which browsers ignore inside a URL
+// scheme.
+fn normalize(text: &str) -> String {
+    let entity = Regex::new(r"(?i)&#(x[0-9a-f]+|[0-9]+);?|#(x[0-9a-f]+|[0-9]+);").unwrap();
+    let decoded = entity.replace_all(text, |caps: &Captures| {
+        let code = caps.get(1).or(caps.get(2)).map_or("", |code| code.as_str());
+        let value = match code.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => code.parse().ok(),
+        };
+        value
+            .and_then(char::from_u32)
+            .map(String::from)
+            .unwrap_or_default()
+    });
+    decoded
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+fn is_script_url(value: &str) -> bool {
+    let value = normalize(value);
+    let value = value.trim_matches(['"', '\'']);
+    SCRIPT_SCHEMES
+        .iter()
+        .any(|scheme| value.starts_with(scheme))
+}
+
+// A URL in rendered SVG is kept if it is a fragment, a web or mail link, a
+// raster data URI, or a relative reference without a scheme.
+fn is_safe_url(value: &str) -> bool {
+    let value = normalize(value);
+    if SAFE_URL_PREFIXES
+        .iter()
+        .any(|prefix| value.starts_with(prefix))
+    {
+        return true;
+    }
+    match value.find(':') {
+        Some(colon) => value[..colon].contains(['/', '?', '#']),
+        None => true,
+    }
+}
+
+// Servers the app sends documents or credentials to must use https, unless
+// they run on this computer.
+pub fn is_secure_endpoint(url: &reqwest::Url) -> bool {
+    if url.scheme() == "https" {
+        return true;
+    }
+    let host = url
+        .host_str()
+        .unwrap_or("")
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    url.scheme() == "http"
+        && (host.eq_ignore_ascii_case("localhost")
+            || host
+                .parse::<std::net::IpAddr>()
+                .map(|ip| ip.is_loopback())
+                .unwrap_or(false))
+}
+
+fn line_at(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+fn snippet(line: &str) -> String {
+    let line = line.trim();
+    match line.char_indices().nth(80) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+struct Patterns {
+    tag: Regex,
+    handler: Regex,
+    url_attribute: Regex,
+    click_url: Regex,
+    callback: Regex,
+    security_level: Regex,
+}
+
+impl Patterns {
+    fn new() -> Self {
+        let tags = CONTENT_TAGS
+            .iter()
+            .chain(EMPTY_TAGS.iter())
+            .copied()
+            .collect::<Vec<_>>()
+            .join("|");
+        // Tag and attribute patterns run over the whole text, since tags
+        // may span lines.
+        Patterns {
+            tag: Regex::new(&format!(r"(?i)<\s*/?\s*({})\b[^>]*>?", tags)).unwrap(),
+            handler: Regex::new(r#"(?i)(<[^>]*?)\s+on[a-z]+\s*=\s*("[^"]*"|'[^']*'|[^\s>]+)"#)
+                .unwrap(),
+            url_attribute: Regex::new(
+                r#"(?i)\b((?:xlink:)?href|src|action|formaction|to|values)\s*=\s*("[^"]*"|'[^']*'|[^\s>]+)"#,
+            )
+            .unwrap(),
+            click_url: Regex::new(
+                r#"(?i)^\s*(?:click|link)\s+\S+\s+(?:href\s+)?"([^"]*)"|^\s*links?\s+[^:]+:(.*)$"#,
+            )
+            .unwrap(),
+            callback: Regex::new(r"^\s*click\s+\S+\s+(?:call\s|([A-Za-z_$][\w$]*)(?:\s|$))")
+                .unwrap(),
+            security_level: Regex::new(r#"(?i)(securitylevel["']?\s*:\s*["']?)loose"#).unwrap(),
+        }
+    }
+
+    fn is_callback(&self, line: &str) -> bool {
+        self.callback
+            .captures(line)
+            .is_some_and(|caps| caps.get(1).map_or("", |name| name.as_str()) != "href")
+    }
+
+    fn is_script_directive(&self, line: &str) -> bool {
+        self.click_url.captures(line).is_some_and(|caps| {
+            caps.get(1).or(caps.get(2)).is_some_and(|target| {
+                let target = normalize(target.as_str());
+                SCRIPT_SCHEMES.iter().any(|scheme| target.contains(scheme))
+            })
+        })
+    }
+}
+
+// Flags script tags, embedded documents, event handler attributes, script
+// URLs, click callbacks and a lowered security level, in diagram source or
+// in rendered SVG.
+pub fn scan(content: &str) -> Vec<Finding> {
+    let patterns = Patterns::new();
+    let lines: Vec<&str> = content.lines().collect();
+    let mut findings = Vec::new();
+    let mut push = |line: usize, kind: FindingKind, message: String| {
+        findings.push(Finding {
+            line,
+            kind,
+            message,
+            snippet: snippet(lines.get(line - 1).copied().unwrap_or_default()),
+        })
+    };
+
+    for caps in patterns.tag.captures_iter(content) {
+        if caps[0]
+            .trim_start_matches('<')
+            .trim_start()
+            .starts_with('/')
+        {
+            continue;
+        }
+        let line = line_at(content, caps.get(0).map_or(0, |tag| tag.start()));
+        let name = caps[1].to_lowercase();
+        if name == "script" {
+            push(
+                line,
+                FindingKind::ScriptTag,
+                "Embedded <script> tag".to_string(),
+            );
+        } else {
+            push(
+                line,
+                FindingKind::EmbeddedContent,
+                format!("Embedded <{}> tag", name),
+            );
+        }
+    }
+    for found in patterns.handler.find_iter(content) {
+        push(
+            line_at(content, found.end()),
+            FindingKind::EventHandler,
+            "HTML event handler attribute".to_string(),
+        );
+    }
+    for caps in patterns.url_attribute.captures_iter(content) {
+        if is_script_url(&caps[2]) {
+            push(
+                line_at(
+                    content,
+                    caps.get(0).map_or(0, |attribute| attribute.start()),
+                ),
+                FindingKind::ScriptUrl,
+                "Link runs script when followed".to_string(),
+            );
+        }
+    }
+    for (index, line) in lines.iter().enumerate() {
+        if patterns.is_script_directive(line) {
+            push(
+                index + 1,
+                FindingKind::ScriptUrl,
+                "Link runs script when followed".to_string(),
+            );
+        }
+        if patterns.is_callback(line) {
+            push(
+                index + 1,
+                FindingKind::Callback,
+                "Click callback calls page JavaScript".to_string(),
+            );
+        }
+        if patterns.security_level.is_match(line) {
+            push(
+                index + 1,
+                FindingKind::SecurityLevel,
+                "Security level is lowered to loose".to_string(),
+            );
+        }
+    }
+    findings.sort_by_key(|finding| finding.line);
+    findings
+}
+
+fn is_svg(content: &str) -> bool {
+    let start = content.trim_start();
+    start.starts_with("<svg") || start.starts_with("<?xml")
+}
+
+// Removes what `scan` flags: script and embedded tags with their content,
+// event handlers, script URLs and the directives holding them. A lowered
+// security level is raised back to strict. Rendered SVG goes through
+// `sanitize_svg` instead.
+pub fn sanitize(content: &str) -> Sanitized {
+    if is_svg(content) {
+        match sanitize_svg(content) {
+            Ok(sanitized) => return sanitized,
+            Err(e) => tracing::warn!(error = %e, "sanitizing SVG as text"),
+        }
+    }
+    let findings = scan(content);
+    if findings.is_empty() {
+        return Sanitized {
+            content: content.to_string(),
+            findings,
+        };
+    }
+    let patterns = Patterns::new();
+
+    let lines: Vec<&str> = content
+        .lines()
+        .filter(|line| !patterns.is_callback(line) && !patterns.is_script_directive(line))
+        .collect();
+    let mut text = lines.join("\n");
+    for tag in CONTENT_TAGS {
+        let block = Regex::new(&format!(r"(?is)<\s*{0}\b.*?<\s*/\s*{0}\s*>", tag)).unwrap();
+        text = block.replace_all(&text, "").to_string();
+    }
+    text = patterns.tag.replace_all(&text, "").to_string();
+    while patterns.handler.is_match(&text) {
+        text = patterns.handler.replace_all(&text, "$1").to_string();
+    }
+    text = patterns
+        .url_attribute
+        .replace_all(&text, |caps: &Captures| {
+            if is_script_url(&caps[2]) {
+                format!("{}='#'", &caps[1])
+            } else {
+                caps[0].to_string()
+            }
+        })
+        .to_string();
+    text = patterns
+        .security_level
+        .replace_all(&text, "${1}strict")
+        .to_string();
+
+    if content.ends_with('\n') {
+        text.push('\n');
+    }
+    Sanitized {
+        content: text,
+        findings,
+    }
+}
+
+fn lowercase(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).to_lowercase()
+}
+
+// Keeps the attributes of an allowed element that cannot run script:
+// anything but event handlers, and URLs that pass `is_safe_url`.
+fn clean_element(
+    element: &BytesStart,
+    mut finding: impl FnMut(FindingKind, String),
+) -> BytesStart<'static> {
+    let name = String::from_utf8_lossy(element.name().as_ref()).to_string();
+    let mut cleaned = BytesStart::new(name);
+    for attribute in element.attributes().with_checks(false).flatten() {
+        let key = lowercase(attribute.key.local_name().as_ref());
+        if key.starts_with("on") {
+            finding(
+                FindingKind::EventHandler,
+                "HTML event handler attribute".to_string(),
+            );
+            continue;
+        }
+        let value = attribute
+            .unescape_value()
+            .map(|value| value.to_string())
+            .unwrap_or_else(|_| String::from_utf8_lossy(&attribute.value).to_string());
+        if URL_ATTRIBUTES.contains(&key.as_str()) && !is_safe_url(&value) {
+            finding(
+                FindingKind::ScriptUrl,
+                format!("Removed {} with an unsupported URL", key),
+            );
+            continue;
+        }
+        if key == "style" && normalize(&value).contains("url(") && !is_safe_css(&value) {
+            finding(
+                FindingKind::ScriptUrl,
+                "Removed a style with an unsupported URL".to_string(),
+            );
+            continue;
+        }
+        cleaned.push_attribute(Attribute {
+            key: attribute.key,
+            value: attribute.value,
+        });
+    }
+    cleaned.into_owned()
+}
+
+// Inline styles may refer to gradients and markers in the same document,
+// and nothing else.
+fn is_safe_css(style: &str) -> bool {
+    let url = Regex::new(r#"url\(\s*["']?([^"')]*)"#).unwrap();
+    let style = normalize(style);
+    let safe = url
+        .captures_iter(&style)
+        .all(|caps| caps[1].starts_with('#'));
+    safe
+}
+
+// Rebuilds rendered SVG from an allowlist: only SVG_ELEMENTS and
+// HTML_ELEMENTS are kept, without event handlers or URLs other than web
+// links, fragments and raster images. Unlike `sanitize`, nothing depends on
+// how the markup is written, so entities, line breaks and animation
+// elements cannot get script past it. Runs on the finished SVG, after
+// images are inlined.
+pub fn sanitize_svg(svg: &str) -> Result<Sanitized, String> {
+    let mut reader = Reader::from_str(svg);
+    let mut writer = Writer::new(Vec::new());
+    let mut findings = Vec::new();
+    let lines: Vec<&str> = svg.lines().collect();
+    // Depth inside an element that is being removed with its content.
+    let mut skipping = 0usize;
+
+    loop {
+        let position = reader.buffer_position() as usize;
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Failed to parse SVG: {}", e))?;
+        let line = line_at(svg, position);
+        let mut finding = |kind: FindingKind, message: String| {
+            findings.push(Finding {
+                line,
+                kind,
+                message,
+                snippet: snippet(lines.get(line - 1).copied().unwrap_or_default()),
+            })
+        };
+        let event = match event {
+            Event::Eof => break,
+            Event::Start(_) | Event::Empty(_) if skipping > 0 => {
+                if matches!(event, Event::Start(_)) {
+                    skipping += 1;
+                }
+                continue;
+            }
+            Event::End(_) if skipping > 0 => {
+                skipping -= 1;
+                continue;
+            }
+            _ if skipping > 0 => continue,
+            Event::Start(ref element) | Event::Empty(ref element) => {
+                let name = lowercase(element.local_name().as_ref());
+                if !SVG_ELEMENTS.contains(&name.as_str()) && !HTML_ELEMENTS.contains(&name.as_str())
+                {
+                    let kind = if name == "script" {
+                        FindingKind::ScriptTag
+                    } else {
+                        FindingKind::EmbeddedContent
+                    };
+                    finding(kind, format!("Removed <{}> element", name));
+                    if matches!(event, Event::Start(_)) {
+                        skipping = 1;
+                    }
+                    continue;
+                }
+                let cleaned = clean_element(element, &mut finding);
+                if matches!(event, Event::Start(_)) {
+                    Event::Start(cleaned)
+                } else {
+                    Event::Empty(cleaned)
+                }
+            }
+            // Declarations could define entities; instructions are not
+            // needed to display SVG.
+            Event::DocType(_) | Event::PI(_) => continue,
+            event => event,
+        };
+        writer
+            .write_event(event)
+            .map_err(|e| format!("Failed to write SVG: {}", e))?;
+    }
+
+    let content = String::from_utf8(writer.into_inner())
+        .map_err(|e| format!("Failed to write SVG: {}", e))?;
+    Ok(Sanitized { content, findings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(sanitized: &Sanitized) -> Vec<FindingKind> {
+        sanitized
+            .findings
+            .iter()
+            .map(|finding| finding.kind)
+            .collect()
+    }
+
+    fn secure(url: &str) -> bool {
+        is_secure_endpoint(&reqwest::Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn keeps_ordinary_rendered_svg() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"><defs><linearGradient id="g"/></defs><g class="node"><rect style="fill:url(#g)" width="5" height="5"/><text>A &amp; B</text><foreignObject><div xmlns="http://www.w3.org/1999/xhtml"><span>Label</span></div></foreignObject></g><a href="https://example.com"><text>link</text></a><image href="data:image/png;base64,AAAA"/></svg>"##;
+        let sanitized = sanitize_svg(svg).unwrap();
+        assert_eq!(sanitized.content, svg);
+        assert!(sanitized.findings.is_empty());
+    }
+
+    #[test]
+    fn removes_scripts_and_event_handlers() {
+        let svg = r#"<svg><script>alert(1)</script><g onload="alert(1)" onClick="x()"><text>ok</text></g></svg>"#;
+        let sanitized = sanitize_svg(svg).unwrap();
+        assert_eq!(sanitized.content, "<svg><g><text>ok</text></g></svg>");
+        assert_eq!(
+            kinds(&sanitized),
+            [
+                FindingKind::ScriptTag,
+                FindingKind::EventHandler,
+                FindingKind::EventHandler
+            ]
+        );
+    }
+
+    #[test]
+    fn removes_script_urls_however_they_are_written() {
+        let svg = "<svg><a href=\"&#106;avascript:alert(1)\"><text>x</text></a><a href=\"java\nscript:alert(1)\"><text>y</text></a><a xlink:href=\"data:text/html,x\"><text>z</text></a></svg>";
+        let sanitized = sanitize_svg(svg).unwrap();
+        assert_eq!(
+            sanitized.content,
+            "<svg><a><text>x</text></a><a><text>y</text></a><a><text>z</text></a></svg>"
+        );
+        assert_eq!(kinds(&sanitized), [FindingKind::ScriptUrl; 3]);
+    }
+
+    #[test]
+    fn removes_animations_that_could_set_links() {
+        let svg = r##"<svg><a href="#top"><animate attributeName="href" to="javascript:alert(1)"/><set attributeName="href" to="javascript:alert(1)"/><text>x</text></a></svg>"##;
+        let sanitized = sanitize_svg(svg).unwrap();
+        assert_eq!(
+            sanitized.content,
+            r##"<svg><a href="#top"><text>x</text></a></svg>"##
+        );
+    }
+
+    #[test]
+    fn removes_unknown_elements_and_outside_style_urls() {
+        let svg = r#"<svg><rect style="fill: url(http://evil.example/x.svg#a)"/><foreignObject><iframe src="https://evil.example"></iframe><div>kept</div></foreignObject><unknown><text>inner</text></unknown></svg>"#;
+        let sanitized = sanitize_svg(svg).unwrap();
+        assert_eq!(
+            sanitized.content,
+            "<svg><rect/><foreignObject><div>kept</div></foreignObject></svg>"
+        );
+        assert_eq!(
+            kinds(&sanitized),
+            [
+                FindingKind::ScriptUrl,
+                FindingKind::EmbeddedContent,
+                FindingKind::EmbeddedContent
+            ]
+        );
+    }
+
+    #[test]
+    fn secure_endpoints_use_https_or_stay_on_this_computer() {
+        assert!(secure("https://example.com/api"));
+        assert!(secure("http://localhost:8080"));
+        assert!(secure("http://127.0.0.1:11434"));
+        assert!(secure("http://[::1]:3000"));
+        assert!(!secure("http://example.com"));
+        assert!(!secure("http://10.0.0.5"));
+        assert!(!secure("http://localhost.example.com"));
+        assert!(!secure("ftp://localhost"));
+    }
+}
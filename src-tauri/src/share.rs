@@ -0,0 +1,92 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+use crate::deep_link;
+
+// Share links carry the whole diagram, zlib-compressed, in the fragment:
+// `flowcraft://view#diagram=<base64url>`, or the same fragment after a web
+// viewer's URL. Fragments never reach a server, so the diagram stays with
+// whoever has the link.
+const PARAM: &str = "diagram=";
+// Windows hands deep links to the app on the command line, which is limited
+// to 32K characters.
+const MAX_APP_LINK_CHARS: usize = 32_000;
+// Decompressing stops here, so a crafted link cannot exhaust memory.
+const MAX_SOURCE_BYTES: u64 = 1024 * 1024;
+
+fn app_link_prefix() -> String {
+    format!("{}://view", deep_link::SCHEME)
+}
+
+pub fn is_app_link(url: &str) -> bool {
+    url.trim().starts_with(&app_link_prefix())
+}
+
+pub fn is_share_link(text: &str) -> bool {
+    let text = text.trim();
+    (is_app_link(text) || text.starts_with("https://"))
+        && !text.contains(char::is_whitespace)
+        && fragment_payload(text).is_some()
+}
+
+fn fragment_payload(url: &str) -> Option<&str> {
+    let (_, fragment) = url.split_once('#')?;
+    fragment
+        .split('&')
+        .find_map(|param| param.strip_prefix(PARAM))
+}
+
+// Builds a link that opens the diagram in the app, or in the web viewer at
+// `viewer_url` when one is given.
+pub fn create_link(content: &str, viewer_url: Option<&str>) -> Result<String, String> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder
+        .write_all(content.as_bytes())
+        .map_err(|e| format!("Failed to compress diagram: {}", e))?;
+    let compressed = encoder
+        .finish()
+        .map_err(|e| format!("Failed to compress diagram: {}", e))?;
+    let payload = URL_SAFE_NO_PAD.encode(compressed);
+
+    match viewer_url {
+        Some(viewer_url) => {
+            let base = reqwest::Url::parse(viewer_url)
+                .map_err(|e| format!("Invalid viewer URL: {}", e))?;
+            if base.scheme() != "https" {
+                return Err("The viewer URL must use https".to_string());
+            }
+            let base = viewer_url.split('#').next().unwrap_or(viewer_url);
+            Ok(format!("{}#{}{}", base, PARAM, payload))
+        }
+        None => {
+            let link = format!("{}#{}{}", app_link_prefix(), PARAM, payload);
+            if link.len() > MAX_APP_LINK_CHARS {
+                return Err(
+                    "Diagram is too large for an app link; use a web viewer link".to_string(),
+                );
+            }
+            Ok(link)
+        }
+    }
+}
+
+pub fn decode_link(url: &str) -> Result<String, String> {
+    let payload = fragment_payload(url.trim()).ok_or("Not a share link")?;
+    let compressed = URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .map_err(|e| format!("Invalid share link: {}", e))?;
+
+    let mut content = Vec::new();
+    ZlibDecoder::new(compressed.as_slice())
+        .take(MAX_SOURCE_BYTES + 1)
+        .read_to_end(&mut content)
+        .map_err(|e| format!("Invalid share link: {}", e))?;
+    if content.len() as u64 > MAX_SOURCE_BYTES {
+        return Err("Shared diagram is too large".to_string());
+    }
+    String::from_utf8(content).map_err(|e| format!("Invalid share link: {}", e))
+}
@@ -0,0 +1,49 @@
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+use crate::keybindings::{self, Keybinding};
+use crate::{focus_main_window, open_capture_window, AppStateType};
+
+fn current_bindings(app: &AppHandle) -> Vec<Keybinding> {
+    app.state::<AppStateType>()
+        .lock()
+        .map(|app_state| keybindings::resolve(&app_state.keybindings))
+        .unwrap_or_else(|_| keybindings::resolve(&Default::default()))
+}
+
+// Re-registers every global action with the OS. Called on startup and
+// whenever a global binding changes.
+pub fn register(app: &AppHandle) -> Result<(), String> {
+    let global_shortcut = app.global_shortcut();
+    global_shortcut
+        .unregister_all()
+        .map_err(|e| format!("Failed to unregister global shortcuts: {}", e))?;
+
+    for binding in current_bindings(app).into_iter().filter(|b| b.global) {
+        let Some(accelerator) = binding.accelerator else {
+            continue;
+        };
+        let action = binding.action;
+
+        global_shortcut
+            .on_shortcut(accelerator.as_str(), move |app, _shortcut, event| {
+                if event.state() != ShortcutState::Pressed {
+                    return;
+                }
+                tracing::debug!(action = %action, "global shortcut");
+                // Quick capture opens its own window and leaves the main
+                // one where it is.
+                if action == "quick_capture" {
+                    if let Err(e) = open_capture_window(app) {
+                        tracing::warn!(error = %e, "quick capture failed");
+                    }
+                    return;
+                }
+                focus_main_window(app);
+                let _ = app.emit("global-shortcut", &action);
+            })
+            .map_err(|e| format!("Failed to register {}: {}", accelerator, e))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,275 @@
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::secrets;
+
+// The private key is a base64 Ed25519 seed kept in the OS keychain.
+const SIGNING_KEY_SECRET: &str = "signing-key";
+const FORMAT: &str = "flowcraft-signature";
+const VERSION: u32 = 1;
+const ALGORITHM: &str = "ed25519";
+// Embedded signatures go on a final comment line, which SVG and PDF readers
+// both ignore. Everything before that line is what gets signed.
+const SVG_MARKER: &[u8] = b"\n<!-- flowcraft-signature ";
+const PDF_MARKER: &[u8] = b"\n%flowcraft-signature ";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureMode {
+    // A `<file>.sig` next to the export; the export itself is untouched.
+    Detached,
+    Embedded,
+}
+
+impl SignatureMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "detached" => Ok(SignatureMode::Detached),
+            "embedded" => Ok(SignatureMode::Embedded),
+            _ => Err(format!("Unknown signature mode: {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SigningKeyInfo {
+    pub public_key: String,
+    pub fingerprint: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Verification {
+    // The signature matches the file and was made with a trusted key.
+    pub valid: bool,
+    // The signature matches the file, whoever made it. Anyone can sign a
+    // file with a key of their own, so this alone proves nothing.
+    pub signature_matches: bool,
+    // The signer is this machine's key or one of the trusted keys.
+    pub trusted: bool,
+    pub embedded: bool,
+    pub public_key: String,
+    pub fingerprint: String,
+    // Whether the signature was made with this machine's signing key.
+    pub own_key: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DetachedSignature {
+    format: String,
+    version: u32,
+    algorithm: String,
+    public_key: String,
+    signature: String,
+}
+
+fn info(key: &VerifyingKey) -> SigningKeyInfo {
+    let digest = Sha256::digest(key.as_bytes());
+    SigningKeyInfo {
+        public_key: general_purpose::STANDARD.encode(key.as_bytes()),
+        fingerprint: digest[..8]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join(":"),
+    }
+}
+
+fn decode_key(encoded: &str) -> Result<SigningKey, String> {
+    let bytes = general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| format!("Invalid signing key: {}", e))?;
+    let seed: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| "Invalid signing key: expected a 32-byte Ed25519 seed".to_string())?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+fn load_key() -> Result<SigningKey, String> {
+    let encoded = secrets::get_secret(SIGNING_KEY_SECRET)?
+        .ok_or("No signing key configured; generate or import one first")?;
+    decode_key(&encoded)
+}
+
+pub fn key_info() -> Result<Option<SigningKeyInfo>, String> {
+    match secrets::get_secret(SIGNING_KEY_SECRET)? {
+        Some(encoded) => Ok(Some(info(&decode_key(&encoded)?.verifying_key()))),
+        None => Ok(None),
+    }
+}
+
+// Stores `private_key` (a base64 Ed25519 seed) as the signing key, or a new
+// random key when none is given.
+pub fn set_key(private_key: Option<&str>) -> Result<SigningKeyInfo, String> {
+    let key = match private_key {
+        Some(encoded) => decode_key(encoded)?,
+        None => {
+            let mut seed = [0u8; 32];
+            getrandom::getrandom(&mut seed)
+                .map_err(|e| format!("Failed to generate signing key: {}", e))?;
+            SigningKey::from_bytes(&seed)
+        }
+    };
+    secrets::set_secret(
+        SIGNING_KEY_SECRET,
+        &general_purpose::STANDARD.encode(key.to_bytes()),
+    )?;
+    Ok(info(&key.verifying_key()))
+}
+
+pub fn detached_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".sig");
+    PathBuf::from(name)
+}
+
+fn marker(format: &str) -> Result<&'static [u8], String> {
+    match format {
+        "svg" => Ok(SVG_MARKER),
+        "pdf" => Ok(PDF_MARKER),
+        _ => Err(format!(
+            "Only SVG and PDF exports can be signed, not {}",
+            format
+        )),
+    }
+}
+
+fn decode_public_key(encoded: &str) -> Option<VerifyingKey> {
+    general_purpose::STANDARD
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+}
+
+// Checks keys to trust, given as base64 Ed25519 public keys.
+pub fn validate_public_keys(keys: &[String]) -> Result<(), String> {
+    match keys.iter().find(|key| decode_public_key(key).is_none()) {
+        Some(key) => Err(format!("Invalid public key: {}", key)),
+        None => Ok(()),
+    }
+}
+
+fn format_from_path(path: &Path) -> Option<String> {
+    Some(path.extension()?.to_string_lossy().to_lowercase())
+}
+
+// Returns `bytes` with a signature line appended.
+pub fn embed(bytes: &[u8], format: &str) -> Result<Vec<u8>, String> {
+    let marker = marker(format)?;
+    let key = load_key()?;
+    let signature = key.sign(bytes);
+    let line = format!(
+        "{} {} {}",
+        ALGORITHM,
+        general_purpose::STANDARD.encode(key.verifying_key().as_bytes()),
+        general_purpose::STANDARD.encode(signature.to_bytes())
+    );
+
+    let mut signed = bytes.to_vec();
+    signed.extend_from_slice(marker);
+    signed.extend_from_slice(line.as_bytes());
+    signed.extend_from_slice(if format == "svg" { b" -->\n" } else { b"\n" });
+    Ok(signed)
+}
+
+// Returns the contents of a detached signature file for `bytes`.
+pub fn detached(bytes: &[u8], format: &str) -> Result<String, String> {
+    marker(format)?;
+    let key = load_key()?;
+    let signature = DetachedSignature {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        algorithm: ALGORITHM.to_string(),
+        public_key: general_purpose::STANDARD.encode(key.verifying_key().as_bytes()),
+        signature: general_purpose::STANDARD.encode(key.sign(bytes).to_bytes()),
+    };
+    serde_json::to_string_pretty(&signature)
+        .map_err(|e| format!("Failed to write signature: {}", e))
+}
+
+fn check(
+    signed: &[u8],
+    public_key: &str,
+    signature: &str,
+    embedded: bool,
+    trusted_keys: &[String],
+) -> Result<Verification, String> {
+    let public_key = decode_public_key(public_key).ok_or("Invalid public key in signature")?;
+    let signature = general_purpose::STANDARD
+        .decode(signature)
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or("Invalid signature")?;
+
+    let own_key = load_key()
+        .map(|key| key.verifying_key() == public_key)
+        .unwrap_or(false);
+    let trusted = own_key
+        || trusted_keys
+            .iter()
+            .any(|key| decode_public_key(key) == Some(public_key));
+    let signature_matches = public_key.verify(signed, &signature).is_ok();
+    let info = info(&public_key);
+    Ok(Verification {
+        valid: signature_matches && trusted,
+        signature_matches,
+        trusted,
+        embedded,
+        public_key: info.public_key,
+        fingerprint: info.fingerprint,
+        own_key,
+    })
+}
+
+fn find_last(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .rposition(|window| window == needle)
+}
+
+// Verifies an exported file against its `.sig` file, or against the
+// signature embedded in it when there is no `.sig`. The public key in the
+// signature only says who claims to have signed; the file is valid only if
+// that is this machine's key or one of `trusted_keys`.
+pub fn verify(path: &Path, trusted_keys: &[String]) -> Result<Verification, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let sig_path = detached_path(path);
+    if sig_path.is_file() {
+        let data = fs::read_to_string(&sig_path)
+            .map_err(|e| format!("Failed to read signature: {}", e))?;
+        let detached: DetachedSignature =
+            serde_json::from_str(&data).map_err(|e| format!("Invalid signature file: {}", e))?;
+        if detached.format != FORMAT || detached.algorithm != ALGORITHM {
+            return Err("Unsupported signature format".to_string());
+        }
+        return check(
+            &bytes,
+            &detached.public_key,
+            &detached.signature,
+            false,
+            trusted_keys,
+        );
+    }
+
+    let format = format_from_path(path).unwrap_or_default();
+    let marker = marker(&format)?;
+    let start = find_last(&bytes, marker)
+        .ok_or_else(|| format!("No signature found for {}", path.display()))?;
+    let line = String::from_utf8_lossy(&bytes[start + marker.len()..]);
+    let fields: Vec<&str> = line
+        .trim()
+        .trim_end_matches("-->")
+        .split_whitespace()
+        .collect();
+    match fields.as_slice() {
+        [ALGORITHM, public_key, signature] => {
+            check(&bytes[..start], public_key, signature, true, trusted_keys)
+        }
+        _ => Err("Unsupported signature format".to_string()),
+    }
+}
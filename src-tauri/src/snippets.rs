@@ -0,0 +1,298 @@
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::get_app_data_dir;
+
+// Snippets are fragments inserted into a diagram, unlike templates, which
+// start one. Larger ones belong in a template.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+// Placeholders are `${name}` or `${name:default}`. Every occurrence of a
+// name gets the same value, and the first default given for it applies.
+const PLACEHOLDER: &str = r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?::([^}]*))?\}";
+
+const BUILTIN_SNIPPETS: [(&str, &str, &str, &str, &str); 3] = [
+    (
+        "legend",
+        "Legend",
+        "A subgraph explaining the node shapes",
+        "Flowchart",
+        "subgraph ${id:legend}[${title:Legend}]\n    direction LR\n    ${id:legend}_terminal([Start or end])\n    ${id:legend}_step[Step]\n    ${id:legend}_decision{Decision}\nend\n",
+    ),
+    (
+        "decision",
+        "Styled decision",
+        "A highlighted decision with yes and no branches",
+        "Flowchart",
+        "${id:check}{${question:Is it valid?}}\n${id:check} -->|Yes| ${yes:accept}\n${id:check} -->|No| ${no:reject}\nstyle ${id:check} fill:#fff3cd,stroke:#d39e00,stroke-width:2px\n",
+    ),
+    (
+        "note",
+        "Note",
+        "A note spanning two participants",
+        "Sequence",
+        "Note over ${from:Alice},${to:Bob}: ${text:Note}\n",
+    ),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Placeholder {
+    pub name: String,
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Snippet {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub category: String,
+    pub body: String,
+    #[serde(default, skip_deserializing)]
+    pub builtin: bool,
+    // Filled in when listing, for the insert form.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub placeholders: Vec<Placeholder>,
+}
+
+// What the editor sends when creating or updating a snippet.
+#[derive(Debug, Deserialize, Clone)]
+pub struct SnippetDraft {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub category: String,
+    pub body: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Expanded {
+    pub content: String,
+    // Placeholders without a value or default; their names are inserted.
+    pub missing: Vec<String>,
+}
+
+pub fn snippets_path() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("snippets.json"))
+}
+
+pub fn placeholders(body: &str) -> Vec<Placeholder> {
+    let mut found: Vec<Placeholder> = Vec::new();
+    for caps in Regex::new(PLACEHOLDER).unwrap().captures_iter(body) {
+        let default = caps.get(2).map(|default| default.as_str().to_string());
+        match found
+            .iter_mut()
+            .find(|placeholder| placeholder.name == caps[1])
+        {
+            Some(placeholder) => {
+                if placeholder.default.is_none() {
+                    placeholder.default = default;
+                }
+            }
+            None => found.push(Placeholder {
+                name: caps[1].to_string(),
+                default,
+            }),
+        }
+    }
+    found
+}
+
+fn builtin_snippets() -> Vec<Snippet> {
+    BUILTIN_SNIPPETS
+        .iter()
+        .map(|(id, name, description, category, body)| Snippet {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            category: category.to_string(),
+            body: body.to_string(),
+            builtin: true,
+            placeholders: Vec::new(),
+        })
+        .collect()
+}
+
+pub fn parse(json: &str) -> Result<Vec<Snippet>, String> {
+    serde_json::from_str(json).map_err(|e| format!("Invalid snippets file: {}", e))
+}
+
+pub fn user_snippets() -> Result<Vec<Snippet>, String> {
+    let path = snippets_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let json = fs::read_to_string(&path).map_err(|e| format!("Failed to read snippets: {}", e))?;
+    parse(&json)
+}
+
+pub fn save_user_snippets(snippets: &[Snippet]) -> Result<(), String> {
+    let path = snippets_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create data folder: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(snippets)
+        .map_err(|e| format!("Failed to serialize snippets: {}", e))?;
+    fs::write(path, json).map_err(|e| format!("Failed to save snippets: {}", e))
+}
+
+// The built-in snippets the user has not saved their own version of, and
+// the user's, sorted by category and name.
+pub fn list() -> Result<Vec<Snippet>, String> {
+    let user = user_snippets()?;
+    let mut snippets: Vec<Snippet> = builtin_snippets()
+        .into_iter()
+        .filter(|builtin| !user.iter().any(|snippet| snippet.id == builtin.id))
+        .collect();
+    snippets.extend(user);
+    snippets.sort_by(|a, b| {
+        (a.category.to_lowercase(), a.name.to_lowercase())
+            .cmp(&(b.category.to_lowercase(), b.name.to_lowercase()))
+    });
+    for snippet in &mut snippets {
+        snippet.placeholders = placeholders(&snippet.body);
+    }
+    Ok(snippets)
+}
+
+pub fn categories() -> Result<Vec<String>, String> {
+    let mut categories: Vec<String> = list()?
+        .into_iter()
+        .map(|snippet| snippet.category)
+        .filter(|category| !category.is_empty())
+        .collect();
+    categories.dedup();
+    Ok(categories)
+}
+
+pub fn get(id: &str) -> Result<Snippet, String> {
+    list()?
+        .into_iter()
+        .find(|snippet| snippet.id == id)
+        .ok_or_else(|| format!("Unknown snippet: {}", id))
+}
+
+fn validate(draft: &SnippetDraft) -> Result<(), String> {
+    if draft.name.trim().is_empty() {
+        return Err("The snippet needs a name".to_string());
+    }
+    if draft.body.trim().is_empty() {
+        return Err("The snippet is empty".to_string());
+    }
+    if draft.body.len() > MAX_BODY_BYTES {
+        return Err(format!(
+            "The snippet is larger than {} KB; save it as a template instead",
+            MAX_BODY_BYTES / 1024
+        ));
+    }
+    Ok(())
+}
+
+// An id from the name, with a number added when it is taken.
+fn new_id(name: &str, taken: &[Snippet]) -> String {
+    let mut base = String::new();
+    for c in name.trim().to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            base.push(c);
+        } else if !base.is_empty() && !base.ends_with('-') {
+            base.push('-');
+        }
+    }
+    let base = match base.trim_end_matches('-') {
+        "" => "snippet".to_string(),
+        trimmed => trimmed.to_string(),
+    };
+    let is_taken = |id: &str| {
+        taken.iter().any(|snippet| snippet.id == id)
+            || BUILTIN_SNIPPETS.iter().any(|builtin| builtin.0 == id)
+    };
+    let mut id = base.clone();
+    let mut number = 2;
+    while is_taken(&id) {
+        id = format!("{}-{}", base, number);
+        number += 1;
+    }
+    id
+}
+
+fn from_draft(id: String, draft: SnippetDraft) -> Snippet {
+    Snippet {
+        id,
+        name: draft.name.trim().to_string(),
+        description: draft.description.trim().to_string(),
+        category: draft.category.trim().to_string(),
+        body: draft.body,
+        builtin: false,
+        placeholders: Vec::new(),
+    }
+}
+
+pub fn create(draft: SnippetDraft) -> Result<Snippet, String> {
+    validate(&draft)?;
+    let mut snippets = user_snippets()?;
+    let snippet = from_draft(new_id(&draft.name, &snippets), draft);
+    snippets.push(snippet.clone());
+    save_user_snippets(&snippets)?;
+    get(&snippet.id)
+}
+
+// Updating a built-in snippet saves the user's own version of it.
+pub fn update(id: &str, draft: SnippetDraft) -> Result<Snippet, String> {
+    validate(&draft)?;
+    let mut snippets = user_snippets()?;
+    let snippet = from_draft(id.to_string(), draft);
+    match snippets.iter_mut().find(|snippet| snippet.id == id) {
+        Some(existing) => *existing = snippet,
+        None if BUILTIN_SNIPPETS.iter().any(|builtin| builtin.0 == id) => snippets.push(snippet),
+        None => return Err(format!("Unknown snippet: {}", id)),
+    }
+    save_user_snippets(&snippets)?;
+    get(id)
+}
+
+// Deleting the user's version of a built-in snippet restores the original.
+pub fn delete(id: &str) -> Result<(), String> {
+    let mut snippets = user_snippets()?;
+    let before = snippets.len();
+    snippets.retain(|snippet| snippet.id != id);
+    if snippets.len() == before {
+        if BUILTIN_SNIPPETS.iter().any(|builtin| builtin.0 == id) {
+            return Err("Built-in snippets cannot be deleted".to_string());
+        }
+        return Err(format!("Unknown snippet: {}", id));
+    }
+    save_user_snippets(&snippets)
+}
+
+// Fills in the placeholders of a snippet body. Missing values fall back to
+// the placeholder's default, and then to its name.
+pub fn expand(body: &str, values: &HashMap<String, String>) -> Expanded {
+    let defaults = placeholders(body);
+    let mut missing = Vec::new();
+    let content = Regex::new(PLACEHOLDER)
+        .unwrap()
+        .replace_all(body, |caps: &Captures| {
+            let name = &caps[1];
+            if let Some(value) = values.get(name) {
+                return value.clone();
+            }
+            let default = defaults
+                .iter()
+                .find(|placeholder| placeholder.name == name)
+                .and_then(|placeholder| placeholder.default.clone());
+            default.unwrap_or_else(|| {
+                if !missing.iter().any(|missing| missing == name) {
+                    missing.push(name.to_string());
+                }
+                name.to_string()
+            })
+        })
+        .to_string();
+    Expanded { content, missing }
+}
@@ -0,0 +1,451 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::{detect_diagram_type, diagrams, get_app_data_dir};
+
+// Word lists shipped with the app, one lowercase word per line. Inflected
+// forms are mostly left out and matched through their stems.
+const BUNDLED: [(&str, &str); 2] = [
+    ("en", include_str!("../dictionaries/en.txt")),
+    ("software", include_str!("../dictionaries/software.txt")),
+];
+
+// Word lists installed with the system, added to the English dictionary
+// where present. Hunspell entries carry affix flags after a slash.
+const SYSTEM_WORDS: [&str; 2] = ["/usr/share/dict/words", "/usr/share/hunspell/en_US.dic"];
+
+const MAX_SUGGESTIONS: usize = 3;
+
+// Shorter words are too often abbreviations to check.
+const MIN_WORD_LEN: usize = 4;
+
+static DICTIONARIES: OnceLock<Vec<(&'static str, HashSet<String>)>> = OnceLock::new();
+
+fn dictionaries() -> &'static [(&'static str, HashSet<String>)] {
+    DICTIONARIES.get_or_init(|| {
+        BUNDLED
+            .iter()
+            .map(|&(id, words)| {
+                let mut set: HashSet<String> = words.lines().map(str::to_string).collect();
+                if id == "en" {
+                    set.extend(system_words());
+                }
+                (id, set)
+            })
+            .collect()
+    })
+}
+
+fn system_words() -> Vec<String> {
+    SYSTEM_WORDS
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|text| {
+            text.lines()
+                .filter_map(|line| line.split('/').next())
+                .map(|word| word.trim().to_lowercase())
+                .filter(|word| word.chars().all(|c| c.is_alphabetic() || c == '\''))
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+// The enabled word lists, looked up in place rather than merged.
+struct Dictionary<'a> {
+    lists: Vec<&'a HashSet<String>>,
+}
+
+impl Dictionary<'_> {
+    fn contains(&self, word: &str) -> bool {
+        self.lists.iter().any(|list| list.contains(word))
+    }
+
+    fn words(&self) -> impl Iterator<Item = &str> {
+        self.lists
+            .iter()
+            .flat_map(|list| list.iter().map(String::as_str))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpellCheckConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_dictionaries")]
+    pub dictionaries: Vec<String>,
+}
+
+impl Default for SpellCheckConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dictionaries: default_dictionaries(),
+        }
+    }
+}
+
+fn default_dictionaries() -> Vec<String> {
+    BUNDLED.iter().map(|(id, _)| id.to_string()).collect()
+}
+
+impl SpellCheckConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for id in &self.dictionaries {
+            if !BUNDLED.iter().any(|(bundled, _)| bundled == id) {
+                return Err(format!("Unknown dictionary: {}", id));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TextKind {
+    Label,
+    Edge,
+    Note,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SpellingIssue {
+    pub line: usize,
+    // 1-based, in characters.
+    pub column: usize,
+    pub word: String,
+    pub kind: TextKind,
+    pub suggestions: Vec<String>,
+}
+
+pub fn user_dictionary_path() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("dictionary.txt"))
+}
+
+pub fn user_words() -> Vec<String> {
+    user_dictionary_path()
+        .and_then(|path| fs::read_to_string(path).map_err(|e| e.to_string()))
+        .map(|text| {
+            text.lines()
+                .map(str::trim)
+                .filter(|word| !word.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn save_user_words(words: &[String]) -> Result<(), String> {
+    let path = user_dictionary_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    }
+    let mut text = words.join("\n");
+    text.push('\n');
+    fs::write(path, text).map_err(|e| format!("Failed to save dictionary: {}", e))
+}
+
+pub fn add_word(word: &str) -> Result<Vec<String>, String> {
+    let word = word.trim();
+    if word.is_empty() || word.contains(char::is_whitespace) {
+        return Err("Add one word at a time".to_string());
+    }
+    let mut words = user_words();
+    if !words
+        .iter()
+        .any(|existing| existing.eq_ignore_ascii_case(word))
+    {
+        words.push(word.to_string());
+        words.sort_by_key(|word| word.to_lowercase());
+        save_user_words(&words)?;
+    }
+    Ok(words)
+}
+
+pub fn remove_word(word: &str) -> Result<Vec<String>, String> {
+    let mut words = user_words();
+    words.retain(|existing| !existing.eq_ignore_ascii_case(word.trim()));
+    save_user_words(&words)?;
+    Ok(words)
+}
+
+fn span(text: regex::Match<'_>, kind: TextKind) -> (usize, TextKind, &str) {
+    (text.start(), kind, text.as_str())
+}
+
+// The text of labels, edge labels and notes as (line, byte offset in the
+// line, kind, text). Identifiers, styling and directives are left out.
+pub fn texts(content: &str) -> Vec<(usize, usize, TextKind, &str)> {
+    let note = Regex::new(r"(?i)^note\b").unwrap();
+    let quoted = Regex::new(r#""([^"]*)""#).unwrap();
+    let title =
+        Regex::new(r"^(?:subgraph\s+[\w-]+\s*\[\s*|(?:title|section|subgraph)\s+)(.*?)\]?$")
+            .unwrap();
+    let edge_label = Regex::new(r"\|([^|]*)\||--\s+([^->|][^>|]*?)\s+--[->]").unwrap();
+    let node_label = Regex::new(
+        r#"\w(?:[\w-]*\w)?\s*(?:\[\[|\[\(|\(\(\(|\(\(|\(\[|\[/|\[\\|\{\{|[\[({>])\s*(?:"([^"]*)"|([^\]\)\}"|]+))"#,
+    )
+    .unwrap();
+    let message = Regex::new(r"^[^:]*(?:->|--|\}|\|)[^:]*:\s*(.+)$").unwrap();
+    let block = Regex::new(r"^(?:loop|alt|else|opt|par|and|critical|break)\s+(.+)$").unwrap();
+    let participant = Regex::new(r"^(?:participant|actor)\s+\S+\s+as\s+(.+)$").unwrap();
+    let description = Regex::new(r"^[\w-]+\s*:\s*(.+)$").unwrap();
+
+    let diagram_type = detect_diagram_type(content).unwrap_or_default();
+    let flowchart = matches!(diagram_type, "graph" | "flowchart");
+    let lines: Vec<&str> = content.lines().collect();
+    let mut found = Vec::new();
+    let mut in_note = false;
+
+    for (number, line) in diagrams::statements(content) {
+        // Spans as (offset in the statement, kind, text).
+        let mut spans: Vec<(usize, TextKind, &str)> = Vec::new();
+
+        if in_note {
+            if line.eq_ignore_ascii_case("end note") {
+                in_note = false;
+            } else {
+                spans.push((0, TextKind::Note, line));
+            }
+        } else if note.is_match(line) {
+            if let Some(colon) = line.find(':') {
+                spans.push((colon + 1, TextKind::Note, &line[colon + 1..]));
+            } else if let Some(caps) = quoted.captures(line) {
+                spans.push(span(caps.get(1).unwrap(), TextKind::Note));
+            } else {
+                in_note = diagram_type.starts_with("state");
+            }
+        } else if let Some(caps) = title.captures(line) {
+            spans.push(span(caps.get(1).unwrap(), TextKind::Label));
+        } else if flowchart {
+            let keyword = line.split_whitespace().next().unwrap_or_default();
+            if !matches!(
+                keyword,
+                "classDef" | "class" | "style" | "linkStyle" | "click" | "direction" | "end"
+            ) {
+                for caps in edge_label.captures_iter(line) {
+                    spans.push(span(caps.get(1).or(caps.get(2)).unwrap(), TextKind::Edge));
+                }
+                for caps in node_label.captures_iter(line) {
+                    spans.push(span(caps.get(1).or(caps.get(2)).unwrap(), TextKind::Label));
+                }
+            }
+        } else if let Some(caps) = participant.captures(line).or_else(|| block.captures(line)) {
+            spans.push(span(caps.get(1).unwrap(), TextKind::Label));
+        } else if let Some(caps) = message.captures(line) {
+            spans.push(span(caps.get(1).unwrap(), TextKind::Edge));
+        } else if let Some(caps) = description.captures(line) {
+            // State descriptions; class members are code, not prose.
+            if !diagram_type.starts_with("class") {
+                spans.push(span(caps.get(1).unwrap(), TextKind::Label));
+            }
+        } else {
+            for caps in quoted.captures_iter(line) {
+                spans.push(span(caps.get(1).unwrap(), TextKind::Label));
+            }
+        }
+
+        // Statements are trimmed; offsets are kept relative to the line.
+        let indent = lines[number - 1].len() - lines[number - 1].trim_start().len();
+        found.extend(
+            spans
+                .into_iter()
+                .map(|(offset, kind, text)| (number, indent + offset, kind, text)),
+        );
+    }
+    found
+}
+
+// Words in a piece of label text as (offset, word). Markup, links, paths
+// and words mixing in digits or underscores are skipped.
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let skip = Regex::new(r"<[^>]*>|\S+://\S+|\S+@\S+|#\w+;|\w+(?:[./\\]\w+)+").unwrap();
+    let token = Regex::new(r"[\w']+").unwrap();
+
+    let skipped: Vec<(usize, usize)> = skip.find_iter(text).map(|m| (m.start(), m.end())).collect();
+    token
+        .find_iter(text)
+        .filter(|m| {
+            !skipped
+                .iter()
+                .any(|(start, end)| m.start() < *end && m.end() > *start)
+        })
+        .filter_map(|m| {
+            let word = m.as_str().trim_matches('\'');
+            let offset = m.start() + m.as_str().find(word).unwrap_or(0);
+            let letters = word.chars().all(|c| c.is_alphabetic() || c == '\'');
+            let rest_upper = word.chars().skip(1).any(char::is_uppercase);
+            (letters && !rest_upper && word.chars().count() >= MIN_WORD_LEN)
+                .then_some((offset, word))
+        })
+        .collect()
+}
+
+// Whether a lowercase word or one of its likely stems is known. Covers
+// plurals, past tenses, `-ing`, `-ly`, `-er` and common prefixes.
+fn known(word: &str, dictionary: &Dictionary) -> bool {
+    let word = word
+        .strip_suffix("'s")
+        .or_else(|| word.strip_suffix('\''))
+        .unwrap_or(word);
+    if dictionary.contains(word) {
+        return true;
+    }
+    let mut stems = Vec::new();
+    for (suffix, replacements) in [
+        ("ies", &["y"][..]),
+        ("ied", &["y"]),
+        ("ily", &["y"]),
+        ("es", &["", "e"]),
+        ("s", &[""]),
+        ("ed", &["", "e"]),
+        ("ing", &["", "e"]),
+        ("ly", &[""]),
+        ("ers", &["", "e"]),
+        ("er", &["", "e"]),
+        ("est", &["", "e"]),
+        ("ment", &[""]),
+        ("ness", &[""]),
+        ("able", &["", "e"]),
+    ] {
+        if let Some(stem) = word.strip_suffix(suffix) {
+            for replacement in replacements {
+                stems.push(format!("{}{}", stem, replacement));
+            }
+            // Doubled consonants, as in `shipped` or `planning`.
+            let mut chars = stem.chars().rev();
+            if let (true, Some(last), Some(before)) =
+                (matches!(suffix, "ed" | "ing"), chars.next(), chars.next())
+            {
+                if last == before && !"aeiou".contains(last) {
+                    stems.push(stem[..stem.len() - last.len_utf8()].to_string());
+                }
+            }
+        }
+    }
+    for prefix in [
+        "un", "re", "pre", "non", "sub", "multi", "auto", "over", "under",
+    ] {
+        if let Some(rest) = word.strip_prefix(prefix) {
+            if rest.len() >= 3 && known(rest, dictionary) {
+                return true;
+            }
+        }
+    }
+    stems
+        .iter()
+        .any(|stem| stem.len() >= 2 && dictionary.contains(stem))
+}
+
+// Optimal string alignment distance, giving up once it passes `limit`.
+pub fn distance(a: &str, b: &str, limit: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > limit {
+        return None;
+    }
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (rows[i - 1][j] + 1)
+                .min(row[j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        if row.iter().min().is_some_and(|&min| min > limit) {
+            return None;
+        }
+        rows.push(row);
+    }
+    Some(rows[a.len()][b.len()]).filter(|&distance| distance <= limit)
+}
+
+fn suggestions(word: &str, dictionary: &Dictionary) -> Vec<String> {
+    let limit = if word.chars().count() <= 5 { 1 } else { 2 };
+    // Closest first, preferring words of the same length.
+    let mut close: Vec<(usize, usize, &str)> = dictionary
+        .words()
+        .filter_map(|candidate| {
+            distance(word, candidate, limit)
+                .map(|d| (d, candidate.len().abs_diff(word.len()), candidate))
+        })
+        .collect();
+    close.sort_unstable();
+    // A word can be in more than one list.
+    close.dedup();
+    close
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, _, candidate)| candidate.to_string())
+        .collect()
+}
+
+// Matches the case of the word being replaced.
+fn match_case(suggestion: &str, word: &str) -> String {
+    if word.chars().all(char::is_uppercase) {
+        suggestion.to_uppercase()
+    } else if word.starts_with(char::is_uppercase) {
+        let mut chars = suggestion.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    } else {
+        suggestion.to_string()
+    }
+}
+
+// Checks the words of labels, edge labels and notes against the chosen
+// bundled dictionaries and the user's own. Unknown words with no close
+// match are taken to be names or jargon rather than typos, so every issue
+// comes with at least one suggestion.
+pub fn check(content: &str, config: &SpellCheckConfig) -> Vec<SpellingIssue> {
+    let user = user_words()
+        .into_iter()
+        .map(|word| word.to_lowercase())
+        .collect::<HashSet<_>>();
+    let mut dictionary = Dictionary {
+        lists: dictionaries()
+            .iter()
+            .filter(|(id, _)| config.dictionaries.iter().any(|enabled| enabled == id))
+            .map(|(_, words)| words)
+            .collect(),
+    };
+    dictionary.lists.push(&user);
+
+    let lines: Vec<&str> = content.lines().collect();
+    let mut issues = Vec::new();
+    for (number, offset, kind, text) in texts(content) {
+        for (position, word) in words(text) {
+            let lower = word.to_lowercase();
+            if known(&lower, &dictionary) {
+                continue;
+            }
+            let suggestions = suggestions(&lower, &dictionary);
+            if suggestions.is_empty() {
+                continue;
+            }
+            issues.push(SpellingIssue {
+                line: number,
+                column: lines[number - 1][..offset + position].chars().count() + 1,
+                word: word.to_string(),
+                kind,
+                suggestions: suggestions
+                    .iter()
+                    .map(|suggestion| match_case(suggestion, word))
+                    .collect(),
+            });
+        }
+    }
+    issues.sort_by_key(|issue| (issue.line, issue.column));
+    issues
+}
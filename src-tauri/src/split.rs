@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::model::{self, Edge, Node};
+
+// When a flowchart counts as too complex to read comfortably. Clusters
+// smaller than `min_cluster_size` are never proposed on their own.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SplitThresholds {
+    #[serde(default = "default_max_nodes")]
+    pub max_nodes: usize,
+    #[serde(default = "default_max_edges")]
+    pub max_edges: usize,
+    #[serde(default = "default_min_cluster_size")]
+    pub min_cluster_size: usize,
+}
+
+fn default_max_nodes() -> usize {
+    40
+}
+
+fn default_max_edges() -> usize {
+    60
+}
+
+fn default_min_cluster_size() -> usize {
+    4
+}
+
+impl Default for SplitThresholds {
+    fn default() -> Self {
+        Self {
+            max_nodes: default_max_nodes(),
+            max_edges: default_max_edges(),
+            min_cluster_size: default_min_cluster_size(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SplitSuggestion {
+    pub nodes: Vec<String>,
+    pub internal_edges: usize,
+    // Edges that would link the sub-diagram back to the rest; each one
+    // becomes a link through the placeholder node.
+    pub boundary_edges: Vec<Edge>,
+    // Share of the cluster's edges that stay inside it, from 0 to 1.
+    pub cohesion: f64,
+    // A free node id the parent can use to stand in for the sub-diagram.
+    pub placeholder_id: String,
+    pub source: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SplitAnalysis {
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub exceeds_thresholds: bool,
+    pub suggestions: Vec<SplitSuggestion>,
+}
+
+// Greedy modularity clustering: starts with one cluster per node and keeps
+// merging the connected pair that improves modularity the most. Diagrams
+// are small enough for the simple quadratic version.
+fn clusters(nodes: &[Node], edges: &[Edge]) -> Vec<Vec<usize>> {
+    let index: BTreeMap<&str, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id.as_str(), i))
+        .collect();
+    let links: Vec<(usize, usize)> = edges
+        .iter()
+        .filter(|edge| edge.from != edge.to)
+        .filter_map(|edge| {
+            Some((
+                *index.get(edge.from.as_str())?,
+                *index.get(edge.to.as_str())?,
+            ))
+        })
+        .collect();
+    let mut members: Vec<Vec<usize>> = (0..nodes.len()).map(|i| vec![i]).collect();
+    if links.is_empty() {
+        return members;
+    }
+
+    let total = 2.0 * links.len() as f64;
+    let mut cluster_of: Vec<usize> = (0..nodes.len()).collect();
+    let mut degree = vec![0.0; nodes.len()];
+    for (from, to) in &links {
+        degree[*from] += 1.0;
+        degree[*to] += 1.0;
+    }
+
+    loop {
+        let mut between: BTreeMap<(usize, usize), f64> = BTreeMap::new();
+        for (from, to) in &links {
+            let (a, b) = (cluster_of[*from], cluster_of[*to]);
+            if a != b {
+                *between.entry((a.min(b), a.max(b))).or_default() += 1.0;
+            }
+        }
+
+        let best = between
+            .iter()
+            .map(|(&(a, b), count)| {
+                let gain = 2.0 * (count / total - (degree[a] / total) * (degree[b] / total));
+                (gain, a, b)
+            })
+            .filter(|(gain, _, _)| *gain > 0.0)
+            .max_by(|x, y| x.0.total_cmp(&y.0));
+        let Some((_, keep, merged)) = best else {
+            break;
+        };
+
+        for node in std::mem::take(&mut members[merged]) {
+            cluster_of[node] = keep;
+            members[keep].push(node);
+        }
+        degree[keep] += degree[merged];
+        degree[merged] = 0.0;
+    }
+
+    members.retain(|cluster| !cluster.is_empty());
+    members
+}
+
+fn node_expression(node: &Node) -> String {
+    match &node.label {
+        Some(label) => format!("{}[\"{}\"]", node.id, label.replace('"', "#quot;")),
+        None => node.id.clone(),
+    }
+}
+
+// The cluster as a diagram of its own, keeping the parent's header so the
+// direction matches. Labels are declared where each node first appears.
+fn sub_diagram(header: &str, nodes: &[&Node], edges: &[&Edge]) -> String {
+    let mut lines = vec![header.to_string()];
+    let mut declared = BTreeSet::new();
+    let mut expression = |id: &str| {
+        let node = nodes.iter().find(|node| node.id == id);
+        match node {
+            Some(node) if declared.insert(id.to_string()) => node_expression(node),
+            _ => id.to_string(),
+        }
+    };
+
+    for edge in edges {
+        let from = expression(&edge.from);
+        let to = expression(&edge.to);
+        lines.push(match &edge.label {
+            Some(label) => format!("    {} -->|{}| {}", from, label, to),
+            None => format!("    {} --> {}", from, to),
+        });
+    }
+    for node in nodes {
+        if declared.insert(node.id.clone()) {
+            lines.push(format!("    {}", node_expression(node)));
+        }
+    }
+
+    let mut source = lines.join("\n");
+    source.push('\n');
+    source
+}
+
+fn unique_id(base: &str, taken: &BTreeSet<String>) -> String {
+    (1..)
+        .map(|n| format!("{}{}", base, n))
+        .find(|id| !taken.contains(id))
+        .unwrap_or_else(|| base.to_string())
+}
+
+// Measures a flowchart and, when it crosses the thresholds, proposes
+// loosely connected clusters that could move into linked sub-diagrams.
+// Suggestions are ordered by how cleanly they come apart.
+pub fn analyze(content: &str, thresholds: &SplitThresholds) -> Result<SplitAnalysis, String> {
+    let parsed = model::parse_cached(content);
+    if !matches!(
+        parsed.model.diagram_type.as_deref(),
+        Some("graph") | Some("flowchart")
+    ) {
+        return Err("Splitting suggestions are only available for flowcharts".to_string());
+    }
+
+    let (nodes, edges) = (&parsed.nodes, &parsed.edges);
+    let exceeds_thresholds =
+        nodes.len() > thresholds.max_nodes || edges.len() > thresholds.max_edges;
+    let mut analysis = SplitAnalysis {
+        node_count: nodes.len(),
+        edge_count: edges.len(),
+        exceeds_thresholds,
+        suggestions: Vec::new(),
+    };
+    if !exceeds_thresholds {
+        return Ok(analysis);
+    }
+
+    let header = parsed
+        .model
+        .lines
+        .iter()
+        .map(|line| line.trim())
+        .find(|line| {
+            let lower = line.to_lowercase();
+            lower.starts_with("graph") || lower.starts_with("flowchart")
+        })
+        .unwrap_or("flowchart TD")
+        .to_string();
+    let mut taken: BTreeSet<String> = nodes.iter().map(|node| node.id.clone()).collect();
+
+    let mut groups = clusters(nodes, edges);
+    // The biggest cluster stays behind as the parent diagram.
+    groups.sort_by_key(|group| std::cmp::Reverse(group.len()));
+    for mut group in groups.into_iter().skip(1) {
+        if group.len() < thresholds.min_cluster_size {
+            continue;
+        }
+
+        // Keep the order nodes appear in the source.
+        group.sort_unstable();
+        let cluster_nodes: Vec<&Node> = group.iter().map(|i| &nodes[*i]).collect();
+        let ids: BTreeSet<&str> = cluster_nodes.iter().map(|node| node.id.as_str()).collect();
+
+        let internal: Vec<&Edge> = edges
+            .iter()
+            .filter(|edge| ids.contains(edge.from.as_str()) && ids.contains(edge.to.as_str()))
+            .collect();
+        let boundary: Vec<Edge> = edges
+            .iter()
+            .filter(|edge| ids.contains(edge.from.as_str()) != ids.contains(edge.to.as_str()))
+            .cloned()
+            .collect();
+
+        analysis.suggestions.push(SplitSuggestion {
+            nodes: cluster_nodes.iter().map(|node| node.id.clone()).collect(),
+            internal_edges: internal.len(),
+            cohesion: internal.len() as f64 / (internal.len() + boundary.len()).max(1) as f64,
+            source: sub_diagram(&header, &cluster_nodes, &internal),
+            boundary_edges: boundary,
+            placeholder_id: String::new(),
+        });
+    }
+
+    analysis.suggestions.sort_by(|a, b| {
+        a.boundary_edges
+            .len()
+            .cmp(&b.boundary_edges.len())
+            .then(b.nodes.len().cmp(&a.nodes.len()))
+    });
+    for suggestion in &mut analysis.suggestions {
+        suggestion.placeholder_id = unique_id("subdiagram", &taken);
+        taken.insert(suggestion.placeholder_id.clone());
+    }
+    Ok(analysis)
+}
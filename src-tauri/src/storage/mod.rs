@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{secrets, security};
+
+pub mod s3;
+pub mod webdav;
+
+// Remote documents are addressed as `storage://<backend id>/<key>` wherever
+// the frontend passes a file path.
+pub const SCHEME: &str = "storage://";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BackendConfig {
+    Webdav(webdav::WebDavConfig),
+    S3(s3::S3Config),
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RemoteFile {
+    pub path: String,
+    pub name: String,
+    pub size: Option<u64>,
+    pub modified: Option<String>,
+}
+
+impl BackendConfig {
+    pub fn id(&self) -> &str {
+        match self {
+            BackendConfig::Webdav(config) => &config.id,
+            BackendConfig::S3(config) => &config.id,
+        }
+    }
+
+    fn url(&self) -> &str {
+        match self {
+            BackendConfig::Webdav(config) => &config.url,
+            BackendConfig::S3(config) => &config.endpoint,
+        }
+    }
+}
+
+pub fn is_remote(path: &str) -> bool {
+    path.starts_with(SCHEME)
+}
+
+// Splits a `storage://` path into the backend id and the key.
+pub fn parse(path: &str) -> Result<(&str, &str), String> {
+    let (id, key) = path
+        .strip_prefix(SCHEME)
+        .and_then(|rest| rest.split_once('/'))
+        .ok_or_else(|| format!("Invalid remote path: {}", path))?;
+    validate_key(key)?;
+    Ok((id, key))
+}
+
+pub fn uri(backend_id: &str, key: &str) -> String {
+    format!("{}{}/{}", SCHEME, backend_id, key)
+}
+
+fn validate_key(key: &str) -> Result<(), String> {
+    if key.is_empty() || key.split('/').any(|segment| segment == "..") {
+        return Err(format!("Invalid remote file name: {}", key));
+    }
+    Ok(())
+}
+
+pub fn validate(config: &BackendConfig) -> Result<(), String> {
+    let id = config.id();
+    if id.is_empty()
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    {
+        return Err("Storage id must use lowercase letters, digits, '-' or '_'".to_string());
+    }
+    let url =
+        reqwest::Url::parse(config.url()).map_err(|e| format!("Invalid storage URL: {}", e))?;
+    if !matches!(url.scheme(), "https" | "http") {
+        return Err(format!("Unsupported storage URL: {}", config.url()));
+    }
+    if !security::is_secure_endpoint(&url) {
+        return Err(format!(
+            "Storage URL must use https unless the server runs on this computer: {}",
+            config.url()
+        ));
+    }
+    Ok(())
+}
+
+// Passwords and secret keys live in the keychain under the backend's id.
+fn secret_name(backend_id: &str) -> String {
+    format!("storage-{}", backend_id)
+}
+
+// `None` keeps the stored secret, an empty string removes it.
+pub fn set_secret(backend_id: &str, secret: Option<&str>) -> Result<(), String> {
+    match secret.map(str::trim) {
+        Some("") => secrets::delete_secret(&secret_name(backend_id)),
+        Some(secret) => secrets::set_secret(&secret_name(backend_id), secret),
+        None => Ok(()),
+    }
+}
+
+pub fn delete_secret(backend_id: &str) -> Result<(), String> {
+    secrets::delete_secret(&secret_name(backend_id))
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+pub async fn read(config: &BackendConfig, key: &str) -> Result<Vec<u8>, String> {
+    validate_key(key)?;
+    let secret = secrets::get_secret(&secret_name(config.id()))?;
+    let client = client()?;
+    match config {
+        BackendConfig::Webdav(config) => {
+            webdav::read(&client, config, secret.as_deref(), key).await
+        }
+        BackendConfig::S3(config) => s3::read(&client, config, secret.as_deref(), key).await,
+    }
+}
+
+pub async fn write(config: &BackendConfig, key: &str, bytes: Vec<u8>) -> Result<(), String> {
+    validate_key(key)?;
+    let secret = secrets::get_secret(&secret_name(config.id()))?;
+    let client = client()?;
+    match config {
+        BackendConfig::Webdav(config) => {
+            webdav::write(&client, config, secret.as_deref(), key, bytes).await
+        }
+        BackendConfig::S3(config) => {
+            s3::write(&client, config, secret.as_deref(), key, bytes).await
+        }
+    }
+}
+
+// Lists the files directly below `prefix`, a folder key like `team/flows/`.
+pub async fn list(config: &BackendConfig, prefix: &str) -> Result<Vec<RemoteFile>, String> {
+    if prefix.split('/').any(|segment| segment == "..") {
+        return Err(format!("Invalid remote folder: {}", prefix));
+    }
+    let secret = secrets::get_secret(&secret_name(config.id()))?;
+    let client = client()?;
+    let mut files = match config {
+        BackendConfig::Webdav(config) => {
+            webdav::list(&client, config, secret.as_deref(), prefix).await?
+        }
+        BackendConfig::S3(config) => s3::list(&client, config, secret.as_deref(), prefix).await?,
+    };
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+fn remote_file(
+    backend_id: &str,
+    key: &str,
+    size: Option<u64>,
+    modified: Option<String>,
+) -> RemoteFile {
+    RemoteFile {
+        path: uri(backend_id, key),
+        name: key.rsplit('/').next().unwrap_or(key).to_string(),
+        size,
+        modified,
+    }
+}
+
+// Percent-encodes everything outside RFC 3986's unreserved set, keeping `/`
+// when `keep_slash` is set.
+fn encode(value: &str, keep_slash: bool) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) || (keep_slash && byte == b'/') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+async fn check_response(
+    response: reqwest::Response,
+    action: &str,
+) -> Result<reqwest::Response, String> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    Err(format!(
+        "Failed to {}: server returned {} {}",
+        action,
+        status.as_u16(),
+        status.canonical_reason().unwrap_or("")
+    ))
+}
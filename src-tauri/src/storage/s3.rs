@@ -0,0 +1,282 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use reqwest::{Client, Method, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{check_response, encode, remote_file, unescape_xml, RemoteFile};
+
+// ListObjectsV2 pages hold up to 1000 keys; stop after this many pages.
+const MAX_LIST_PAGES: usize = 20;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct S3Config {
+    pub id: String,
+    pub name: String,
+    // e.g. `https://s3.eu-west-1.amazonaws.com` or a MinIO server's URL.
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub access_key_id: String,
+    // Addresses the bucket as `<endpoint>/<bucket>` instead of as a
+    // subdomain, which most self-hosted servers need.
+    #[serde(default)]
+    pub path_style: bool,
+}
+
+// The parts of a request that go into its Signature Version 4 signature.
+struct SignedRequest<'a> {
+    method: &'a str,
+    uri: &'a str,
+    query: &'a [(String, String)],
+    // Lowercase names, sorted.
+    headers: &'a [(String, String)],
+    payload_hash: &'a str,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn canonical_query(query: &[(String, String)]) -> String {
+    let mut pairs: Vec<(String, String)> = query
+        .iter()
+        .map(|(name, value)| (encode(name, false), encode(value, false)))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+// Builds the Authorization header for `request`, as described in
+// https://docs.aws.amazon.com/AmazonS3/latest/API/sig-v4-header-based-auth.html
+fn authorization(
+    request: &SignedRequest,
+    access_key_id: &str,
+    secret_key: &str,
+    region: &str,
+    amz_date: &str,
+) -> String {
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let signed_headers = request
+        .headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers: String = request
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        request.method,
+        request.uri,
+        canonical_query(request.query),
+        canonical_headers,
+        signed_headers,
+        request.payload_hash
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        sha256_hex(canonical_request.as_bytes())
+    );
+
+    let key = hmac(format!("AWS4{}", secret_key).as_bytes(), date);
+    let key = hmac(&key, region);
+    let key = hmac(&key, "s3");
+    let key = hmac(&key, "aws4_request");
+    let signature = hex(&hmac(&key, &string_to_sign));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{},SignedHeaders={},Signature={}",
+        access_key_id, scope, signed_headers, signature
+    )
+}
+
+// Returns the URL for `key` and the path that gets signed for it.
+fn object_url(config: &S3Config, key: &str) -> Result<(Url, String), String> {
+    let mut url =
+        Url::parse(&config.endpoint).map_err(|e| format!("Invalid storage URL: {}", e))?;
+    let path = if config.path_style {
+        format!("/{}/{}", encode(&config.bucket, false), encode(key, true))
+    } else {
+        let host = url.host_str().unwrap_or_default().to_string();
+        url.set_host(Some(&format!("{}.{}", config.bucket, host)))
+            .map_err(|e| format!("Invalid storage URL: {}", e))?;
+        format!("/{}", encode(key, true))
+    };
+    url.set_path(&path);
+    Ok((url, path))
+}
+
+async fn send(
+    client: &Client,
+    config: &S3Config,
+    secret_key: Option<&str>,
+    method: Method,
+    key: &str,
+    query: &[(String, String)],
+    body: Vec<u8>,
+) -> Result<reqwest::Response, String> {
+    let secret_key =
+        secret_key.ok_or_else(|| format!("No secret key configured for {}", config.name))?;
+    let (mut url, path) = object_url(config, key)?;
+    if !query.is_empty() {
+        url.set_query(Some(&canonical_query(query)));
+    }
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let payload_hash = sha256_hex(&body);
+    let headers = [
+        ("host".to_string(), host),
+        ("x-amz-content-sha256".to_string(), payload_hash.clone()),
+        ("x-amz-date".to_string(), amz_date.clone()),
+    ];
+    let request = SignedRequest {
+        method: method.as_str(),
+        uri: &path,
+        query,
+        headers: &headers,
+        payload_hash: &payload_hash,
+    };
+    let authorization = authorization(
+        &request,
+        &config.access_key_id,
+        secret_key,
+        &config.region,
+        &amz_date,
+    );
+
+    client
+        .request(method, url)
+        .header("x-amz-content-sha256", payload_hash)
+        .header("x-amz-date", amz_date)
+        .header("Authorization", authorization)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach {}: {}", config.name, e))
+}
+
+pub async fn read(
+    client: &Client,
+    config: &S3Config,
+    secret_key: Option<&str>,
+    key: &str,
+) -> Result<Vec<u8>, String> {
+    let response = send(
+        client,
+        config,
+        secret_key,
+        Method::GET,
+        key,
+        &[],
+        Vec::new(),
+    )
+    .await?;
+    let response = check_response(response, &format!("download {}", key)).await?;
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", key, e))?;
+    Ok(bytes.to_vec())
+}
+
+pub async fn write(
+    client: &Client,
+    config: &S3Config,
+    secret_key: Option<&str>,
+    key: &str,
+    bytes: Vec<u8>,
+) -> Result<(), String> {
+    let response = send(client, config, secret_key, Method::PUT, key, &[], bytes).await?;
+    check_response(response, &format!("upload {}", key)).await?;
+    Ok(())
+}
+
+fn tag(block: &str, name: &str) -> Option<String> {
+    let pattern = format!(r"(?s)<{0}>(.*?)</{0}>", name);
+    Regex::new(&pattern)
+        .ok()?
+        .captures(block)
+        .map(|captures| unescape_xml(&captures[1]))
+}
+
+pub async fn list(
+    client: &Client,
+    config: &S3Config,
+    secret_key: Option<&str>,
+    prefix: &str,
+) -> Result<Vec<RemoteFile>, String> {
+    let contents = Regex::new(r"(?s)<Contents>.*?</Contents>").unwrap();
+    let mut files = Vec::new();
+    let mut continuation: Option<String> = None;
+
+    for _ in 0..MAX_LIST_PAGES {
+        let mut query = vec![
+            ("list-type".to_string(), "2".to_string()),
+            ("delimiter".to_string(), "/".to_string()),
+            ("prefix".to_string(), prefix.to_string()),
+        ];
+        if let Some(token) = &continuation {
+            query.push(("continuation-token".to_string(), token.clone()));
+        }
+        let response = send(
+            client,
+            config,
+            secret_key,
+            Method::GET,
+            "",
+            &query,
+            Vec::new(),
+        )
+        .await?;
+        let body = check_response(response, "list files")
+            .await?
+            .text()
+            .await
+            .map_err(|e| format!("Failed to list files: {}", e))?;
+
+        for block in contents.find_iter(&body).map(|found| found.as_str()) {
+            let Some(key) = tag(block, "Key").filter(|key| !key.ends_with('/')) else {
+                continue;
+            };
+            files.push(remote_file(
+                &config.id,
+                &key,
+                tag(block, "Size").and_then(|size| size.parse().ok()),
+                tag(block, "LastModified")
+                    .and_then(|modified| DateTime::parse_from_rfc3339(&modified).ok())
+                    .map(|modified| modified.with_timezone(&Utc).to_rfc3339()),
+            ));
+        }
+
+        continuation = tag(&body, "NextContinuationToken");
+        if tag(&body, "IsTruncated").as_deref() != Some("true") || continuation.is_none() {
+            break;
+        }
+    }
+    Ok(files)
+}
@@ -0,0 +1,140 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use reqwest::{Client, Method, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::{check_response, decode, encode, remote_file, unescape_xml, RemoteFile};
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/><d:getcontentlength/><d:getlastmodified/></d:prop></d:propfind>"#;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebDavConfig {
+    pub id: String,
+    pub name: String,
+    // The collection files are stored under, e.g.
+    // `https://cloud.example.com/remote.php/dav/files/me/diagrams`.
+    pub url: String,
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+fn url(config: &WebDavConfig, key: &str) -> String {
+    format!("{}/{}", config.url.trim_end_matches('/'), encode(key, true))
+}
+
+fn request(
+    client: &Client,
+    method: Method,
+    url: &str,
+    config: &WebDavConfig,
+    password: Option<&str>,
+) -> RequestBuilder {
+    let request = client.request(method, url);
+    match &config.username {
+        Some(username) => request.basic_auth(username, password),
+        None => request,
+    }
+}
+
+pub async fn read(
+    client: &Client,
+    config: &WebDavConfig,
+    password: Option<&str>,
+    key: &str,
+) -> Result<Vec<u8>, String> {
+    let response = request(client, Method::GET, &url(config, key), config, password)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach {}: {}", config.name, e))?;
+    let response = check_response(response, &format!("download {}", key)).await?;
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", key, e))?;
+    Ok(bytes.to_vec())
+}
+
+pub async fn write(
+    client: &Client,
+    config: &WebDavConfig,
+    password: Option<&str>,
+    key: &str,
+    bytes: Vec<u8>,
+) -> Result<(), String> {
+    let response = request(client, Method::PUT, &url(config, key), config, password)
+        .body(bytes)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach {}: {}", config.name, e))?;
+    check_response(response, &format!("upload {}", key)).await?;
+    Ok(())
+}
+
+fn tag(response: &str, name: &str) -> Option<String> {
+    let pattern = format!(r"(?s)<(?:[\w-]+:)?{0}[^>]*>(.*?)</(?:[\w-]+:)?{0}>", name);
+    Regex::new(&pattern)
+        .ok()?
+        .captures(response)
+        .map(|captures| unescape_xml(captures[1].trim()))
+}
+
+pub async fn list(
+    client: &Client,
+    config: &WebDavConfig,
+    password: Option<&str>,
+    prefix: &str,
+) -> Result<Vec<RemoteFile>, String> {
+    let method = Method::from_bytes(b"PROPFIND").map_err(|e| e.to_string())?;
+    let collection = format!("{}/", url(config, prefix).trim_end_matches('/'));
+    let response = request(client, method, &collection, config, password)
+        .header("Depth", "1")
+        .header("Content-Type", "application/xml; charset=utf-8")
+        .body(PROPFIND_BODY)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach {}: {}", config.name, e))?;
+    let body = check_response(response, "list files")
+        .await?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to list files: {}", e))?;
+
+    // Servers answer with hrefs that are either absolute URLs or absolute
+    // paths; only the path below the configured collection is the key.
+    let base_path = reqwest::Url::parse(&config.url)
+        .map(|url| decode(url.path()).trim_end_matches('/').to_string())
+        .unwrap_or_default();
+    let response_tag =
+        Regex::new(r"(?s)<(?:[\w-]+:)?response[\s>].*?</(?:[\w-]+:)?response>").unwrap();
+
+    let mut files = Vec::new();
+    for response in response_tag.find_iter(&body).map(|found| found.as_str()) {
+        if tag(response, "resourcetype").is_some_and(|kind| kind.contains("collection")) {
+            continue;
+        }
+        let Some(href) = tag(response, "href") else {
+            continue;
+        };
+        let path = reqwest::Url::parse(&href)
+            .map(|url| url.path().to_string())
+            .unwrap_or(href);
+        let path = decode(&path);
+        let Some(key) = path
+            .strip_prefix(&base_path)
+            .map(|key| key.trim_start_matches('/'))
+            .filter(|key| !key.is_empty())
+        else {
+            continue;
+        };
+        files.push(remote_file(
+            &config.id,
+            key,
+            tag(response, "getcontentlength").and_then(|size| size.parse().ok()),
+            tag(response, "getlastmodified")
+                .and_then(|modified| DateTime::parse_from_rfc2822(&modified).ok())
+                .map(|modified| modified.with_timezone(&Utc).to_rfc3339()),
+        ));
+    }
+    Ok(files)
+}
@@ -0,0 +1,252 @@
+use base64::{engine::general_purpose, Engine as _};
+use fontdb::{Database, Family, Query, Weight, ID};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::OnceLock;
+
+use crate::get_app_data_dir;
+
+const GENERIC_FAMILIES: [&str; 8] = [
+    "serif",
+    "sans-serif",
+    "monospace",
+    "cursive",
+    "fantasy",
+    "system-ui",
+    "inherit",
+    "initial",
+];
+
+// Mermaid draws titles and some labels in bold, so both weights are embedded
+// when the family has them.
+const WEIGHTS: [Weight; 2] = [Weight::NORMAL, Weight::BOLD];
+
+// Fonts are embedded whole, so very large ones, typically CJK, are left
+// out rather than growing the SVG by tens of megabytes.
+const MAX_FONT_BYTES: usize = 8 * 1024 * 1024;
+
+static FONTS: OnceLock<Database> = OnceLock::new();
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EmbeddedSvg {
+    pub svg: String,
+    pub embedded: Vec<String>,
+    pub missing: Vec<String>,
+    // Installed fonts that could not be embedded, with the reason.
+    pub skipped: Vec<String>,
+}
+
+// System fonts plus any dropped into `<app data>/fonts`. Scanning is slow,
+// so it happens once per run.
+fn fonts() -> &'static Database {
+    FONTS.get_or_init(|| {
+        let mut db = Database::new();
+        db.load_system_fonts();
+        if let Ok(dir) = get_app_data_dir() {
+            db.load_fonts_dir(dir.join("fonts"));
+        }
+        db
+    })
+}
+
+fn decode_entities(text: &str) -> String {
+    let entity = Regex::new(r"&(#x[0-9a-fA-F]+|#[0-9]+|amp|lt|gt|quot|apos|nbsp);").unwrap();
+    entity
+        .replace_all(text, |caps: &regex::Captures| {
+            let name = &caps[1];
+            let code = if let Some(hex) = name.strip_prefix("#x") {
+                u32::from_str_radix(hex, 16).ok()
+            } else if let Some(dec) = name.strip_prefix('#') {
+                dec.parse().ok()
+            } else {
+                None
+            };
+            match (code.and_then(char::from_u32), name) {
+                (Some(c), _) => c.to_string(),
+                (None, "amp") => "&".to_string(),
+                (None, "lt") => "<".to_string(),
+                (None, "gt") => ">".to_string(),
+                (None, "quot") => "\"".to_string(),
+                (None, "apos") => "'".to_string(),
+                (None, "nbsp") => "\u{a0}".to_string(),
+                _ => caps[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+// Every character drawn by the SVG, including HTML labels inside
+// foreignObject.
+fn used_chars(svg: &str) -> BTreeSet<char> {
+    let style = Regex::new(r"(?s)<style[^>]*>.*?</style>").unwrap();
+    let text = Regex::new(r">([^<]+)<").unwrap();
+    let without_styles = style.replace_all(svg, "");
+
+    let mut chars: BTreeSet<char> = text
+        .captures_iter(&without_styles)
+        .flat_map(|caps| decode_entities(&caps[1]).chars().collect::<Vec<_>>())
+        .filter(|c| !c.is_control())
+        .collect();
+    chars.insert(' ');
+    chars
+}
+
+// The first concrete family of each `font-family` list, which is what a
+// viewer with that font installed would use.
+fn declared_families(svg: &str) -> Vec<String> {
+    let css = Regex::new(r#"font-family\s*:\s*((?:[^;}"<>]|"[^"<>]*")+)"#).unwrap();
+    let attribute = Regex::new(r#"font-family\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+
+    let lists = css
+        .captures_iter(svg)
+        .chain(attribute.captures_iter(svg))
+        .filter_map(|caps| caps.get(1).or_else(|| caps.get(2)))
+        .map(|list| list.as_str().replace("&quot;", "\""));
+
+    let mut families: Vec<String> = Vec::new();
+    for list in lists {
+        let family = list
+            .split(',')
+            .map(|name| name.trim().trim_matches(['"', '\'']).trim())
+            .find(|name| {
+                !name.is_empty()
+                    && !name.starts_with("var(")
+                    && !GENERIC_FAMILIES.contains(&name.to_lowercase().as_str())
+            });
+        if let Some(family) = family {
+            if !families
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(family))
+            {
+                families.push(family.to_string());
+            }
+        }
+    }
+    families
+}
+
+// fontdb matches family names exactly, while CSS does not care about case.
+fn installed_family(db: &Database, name: &str) -> Option<String> {
+    db.faces()
+        .flat_map(|face| face.families.iter())
+        .map(|(family, _)| family)
+        .find(|family| family.eq_ignore_ascii_case(name))
+        .cloned()
+}
+
+// The whole font file goes in: a PDF-style subset keeps glyph outlines but
+// not the tables browsers need to map text to them and shape it.
+fn font_face(
+    db: &Database,
+    id: ID,
+    family: &str,
+    chars: &BTreeSet<char>,
+) -> Result<Option<String>, String> {
+    let weight = db.face(id).map_or(400, |face| face.weight.0);
+    let face = db.with_face_data(id, |data, index| {
+        let face = ttf_parser::Face::parse(data, index)
+            .map_err(|e| format!("Failed to read font {}: {}", family, e))?;
+        if !chars.iter().any(|c| face.glyph_index(*c).is_some()) {
+            return Ok(None);
+        }
+        // Browsers do not load single faces out of .ttc collections.
+        if data.starts_with(b"ttcf") {
+            return Err(format!("{} is part of a font collection, not embedded", family));
+        }
+        if data.len() > MAX_FONT_BYTES {
+            return Err(format!(
+                "{} is larger than {} MB, not embedded",
+                family,
+                MAX_FONT_BYTES / 1024 / 1024
+            ));
+        }
+        let (mime, format) = if face.tables().cff.is_some() {
+            ("font/otf", "opentype")
+        } else {
+            ("font/ttf", "truetype")
+        };
+        Ok(Some(format!(
+            "@font-face{{font-family:\"{}\";font-weight:{};src:url(data:{};base64,{}) format(\"{}\");}}",
+            family,
+            weight,
+            mime,
+            general_purpose::STANDARD.encode(data),
+            format
+        )))
+    });
+    face.unwrap_or_else(|| Err(format!("Failed to read font {}", family)))
+}
+
+// Adds @font-face rules for every font the SVG names, so it looks the same
+// on machines without those fonts. Fonts that are not installed are
+// reported as missing, and ones that cannot be embedded as skipped; both
+// are left to the viewer's fallback.
+pub fn embed_fonts(svg: &str) -> Result<EmbeddedSvg, String> {
+    let open_tag = Regex::new(r"<svg\b[^>]*>").unwrap();
+    let insert_at = open_tag
+        .find(svg)
+        .map(|tag| tag.end())
+        .ok_or("Not an SVG document")?;
+
+    let db = fonts();
+    let chars = used_chars(svg);
+    let mut rules = Vec::new();
+    let mut embedded = Vec::new();
+    let mut missing = Vec::new();
+    let mut skipped = Vec::new();
+
+    for declared in declared_families(svg) {
+        let Some(family) = installed_family(db, &declared) else {
+            missing.push(declared);
+            continue;
+        };
+
+        let mut ids: Vec<ID> = Vec::new();
+        for weight in WEIGHTS {
+            let query = Query {
+                families: &[Family::Name(&family)],
+                weight,
+                ..Query::default()
+            };
+            if let Some(id) = db.query(&query).filter(|id| !ids.contains(id)) {
+                ids.push(id);
+            }
+        }
+
+        let mut faces = Vec::new();
+        for id in ids {
+            match font_face(db, id, &declared, &chars) {
+                Ok(face) => faces.extend(face),
+                Err(reason) => skipped.push(reason),
+            }
+        }
+        if !faces.is_empty() {
+            rules.extend(faces);
+            embedded.push(family);
+        }
+    }
+
+    if rules.is_empty() {
+        return Ok(EmbeddedSvg {
+            svg: svg.to_string(),
+            embedded,
+            missing,
+            skipped,
+        });
+    }
+
+    let mut output = String::with_capacity(svg.len());
+    output.push_str(&svg[..insert_at]);
+    output.push_str("<style>");
+    output.push_str(&rules.join(""));
+    output.push_str("</style>");
+    output.push_str(&svg[insert_at..]);
+
+    Ok(EmbeddedSvg {
+        svg: output,
+        embedded,
+        missing,
+        skipped,
+    })
+}
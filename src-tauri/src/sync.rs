@@ -0,0 +1,305 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{snippets, themes, AppState};
+
+const FORMAT: &str = "flowcraft-state-bundle";
+const VERSION: u32 = 1;
+// Entries larger than this are refused, so a crafted archive cannot fill
+// memory on import.
+const MAX_ENTRY_BYTES: u64 = 8 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    format: String,
+    version: u32,
+    app_version: String,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportSummary {
+    pub recent_files: usize,
+    pub starred_files: usize,
+    pub tagged_files: usize,
+    pub keybindings: usize,
+    pub themes: Vec<String>,
+    pub snippets: usize,
+    pub skipped: Vec<String>,
+}
+
+fn add_entry(
+    zip: &mut ZipWriter<File>,
+    name: &str,
+    bytes: &[u8],
+    options: SimpleFileOptions,
+) -> Result<(), String> {
+    zip.start_file(name, options)
+        .map_err(|e| format!("Failed to add {} to bundle: {}", name, e))?;
+    zip.write_all(bytes)
+        .map_err(|e| format!("Failed to add {} to bundle: {}", name, e))
+}
+
+fn theme_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = themes::themes_dir()
+        .and_then(|dir| {
+            fs::read_dir(dir).map_err(|e| format!("Failed to read themes directory: {}", e))
+        })
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+// Zips the settings, recent files and keybindings in `state` together with
+// the user's themes and snippets. Secrets live in the keychain and are not
+// included.
+pub fn export_bundle(output: &Path, state: &AppState) -> Result<(), String> {
+    let file = File::create(output).map_err(|e| format!("Failed to create bundle: {}", e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Utc::now().to_rfc3339(),
+    };
+    let manifest = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize bundle: {}", e))?;
+    add_entry(&mut zip, "manifest.json", manifest.as_bytes(), options)?;
+
+    let state = serde_json::to_string_pretty(state)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    add_entry(&mut zip, "state.json", state.as_bytes(), options)?;
+
+    for path in theme_files() {
+        let bytes = fs::read(&path).map_err(|e| format!("Failed to read theme: {}", e))?;
+        let name = format!(
+            "themes/{}",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        add_entry(&mut zip, &name, &bytes, options)?;
+    }
+
+    let snippets = snippets::user_snippets()?;
+    if !snippets.is_empty() {
+        let snippets = serde_json::to_string_pretty(&snippets)
+            .map_err(|e| format!("Failed to serialize snippets: {}", e))?;
+        add_entry(&mut zip, "snippets.json", snippets.as_bytes(), options)?;
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to write bundle: {}", e))?;
+    Ok(())
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<String, String> {
+    let entry = archive
+        .by_name(name)
+        .map_err(|_| format!("Bundle is missing {}", name))?;
+    read_limited(entry, name)
+}
+
+fn read_limited(entry: impl Read, name: &str) -> Result<String, String> {
+    let mut content = String::new();
+    entry
+        .take(MAX_ENTRY_BYTES + 1)
+        .read_to_string(&mut content)
+        .map_err(|e| format!("Failed to read {} from bundle: {}", name, e))?;
+    if content.len() as u64 > MAX_ENTRY_BYTES {
+        return Err(format!("{} in bundle is too large", name));
+    }
+    Ok(content)
+}
+
+// Settings that only make sense on the machine they were made on: folders,
+// local programs, installed plugins, storage backends whose credentials are
+// in this machine's keychain, and per-machine consent. The AI endpoint and
+// trusted signing keys stay too, since a bundle from someone else could
+// otherwise send diagrams to their server or vouch for their signatures.
+fn keep_local_settings(imported: &mut AppState, local: &AppState) {
+    imported.enabled_plugins = local.enabled_plugins.clone();
+    imported.watch_config = local.watch_config.clone();
+    imported.preview_server = local.preview_server.clone();
+    imported.workspace = local.workspace.clone();
+    imported.external_editor = local.external_editor.clone();
+    imported.storage_backends = local.storage_backends.clone();
+    imported.mermaid_renderer = local.mermaid_renderer.clone();
+    imported.telemetry_enabled = local.telemetry_enabled;
+    imported.ai_config = local.ai_config.clone();
+    imported.trusted_signing_keys = local.trusted_signing_keys.clone();
+}
+
+// Paths this machine's user opened or starred here, as opposed to ones that
+// arrived in an earlier bundle.
+fn local_paths<'a>(paths: impl Iterator<Item = (&'a String, bool)>) -> HashSet<&'a String> {
+    paths
+        .filter(|(_, imported)| !imported)
+        .map(|(path, _)| path)
+        .collect()
+}
+
+// Recent files from both machines, newest first. Those from the bundle are
+// kept only if they exist here, and are marked as imported unless they are
+// local too, so they are not granted file access.
+fn merge_recent_files(imported: &mut AppState, local: &AppState) {
+    let local_paths = local_paths(
+        local
+            .recent_files
+            .iter()
+            .map(|file| (&file.path, file.imported)),
+    );
+    let mut recent: Vec<_> = imported
+        .recent_files
+        .iter()
+        .filter(|file| Path::new(&file.path).exists())
+        .cloned()
+        .collect();
+    recent.extend(local.recent_files.iter().cloned());
+    recent.sort_by_key(|file| Reverse(file.last_opened));
+    let mut seen = HashSet::new();
+    recent.retain(|file| seen.insert(file.path.clone()));
+    recent.truncate(10);
+    for file in &mut recent {
+        file.imported = !local_paths.contains(&file.path);
+    }
+    imported.recent_files = recent;
+}
+
+// Starred files from both machines; a file starred on both keeps the
+// earlier star. Imported stars are handled as imported recent files are.
+fn merge_starred_files(imported: &mut AppState, local: &AppState) {
+    let local_paths = local_paths(
+        local
+            .starred_files
+            .iter()
+            .map(|file| (&file.path, file.imported)),
+    );
+    let mut starred: Vec<_> = imported
+        .starred_files
+        .iter()
+        .filter(|file| Path::new(&file.path).exists())
+        .cloned()
+        .collect();
+    starred.extend(local.starred_files.iter().cloned());
+    starred.sort_by_key(|file| file.starred_at);
+    let mut seen = HashSet::new();
+    starred.retain(|file| seen.insert(file.path.clone()));
+    starred.reverse();
+    for file in &mut starred {
+        file.imported = !local_paths.contains(&file.path);
+    }
+    imported.starred_files = starred;
+}
+
+// Tags from both machines, combined per file.
+fn merge_file_tags(imported: &mut AppState, local: &AppState) {
+    for (path, local_tags) in &local.file_tags {
+        let file = imported.file_tags.entry(path.clone()).or_default();
+        file.extend(local_tags.iter().cloned());
+        file.sort();
+        file.dedup();
+    }
+}
+
+// Snippets from the bundle replace local ones with the same id; the others
+// are kept. Returns how many came from the bundle.
+fn merge_snippets(archive: &mut ZipArchive<File>) -> Result<usize, String> {
+    let Ok(entry) = archive.by_name("snippets.json") else {
+        return Ok(0);
+    };
+    let imported = snippets::parse(&read_limited(entry, "snippets.json")?)?;
+    let mut merged: Vec<snippets::Snippet> = snippets::user_snippets()?
+        .into_iter()
+        .filter(|local| !imported.iter().any(|snippet| snippet.id == local.id))
+        .collect();
+    let count = imported.len();
+    merged.extend(imported);
+    snippets::save_user_snippets(&merged)?;
+    Ok(count)
+}
+
+// Reads a bundle written by `export_bundle` and returns the state to switch
+// to, built from `local` and the bundle. Valid themes are installed right
+// away; invalid ones are skipped and reported. Snippets are merged into the
+// local ones.
+pub fn import_bundle(input: &Path, local: &AppState) -> Result<(AppState, ImportSummary), String> {
+    let file = File::open(input).map_err(|e| format!("Failed to open bundle: {}", e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("Invalid bundle: {}", e))?;
+
+    let manifest: Manifest = serde_json::from_str(&read_entry(&mut archive, "manifest.json")?)
+        .map_err(|e| format!("Invalid bundle manifest: {}", e))?;
+    if manifest.format != FORMAT || manifest.version != VERSION {
+        return Err("Unsupported bundle format".to_string());
+    }
+
+    let mut state: AppState = serde_json::from_str(&read_entry(&mut archive, "state.json")?)
+        .map_err(|e| format!("Invalid settings in bundle: {}", e))?;
+    keep_local_settings(&mut state, local);
+    merge_recent_files(&mut state, local);
+    merge_starred_files(&mut state, local);
+    merge_file_tags(&mut state, local);
+
+    let mut summary = ImportSummary {
+        recent_files: state.recent_files.len(),
+        starred_files: state.starred_files.len(),
+        tagged_files: state.file_tags.len(),
+        keybindings: state.keybindings.len(),
+        themes: Vec::new(),
+        snippets: 0,
+        skipped: Vec::new(),
+    };
+
+    let themes_dir = themes::themes_dir()?;
+    for index in 0..archive.len() {
+        let entry = archive
+            .by_index(index)
+            .map_err(|e| format!("Invalid bundle: {}", e))?;
+        let entry_name = entry.name().to_string();
+        // Only flat .json files under themes/ are accepted, so entries
+        // cannot write anywhere else.
+        let Some(file_name) = entry_name
+            .strip_prefix("themes/")
+            .filter(|name| !name.contains(['/', '\\', ':']) && name.ends_with(".json"))
+            .map(str::to_string)
+        else {
+            continue;
+        };
+
+        let theme = read_limited(entry, &entry_name).and_then(|content| {
+            themes::parse(&content)?;
+            Ok(content)
+        });
+        match theme {
+            Ok(content) => {
+                fs::create_dir_all(&themes_dir)
+                    .map_err(|e| format!("Failed to create themes directory: {}", e))?;
+                fs::write(themes_dir.join(&file_name), content)
+                    .map_err(|e| format!("Failed to install theme: {}", e))?;
+                summary.themes.push(file_name);
+            }
+            Err(e) => summary.skipped.push(format!("{}: {}", entry_name, e)),
+        }
+    }
+
+    match merge_snippets(&mut archive) {
+        Ok(count) => summary.snippets = count,
+        Err(e) => summary.skipped.push(format!("snippets.json: {}", e)),
+    }
+
+    Ok((state, summary))
+}
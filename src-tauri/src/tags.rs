@@ -0,0 +1,117 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const MAX_TAG_LENGTH: usize = 40;
+
+// Tags per file path, as kept in the app state.
+pub type FileTags = BTreeMap<String, Vec<String>>;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TaggedFile {
+    pub path: String,
+    pub name: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TagCount {
+    pub tag: String,
+    pub files: usize,
+}
+
+// Tags are matched ignoring case, so they are stored in lower case, with
+// runs of whitespace made single spaces.
+pub fn normalize(tag: &str) -> Result<String, String> {
+    let tag = tag
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    if tag.is_empty() {
+        return Err("Tags cannot be empty".to_string());
+    }
+    if tag.chars().count() > MAX_TAG_LENGTH {
+        return Err(format!("Tags can be at most {} characters", MAX_TAG_LENGTH));
+    }
+    if tag.contains(',') {
+        return Err("Tags cannot contain commas".to_string());
+    }
+    Ok(tag)
+}
+
+// Returns the file's tags after adding.
+pub fn add(tags: &mut FileTags, path: &str, tag: &str) -> Result<Vec<String>, String> {
+    let tag = normalize(tag)?;
+    let file = tags.entry(path.to_string()).or_default();
+    if !file.contains(&tag) {
+        file.push(tag);
+        file.sort();
+    }
+    Ok(file.clone())
+}
+
+// Returns the file's tags after removing; files left without any are
+// dropped.
+pub fn remove(tags: &mut FileTags, path: &str, tag: &str) -> Vec<String> {
+    let Ok(tag) = normalize(tag) else {
+        return get(tags, path);
+    };
+    if let Some(file) = tags.get_mut(path) {
+        file.retain(|existing| *existing != tag);
+        if file.is_empty() {
+            tags.remove(path);
+        }
+    }
+    get(tags, path)
+}
+
+pub fn get(tags: &FileTags, path: &str) -> Vec<String> {
+    tags.get(path).cloned().unwrap_or_default()
+}
+
+// Files with the tag, by path.
+pub fn find(tags: &FileTags, tag: &str) -> Vec<TaggedFile> {
+    let Ok(tag) = normalize(tag) else {
+        return Vec::new();
+    };
+    tags.iter()
+        .filter(|(_, file)| file.contains(&tag))
+        .map(|(path, file)| TaggedFile {
+            path: path.clone(),
+            name: Path::new(path)
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            tags: file.clone(),
+        })
+        .collect()
+}
+
+// Every tag in use, for suggestions and a tag list, most used first.
+pub fn all(tags: &FileTags) -> Vec<TagCount> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for tag in tags.values().flatten() {
+        *counts.entry(tag).or_insert(0) += 1;
+    }
+    let mut all: Vec<TagCount> = counts
+        .into_iter()
+        .map(|(tag, files)| TagCount {
+            tag: tag.to_string(),
+            files,
+        })
+        .collect();
+    all.sort_by(|a, b| b.files.cmp(&a.files).then_with(|| a.tag.cmp(&b.tag)));
+    all
+}
+
+// Keeps the tags with a file that was renamed or moved.
+pub fn rename(tags: &mut FileTags, from: &str, to: &str) {
+    if let Some(moved) = tags.remove(from) {
+        let file = tags.entry(to.to_string()).or_default();
+        file.extend(moved);
+        file.sort();
+        file.dedup();
+    }
+}
@@ -0,0 +1,282 @@
+// Opt-in usage telemetry. Only counters are collected: how often a feature
+// was used and which category of error it hit. No file names, paths, diagram
+// content or error messages are recorded, and there is no install or user id.
+// Counters accumulate in a local batch that the user can inspect with
+// `get_pending_telemetry` before it is sent, at most once per batch interval,
+// and only when the build or the environment names an endpoint.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::{get_app_data_dir, security};
+
+const BATCH_INTERVAL_HOURS: i64 = 24;
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+// Counters are written to disk at most this often rather than on every
+// command.
+const SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static BATCH: Mutex<Option<TelemetryBatch>> = Mutex::new(None);
+// Set when the batch in memory has counts the file on disk lacks.
+static DIRTY: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelemetryBatch {
+    pub app_version: String,
+    pub os: String,
+    pub since: DateTime<Utc>,
+    pub features: BTreeMap<String, u64>,
+    pub errors: BTreeMap<String, u64>,
+}
+
+// What a failed command was doing. Callers name it, since error messages
+// are free text and may be translated.
+#[derive(Debug, Clone, Copy)]
+pub enum ErrorKind {
+    Io,
+    Parse,
+    Render,
+    Network,
+    Config,
+}
+
+impl ErrorKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::Io => "io",
+            ErrorKind::Parse => "parse",
+            ErrorKind::Render => "render",
+            ErrorKind::Network => "network",
+            ErrorKind::Config => "config",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PendingTelemetry {
+    pub enabled: bool,
+    // None when this build has nowhere to send the batch.
+    pub endpoint: Option<String>,
+    pub batch: Option<TelemetryBatch>,
+}
+
+// There is no built-in endpoint: a build sets FLOWCRAFT_TELEMETRY_URL at
+// compile time, and the variable at run time overrides it.
+fn endpoint() -> Option<String> {
+    env::var("FLOWCRAFT_TELEMETRY_URL")
+        .ok()
+        .or_else(|| option_env!("FLOWCRAFT_TELEMETRY_URL").map(str::to_string))
+        .filter(|url| !url.is_empty())
+}
+
+fn queue_path() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("telemetry.json"))
+}
+
+fn new_batch(app_version: &str) -> TelemetryBatch {
+    TelemetryBatch {
+        app_version: app_version.to_string(),
+        os: env::consts::OS.to_string(),
+        since: Utc::now(),
+        features: BTreeMap::new(),
+        errors: BTreeMap::new(),
+    }
+}
+
+fn save(batch: &TelemetryBatch) {
+    if let Ok(path) = queue_path() {
+        if let Ok(json) = serde_json::to_string_pretty(batch) {
+            let _ = fs::write(path, json);
+        }
+    }
+}
+
+// Writes the batch out if it changed since the last save.
+pub fn persist() {
+    if !DIRTY.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    if let Ok(batch) = BATCH.lock() {
+        if let Some(batch) = batch.as_ref() {
+            save(batch);
+        }
+    }
+}
+
+fn discard() {
+    DIRTY.store(false, Ordering::SeqCst);
+    if let Ok(mut batch) = BATCH.lock() {
+        *batch = None;
+    }
+    if let Ok(path) = queue_path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// Restores the pending batch from disk when telemetry is enabled.
+pub fn init(enabled: bool, app_version: &str) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+    if !enabled {
+        discard();
+        return;
+    }
+
+    let stored = queue_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str::<TelemetryBatch>(&json).ok());
+
+    if let Ok(mut batch) = BATCH.lock() {
+        let mut restored = stored.unwrap_or_else(|| new_batch(app_version));
+        restored.app_version = app_version.to_string();
+        *batch = Some(restored);
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+fn increment(select: impl FnOnce(&mut TelemetryBatch) -> &mut BTreeMap<String, u64>, key: &str) {
+    if !is_enabled() {
+        return;
+    }
+
+    if let Ok(mut batch) = BATCH.lock() {
+        if let Some(batch) = batch.as_mut() {
+            *select(batch).entry(key.to_string()).or_insert(0) += 1;
+            DIRTY.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+pub fn record_feature(feature: &str) {
+    increment(|batch| &mut batch.features, feature);
+}
+
+pub fn record_error(feature: &str, kind: ErrorKind) {
+    increment(
+        |batch| &mut batch.errors,
+        &format!("{}:{}", feature, kind.as_str()),
+    );
+}
+
+// Counts a command invocation and, on failure, the kind of error it hit.
+pub fn track<T>(feature: &str, kind: ErrorKind, result: Result<T, String>) -> Result<T, String> {
+    record_feature(feature);
+    if result.is_err() {
+        record_error(feature, kind);
+    }
+    result
+}
+
+pub fn pending() -> PendingTelemetry {
+    PendingTelemetry {
+        enabled: is_enabled(),
+        endpoint: endpoint(),
+        batch: BATCH.lock().ok().and_then(|batch| batch.clone()),
+    }
+}
+
+// Sends the batch once it is old enough and has something in it. The batch
+// is only cleared after the endpoint accepted it.
+pub async fn flush_if_due(client: &reqwest::Client) -> Result<bool, String> {
+    if !is_enabled() {
+        return Ok(false);
+    }
+
+    let due = match BATCH.lock() {
+        Ok(batch) => batch.clone().filter(|batch| {
+            Utc::now() - batch.since >= Duration::hours(BATCH_INTERVAL_HOURS)
+                && !(batch.features.is_empty() && batch.errors.is_empty())
+        }),
+        Err(_) => return Err("Failed to access telemetry queue".to_string()),
+    };
+    let Some(due) = due else {
+        return Ok(false);
+    };
+    let Some(endpoint) = endpoint() else {
+        return Ok(false);
+    };
+    let url =
+        reqwest::Url::parse(&endpoint).map_err(|e| format!("Invalid telemetry endpoint: {}", e))?;
+    if !security::is_secure_endpoint(&url) {
+        return Err(format!("Telemetry endpoint must use https: {}", endpoint));
+    }
+
+    let response = client
+        .post(url)
+        .json(&json_payload(&due))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send telemetry: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Telemetry endpoint returned {}",
+            response.status().as_u16()
+        ));
+    }
+
+    if let Ok(mut batch) = BATCH.lock() {
+        let app_version = due.app_version.clone();
+        let mut fresh = new_batch(&app_version);
+
+        // Keep anything recorded while the request was in flight.
+        if let Some(current) = batch.as_ref() {
+            for (key, count) in &current.features {
+                let sent = due.features.get(key).copied().unwrap_or(0);
+                if *count > sent {
+                    fresh.features.insert(key.clone(), count - sent);
+                }
+            }
+            for (key, count) in &current.errors {
+                let sent = due.errors.get(key).copied().unwrap_or(0);
+                if *count > sent {
+                    fresh.errors.insert(key.clone(), count - sent);
+                }
+            }
+        }
+
+        save(&fresh);
+        DIRTY.store(false, Ordering::SeqCst);
+        *batch = Some(fresh);
+    }
+
+    Ok(true)
+}
+
+fn json_payload(batch: &TelemetryBatch) -> serde_json::Value {
+    serde_json::json!({
+        "app_version": batch.app_version,
+        "os": batch.os,
+        "since": batch.since,
+        "until": Utc::now(),
+        "features": batch.features,
+        "errors": batch.errors,
+    })
+}
+
+pub fn spawn_uploader() {
+    tauri::async_runtime::spawn(async {
+        let client = reqwest::Client::new();
+        let mut since_check = CHECK_INTERVAL;
+        loop {
+            persist();
+            if since_check >= CHECK_INTERVAL {
+                since_check = std::time::Duration::ZERO;
+                if let Err(e) = flush_if_due(&client).await {
+                    tracing::debug!(error = %e, "telemetry upload failed");
+                }
+            }
+            tokio::time::sleep(SAVE_INTERVAL).await;
+            since_check += SAVE_INTERVAL;
+        }
+    });
+}
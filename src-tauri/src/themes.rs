@@ -0,0 +1,277 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::get_app_data_dir;
+use crate::render::RenderConfig;
+
+pub const DEFAULT_THEME: &str = "light";
+// Used for the dark half of light and dark exports.
+pub const DEFAULT_DARK_THEME: &str = "dark";
+
+// Shipped themes. JSON files in `<app data>/themes` add more or replace a
+// shipped theme with the same id.
+const BUILTIN_THEMES: [&str; 2] = [
+    include_str!("../themes/light.json"),
+    include_str!("../themes/dark.json"),
+];
+
+pub const MERMAID_THEMES: [&str; 5] = ["default", "dark", "forest", "neutral", "base"];
+
+const COLOR: &str = r"^(#([0-9a-fA-F]{3,4}|[0-9a-fA-F]{6}|[0-9a-fA-F]{8})|(rgb|rgba|hsl|hsla)\([0-9.,%\s/]+\)|[a-zA-Z]+)$";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Appearance {
+    #[default]
+    Light,
+    Dark,
+}
+
+// The on-disk schema. Unknown fields are rejected so typos surface as
+// errors instead of silently falling back to defaults.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub appearance: Appearance,
+    // One of Mermaid's built-in themes; `base` is the one meant to be
+    // customized through `theme_variables`.
+    #[serde(default = "default_mermaid_theme")]
+    pub mermaid_theme: String,
+    #[serde(default)]
+    pub theme_variables: BTreeMap<String, String>,
+    #[serde(default)]
+    pub background: Option<String>,
+    #[serde(default)]
+    pub editor_colors: BTreeMap<String, String>,
+    #[serde(default)]
+    pub syntax_colors: BTreeMap<String, String>,
+    #[serde(default, skip_deserializing)]
+    pub builtin: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ThemeSummary {
+    pub id: String,
+    pub name: String,
+    pub appearance: Appearance,
+    pub builtin: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct InvalidTheme {
+    pub file: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ThemeList {
+    pub themes: Vec<ThemeSummary>,
+    pub invalid: Vec<InvalidTheme>,
+}
+
+fn default_mermaid_theme() -> String {
+    "default".to_string()
+}
+
+impl Theme {
+    // The `--configFile` and background the Mermaid CLI needs to render
+    // exports the same way the preview does.
+    pub fn render_config(&self) -> RenderConfig {
+        let mut mermaid = json!({ "theme": self.mermaid_theme });
+        if !self.theme_variables.is_empty() {
+            mermaid["themeVariables"] = json!(self.theme_variables);
+        }
+        RenderConfig {
+            mermaid,
+            background: self.background.clone(),
+        }
+    }
+
+    fn summary(&self) -> ThemeSummary {
+        ThemeSummary {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            appearance: self.appearance,
+            builtin: self.builtin,
+        }
+    }
+}
+
+pub fn themes_dir() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("themes"))
+}
+
+fn validate(theme: &Theme) -> Result<(), String> {
+    let color = Regex::new(COLOR).unwrap();
+    let mut errors = Vec::new();
+
+    let valid_id = theme
+        .id
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if theme.id.is_empty() || !valid_id {
+        errors.push("id must use lowercase letters, digits, '-' or '_'".to_string());
+    }
+    if theme.name.trim().is_empty() {
+        errors.push("name must not be empty".to_string());
+    }
+    if !MERMAID_THEMES.contains(&theme.mermaid_theme.as_str()) {
+        errors.push(format!(
+            "mermaid_theme must be one of {}",
+            MERMAID_THEMES.join(", ")
+        ));
+    }
+    if let Some(background) = &theme.background {
+        if !color.is_match(background) {
+            errors.push(format!("background is not a color: {}", background));
+        }
+    }
+    for (group, colors) in [
+        ("editor_colors", &theme.editor_colors),
+        ("syntax_colors", &theme.syntax_colors),
+    ] {
+        for (key, value) in colors {
+            if !color.is_match(value) {
+                errors.push(format!("{}.{} is not a color: {}", group, key, value));
+            }
+        }
+    }
+    // Variables also hold fonts and sizes, so only keep out characters that
+    // could break out of the generated CSS.
+    for (key, value) in &theme.theme_variables {
+        if value.contains(['{', '}', ';', '<', '>']) {
+            errors.push(format!(
+                "theme_variables.{} contains invalid characters",
+                key
+            ));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+pub fn parse(json: &str) -> Result<Theme, String> {
+    let theme: Theme =
+        serde_json::from_str(json).map_err(|e| format!("Invalid theme file: {}", e))?;
+    validate(&theme)?;
+    Ok(theme)
+}
+
+fn builtin_themes() -> Vec<Theme> {
+    BUILTIN_THEMES
+        .iter()
+        .filter_map(|json| parse(json).ok())
+        .map(|theme| Theme {
+            builtin: true,
+            ..theme
+        })
+        .collect()
+}
+
+fn load_file(path: &Path) -> Result<Theme, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read theme file: {}", e))?;
+    parse(&content)
+}
+
+fn user_themes() -> (Vec<Theme>, Vec<InvalidTheme>) {
+    let mut themes = Vec::new();
+    let mut invalid = Vec::new();
+
+    let Ok(entries) = themes_dir().and_then(|dir| {
+        fs::read_dir(dir).map_err(|e| format!("Failed to read themes directory: {}", e))
+    }) else {
+        return (themes, invalid);
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        match load_file(&path) {
+            Ok(theme) => themes.push(theme),
+            Err(error) => {
+                tracing::warn!(path = %path.display(), error = %error, "skipping invalid theme");
+                invalid.push(InvalidTheme {
+                    file: path.to_string_lossy().to_string(),
+                    error,
+                });
+            }
+        }
+    }
+
+    (themes, invalid)
+}
+
+fn all_themes() -> (Vec<Theme>, Vec<InvalidTheme>) {
+    let (user, invalid) = user_themes();
+    let mut themes: Vec<Theme> = builtin_themes()
+        .into_iter()
+        .filter(|builtin| !user.iter().any(|theme| theme.id == builtin.id))
+        .collect();
+    themes.extend(user);
+    (themes, invalid)
+}
+
+pub fn list() -> ThemeList {
+    let (themes, invalid) = all_themes();
+    ThemeList {
+        themes: themes.iter().map(Theme::summary).collect(),
+        invalid,
+    }
+}
+
+pub fn get(id: &str) -> Result<Theme, String> {
+    all_themes()
+        .0
+        .into_iter()
+        .find(|theme| theme.id == id)
+        .ok_or_else(|| format!("Unknown theme: {}", id))
+}
+
+// A workspace's theme for its diagrams, kept as a theme id in the
+// workspace's hidden settings folder next to its export CSS.
+pub fn workspace_theme_path(workspace: &Path) -> PathBuf {
+    workspace.join(".flowcraft").join("theme")
+}
+
+pub fn workspace_theme(workspace: &Path) -> Option<String> {
+    fs::read_to_string(workspace_theme_path(workspace))
+        .ok()
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+}
+
+// Stores the workspace's theme, or removes it when `id` is None.
+pub fn set_workspace_theme(workspace: &Path, id: Option<&str>) -> Result<(), String> {
+    let path = workspace_theme_path(workspace);
+    match id {
+        Some(id) => {
+            get(id)?;
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|e| format!("Failed to create folder: {}", e))?;
+            }
+            fs::write(&path, format!("{}\n", id))
+                .map_err(|e| format!("Failed to save workspace theme: {}", e))
+        }
+        None if path.exists() => {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove workspace theme: {}", e))
+        }
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,207 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::export::{self, ExportOptions};
+use crate::{files, get_app_data_dir, is_diagram_file};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const THUMBNAIL_WIDTH: u32 = 240;
+const THUMBNAIL_HEIGHT: u32 = 180;
+// The oldest thumbnails are removed once the cache grows past this.
+const MAX_CACHE_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ThumbnailEvent {
+    Updated { path: String },
+    Failed { path: String, error: String },
+}
+
+pub struct ThumbnailHandle {
+    pub workspace: String,
+    stop: Arc<AtomicBool>,
+}
+
+impl ThumbnailHandle {
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for ThumbnailHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn cache_dir() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("thumbnails"))
+}
+
+// Thumbnails are named after the file's path and modification time, so an
+// edited file simply misses the cache and its old thumbnail ages out.
+fn cache_path(path: &Path, modified: SystemTime) -> Result<PathBuf, String> {
+    let mut hasher = DefaultHasher::new();
+    path.canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .hash(&mut hasher);
+    modified
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    Ok(cache_dir()?.join(format!("{:016x}.png", hasher.finish())))
+}
+
+fn modified(path: &Path) -> Result<SystemTime, String> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| format!("Failed to read file: {}", e))
+}
+
+fn generate(path: &Path, cached: &Path) -> Result<Vec<u8>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let options = ExportOptions {
+        max_width: Some(THUMBNAIL_WIDTH),
+        max_height: Some(THUMBNAIL_HEIGHT),
+        ..ExportOptions::default()
+    };
+    let rendered = export::render(&content, "png", &options)?;
+
+    fs::create_dir_all(cache_dir()?)
+        .map_err(|e| format!("Failed to create thumbnail cache: {}", e))?;
+    fs::write(cached, &rendered.bytes).map_err(|e| format!("Failed to write thumbnail: {}", e))?;
+    Ok(rendered.bytes)
+}
+
+// Returns the thumbnail for `path` as a PNG data URI, rendering it first
+// when the cache has none for the file's current version.
+pub fn get(path: &Path) -> Result<String, String> {
+    if !is_diagram_file(path) {
+        return Err(format!("Not a diagram file: {}", path.display()));
+    }
+    let cached = cache_path(path, modified(path)?)?;
+    let bytes = match fs::read(&cached) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            let bytes = generate(path, &cached)?;
+            prune();
+            bytes
+        }
+    };
+    Ok(format!(
+        "data:image/png;base64,{}",
+        general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+// Deletes the least recently written thumbnails until the cache fits in
+// MAX_CACHE_BYTES.
+fn prune() {
+    let Ok(entries) = cache_dir().and_then(|dir| {
+        fs::read_dir(dir).map_err(|e| format!("Failed to read thumbnail cache: {}", e))
+    }) else {
+        return;
+    };
+
+    let mut files: Vec<(PathBuf, u64, SystemTime)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((entry.path(), metadata.len(), metadata.modified().ok()?))
+        })
+        .collect();
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    files.sort_by_key(|(_, _, modified)| *modified);
+
+    for (path, size, _) in files {
+        if total <= MAX_CACHE_BYTES {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total -= size;
+        }
+    }
+}
+
+// Keeps thumbnails for every diagram under `workspace` up to date in the
+// background, one render at a time.
+pub fn spawn<F>(workspace: String, on_event: F) -> Result<ThumbnailHandle, String>
+where
+    F: Fn(ThumbnailEvent) + Send + 'static,
+{
+    let root = PathBuf::from(&workspace);
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", workspace));
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+
+    thread::Builder::new()
+        .name("thumbnails".to_string())
+        .spawn(move || run(&root, &thread_stop, on_event))
+        .map_err(|e| format!("Failed to start thumbnail generator: {}", e))?;
+
+    Ok(ThumbnailHandle { workspace, stop })
+}
+
+fn run<F>(root: &Path, stop: &AtomicBool, on_event: F)
+where
+    F: Fn(ThumbnailEvent),
+{
+    let mut seen: HashMap<PathBuf, SystemTime> = HashMap::new();
+
+    while !stop.load(Ordering::SeqCst) {
+        let current: HashMap<PathBuf, SystemTime> = files::collect_diagrams(root, None)
+            .into_iter()
+            .filter_map(|(path, metadata)| Some((path, metadata.modified().ok()?)))
+            .collect();
+        let mut generated = false;
+
+        for (path, modified) in &current {
+            if stop.load(Ordering::SeqCst) {
+                return;
+            }
+            if seen.get(path) == Some(modified) {
+                continue;
+            }
+            let Ok(cached) = cache_path(path, *modified) else {
+                continue;
+            };
+            if cached.is_file() {
+                continue;
+            }
+
+            let source = path.to_string_lossy().to_string();
+            match generate(path, &cached) {
+                Ok(_) => {
+                    generated = true;
+                    on_event(ThumbnailEvent::Updated { path: source });
+                }
+                Err(error) => {
+                    tracing::warn!(path = %source, error = %error, "thumbnail failed");
+                    on_event(ThumbnailEvent::Failed {
+                        path: source,
+                        error,
+                    });
+                }
+            }
+        }
+
+        if generated {
+            prune();
+        }
+        seen = current;
+        thread::sleep(POLL_INTERVAL);
+    }
+}
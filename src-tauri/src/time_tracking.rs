@@ -0,0 +1,189 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::get_app_data_dir;
+
+// A session left open by a window that lost track of it, or a machine that
+// went to sleep, counts for at most this long.
+const MAX_SESSION_SECONDS: i64 = 4 * 60 * 60;
+
+// Files being edited, with when their session started.
+static ACTIVE: Mutex<Option<HashMap<PathBuf, DateTime<Utc>>>> = Mutex::new(None);
+
+// Seconds spent per day, keyed by the day the session started.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct Recorded {
+    #[serde(default)]
+    days: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TimeTotal {
+    pub path: String,
+    pub total_seconds: u64,
+    pub days: BTreeMap<String, u64>,
+    // A session is running, and is not in the totals yet.
+    pub active: bool,
+}
+
+fn store_path() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join("time_tracking.json"))
+}
+
+fn load() -> Result<BTreeMap<String, Recorded>, String> {
+    let path = store_path()?;
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let json =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read time tracking: {}", e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid time tracking file: {}", e))
+}
+
+fn save(recorded: &BTreeMap<String, Recorded>) -> Result<(), String> {
+    let path = store_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create data folder: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(recorded)
+        .map_err(|e| format!("Failed to serialize time tracking: {}", e))?;
+    fs::write(path, json).map_err(|e| format!("Failed to save time tracking: {}", e))
+}
+
+fn key(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+fn is_active(path: &Path) -> bool {
+    ACTIVE
+        .lock()
+        .ok()
+        .and_then(|active| active.as_ref().map(|active| active.contains_key(path)))
+        .unwrap_or(false)
+}
+
+fn total(path: &str, recorded: &Recorded) -> TimeTotal {
+    TimeTotal {
+        path: path.to_string(),
+        total_seconds: recorded.days.values().sum(),
+        days: recorded.days.clone(),
+        active: is_active(Path::new(path)),
+    }
+}
+
+// Starts timing the file. Starting again while a session runs keeps the
+// earlier start, so focus events can be sent freely.
+pub fn start(path: &Path) {
+    if let Ok(mut active) = ACTIVE.lock() {
+        active
+            .get_or_insert_with(HashMap::new)
+            .entry(path.to_path_buf())
+            .or_insert_with(Utc::now);
+    }
+}
+
+fn add(recorded: &mut BTreeMap<String, Recorded>, path: &Path, started: DateTime<Utc>) {
+    let seconds = (Utc::now() - started)
+        .num_seconds()
+        .clamp(0, MAX_SESSION_SECONDS) as u64;
+    if seconds == 0 {
+        return;
+    }
+    let day = started.format("%Y-%m-%d").to_string();
+    *recorded
+        .entry(key(path))
+        .or_default()
+        .days
+        .entry(day)
+        .or_insert(0) += seconds;
+}
+
+// Ends the file's session and adds it to the totals.
+pub fn end(path: &Path) -> Result<TimeTotal, String> {
+    let started = ACTIVE
+        .lock()
+        .ok()
+        .and_then(|mut active| active.as_mut()?.remove(path));
+    let mut recorded = load()?;
+    if let Some(started) = started {
+        add(&mut recorded, path, started);
+        save(&recorded)?;
+    }
+    Ok(total(
+        &key(path),
+        &recorded.remove(&key(path)).unwrap_or_default(),
+    ))
+}
+
+// Ends every running session, for when the app quits.
+pub fn end_all() -> Result<(), String> {
+    let sessions = match ACTIVE.lock() {
+        Ok(mut active) => active.take().unwrap_or_default(),
+        Err(_) => return Ok(()),
+    };
+    if sessions.is_empty() {
+        return Ok(());
+    }
+    let mut recorded = load()?;
+    for (path, started) in sessions {
+        add(&mut recorded, &path, started);
+    }
+    save(&recorded)
+}
+
+// Totals for one file, or for every file with time recorded, most time
+// first.
+pub fn totals(path: Option<&Path>) -> Result<Vec<TimeTotal>, String> {
+    let recorded = load()?;
+    if let Some(path) = path {
+        let key = key(path);
+        return Ok(vec![total(
+            &key,
+            &recorded.get(&key).cloned().unwrap_or_default(),
+        )]);
+    }
+    let mut totals: Vec<TimeTotal> = recorded
+        .iter()
+        .map(|(path, recorded)| total(path, recorded))
+        .collect();
+    totals.sort_by_key(|total| Reverse(total.total_seconds));
+    Ok(totals)
+}
+
+// Keeps the time with a file that was renamed or moved.
+pub fn rename(from: &Path, to: &Path) -> Result<(), String> {
+    if let Ok(mut active) = ACTIVE.lock() {
+        if let Some(active) = active.as_mut() {
+            if let Some(started) = active.remove(from) {
+                active.insert(to.to_path_buf(), started);
+            }
+        }
+    }
+    let mut recorded = load()?;
+    let Some(moved) = recorded.remove(&key(from)) else {
+        return Ok(());
+    };
+    let target = recorded.entry(key(to)).or_default();
+    for (day, seconds) in moved.days {
+        *target.days.entry(day).or_insert(0) += seconds;
+    }
+    save(&recorded)
+}
+
+pub fn reset(path: &Path) -> Result<(), String> {
+    if let Ok(mut active) = ACTIVE.lock() {
+        if let Some(active) = active.as_mut() {
+            active.remove(path);
+        }
+    }
+    let mut recorded = load()?;
+    if recorded.remove(&key(path)).is_some() {
+        save(&recorded)?;
+    }
+    Ok(())
+}
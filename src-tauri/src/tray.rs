@@ -0,0 +1,92 @@
+use std::path::Path;
+use tauri::menu::{Menu, MenuBuilder, MenuEvent, MenuItemBuilder, SubmenuBuilder};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use crate::{
+    focus_main_window, i18n, new_diagram_content, open_in_running_app, AppStateType, RecentFile,
+};
+
+const TRAY_ID: &str = "main";
+const RECENT_PREFIX: &str = "recent:";
+
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    let menu = build_menu(app, &current_recent_files(app))?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip(i18n::t("tray.tooltip"))
+        .menu(&menu)
+        .on_menu_event(handle_menu_event);
+
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+
+    builder.build(app)?;
+    Ok(())
+}
+
+// Rebuilds the menu so the recent files submenu matches the store.
+pub fn refresh(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+
+    if let Ok(menu) = build_menu(app, &current_recent_files(app)) {
+        let _ = tray.set_menu(Some(menu));
+    }
+}
+
+fn current_recent_files(app: &AppHandle) -> Vec<RecentFile> {
+    app.state::<AppStateType>()
+        .lock()
+        .map(|app_state| app_state.recent_files.clone())
+        .unwrap_or_default()
+}
+
+fn build_menu(app: &AppHandle, recent_files: &[RecentFile]) -> tauri::Result<Menu<Wry>> {
+    let mut recent = SubmenuBuilder::new(app, i18n::t("tray.recent"));
+
+    if recent_files.is_empty() {
+        recent = recent.item(
+            &MenuItemBuilder::with_id("recent-empty", i18n::t("tray.recent_empty"))
+                .enabled(false)
+                .build(app)?,
+        );
+    }
+    for file in recent_files {
+        recent = recent.text(format!("{}{}", RECENT_PREFIX, file.path), &file.name);
+    }
+
+    MenuBuilder::new(app)
+        .text("new", i18n::t("tray.new"))
+        .item(&recent.build()?)
+        .text("export", i18n::t("tray.export"))
+        .separator()
+        .text("show", i18n::t("tray.show"))
+        .text("quit", i18n::t("tray.quit"))
+        .build()
+}
+
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    let id = event.id().as_ref();
+
+    if let Some(path) = id.strip_prefix(RECENT_PREFIX) {
+        open_in_running_app(app, Path::new(path), None);
+        return;
+    }
+
+    match id {
+        "new" => {
+            let _ = app.emit("tray-new-diagram", new_diagram_content());
+            focus_main_window(app);
+        }
+        "export" => {
+            let _ = app.emit("tray-export-current", ());
+            focus_main_window(app);
+        }
+        "show" => focus_main_window(app),
+        "quit" => app.exit(0),
+        _ => {}
+    }
+}
@@ -0,0 +1,124 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::encryption;
+
+// Kept per stack, oldest steps dropped first.
+const MAX_STEPS: usize = 100;
+// Steps are whole documents, so big diagrams keep fewer of them.
+const MAX_BYTES: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UndoStep {
+    pub content: String,
+    // Anchor and head of the selection to restore with the step.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selection: Option<[usize; 2]>,
+}
+
+// Both stacks end with the step applied next.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct UndoHistory {
+    #[serde(default)]
+    pub undo: Vec<UndoStep>,
+    #[serde(default)]
+    pub redo: Vec<UndoStep>,
+}
+
+impl UndoHistory {
+    fn is_empty(&self) -> bool {
+        self.undo.is_empty() && self.redo.is_empty()
+    }
+
+    // Drops the steps furthest from the current document until both limits
+    // hold, taking from whichever stack is longer.
+    fn trim(&mut self) {
+        let size = |steps: &[UndoStep]| steps.iter().map(|step| step.content.len()).sum::<usize>();
+        self.undo.drain(..self.undo.len().saturating_sub(MAX_STEPS));
+        self.redo.drain(..self.redo.len().saturating_sub(MAX_STEPS));
+        while size(&self.undo) + size(&self.redo) > MAX_BYTES {
+            if self.undo.len() >= self.redo.len() {
+                self.undo.remove(0);
+            } else {
+                self.redo.remove(0);
+            }
+        }
+    }
+}
+
+// The history is stored with a hash of the document it leads up to. If the
+// file was changed elsewhere since, the steps no longer fit and are dropped.
+#[derive(Debug, Serialize, Deserialize)]
+struct Stored {
+    content_hash: String,
+    saved_at: String,
+    #[serde(flatten)]
+    history: UndoHistory,
+}
+
+// Kept in a sidecar such as `flow.mmd.undo.json`.
+pub fn sidecar_path(diagram: &Path) -> PathBuf {
+    let mut name = diagram.as_os_str().to_owned();
+    name.push(".undo.json");
+    PathBuf::from(name)
+}
+
+fn hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// The history saved for the diagram, if it still applies to the file.
+pub fn get(diagram: &Path) -> Result<Option<UndoHistory>, String> {
+    let sidecar = sidecar_path(diagram);
+    if encryption::is_encrypted_path(diagram) || !sidecar.is_file() {
+        return Ok(None);
+    }
+    let data =
+        fs::read_to_string(&sidecar).map_err(|e| format!("Failed to read undo history: {}", e))?;
+    let stored: Stored = match serde_json::from_str(&data) {
+        Ok(stored) => stored,
+        Err(e) => {
+            tracing::warn!(path = %sidecar.display(), error = %e, "ignoring invalid undo history");
+            return Ok(None);
+        }
+    };
+    let content = fs::read_to_string(diagram).map_err(|e| format!("Failed to read file: {}", e))?;
+    if stored.content_hash != hash(&content) {
+        return Ok(None);
+    }
+    Ok(Some(stored.history))
+}
+
+// Saves the history for the diagram as it is on disk, so the editor calls
+// this after saving. An empty history removes the sidecar. Encrypted
+// diagrams get none, since the steps would be stored in plain text.
+pub fn set(diagram: &Path, mut history: UndoHistory) -> Result<(), String> {
+    if encryption::is_encrypted_path(diagram) {
+        return Ok(());
+    }
+    let sidecar = sidecar_path(diagram);
+    if history.is_empty() {
+        if sidecar.exists() {
+            fs::remove_file(&sidecar)
+                .map_err(|e| format!("Failed to remove undo history: {}", e))?;
+        }
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(diagram).map_err(|e| format!("Failed to read file: {}", e))?;
+    history.trim();
+    let stored = Stored {
+        content_hash: hash(&content),
+        saved_at: Utc::now().to_rfc3339(),
+        history,
+    };
+    let data = serde_json::to_string(&stored)
+        .map_err(|e| format!("Failed to save undo history: {}", e))?;
+    fs::write(&sidecar, data).map_err(|e| format!("Failed to save undo history: {}", e))
+}
@@ -0,0 +1,123 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::time::Duration;
+
+const RELEASES_URL: &str = "https://api.github.com/repos/Pyro18/flowcraft-studio/releases/latest";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    html_url: String,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    published_at: Option<String>,
+    #[serde(default)]
+    assets: Vec<GithubAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UpdateInfo {
+    pub current_version: String,
+    pub latest_version: String,
+    pub update_available: bool,
+    pub changelog: String,
+    pub download_url: String,
+    pub release_url: String,
+    pub published_at: Option<String>,
+}
+
+fn releases_url() -> String {
+    env::var("FLOWCRAFT_UPDATE_URL").unwrap_or_else(|_| RELEASES_URL.to_string())
+}
+
+fn parse_version(tag: &str) -> Result<Version, String> {
+    let tag = tag.trim();
+    let tag = tag
+        .strip_prefix('v')
+        .or_else(|| tag.strip_prefix("app-v"))
+        .unwrap_or(tag);
+    Version::parse(tag).map_err(|e| format!("Invalid release version {}: {}", tag, e))
+}
+
+// Installer extensions in order of preference for this platform.
+fn installer_extensions() -> &'static [&'static str] {
+    match env::consts::OS {
+        "windows" => &[".msi", "-setup.exe", ".exe"],
+        "macos" => &[".dmg", ".app.tar.gz"],
+        "linux" => &[".AppImage", ".deb", ".rpm"],
+        _ => &[],
+    }
+}
+
+fn pick_asset(assets: &[GithubAsset]) -> Option<&GithubAsset> {
+    let arch_hints: &[&str] = match env::consts::ARCH {
+        "x86_64" => &["x64", "x86_64", "amd64"],
+        "aarch64" => &["aarch64", "arm64"],
+        _ => &[],
+    };
+
+    installer_extensions().iter().find_map(|extension| {
+        let candidates: Vec<&GithubAsset> = assets
+            .iter()
+            .filter(|asset| asset.name.ends_with(extension))
+            .collect();
+        candidates
+            .iter()
+            .find(|asset| arch_hints.iter().any(|hint| asset.name.contains(hint)))
+            .or_else(|| candidates.first())
+            .copied()
+    })
+}
+
+pub async fn check(current_version: &str) -> Result<UpdateInfo, String> {
+    let current = Version::parse(current_version)
+        .map_err(|e| format!("Invalid app version {}: {}", current_version, e))?;
+
+    let response = reqwest::Client::new()
+        .get(releases_url())
+        .header(
+            "User-Agent",
+            format!("flowcraft-studio/{}", current_version),
+        )
+        .header("Accept", "application/vnd.github+json")
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to check for updates: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to check for updates: GitHub returned {}",
+            response.status().as_u16()
+        ));
+    }
+
+    let release: GithubRelease = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to read release information: {}", e))?;
+    let latest = parse_version(&release.tag_name)?;
+
+    let download_url = pick_asset(&release.assets)
+        .map(|asset| asset.browser_download_url.clone())
+        .unwrap_or_else(|| release.html_url.clone());
+
+    Ok(UpdateInfo {
+        current_version: current.to_string(),
+        latest_version: latest.to_string(),
+        update_available: latest > current,
+        changelog: release.body.unwrap_or_default(),
+        download_url,
+        release_url: release.html_url,
+        published_at: release.published_at,
+    })
+}
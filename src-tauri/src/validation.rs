@@ -0,0 +1,126 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::{validate_content, ValidationResult};
+
+// How long a document has to stay unchanged before it is validated; calls
+// arriving within the window replace the pending one.
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+// One counter for all requests, so generations also increase per document.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+// Latest generation per document. Each pending request watches its
+// document's channel and gives up as soon as a newer one arrives.
+static DOCUMENTS: Mutex<Option<HashMap<String, watch::Sender<u64>>>> = Mutex::new(None);
+
+#[derive(Debug, Serialize)]
+pub struct ValidationResponse {
+    #[serde(flatten)]
+    pub result: ValidationResult,
+    // Increases with every request for the same document; the UI should
+    // drop any response older than the newest it has shown.
+    pub generation: u64,
+    // Set when a newer request replaced this one before it finished. The
+    // result is empty and should be ignored.
+    pub stale: bool,
+}
+
+fn next_generation() -> u64 {
+    GENERATION.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+fn register(document: &str) -> Result<(u64, watch::Receiver<u64>), String> {
+    let mut documents = DOCUMENTS
+        .lock()
+        .map_err(|_| "Failed to lock validation state".to_string())?;
+    let sender = documents
+        .get_or_insert_with(HashMap::new)
+        .entry(document.to_string())
+        .or_insert_with(|| watch::channel(0).0);
+
+    let generation = next_generation();
+    sender.send_replace(generation);
+    Ok((generation, sender.subscribe()))
+}
+
+// Forgets the document once its newest request is done, so the map only
+// holds documents with a request in flight.
+fn release(document: &str, generation: u64) {
+    if let Ok(mut documents) = DOCUMENTS.lock() {
+        if let Some(documents) = documents.as_mut() {
+            if documents
+                .get(document)
+                .is_some_and(|sender| *sender.borrow() == generation)
+            {
+                documents.remove(document);
+            }
+        }
+    }
+}
+
+fn stale(generation: u64) -> ValidationResponse {
+    ValidationResponse {
+        result: ValidationResult {
+            is_valid: true,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        },
+        generation,
+        stale: true,
+    }
+}
+
+// Resolves once a newer request for the document has been registered.
+async fn superseded(mut receiver: watch::Receiver<u64>, generation: u64) {
+    while receiver.changed().await.is_ok() {
+        if *receiver.borrow() != generation {
+            return;
+        }
+    }
+    std::future::pending::<()>().await
+}
+
+async fn run(content: String) -> Result<ValidationResult, String> {
+    tokio::task::spawn_blocking(move || validate_content(&content))
+        .await
+        .map_err(|e| format!("Failed to validate: {}", e))
+}
+
+// Validates `content` for `document` (a file path or editor tab id). Rapid
+// calls for the same document are debounced and only the newest one does
+// the work; the others come back marked stale. Without a document every
+// call is validated right away.
+pub async fn validate(
+    content: String,
+    document: Option<String>,
+) -> Result<ValidationResponse, String> {
+    let Some(document) = document else {
+        let generation = next_generation();
+        return Ok(ValidationResponse {
+            result: run(content).await?,
+            generation,
+            stale: false,
+        });
+    };
+
+    let (generation, receiver) = register(&document)?;
+    let work = async {
+        tokio::time::sleep(DEBOUNCE).await;
+        run(content).await
+    };
+
+    let response = tokio::select! {
+        _ = superseded(receiver, generation) => Ok(stale(generation)),
+        result = work => result.map(|result| ValidationResponse {
+            result,
+            generation,
+            stale: false,
+        }),
+    };
+    release(&document, generation);
+    response
+}
@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::redaction::{self, RedactionConfig};
+use crate::{files, render};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WatchConfig {
+    pub source_dir: String,
+    pub output_dir: String,
+    pub formats: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WatchEvent {
+    Started { source_dir: String },
+    Exported { source: String, output: String },
+    Failed { source: String, error: String },
+    Stopped,
+}
+
+pub struct WatchHandle {
+    pub config: WatchConfig,
+    stop: Arc<AtomicBool>,
+}
+
+impl WatchHandle {
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// Exports go through `redaction` when it is set, like every other export.
+pub fn spawn<F>(
+    config: WatchConfig,
+    redaction: Option<RedactionConfig>,
+    on_event: F,
+) -> Result<WatchHandle, String>
+where
+    F: Fn(WatchEvent) + Send + 'static,
+{
+    let source_dir = PathBuf::from(&config.source_dir);
+    if !source_dir.is_dir() {
+        return Err(format!("Not a directory: {}", config.source_dir));
+    }
+    if config.formats.is_empty() {
+        return Err("No export formats selected".to_string());
+    }
+    if let Some(format) = config
+        .formats
+        .iter()
+        .find(|f| !render::SUPPORTED_FORMATS.contains(&f.as_str()))
+    {
+        return Err(format!("Unsupported format: {}", format));
+    }
+
+    fs::create_dir_all(&config.output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let thread_config = config.clone();
+
+    thread::Builder::new()
+        .name("watch-export".to_string())
+        .spawn(move || run_pipeline(&thread_config, redaction.as_ref(), &thread_stop, on_event))
+        .map_err(|e| format!("Failed to start watcher: {}", e))?;
+
+    Ok(WatchHandle { config, stop })
+}
+
+fn run_pipeline<F>(
+    config: &WatchConfig,
+    redaction: Option<&RedactionConfig>,
+    stop: &AtomicBool,
+    on_event: F,
+) where
+    F: Fn(WatchEvent),
+{
+    let source_dir = Path::new(&config.source_dir);
+    let output_dir = Path::new(&config.output_dir);
+    let mut seen: HashMap<PathBuf, SystemTime> = HashMap::new();
+
+    on_event(WatchEvent::Started {
+        source_dir: config.source_dir.clone(),
+    });
+
+    while !stop.load(Ordering::SeqCst) {
+        let current: HashMap<PathBuf, SystemTime> =
+            files::collect_diagrams(source_dir, Some(output_dir))
+                .into_iter()
+                .filter_map(|(path, metadata)| Some((path, metadata.modified().ok()?)))
+                .collect();
+
+        for (path, modified) in &current {
+            if seen.get(path) == Some(modified) {
+                continue;
+            }
+
+            for format in &config.formats {
+                let output = output_path(source_dir, output_dir, path, format);
+
+                // On the first pass only stale outputs are rebuilt.
+                if !seen.contains_key(path) && is_up_to_date(&output, *modified) {
+                    continue;
+                }
+
+                let result = output
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .map_err(|e| format!("Failed to create output directory: {}", e))
+                    .and_then(|_| export_file(path, &output, format, redaction));
+
+                match &result {
+                    Ok(()) => {
+                        tracing::info!(source = %path.display(), output = %output.display(), "exported diagram")
+                    }
+                    Err(e) => {
+                        tracing::warn!(source = %path.display(), error = %e, "watch export failed")
+                    }
+                }
+
+                on_event(match result {
+                    Ok(()) => WatchEvent::Exported {
+                        source: path.to_string_lossy().to_string(),
+                        output: output.to_string_lossy().to_string(),
+                    },
+                    Err(error) => WatchEvent::Failed {
+                        source: path.to_string_lossy().to_string(),
+                        error,
+                    },
+                });
+            }
+        }
+
+        seen = current;
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    on_event(WatchEvent::Stopped);
+}
+
+fn export_file(
+    source: &Path,
+    output: &Path,
+    format: &str,
+    redaction: Option<&RedactionConfig>,
+) -> Result<(), String> {
+    let Some(config) = redaction else {
+        return render::render_file(source, output, format);
+    };
+    let content =
+        fs::read_to_string(source).map_err(|e| format!("Failed to read diagram: {}", e))?;
+    let redacted = redaction::apply(&content, config);
+    let bytes = render::render_content_to_bytes(&redacted.content, format)?;
+    fs::write(output, bytes).map_err(|e| format!("Failed to write export: {}", e))
+}
+
+fn output_path(source_dir: &Path, output_dir: &Path, source: &Path, format: &str) -> PathBuf {
+    let relative = source.strip_prefix(source_dir).unwrap_or(source);
+    output_dir.join(relative).with_extension(format)
+}
+
+fn is_up_to_date(output: &Path, source_modified: SystemTime) -> bool {
+    fs::metadata(output)
+        .and_then(|m| m.modified())
+        .map(|modified| modified >= source_modified)
+        .unwrap_or(false)
+}
@@ -0,0 +1,334 @@
+use chrono::Utc;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use crate::export::{self, ExportOptions};
+use crate::mermaid_config::MermaidConfig;
+use crate::{export_css, file_settings, files};
+
+pub const DEFAULT_PATTERN: &str = "{relpath}/{name}.{ext}";
+
+// Written to the output folder after every run, for CI to check.
+pub const MANIFEST_FILE: &str = "export-manifest.json";
+
+const PLACEHOLDERS: [&str; 3] = ["relpath", "name", "ext"];
+
+// Paths in the manifest are relative, sources to the workspace and outputs
+// to the output folder, so it reads the same on every machine.
+#[derive(Debug, Serialize, Clone)]
+pub struct ExportedFile {
+    pub source: String,
+    // SHA-256 of the diagram source.
+    pub input_hash: String,
+    pub output: String,
+    pub render_ms: u64,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SkippedExport {
+    pub source: String,
+    pub input_hash: String,
+    pub output: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FailedExport {
+    pub source: String,
+    pub input_hash: Option<String>,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct WorkspaceExport {
+    pub generated_at: String,
+    pub format: String,
+    pub pattern: String,
+    // SHA-256 of the format, export options and Mermaid settings; outputs
+    // of a run with a different hash are exported again.
+    pub options_hash: String,
+    pub exported: Vec<ExportedFile>,
+    // Sources whose output is newer than they are.
+    pub skipped: Vec<SkippedExport>,
+    pub failed: Vec<FailedExport>,
+}
+
+// Checks that a naming pattern only uses known placeholders and stays
+// inside the output folder.
+pub fn validate_pattern(pattern: &str) -> Result<(), String> {
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or("The pattern has a `{` without a matching `}`")?;
+        let name = &rest[start + 1..end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "Unknown placeholder {{{}}}; use {{relpath}}, {{name}} or {{ext}}",
+                name
+            ));
+        }
+        rest = &rest[end + 1..];
+    }
+    if !pattern.contains("{name}") {
+        return Err("The pattern must contain {name}".to_string());
+    }
+    let sample = pattern
+        .replace("{relpath}", "folder")
+        .replace("{name}", "name")
+        .replace("{ext}", "svg");
+    if !only_names(Path::new(&sample)) {
+        return Err("The pattern must stay inside the output folder".to_string());
+    }
+    Ok(())
+}
+
+// True when the path only descends: no root, drive, `.` or `..`.
+fn only_names(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+// Output path of `source` under `output_dir`. `{relpath}` is the folder of
+// the diagram relative to the workspace, empty at its root.
+pub fn output_path(
+    workspace: &Path,
+    output_dir: &Path,
+    source: &Path,
+    pattern: &str,
+    format: &str,
+) -> Result<PathBuf, String> {
+    let relative = source
+        .strip_prefix(workspace)
+        .map_err(|_| format!("{} is not in the workspace", source.display()))?;
+    let relpath = relative
+        .parent()
+        .map(|dir| dir.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default();
+    let name = source.file_stem().unwrap_or_default().to_string_lossy();
+    let expanded = pattern
+        .replace("{relpath}", &relpath)
+        .replace("{name}", &name)
+        .replace("{ext}", format);
+    // Empty parts come from {relpath} at the workspace root.
+    let expanded: PathBuf = expanded
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .collect();
+    if expanded.as_os_str().is_empty() || !only_names(&expanded) {
+        return Err(format!(
+            "The output of {} would be outside the output folder",
+            relative.display()
+        ));
+    }
+    Ok(output_dir.join(expanded))
+}
+
+// Identifies everything besides the sources that decides what an export
+// looks like, so changing it makes every output stale.
+fn options_hash(
+    format: &str,
+    options: &ExportOptions,
+    mermaid_config: Option<&MermaidConfig>,
+) -> String {
+    let settings = serde_json::json!({
+        "format": format,
+        "options": options,
+        "redaction": options.redaction,
+        "mermaid_config": mermaid_config,
+    });
+    hash(&settings.to_string())
+}
+
+// The options hash of the previous run into `output_dir`, if it left a
+// manifest.
+fn previous_options_hash(output_dir: &Path) -> Option<String> {
+    let manifest = fs::read_to_string(output_dir.join(MANIFEST_FILE)).ok()?;
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).ok()?;
+    manifest.get("options_hash")?.as_str().map(str::to_string)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn relative(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+fn export_one(
+    workspace: &Path,
+    source: &Path,
+    content: &str,
+    output: &Path,
+    format: &str,
+    options: &ExportOptions,
+    mermaid_config: Option<&MermaidConfig>,
+) -> Result<export::ExportResult, String> {
+    let settings = file_settings::get(content)?;
+    let mut options = options.clone();
+    settings.export.apply(&mut options);
+    options.source = Some(source.to_string_lossy().to_string());
+    options.image_dir = source.parent().map(|dir| dir.to_string_lossy().to_string());
+    options.workspace = Some(workspace.to_string_lossy().to_string());
+    if options.css.is_none() {
+        options.css = export_css::resolve(Some(source), Some(workspace))?;
+    }
+    if let Some(dir) = output.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create output folder: {}", e))?;
+    }
+    let run = || export::export(content, output, format, &options);
+    match mermaid_config {
+        Some(mermaid_config) => {
+            let theme = file_settings::export_theme(content, Some(source), Some(workspace));
+            file_settings::with_theme(&theme, mermaid_config, run)
+        }
+        None => run(),
+    }
+}
+
+// Exports every diagram in the workspace to `output_dir`, named by
+// `pattern`. Outputs newer than their source are left alone unless the
+// options changed since the last run, and a failing diagram does not stop
+// the others. The result is also written to the output folder as the
+// manifest. Diagrams get their own or the workspace's
+// theme with `mermaid_config` on top; without one they all use the current
+// theme.
+pub fn export_all(
+    workspace: &Path,
+    output_dir: &Path,
+    pattern: &str,
+    format: &str,
+    options: &ExportOptions,
+    mermaid_config: Option<&MermaidConfig>,
+) -> Result<WorkspaceExport, String> {
+    if !workspace.is_dir() {
+        return Err(format!("Not a folder: {}", workspace.display()));
+    }
+    if !export::FORMATS.contains(&format) {
+        return Err(format!("Unsupported format: {}", format));
+    }
+    validate_pattern(pattern)?;
+    fs::create_dir_all(output_dir).map_err(|e| format!("Failed to create output folder: {}", e))?;
+
+    let mut sources: Vec<PathBuf> = files::collect_diagrams(workspace, Some(output_dir))
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect();
+    sources.sort();
+    let options_hash = options_hash(format, options, mermaid_config);
+    let same_options = previous_options_hash(output_dir).as_deref() == Some(options_hash.as_str());
+
+    let mut result = WorkspaceExport {
+        generated_at: Utc::now().to_rfc3339(),
+        format: format.to_string(),
+        pattern: pattern.to_string(),
+        options_hash,
+        exported: Vec::new(),
+        skipped: Vec::new(),
+        failed: Vec::new(),
+    };
+    // Without {relpath}, diagrams in different folders can share a name.
+    let mut claimed: HashMap<PathBuf, PathBuf> = HashMap::new();
+    for source in sources {
+        let output = match output_path(workspace, output_dir, &source, pattern, format) {
+            Ok(output) => output,
+            Err(error) => {
+                result.failed.push(FailedExport {
+                    source: relative(&source, workspace),
+                    input_hash: None,
+                    error,
+                });
+                continue;
+            }
+        };
+        if let Some(first) = claimed.get(&output) {
+            result.failed.push(FailedExport {
+                source: relative(&source, workspace),
+                input_hash: None,
+                error: format!(
+                    "{} is already the output of {}",
+                    relative(&output, output_dir),
+                    relative(first, workspace)
+                ),
+            });
+            continue;
+        }
+        claimed.insert(output.clone(), source.clone());
+        let up_to_date = same_options
+            && match (modified(&source), modified(&output)) {
+                (Some(source), Some(output)) => output >= source,
+                _ => false,
+            };
+        let content = match fs::read_to_string(&source) {
+            Ok(content) => content,
+            Err(e) => {
+                result.failed.push(FailedExport {
+                    source: relative(&source, workspace),
+                    input_hash: None,
+                    error: format!("Failed to read file: {}", e),
+                });
+                continue;
+            }
+        };
+        let input_hash = hash(&content);
+        if up_to_date {
+            result.skipped.push(SkippedExport {
+                source: relative(&source, workspace),
+                input_hash,
+                output: relative(&output, output_dir),
+            });
+            continue;
+        }
+
+        let started = Instant::now();
+        match export_one(
+            workspace,
+            &source,
+            &content,
+            &output,
+            format,
+            options,
+            mermaid_config,
+        ) {
+            Ok(exported) => result.exported.push(ExportedFile {
+                source: relative(&source, workspace),
+                input_hash,
+                output: relative(&output, output_dir),
+                render_ms: started.elapsed().as_millis() as u64,
+                warnings: exported.warnings,
+            }),
+            Err(error) => {
+                tracing::warn!(source = %source.display(), error = %error, "workspace export failed");
+                result.failed.push(FailedExport {
+                    source: relative(&source, workspace),
+                    input_hash: Some(input_hash),
+                    error,
+                });
+            }
+        }
+    }
+
+    let manifest = serde_json::to_string_pretty(&result)
+        .map_err(|e| format!("Failed to serialize export manifest: {}", e))?;
+    fs::write(output_dir.join(MANIFEST_FILE), manifest)
+        .map_err(|e| format!("Failed to write export manifest: {}", e))?;
+    Ok(result)
+}
@@ -4,7 +4,8 @@ import { useState, useEffect } from 'react';
 import { MermaidEditor } from '@/components/mermaid-editor';
 import { MermaidPreview } from '@/components/mermaid-preview';
 import { TemplateManager } from '@/components/template-manager';
-import { useTauri, Template, RecentFile, ValidationResult } from '@/hooks/use-tauri';
+import { PassphraseDialog } from '@/components/passphrase-dialog';
+import { useTauri, FileContent, Template, RecentFile, ValidationResult } from '@/hooks/use-tauri';
 import { Card, CardContent, CardHeader, CardTitle } from '@/components/ui/card';
 import { Button } from '@/components/ui/button';
 import { Tabs, TabsContent, TabsList, TabsTrigger } from '@/components/ui/tabs';
@@ -30,7 +31,7 @@ import { useToast } from '@/hooks/use-toast';
 export function FlowCraftStudio() {
   const { theme, setTheme } = useTheme();
   const { toast } = useToast();
-  const { getRecentFiles, clearRecentFiles, loadFile } = useTauri();
+  const { getRecentFiles, clearRecentFiles, loadFile, openEncryptedFile } = useTauri();
 
   const [content, setContent] = useState(`flowchart TD
     A[Start] --> B{Decision?}
@@ -42,6 +43,8 @@ export function FlowCraftStudio() {
   const [validation, setValidation] = useState<ValidationResult | null>(null);
   const [recentFiles, setRecentFiles] = useState<RecentFile[]>([]);
   const [activeTab, setActiveTab] = useState('editor');
+  // A recent file that turned out to be encrypted, waiting for its passphrase.
+  const [encryptedFile, setEncryptedFile] = useState<RecentFile | null>(null);
 
   useEffect(() => {
     loadRecentFiles();
@@ -65,22 +68,45 @@ export function FlowCraftStudio() {
     });
   };
 
+  const showOpenedFile = async (file: RecentFile, fileContent: FileContent) => {
+    setContent(fileContent.content);
+    setActiveTab('editor');
+    await loadRecentFiles(); // Refresh recent files list
+    toast({
+      title: "File caricato",
+      description: `File "${file.name}" aperto con successo`,
+    });
+  };
+
+  const showOpenError = (error: unknown) => {
+    toast({
+      title: "Errore",
+      description: `Impossibile aprire il file: ${(error as Error).message}`,
+      variant: "destructive",
+    });
+  };
+
   const handleRecentFileOpen = async (file: RecentFile) => {
     try {
       const fileContent = await loadFile(file.path);
-      setContent(fileContent.content);
-      setActiveTab('editor');
-      await loadRecentFiles(); // Refresh recent files list
-      toast({
-        title: "File caricato",
-        description: `File "${file.name}" aperto con successo`,
-      });
+      if (fileContent.encrypted) {
+        setEncryptedFile(file);
+        return;
+      }
+      await showOpenedFile(file, fileContent);
     } catch (error) {
-      toast({
-        title: "Errore",
-        description: `Impossibile aprire il file: ${(error as Error).message}`,
-        variant: "destructive",
-      });
+      showOpenError(error);
+    }
+  };
+
+  const handleEncryptedFileOpen = async (passphrase: string) => {
+    const file = encryptedFile;
+    setEncryptedFile(null);
+    if (!file) return;
+    try {
+      await showOpenedFile(file, await openEncryptedFile(file.path, passphrase));
+    } catch (error) {
+      showOpenError(error);
     }
   };
 
@@ -291,6 +317,14 @@ export function FlowCraftStudio() {
           </div>
         </Tabs>
       </main>
+
+      <PassphraseDialog
+        open={encryptedFile !== null}
+        mode="open"
+        fileName={encryptedFile?.name}
+        onSubmit={handleEncryptedFileOpen}
+        onCancel={() => setEncryptedFile(null)}
+      />
     </div>
   );
 }
@@ -2,14 +2,16 @@
 
 import { useEffect, useRef, useState } from 'react';
 import { useMonacoEditor } from '@/hooks/use-monaco-editor';
-import { useTauri, ValidationResult } from '@/hooks/use-tauri';
+import { useTauri, FileContent, ValidationResult } from '@/hooks/use-tauri';
 import { Button } from '@/components/ui/button';
 import { Card, CardContent, CardHeader, CardTitle } from '@/components/ui/card';
 import { Separator } from '@/components/ui/separator';
 import { Badge } from '@/components/ui/badge';
 import { Alert, AlertDescription } from '@/components/ui/alert';
+import { PassphraseDialog } from '@/components/passphrase-dialog';
 import {
   Save,
+  Lock,
   FolderOpen,
   Download,
   Play,
@@ -37,6 +39,8 @@ export function MermaidEditor({
   const {
     saveFile,
     loadFile,
+    saveEncryptedFile,
+    openEncryptedFile,
     validateMermaidSyntax,
     exportDiagram,
     isLoading,
@@ -47,6 +51,11 @@ export function MermaidEditor({
   const [currentFilePath, setCurrentFilePath] = useState<string | null>(null);
   const [validation, setValidation] = useState<ValidationResult | null>(null);
   const [isValidating, setIsValidating] = useState(false);
+  const latestValidation = useRef(0);
+  // An encrypted file waiting for its passphrase, or a save waiting for one.
+  const [passphrasePrompt, setPassphrasePrompt] = useState<
+    { mode: 'open'; path: string } | { mode: 'save' } | null
+  >(null);
 
   // Initialize editor
   useEffect(() => {
@@ -103,17 +112,31 @@ export function MermaidEditor({
 
     setIsValidating(true);
     try {
-      const result = await validateMermaidSyntax(content);
+      // The backend coalesces rapid calls per document; superseded or
+      // out-of-order responses are dropped here.
+      const result = await validateMermaidSyntax(content, currentFilePath ?? 'untitled');
+      const generation = result.generation ?? 0;
+      if (result.stale || generation < latestValidation.current) {
+        return;
+      }
+      latestValidation.current = generation;
       setValidation(result);
       onValidationChange?.(result);
+      setIsValidating(false);
     } catch (err) {
       console.error('Validation error:', err);
-    } finally {
       setIsValidating(false);
     }
   };
 
+  const isEncryptedPath = (path: string | null) => !!path && /\.mmdx$/i.test(path);
+
   const handleSave = async () => {
+    // The backend refuses to write plain text under the encrypted extension.
+    if (isEncryptedPath(currentFilePath)) {
+      setPassphrasePrompt({ mode: 'save' });
+      return;
+    }
     try {
       const filePath = await saveFile(content, currentFilePath || undefined);
       setCurrentFilePath(filePath);
@@ -130,20 +153,60 @@ export function MermaidEditor({
     }
   };
 
+  const handleSaveEncrypted = async (passphrase: string) => {
+    setPassphrasePrompt(null);
+    try {
+      const path = isEncryptedPath(currentFilePath) ? currentFilePath! : undefined;
+      const filePath = await saveEncryptedFile(content, passphrase, path);
+      setCurrentFilePath(filePath);
+      toast({
+        title: "File salvato",
+        description: `File cifrato salvato in: ${filePath}`,
+      });
+    } catch (err) {
+      toast({
+        title: "Errore nel salvare",
+        description: (err as Error).message,
+        variant: "destructive",
+      });
+    }
+  };
+
+  const showLoaded = (fileContent: FileContent) => {
+    setContent(fileContent.content);
+    setCurrentFilePath(fileContent.path || null);
+
+    if (editorRef.current) {
+      editorRef.current.setValue(fileContent.content);
+    }
+
+    toast({
+      title: "File caricato",
+      description: `File caricato: ${fileContent.path}`,
+    });
+  };
+
   const handleLoad = async () => {
     try {
       const fileContent = await loadFile();
-      setContent(fileContent.content);
-      setCurrentFilePath(fileContent.path || null);
-
-      if (editorRef.current) {
-        editorRef.current.setValue(fileContent.content);
+      if (fileContent.encrypted && fileContent.path) {
+        setPassphrasePrompt({ mode: 'open', path: fileContent.path });
+        return;
       }
-
+      showLoaded(fileContent);
+    } catch (err) {
       toast({
-        title: "File caricato",
-        description: `File caricato: ${fileContent.path}`,
+        title: "Errore nel caricare",
+        description: (err as Error).message,
+        variant: "destructive",
       });
+    }
+  };
+
+  const handleOpenEncrypted = async (path: string, passphrase: string) => {
+    setPassphrasePrompt(null);
+    try {
+      showLoaded(await openEncryptedFile(path, passphrase));
     } catch (err) {
       toast({
         title: "Errore nel caricare",
@@ -234,6 +297,16 @@ export function MermaidEditor({
               Apri
             </Button>
 
+            <Button
+              onClick={() => setPassphrasePrompt({ mode: 'save' })}
+              disabled={isLoading}
+              size="sm"
+              variant="outline"
+            >
+              <Lock className="w-4 h-4 mr-2" />
+              Salva cifrato
+            </Button>
+
             <Separator orientation="vertical" className="h-6" />
 
             <Button
@@ -322,6 +395,24 @@ export function MermaidEditor({
           <AlertDescription>{error}</AlertDescription>
         </Alert>
       )}
+
+      <PassphraseDialog
+        open={passphrasePrompt !== null}
+        mode={passphrasePrompt?.mode ?? 'open'}
+        fileName={
+          passphrasePrompt?.mode === 'open'
+            ? passphrasePrompt.path.split(/[/\\]/).pop()
+            : undefined
+        }
+        onSubmit={(passphrase) => {
+          if (passphrasePrompt?.mode === 'open') {
+            handleOpenEncrypted(passphrasePrompt.path, passphrase);
+          } else {
+            handleSaveEncrypted(passphrase);
+          }
+        }}
+        onCancel={() => setPassphrasePrompt(null)}
+      />
     </div>
   );
 }
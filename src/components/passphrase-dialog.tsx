@@ -0,0 +1,112 @@
+'use client';
+
+import { useEffect, useState, type FormEvent } from 'react';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { Label } from '@/components/ui/label';
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogFooter,
+  DialogHeader,
+  DialogTitle,
+} from '@/components/ui/dialog';
+
+// Matches MIN_PASSPHRASE_CHARS in the backend, which checks it again.
+const MIN_PASSPHRASE_CHARS = 8;
+
+interface PassphraseDialogProps {
+  open: boolean;
+  // 'open' asks once; 'save' asks twice so a typo does not lock the file.
+  mode: 'open' | 'save';
+  fileName?: string;
+  onSubmit: (passphrase: string) => void;
+  onCancel: () => void;
+}
+
+export function PassphraseDialog({
+  open,
+  mode,
+  fileName,
+  onSubmit,
+  onCancel
+}: PassphraseDialogProps) {
+  const [passphrase, setPassphrase] = useState('');
+  const [confirmation, setConfirmation] = useState('');
+
+  // Never keep a passphrase around once the dialog closes.
+  useEffect(() => {
+    if (!open) {
+      setPassphrase('');
+      setConfirmation('');
+    }
+  }, [open]);
+
+  const tooShort = mode === 'save' && Array.from(passphrase).length < MIN_PASSPHRASE_CHARS;
+  const mismatch = mode === 'save' && passphrase !== confirmation;
+  const canSubmit = passphrase.length > 0 && !tooShort && !mismatch;
+
+  const handleSubmit = (event: FormEvent) => {
+    event.preventDefault();
+    if (canSubmit) {
+      onSubmit(passphrase);
+    }
+  };
+
+  return (
+    <Dialog open={open} onOpenChange={(isOpen) => !isOpen && onCancel()}>
+      <DialogContent>
+        <form onSubmit={handleSubmit} className="space-y-4">
+          <DialogHeader>
+            <DialogTitle>
+              {mode === 'open' ? 'File protetto da passphrase' : 'Salva cifrato'}
+            </DialogTitle>
+            <DialogDescription>
+              {mode === 'open'
+                ? `Inserisci la passphrase per aprire ${fileName ?? 'il file'}.`
+                : `La passphrase non può essere recuperata: senza di essa il file resta illeggibile. Almeno ${MIN_PASSPHRASE_CHARS} caratteri.`}
+            </DialogDescription>
+          </DialogHeader>
+
+          <div className="space-y-2">
+            <Label htmlFor="passphrase">Passphrase</Label>
+            <Input
+              id="passphrase"
+              type="password"
+              autoFocus
+              autoComplete={mode === 'open' ? 'current-password' : 'new-password'}
+              value={passphrase}
+              onChange={(event) => setPassphrase(event.target.value)}
+            />
+          </div>
+
+          {mode === 'save' && (
+            <div className="space-y-2">
+              <Label htmlFor="passphrase-confirmation">Conferma passphrase</Label>
+              <Input
+                id="passphrase-confirmation"
+                type="password"
+                autoComplete="new-password"
+                value={confirmation}
+                onChange={(event) => setConfirmation(event.target.value)}
+              />
+              {confirmation.length > 0 && mismatch && (
+                <p className="text-sm text-destructive">Le passphrase non coincidono</p>
+              )}
+            </div>
+          )}
+
+          <DialogFooter>
+            <Button type="button" variant="outline" onClick={onCancel}>
+              Annulla
+            </Button>
+            <Button type="submit" disabled={!canSubmit}>
+              {mode === 'open' ? 'Apri' : 'Salva'}
+            </Button>
+          </DialogFooter>
+        </form>
+      </DialogContent>
+    </Dialog>
+  );
+}
@@ -12,12 +12,21 @@ export interface RecentFile {
 export interface FileContent {
   content: string;
   path?: string;
+  // True for passphrase-protected files. Loading one returns empty content;
+  // ask for the passphrase and call openEncryptedFile.
+  encrypted?: boolean;
+  // Saving to `path` is refused; use Save As instead.
+  read_only?: boolean;
+  // The file has git conflict markers; resolve them with resolve_conflict.
+  conflicted?: boolean;
 }
 
 export interface ValidationResult {
   is_valid: boolean;
   errors: string[];
   warnings: string[];
+  generation?: number;
+  stale?: boolean;
 }
 
 export interface Template {
@@ -47,11 +56,11 @@ export const useTauri = () => {
     }
   }, []);
 
-  const loadFile = useCallback(async (path?: string): Promise<FileContent> => {
+  const loadFile = useCallback(async (path?: string, readOnly?: boolean): Promise<FileContent> => {
     setIsLoading(true);
     setError(null);
     try {
-      const result = await invoke<FileContent>('load_file', { path });
+      const result = await invoke<FileContent>('load_file', { path, readOnly });
       return result;
     } catch (err) {
       const errorMessage = err as string;
@@ -62,9 +71,39 @@ export const useTauri = () => {
     }
   }, []);
 
-  const validateMermaidSyntax = useCallback(async (content: string): Promise<ValidationResult> => {
+  const saveEncryptedFile = useCallback(async (content: string, passphrase: string, path?: string): Promise<string> => {
+    setIsLoading(true);
+    setError(null);
+    try {
+      const result = await invoke<string>('save_encrypted_file', { content, passphrase, path });
+      return result;
+    } catch (err) {
+      const errorMessage = err as string;
+      setError(errorMessage);
+      throw new Error(errorMessage);
+    } finally {
+      setIsLoading(false);
+    }
+  }, []);
+
+  const openEncryptedFile = useCallback(async (path: string, passphrase: string, readOnly?: boolean): Promise<FileContent> => {
+    setIsLoading(true);
+    setError(null);
+    try {
+      const result = await invoke<FileContent>('open_encrypted_file', { path, passphrase, readOnly });
+      return result;
+    } catch (err) {
+      const errorMessage = err as string;
+      setError(errorMessage);
+      throw new Error(errorMessage);
+    } finally {
+      setIsLoading(false);
+    }
+  }, []);
+
+  const validateMermaidSyntax = useCallback(async (content: string, document?: string): Promise<ValidationResult> => {
     try {
-      const result = await invoke<ValidationResult>('validate_mermaid_syntax', { content });
+      const result = await invoke<ValidationResult>('validate_mermaid_syntax', { content, document });
       return result;
     } catch (err) {
       const errorMessage = err as string;
@@ -123,6 +162,8 @@ export const useTauri = () => {
   return {
     saveFile,
     loadFile,
+    saveEncryptedFile,
+    openEncryptedFile,
     validateMermaidSyntax,
     getRecentFiles,
     clearRecentFiles,